@@ -0,0 +1,179 @@
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait},
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    entities::{
+        announcement_mute, classroom_assistant, reservation,
+        sea_orm_active_enums::{ReservationStatus, Role},
+        user,
+    },
+    reservation_groups,
+    reservation_lifecycle::{self, Actor},
+    versioning,
+};
+
+/// Cancel reason recorded on upcoming reservations of a deleted account.
+pub const CANCEL_REASON: &str = "Account deleted";
+
+/// What happens to a deleted user's past reservations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReservationPolicy {
+    /// Keep them linked to the anonymized account, so usage history stays
+    /// per user
+    Retain,
+    /// Unlink them from the account entirely
+    Detach,
+}
+
+impl ReservationPolicy {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "retain" => Some(Self::Retain),
+            "detach" => Some(Self::Detach),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AccountDeletionConfig {
+    pub reservations: ReservationPolicy,
+}
+
+impl Default for AccountDeletionConfig {
+    fn default() -> Self {
+        Self {
+            reservations: ReservationPolicy::Retain,
+        }
+    }
+}
+
+/// Why an account cannot be deleted, if it cannot.
+pub fn deletion_blocked(user: &user::Model) -> Option<&'static str> {
+    if user.deleted_at.is_some() {
+        Some("Account already deleted")
+    } else if user.role == Role::Admin {
+        Some("Admin accounts must be demoted before they can be deleted")
+    } else {
+        None
+    }
+}
+
+/// The user with every personal field replaced. Unique columns get values
+/// derived from the ID, and the empty password hash never verifies.
+pub fn anonymized(user: user::Model, now: DateTimeWithTimeZone) -> user::Model {
+    user::Model {
+        username: format!("deleted_{}", user.id),
+        name: "Deleted user".to_string(),
+        email: format!("deleted_{}@deleted.invalid", user.id),
+        password: String::new(),
+        phone_number: String::new(),
+        role: Role::User,
+        updated_at: now,
+        locale: None,
+        email_undeliverable_at: None,
+        email_undeliverable_reason: None,
+        department: None,
+        student_id: None,
+        deleted_at: Some(now),
+        ..user
+    }
+}
+
+/// Upcoming reservations that would otherwise be kept for a user who no
+/// longer exists.
+pub fn is_cancelled_on_deletion(
+    reservation: &reservation::Model,
+    now: DateTimeWithTimeZone,
+) -> bool {
+    matches!(
+        reservation.status,
+        ReservationStatus::Approved | ReservationStatus::Pending
+    ) && reservation.start_time > now
+        && reservation_lifecycle::check(reservation, &ReservationStatus::Cancelled, Actor::System)
+            .is_ok()
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct DeletionSummary {
+    /// Upcoming reservations that were cancelled
+    pub cancelled_reservations: u64,
+    /// Reservations unlinked from the account under the `detach` policy
+    pub detached_reservations: u64,
+}
+
+/// Anonymizes the user, cancels their upcoming reservations and handles the
+/// rest per `policy`, in one transaction. Returns the cancelled
+/// reservations; revoking their keys, sessions and caches is left to the
+/// caller.
+pub async fn delete_account(
+    db: &DatabaseConnection,
+    user: user::Model,
+    policy: ReservationPolicy,
+    now: DateTimeWithTimeZone,
+) -> Result<(DeletionSummary, Vec<reservation::Model>), DbErr> {
+    let txn = db.begin().await?;
+
+    let upcoming: Vec<reservation::Model> = reservation::Entity::find()
+        .filter(reservation::Column::UserId.eq(&user.id))
+        .filter(reservation::Column::StartTime.gt(now))
+        .all(&txn)
+        .await?;
+    let upcoming: Vec<reservation::Model> = reservation_groups::with_members(&txn, upcoming)
+        .await?
+        .into_iter()
+        .filter(|r| is_cancelled_on_deletion(r, now))
+        .collect();
+    let mut cancelled = Vec::with_capacity(upcoming.len());
+    for reservation in upcoming {
+        let mut active: reservation::ActiveModel = reservation.into();
+        active.status = Set(ReservationStatus::Cancelled);
+        active.cancel_reason = Set(Some(CANCEL_REASON.to_string()));
+        cancelled.push(versioning::update(&txn, active, reservation::Column::Version, None).await?);
+    }
+
+    let detached_reservations = match policy {
+        ReservationPolicy::Retain => 0,
+        ReservationPolicy::Detach => {
+            reservation::Entity::update_many()
+                .col_expr(
+                    reservation::Column::UserId,
+                    Expr::value(Option::<String>::None),
+                )
+                .col_expr(
+                    reservation::Column::Version,
+                    Expr::col(reservation::Column::Version).add(1),
+                )
+                .filter(reservation::Column::UserId.eq(&user.id))
+                .exec(&txn)
+                .await?
+                .rows_affected
+        }
+    };
+
+    announcement_mute::Entity::delete_many()
+        .filter(announcement_mute::Column::UserId.eq(&user.id))
+        .exec(&txn)
+        .await?;
+    classroom_assistant::Entity::delete_many()
+        .filter(classroom_assistant::Column::UserId.eq(&user.id))
+        .exec(&txn)
+        .await?;
+
+    let active: user::ActiveModel = anonymized(user, now).into();
+    versioning::update(&txn, active.reset_all(), user::Column::Version, None).await?;
+
+    txn.commit().await?;
+    let summary = DeletionSummary {
+        cancelled_reservations: cancelled.len() as u64,
+        detached_reservations,
+    };
+    Ok((summary, cancelled))
+}
@@ -0,0 +1,163 @@
+#[cfg(test)]
+mod tests {
+    use super::super::account_deletion::{
+        ReservationPolicy, anonymized, deletion_blocked, is_cancelled_on_deletion,
+    };
+    use super::super::entities::{
+        domain_event, reservation,
+        sea_orm_active_enums::{ReservationStatus, Role},
+        user,
+    };
+    use super::super::pickup::{active_code, create_code};
+    use super::super::test_support::{
+        add_classroom, add_user, reservation_at, router, send, sign_in, state,
+    };
+    use axum::http::StatusCode;
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter,
+        prelude::DateTimeWithTimeZone,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn user(role: Role) -> user::Model {
+        let at = dt("2025-03-01T09:00:00+08:00");
+        user::Model {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            name: "Alice Chen".to_string(),
+            email: "alice@example.edu".to_string(),
+            password: "$argon2id$hash".to_string(),
+            phone_number: "0912345678".to_string(),
+            role,
+            created_at: at,
+            updated_at: at,
+            locale: Some("zh-TW".to_string()),
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: Some("CSIE".to_string()),
+            student_id: Some("B11000001".to_string()),
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_parse_reservation_policy() {
+        assert_eq!(
+            ReservationPolicy::parse(" Detach"),
+            Some(ReservationPolicy::Detach)
+        );
+        assert_eq!(
+            ReservationPolicy::parse("retain"),
+            Some(ReservationPolicy::Retain)
+        );
+        assert_eq!(ReservationPolicy::parse("delete"), None);
+    }
+
+    #[test]
+    fn test_anonymized_clears_personal_fields() {
+        let now = dt("2025-06-01T12:00:00+08:00");
+        let deleted = anonymized(user(Role::Assistant), now);
+        assert_eq!(deleted.id, "u1");
+        assert_eq!(deleted.username, "deleted_u1");
+        assert_eq!(deleted.email, "deleted_u1@deleted.invalid");
+        assert!(deleted.password.is_empty());
+        assert!(deleted.phone_number.is_empty());
+        assert_eq!(deleted.role, Role::User);
+        assert_eq!(deleted.student_id, None);
+        assert_eq!(deleted.department, None);
+        assert_eq!(deleted.locale, None);
+        assert_eq!(deleted.created_at, dt("2025-03-01T09:00:00+08:00"));
+        assert_eq!(deleted.deleted_at, Some(now));
+        assert!(deletion_blocked(&deleted).is_some());
+    }
+
+    #[test]
+    fn test_admins_cannot_be_deleted() {
+        assert!(deletion_blocked(&user(Role::Admin)).is_some());
+        assert!(deletion_blocked(&user(Role::User)).is_none());
+    }
+
+    #[test]
+    fn test_only_upcoming_active_reservations_are_cancelled() {
+        let now = dt("2025-06-01T12:00:00+08:00");
+        let reservation = |start: &str, status| reservation::Model {
+            classroom_id: Some("r101".to_string()),
+            status,
+            end_time: dt(start) + chrono::Duration::hours(1),
+            ..reservation_at("r", start, start)
+        };
+        let upcoming = "2025-06-02T10:00:00+08:00";
+        assert!(is_cancelled_on_deletion(
+            &reservation(upcoming, ReservationStatus::Approved),
+            now
+        ));
+        assert!(is_cancelled_on_deletion(
+            &reservation(upcoming, ReservationStatus::Pending),
+            now
+        ));
+        assert!(!is_cancelled_on_deletion(
+            &reservation(upcoming, ReservationStatus::Rejected),
+            now
+        ));
+        assert!(!is_cancelled_on_deletion(
+            &reservation("2025-05-30T10:00:00+08:00", ReservationStatus::Approved),
+            now
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_deletion_withdraws_what_cancelled_reservations_granted() {
+        let state = state().await;
+        add_user(&state, "u1", "u1@example.com", Role::User).await;
+        add_classroom(&state, "c1").await;
+        let booked = reservation::Model {
+            status: ReservationStatus::Approved,
+            ..reservation_at(
+                "r1",
+                "2030-03-11T09:00:00+08:00",
+                "2030-03-11T11:00:00+08:00",
+            )
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(&state.db)
+        .await
+        .unwrap();
+        create_code(&state.db, &booked).await.unwrap();
+        let app = router(state.clone());
+        let cookie = sign_in(&app, "u1@example.com").await;
+
+        let response = app
+            .oneshot(send("DELETE", "/user/self", &cookie, json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let cancelled = reservation::Entity::find_by_id("r1")
+            .one(&state.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(cancelled.status, ReservationStatus::Cancelled);
+        let events = domain_event::Entity::find()
+            .filter(domain_event::Column::Kind.eq("reservation_cancelled"))
+            .all(&state.db)
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        // Revoked in the background
+        for _ in 0..50 {
+            if active_code(&state.db, "r1").await.unwrap().is_none() {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        panic!("the pickup code of the cancelled reservation is still active");
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset,
+    bans,
+    entities::{
+        announcement, announcement_mute, black_list, reservation,
+        sea_orm_active_enums::{
+            AnnouncementAudience, AnnouncementCategory, ReservationStatus, Role,
+        },
+        user,
+    },
+    permissions::{Permission, has_permission},
+};
+
+/// Which users a targeted announcement is for, as stored on it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub audience: AnnouncementAudience,
+    /// Only set for [`AnnouncementAudience::ClassroomUsers`]
+    pub classroom_id: Option<String>,
+}
+
+impl Target {
+    /// A target from request fields. Audiences other than `classroom_users`
+    /// drop the classroom; `classroom_users` needs one.
+    pub fn new(
+        audience: Option<AnnouncementAudience>,
+        classroom_id: Option<String>,
+    ) -> Result<Self, &'static str> {
+        let audience = audience.unwrap_or(AnnouncementAudience::All);
+        let classroom_id = match audience {
+            AnnouncementAudience::ClassroomUsers => Some(
+                classroom_id
+                    .filter(|id| !id.trim().is_empty())
+                    .ok_or("audience_classroom_id is required for classroom_users")?,
+            ),
+            _ => None,
+        };
+        Ok(Self {
+            audience,
+            classroom_id,
+        })
+    }
+
+    pub fn of(announcement: &announcement::Model) -> Self {
+        Self {
+            audience: announcement.audience.clone(),
+            classroom_id: announcement.audience_classroom_id.clone(),
+        }
+    }
+}
+
+/// Reservations that make their owner one of a classroom's users: pending or
+/// approved ones that have not ended yet.
+fn classroom_use_condition(now: DateTimeWithTimeZone) -> Condition {
+    Condition::all()
+        .add(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .add(reservation::Column::EndTime.gt(now))
+}
+
+/// What decides which targeted announcements someone gets to read.
+#[derive(Default)]
+pub struct Reader {
+    /// `None` when not signed in
+    pub role: Option<Role>,
+    pub blacklisted: bool,
+    /// Classrooms the reader has a pending or approved reservation in that
+    /// has not ended
+    pub classroom_ids: HashSet<String>,
+}
+
+impl Reader {
+    pub async fn load(db: &DatabaseConnection, user: Option<&user::Model>) -> Result<Self, DbErr> {
+        let Some(user) = user else {
+            return Ok(Self::default());
+        };
+        let now = Utc::now().with_timezone(&campus_offset());
+        let blacklisted = !bans::active_for_user(db, &user.id, now).await?.is_empty();
+        let classroom_ids = reservation::Entity::find()
+            .filter(reservation::Column::UserId.eq(&user.id))
+            .filter(classroom_use_condition(now))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter_map(|r| r.classroom_id)
+            .collect();
+        Ok(Self {
+            role: Some(user.role.clone()),
+            blacklisted,
+            classroom_ids,
+        })
+    }
+
+    /// Whether the reader is among the users `target` is for.
+    pub fn is_targeted(&self, target: &Target) -> bool {
+        match target.audience {
+            AnnouncementAudience::All => true,
+            AnnouncementAudience::Admins => self.role == Some(Role::Admin),
+            AnnouncementAudience::Blacklisted => self.blacklisted,
+            AnnouncementAudience::ClassroomUsers => target
+                .classroom_id
+                .as_ref()
+                .is_some_and(|id| self.classroom_ids.contains(id)),
+        }
+    }
+
+    /// Those who manage announcements see every one of them.
+    fn sees_all(&self) -> bool {
+        self.role
+            .as_ref()
+            .is_some_and(|role| has_permission(role, Permission::ManageAnnouncements))
+    }
+
+    pub fn can_read(&self, announcement: &announcement::Model) -> bool {
+        self.sees_all() || self.is_targeted(&Target::of(announcement))
+    }
+
+    /// Filter for the announcements the reader can read.
+    pub fn visible_condition(&self) -> Condition {
+        if self.sees_all() {
+            return Condition::all();
+        }
+        let mut condition =
+            Condition::any().add(announcement::Column::Audience.eq(AnnouncementAudience::All));
+        if self.role == Some(Role::Admin) {
+            condition =
+                condition.add(announcement::Column::Audience.eq(AnnouncementAudience::Admins));
+        }
+        if self.blacklisted {
+            condition =
+                condition.add(announcement::Column::Audience.eq(AnnouncementAudience::Blacklisted));
+        }
+        if !self.classroom_ids.is_empty() {
+            condition = condition.add(
+                Condition::all()
+                    .add(announcement::Column::Audience.eq(AnnouncementAudience::ClassroomUsers))
+                    .add(
+                        announcement::Column::AudienceClassroomId.is_in(self.classroom_ids.clone()),
+                    ),
+            );
+        }
+        condition
+    }
+}
+
+/// Filter for the users `target` is for.
+async fn target_condition(db: &DatabaseConnection, target: &Target) -> Result<Condition, DbErr> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    Ok(match &target.audience {
+        AnnouncementAudience::All => Condition::all(),
+        AnnouncementAudience::Admins => Condition::all().add(user::Column::Role.eq(Role::Admin)),
+        AnnouncementAudience::Blacklisted => {
+            let user_ids: Vec<Option<String>> = black_list::Entity::find()
+                .select_only()
+                .column(black_list::Column::UserId)
+                .filter(bans::active_condition(now))
+                .into_tuple()
+                .all(db)
+                .await?;
+            Condition::all().add(user::Column::Id.is_in(user_ids.into_iter().flatten()))
+        }
+        AnnouncementAudience::ClassroomUsers => {
+            let user_ids: Vec<Option<String>> = reservation::Entity::find()
+                .select_only()
+                .column(reservation::Column::UserId)
+                .filter(reservation::Column::ClassroomId.eq(target.classroom_id.clone()))
+                .filter(classroom_use_condition(now))
+                .into_tuple()
+                .all(db)
+                .await?;
+            Condition::all().add(user::Column::Id.is_in(user_ids.into_iter().flatten()))
+        }
+    })
+}
+
+/// Users an announcement email goes to, out of those it is targeted at.
+pub struct Audience {
+    pub recipients: Vec<user::Model>,
+    /// Users left out because they muted the category
+    pub muted: u64,
+    /// Users left out because their address bounced
+    pub undeliverable: u64,
+}
+
+/// Emergency announcements go to everyone regardless of mutes; regular ones
+/// skip users who muted the category. Undeliverable addresses are always
+/// skipped.
+pub fn resolve(
+    users: Vec<user::Model>,
+    muted_user_ids: &HashSet<String>,
+    emergency: bool,
+) -> Audience {
+    let mut audience = Audience {
+        recipients: Vec::new(),
+        muted: 0,
+        undeliverable: 0,
+    };
+    for user in users {
+        if !emergency && muted_user_ids.contains(&user.id) {
+            audience.muted += 1;
+        } else if user.email_undeliverable_at.is_some() {
+            audience.undeliverable += 1;
+        } else {
+            audience.recipients.push(user);
+        }
+    }
+    audience
+}
+
+pub async fn load(
+    db: &DatabaseConnection,
+    category: &AnnouncementCategory,
+    emergency: bool,
+    target: &Target,
+) -> Result<Audience, DbErr> {
+    let muted = if emergency {
+        HashSet::new()
+    } else {
+        announcement_mute::Entity::find()
+            .filter(announcement_mute::Column::Category.eq(category.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|m| m.user_id)
+            .collect()
+    };
+    let users = user::Entity::find()
+        .filter(user::Column::DeletedAt.is_null())
+        .filter(target_condition(db, target).await?)
+        .all(db)
+        .await?;
+    Ok(resolve(users, &muted, emergency))
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct RoleCount {
+    pub role: Role,
+    pub recipients: u64,
+}
+
+/// Who a broadcast would reach, without sending anything.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct RecipientPreview {
+    /// Emails that would be sent
+    pub recipients: u64,
+    /// Recipients per role, for spotting a broadcast meant for staff that
+    /// would reach every student
+    pub by_role: Vec<RoleCount>,
+    pub muted: u64,
+    pub undeliverable: u64,
+}
+
+impl Audience {
+    pub fn preview(&self) -> RecipientPreview {
+        let by_role = [Role::Admin, Role::Assistant, Role::User]
+            .into_iter()
+            .map(|role| RoleCount {
+                recipients: self.recipients.iter().filter(|u| u.role == role).count() as u64,
+                role,
+            })
+            .filter(|count| count.recipients > 0)
+            .collect();
+        RecipientPreview {
+            recipients: self.recipients.len() as u64,
+            by_role,
+            muted: self.muted,
+            undeliverable: self.undeliverable,
+        }
+    }
+}
@@ -0,0 +1,142 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::super::announcement_audience::{Reader, RoleCount, Target, resolve};
+    use super::super::entities::{
+        sea_orm_active_enums::{AnnouncementAudience, Role},
+        user,
+    };
+
+    fn user(id: &str, role: Role, undeliverable: bool) -> user::Model {
+        let at = "2025-03-01T09:00:00+08:00".parse().unwrap();
+        user::Model {
+            id: id.to_string(),
+            username: id.to_string(),
+            name: id.to_string(),
+            email: format!("{}@example.edu", id),
+            password: String::new(),
+            phone_number: String::new(),
+            role,
+            created_at: at,
+            updated_at: at,
+            locale: None,
+            email_undeliverable_at: undeliverable.then_some(at),
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    fn users() -> Vec<user::Model> {
+        vec![
+            user("admin", Role::Admin, false),
+            user("alice", Role::User, false),
+            user("bob", Role::User, false),
+            user("bounced", Role::User, true),
+        ]
+    }
+
+    #[test]
+    fn test_regular_broadcast_skips_muted_and_undeliverable() {
+        let muted = HashSet::from(["bob".to_string()]);
+        let audience = resolve(users(), &muted, false);
+        let ids: Vec<&str> = audience.recipients.iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(ids, ["admin", "alice"]);
+
+        let preview = audience.preview();
+        assert_eq!(preview.recipients, 2);
+        assert_eq!(preview.muted, 1);
+        assert_eq!(preview.undeliverable, 1);
+        assert_eq!(
+            preview.by_role,
+            vec![
+                RoleCount {
+                    role: Role::Admin,
+                    recipients: 1,
+                },
+                RoleCount {
+                    role: Role::User,
+                    recipients: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_emergency_broadcast_ignores_mutes() {
+        let muted = HashSet::from(["bob".to_string()]);
+        let preview = resolve(users(), &muted, true).preview();
+        assert_eq!(preview.recipients, 3);
+        assert_eq!(preview.muted, 0);
+        assert_eq!(preview.undeliverable, 1);
+    }
+
+    #[test]
+    fn test_target_needs_classroom_only_for_classroom_users() {
+        assert_eq!(
+            Target::new(None, Some("c1".to_string())),
+            Ok(Target {
+                audience: AnnouncementAudience::All,
+                classroom_id: None,
+            })
+        );
+        assert!(Target::new(Some(AnnouncementAudience::ClassroomUsers), None).is_err());
+        assert!(
+            Target::new(
+                Some(AnnouncementAudience::ClassroomUsers),
+                Some(" ".to_string())
+            )
+            .is_err()
+        );
+        assert_eq!(
+            Target::new(
+                Some(AnnouncementAudience::ClassroomUsers),
+                Some("c1".to_string())
+            )
+            .unwrap()
+            .classroom_id
+            .as_deref(),
+            Some("c1")
+        );
+    }
+
+    #[test]
+    fn test_reader_is_targeted_by_audience() {
+        let classroom = |id: &str| Target {
+            audience: AnnouncementAudience::ClassroomUsers,
+            classroom_id: Some(id.to_string()),
+        };
+        let admins = Target {
+            audience: AnnouncementAudience::Admins,
+            classroom_id: None,
+        };
+        let blacklisted = Target {
+            audience: AnnouncementAudience::Blacklisted,
+            classroom_id: None,
+        };
+
+        let anonymous = Reader::default();
+        assert!(anonymous.is_targeted(&Target::new(None, None).unwrap()));
+        assert!(!anonymous.is_targeted(&admins));
+
+        let reader = Reader {
+            role: Some(Role::User),
+            blacklisted: true,
+            classroom_ids: HashSet::from(["c1".to_string()]),
+        };
+        assert!(reader.is_targeted(&blacklisted));
+        assert!(reader.is_targeted(&classroom("c1")));
+        assert!(!reader.is_targeted(&classroom("c2")));
+        assert!(!reader.is_targeted(&admins));
+
+        let admin = Reader {
+            role: Some(Role::Admin),
+            ..Reader::default()
+        };
+        assert!(admin.is_targeted(&admins));
+        assert!(!admin.is_targeted(&blacklisted));
+    }
+}
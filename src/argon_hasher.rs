@@ -3,11 +3,9 @@ use argon2::{
     password_hash::{self, SaltString, rand_core::OsRng},
 };
 use std::sync::Arc;
-use std::sync::OnceLock;
 use tokio::task;
 
-static GLOBAL_ARGON2: OnceLock<Arc<Argon2<'static>>> = OnceLock::new();
-
+#[derive(Clone)]
 pub struct Argon2Config {
     pub secret_key: Vec<u8>,
     pub iterations: u32,
@@ -15,59 +13,64 @@ pub struct Argon2Config {
     pub memory_cost: u32,
 }
 
-pub fn set_config(config: Argon2Config) {
-    let secret_bytes = Box::leak(config.secret_key.into_boxed_slice());
+/// Hashes and verifies passwords with the configured Argon2 instance. Cheap
+/// to clone; carried in `AppState` and the auth backend.
+#[derive(Clone)]
+pub struct Hasher {
+    argon2: Arc<Argon2<'static>>,
+}
 
-    let argon2 = Argon2::new_with_secret(
-        secret_bytes,
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(
-            config.memory_cost,
-            config.iterations,
-            config.parallelism,
-            None,
-        )
-        .unwrap(),
-    )
-    .unwrap();
+impl Hasher {
+    pub fn new(config: Argon2Config) -> Self {
+        let secret_bytes = Box::leak(config.secret_key.into_boxed_slice());
 
-    let _ = GLOBAL_ARGON2.set(Arc::new(argon2));
-}
+        let argon2 = Argon2::new_with_secret(
+            secret_bytes,
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(
+                config.memory_cost,
+                config.iterations,
+                config.parallelism,
+                None,
+            )
+            .unwrap(),
+        )
+        .unwrap();
 
-pub async fn hash(password: impl AsRef<[u8]>) -> Result<String, password_hash::Error> {
-    let argon2 = GLOBAL_ARGON2
-        .get()
-        .expect("Argon2 instance not initialized. Call set_config first.")
-        .clone();
+        Self {
+            argon2: Arc::new(argon2),
+        }
+    }
 
-    let password = password.as_ref().to_owned();
+    pub async fn hash(&self, password: impl AsRef<[u8]>) -> Result<String, password_hash::Error> {
+        let argon2 = self.argon2.clone();
+        let password = password.as_ref().to_owned();
 
-    let res = task::spawn_blocking(move || {
-        let salt = SaltString::generate(&mut OsRng);
-        argon2
-            .hash_password(&password, &salt)
-            .map(|ph| ph.to_string())
-    });
+        let res = task::spawn_blocking(move || {
+            let salt = SaltString::generate(&mut OsRng);
+            argon2
+                .hash_password(&password, &salt)
+                .map(|ph| ph.to_string())
+        });
 
-    res.await.unwrap()
-}
+        res.await.unwrap()
+    }
 
-pub async fn verify(
-    password: impl AsRef<[u8]>,
-    hash: impl AsRef<str>,
-) -> Result<bool, password_hash::Error> {
-    let argon2 = GLOBAL_ARGON2
-        .get()
-        .expect("Argon2 instance not initialized. Call set_config first.")
-        .clone();
-    let password = password.as_ref().to_owned();
-    let hash = hash.as_ref().to_owned();
+    pub async fn verify(
+        &self,
+        password: impl AsRef<[u8]>,
+        hash: impl AsRef<str>,
+    ) -> Result<bool, password_hash::Error> {
+        let argon2 = self.argon2.clone();
+        let password = password.as_ref().to_owned();
+        let hash = hash.as_ref().to_owned();
 
-    let res = task::spawn_blocking(move || {
-        let hash = PasswordHash::new(&hash).unwrap();
-        argon2.verify_password(&password, &hash).map(|_| true)
-    });
+        let res = task::spawn_blocking(move || {
+            let hash = PasswordHash::new(&hash)?;
+            argon2.verify_password(&password, &hash).map(|_| true)
+        });
 
-    res.await.unwrap()
+        res.await.unwrap()
+    }
 }
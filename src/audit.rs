@@ -0,0 +1,149 @@
+use std::{convert::Infallible, net::SocketAddr};
+
+use axum::{
+    extract::{ConnectInfo, FromRequestParts},
+    http::request::Parts,
+};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ConnectionTrait, DbErr,
+};
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+
+use crate::{
+    AppState,
+    entities::{audit_log, user},
+    login_guard::client_ip,
+    login_system::AuthSession,
+};
+
+/// Kinds of records admin changes are recorded for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Target {
+    Classroom,
+    Key,
+    Reservation,
+    BlackList,
+    User,
+}
+
+impl Target {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Classroom => "classroom",
+            Self::Key => "key",
+            Self::Reservation => "reservation",
+            Self::BlackList => "black_list",
+            Self::User => "user",
+        }
+    }
+}
+
+/// A record as it is kept in the log.
+pub fn snapshot(record: &impl Serialize) -> Value {
+    serde_json::to_value(record).unwrap_or(Value::Null)
+}
+
+/// A user as it is kept in the log: only the fields that say who they are
+/// and what they may do, so password hashes and contact details stay out.
+pub fn user_snapshot(user: &user::Model) -> Value {
+    serde_json::json!({
+        "id": user.id,
+        "username": user.username,
+        "role": user.role,
+        "deleted_at": user.deleted_at,
+    })
+}
+
+/// Who is making a change and from where. Taken by admin handlers that
+/// mutate data, so the change can be recorded with `record`.
+#[derive(Clone, Debug, Default)]
+pub struct AuditContext {
+    pub actor_id: Option<String>,
+    pub ip: Option<String>,
+}
+
+impl FromRequestParts<AppState> for AuditContext {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let actor_id = parts
+            .extensions
+            .get::<AuthSession>()
+            .and_then(|session| session.user.as_ref())
+            .map(|user| user.id.clone());
+        let ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(peer)| {
+                client_ip(
+                    &parts.headers,
+                    peer.ip(),
+                    state.config.login_guard.trust_forwarded_for,
+                )
+                .to_string()
+            });
+        Ok(Self { actor_id, ip })
+    }
+}
+
+impl AuditContext {
+    /// Records `action` (e.g. `update`) on a `target` record, with the record
+    /// before and after. Pass `db` a transaction to keep the entry with the
+    /// change it describes.
+    pub async fn record<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        target: Target,
+        target_id: &str,
+        action: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) -> Result<audit_log::Model, DbErr> {
+        audit_log::ActiveModel {
+            id: Set(nanoid!()),
+            actor_id: Set(self.actor_id.clone()),
+            action: Set(format!("{}.{}", target.name(), action)),
+            target_type: Set(target.name().to_string()),
+            target_id: Set(target_id.to_string()),
+            before: Set(before),
+            after: Set(after),
+            ip: Set(self.ip.clone()),
+            created_at: NotSet,
+        }
+        .insert(db)
+        .await
+    }
+
+    /// `record` for changes that are already saved: a failure to record is
+    /// logged rather than undoing or failing the request.
+    pub async fn log<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        target: Target,
+        target_id: &str,
+        action: &str,
+        before: Option<Value>,
+        after: Option<Value>,
+    ) {
+        if let Err(e) = self
+            .record(db, target, target_id, action, before, after)
+            .await
+        {
+            warn!(
+                "Failed to record {}.{} of {} in the audit log: {}",
+                target.name(),
+                action,
+                target_id,
+                e
+            );
+        }
+    }
+}
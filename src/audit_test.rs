@@ -0,0 +1,94 @@
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use axum::{
+        extract::{ConnectInfo, FromRequestParts},
+        http::Request,
+    };
+    use chrono::Utc;
+    use sea_orm::EntityTrait;
+    use serde_json::json;
+
+    use super::super::{
+        audit::{AuditContext, Target, snapshot, user_snapshot},
+        entities::{audit_log, sea_orm_active_enums::Role, user},
+        memory_mode, test_support,
+    };
+
+    #[tokio::test]
+    async fn test_context_takes_the_peer_address() {
+        let request = Request::builder()
+            .extension(ConnectInfo(SocketAddr::from(([192, 0, 2, 10], 4000))))
+            .body(())
+            .unwrap();
+        let (mut parts, _) = request.into_parts();
+        let audit = AuditContext::from_request_parts(&mut parts, &test_support::state().await)
+            .await
+            .unwrap();
+        assert_eq!(audit.ip.as_deref(), Some("192.0.2.10"));
+        // Nobody is signed in without the auth layer
+        assert_eq!(audit.actor_id, None);
+    }
+
+    #[test]
+    fn test_user_snapshot_leaves_out_secrets() {
+        let now = Utc::now().fixed_offset();
+        let user = user::Model {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            name: "Alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password: "$argon2id$hash".to_string(),
+            phone_number: "0912345678".to_string(),
+            role: Role::Assistant,
+            created_at: now,
+            updated_at: now,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: None,
+            version: 1,
+        };
+        let value = user_snapshot(&user);
+        assert_eq!(value["role"], json!("Assistant"));
+        let text = value.to_string();
+        assert!(!text.contains("argon2"));
+        assert!(!text.contains("alice@example.com"));
+        assert!(!text.contains("0912345678"));
+    }
+
+    #[tokio::test]
+    async fn test_record_stores_the_change() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let audit = AuditContext {
+            actor_id: None,
+            ip: Some("192.0.2.10".to_string()),
+        };
+        let entry = audit
+            .record(
+                &db,
+                Target::Key,
+                "k1",
+                "update",
+                Some(snapshot(&json!({ "key_number": "A-1" }))),
+                Some(snapshot(&json!({ "key_number": "A-2" }))),
+            )
+            .await
+            .unwrap();
+        assert_eq!(entry.action, "key.update");
+        assert_eq!(entry.target_type, "key");
+
+        let stored = audit_log::Entity::find_by_id(&entry.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.target_id, "k1");
+        assert_eq!(stored.ip.as_deref(), Some("192.0.2.10"));
+        assert_eq!(stored.before, Some(json!({ "key_number": "A-1" })));
+        assert_eq!(stored.after, Some(json!({ "key_number": "A-2" })));
+    }
+}
@@ -0,0 +1,281 @@
+use chrono::{Datelike, Duration, FixedOffset};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    constants::CAMPUS_UTC_OFFSET_SECONDS,
+    entities::{
+        classroom, classroom_closure, classroom_schedule, course_session, reservation,
+        sea_orm_active_enums::{ClassroomStatus, ReservationStatus},
+    },
+    timetable::sessions_in_window,
+};
+
+/// Why a classroom cannot be booked for a requested window.
+pub enum Unavailability {
+    OutOfService(classroom::Model),
+    OutsideOpeningHours,
+    Closed(classroom_closure::Model),
+    Course(course_session::Model),
+    Conflict(reservation::Model),
+}
+
+impl Unavailability {
+    pub fn message(&self) -> String {
+        match self {
+            Unavailability::OutOfService(classroom) => match &classroom.status_reason {
+                Some(reason) => format!("Classroom is out of service: {}", reason),
+                None => "Classroom is out of service".to_string(),
+            },
+            Unavailability::OutsideOpeningHours => {
+                "Requested time is outside the classroom's opening hours".to_string()
+            }
+            Unavailability::Closed(closure) => {
+                format!("Classroom is closed during this time: {}", closure.reason)
+            }
+            Unavailability::Course(session) => format!(
+                "Classroom is used by the course {} during this time",
+                session.course_name
+            ),
+            Unavailability::Conflict(reservation) => format!(
+                "Requested time conflicts with an approved reservation ({} - {})",
+                reservation.start_time, reservation.end_time
+            ),
+        }
+    }
+}
+
+pub fn campus_offset() -> FixedOffset {
+    FixedOffset::east_opt(CAMPUS_UTC_OFFSET_SECONDS).unwrap()
+}
+
+/// Days reservation lists can be filtered by name, so clients do not have
+/// to work out the boundaries themselves. Weeks run from Monday to Sunday.
+#[derive(Deserialize, Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateShorthand {
+    Today,
+    Tomorrow,
+    ThisWeek,
+    NextWeek,
+}
+
+impl DateShorthand {
+    /// `[start, end)` of the days meant at `now`, as campus midnights.
+    pub fn bounds(self, now: DateTimeWithTimeZone) -> (DateTimeWithTimeZone, DateTimeWithTimeZone) {
+        let today = now.with_timezone(&campus_offset()).date_naive();
+        let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+        let (first, days) = match self {
+            Self::Today => (today, 1),
+            Self::Tomorrow => (today + Duration::days(1), 1),
+            Self::ThisWeek => (monday, 7),
+            Self::NextWeek => (monday + Duration::days(7), 7),
+        };
+        let start = first
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_local_timezone(campus_offset())
+            .unwrap();
+        (start, start + Duration::days(days))
+    }
+}
+
+/// Maintenance and Unavailable take a classroom out of service; other statuses
+/// leave it bookable.
+pub fn is_out_of_service(status: &ClassroomStatus) -> bool {
+    matches!(
+        status,
+        ClassroomStatus::Maintenance | ClassroomStatus::Unavailable
+    )
+}
+
+/// Whether `[start, end)` overlaps the classroom's out-of-service period. An
+/// open `status_from`/`status_until` extends the period indefinitely.
+pub fn out_of_service_in_window(
+    classroom: &classroom::Model,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> bool {
+    if !is_out_of_service(&classroom.status) {
+        return false;
+    }
+    let starts_before_end = classroom.status_from.is_none_or(|from| from < end);
+    let ends_after_start = classroom.status_until.is_none_or(|until| until > start);
+    starts_before_end && ends_after_start
+}
+
+/// Checks `[start, end)` against the weekly opening hours, evaluated in the
+/// campus timezone. Weekdays are numbered from Monday (0) to Sunday (6).
+/// A classroom without any configured hours is treated as always open.
+pub fn within_opening_hours(
+    hours: &[classroom_schedule::Model],
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> bool {
+    if hours.is_empty() {
+        return true;
+    }
+
+    let start = start.with_timezone(&campus_offset());
+    let end = end.with_timezone(&campus_offset());
+
+    // A booking has to fit inside a single day's opening window
+    if start.date_naive() != end.date_naive() {
+        return false;
+    }
+
+    let weekday = start.weekday().num_days_from_monday() as i16;
+    hours
+        .iter()
+        .any(|h| h.weekday == weekday && h.open_time <= start.time() && end.time() <= h.close_time)
+}
+
+pub async fn closures_in_window(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<Vec<classroom_closure::Model>, DbErr> {
+    classroom_closure::Entity::find()
+        .filter(classroom_closure::Column::ClassroomId.eq(classroom_id))
+        .filter(classroom_closure::Column::StartAt.lt(end))
+        .filter(classroom_closure::Column::EndAt.gt(start))
+        .all(db)
+        .await
+}
+
+pub async fn approved_reservations_in_window(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    reservation::Entity::find()
+        .filter(reservation::Column::ClassroomId.eq(classroom_id))
+        .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+        .filter(reservation::Column::StartTime.lt(end))
+        .filter(reservation::Column::EndTime.gt(start))
+        .all(db)
+        .await
+}
+
+/// Returns the first reason the classroom cannot be booked for the window, or
+/// `None` when it is free.
+pub async fn find_unavailability(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<Option<Unavailability>, DbErr> {
+    if let Some(classroom) = classroom::Entity::find_by_id(classroom_id).one(db).await?
+        && out_of_service_in_window(&classroom, start, end)
+    {
+        return Ok(Some(Unavailability::OutOfService(classroom)));
+    }
+
+    let hours = classroom_schedule::Entity::find()
+        .filter(classroom_schedule::Column::ClassroomId.eq(classroom_id))
+        .all(db)
+        .await?;
+    if !within_opening_hours(&hours, start, end) {
+        return Ok(Some(Unavailability::OutsideOpeningHours));
+    }
+
+    if let Some(closure) = closures_in_window(db, classroom_id, start, end)
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Ok(Some(Unavailability::Closed(closure)));
+    }
+
+    if let Some(session) = sessions_in_window(db, classroom_id, start, end)
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Ok(Some(Unavailability::Course(session)));
+    }
+
+    if let Some(conflict) = approved_reservations_in_window(db, classroom_id, start, end)
+        .await?
+        .into_iter()
+        .next()
+    {
+        return Ok(Some(Unavailability::Conflict(conflict)));
+    }
+
+    Ok(None)
+}
+
+/// A classroom offered in place of one that cannot be used.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct AlternativeRoom {
+    pub id: String,
+    pub name: String,
+    pub location: String,
+    pub capacity: i32,
+}
+
+impl From<classroom::Model> for AlternativeRoom {
+    fn from(model: classroom::Model) -> Self {
+        Self {
+            id: model.id,
+            name: model.name,
+            location: model.location,
+            capacity: model.capacity,
+        }
+    }
+}
+
+/// Orders candidate rooms by how closely they match `original`: rooms that
+/// are too small are dropped, the rest are ranked by capacity difference and
+/// then by being in the same location.
+pub fn rank_alternatives(
+    original: &classroom::Model,
+    candidates: Vec<classroom::Model>,
+) -> Vec<classroom::Model> {
+    let mut candidates: Vec<classroom::Model> = candidates
+        .into_iter()
+        .filter(|c| c.id != original.id && c.capacity >= original.capacity)
+        .collect();
+    candidates.sort_by_key(|c| {
+        (
+            c.capacity - original.capacity,
+            c.location != original.location,
+        )
+    });
+    candidates
+}
+
+/// Up to `limit` bookable classrooms similar to `original` that are free for
+/// the whole window.
+pub async fn suggest_alternatives(
+    db: &DatabaseConnection,
+    original: &classroom::Model,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+    limit: usize,
+) -> Result<Vec<AlternativeRoom>, DbErr> {
+    let candidates = classroom::Entity::find()
+        .filter(classroom::Column::DeletedAt.is_null())
+        .all(db)
+        .await?;
+
+    let mut alternatives = Vec::new();
+    for candidate in rank_alternatives(original, candidates) {
+        if alternatives.len() >= limit {
+            break;
+        }
+        if find_unavailability(db, &candidate.id, start, end)
+            .await?
+            .is_none()
+        {
+            alternatives.push(candidate.into());
+        }
+    }
+    Ok(alternatives)
+}
@@ -0,0 +1,262 @@
+#[cfg(test)]
+mod tests {
+    use super::super::availability::{
+        DateShorthand, out_of_service_in_window, rank_alternatives, within_opening_hours,
+    };
+    use super::super::entities::{
+        classroom, classroom_schedule,
+        sea_orm_active_enums::{ClassroomStatus, Role},
+    };
+    use super::super::test_support::{add_classroom, add_user, router, send, sign_in, state};
+    use axum::http::StatusCode;
+    use chrono::NaiveTime;
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set, prelude::DateTimeWithTimeZone};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn hours(weekday: i16, open: &str, close: &str) -> classroom_schedule::Model {
+        classroom_schedule::Model {
+            id: "h".to_string(),
+            classroom_id: "c".to_string(),
+            weekday,
+            open_time: NaiveTime::parse_from_str(open, "%H:%M").unwrap(),
+            close_time: NaiveTime::parse_from_str(close, "%H:%M").unwrap(),
+        }
+    }
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn room(status: ClassroomStatus, from: Option<&str>, until: Option<&str>) -> classroom::Model {
+        classroom::Model {
+            id: "c".to_string(),
+            name: "A101".to_string(),
+            location: "Building A".to_string(),
+            capacity: 40,
+            description: String::new(),
+            status,
+            created_at: dt("2025-01-01T00:00:00+08:00"),
+            updated_at: dt("2025-01-01T00:00:00+08:00"),
+            photo_id: String::new(),
+            status_reason: Some("Projector repair".to_string()),
+            status_from: from.map(dt),
+            status_until: until.map(dt),
+            deleted_at: None,
+            name_en: None,
+            name_zh_tw: None,
+            description_en: None,
+            description_zh_tw: None,
+            requires_double_approval: false,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_no_hours_means_always_open() {
+        assert!(within_opening_hours(
+            &[],
+            dt("2025-01-06T02:00:00+08:00"),
+            dt("2025-01-06T03:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_inside_weekday_hours() {
+        // 2025-01-06 is a Monday
+        let schedule = vec![hours(0, "08:00", "17:00")];
+        assert!(within_opening_hours(
+            &schedule,
+            dt("2025-01-06T08:00:00+08:00"),
+            dt("2025-01-06T17:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_outside_weekday_hours() {
+        let schedule = vec![hours(0, "08:00", "17:00")];
+        assert!(!within_opening_hours(
+            &schedule,
+            dt("2025-01-06T16:00:00+08:00"),
+            dt("2025-01-06T18:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_wrong_weekday() {
+        // 2025-01-07 is a Tuesday
+        let schedule = vec![hours(0, "08:00", "17:00")];
+        assert!(!within_opening_hours(
+            &schedule,
+            dt("2025-01-07T09:00:00+08:00"),
+            dt("2025-01-07T10:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_evaluated_in_campus_timezone() {
+        // 01:00Z is 09:00 in Taipei on the same Monday
+        let schedule = vec![hours(0, "08:00", "17:00")];
+        assert!(within_opening_hours(
+            &schedule,
+            dt("2025-01-06T01:00:00Z"),
+            dt("2025-01-06T02:00:00Z")
+        ));
+    }
+
+    #[test]
+    fn test_spanning_midnight_is_rejected() {
+        let schedule = vec![hours(0, "00:00", "23:59"), hours(1, "00:00", "23:59")];
+        assert!(!within_opening_hours(
+            &schedule,
+            dt("2025-01-06T23:00:00+08:00"),
+            dt("2025-01-07T01:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_available_room_is_in_service() {
+        let classroom = room(ClassroomStatus::Available, None, None);
+        assert!(!out_of_service_in_window(
+            &classroom,
+            dt("2025-01-06T08:00:00+08:00"),
+            dt("2025-01-06T09:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_open_ended_maintenance_blocks_everything() {
+        let classroom = room(ClassroomStatus::Maintenance, None, None);
+        assert!(out_of_service_in_window(
+            &classroom,
+            dt("2030-01-06T08:00:00+08:00"),
+            dt("2030-01-06T09:00:00+08:00")
+        ));
+    }
+
+    #[test]
+    fn test_maintenance_window_bounds() {
+        let classroom = room(
+            ClassroomStatus::Unavailable,
+            Some("2025-01-06T10:00:00+08:00"),
+            Some("2025-01-06T12:00:00+08:00"),
+        );
+        // Ends exactly when maintenance starts
+        assert!(!out_of_service_in_window(
+            &classroom,
+            dt("2025-01-06T09:00:00+08:00"),
+            dt("2025-01-06T10:00:00+08:00")
+        ));
+        assert!(out_of_service_in_window(
+            &classroom,
+            dt("2025-01-06T11:00:00+08:00"),
+            dt("2025-01-06T13:00:00+08:00")
+        ));
+        // Starts exactly when maintenance ends
+        assert!(!out_of_service_in_window(
+            &classroom,
+            dt("2025-01-06T12:00:00+08:00"),
+            dt("2025-01-06T13:00:00+08:00")
+        ));
+    }
+
+    fn candidate(id: &str, capacity: i32, location: &str) -> classroom::Model {
+        classroom::Model {
+            id: id.to_string(),
+            capacity,
+            location: location.to_string(),
+            ..room(ClassroomStatus::Available, None, None)
+        }
+    }
+
+    #[test]
+    fn test_rank_alternatives_by_capacity_then_location() {
+        let original = candidate("c", 40, "Building A");
+        let ranked = rank_alternatives(
+            &original,
+            vec![
+                candidate("c", 40, "Building A"),
+                candidate("small", 20, "Building A"),
+                candidate("large", 80, "Building A"),
+                candidate("same-size-elsewhere", 40, "Building B"),
+                candidate("same-size-nearby", 40, "Building A"),
+            ],
+        );
+        let ids: Vec<&str> = ranked.iter().map(|c| c.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["same-size-nearby", "same-size-elsewhere", "large"]
+        );
+    }
+
+    #[test]
+    fn test_date_shorthands_use_campus_days_and_monday_weeks() {
+        // Sunday 23:30 UTC is already Monday morning on campus
+        let now = dt("2025-03-09T23:30:00+00:00");
+        assert_eq!(
+            DateShorthand::Today.bounds(now),
+            (
+                dt("2025-03-10T00:00:00+08:00"),
+                dt("2025-03-11T00:00:00+08:00")
+            )
+        );
+        assert_eq!(
+            DateShorthand::Tomorrow.bounds(now),
+            (
+                dt("2025-03-11T00:00:00+08:00"),
+                dt("2025-03-12T00:00:00+08:00")
+            )
+        );
+
+        let sunday = dt("2025-03-16T22:00:00+08:00");
+        assert_eq!(
+            DateShorthand::ThisWeek.bounds(sunday),
+            (
+                dt("2025-03-10T00:00:00+08:00"),
+                dt("2025-03-17T00:00:00+08:00")
+            )
+        );
+        assert_eq!(
+            DateShorthand::NextWeek.bounds(sunday),
+            (
+                dt("2025-03-17T00:00:00+08:00"),
+                dt("2025-03-24T00:00:00+08:00")
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleted_classrooms_are_not_found() {
+        let state = state().await;
+        add_user(&state, "a1", "a1@example.com", Role::Admin).await;
+        let mut deleted: classroom::ActiveModel = add_classroom(&state, "c1").await.into();
+        deleted.deleted_at = Set(Some(dt("2025-03-01T00:00:00+08:00")));
+        deleted.update(&state.db).await.unwrap();
+        let app = router(state);
+        let admin = sign_in(&app, "a1@example.com").await;
+
+        for (method, uri, body) in [
+            ("GET", "/classroom/c1/schedule", json!({})),
+            (
+                "GET",
+                "/classroom/c1/availability?from=2025-03-10T09:00:00%2B08:00&to=2025-03-10T11:00:00%2B08:00",
+                json!({}),
+            ),
+            ("PUT", "/classroom/c1/schedule", json!({ "hours": [] })),
+            ("PATCH", "/classroom/c1", json!({ "name": "A102" })),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(send(method, uri, &admin, body))
+                .await
+                .unwrap();
+            assert_eq!(
+                response.status(),
+                StatusCode::NOT_FOUND,
+                "{} {}",
+                method,
+                uri
+            );
+        }
+    }
+}
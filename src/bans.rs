@@ -0,0 +1,131 @@
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone, sea_query::Expr,
+};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_queue::{Priority, queue_email_to_user},
+    email_templates,
+    entities::{black_list, user},
+};
+
+/// How often ended bans are looked for to notify their users.
+const END_SCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15 * 60);
+
+/// Whether a blacklist record still applies at `now`. Records without an
+/// `end_at` are permanent.
+pub fn is_active(record: &black_list::Model, now: DateTimeWithTimeZone) -> bool {
+    record.end_at.is_none_or(|end_at| end_at > now)
+}
+
+/// Filter for records still in force at `now`.
+pub fn active_condition(now: DateTimeWithTimeZone) -> Condition {
+    Condition::any()
+        .add(black_list::Column::EndAt.is_null())
+        .add(black_list::Column::EndAt.gt(now))
+}
+
+/// Bans of a user in force at `now`, newest first.
+pub async fn active_for_user(
+    db: &DatabaseConnection,
+    user_id: &str,
+    now: DateTimeWithTimeZone,
+) -> Result<Vec<black_list::Model>, DbErr> {
+    black_list::Entity::find()
+        .filter(black_list::Column::UserId.eq(user_id))
+        .filter(active_condition(now))
+        .order_by_desc(black_list::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+/// Why a user with `bans` in force may not book classrooms or borrow keys,
+/// naming when the last of them ends. `None` when there are none.
+pub fn banned_message(bans: &[black_list::Model]) -> Option<String> {
+    if bans.is_empty() {
+        return None;
+    }
+    if bans.iter().any(|b| b.end_at.is_none()) {
+        return Some("You are blacklisted".to_string());
+    }
+    let until = bans.iter().filter_map(|b| b.end_at).max()?;
+    Some(format!(
+        "You are blacklisted until {}",
+        until
+            .with_timezone(&campus_offset())
+            .format("%Y-%m-%d %H:%M (GMT+8)")
+    ))
+}
+
+/// Rejects a user who is blacklisted right now. The outer error is a database
+/// failure, the inner one the message for the user.
+pub async fn ensure_not_banned(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<Result<(), String>, DbErr> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let bans = active_for_user(db, user_id, now).await?;
+    Ok(match banned_message(&bans) {
+        Some(message) => Err(message),
+        None => Ok(()),
+    })
+}
+
+/// Emails users whose ban ended every [`END_SCAN_INTERVAL`]. Spawned once at
+/// startup.
+pub async fn run(state: AppState) {
+    let mut ticker = tokio::time::interval(END_SCAN_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = notify_ended(&state).await {
+            warn!("Blacklist expiry scan failed: {}", e);
+        }
+    }
+}
+
+/// Tells users their ban is over, unless another one still applies. Each
+/// record is notified at most once.
+pub async fn notify_ended(state: &AppState) -> Result<(), DbErr> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let ended = black_list::Entity::find()
+        .filter(black_list::Column::EndAt.lte(now))
+        .filter(black_list::Column::EndNotifiedAt.is_null())
+        .all(&state.db)
+        .await?;
+    for record in ended {
+        // Claimed first so parallel instances only send once
+        let claimed = black_list::Entity::update_many()
+            .col_expr(black_list::Column::EndNotifiedAt, Expr::value(now))
+            .filter(black_list::Column::Id.eq(&record.id))
+            .filter(black_list::Column::EndNotifiedAt.is_null())
+            .exec(&state.db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            continue;
+        }
+        let Some(user_id) = &record.user_id else {
+            continue;
+        };
+        if !active_for_user(&state.db, user_id, now).await?.is_empty() {
+            continue;
+        }
+        let Some(user) = user::Entity::find_by_id(user_id).one(&state.db).await? else {
+            continue;
+        };
+        info!("Blacklist record {} of user {} ended", record.id, user.id);
+        let email =
+            email_templates::black_list_ended(state.config.email_templates.locale_for(&user));
+        if let Err(e) =
+            queue_email_to_user(state, &user, email.subject, email.body, Priority::Normal).await
+        {
+            warn!("Failed to notify user {} of ended ban: {}", user.id, e);
+        }
+    }
+    Ok(())
+}
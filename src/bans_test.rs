@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use chrono::{DateTime, Duration, FixedOffset};
+    use sea_orm::{ActiveModelTrait, EntityTrait, IntoActiveModel};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::super::{
+        bans::{banned_message, is_active},
+        entities::{
+            black_list, key, key_transaction_log,
+            sea_orm_active_enums::{KeyStatus, ReservationStatus, Role},
+        },
+        test_support::{
+            add_classroom, add_user, json, reservation_at, router, send, sign_in, state,
+        },
+    };
+
+    fn at(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn record(end_at: Option<&str>) -> black_list::Model {
+        black_list::Model {
+            id: "b1".to_string(),
+            user_id: Some("u1".to_string()),
+            infraction_id: None,
+            created_by: None,
+            created_at: at("2025-03-01T09:00:00+08:00"),
+            end_at: end_at.map(at),
+            end_notified_at: None,
+        }
+    }
+
+    #[test]
+    fn ban_without_end_is_permanent() {
+        let now = at("2030-01-01T00:00:00+08:00");
+        assert!(is_active(&record(None), now));
+    }
+
+    #[test]
+    fn ban_ends_at_end_at() {
+        let ban = record(Some("2025-04-01T00:00:00+08:00"));
+        let end = ban.end_at.unwrap();
+        assert!(is_active(&ban, end - Duration::seconds(1)));
+        assert!(!is_active(&ban, end));
+    }
+
+    #[test]
+    fn no_message_without_bans() {
+        assert_eq!(banned_message(&[]), None);
+    }
+
+    #[test]
+    fn message_names_latest_end() {
+        let bans = [
+            record(Some("2025-04-01T00:00:00+08:00")),
+            record(Some("2025-05-02T12:30:00Z")),
+        ];
+        assert_eq!(
+            banned_message(&bans).as_deref(),
+            Some("You are blacklisted until 2025-05-02 20:30 (GMT+8)")
+        );
+    }
+
+    #[test]
+    fn permanent_ban_has_no_end_in_message() {
+        let bans = [record(Some("2025-04-01T00:00:00+08:00")), record(None)];
+        assert_eq!(
+            banned_message(&bans).as_deref(),
+            Some("You are blacklisted")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_desk_sync_refuses_borrows_for_banned_users() {
+        let state = state().await;
+        add_user(&state, "u1", "u1@example.com", Role::User).await;
+        add_user(&state, "a1", "a1@example.com", Role::Admin).await;
+        add_classroom(&state, "c1").await;
+        key::Model {
+            id: "k1".to_string(),
+            classroom_id: Some("c1".to_string()),
+            key_number: "A-1".to_string(),
+            status: KeyStatus::Active,
+            cabinet_slot: None,
+            version: 1,
+        }
+        .into_active_model()
+        .insert(&state.db)
+        .await
+        .unwrap();
+        let mut reservation = reservation_at(
+            "r1",
+            "2030-03-11T09:00:00+08:00",
+            "2030-03-11T11:00:00+08:00",
+        );
+        reservation.status = ReservationStatus::Approved;
+        reservation
+            .into_active_model()
+            .reset_all()
+            .insert(&state.db)
+            .await
+            .unwrap();
+        record(None)
+            .into_active_model()
+            .reset_all()
+            .insert(&state.db)
+            .await
+            .unwrap();
+        let app = router(state.clone());
+        let admin = sign_in(&app, "a1@example.com").await;
+
+        let response = app
+            .oneshot(send(
+                "POST",
+                "/key/sync",
+                &admin,
+                json!({
+                    "actions": [{
+                        "client_action_id": "desk-1",
+                        "action": "borrow",
+                        "key_id": "k1",
+                        "recorded_at": "2030-03-11T09:00:00+08:00",
+                        "reservation_id": "r1",
+                    }],
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json(response).await;
+        assert_eq!(body["failed"], 1);
+        assert_eq!(body["results"][0]["error"], "You are blacklisted");
+        assert!(
+            key_transaction_log::Entity::find()
+                .all(&state.db)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
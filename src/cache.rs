@@ -0,0 +1,337 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Write},
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use futures_util::future::BoxFuture;
+use redis::{AsyncCommands, Expiry, SetExpiry, SetOptions};
+use serde::{Serialize, de::DeserializeOwned};
+use tracing::warn;
+
+use crate::{
+    constants::{NOT_FOUND_EXPIRY_SECONDS, REDIS_EXPIRY_JITTER_SECONDS, REDIS_EXPIRY_SECONDS},
+    entities::reservation,
+    redis_breaker::RedisConnection,
+};
+
+/// Loads in progress, one per key, so concurrent misses wait for the first
+/// instead of all querying the database.
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(Default::default);
+
+/// Hits and misses of `get_or_load` per kind of key.
+static LOOKUPS: LazyLock<Mutex<BTreeMap<&'static str, (u64, u64)>>> =
+    LazyLock::new(Default::default);
+
+/// Everything kept in the cache. Key formats live here and nowhere else, so
+/// readers and invalidation cannot drift apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheKey<'a> {
+    Classroom(&'a str),
+    ClassroomWithKeys(&'a str),
+    ClassroomWithReservations(&'a str),
+    ClassroomWithKeysAndReservations(&'a str),
+    ClassroomKeySummary(&'a str),
+    ClassroomList,
+    Reservation(&'a str),
+    /// All reservations of a user
+    UserReservations(&'a str),
+    User(&'a str),
+    /// Marks a classroom ID found not to exist
+    MissingClassroom(&'a str),
+    /// Marks a user ID found not to exist
+    MissingUser(&'a str),
+}
+
+impl CacheKey<'_> {
+    /// The key without its ID, as used to label metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Classroom(_) => "classroom",
+            Self::ClassroomWithKeys(_) => "classroom_with_keys",
+            Self::ClassroomWithReservations(_) => "classroom_with_reservations",
+            Self::ClassroomWithKeysAndReservations(_) => "classroom_with_keys_and_reservations",
+            Self::ClassroomKeySummary(_) => "classroom_key_summary",
+            Self::ClassroomList => "classroom_list",
+            Self::Reservation(_) => "reservation",
+            Self::UserReservations(_) => "user_reservations",
+            Self::User(_) => "user",
+            Self::MissingClassroom(_) => "missing_classroom",
+            Self::MissingUser(_) => "missing_user",
+        }
+    }
+}
+
+impl fmt::Display for CacheKey<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Classroom(id) => write!(f, "classroom_{}", id),
+            Self::ClassroomWithKeys(id) => write!(f, "classroom_{}_keys", id),
+            Self::ClassroomWithReservations(id) => write!(f, "classroom_{}_reservations", id),
+            Self::ClassroomWithKeysAndReservations(id) => {
+                write!(f, "classroom_{}_keys_reservations", id)
+            }
+            Self::ClassroomKeySummary(id) => write!(f, "classroom_{}_key_summary", id),
+            Self::ClassroomList => write!(f, "classrooms:list"),
+            Self::Reservation(id) => write!(f, "reservation_{}", id),
+            Self::UserReservations(user_id) => write!(f, "reservations_user_{}", user_id),
+            Self::User(id) => write!(f, "user_{}", id),
+            Self::MissingClassroom(id) => write!(f, "classroom_{}_missing", id),
+            Self::MissingUser(id) => write!(f, "user_{}_missing", id),
+        }
+    }
+}
+
+/// Where handlers cache what they read; users signed in are cached by
+/// `UserCache` under `CacheKey::User`. Caching is best effort: errors are
+/// logged and treated as a miss.
+pub trait CacheService: Send + Sync {
+    /// The cached JSON under `key`, keeping it for another `expiry_seconds`
+    fn get_raw<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, Option<String>>;
+    fn set_raw<'a>(&'a self, key: CacheKey<'a>, value: String) -> BoxFuture<'a, ()>;
+    fn remove<'a>(&'a self, keys: &'a [CacheKey<'a>]) -> BoxFuture<'a, ()>;
+    /// Remembers for `NOT_FOUND_EXPIRY_SECONDS` that what `key` marks does
+    /// not exist
+    fn mark_missing<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, ()>;
+    fn is_missing<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, bool>;
+}
+
+impl dyn CacheService {
+    pub async fn get<T: DeserializeOwned>(&self, key: CacheKey<'_>) -> Option<T> {
+        let cached = self.get_raw(key).await?;
+        serde_json::from_str(&cached).ok()
+    }
+
+    /// The cached value under `key`, or the one `load` returns, which is then
+    /// cached. Of several requests missing the same key at once only the
+    /// first loads it; the others wait and read what it cached.
+    pub async fn get_or_load<T, E, F, Fut>(&self, key: CacheKey<'_>, load: F) -> Result<T, E>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.get(key).await {
+            record_lookup(key, true);
+            return Ok(value);
+        }
+
+        let name = key.to_string();
+        let flight = IN_FLIGHT
+            .lock()
+            .unwrap()
+            .entry(name.clone())
+            .or_default()
+            .clone();
+        let guard = flight.lock().await;
+        let result = match self.get(key).await {
+            Some(value) => {
+                record_lookup(key, true);
+                Ok(value)
+            }
+            None => {
+                record_lookup(key, false);
+                let loaded = load().await;
+                if let Ok(value) = &loaded {
+                    self.set(key, value).await;
+                }
+                loaded
+            }
+        };
+        drop(guard);
+
+        // Nobody else is waiting once only the map and this request hold it
+        let mut in_flight = IN_FLIGHT.lock().unwrap();
+        if Arc::strong_count(&flight) <= 2 {
+            in_flight.remove(&name);
+        }
+        result
+    }
+
+    pub async fn set<T: Serialize>(&self, key: CacheKey<'_>, value: &T) {
+        self.set_raw(key, serde_json::to_string(value).unwrap())
+            .await
+    }
+
+    /// Drops every cached view of a classroom along with the classrooms list
+    /// and any mark that it does not exist.
+    pub async fn invalidate_classroom(&self, classroom_id: &str) {
+        self.remove(&[
+            CacheKey::Classroom(classroom_id),
+            CacheKey::ClassroomWithKeys(classroom_id),
+            CacheKey::ClassroomWithReservations(classroom_id),
+            CacheKey::ClassroomWithKeysAndReservations(classroom_id),
+            CacheKey::ClassroomList,
+            CacheKey::MissingClassroom(classroom_id),
+        ])
+        .await
+    }
+
+    /// Drops the views of a classroom that list its keys, after a key was
+    /// added, moved, removed or changed status.
+    pub async fn invalidate_classroom_keys(&self, classroom_id: &str) {
+        self.remove(&[
+            CacheKey::ClassroomWithKeys(classroom_id),
+            CacheKey::ClassroomWithKeysAndReservations(classroom_id),
+        ])
+        .await
+    }
+
+    /// Drops a reservation, its holder's reservation list and the views of
+    /// its classroom that list reservations.
+    pub async fn invalidate_reservation(&self, reservation: &reservation::Model) {
+        let mut keys = vec![CacheKey::Reservation(&reservation.id)];
+        if let Some(user_id) = &reservation.user_id {
+            keys.push(CacheKey::UserReservations(user_id));
+        }
+        if let Some(classroom_id) = &reservation.classroom_id {
+            keys.push(CacheKey::ClassroomWithReservations(classroom_id));
+            keys.push(CacheKey::ClassroomWithKeysAndReservations(classroom_id));
+        }
+        self.remove(&keys).await
+    }
+}
+
+/// Seconds an entry is kept: `REDIS_EXPIRY_SECONDS` plus up to
+/// `REDIS_EXPIRY_JITTER_SECONDS` at random.
+pub fn expiry_seconds() -> u64 {
+    REDIS_EXPIRY_SECONDS + OsRng.next_u64() % (REDIS_EXPIRY_JITTER_SECONDS + 1)
+}
+
+fn record_lookup(key: CacheKey<'_>, hit: bool) {
+    let mut lookups = LOOKUPS.lock().unwrap();
+    let (hits, misses) = lookups.entry(key.kind()).or_default();
+    match hit {
+        true => *hits += 1,
+        false => *misses += 1,
+    }
+}
+
+/// Hits and misses of `get_or_load` in the Prometheus text format.
+pub fn render_metrics() -> String {
+    let lookups = LOOKUPS.lock().unwrap();
+    let mut out = String::new();
+    let _ = writeln!(out, "# HELP cache_lookups_total Cache lookups by result");
+    let _ = writeln!(out, "# TYPE cache_lookups_total counter");
+    for (kind, (hits, misses)) in lookups.iter() {
+        for (result, count) in [("hit", hits), ("miss", misses)] {
+            let _ = writeln!(
+                out,
+                "cache_lookups_total{{key=\"{}\",result=\"{}\"}} {}",
+                kind, result, count
+            );
+        }
+    }
+    out
+}
+
+/// Caches in Redis for `expiry_seconds`, refreshed on every read.
+pub struct RedisCache {
+    redis: RedisConnection,
+}
+
+impl RedisCache {
+    pub fn new(redis: RedisConnection) -> Self {
+        Self { redis }
+    }
+}
+
+impl CacheService for RedisCache {
+    fn get_raw<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let expiry = Expiry::EX(expiry_seconds());
+            match redis.get_ex(key.to_string(), expiry).await {
+                Ok(cached) => cached,
+                Err(e) => {
+                    warn!("Failed to get {} from Redis cache: {}", key, e);
+                    None
+                }
+            }
+        })
+    }
+
+    fn set_raw<'a>(&'a self, key: CacheKey<'a>, value: String) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let options = SetOptions::default().with_expiration(SetExpiry::EX(expiry_seconds()));
+            let result: Result<(), redis::RedisError> =
+                redis.set_options(key.to_string(), value, options).await;
+            if let Err(e) = result {
+                warn!("Failed to cache {} in Redis: {}", key, e);
+            }
+        })
+    }
+
+    fn remove<'a>(&'a self, keys: &'a [CacheKey<'a>]) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            if keys.is_empty() {
+                return;
+            }
+            let mut redis = self.redis.clone();
+            let names: Vec<String> = keys.iter().map(ToString::to_string).collect();
+            let result: Result<(), redis::RedisError> = redis.del(&names).await;
+            if let Err(e) = result {
+                warn!(
+                    "Failed to drop {} from Redis cache: {}",
+                    names.join(", "),
+                    e
+                );
+            }
+        })
+    }
+
+    fn mark_missing<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let result: Result<(), redis::RedisError> = redis
+                .set_ex(key.to_string(), 1, NOT_FOUND_EXPIRY_SECONDS)
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to cache {} in Redis: {}", key, e);
+            }
+        })
+    }
+
+    fn is_missing<'a>(&'a self, key: CacheKey<'a>) -> BoxFuture<'a, bool> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            match redis.exists(key.to_string()).await {
+                Ok(exists) => exists,
+                Err(e) => {
+                    warn!("Failed to get {} from Redis cache: {}", key, e);
+                    false
+                }
+            }
+        })
+    }
+}
+
+/// Caches nothing, so every read goes to the database.
+#[cfg(test)]
+pub struct NoCache;
+
+#[cfg(test)]
+impl CacheService for NoCache {
+    fn get_raw<'a>(&'a self, _key: CacheKey<'a>) -> BoxFuture<'a, Option<String>> {
+        Box::pin(async { None })
+    }
+
+    fn set_raw<'a>(&'a self, _key: CacheKey<'a>, _value: String) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+
+    fn remove<'a>(&'a self, _keys: &'a [CacheKey<'a>]) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+
+    fn mark_missing<'a>(&'a self, _key: CacheKey<'a>) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+
+    fn is_missing<'a>(&'a self, _key: CacheKey<'a>) -> BoxFuture<'a, bool> {
+        Box::pin(async { false })
+    }
+}
@@ -0,0 +1,146 @@
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+        time::Duration,
+    };
+
+    use super::super::{
+        cache::{CacheKey, CacheService, NoCache, RedisCache, expiry_seconds, render_metrics},
+        constants::{REDIS_EXPIRY_JITTER_SECONDS, REDIS_EXPIRY_SECONDS},
+        entities::reservation,
+        memory_redis::MemoryRedis,
+        redis_breaker::RedisConnection,
+        test_support::reservation_at,
+    };
+
+    fn booking() -> reservation::Model {
+        reservation_at(
+            "r1",
+            "2025-03-10T09:00:00+08:00",
+            "2025-03-10T11:00:00+08:00",
+        )
+    }
+
+    #[test]
+    fn test_keys_keep_their_redis_names() {
+        assert_eq!(CacheKey::Classroom("c1").to_string(), "classroom_c1");
+        assert_eq!(
+            CacheKey::ClassroomWithKeysAndReservations("c1").to_string(),
+            "classroom_c1_keys_reservations"
+        );
+        assert_eq!(CacheKey::ClassroomList.to_string(), "classrooms:list");
+        assert_eq!(
+            CacheKey::UserReservations("u1").to_string(),
+            "reservations_user_u1"
+        );
+        assert_eq!(CacheKey::User("u1").to_string(), "user_u1");
+    }
+
+    #[tokio::test]
+    async fn test_invalidating_a_reservation_drops_every_view_of_it() {
+        let cache: Arc<dyn CacheService> = Arc::new(RedisCache::new(RedisConnection::in_memory(
+            MemoryRedis::default(),
+        )));
+        let reservation = booking();
+        cache.set(CacheKey::Reservation("r1"), &reservation).await;
+        cache
+            .set(CacheKey::UserReservations("u1"), &vec![reservation.clone()])
+            .await;
+        cache
+            .set(CacheKey::ClassroomWithReservations("c1"), &"view")
+            .await;
+        cache.set(CacheKey::Classroom("c1"), &"classroom").await;
+        assert_eq!(
+            cache
+                .get::<reservation::Model>(CacheKey::Reservation("r1"))
+                .await,
+            Some(reservation.clone())
+        );
+
+        cache.invalidate_reservation(&reservation).await;
+        assert!(cache.get_raw(CacheKey::Reservation("r1")).await.is_none());
+        assert!(
+            cache
+                .get_raw(CacheKey::UserReservations("u1"))
+                .await
+                .is_none()
+        );
+        assert!(
+            cache
+                .get_raw(CacheKey::ClassroomWithReservations("c1"))
+                .await
+                .is_none()
+        );
+        // The classroom itself does not list reservations
+        assert_eq!(
+            cache.get::<String>(CacheKey::Classroom("c1")).await,
+            Some("classroom".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_cache_always_misses() {
+        let cache: Arc<dyn CacheService> = Arc::new(NoCache);
+        cache.set(CacheKey::ClassroomList, &vec!["c1"]).await;
+        assert!(cache.get_raw(CacheKey::ClassroomList).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_misses_load_once() {
+        let cache: Arc<dyn CacheService> = Arc::new(RedisCache::new(RedisConnection::in_memory(
+            MemoryRedis::default(),
+        )));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let requests = (0..8).map(|_| {
+            let cache = cache.clone();
+            let loads = loads.clone();
+            tokio::spawn(async move {
+                cache
+                    .get_or_load(CacheKey::Classroom("stampede"), || async {
+                        loads.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok::<_, Infallible>(vec!["c1".to_string()])
+                    })
+                    .await
+                    .unwrap()
+            })
+        });
+        for request in requests.collect::<Vec<_>>() {
+            assert_eq!(request.await.unwrap(), vec!["c1".to_string()]);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(
+            render_metrics().contains("cache_lookups_total{key=\"classroom\",result=\"miss\"}")
+        );
+    }
+
+    #[test]
+    fn test_expiry_is_jittered_within_bounds() {
+        for _ in 0..100 {
+            let seconds = expiry_seconds();
+            assert!(seconds >= REDIS_EXPIRY_SECONDS);
+            assert!(seconds <= REDIS_EXPIRY_SECONDS + REDIS_EXPIRY_JITTER_SECONDS);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_marks_clear_when_the_classroom_appears() {
+        let cache: Arc<dyn CacheService> = Arc::new(RedisCache::new(RedisConnection::in_memory(
+            MemoryRedis::default(),
+        )));
+        let key = CacheKey::MissingClassroom("c9");
+        assert!(!cache.is_missing(key).await);
+        cache.mark_missing(key).await;
+        assert!(cache.is_missing(key).await);
+        // Only the classroom is marked, not a user with the same ID
+        assert!(!cache.is_missing(CacheKey::MissingUser("c9")).await);
+
+        cache.invalidate_classroom("c9").await;
+        assert!(!cache.is_missing(key).await);
+    }
+}
@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use super::super::check_in::{CheckInTokenError, can_check_in, check_in_url, sign, verify};
+    use super::super::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+    use super::super::test_support::reservation_at;
+    use chrono::{DateTime, Duration, Utc};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    const SECRET: &[u8] = b"test-secret";
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        dt(s).with_timezone(&Utc)
+    }
+
+    fn booking(status: ReservationStatus) -> reservation::Model {
+        reservation::Model {
+            user_id: Some("u".to_string()),
+            classroom_id: Some("c".to_string()),
+            status,
+            ..reservation_at(
+                "r",
+                "2025-03-10T09:00:00+08:00",
+                "2025-03-10T11:00:00+08:00",
+            )
+        }
+    }
+
+    #[test]
+    fn test_signed_link_verifies() {
+        let now = utc("2025-03-10T08:00:00+08:00");
+        let expires = (now + Duration::hours(1)).timestamp();
+        let sig = sign(SECRET, "c", expires);
+        assert_eq!(verify(SECRET, "c", expires, &sig, now), Ok(()));
+    }
+
+    #[test]
+    fn test_tampered_link_is_rejected() {
+        let now = utc("2025-03-10T08:00:00+08:00");
+        let expires = (now + Duration::hours(1)).timestamp();
+        let sig = sign(SECRET, "c", expires);
+
+        // Another classroom, a pushed-back expiry, another secret, garbage
+        assert_eq!(
+            verify(SECRET, "other", expires, &sig, now),
+            Err(CheckInTokenError::InvalidSignature)
+        );
+        assert_eq!(
+            verify(SECRET, "c", expires + 3600, &sig, now),
+            Err(CheckInTokenError::InvalidSignature)
+        );
+        assert_eq!(
+            verify(b"other-secret", "c", expires, &sig, now),
+            Err(CheckInTokenError::InvalidSignature)
+        );
+        assert_eq!(
+            verify(SECRET, "c", expires, "not-hex", now),
+            Err(CheckInTokenError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn test_expired_link_is_rejected() {
+        let now = utc("2025-03-10T08:00:00+08:00");
+        let expires = now.timestamp();
+        let sig = sign(SECRET, "c", expires);
+        assert_eq!(
+            verify(SECRET, "c", expires, &sig, now),
+            Err(CheckInTokenError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_check_in_url() {
+        let url = check_in_url("https://api.example.edu/", SECRET, "c", 1700000000);
+        assert_eq!(
+            url,
+            format!(
+                "https://api.example.edu/classroom/c/check-in?expires=1700000000&sig={}",
+                sign(SECRET, "c", 1700000000)
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_in_window() {
+        let approved = booking(ReservationStatus::Approved);
+        let early = Duration::minutes(15);
+        assert!(!can_check_in(
+            &approved,
+            utc("2025-03-10T08:40:00+08:00"),
+            early
+        ));
+        assert!(can_check_in(
+            &approved,
+            utc("2025-03-10T08:45:00+08:00"),
+            early
+        ));
+        assert!(can_check_in(
+            &approved,
+            utc("2025-03-10T10:59:00+08:00"),
+            early
+        ));
+        assert!(!can_check_in(
+            &approved,
+            utc("2025-03-10T11:00:00+08:00"),
+            early
+        ));
+    }
+
+    #[test]
+    fn test_only_approved_reservations_check_in() {
+        let now = utc("2025-03-10T09:30:00+08:00");
+        for status in [
+            ReservationStatus::Pending,
+            ReservationStatus::Rejected,
+            ReservationStatus::Cancelled,
+        ] {
+            assert!(!can_check_in(&booking(status), now, Duration::zero()));
+        }
+    }
+}
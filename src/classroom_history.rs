@@ -0,0 +1,94 @@
+use chrono::Utc;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, prelude::DateTimeWithTimeZone,
+};
+
+use crate::{
+    availability::campus_offset,
+    entities::{classroom, classroom_history},
+};
+
+/// Records `current` as the classroom's configuration from now on, or from
+/// its creation when `previous` is `None`. Otherwise pass the row as it was
+/// before the write as `previous`: the first time a classroom is versioned,
+/// that state is recorded too, dated back to its creation, so questions about
+/// earlier times can still be answered.
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    previous: Option<&classroom::Model>,
+    current: &classroom::Model,
+) -> Result<(), DbErr> {
+    if let Some(previous) = previous {
+        let versions = classroom_history::Entity::find()
+            .filter(classroom_history::Column::ClassroomId.eq(&previous.id))
+            .count(db)
+            .await?;
+        if versions == 0 {
+            insert(db, previous, previous.created_at).await?;
+        }
+    }
+    let valid_from = match previous {
+        Some(_) => Utc::now().with_timezone(&campus_offset()),
+        None => current.created_at,
+    };
+    insert(db, current, valid_from).await
+}
+
+async fn insert<C: ConnectionTrait>(
+    db: &C,
+    classroom: &classroom::Model,
+    valid_from: DateTimeWithTimeZone,
+) -> Result<(), DbErr> {
+    classroom_history::ActiveModel {
+        id: Set(nanoid!()),
+        classroom_id: Set(classroom.id.clone()),
+        snapshot: Set(serde_json::to_value(classroom).unwrap()),
+        valid_from: Set(valid_from),
+    }
+    .insert(db)
+    .await
+    .map(|_| ())
+}
+
+/// The version in effect at `timestamp`: the latest one that started at or
+/// before it, or the earliest version for times before versioning caught up
+/// with the classroom's creation.
+pub fn version_at(
+    versions: &[classroom_history::Model],
+    timestamp: DateTimeWithTimeZone,
+) -> Option<&classroom_history::Model> {
+    versions
+        .iter()
+        .filter(|v| v.valid_from <= timestamp)
+        .max_by_key(|v| v.valid_from)
+        .or_else(|| versions.iter().min_by_key(|v| v.valid_from))
+}
+
+/// How the classroom was configured at `timestamp`, with the time that
+/// configuration took effect. `None` if the classroom did not exist yet.
+/// A classroom never changed since versioning began is returned as it is
+/// now, with no `valid_from`.
+pub async fn as_of<C: ConnectionTrait>(
+    db: &C,
+    current: classroom::Model,
+    timestamp: DateTimeWithTimeZone,
+) -> Result<Option<(classroom::Model, Option<DateTimeWithTimeZone>)>, DbErr> {
+    if timestamp < current.created_at {
+        return Ok(None);
+    }
+    let versions = classroom_history::Entity::find()
+        .filter(classroom_history::Column::ClassroomId.eq(&current.id))
+        .order_by_asc(classroom_history::Column::ValidFrom)
+        .all(db)
+        .await?;
+    match version_at(&versions, timestamp) {
+        Some(version) => {
+            let snapshot = serde_json::from_value(version.snapshot.clone())
+                .map_err(|e| DbErr::Json(e.to_string()))?;
+            Ok(Some((snapshot, Some(version.valid_from))))
+        }
+        None => Ok(Some((current, None))),
+    }
+}
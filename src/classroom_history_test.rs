@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod tests {
+    use super::super::classroom_history::version_at;
+    use super::super::entities::classroom_history;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+    use serde_json::json;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn version(id: &str, valid_from: &str) -> classroom_history::Model {
+        classroom_history::Model {
+            id: id.to_string(),
+            classroom_id: "room-1".to_string(),
+            snapshot: json!({ "id": "room-1" }),
+            valid_from: dt(valid_from),
+        }
+    }
+
+    #[test]
+    fn test_latest_version_at_or_before_timestamp() {
+        let versions = vec![
+            version("v1", "2025-01-01T00:00:00+08:00"),
+            version("v3", "2025-03-01T00:00:00+08:00"),
+            version("v2", "2025-02-01T00:00:00+08:00"),
+        ];
+        let at = |s| version_at(&versions, dt(s)).map(|v| v.id.as_str());
+        assert_eq!(at("2025-01-15T00:00:00+08:00"), Some("v1"));
+        assert_eq!(at("2025-02-01T00:00:00+08:00"), Some("v2"));
+        assert_eq!(at("2025-06-01T00:00:00+08:00"), Some("v3"));
+    }
+
+    #[test]
+    fn test_earliest_version_before_versioning_began() {
+        let versions = vec![
+            version("v2", "2025-02-01T00:00:00+08:00"),
+            version("v1", "2025-01-01T00:00:00+08:00"),
+        ];
+        assert_eq!(
+            version_at(&versions, dt("2024-12-01T00:00:00+08:00")).map(|v| v.id.as_str()),
+            Some("v1")
+        );
+        assert!(version_at(&[], dt("2025-01-01T00:00:00+08:00")).is_none());
+    }
+}
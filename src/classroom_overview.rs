@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Days, Duration};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    availability::{campus_offset, is_out_of_service},
+    entities::{
+        classroom, classroom_closure, classroom_schedule, course_session, key, reservation,
+        sea_orm_active_enums::{KeyStatus, ReservationStatus},
+    },
+    timetable::occurrences,
+};
+
+/// How far ahead the next free slot is looked for.
+pub const NEXT_FREE_SLOT_HORIZON_DAYS: i64 = 14;
+
+/// Key counts and the next free moment of a classroom, shown as badges in
+/// the room picker.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default, PartialEq)]
+pub struct ClassroomOverview {
+    /// Every key that has not been retired
+    pub total_keys: u64,
+    /// Keys on hand that can be borrowed
+    pub available_keys: u64,
+    /// The first moment from now, within the next two weeks, that the
+    /// classroom is open and not taken by a reservation, closure, course or
+    /// maintenance; `null` when there is none
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub next_free_slot: Option<DateTimeWithTimeZone>,
+}
+
+type Interval = (DateTimeWithTimeZone, DateTimeWithTimeZone);
+
+/// The first moment in `[now, until)` not covered by any of `busy`.
+pub fn first_free(
+    now: DateTimeWithTimeZone,
+    until: DateTimeWithTimeZone,
+    mut busy: Vec<Interval>,
+) -> Option<DateTimeWithTimeZone> {
+    busy.sort_by_key(|(start, _)| *start);
+    let mut free_from = now;
+    for (start, end) in busy {
+        if start > free_from {
+            break;
+        }
+        free_from = free_from.max(end);
+    }
+    (free_from < until).then_some(free_from)
+}
+
+/// The times in `[from, to)` outside the weekly opening hours, in campus
+/// time. A classroom without any configured hours is always open.
+pub fn closed_hours(
+    hours: &[&classroom_schedule::Model],
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+) -> Vec<Interval> {
+    if hours.is_empty() {
+        return Vec::new();
+    }
+    let offset = campus_offset();
+    let at = |day: chrono::NaiveDate, time: chrono::NaiveTime| {
+        day.and_time(time).and_local_timezone(offset).unwrap()
+    };
+
+    let mut closed = Vec::new();
+    let mut day = from.with_timezone(&offset).date_naive();
+    let last_day = to.with_timezone(&offset).date_naive();
+    while day <= last_day {
+        let weekday = day.weekday().num_days_from_monday() as i16;
+        let mut windows: Vec<_> = hours.iter().filter(|h| h.weekday == weekday).collect();
+        windows.sort_by_key(|h| h.open_time);
+
+        let mut closed_from = at(day, chrono::NaiveTime::MIN);
+        for window in windows {
+            closed.push((closed_from, at(day, window.open_time)));
+            closed_from = closed_from.max(at(day, window.close_time));
+        }
+        let Some(next_day) = day.checked_add_days(Days::new(1)) else {
+            break;
+        };
+        closed.push((closed_from, at(next_day, chrono::NaiveTime::MIN)));
+        day = next_day;
+    }
+    closed.retain(|(start, end)| start < end);
+    closed
+}
+
+/// The out-of-service period of a classroom, as far as it reaches into
+/// `[from, to)`.
+fn out_of_service(
+    classroom: &classroom::Model,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+) -> Option<Interval> {
+    if !is_out_of_service(&classroom.status) {
+        return None;
+    }
+    Some((
+        classroom.status_from.unwrap_or(from),
+        classroom.status_until.unwrap_or(to),
+    ))
+}
+
+/// Overviews of `classrooms` at `now`, by classroom id. Runs a fixed number
+/// of queries however many classrooms there are.
+pub async fn load_overviews(
+    db: &DatabaseConnection,
+    classrooms: &[classroom::Model],
+    now: DateTimeWithTimeZone,
+) -> Result<HashMap<String, ClassroomOverview>, DbErr> {
+    let until = now + Duration::days(NEXT_FREE_SLOT_HORIZON_DAYS);
+    let ids: Vec<String> = classrooms.iter().map(|c| c.id.clone()).collect();
+
+    let key_counts: Vec<(Option<String>, KeyStatus, i64)> = key::Entity::find()
+        .select_only()
+        .column(key::Column::ClassroomId)
+        .column(key::Column::Status)
+        .column_as(key::Column::Id.count(), "count")
+        .filter(key::Column::ClassroomId.is_in(ids.clone()))
+        .group_by(key::Column::ClassroomId)
+        .group_by(key::Column::Status)
+        .into_tuple()
+        .all(db)
+        .await?;
+
+    let reservations: Vec<(Option<String>, DateTimeWithTimeZone, DateTimeWithTimeZone)> =
+        reservation::Entity::find()
+            .select_only()
+            .column(reservation::Column::ClassroomId)
+            .column(reservation::Column::StartTime)
+            .column(reservation::Column::EndTime)
+            .filter(reservation::Column::ClassroomId.is_in(ids.clone()))
+            .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+            .filter(reservation::Column::StartTime.lt(until))
+            .filter(reservation::Column::EndTime.gt(now))
+            .into_tuple()
+            .all(db)
+            .await?;
+
+    let closures = classroom_closure::Entity::find()
+        .filter(classroom_closure::Column::ClassroomId.is_in(ids.clone()))
+        .filter(classroom_closure::Column::StartAt.lt(until))
+        .filter(classroom_closure::Column::EndAt.gt(now))
+        .all(db)
+        .await?;
+
+    let sessions = course_session::Entity::find()
+        .filter(course_session::Column::ClassroomId.is_in(ids.clone()))
+        .filter(
+            course_session::Column::TermEnd.gte(now.with_timezone(&campus_offset()).date_naive()),
+        )
+        .filter(
+            course_session::Column::TermStart
+                .lte(until.with_timezone(&campus_offset()).date_naive()),
+        )
+        .all(db)
+        .await?;
+
+    let hours = classroom_schedule::Entity::find()
+        .filter(classroom_schedule::Column::ClassroomId.is_in(ids))
+        .all(db)
+        .await?;
+
+    let mut overviews: HashMap<String, ClassroomOverview> = HashMap::new();
+    for (classroom_id, status, count) in key_counts {
+        let Some(classroom_id) = classroom_id else {
+            continue;
+        };
+        let overview = overviews.entry(classroom_id).or_default();
+        match status {
+            KeyStatus::Retired => continue,
+            KeyStatus::Active => overview.available_keys += count as u64,
+            _ => {}
+        }
+        overview.total_keys += count as u64;
+    }
+
+    let mut busy: HashMap<&str, Vec<Interval>> = HashMap::new();
+    for (classroom_id, start, end) in &reservations {
+        if let Some(classroom_id) = classroom_id {
+            busy.entry(classroom_id).or_default().push((*start, *end));
+        }
+    }
+    for closure in &closures {
+        busy.entry(&closure.classroom_id)
+            .or_default()
+            .push((closure.start_at, closure.end_at));
+    }
+    for session in &sessions {
+        busy.entry(&session.classroom_id)
+            .or_default()
+            .extend(occurrences(session, now, until));
+    }
+
+    for classroom in classrooms {
+        let mut intervals = busy.remove(classroom.id.as_str()).unwrap_or_default();
+        let opening_hours: Vec<_> = hours
+            .iter()
+            .filter(|h| h.classroom_id == classroom.id)
+            .collect();
+        intervals.extend(closed_hours(&opening_hours, now, until));
+        intervals.extend(out_of_service(classroom, now, until));
+        overviews
+            .entry(classroom.id.clone())
+            .or_default()
+            .next_free_slot = first_free(now, until, intervals);
+    }
+
+    Ok(overviews)
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use sea_orm::{
+        ActiveModelTrait,
+        ActiveValue::{NotSet, Set},
+        IntoActiveModel,
+        prelude::DateTimeWithTimeZone,
+    };
+
+    use super::super::{
+        classroom_overview::{closed_hours, first_free, load_overviews},
+        entities::{
+            classroom, classroom_schedule, key, reservation,
+            sea_orm_active_enums::{ClassroomStatus, KeyStatus, ReservationStatus},
+        },
+        memory_mode,
+    };
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn room(id: &str) -> classroom::Model {
+        classroom::Model {
+            id: id.to_string(),
+            name: id.to_string(),
+            location: "Building A".to_string(),
+            capacity: 40,
+            description: String::new(),
+            status: ClassroomStatus::Available,
+            created_at: dt("2025-01-01T00:00:00+08:00"),
+            updated_at: dt("2025-01-01T00:00:00+08:00"),
+            photo_id: String::new(),
+            status_reason: None,
+            status_from: None,
+            status_until: None,
+            deleted_at: None,
+            name_en: None,
+            name_zh_tw: None,
+            description_en: None,
+            description_zh_tw: None,
+            requires_double_approval: false,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_first_free_skips_back_to_back_bookings() {
+        let now = dt("2025-03-10T09:30:00+08:00");
+        let until = dt("2025-03-11T00:00:00+08:00");
+        assert_eq!(first_free(now, until, vec![]), Some(now));
+        assert_eq!(
+            first_free(
+                now,
+                until,
+                vec![
+                    (
+                        dt("2025-03-10T10:00:00+08:00"),
+                        dt("2025-03-10T11:00:00+08:00")
+                    ),
+                    (
+                        dt("2025-03-10T09:00:00+08:00"),
+                        dt("2025-03-10T10:00:00+08:00")
+                    ),
+                    (
+                        dt("2025-03-10T12:00:00+08:00"),
+                        dt("2025-03-10T13:00:00+08:00")
+                    ),
+                ]
+            ),
+            Some(dt("2025-03-10T11:00:00+08:00"))
+        );
+        assert_eq!(
+            first_free(now, until, vec![(now, until)]),
+            None,
+            "busy until the end of the horizon"
+        );
+    }
+
+    #[test]
+    fn test_closed_hours_cover_nights() {
+        // Monday 2025-03-10, open 08:00-12:00 and 13:00-17:00 on Mondays only
+        let hours = [
+            classroom_schedule::Model {
+                id: "am".to_string(),
+                classroom_id: "c".to_string(),
+                weekday: 0,
+                open_time: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                close_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            },
+            classroom_schedule::Model {
+                id: "pm".to_string(),
+                classroom_id: "c".to_string(),
+                weekday: 0,
+                open_time: NaiveTime::from_hms_opt(13, 0, 0).unwrap(),
+                close_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            },
+        ];
+        let hours: Vec<_> = hours.iter().collect();
+        let from = dt("2025-03-10T12:30:00+08:00");
+        let until = dt("2025-03-24T00:00:00+08:00");
+        let closed = closed_hours(&hours, from, until);
+        assert_eq!(
+            first_free(from, until, closed.clone()),
+            Some(dt("2025-03-10T13:00:00+08:00"))
+        );
+        assert_eq!(
+            first_free(dt("2025-03-10T17:00:00+08:00"), until, closed),
+            Some(dt("2025-03-17T08:00:00+08:00"))
+        );
+        assert!(closed_hours(&[], from, until).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overviews_count_keys_and_bookings() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let now = dt("2025-03-10T09:30:00+08:00");
+        let busy = room("busy");
+        let idle = room("idle");
+        let closed = classroom::Model {
+            status: ClassroomStatus::Maintenance,
+            ..room("closed")
+        };
+        for classroom in [&busy, &idle, &closed] {
+            classroom
+                .clone()
+                .into_active_model()
+                .insert(&db)
+                .await
+                .unwrap();
+        }
+        for (id, status) in [
+            ("k1", KeyStatus::Active),
+            ("k2", KeyStatus::Active),
+            ("k3", KeyStatus::Borrowed),
+            ("k4", KeyStatus::Retired),
+        ] {
+            key::ActiveModel {
+                id: Set(id.to_string()),
+                classroom_id: Set(Some("busy".to_string())),
+                key_number: Set(id.to_string()),
+                status: Set(status),
+                cabinet_slot: Set(None),
+                version: NotSet,
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+        reservation::ActiveModel {
+            id: Set("r".to_string()),
+            classroom_id: Set(Some("busy".to_string())),
+            purpose: Set("Seminar".to_string()),
+            start_time: Set(dt("2025-03-10T09:00:00+08:00")),
+            end_time: Set(dt("2025-03-10T11:00:00+08:00")),
+            status: Set(ReservationStatus::Approved),
+            flagged_for_review: Set(false),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let overviews = load_overviews(&db, &[busy, idle, closed], now)
+            .await
+            .unwrap();
+        assert_eq!(overviews["busy"].total_keys, 3);
+        assert_eq!(overviews["busy"].available_keys, 2);
+        assert_eq!(
+            overviews["busy"].next_free_slot,
+            Some(dt("2025-03-10T11:00:00+08:00"))
+        );
+        assert_eq!(overviews["idle"].total_keys, 0);
+        assert_eq!(overviews["idle"].next_free_slot, Some(now));
+        assert_eq!(overviews["closed"].next_free_slot, None);
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::{AlternativeRoom, suggest_alternatives},
+    constants::MAX_ALTERNATIVE_ROOMS,
+    email_queue::{Priority, queue_email_to_user},
+    email_templates,
+    entities::{classroom, reservation, sea_orm_active_enums::ReservationStatus, user},
+    key_cabinet, pickup, reservation_groups,
+    reservation_lifecycle::{self, Actor},
+    versioning,
+};
+
+/// What to do with pending/approved reservations that fall inside a closure or
+/// out-of-service window.
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClosureAction {
+    /// Keep the reservation but flag it for an admin to review
+    #[default]
+    Flag,
+    /// Cancel the reservation with the closure reason
+    Cancel,
+    /// Move the reservation to `NeedsRebooking` and suggest free rooms
+    Rebook,
+}
+
+#[derive(Serialize, ToSchema, Clone)]
+pub struct AffectedReservation {
+    pub reservation: reservation::Model,
+    /// Free rooms for the same window; only filled for `rebook`
+    pub alternatives: Vec<AlternativeRoom>,
+}
+
+/// Pending/approved reservations in the given classrooms overlapping
+/// `[from, to)`. An open `to` extends the window indefinitely.
+pub async fn find_affected<C: ConnectionTrait>(
+    conn: &C,
+    classroom_ids: Vec<String>,
+    from: DateTimeWithTimeZone,
+    to: Option<DateTimeWithTimeZone>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let mut find_query = reservation::Entity::find()
+        .filter(reservation::Column::ClassroomId.is_in(classroom_ids))
+        .filter(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .filter(reservation::Column::EndTime.gt(from));
+    if let Some(to) = to {
+        find_query = find_query.filter(reservation::Column::StartTime.lt(to));
+    }
+    find_query.all(conn).await
+}
+
+/// Applies `action` to every reservation and to the rooms booked together
+/// with it. Meant to run inside the same transaction that creates the
+/// closure or changes the classroom status.
+pub async fn apply_action<C: ConnectionTrait>(
+    conn: &C,
+    reservations: Vec<reservation::Model>,
+    action: ClosureAction,
+    reason: &str,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let reservations = reservation_groups::with_members(conn, reservations).await?;
+    let mut updated = Vec::with_capacity(reservations.len());
+    for reservation_model in reservations {
+        let next_status = match action {
+            ClosureAction::Flag => None,
+            ClosureAction::Cancel => Some(ReservationStatus::Cancelled),
+            ClosureAction::Rebook => Some(ReservationStatus::NeedsRebooking),
+        };
+        // Anything the lifecycle refuses (e.g. already final) is only flagged
+        let next_status = next_status.filter(|to| {
+            reservation_lifecycle::check(&reservation_model, to, Actor::System).is_ok()
+        });
+
+        let mut active: reservation::ActiveModel = reservation_model.into();
+        match next_status {
+            Some(ReservationStatus::Cancelled) => {
+                active.status = Set(ReservationStatus::Cancelled);
+                active.cancel_reason = Set(Some(reason.to_string()));
+            }
+            Some(status) => {
+                active.status = Set(status);
+                active.flagged_for_review = Set(true);
+                active.flag_reason = Set(Some(reason.to_string()));
+            }
+            None => {
+                active.flagged_for_review = Set(true);
+                active.flag_reason = Set(Some(reason.to_string()));
+            }
+        }
+        updated.push(versioning::update(conn, active, reservation::Column::Version, None).await?);
+    }
+    Ok(updated)
+}
+
+/// Post-commit half of the flow: drops cached reservations, looks up
+/// alternatives when rebooking and emails every affected user once.
+pub async fn finish(
+    state: &AppState,
+    updated: Vec<reservation::Model>,
+    action: ClosureAction,
+    reason: String,
+) -> Vec<AffectedReservation> {
+    if updated.is_empty() {
+        return Vec::new();
+    }
+
+    for reservation_model in &updated {
+        if reservation_model.status != ReservationStatus::Approved {
+            tokio::spawn(key_cabinet::revoke_pins(
+                state.clone(),
+                reservation_model.id.clone(),
+            ));
+            tokio::spawn(pickup::revoke_codes(
+                state.clone(),
+                reservation_model.id.clone(),
+            ));
+        }
+        state.cache.invalidate_reservation(reservation_model).await;
+    }
+
+    let classrooms = classroom::Entity::find()
+        .filter(classroom::Column::Id.is_in(updated.iter().filter_map(|r| r.classroom_id.clone())))
+        .all(&state.db)
+        .await
+        .unwrap_or_default();
+
+    let mut affected = Vec::with_capacity(updated.len());
+    for reservation_model in updated {
+        let original = classrooms
+            .iter()
+            .find(|c| reservation_model.classroom_id.as_deref() == Some(c.id.as_str()));
+        let alternatives = match (action, original) {
+            (ClosureAction::Rebook, Some(original)) => suggest_alternatives(
+                &state.db,
+                original,
+                reservation_model.start_time,
+                reservation_model.end_time,
+                MAX_ALTERNATIVE_ROOMS,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to suggest alternatives for reservation {}: {}",
+                    reservation_model.id, e
+                );
+                Vec::new()
+            }),
+            _ => Vec::new(),
+        };
+        affected.push(AffectedReservation {
+            reservation: reservation_model,
+            alternatives,
+        });
+    }
+
+    tokio::spawn(notify_affected_users(
+        state.clone(),
+        affected.clone(),
+        classrooms,
+        action,
+        reason,
+    ));
+
+    affected
+}
+
+/// Sends one email per affected user listing all of their reservations.
+async fn notify_affected_users(
+    state: AppState,
+    affected: Vec<AffectedReservation>,
+    classrooms: Vec<classroom::Model>,
+    action: ClosureAction,
+    reason: String,
+) {
+    let mut by_user: BTreeMap<String, Vec<(AffectedReservation, Option<classroom::Model>)>> =
+        BTreeMap::new();
+    for item in affected {
+        let Some(user_id) = item.reservation.user_id.clone() else {
+            continue;
+        };
+        let classroom_model = classrooms
+            .iter()
+            .find(|c| item.reservation.classroom_id.as_deref() == Some(c.id.as_str()))
+            .cloned();
+        by_user
+            .entry(user_id)
+            .or_default()
+            .push((item, classroom_model));
+    }
+
+    let users = match user::Entity::find()
+        .filter(user::Column::Id.is_in(by_user.keys().cloned()))
+        .all(&state.db)
+        .await
+    {
+        Ok(users) => users,
+        Err(e) => {
+            warn!("Failed to fetch users for closure notification: {}", e);
+            return;
+        }
+    };
+
+    for user_model in users {
+        let Some(items) = by_user.get(&user_model.id) else {
+            continue;
+        };
+        let email = email_templates::reservations_affected(
+            &state.config.email_templates,
+            items,
+            action,
+            &reason,
+            state.config.email_templates.locale_for(&user_model),
+        );
+        if let Err(e) = queue_email_to_user(
+            &state,
+            &user_model,
+            email.subject,
+            email.body,
+            Priority::Normal,
+        )
+        .await
+        {
+            warn!(
+                "Failed to send closure notification to {}: {}",
+                user_model.id, e
+            );
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::fmt::Write;
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+use std::time::Duration;
+
+use axum::{
+    Extension,
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use futures_util::StreamExt;
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+use crate::error::AppError;
+
+/// How many expensive requests may run at once. Requests over the limit wait
+/// up to `queue_timeout` for a slot and are then turned away with 503.
+#[derive(Clone)]
+pub struct ConcurrencyConfig {
+    /// Multipart photo uploads, which hold the whole image in memory
+    pub photo_uploads: usize,
+    /// Reports and exports, which scan large parts of the database
+    pub reports: usize,
+    pub queue_timeout: Duration,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            photo_uploads: 4,
+            reports: 2,
+            queue_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+pub struct ConcurrencyLimiter {
+    name: &'static str,
+    max: usize,
+    semaphore: Arc<Semaphore>,
+    admitted: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl ConcurrencyLimiter {
+    fn new(name: &'static str, max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            name,
+            max,
+            semaphore: Arc::new(Semaphore::new(max)),
+            admitted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    fn in_flight(&self) -> usize {
+        self.max - self.semaphore.available_permits()
+    }
+}
+
+type MetricValue = fn(&ConcurrencyLimiter) -> u64;
+
+/// The limiters of one app, shared with the route layers through a request
+/// extension because those layers run without the router state.
+pub struct ConcurrencyLimits {
+    photo_uploads: ConcurrencyLimiter,
+    reports: ConcurrencyLimiter,
+    queue_timeout: Duration,
+}
+
+impl ConcurrencyLimits {
+    pub fn new(config: &ConcurrencyConfig) -> Self {
+        Self {
+            photo_uploads: ConcurrencyLimiter::new("photo_uploads", config.photo_uploads),
+            reports: ConcurrencyLimiter::new("reports", config.reports),
+            queue_timeout: config.queue_timeout,
+        }
+    }
+}
+
+async fn run_limited(
+    limiter: &ConcurrencyLimiter,
+    queue_timeout: Duration,
+    req: Request,
+    next: Next,
+) -> Response {
+    let permit = match tokio::time::timeout(
+        queue_timeout,
+        limiter.semaphore.clone().acquire_owned(),
+    )
+    .await
+    {
+        Ok(Ok(permit)) => permit,
+        _ => {
+            limiter.rejected.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "Concurrency limit {} reached ({} in flight)",
+                limiter.name,
+                limiter.in_flight()
+            );
+            let mut response = AppError::ServiceUnavailable(
+                "Server is busy, please try again shortly".to_string(),
+            )
+            .into_response();
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from_static("5"));
+            return response;
+        }
+    };
+    limiter.admitted.fetch_add(1, Ordering::Relaxed);
+
+    // Keep the slot until the body has been sent so streamed exports count
+    // for as long as they read from the database
+    let (parts, body) = next.run(req).await.into_parts();
+    let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+        let _ = &permit;
+        chunk
+    }));
+    Response::from_parts(parts, body)
+}
+
+/// Route layer for multipart photo uploads.
+pub async fn limit_photo_uploads(
+    Extension(limits): Extension<Arc<ConcurrencyLimits>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    run_limited(&limits.photo_uploads, limits.queue_timeout, req, next).await
+}
+
+/// Route layer for reports and CSV/NDJSON exports.
+pub async fn limit_reports(
+    Extension(limits): Extension<Arc<ConcurrencyLimits>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    run_limited(&limits.reports, limits.queue_timeout, req, next).await
+}
+
+/// Limiter gauges and counters in the Prometheus text format.
+pub fn render_metrics(limits: &ConcurrencyLimits) -> String {
+    let limiters = [&limits.photo_uploads, &limits.reports];
+    let mut out = String::new();
+    let metrics: [(&str, &str, &str, MetricValue); 4] = [
+        (
+            "concurrency_limit",
+            "gauge",
+            "Maximum concurrent requests",
+            |l| l.max as u64,
+        ),
+        (
+            "concurrency_in_flight",
+            "gauge",
+            "Requests currently holding a slot",
+            |l| l.in_flight() as u64,
+        ),
+        (
+            "concurrency_admitted_total",
+            "counter",
+            "Requests admitted",
+            |l| l.admitted.load(Ordering::Relaxed),
+        ),
+        (
+            "concurrency_rejected_total",
+            "counter",
+            "Requests rejected after waiting for a slot",
+            |l| l.rejected.load(Ordering::Relaxed),
+        ),
+    ];
+    for (name, kind, help, value) in metrics {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for limiter in limiters {
+            let _ = writeln!(
+                out,
+                "{}{{limiter=\"{}\"}} {}",
+                name,
+                limiter.name,
+                value(limiter)
+            );
+        }
+    }
+    out
+}
@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use super::super::concurrency::{ConcurrencyConfig, ConcurrencyLimits, render_metrics};
+
+    #[test]
+    fn test_metrics_cover_every_limiter() {
+        let metrics = render_metrics(&ConcurrencyLimits::new(&ConcurrencyConfig::default()));
+        for name in [
+            "concurrency_limit",
+            "concurrency_in_flight",
+            "concurrency_admitted_total",
+            "concurrency_rejected_total",
+        ] {
+            assert!(metrics.contains(&format!("# TYPE {} ", name)));
+            for limiter in ["photo_uploads", "reports"] {
+                assert!(metrics.contains(&format!("{}{{limiter=\"{}\"}} ", name, limiter)));
+            }
+        }
+        assert!(metrics.contains("concurrency_in_flight{limiter=\"reports\"} 0\n"));
+    }
+}
@@ -0,0 +1,817 @@
+use std::{collections::HashMap, env, fmt, fs, str::FromStr};
+
+use argon2::Params;
+use toml_edit::{DocumentMut, Item, Value};
+use tower_sessions::cookie::{SameSite, time::Duration};
+
+use crate::{
+    account_deletion::{AccountDeletionConfig, ReservationPolicy},
+    argon_hasher::Argon2Config,
+    check_in::CheckInConfig,
+    concurrency::ConcurrencyConfig,
+    cors::{self, CorsConfig},
+    datetime::{DatetimeConfig, DatetimeFormat},
+    door_events::{DoorEventsConfig, DoorToken},
+    email_client::EmailClientConfig,
+    email_events::EmailEventsConfig,
+    email_queue::{EmailQueueConfig, QuietHours},
+    email_templates::{EmailTemplateConfig, Locale},
+    entities::sea_orm_active_enums::Role,
+    event_duplicates::EventDuplicatesConfig,
+    idempotency::IdempotencyConfig,
+    key_cabinet::KeyCabinetConfig,
+    login_guard::LoginGuardConfig,
+    overdue::OverdueConfig,
+    password_reset::{MAX_CODE_LENGTH, MIN_CODE_LENGTH, PasswordResetConfig},
+    pickup::PickupConfig,
+    public_stats::PublicStatsConfig,
+    quota::QuotaConfig,
+    rate_limit::{EndpointLimit, RateLimitConfig},
+    redis_breaker::RedisBreakerConfig,
+    reporting::{self, ReportingConfig},
+    retention::RetentionConfig,
+    review_lock::ReviewLockConfig,
+    routes::debug::DebugConfig,
+    server_timing::{ServerTimingConfig, is_debug_environment},
+    slots::{SlotConfig, SlotPolicy},
+    soft_launch::SoftLaunchConfig,
+    student_id::{StudentIdConfig, StudentIdValidator},
+};
+
+/// Where settings are read from: the environment first, then the TOML file
+/// named by `CONFIG_FILE`, if any.
+///
+/// The file uses the same names as the environment, lowercased, with tables
+/// standing for prefixes: `[smtp] port = 587` is `SMTP_PORT`. Arrays are
+/// read as comma separated lists.
+#[derive(Default)]
+pub struct Source {
+    env: bool,
+    file: HashMap<String, String>,
+}
+
+impl Source {
+    pub fn load() -> Result<Self, ConfigError> {
+        let source = match env::var("CONFIG_FILE") {
+            Ok(path) => {
+                let text = fs::read_to_string(&path).map_err(|e| {
+                    ConfigError(vec![format!(
+                        "CONFIG_FILE '{}' cannot be read: {}",
+                        path, e
+                    )])
+                })?;
+                Self::from_toml(&text).map_err(|e| {
+                    ConfigError(vec![format!(
+                        "CONFIG_FILE '{}' is not valid TOML: {}",
+                        path, e
+                    )])
+                })?
+            }
+            Err(_) => Self::default(),
+        };
+        Ok(Self {
+            env: true,
+            ..source
+        })
+    }
+
+    /// Settings from TOML alone, ignoring the environment.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        Ok(Self {
+            env: false,
+            file: flatten_toml(text)?,
+        })
+    }
+
+    /// Reads a setting like `std::env::var` does.
+    pub fn var(&self, name: &str) -> Result<String, env::VarError> {
+        if self.env {
+            match env::var(name) {
+                Err(env::VarError::NotPresent) => {}
+                found => return found,
+            }
+        }
+        self.file
+            .get(name)
+            .cloned()
+            .ok_or(env::VarError::NotPresent)
+    }
+}
+
+fn flatten_toml(text: &str) -> Result<HashMap<String, String>, String> {
+    let document: DocumentMut = text.parse().map_err(|e| format!("{}", e))?;
+    let mut settings = HashMap::new();
+    for (key, item) in document.iter() {
+        flatten_item(key, item, &mut settings);
+    }
+    Ok(settings)
+}
+
+fn flatten_item(name: &str, item: &Item, settings: &mut HashMap<String, String>) {
+    match item {
+        Item::Table(table) => {
+            for (key, item) in table.iter() {
+                flatten_item(&format!("{}_{}", name, key), item, settings);
+            }
+        }
+        Item::Value(Value::InlineTable(table)) => {
+            for (key, value) in table.iter() {
+                flatten_value(&format!("{}_{}", name, key), value, settings);
+            }
+        }
+        Item::Value(value) => flatten_value(name, value, settings),
+        _ => {}
+    }
+}
+
+fn flatten_value(name: &str, value: &Value, settings: &mut HashMap<String, String>) {
+    if let Value::InlineTable(_) = value {
+        return flatten_item(name, &Item::Value(value.clone()), settings);
+    }
+    settings.insert(name.to_uppercase(), scalar(value));
+}
+
+fn scalar(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.value().clone(),
+        Value::Integer(i) => i.value().to_string(),
+        Value::Float(f) => f.value().to_string(),
+        Value::Boolean(b) => b.value().to_string(),
+        Value::Datetime(d) => d.value().to_string(),
+        Value::Array(values) => values.iter().map(scalar).collect::<Vec<_>>().join(","),
+        // Tables inside arrays have no setting to go to
+        Value::InlineTable(_) => String::new(),
+    }
+}
+
+/// Every problem found in the settings, so they can all be fixed at once.
+#[derive(Debug, PartialEq)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Clone)]
+pub struct RedisSettings {
+    pub host: String,
+    pub port: u16,
+}
+
+impl RedisSettings {
+    pub fn url(&self) -> String {
+        format!("redis://{}:{}", self.host, self.port)
+    }
+}
+
+/// The service classroom photos are stored in.
+#[derive(Clone)]
+pub struct ImageServiceConfig {
+    pub url: String,
+    pub api_key: String,
+}
+
+#[derive(Clone)]
+pub struct SessionCookieConfig {
+    /// Only send the cookie over HTTPS
+    pub secure: bool,
+    pub same_site: SameSite,
+    /// Sessions end after this long without a request
+    pub idle_timeout: Duration,
+}
+
+impl Default for SessionCookieConfig {
+    fn default() -> Self {
+        Self {
+            secure: false,
+            same_site: SameSite::Lax,
+            idle_timeout: Duration::days(1),
+        }
+    }
+}
+
+/// Settings the server needs to start, read once in `main` and carried in
+/// `AppState`.
+#[derive(Clone)]
+pub struct Config {
+    /// Keep the database, Redis and sessions in process memory, for tests
+    /// and demos without Postgres or Redis; see `memory_mode`
+    pub in_memory: bool,
+    pub port: u16,
+    /// `None` in memory mode
+    pub database_url: Option<String>,
+    /// Apply pending migrations when starting
+    pub run_migrations: bool,
+    pub redis: RedisSettings,
+    /// `None` in memory mode, where emails are only logged
+    pub smtp: Option<EmailClientConfig>,
+    pub image_service: ImageServiceConfig,
+    pub argon2: Argon2Config,
+    pub session_cookie: SessionCookieConfig,
+    pub redis_breaker: RedisBreakerConfig,
+    /// e.g. `production`; names the server in the API docs and decides
+    /// whether debug aids are on by default
+    pub app_environment: String,
+    /// Where the API is reachable from outside, e.g. `https://api.example.edu`
+    pub public_base_url: Option<String>,
+    pub server_timing: ServerTimingConfig,
+    pub debug: DebugConfig,
+    pub datetime: DatetimeConfig,
+    pub cors: CorsConfig,
+    pub concurrency: ConcurrencyConfig,
+    pub idempotency: IdempotencyConfig,
+    pub rate_limit: RateLimitConfig,
+    pub login_guard: LoginGuardConfig,
+    pub password_reset: PasswordResetConfig,
+    pub account_deletion: AccountDeletionConfig,
+    pub student_id: StudentIdConfig,
+    pub soft_launch: SoftLaunchConfig,
+    pub email_templates: EmailTemplateConfig,
+    pub email_queue: EmailQueueConfig,
+    pub email_events: EmailEventsConfig,
+    pub quota: QuotaConfig,
+    pub slots: SlotConfig,
+    pub event_duplicates: EventDuplicatesConfig,
+    pub review_lock: ReviewLockConfig,
+    pub pickup: PickupConfig,
+    pub key_cabinet: KeyCabinetConfig,
+    pub check_in: CheckInConfig,
+    pub door_events: DoorEventsConfig,
+    pub overdue: OverdueConfig,
+    pub public_stats: PublicStatsConfig,
+    pub reporting: ReportingConfig,
+    pub retention: RetentionConfig,
+}
+
+/// Reads settings while collecting what is wrong with them.
+struct Reader<'a> {
+    source: &'a Source,
+    problems: Vec<String>,
+}
+
+impl Reader<'_> {
+    fn optional(&self, name: &str) -> Option<String> {
+        self.source.var(name).ok().filter(|v| !v.trim().is_empty())
+    }
+
+    /// A setting that has to be set, except in memory mode where `demo`
+    /// stands in for it.
+    fn required(&mut self, name: &str, demo: Option<&str>) -> String {
+        match (self.optional(name), demo) {
+            (Some(value), _) => value,
+            (None, Some(demo)) => demo.to_string(),
+            (None, None) => {
+                self.problems.push(format!("{} must be set", name));
+                String::new()
+            }
+        }
+    }
+
+    fn parsed<T: FromStr>(&mut self, name: &str, default: T, expected: &str) -> T {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        value.trim().parse().unwrap_or_else(|_| {
+            self.problems
+                .push(format!("{} must be {}, got '{}'", name, expected, value));
+            default
+        })
+    }
+
+    /// Like [`Reader::parsed`] for settings that are off when unset.
+    fn maybe<T: FromStr>(&mut self, name: &str, expected: &str) -> Option<T> {
+        let value = self.optional(name)?;
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.problems
+                    .push(format!("{} must be {}, got '{}'", name, expected, value));
+                None
+            }
+        }
+    }
+
+    /// A setting with its own syntax; `parse` explains what is wrong with it.
+    fn with<T>(
+        &mut self,
+        name: &str,
+        default: T,
+        parse: impl FnOnce(&str) -> Result<T, String>,
+    ) -> T {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        parse(&value).unwrap_or_else(|e| {
+            self.problems.push(format!("{}: {}", name, e));
+            default
+        })
+    }
+
+    fn seconds(&mut self, name: &str, default: chrono::Duration) -> chrono::Duration {
+        chrono::Duration::seconds(self.parsed(name, default.num_seconds(), "a number of seconds"))
+    }
+
+    fn minutes(&mut self, name: &str, default: chrono::Duration) -> chrono::Duration {
+        chrono::Duration::minutes(self.parsed(name, default.num_minutes(), "a number of minutes"))
+    }
+
+    fn hours(&mut self, name: &str, default: chrono::Duration) -> chrono::Duration {
+        chrono::Duration::hours(self.parsed(name, default.num_hours(), "a number of hours"))
+    }
+
+    /// Days a table is kept; 0 keeps it forever.
+    fn retention(
+        &mut self,
+        name: &str,
+        default: Option<chrono::Duration>,
+    ) -> Option<chrono::Duration> {
+        let days = self.parsed(
+            name,
+            default.map_or(0, |days| days.num_days()),
+            "a number of days",
+        );
+        (days > 0).then(|| chrono::Duration::days(days))
+    }
+
+    fn endpoint_limit(&mut self, prefix: &str, default: EndpointLimit) -> EndpointLimit {
+        EndpointLimit {
+            per_ip: self.parsed(
+                &format!("RATE_LIMIT_{}_PER_IP", prefix),
+                default.per_ip,
+                "a number",
+            ),
+            per_user: self.parsed(
+                &format!("RATE_LIMIT_{}_PER_USER", prefix),
+                default.per_user,
+                "a number",
+            ),
+        }
+    }
+
+    fn flag(&mut self, name: &str, default: bool) -> bool {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        match value.trim() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => {
+                self.problems
+                    .push(format!("{} must be true or false, got '{}'", name, value));
+                default
+            }
+        }
+    }
+
+    fn same_site(&mut self, name: &str, default: SameSite) -> SameSite {
+        let Some(value) = self.optional(name) else {
+            return default;
+        };
+        match value.trim().to_ascii_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            _ => {
+                self.problems.push(format!(
+                    "{} must be strict, lax or none, got '{}'",
+                    name, value
+                ));
+                default
+            }
+        }
+    }
+}
+
+impl Config {
+    pub fn from_source(source: &Source) -> Result<Self, ConfigError> {
+        let mut r = Reader {
+            source,
+            problems: Vec::new(),
+        };
+        let in_memory = r.flag("IN_MEMORY", false);
+        // Stand-ins for what memory mode does not need
+        let demo = |value: &'static str| in_memory.then_some(value);
+
+        let port = r.parsed("PORT", 3000, "a port number");
+        let database_url = (!in_memory).then(|| r.required("DATABASE_URL", None));
+        let run_migrations = r.flag("RUN_MIGRATIONS", false);
+        let redis = RedisSettings {
+            host: r.optional("REDIS_IP").unwrap_or_else(|| "localhost".into()),
+            port: r.parsed("REDIS_PORT", 6379, "a port number"),
+        };
+        let smtp = (!in_memory).then(|| EmailClientConfig {
+            smtp_server: r.required("SMTP_SERVER", None),
+            smtp_port: r.parsed("SMTP_PORT", 587, "a port number"),
+            username: r.required("SMTP_USERNAME", None),
+            password: r.required("SMTP_PASSWORD", None),
+        });
+        let image_service = ImageServiceConfig {
+            url: r.required("IMAGE_SERVICE_IP", demo("127.0.0.1:8081")),
+            api_key: r.required("IMAGE_SERVICE_API_KEY", demo("in-memory-key")),
+        };
+
+        let argon2 = Argon2Config {
+            secret_key: r
+                .required("PASSWORD_HASHING_SECRET", demo("in-memory-secret"))
+                .into_bytes(),
+            iterations: r.parsed("ARGON2_ITERATIONS", 4, "a number"),
+            parallelism: r.parsed("ARGON2_PARALLELISM", 4, "a number"),
+            memory_cost: r.parsed("ARGON2_MEMORY_KIB", 512, "a number of KiB"),
+        };
+        if let Err(e) = Params::new(
+            argon2.memory_cost,
+            argon2.iterations,
+            argon2.parallelism,
+            None,
+        ) {
+            r.problems.push(format!(
+                "ARGON2_ITERATIONS, ARGON2_PARALLELISM and ARGON2_MEMORY_KIB do not fit together: {}",
+                e
+            ));
+        }
+
+        let cookie_defaults = SessionCookieConfig::default();
+        let session_cookie = SessionCookieConfig {
+            secure: r.flag("SESSION_COOKIE_SECURE", cookie_defaults.secure),
+            same_site: r.same_site("SESSION_COOKIE_SAME_SITE", cookie_defaults.same_site),
+            idle_timeout: Duration::hours(r.parsed(
+                "SESSION_IDLE_HOURS",
+                cookie_defaults.idle_timeout.whole_hours(),
+                "a number of hours",
+            )),
+        };
+        // Browsers drop SameSite=None cookies that are not Secure
+        if session_cookie.same_site == SameSite::None && !session_cookie.secure {
+            r.problems
+                .push("SESSION_COOKIE_SAME_SITE=none needs SESSION_COOKIE_SECURE=true".to_string());
+        }
+
+        let breaker_defaults = RedisBreakerConfig::default();
+        let redis_breaker = RedisBreakerConfig {
+            failure_threshold: r.parsed(
+                "REDIS_BREAKER_FAILURES",
+                breaker_defaults.failure_threshold,
+                "a number",
+            ),
+            open_for: std::time::Duration::from_secs(r.parsed(
+                "REDIS_BREAKER_OPEN_SECONDS",
+                breaker_defaults.open_for.as_secs(),
+                "a number of seconds",
+            )),
+            timeout: std::time::Duration::from_millis(r.parsed(
+                "REDIS_TIMEOUT_MS",
+                breaker_defaults.timeout.as_millis() as u64,
+                "a number of milliseconds",
+            )),
+        };
+
+        let explicit_environment = r.optional("APP_ENV");
+        let app_environment = explicit_environment
+            .clone()
+            .unwrap_or_else(|| "local".into());
+        let public_base_url = r.optional("PUBLIC_BASE_URL");
+        let server_timing = ServerTimingConfig {
+            expose_header: r.flag("SERVER_TIMING", is_debug_environment(&app_environment)),
+        };
+        let debug = DebugConfig {
+            // Only an explicit development APP_ENV turns them on by default, so
+            // a deployment that forgets to set it stays locked down
+            enabled: r.flag(
+                "DEBUG_ROUTES",
+                explicit_environment
+                    .as_deref()
+                    .is_some_and(is_debug_environment),
+            ),
+        };
+        let datetime = DatetimeConfig {
+            format: r.with("DATETIME_FORMAT", DatetimeFormat::default(), |v| {
+                match v.trim() {
+                    "rfc3339" => Ok(DatetimeFormat::Rfc3339),
+                    "legacy" => Ok(DatetimeFormat::Legacy),
+                    _ => Err("must be rfc3339 or legacy".to_string()),
+                }
+            }),
+        };
+        let cors_defaults = CorsConfig::default();
+        let mut exposed_headers = cors_defaults.exposed_headers.clone();
+        exposed_headers.extend(r.with("CORS_EXPOSED_HEADERS", Vec::new(), cors::parse_headers));
+        let cors = CorsConfig {
+            allowed_origins: r.with("CORS_ALLOWED_ORIGINS", Vec::new(), cors::parse_origins),
+            allow_credentials: r.flag("CORS_ALLOW_CREDENTIALS", cors_defaults.allow_credentials),
+            exposed_headers,
+            max_age: std::time::Duration::from_secs(r.parsed(
+                "CORS_MAX_AGE_SECONDS",
+                cors_defaults.max_age.as_secs(),
+                "a number of seconds",
+            )),
+        };
+
+        let concurrency_defaults = ConcurrencyConfig::default();
+        let concurrency = ConcurrencyConfig {
+            photo_uploads: r.parsed(
+                "PHOTO_UPLOAD_CONCURRENCY",
+                concurrency_defaults.photo_uploads,
+                "a number",
+            ),
+            reports: r.parsed(
+                "REPORT_CONCURRENCY",
+                concurrency_defaults.reports,
+                "a number",
+            ),
+            queue_timeout: std::time::Duration::from_millis(r.parsed(
+                "CONCURRENCY_QUEUE_TIMEOUT_MS",
+                concurrency_defaults.queue_timeout.as_millis() as u64,
+                "a number of milliseconds",
+            )),
+        };
+        let idempotency_defaults = IdempotencyConfig::default();
+        let idempotency = IdempotencyConfig {
+            enabled: r.flag("IDEMPOTENCY_ENABLED", idempotency_defaults.enabled),
+            ttl: r.seconds("IDEMPOTENCY_TTL_SECONDS", idempotency_defaults.ttl),
+            ..idempotency_defaults
+        };
+
+        // Both the login guard and the rate limits count per client IP
+        let trust_forwarded_for = r.flag("TRUST_FORWARDED_FOR", false);
+        let rate_limit_defaults = RateLimitConfig::default();
+        let rate_limit = RateLimitConfig {
+            enabled: r.flag("RATE_LIMIT_ENABLED", rate_limit_defaults.enabled),
+            window: r.seconds("RATE_LIMIT_WINDOW_SECONDS", rate_limit_defaults.window),
+            register: r.endpoint_limit("REGISTER", rate_limit_defaults.register),
+            login: r.endpoint_limit("LOGIN", rate_limit_defaults.login),
+            forgot_password: r
+                .endpoint_limit("FORGOT_PASSWORD", rate_limit_defaults.forgot_password),
+            create_reservation: r
+                .endpoint_limit("CREATE_RESERVATION", rate_limit_defaults.create_reservation),
+            trust_forwarded_for,
+        };
+        let login_guard_defaults = LoginGuardConfig::default();
+        let login_guard = LoginGuardConfig {
+            max_failures_per_email: r.parsed(
+                "LOGIN_MAX_FAILURES_PER_EMAIL",
+                login_guard_defaults.max_failures_per_email,
+                "a number",
+            ),
+            max_failures_per_ip: r.parsed(
+                "LOGIN_MAX_FAILURES_PER_IP",
+                login_guard_defaults.max_failures_per_ip,
+                "a number",
+            ),
+            window: r.minutes("LOGIN_FAILURE_WINDOW_MINUTES", login_guard_defaults.window),
+            lock_accounts: r.flag("LOGIN_LOCK_ACCOUNTS", login_guard_defaults.lock_accounts),
+            trust_forwarded_for,
+            ..login_guard_defaults
+        };
+
+        let password_reset_defaults = PasswordResetConfig::default();
+        let password_reset = PasswordResetConfig {
+            code_length: r.parsed(
+                "PASSWORD_RESET_CODE_LENGTH",
+                password_reset_defaults.code_length,
+                "a number",
+            ),
+            code_ttl: r.minutes(
+                "PASSWORD_RESET_CODE_TTL_MINUTES",
+                password_reset_defaults.code_ttl,
+            ),
+            max_verify_attempts: r.parsed(
+                "PASSWORD_RESET_MAX_ATTEMPTS",
+                password_reset_defaults.max_verify_attempts,
+                "a number",
+            ),
+            ..password_reset_defaults
+        };
+        if !(MIN_CODE_LENGTH..=MAX_CODE_LENGTH).contains(&password_reset.code_length) {
+            r.problems.push(format!(
+                "PASSWORD_RESET_CODE_LENGTH must be between {} and {}",
+                MIN_CODE_LENGTH, MAX_CODE_LENGTH
+            ));
+        }
+        if password_reset.code_ttl <= chrono::Duration::zero() {
+            r.problems
+                .push("PASSWORD_RESET_CODE_TTL_MINUTES must be more than 0".to_string());
+        }
+        let account_deletion = AccountDeletionConfig {
+            reservations: r.with(
+                "ACCOUNT_DELETION_RESERVATIONS",
+                AccountDeletionConfig::default().reservations,
+                |v| {
+                    ReservationPolicy::parse(v)
+                        .ok_or_else(|| "must be retain or detach".to_string())
+                },
+            ),
+        };
+        let student_id = StudentIdConfig {
+            validator: {
+                let pattern = r.optional("STUDENT_ID_PATTERN");
+                r.with("STUDENT_ID_VALIDATOR", StudentIdValidator::default(), |v| {
+                    StudentIdValidator::from_setting(v, pattern.as_deref())
+                })
+            },
+            exempt_roles: r.with("STUDENT_ID_EXEMPT_ROLES", Vec::new(), |v| {
+                v.split(',')
+                    .map(str::trim)
+                    .filter(|role| !role.is_empty())
+                    .map(|role| match role {
+                        "admin" => Ok(Role::Admin),
+                        "assistant" => Ok(Role::Assistant),
+                        "user" => Ok(Role::User),
+                        _ => Err("must list admin, assistant and/or user".to_string()),
+                    })
+                    .collect()
+            }),
+        };
+        let soft_launch = SoftLaunchConfig {
+            email_domains: r
+                .optional("SOFT_LAUNCH_EMAIL_DOMAINS")
+                .map(|v| SoftLaunchConfig::parse_domains(&v))
+                .unwrap_or_default(),
+            student_id_prefixes: r
+                .optional("SOFT_LAUNCH_STUDENT_ID_PREFIXES")
+                .map(|v| SoftLaunchConfig::parse_prefixes(&v))
+                .unwrap_or_default(),
+        };
+
+        let email_templates = EmailTemplateConfig {
+            frontend_base_url: r.optional("FRONTEND_BASE_URL"),
+            default_locale: r.with("EMAIL_DEFAULT_LOCALE", Locale::En, |v| {
+                Locale::from_tag(v).ok_or_else(|| "must be en or zh-TW".to_string())
+            }),
+        };
+        let email_queue_defaults = EmailQueueConfig::default();
+        let email_queue = EmailQueueConfig {
+            // 0 sends as fast as the relay takes them
+            max_per_minute: match r.maybe::<u32>("EMAIL_MAX_PER_MINUTE", "a number") {
+                Some(max) => Some(max).filter(|max| *max > 0),
+                None => email_queue_defaults.max_per_minute,
+            },
+            // Set but empty turns quiet hours off, like `off`
+            quiet_hours: match r.source.var("EMAIL_QUIET_HOURS") {
+                Ok(v) if v.trim().is_empty() => None,
+                _ => r.with(
+                    "EMAIL_QUIET_HOURS",
+                    email_queue_defaults.quiet_hours,
+                    |v| match v.trim() {
+                        "off" => Ok(None),
+                        v => QuietHours::parse(v)
+                            .map(Some)
+                            .ok_or_else(|| "must look like 23:00-07:00 or be off".to_string()),
+                    },
+                ),
+            },
+            ..email_queue_defaults
+        };
+        let email_events = EmailEventsConfig {
+            secret: r.optional("EMAIL_WEBHOOK_SECRET").map(String::into_bytes),
+            tolerance: r.seconds(
+                "EMAIL_WEBHOOK_TOLERANCE_SECONDS",
+                EmailEventsConfig::default().tolerance,
+            ),
+        };
+
+        let quota = QuotaConfig {
+            max_active_reservations: r.maybe("RESERVATION_QUOTA_MAX_ACTIVE", "a number"),
+            max_active_hours: r.maybe("RESERVATION_QUOTA_MAX_HOURS", "a number of hours"),
+        };
+        let slots = SlotConfig {
+            granularity_minutes: r.maybe("RESERVATION_SLOT_MINUTES", "a number of minutes"),
+            policy: r.with(
+                "RESERVATION_SLOT_POLICY",
+                SlotPolicy::default(),
+                SlotPolicy::from_setting,
+            ),
+        };
+        let event_duplicates = EventDuplicatesConfig {
+            window: r.hours(
+                "EVENT_DUPLICATE_WINDOW_HOURS",
+                EventDuplicatesConfig::default().window,
+            ),
+        };
+        let review_lock = ReviewLockConfig {
+            ttl: r.seconds("REVIEW_LOCK_SECONDS", ReviewLockConfig::default().ttl),
+        };
+        let pickup = PickupConfig {
+            enabled: r.flag("PICKUP_CODES_ENABLED", false),
+        };
+        let key_cabinet = KeyCabinetConfig {
+            api_base_url: r.optional("KEY_CABINET_API_URL"),
+            api_key: r.optional("KEY_CABINET_API_KEY"),
+            webhook_secret: r
+                .optional("KEY_CABINET_WEBHOOK_SECRET")
+                .map(String::into_bytes),
+            ..KeyCabinetConfig::default()
+        };
+        let check_in_defaults = CheckInConfig::default();
+        let check_in = CheckInConfig {
+            secret: r.optional("CHECK_IN_SECRET").map(String::into_bytes),
+            public_base_url: public_base_url.clone(),
+            token_ttl: r.minutes("CHECK_IN_TOKEN_TTL_MINUTES", check_in_defaults.token_ttl),
+            early_window: r.minutes("CHECK_IN_EARLY_MINUTES", check_in_defaults.early_window),
+        };
+        let door_events = DoorEventsConfig {
+            tokens: r
+                .optional("DOOR_EVENT_TOKENS")
+                .map(|v| DoorToken::parse_list(&v))
+                .unwrap_or_default(),
+            grace: r.minutes(
+                "DOOR_EVENT_GRACE_MINUTES",
+                DoorEventsConfig::default().grace,
+            ),
+        };
+        let overdue_defaults = OverdueConfig::default();
+        let overdue = OverdueConfig {
+            scan_interval: std::time::Duration::from_secs(r.parsed(
+                "OVERDUE_SCAN_INTERVAL_SECONDS",
+                overdue_defaults.scan_interval.as_secs(),
+                "a number of seconds",
+            )),
+            reminder_delay: r.minutes(
+                "OVERDUE_REMINDER_DELAY_MINUTES",
+                overdue_defaults.reminder_delay,
+            ),
+            second_notice_delay: r.hours(
+                "OVERDUE_SECOND_NOTICE_HOURS",
+                overdue_defaults.second_notice_delay,
+            ),
+            infraction_grace: r.hours(
+                "OVERDUE_INFRACTION_GRACE_HOURS",
+                overdue_defaults.infraction_grace,
+            ),
+        };
+
+        let public_stats = PublicStatsConfig {
+            min_group_size: r.parsed(
+                "PUBLIC_STATS_MIN_GROUP_SIZE",
+                PublicStatsConfig::default().min_group_size,
+                "a number",
+            ),
+        };
+        let reporting = ReportingConfig {
+            tokens: r
+                .optional("REPORTING_TOKENS")
+                .map(|v| reporting::parse_tokens(&v))
+                .unwrap_or_default(),
+        };
+        let retention_defaults = RetentionConfig::default();
+        let retention = RetentionConfig {
+            reservation: r.retention("RESERVATION_RETENTION_DAYS", retention_defaults.reservation),
+            key_transaction_log: r.retention(
+                "KEY_LOG_RETENTION_DAYS",
+                retention_defaults.key_transaction_log,
+            ),
+        };
+
+        if !r.problems.is_empty() {
+            return Err(ConfigError(r.problems));
+        }
+        Ok(Self {
+            in_memory,
+            port,
+            database_url,
+            run_migrations,
+            redis,
+            smtp,
+            image_service,
+            argon2,
+            session_cookie,
+            redis_breaker,
+            app_environment,
+            public_base_url,
+            server_timing,
+            debug,
+            datetime,
+            cors,
+            concurrency,
+            idempotency,
+            rate_limit,
+            login_guard,
+            password_reset,
+            account_deletion,
+            student_id,
+            soft_launch,
+            email_templates,
+            email_queue,
+            email_events,
+            quota,
+            slots,
+            event_duplicates,
+            review_lock,
+            pickup,
+            key_cabinet,
+            check_in,
+            door_events,
+            overdue,
+            public_stats,
+            reporting,
+            retention,
+        })
+    }
+}
@@ -0,0 +1,152 @@
+#[cfg(test)]
+mod tests {
+    use tower_sessions::cookie::{SameSite, time::Duration};
+
+    use super::super::config::{Config, ConfigError, Source};
+
+    fn load(toml: &str) -> Result<Config, ConfigError> {
+        Config::from_source(&Source::from_toml(toml).unwrap())
+    }
+
+    #[test]
+    fn test_tables_stand_for_prefixes() {
+        let source = Source::from_toml(
+            r#"
+            database_url = "postgres://localhost/borrowing"
+            port = 8080
+            cors_allowed_origins = ["https://a.example.edu", "https://b.example.edu"]
+
+            [smtp]
+            server = "smtp.example.edu"
+            port = 587
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            source.var("DATABASE_URL").unwrap(),
+            "postgres://localhost/borrowing"
+        );
+        assert_eq!(source.var("PORT").unwrap(), "8080");
+        assert_eq!(source.var("SMTP_SERVER").unwrap(), "smtp.example.edu");
+        assert_eq!(source.var("SMTP_PORT").unwrap(), "587");
+        assert_eq!(
+            source.var("CORS_ALLOWED_ORIGINS").unwrap(),
+            "https://a.example.edu,https://b.example.edu"
+        );
+        assert!(source.var("SMTP_USERNAME").is_err());
+        assert!(Source::from_toml("port = ").is_err());
+    }
+
+    #[test]
+    fn test_every_problem_is_reported() {
+        let Err(ConfigError(problems)) = load(
+            r#"
+            port = "eighty"
+            session_cookie_same_site = "none"
+            [smtp]
+            server = "smtp.example.edu"
+            "#,
+        ) else {
+            panic!("expected the configuration to be refused");
+        };
+        assert_eq!(
+            problems,
+            vec![
+                "PORT must be a port number, got 'eighty'",
+                "DATABASE_URL must be set",
+                "SMTP_USERNAME must be set",
+                "SMTP_PASSWORD must be set",
+                "IMAGE_SERVICE_IP must be set",
+                "IMAGE_SERVICE_API_KEY must be set",
+                "PASSWORD_HASHING_SECRET must be set",
+                "SESSION_COOKIE_SAME_SITE=none needs SESSION_COOKIE_SECURE=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_memory_mode_needs_nothing_else() {
+        let config = load("in_memory = true").unwrap();
+        assert!(config.in_memory);
+        assert_eq!(config.port, 3000);
+        assert!(config.database_url.is_none());
+        assert!(config.smtp.is_none());
+        assert_eq!(config.redis.url(), "redis://localhost:6379");
+        assert_eq!(config.session_cookie.same_site, SameSite::Lax);
+        assert_eq!(config.session_cookie.idle_timeout, Duration::days(1));
+        // Debug routes stay off unless asked for
+        assert_eq!(config.app_environment, "local");
+        assert!(!config.debug.enabled);
+        assert!(
+            load("in_memory = true\napp_env = \"dev\"")
+                .unwrap()
+                .debug
+                .enabled
+        );
+        assert!(
+            load("in_memory = true\ndebug_routes = true")
+                .unwrap()
+                .debug
+                .enabled
+        );
+
+        let config = load(
+            r#"
+            in_memory = true
+            [session_cookie]
+            secure = true
+            same_site = "None"
+            [session]
+            idle_hours = 2
+            "#,
+        )
+        .unwrap();
+        assert!(config.session_cookie.secure);
+        assert_eq!(config.session_cookie.same_site, SameSite::None);
+        assert_eq!(config.session_cookie.idle_timeout, Duration::hours(2));
+
+        assert!(load("in_memory = true\nargon2_memory_kib = 1").is_err());
+    }
+
+    #[test]
+    fn test_feature_settings_are_carried_in_the_config() {
+        let config = load(
+            r#"
+            in_memory = true
+            app_env = "production"
+            email_quiet_hours = ""
+            reservation_quota_max_active = 2
+            review_lock_seconds = 30
+            trust_forwarded_for = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.app_environment, "production");
+        assert!(!config.debug.enabled);
+        assert!(config.email_queue.quiet_hours.is_none());
+        assert_eq!(config.quota.max_active_reservations, Some(2));
+        assert_eq!(config.review_lock.ttl, chrono::Duration::seconds(30));
+        assert!(config.rate_limit.trust_forwarded_for);
+        assert!(config.login_guard.trust_forwarded_for);
+    }
+
+    #[test]
+    fn test_bad_feature_settings_are_reported_together() {
+        let Err(ConfigError(problems)) = load(
+            r#"
+            in_memory = true
+            password_reset_code_length = 2
+            password_reset_code_ttl_minutes = 0
+            email_default_locale = "fr"
+            reservation_slot_minutes = "half an hour"
+            "#,
+        ) else {
+            panic!("expected the configuration to be refused");
+        };
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        assert!(problems[0].starts_with("PASSWORD_RESET_CODE_LENGTH"));
+        assert!(problems[1].starts_with("PASSWORD_RESET_CODE_TTL_MINUTES"));
+        assert!(problems[2].starts_with("EMAIL_DEFAULT_LOCALE"));
+        assert!(problems[3].starts_with("RESERVATION_SLOT_MINUTES"));
+    }
+}
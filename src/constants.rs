@@ -4,6 +4,25 @@ pub const REDIS_EXPIRY_SECONDS: u64 = 60;
 pub const REDIS_EXPIRY: Expiry = Expiry::EX(REDIS_EXPIRY_SECONDS);
 
 pub fn get_redis_set_options() -> SetOptions {
-    SetOptions::default()
-        .with_expiration(SetExpiry::EX(REDIS_EXPIRY_SECONDS))
+    SetOptions::default().with_expiration(SetExpiry::EX(REDIS_EXPIRY_SECONDS))
 }
+
+/// Up to this many seconds are added at random to cache expiries, so entries
+/// cached together do not all expire together.
+pub const REDIS_EXPIRY_JITTER_SECONDS: u64 = 15;
+
+/// How long a lookup of an ID that does not exist is answered with 404 from
+/// the cache.
+pub const NOT_FOUND_EXPIRY_SECONDS: u64 = 10;
+
+/// Offset of the campus timezone (Asia/Taipei) from UTC, in seconds.
+pub const CAMPUS_UTC_OFFSET_SECONDS: i32 = 8 * 60 * 60;
+
+/// Maximum number of alternative classrooms suggested for a reservation.
+pub const MAX_ALTERNATIVE_ROOMS: usize = 5;
+
+/// Maximum number of classrooms booked together in one reservation request.
+pub const MAX_ROOMS_PER_RESERVATION: usize = 3;
+
+/// How long browsers may reuse a proxied classroom photo before revalidating.
+pub const PHOTO_CACHE_MAX_AGE_SECONDS: u64 = 300;
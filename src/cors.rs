@@ -0,0 +1,94 @@
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method, header};
+use tower_http::cors::{AllowHeaders, AllowOrigin, CorsLayer};
+
+use crate::{
+    idempotency::IDEMPOTENT_REPLAYED,
+    quota::{QUOTA_REMAINING_HOURS, QUOTA_REMAINING_RESERVATIONS, QUOTA_WARNING},
+    request_id::REQUEST_ID,
+    server_timing::SERVER_TIMING,
+};
+
+/// Which browser origins other than the API's own may call it.
+#[derive(Clone)]
+pub struct CorsConfig {
+    /// Origins such as `https://borrow.example.edu`; none means browsers on
+    /// other origins are refused
+    pub allowed_origins: Vec<HeaderValue>,
+    /// Lets those origins send the session cookie. Across sites the cookie
+    /// still needs a `SameSite` setting that allows it
+    pub allow_credentials: bool,
+    /// Response headers scripts on those origins may read
+    pub exposed_headers: Vec<HeaderName>,
+    /// How long browsers may cache a preflight
+    pub max_age: Duration,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: Vec::new(),
+            allow_credentials: true,
+            exposed_headers: vec![
+                REQUEST_ID.clone(),
+                header::RETRY_AFTER,
+                SERVER_TIMING.clone(),
+                IDEMPOTENT_REPLAYED.clone(),
+                QUOTA_WARNING.clone(),
+                QUOTA_REMAINING_RESERVATIONS.clone(),
+                QUOTA_REMAINING_HOURS.clone(),
+            ],
+            max_age: Duration::from_secs(600),
+        }
+    }
+}
+
+/// Reads a comma separated list of origins. A wildcard is refused, since
+/// the session cookie must only go to origins named here.
+pub fn parse_origins(value: &str) -> Result<Vec<HeaderValue>, String> {
+    value
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .map(|origin| {
+            if origin == "*" {
+                return Err("Origins must be listed, not given as *".to_string());
+            }
+            if !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                return Err(format!(
+                    "Origin '{}' must start with http:// or https://",
+                    origin
+                ));
+            }
+            HeaderValue::from_str(origin).map_err(|_| format!("Invalid origin '{}'", origin))
+        })
+        .collect()
+}
+
+/// Reads a comma separated list of header names.
+pub fn parse_headers(value: &str) -> Result<Vec<HeaderName>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| HeaderName::try_from(name).map_err(|_| format!("Invalid header '{}'", name)))
+        .collect()
+}
+
+pub fn layer(config: &CorsConfig) -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(config.allowed_origins.clone()))
+        .allow_credentials(config.allow_credentials)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        // Wildcards are not allowed together with credentials
+        .allow_headers(AllowHeaders::mirror_request())
+        .expose_headers(config.exposed_headers.clone())
+        .max_age(config.max_age)
+}
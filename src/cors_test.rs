@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::Body,
+        http::{HeaderValue, Request, StatusCode, header},
+        routing::get,
+    };
+    use tower::ServiceExt;
+
+    use super::super::{
+        cors::{CorsConfig, layer, parse_headers, parse_origins},
+        error::AppError,
+    };
+
+    const FRONTEND: &str = "https://borrow.example.edu";
+
+    fn app() -> Router {
+        let config = CorsConfig {
+            allowed_origins: vec![HeaderValue::from_static(FRONTEND)],
+            ..CorsConfig::default()
+        };
+        Router::new()
+            .route(
+                "/private",
+                get(|| async { AppError::Unauthorized("Log in first".to_string()) }),
+            )
+            .layer(layer(&config))
+    }
+
+    #[test]
+    fn test_origins_are_listed() {
+        assert_eq!(
+            parse_origins(" https://borrow.example.edu/, http://localhost:5173 ,").unwrap(),
+            vec![
+                HeaderValue::from_static("https://borrow.example.edu"),
+                HeaderValue::from_static("http://localhost:5173"),
+            ]
+        );
+        assert!(parse_origins("*").is_err());
+        assert!(parse_origins("borrow.example.edu").is_err());
+        assert!(parse_origins("").unwrap().is_empty());
+        assert_eq!(parse_headers("x-total-count, etag").unwrap().len(), 2);
+        assert!(parse_headers("bad header").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_preflight_from_allowed_origin() {
+        let response = app()
+            .oneshot(
+                Request::options("/private")
+                    .header(header::ORIGIN, FRONTEND)
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                    .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], FRONTEND);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_HEADERS],
+            "content-type"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors_expose_headers_to_allowed_origin_only() {
+        let request = |origin: &str| {
+            Request::get("/private")
+                .header(header::ORIGIN, origin)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app().oneshot(request(FRONTEND)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], FRONTEND);
+        let exposed = headers[header::ACCESS_CONTROL_EXPOSE_HEADERS]
+            .to_str()
+            .unwrap();
+        assert!(exposed.contains("x-request-id"));
+        assert!(exposed.contains("retry-after"));
+
+        let response = app()
+            .oneshot(request("https://elsewhere.example.com"))
+            .await
+            .unwrap();
+        assert!(
+            !response
+                .headers()
+                .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use std::sync::OnceLock;
+
+use chrono::{DateTime, SecondsFormat};
+use sea_orm::prelude::DateTimeWithTimeZone;
+
+static GLOBAL_DATETIME_CONFIG: OnceLock<DatetimeConfig> = OnceLock::new();
+
+/// How timestamps are written in responses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DatetimeFormat {
+    /// RFC 3339 with the offset, e.g. `2025-03-10T09:00:00+08:00`
+    #[default]
+    Rfc3339,
+    /// What key logs and key borrows used to return, e.g.
+    /// `2025-03-10 09:00:00 +08:00`, for clients not yet moved to RFC 3339
+    Legacy,
+}
+
+#[derive(Clone, Default)]
+pub struct DatetimeConfig {
+    pub format: DatetimeFormat,
+}
+
+pub fn set_datetime_config(config: DatetimeConfig) {
+    let _ = GLOBAL_DATETIME_CONFIG.set(config);
+}
+
+pub fn config() -> DatetimeConfig {
+    GLOBAL_DATETIME_CONFIG.get().cloned().unwrap_or_default()
+}
+
+const LEGACY_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.f %:z";
+
+pub fn format(datetime: &DateTimeWithTimeZone, format: DatetimeFormat) -> String {
+    match format {
+        DatetimeFormat::Rfc3339 => datetime.to_rfc3339_opts(SecondsFormat::AutoSi, false),
+        DatetimeFormat::Legacy => datetime.format(LEGACY_FORMAT).to_string(),
+    }
+}
+
+/// Reads either format back, so values cached or stored under one setting
+/// still load after it changes.
+pub fn parse(value: &str) -> Option<DateTimeWithTimeZone> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, LEGACY_FORMAT))
+        .or_else(|_| value.parse())
+        .ok()
+}
+
+/// Serde helpers for timestamp fields of response types, writing them in
+/// the configured [`DatetimeFormat`]:
+/// `#[serde(with = "crate::datetime::rfc3339")]`, or
+/// `#[serde(default, with = "crate::datetime::rfc3339::option")]` for
+/// optional ones.
+pub mod rfc3339 {
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    use super::{DateTimeWithTimeZone, config, format, parse};
+
+    pub fn serialize<S: Serializer>(
+        datetime: &DateTimeWithTimeZone,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format(datetime, config().format))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTimeWithTimeZone, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        parse(&value).ok_or_else(|| D::Error::custom(format!("invalid timestamp '{}'", value)))
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        use super::DateTimeWithTimeZone;
+
+        pub fn serialize<S: Serializer>(
+            datetime: &Option<DateTimeWithTimeZone>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match datetime {
+                Some(datetime) => super::serialize(datetime, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<DateTimeWithTimeZone>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapper(#[serde(with = "super")] DateTimeWithTimeZone);
+
+            Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|Wrapper(datetime)| datetime))
+        }
+    }
+}
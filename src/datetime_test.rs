@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, TimeZone};
+    use serde::{Deserialize, Serialize};
+
+    use super::super::datetime::{DatetimeFormat, format, parse};
+
+    fn nine_am() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, 10, 9, 0, 0)
+            .unwrap()
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Dto {
+        #[serde(with = "crate::datetime::rfc3339")]
+        at: DateTime<FixedOffset>,
+        #[serde(default, with = "crate::datetime::rfc3339::option")]
+        until: Option<DateTime<FixedOffset>>,
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            format(&nine_am(), DatetimeFormat::Rfc3339),
+            "2025-03-10T09:00:00+08:00"
+        );
+        assert_eq!(
+            format(&nine_am(), DatetimeFormat::Legacy),
+            "2025-03-10 09:00:00 +08:00"
+        );
+        // The legacy format is what Display printed
+        assert_eq!(
+            format(&nine_am(), DatetimeFormat::Legacy),
+            nine_am().to_string()
+        );
+        let utc = nine_am().with_timezone(&FixedOffset::east_opt(0).unwrap());
+        assert_eq!(
+            format(&utc, DatetimeFormat::Rfc3339),
+            "2025-03-10T01:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_parse_reads_both_formats() {
+        assert_eq!(parse("2025-03-10T09:00:00+08:00"), Some(nine_am()));
+        assert_eq!(parse("2025-03-10 09:00:00 +08:00"), Some(nine_am()));
+        assert_eq!(parse("10/03/2025"), None);
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        let dto = Dto {
+            at: nine_am(),
+            until: None,
+        };
+        let json = serde_json::to_value(&dto).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "at": "2025-03-10T09:00:00+08:00", "until": null })
+        );
+        assert_eq!(serde_json::from_value::<Dto>(json).unwrap(), dto);
+        assert_eq!(
+            serde_json::from_str::<Dto>(r#"{ "at": "2025-03-10 09:00:00 +08:00" }"#).unwrap(),
+            dto
+        );
+    }
+}
@@ -0,0 +1,475 @@
+use chrono::Utc;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::{AlternativeRoom, campus_offset},
+    email_queue::{Priority, queue_email_to_user},
+    email_templates,
+    entities::{
+        announcement, classroom, domain_event, reservation, reservation_comment,
+        sea_orm_active_enums::{DomainEventStatus, Role},
+        user,
+    },
+    live_events::LiveEvent,
+    permissions::assistants_for_classroom,
+    routes,
+};
+
+/// Every event kind with what it signals, as published in the webhook
+/// documentation.
+pub const EVENT_KINDS: [(&str, &str); 8] = [
+    (
+        "reservation_created",
+        "A reservation request was submitted and waits for review",
+    ),
+    (
+        "reservation_reviewed",
+        "A pending reservation was approved or rejected",
+    ),
+    (
+        "reservation_partially_approved",
+        "A reservation for a room requiring two approvals got its first one",
+    ),
+    (
+        "reservation_nudged",
+        "The requester asked for a pending reservation to be reviewed",
+    ),
+    (
+        "reservation_completed",
+        "An approved reservation ended and was marked completed",
+    ),
+    (
+        "reservation_cancelled",
+        "A pending or approved reservation was cancelled by the system",
+    ),
+    (
+        "reservation_commented",
+        "The requester or a reviewer commented on a reservation",
+    ),
+    (
+        "emergency_announced",
+        "An emergency announcement was published to every user",
+    ),
+];
+
+/// Something that happened to a reservation that other parts of the system
+/// react to. Each event is stored with the data its consumers need, so a
+/// failed delivery can be replayed as it was first sent.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+// The variant names are the stored and published event kinds
+#[allow(clippy::enum_variant_names)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DomainEvent {
+    ReservationCreated {
+        reservation: reservation::Model,
+    },
+    ReservationReviewed {
+        reservation: reservation::Model,
+        /// Rooms offered with a rejection caused by a scheduling conflict
+        #[serde(default)]
+        alternatives: Vec<AlternativeRoom>,
+    },
+    /// The first of two approvals was given; the reservation stays pending
+    /// until another reviewer approves it
+    ReservationPartiallyApproved {
+        reservation: reservation::Model,
+        /// The reviewer who gave the first approval
+        approved_by: String,
+    },
+    /// The requester sent a reminder that the reservation still waits for
+    /// review
+    ReservationNudged {
+        reservation: reservation::Model,
+    },
+    /// An approved reservation ended and was marked completed
+    ReservationCompleted {
+        reservation: reservation::Model,
+    },
+    /// The system cancelled a reservation, e.g. because its owner deleted
+    /// their account; the room is free again
+    ReservationCancelled {
+        reservation: reservation::Model,
+    },
+    /// A comment was added to the thread between requester and reviewers
+    ReservationCommented {
+        reservation: reservation::Model,
+        comment: reservation_comment::Model,
+    },
+    /// An emergency announcement was published; everyone in its audience is
+    /// emailed regardless of their preferences
+    EmergencyAnnounced {
+        announcement: announcement::Model,
+    },
+}
+
+impl DomainEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ReservationCreated { .. } => "reservation_created",
+            Self::ReservationReviewed { .. } => "reservation_reviewed",
+            Self::ReservationPartiallyApproved { .. } => "reservation_partially_approved",
+            Self::ReservationNudged { .. } => "reservation_nudged",
+            Self::ReservationCompleted { .. } => "reservation_completed",
+            Self::ReservationCancelled { .. } => "reservation_cancelled",
+            Self::ReservationCommented { .. } => "reservation_commented",
+            Self::EmergencyAnnounced { .. } => "emergency_announced",
+        }
+    }
+
+    pub fn subject_id(&self) -> Option<&str> {
+        match self {
+            Self::ReservationCreated { reservation }
+            | Self::ReservationReviewed { reservation, .. }
+            | Self::ReservationPartiallyApproved { reservation, .. }
+            | Self::ReservationNudged { reservation }
+            | Self::ReservationCompleted { reservation }
+            | Self::ReservationCancelled { reservation }
+            | Self::ReservationCommented { reservation, .. } => Some(&reservation.id),
+            Self::EmergencyAnnounced { announcement } => Some(&announcement.id),
+        }
+    }
+}
+
+/// Stores `event` as pending.
+pub async fn record(
+    db: &DatabaseConnection,
+    event: &DomainEvent,
+) -> Result<domain_event::Model, DbErr> {
+    domain_event::ActiveModel {
+        id: Set(nanoid!()),
+        kind: Set(event.kind().to_string()),
+        subject_id: Set(event.subject_id().map(str::to_string)),
+        payload: Set(serde_json::to_value(event).unwrap()),
+        status: Set(DomainEventStatus::Pending),
+        attempts: Set(0),
+        error: Set(None),
+        created_at: NotSet,
+        dispatched_at: Set(None),
+    }
+    .insert(db)
+    .await
+}
+
+/// Records `event` and hands it to its consumers. Delivery still happens
+/// when the event cannot be stored, it just cannot be replayed. Live event
+/// streams get it right away; replays do not push it again.
+pub async fn publish(state: &AppState, event: DomainEvent) {
+    if let Some(live) = LiveEvent::from_domain(&event) {
+        state.live.send(live);
+    }
+    match record(&state.db, &event).await {
+        Ok(stored) => {
+            if let Err(e) = dispatch(state, stored).await {
+                warn!("Failed to update {} event: {}", event.kind(), e);
+            }
+        }
+        Err(e) => {
+            warn!("Failed to record {} event: {}", event.kind(), e);
+            if let Err(e) = notify(state, &event).await {
+                warn!("Failed to deliver {} event: {}", event.kind(), e);
+            }
+        }
+    }
+}
+
+/// Delivers a stored event and records the outcome of the attempt.
+pub async fn dispatch(
+    state: &AppState,
+    stored: domain_event::Model,
+) -> Result<domain_event::Model, DbErr> {
+    let result = match serde_json::from_value::<DomainEvent>(stored.payload.clone()) {
+        Ok(event) => notify(state, &event).await,
+        Err(e) => Err(format!("Unreadable payload: {}", e)),
+    };
+    if let Err(e) = &result {
+        warn!("Failed to deliver event {}: {}", stored.id, e);
+    }
+
+    let attempts = stored.attempts + 1;
+    let mut active: domain_event::ActiveModel = stored.into();
+    active.attempts = Set(attempts);
+    match result {
+        Ok(()) => {
+            active.status = Set(DomainEventStatus::Dispatched);
+            active.error = Set(None);
+            active.dispatched_at = Set(Some(Utc::now().with_timezone(&campus_offset())));
+        }
+        Err(e) => {
+            active.status = Set(DomainEventStatus::Failed);
+            active.error = Set(Some(e));
+        }
+    }
+    active.update(&state.db).await
+}
+
+/// The notification consumer: emails the people an event concerns.
+async fn notify(state: &AppState, event: &DomainEvent) -> Result<(), String> {
+    match event {
+        DomainEvent::ReservationCreated { reservation } => {
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let mut errors = Vec::new();
+            if let Some(requester) = find_user(&state.db, reservation).await? {
+                let email = email_templates::reservation_created(
+                    &state.config.email_templates,
+                    reservation,
+                    classroom.as_ref(),
+                    state.config.email_templates.locale_for(&requester),
+                );
+                if let Err(e) = queue_email_to_user(
+                    state,
+                    &requester,
+                    email.subject,
+                    email.body,
+                    Priority::Normal,
+                )
+                .await
+                {
+                    errors.push(format!("{}: {}", requester.id, e));
+                }
+            }
+            for admin in find_reviewers(&state.db, reservation).await? {
+                let email = email_templates::reservation_review_requested(
+                    &state.config.email_templates,
+                    reservation,
+                    classroom.as_ref(),
+                    state.config.email_templates.locale_for(&admin),
+                );
+                if let Err(e) =
+                    queue_email_to_user(state, &admin, email.subject, email.body, Priority::Normal)
+                        .await
+                {
+                    errors.push(format!("{}: {}", admin.id, e));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Failed to email {}", errors.join(", ")))
+            }
+        }
+        DomainEvent::ReservationReviewed {
+            reservation,
+            alternatives,
+        } => {
+            let Some(requester) = find_user(&state.db, reservation).await? else {
+                return Ok(());
+            };
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let email = email_templates::reservation_reviewed(
+                &state.config.email_templates,
+                reservation,
+                classroom.as_ref(),
+                alternatives,
+                state.config.email_templates.locale_for(&requester),
+            );
+            queue_email_to_user(
+                state,
+                &requester,
+                email.subject,
+                email.body,
+                Priority::Normal,
+            )
+            .await
+            .map_err(|e| format!("Failed to email {}: {}", requester.id, e))
+        }
+        DomainEvent::ReservationPartiallyApproved {
+            reservation,
+            approved_by,
+        } => {
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let mut errors = Vec::new();
+            for reviewer in find_reviewers(&state.db, reservation)
+                .await?
+                .into_iter()
+                .filter(|reviewer| &reviewer.id != approved_by)
+            {
+                let email = email_templates::reservation_second_approval_requested(
+                    &state.config.email_templates,
+                    reservation,
+                    classroom.as_ref(),
+                    state.config.email_templates.locale_for(&reviewer),
+                );
+                if let Err(e) = queue_email_to_user(
+                    state,
+                    &reviewer,
+                    email.subject,
+                    email.body,
+                    Priority::Normal,
+                )
+                .await
+                {
+                    errors.push(format!("{}: {}", reviewer.id, e));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Failed to email {}", errors.join(", ")))
+            }
+        }
+        DomainEvent::ReservationNudged { reservation } => {
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let mut errors = Vec::new();
+            for reviewer in find_reviewers(&state.db, reservation).await? {
+                let email = email_templates::reservation_review_reminder(
+                    &state.config.email_templates,
+                    reservation,
+                    classroom.as_ref(),
+                    state.config.email_templates.locale_for(&reviewer),
+                );
+                if let Err(e) = queue_email_to_user(
+                    state,
+                    &reviewer,
+                    email.subject,
+                    email.body,
+                    Priority::Normal,
+                )
+                .await
+                {
+                    errors.push(format!("{}: {}", reviewer.id, e));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Failed to email {}", errors.join(", ")))
+            }
+        }
+        DomainEvent::ReservationCompleted { reservation } => {
+            let Some(requester) = find_user(&state.db, reservation).await? else {
+                return Ok(());
+            };
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let email = email_templates::reservation_feedback_request(
+                &state.config.email_templates,
+                reservation,
+                classroom.as_ref(),
+                state.config.email_templates.locale_for(&requester),
+            );
+            queue_email_to_user(
+                state,
+                &requester,
+                email.subject,
+                email.body,
+                Priority::Normal,
+            )
+            .await
+            .map_err(|e| format!("Failed to email {}: {}", requester.id, e))
+        }
+        // Nobody is left to email: the owner cancelled it or no longer has
+        // an account. Webhooks and live streams still carry it.
+        DomainEvent::ReservationCancelled { .. } => Ok(()),
+        DomainEvent::ReservationCommented {
+            reservation,
+            comment,
+        } => {
+            let author = match &comment.author_id {
+                Some(author_id) => user::Entity::find_by_id(author_id)
+                    .one(&state.db)
+                    .await
+                    .map_err(|e| format!("Failed to fetch user: {}", e))?,
+                None => None,
+            };
+            let author_name = author.as_ref().map(|author| author.name.as_str());
+            // The requester hears from reviewers and the other way round
+            let recipients =
+                if comment.author_id.is_some() && comment.author_id == reservation.user_id {
+                    find_reviewers(&state.db, reservation).await?
+                } else {
+                    find_user(&state.db, reservation)
+                        .await?
+                        .into_iter()
+                        .collect()
+                };
+            let classroom = find_classroom(&state.db, reservation).await?;
+            let mut errors = Vec::new();
+            for recipient in recipients
+                .into_iter()
+                .filter(|recipient| Some(&recipient.id) != comment.author_id.as_ref())
+            {
+                let email = email_templates::reservation_commented(
+                    &state.config.email_templates,
+                    reservation,
+                    classroom.as_ref(),
+                    author_name,
+                    &comment.body,
+                    state.config.email_templates.locale_for(&recipient),
+                );
+                if let Err(e) = queue_email_to_user(
+                    state,
+                    &recipient,
+                    email.subject,
+                    email.body,
+                    Priority::Normal,
+                )
+                .await
+                {
+                    errors.push(format!("{}: {}", recipient.id, e));
+                }
+            }
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(format!("Failed to email {}", errors.join(", ")))
+            }
+        }
+        DomainEvent::EmergencyAnnounced { announcement } => {
+            routes::announcement::broadcast(state, announcement).await
+        }
+    }
+}
+
+/// Admins and the assistants of the reservation's classroom.
+async fn find_reviewers(
+    db: &DatabaseConnection,
+    reservation: &reservation::Model,
+) -> Result<Vec<user::Model>, String> {
+    let mut reviewers = user::Entity::find()
+        .filter(user::Column::Role.eq(Role::Admin))
+        .all(db)
+        .await
+        .map_err(|e| format!("Failed to fetch admins: {}", e))?;
+    if let Some(classroom_id) = &reservation.classroom_id {
+        reviewers.extend(
+            assistants_for_classroom(db, classroom_id)
+                .await
+                .map_err(|e| format!("Failed to fetch assistants: {}", e))?,
+        );
+    }
+    Ok(reviewers)
+}
+
+async fn find_user(
+    db: &DatabaseConnection,
+    reservation: &reservation::Model,
+) -> Result<Option<user::Model>, String> {
+    match &reservation.user_id {
+        Some(user_id) => user::Entity::find_by_id(user_id)
+            .one(db)
+            .await
+            .map_err(|e| format!("Failed to fetch user: {}", e)),
+        None => Ok(None),
+    }
+}
+
+async fn find_classroom(
+    db: &DatabaseConnection,
+    reservation: &reservation::Model,
+) -> Result<Option<classroom::Model>, String> {
+    match &reservation.classroom_id {
+        Some(classroom_id) => classroom::Entity::find_by_id(classroom_id)
+            .one(db)
+            .await
+            .map_err(|e| format!("Failed to fetch classroom: {}", e)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,182 @@
+#[cfg(test)]
+mod tests {
+    use super::super::availability::AlternativeRoom;
+    use super::super::domain_events::{DomainEvent, EVENT_KINDS};
+    use super::super::entities::{
+        announcement, domain_event, reservation, reservation_comment,
+        sea_orm_active_enums::{
+            AnnouncementAudience, AnnouncementCategory, ReservationStatus, Role,
+        },
+    };
+    use super::super::routes::webhooks::webhook_document;
+    use super::super::test_support::{add_user, reservation_at, router, send, sign_in, state};
+    use axum::http::StatusCode;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, prelude::DateTimeWithTimeZone};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reservation() -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some("room-1".to_string()),
+            approved_by: Some("a1".to_string()),
+            reject_reason: Some("Room is taken".to_string()),
+            status: ReservationStatus::Rejected,
+            reviewed_at: Some(dt("2025-03-02T09:00:00+08:00")),
+            ..reservation_at(
+                "r1",
+                "2025-03-10T10:00:00+08:00",
+                "2025-03-10T12:00:00+08:00",
+            )
+        }
+    }
+
+    fn emergency() -> announcement::Model {
+        announcement::Model {
+            id: "n1".to_string(),
+            title: "Building evacuated".to_string(),
+            content: "All classes in Building A are cancelled today".to_string(),
+            published_at: dt("2025-03-10T08:00:00+08:00"),
+            created_by: Some("a1".to_string()),
+            emergency: true,
+            pinned: true,
+            category: AnnouncementCategory::System,
+            title_en: None,
+            title_zh_tw: None,
+            content_en: None,
+            content_zh_tw: None,
+            audience: AnnouncementAudience::All,
+            audience_classroom_id: None,
+        }
+    }
+
+    #[test]
+    fn test_event_kind_and_subject() {
+        let event = DomainEvent::ReservationCreated {
+            reservation: reservation(),
+        };
+        assert_eq!(event.kind(), "reservation_created");
+        assert_eq!(event.subject_id(), Some("r1"));
+
+        let event = DomainEvent::EmergencyAnnounced {
+            announcement: emergency(),
+        };
+        assert_eq!(event.kind(), "emergency_announced");
+        assert_eq!(event.subject_id(), Some("n1"));
+    }
+
+    #[test]
+    fn test_payload_is_tagged_with_kind() {
+        let event = DomainEvent::ReservationCreated {
+            reservation: reservation(),
+        };
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(payload["type"], event.kind());
+        assert_eq!(payload["reservation"]["id"], "r1");
+    }
+
+    #[test]
+    fn test_payload_round_trips_for_replay() {
+        let event = DomainEvent::ReservationReviewed {
+            reservation: reservation(),
+            alternatives: vec![AlternativeRoom {
+                id: "room-2".to_string(),
+                name: "B201".to_string(),
+                location: "Building B".to_string(),
+                capacity: 40,
+            }],
+        };
+        let payload = serde_json::to_value(&event).unwrap();
+        assert_eq!(
+            serde_json::from_value::<DomainEvent>(payload).unwrap(),
+            event
+        );
+    }
+
+    #[test]
+    fn test_every_event_kind_is_documented() {
+        let events = [
+            DomainEvent::ReservationCreated {
+                reservation: reservation(),
+            },
+            DomainEvent::ReservationReviewed {
+                reservation: reservation(),
+                alternatives: Vec::new(),
+            },
+            DomainEvent::ReservationPartiallyApproved {
+                reservation: reservation(),
+                approved_by: "admin".to_string(),
+            },
+            DomainEvent::ReservationNudged {
+                reservation: reservation(),
+            },
+            DomainEvent::ReservationCompleted {
+                reservation: reservation(),
+            },
+            DomainEvent::ReservationCancelled {
+                reservation: reservation(),
+            },
+            DomainEvent::ReservationCommented {
+                reservation: reservation(),
+                comment: reservation_comment::Model {
+                    id: "c1".to_string(),
+                    reservation_id: "r1".to_string(),
+                    author_id: Some("u1".to_string()),
+                    body: "Can we start an hour later?".to_string(),
+                    created_at: dt("2025-03-02T10:00:00+08:00"),
+                },
+            },
+            DomainEvent::EmergencyAnnounced {
+                announcement: emergency(),
+            },
+        ];
+        let document = serde_json::to_value(webhook_document()).unwrap();
+        for event in events {
+            assert!(EVENT_KINDS.iter().any(|(kind, _)| *kind == event.kind()));
+            assert!(document["webhooks"][event.kind()]["post"].is_object());
+        }
+        assert!(document["components"]["schemas"]["DomainEvent"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_emergency_announcement_is_published_as_an_event() {
+        let state = state().await;
+        add_user(&state, "a1", "a1@example.com", Role::Admin).await;
+        let app = router(state.clone());
+        let admin = sign_in(&app, "a1@example.com").await;
+
+        let response = app
+            .oneshot(send(
+                "POST",
+                "/announcement",
+                &admin,
+                json!({
+                    "title": "Building evacuated",
+                    "content": "All classes in Building A are cancelled today",
+                    "emergency": true,
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+
+        // Published in the background, after the response
+        let mut events = Vec::new();
+        for _ in 0..50 {
+            events = domain_event::Entity::find()
+                .filter(domain_event::Column::Kind.eq("emergency_announced"))
+                .all(&state.db)
+                .await
+                .unwrap();
+            if !events.is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload["announcement"]["emergency"], true);
+    }
+}
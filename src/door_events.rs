@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use axum::http::{HeaderMap, header::AUTHORIZATION};
+use chrono::Duration;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::entities::{door_event, reservation, sea_orm_active_enums::ReservationStatus};
+
+/// A token the door system authenticates with, optionally limited to the
+/// classrooms whose readers use it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DoorToken {
+    pub token: String,
+    /// `None` allows every classroom
+    pub classrooms: Option<Vec<String>>,
+}
+
+impl DoorToken {
+    /// Parses a comma-separated list of `token` or
+    /// `token@classroom_id|classroom_id` entries.
+    pub fn parse_list(value: &str) -> Vec<Self> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('@') {
+                Some((token, classrooms)) => Self {
+                    token: token.trim().to_string(),
+                    classrooms: Some(
+                        classrooms
+                            .split('|')
+                            .map(str::trim)
+                            .filter(|id| !id.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    ),
+                },
+                None => Self {
+                    token: entry.to_string(),
+                    classrooms: None,
+                },
+            })
+            .filter(|token| !token.token.is_empty())
+            .collect()
+    }
+
+    pub fn allows(&self, classroom_id: &str) -> bool {
+        match &self.classrooms {
+            Some(classrooms) => classrooms.iter().any(|id| id == classroom_id),
+            None => true,
+        }
+    }
+}
+
+/// Settings for ingesting door-open events from the building's access
+/// control system. Without tokens the endpoint is disabled.
+#[derive(Clone)]
+pub struct DoorEventsConfig {
+    pub tokens: Vec<DoorToken>,
+    /// How long before the start a door opening still counts toward the
+    /// reservation, as early access
+    pub grace: Duration,
+}
+
+impl Default for DoorEventsConfig {
+    fn default() -> Self {
+        Self {
+            tokens: Vec::new(),
+            grace: Duration::minutes(15),
+        }
+    }
+}
+
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The token presented as `Authorization: Bearer <token>`.
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// The configured token presented as `Authorization: Bearer <token>`.
+pub fn authorize<'a>(tokens: &'a [DoorToken], headers: &HeaderMap) -> Option<&'a DoorToken> {
+    let presented = bearer_token(headers)?;
+    tokens
+        .iter()
+        .find(|token| constant_time_eq(token.token.as_bytes(), presented.as_bytes()))
+}
+
+/// Reservations a door opening can belong to: those that were or are going
+/// to be used, as opposed to cancelled or rejected ones.
+pub fn is_attendable(reservation: &reservation::Model) -> bool {
+    matches!(
+        reservation.status,
+        ReservationStatus::Approved | ReservationStatus::Completed | ReservationStatus::NoShow
+    )
+}
+
+/// The reservation a door opened at `opened_at` belongs to: one running at
+/// the time, or else the next one starting within `grace`.
+pub fn correlate(
+    reservations: &[reservation::Model],
+    opened_at: DateTimeWithTimeZone,
+    grace: Duration,
+) -> Option<&reservation::Model> {
+    reservations
+        .iter()
+        .filter(|r| is_attendable(r))
+        .filter(|r| r.start_time - grace <= opened_at && opened_at < r.end_time)
+        // A booking that is already running wins over the next one's grace
+        .min_by_key(|r| (r.start_time > opened_at, r.start_time))
+}
+
+/// When a reservation's room was actually used, according to the door.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct ActualUsage {
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub first_door_open: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub last_door_open: DateTimeWithTimeZone,
+    pub door_opens: u64,
+    /// The door was first opened before the reservation started
+    pub early_access: bool,
+}
+
+/// Usage from a reservation's door events, or `None` if the door was never
+/// opened.
+pub fn usage_from(events: &[door_event::Model]) -> Option<ActualUsage> {
+    let first = events.iter().min_by_key(|e| e.opened_at)?;
+    let last = events.iter().max_by_key(|e| e.opened_at)?;
+    Some(ActualUsage {
+        first_door_open: first.opened_at,
+        last_door_open: last.opened_at,
+        door_opens: events.len() as u64,
+        early_access: first.early_access,
+    })
+}
+
+pub async fn usage_for(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+) -> Result<Option<ActualUsage>, DbErr> {
+    let events = door_event::Entity::find()
+        .filter(door_event::Column::ReservationId.eq(reservation_id))
+        .order_by_asc(door_event::Column::OpenedAt)
+        .all(db)
+        .await?;
+    Ok(usage_from(&events))
+}
+
+/// Usage of several reservations at once, keyed by reservation ID.
+pub async fn usage_by_reservation(
+    db: &DatabaseConnection,
+    reservation_ids: Vec<String>,
+) -> Result<HashMap<String, ActualUsage>, DbErr> {
+    let mut by_reservation: HashMap<String, Vec<door_event::Model>> = HashMap::new();
+    for event in door_event::Entity::find()
+        .filter(door_event::Column::ReservationId.is_in(reservation_ids))
+        .all(db)
+        .await?
+    {
+        if let Some(reservation_id) = event.reservation_id.clone() {
+            by_reservation
+                .entry(reservation_id)
+                .or_default()
+                .push(event);
+        }
+    }
+    Ok(by_reservation
+        .into_iter()
+        .filter_map(|(id, events)| usage_from(&events).map(|usage| (id, usage)))
+        .collect())
+}
+
+/// How finished reservations were attended, combining manual check-in with
+/// the door system.
+#[derive(Serialize, ToSchema, Clone, Debug, Default, PartialEq)]
+pub struct AttendanceSummary {
+    /// Approved reservations that have ended, including those already
+    /// marked completed or no-show
+    pub reservations: u64,
+    pub checked_in: u64,
+    /// The door was opened but nobody checked in
+    pub door_only: u64,
+    /// Neither checked in nor opened the door: likely no-shows
+    pub unused: u64,
+    /// Marked as no-show although the door was opened
+    pub no_show_with_door_use: u64,
+    pub early_access: u64,
+}
+
+pub fn summarize_attendance(
+    reservations: &[reservation::Model],
+    usage: &HashMap<String, ActualUsage>,
+) -> AttendanceSummary {
+    let mut summary = AttendanceSummary::default();
+    for reservation in reservations.iter().filter(|r| is_attendable(r)) {
+        let used = usage.get(&reservation.id);
+        summary.reservations += 1;
+        match (reservation.checked_in_at.is_some(), used.is_some()) {
+            (true, _) => summary.checked_in += 1,
+            (false, true) => summary.door_only += 1,
+            (false, false) => summary.unused += 1,
+        }
+        if reservation.status == ReservationStatus::NoShow && used.is_some() {
+            summary.no_show_with_door_use += 1;
+        }
+        if used.is_some_and(|u| u.early_access) {
+            summary.early_access += 1;
+        }
+    }
+    summary
+}
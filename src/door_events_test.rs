@@ -0,0 +1,177 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::door_events::{
+        AttendanceSummary, DoorToken, authorize, correlate, summarize_attendance, usage_from,
+    };
+    use super::super::entities::{
+        door_event, reservation, sea_orm_active_enums::ReservationStatus,
+    };
+    use super::super::test_support::reservation_at;
+    use axum::http::{HeaderMap, HeaderValue, header::AUTHORIZATION};
+    use chrono::Duration;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reservation(
+        id: &str,
+        start: &str,
+        end: &str,
+        status: ReservationStatus,
+    ) -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some("r101".to_string()),
+            status,
+            ..reservation_at(id, start, end)
+        }
+    }
+
+    fn door_open(reservation_id: &str, opened_at: &str, early_access: bool) -> door_event::Model {
+        door_event::Model {
+            id: format!("{}-{}", reservation_id, opened_at),
+            classroom_id: "r101".to_string(),
+            door_id: None,
+            reservation_id: Some(reservation_id.to_string()),
+            opened_at: dt(opened_at),
+            early_access,
+            created_at: dt(opened_at),
+        }
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        assert_eq!(
+            DoorToken::parse_list("abc, def@r101|r102 ,,"),
+            vec![
+                DoorToken {
+                    token: "abc".to_string(),
+                    classrooms: None,
+                },
+                DoorToken {
+                    token: "def".to_string(),
+                    classrooms: Some(vec!["r101".to_string(), "r102".to_string()]),
+                },
+            ]
+        );
+        let scoped = &DoorToken::parse_list("def@r101")[0];
+        assert!(scoped.allows("r101"));
+        assert!(!scoped.allows("r201"));
+    }
+
+    #[test]
+    fn test_authorize_bearer_token() {
+        let tokens = DoorToken::parse_list("abc,def@r101");
+        let mut headers = HeaderMap::new();
+        assert_eq!(authorize(&tokens, &headers), None);
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer def"));
+        assert_eq!(authorize(&tokens, &headers), Some(&tokens[1]));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer de"));
+        assert_eq!(authorize(&tokens, &headers), None);
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("abc"));
+        assert_eq!(authorize(&tokens, &headers), None);
+    }
+
+    #[test]
+    fn test_correlate_prefers_running_reservation_over_next_grace() {
+        let grace = Duration::minutes(15);
+        let reservations = vec![
+            reservation(
+                "first",
+                "2025-03-10T09:00:00+08:00",
+                "2025-03-10T10:00:00+08:00",
+                ReservationStatus::Approved,
+            ),
+            reservation(
+                "second",
+                "2025-03-10T10:00:00+08:00",
+                "2025-03-10T11:00:00+08:00",
+                ReservationStatus::Approved,
+            ),
+        ];
+        let at = |s| correlate(&reservations, dt(s), grace).map(|r| r.id.as_str());
+        assert_eq!(at("2025-03-10T08:50:00+08:00"), Some("first"));
+        assert_eq!(at("2025-03-10T09:55:00+08:00"), Some("first"));
+        assert_eq!(at("2025-03-10T10:00:00+08:00"), Some("second"));
+        assert_eq!(at("2025-03-10T08:40:00+08:00"), None);
+        assert_eq!(at("2025-03-10T11:00:00+08:00"), None);
+    }
+
+    #[test]
+    fn test_correlate_ignores_cancelled_reservations() {
+        let reservations = vec![reservation(
+            "cancelled",
+            "2025-03-10T09:00:00+08:00",
+            "2025-03-10T10:00:00+08:00",
+            ReservationStatus::Cancelled,
+        )];
+        assert!(
+            correlate(
+                &reservations,
+                dt("2025-03-10T09:30:00+08:00"),
+                Duration::minutes(15)
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_usage_from_door_events() {
+        assert_eq!(usage_from(&[]), None);
+        let usage = usage_from(&[
+            door_open("r", "2025-03-10T09:40:00+08:00", false),
+            door_open("r", "2025-03-10T08:55:00+08:00", true),
+            door_open("r", "2025-03-10T09:10:00+08:00", false),
+        ])
+        .unwrap();
+        assert_eq!(usage.first_door_open, dt("2025-03-10T08:55:00+08:00"));
+        assert_eq!(usage.last_door_open, dt("2025-03-10T09:40:00+08:00"));
+        assert_eq!(usage.door_opens, 3);
+        assert!(usage.early_access);
+    }
+
+    #[test]
+    fn test_attendance_separates_door_use_from_no_shows() {
+        let start = "2025-03-10T09:00:00+08:00";
+        let end = "2025-03-10T10:00:00+08:00";
+        let mut checked_in = reservation("checked_in", start, end, ReservationStatus::Completed);
+        checked_in.checked_in_at = Some(dt("2025-03-10T09:02:00+08:00"));
+        let reservations = vec![
+            checked_in,
+            reservation("door_only", start, end, ReservationStatus::Approved),
+            reservation("no_show", start, end, ReservationStatus::NoShow),
+            reservation("unused", start, end, ReservationStatus::Approved),
+            reservation("cancelled", start, end, ReservationStatus::Cancelled),
+        ];
+        let usage: HashMap<_, _> = [
+            (
+                "door_only",
+                door_open("door_only", "2025-03-10T08:50:00+08:00", true),
+            ),
+            (
+                "no_show",
+                door_open("no_show", "2025-03-10T09:20:00+08:00", false),
+            ),
+        ]
+        .into_iter()
+        .map(|(id, event)| (id.to_string(), usage_from(&[event]).unwrap()))
+        .collect();
+
+        assert_eq!(
+            summarize_attendance(&reservations, &usage),
+            AttendanceSummary {
+                reservations: 4,
+                checked_in: 1,
+                door_only: 2,
+                unused: 1,
+                no_show_with_door_use: 1,
+                early_access: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    entities::{reservation, reservation_approval, sea_orm_active_enums::ReservationStatus},
+    error::AppError,
+    permissions::ClassroomScope,
+};
+
+/// What an approval of a pending reservation amounts to.
+#[derive(Debug, PartialEq)]
+pub enum ApprovalStep {
+    /// The reservation becomes approved
+    Approve,
+    /// The first of two approvals; the reservation stays pending until a
+    /// different reviewer approves it too
+    AwaitSecond,
+}
+
+/// The reviewer already gave the first of the two approvals.
+#[derive(Debug, PartialEq)]
+pub struct SameReviewer;
+
+impl From<SameReviewer> for AppError {
+    fn from(_: SameReviewer) -> Self {
+        AppError::Conflict(
+            "You already approved this reservation; the second approval must come from another reviewer"
+                .to_string(),
+        )
+    }
+}
+
+/// What `reviewer_id` approving a pending reservation does, given the
+/// approvals it already has and whether its classroom requires two.
+pub fn next_step(
+    requires_double_approval: bool,
+    approvals: &[reservation_approval::Model],
+    reviewer_id: &str,
+) -> Result<ApprovalStep, SameReviewer> {
+    if !requires_double_approval {
+        return Ok(ApprovalStep::Approve);
+    }
+    if approvals
+        .iter()
+        .any(|a| a.approved_by.as_deref() == Some(reviewer_id))
+    {
+        return Err(SameReviewer);
+    }
+    if approvals.is_empty() {
+        Ok(ApprovalStep::AwaitSecond)
+    } else {
+        Ok(ApprovalStep::Approve)
+    }
+}
+
+/// Approvals given to a reservation, oldest first.
+pub async fn approvals_for<C: ConnectionTrait>(
+    db: &C,
+    reservation_id: &str,
+) -> Result<Vec<reservation_approval::Model>, DbErr> {
+    reservation_approval::Entity::find()
+        .filter(reservation_approval::Column::ReservationId.eq(reservation_id))
+        .order_by_asc(reservation_approval::Column::ApprovedAt)
+        .all(db)
+        .await
+}
+
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    reservation_id: &str,
+    reviewer_id: &str,
+    now: DateTimeWithTimeZone,
+) -> Result<reservation_approval::Model, DbErr> {
+    reservation_approval::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(reservation_id.to_string()),
+        approved_by: Set(Some(reviewer_id.to_string())),
+        approved_at: Set(now),
+    }
+    .insert(db)
+    .await
+}
+
+/// Drops the approvals of a reservation, e.g. after the requester changed
+/// it, so both reviewers see what they approve.
+pub async fn clear(db: &DatabaseConnection, reservation_id: &str) -> Result<(), DbErr> {
+    reservation_approval::Entity::delete_many()
+        .filter(reservation_approval::Column::ReservationId.eq(reservation_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// A reservation holding the first of two approvals.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct AwaitingSecondApproval {
+    pub reservation: reservation::Model,
+    pub first_approval: reservation_approval::Model,
+}
+
+/// Pending reservations in `scope` with one approval from someone other
+/// than `reviewer_id`, earliest start first.
+pub async fn second_approval_queue(
+    db: &DatabaseConnection,
+    scope: &ClassroomScope,
+    reviewer_id: &str,
+) -> Result<Vec<AwaitingSecondApproval>, DbErr> {
+    let mut approvals: HashMap<String, Vec<reservation_approval::Model>> = HashMap::new();
+    for approval in reservation_approval::Entity::find()
+        .find_also_related(reservation::Entity)
+        .filter(reservation::Column::Status.eq(ReservationStatus::Pending))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(approval, reservation)| reservation.map(|_| approval))
+    {
+        approvals
+            .entry(approval.reservation_id.clone())
+            .or_default()
+            .push(approval);
+    }
+    approvals.retain(|_, given| {
+        given
+            .iter()
+            .all(|a| a.approved_by.as_deref() != Some(reviewer_id))
+    });
+
+    let reservations = scope
+        .apply(reservation::Entity::find())
+        .filter(reservation::Column::Id.is_in(approvals.keys().cloned()))
+        .order_by_asc(reservation::Column::StartTime)
+        .all(db)
+        .await?;
+    Ok(reservations
+        .into_iter()
+        .filter_map(|reservation| {
+            let first_approval = approvals
+                .remove(&reservation.id)?
+                .into_iter()
+                .min_by_key(|a| a.approved_at)?;
+            Some(AwaitingSecondApproval {
+                reservation,
+                first_approval,
+            })
+        })
+        .collect())
+}
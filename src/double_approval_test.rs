@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        double_approval::{ApprovalStep, SameReviewer, next_step},
+        entities::reservation_approval,
+        error::AppError,
+    };
+
+    fn approval(by: &str) -> reservation_approval::Model {
+        reservation_approval::Model {
+            id: format!("a-{}", by),
+            reservation_id: "r1".to_string(),
+            approved_by: Some(by.to_string()),
+            approved_at: "2025-03-01T09:00:00+08:00".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_single_approval_rooms_approve_at_once() {
+        assert_eq!(next_step(false, &[], "admin-1"), Ok(ApprovalStep::Approve));
+    }
+
+    #[test]
+    fn test_double_approval_needs_two_different_reviewers() {
+        assert_eq!(
+            next_step(true, &[], "admin-1"),
+            Ok(ApprovalStep::AwaitSecond)
+        );
+        assert_eq!(
+            next_step(true, &[approval("admin-1")], "admin-2"),
+            Ok(ApprovalStep::Approve)
+        );
+        assert_eq!(
+            next_step(true, &[approval("admin-1")], "admin-1"),
+            Err(SameReviewer)
+        );
+        assert!(matches!(
+            AppError::from(SameReviewer),
+            AppError::Conflict(_)
+        ));
+    }
+
+    #[test]
+    fn test_approvals_of_deleted_reviewers_still_count() {
+        let mut deleted = approval("admin-1");
+        deleted.approved_by = None;
+        assert_eq!(
+            next_step(true, &[deleted], "admin-2"),
+            Ok(ApprovalStep::Approve)
+        );
+    }
+}
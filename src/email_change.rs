@@ -0,0 +1,107 @@
+use chrono::Utc;
+use redis::{AsyncCommands, RedisError, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    password_reset::{PasswordResetConfig, attempts_exhausted, gen_code},
+    redis_breaker::RedisConnection,
+};
+
+/// An email change waiting for the code sent to the new address. Uses the
+/// same code length, TTL and attempt limit as password reset codes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PendingEmailChange {
+    pub email: String,
+    pub code: String,
+    pub expires_at: i64, // Unix timestamp
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfirmOutcome {
+    /// The code matched; the user's email can be switched to this address
+    Confirmed(String),
+    /// No change pending, or it expired
+    NotPending,
+    WrongCode,
+    /// Too many wrong codes; the pending change was discarded
+    TooManyAttempts,
+}
+
+fn pending_key(user_id: &str) -> String {
+    format!("email_change:pending:{}", user_id)
+}
+
+fn attempts_key(user_id: &str) -> String {
+    format!("email_change:attempts:{}", user_id)
+}
+
+/// Whether `code` confirms `pending` at `now`, before counting attempts.
+pub fn check(pending: Option<&PendingEmailChange>, code: &str, now: i64) -> ConfirmOutcome {
+    match pending {
+        Some(pending) if pending.expires_at > now => {
+            if pending.code == code.trim() {
+                ConfirmOutcome::Confirmed(pending.email.clone())
+            } else {
+                ConfirmOutcome::WrongCode
+            }
+        }
+        _ => ConfirmOutcome::NotPending,
+    }
+}
+
+/// Stores a new pending change, replacing any earlier one, and returns it so
+/// the code can be emailed to the new address.
+pub async fn start(
+    redis: &mut RedisConnection,
+    config: &PasswordResetConfig,
+    user_id: &str,
+    email: &str,
+) -> Result<PendingEmailChange, RedisError> {
+    let pending = PendingEmailChange {
+        email: email.to_string(),
+        code: gen_code(config.code_length),
+        expires_at: (Utc::now() + config.code_ttl).timestamp(),
+    };
+    let _: () = redis
+        .set_options(
+            pending_key(user_id),
+            serde_json::to_string(&pending).unwrap(),
+            SetOptions::default()
+                .with_expiration(SetExpiry::EX(config.code_ttl.num_seconds() as u64)),
+        )
+        .await?;
+    let _: () = redis.del(attempts_key(user_id)).await?;
+    Ok(pending)
+}
+
+pub async fn confirm(
+    redis: &mut RedisConnection,
+    config: &PasswordResetConfig,
+    user_id: &str,
+    code: &str,
+) -> Result<ConfirmOutcome, RedisError> {
+    let stored: Option<String> = redis.get(pending_key(user_id)).await?;
+    let pending = stored.and_then(|s| serde_json::from_str::<PendingEmailChange>(&s).ok());
+    match check(pending.as_ref(), code, Utc::now().timestamp()) {
+        ConfirmOutcome::WrongCode => {
+            let failed: u32 = redis.incr(attempts_key(user_id), 1).await?;
+            if failed == 1 {
+                let _: () = redis
+                    .expire(attempts_key(user_id), config.code_ttl.num_seconds())
+                    .await?;
+            }
+            if attempts_exhausted(failed, config.max_verify_attempts) {
+                discard(redis, user_id).await?;
+                return Ok(ConfirmOutcome::TooManyAttempts);
+            }
+            Ok(ConfirmOutcome::WrongCode)
+        }
+        outcome => Ok(outcome),
+    }
+}
+
+pub async fn discard(redis: &mut RedisConnection, user_id: &str) -> Result<(), RedisError> {
+    let _: () = redis.del(pending_key(user_id)).await?;
+    let _: () = redis.del(attempts_key(user_id)).await?;
+    Ok(())
+}
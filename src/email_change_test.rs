@@ -0,0 +1,33 @@
+#[cfg(test)]
+mod tests {
+    use super::super::email_change::{ConfirmOutcome, PendingEmailChange, check};
+
+    fn pending() -> PendingEmailChange {
+        PendingEmailChange {
+            email: "new@example.edu".to_string(),
+            code: "123456".to_string(),
+            expires_at: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_matching_code_confirms_new_address() {
+        assert_eq!(
+            check(Some(&pending()), " 123456 ", 999),
+            ConfirmOutcome::Confirmed("new@example.edu".to_string())
+        );
+        assert_eq!(
+            check(Some(&pending()), "654321", 999),
+            ConfirmOutcome::WrongCode
+        );
+    }
+
+    #[test]
+    fn test_expired_or_missing_change_is_not_pending() {
+        assert_eq!(
+            check(Some(&pending()), "123456", 1_000),
+            ConfirmOutcome::NotPending
+        );
+        assert_eq!(check(None, "123456", 0), ConfirmOutcome::NotPending);
+    }
+}
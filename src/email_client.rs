@@ -1,8 +1,10 @@
-use std::sync::OnceLock;
-
 use mail_send::{SmtpClientBuilder, mail_builder::MessageBuilder};
+use tracing::debug;
 
-static GLOBAL_EMAIL_CONFIG: OnceLock<EmailClientConfig> = OnceLock::new();
+use crate::{
+    entities::user,
+    server_timing::{Dependency, measure},
+};
 
 #[derive(Clone)]
 pub struct EmailClientConfig {
@@ -12,18 +14,21 @@ pub struct EmailClientConfig {
     pub password: String,
 }
 
-pub fn set_email_client_config(config: EmailClientConfig) {
-    let _ = GLOBAL_EMAIL_CONFIG.set(config);
-}
-
 pub async fn send_email(
+    smtp: Option<&EmailClientConfig>,
     to: impl AsRef<str>,
     subject: impl AsRef<str>,
     body: impl AsRef<str>,
 ) -> Result<(), mail_send::Error> {
-    let config = GLOBAL_EMAIL_CONFIG
-        .get()
-        .expect("Email client config not set");
+    // Memory mode runs without SMTP; emails only show up in the logs
+    let Some(config) = smtp else {
+        debug!(
+            "No SMTP configured, not sending \"{}\" to {}",
+            subject.as_ref(),
+            to.as_ref()
+        );
+        return Ok(());
+    };
 
     let message = MessageBuilder::new()
         .from(config.username.as_ref())
@@ -31,13 +36,29 @@ pub async fn send_email(
         .subject(subject.as_ref())
         .text_body(body.as_ref());
 
-    SmtpClientBuilder::new(config.smtp_server.as_ref(), config.smtp_port)
-        .implicit_tls(false)
-        .credentials((config.username.as_ref(), config.password.as_ref()))
-        .connect()
-        .await?
-        .send(message)
-        .await?;
+    measure(Dependency::Smtp, async {
+        SmtpClientBuilder::new(config.smtp_server.as_ref(), config.smtp_port)
+            .implicit_tls(false)
+            .credentials((config.username.as_ref(), config.password.as_ref()))
+            .connect()
+            .await?
+            .send(message)
+            .await
+    })
+    .await
+}
 
-    Ok(())
+/// Sends to a user's address unless a bounce or complaint has flagged it as
+/// undeliverable, in which case the email is silently dropped.
+pub async fn send_email_to_user(
+    smtp: Option<&EmailClientConfig>,
+    user: &user::Model,
+    subject: impl AsRef<str>,
+    body: impl AsRef<str>,
+) -> Result<(), mail_send::Error> {
+    if user.email_undeliverable_at.is_some() {
+        debug!("Skipping email to user {}: address undeliverable", user.id);
+        return Ok(());
+    }
+    send_email(smtp, &user.email, subject, body).await
 }
@@ -0,0 +1,68 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Settings for the mail provider's bounce/complaint webhook. Without a
+/// secret the webhook is disabled.
+#[derive(Clone)]
+pub struct EmailEventsConfig {
+    pub secret: Option<Vec<u8>>,
+    /// How far the signed timestamp may be from now, to stop replays
+    pub tolerance: Duration,
+}
+
+impl Default for EmailEventsConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            tolerance: Duration::minutes(5),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailEventKind {
+    Bounce,
+    Complaint,
+    /// Anything else the provider sends, e.g. deliveries; ignored
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, ToSchema, Clone, Debug)]
+pub struct EmailEvent {
+    #[serde(rename = "type")]
+    pub kind: EmailEventKind,
+    pub email: String,
+    /// False for soft bounces such as a full mailbox; defaults to true
+    pub permanent: Option<bool>,
+    /// Provider diagnostic, e.g. `550 5.1.1 user unknown`
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct EmailEventsBody {
+    pub events: Vec<EmailEvent>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct EmailEventsResponse {
+    pub received: usize,
+    /// Users whose address was newly flagged as undeliverable
+    pub flagged: u64,
+}
+
+/// Reason to store on the user when `event` should stop further sends, or
+/// `None` for events that do not, such as soft bounces.
+pub fn undeliverable_reason(event: &EmailEvent) -> Option<String> {
+    let label = match event.kind {
+        EmailEventKind::Bounce if event.permanent.unwrap_or(true) => "bounce",
+        EmailEventKind::Complaint => "complaint",
+        _ => return None,
+    };
+    Some(match &event.reason {
+        Some(reason) if !reason.trim().is_empty() => format!("{}: {}", label, reason.trim()),
+        _ => label.to_string(),
+    })
+}
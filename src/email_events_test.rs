@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod tests {
+    use super::super::email_events::{EmailEvent, EmailEventKind, undeliverable_reason};
+
+    fn event(kind: EmailEventKind, permanent: Option<bool>, reason: Option<&str>) -> EmailEvent {
+        EmailEvent {
+            kind,
+            email: "student@example.edu".to_string(),
+            permanent,
+            reason: reason.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_only_hard_bounces_and_complaints_flag_the_address() {
+        assert_eq!(
+            undeliverable_reason(&event(
+                EmailEventKind::Bounce,
+                None,
+                Some("550 user unknown")
+            )),
+            Some("bounce: 550 user unknown".to_string())
+        );
+        assert_eq!(
+            undeliverable_reason(&event(EmailEventKind::Complaint, None, None)),
+            Some("complaint".to_string())
+        );
+        assert_eq!(
+            undeliverable_reason(&event(EmailEventKind::Bounce, Some(false), None)),
+            None
+        );
+        assert_eq!(
+            undeliverable_reason(&event(EmailEventKind::Other, None, None)),
+            None
+        );
+    }
+}
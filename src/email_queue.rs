@@ -0,0 +1,219 @@
+use chrono::{NaiveTime, Utc};
+use redis::{AsyncCommands, RedisError};
+use serde::{Deserialize, Serialize};
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_client::{send_email, send_email_to_user},
+    entities::user,
+};
+
+const CRITICAL_QUEUE_KEY: &str = "email_queue_critical";
+const NORMAL_QUEUE_KEY: &str = "email_queue_normal";
+
+/// Campus-time window in which non-critical email is held back. The window
+/// may wrap past midnight, e.g. 23:00–07:00.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    /// Parses `HH:MM-HH:MM`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let (start, end) = value.split_once('-')?;
+        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+        (start != end).then_some(Self { start, end })
+    }
+
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Limits applied by the worker that sends queued email, so reminder bursts
+/// stay within what the campus SMTP relay accepts.
+#[derive(Clone)]
+pub struct EmailQueueConfig {
+    /// Emails sent per minute across all instances; `None` is unlimited
+    pub max_per_minute: Option<u32>,
+    /// `None` sends non-critical email around the clock
+    pub quiet_hours: Option<QuietHours>,
+    /// How often the queue is drained
+    pub poll_interval: std::time::Duration,
+    /// Sends tried before a queued email is dropped
+    pub max_attempts: u32,
+}
+
+impl Default for EmailQueueConfig {
+    fn default() -> Self {
+        Self {
+            max_per_minute: Some(30),
+            quiet_hours: Some(QuietHours {
+                start: NaiveTime::from_hms_opt(23, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            }),
+            poll_interval: std::time::Duration::from_secs(10),
+            max_attempts: 5,
+        }
+    }
+}
+
+/// Critical email (emergency announcements) ignores quiet hours and is sent
+/// before anything else; it still counts toward the rate limit.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    Critical,
+    Normal,
+}
+
+impl Priority {
+    fn queue_key(&self) -> &'static str {
+        match self {
+            Self::Critical => CRITICAL_QUEUE_KEY,
+            Self::Normal => NORMAL_QUEUE_KEY,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueuedEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+    pub priority: Priority,
+    #[serde(default)]
+    pub attempts: u32,
+}
+
+/// Whether email of `priority` may be sent at campus time `time`.
+pub fn may_send(config: &EmailQueueConfig, priority: Priority, time: NaiveTime) -> bool {
+    match (priority, config.quiet_hours) {
+        (Priority::Critical, _) | (Priority::Normal, None) => true,
+        (Priority::Normal, Some(quiet_hours)) => !quiet_hours.contains(time),
+    }
+}
+
+/// Queues an email to a user for the worker, dropping it when the address is
+/// undeliverable. Sent straight away if the queue cannot be reached.
+pub async fn queue_email_to_user(
+    state: &AppState,
+    user: &user::Model,
+    subject: impl Into<String>,
+    body: impl Into<String>,
+    priority: Priority,
+) -> Result<(), mail_send::Error> {
+    if user.email_undeliverable_at.is_some() {
+        debug!("Skipping email to user {}: address undeliverable", user.id);
+        return Ok(());
+    }
+    let email = QueuedEmail {
+        to: user.email.clone(),
+        subject: subject.into(),
+        body: body.into(),
+        priority,
+        attempts: 0,
+    };
+    if let Err(e) = enqueue(state, &email).await {
+        warn!("Failed to queue email to {}, sending now: {}", user.id, e);
+        return send_email_to_user(state.config.smtp.as_ref(), user, email.subject, email.body)
+            .await;
+    }
+    Ok(())
+}
+
+async fn enqueue(state: &AppState, email: &QueuedEmail) -> Result<(), RedisError> {
+    let mut redis = state.redis.clone();
+    redis
+        .rpush::<_, _, ()>(
+            email.priority.queue_key(),
+            serde_json::to_string(email).unwrap(),
+        )
+        .await
+}
+
+/// Drains the queue every [`EmailQueueConfig::poll_interval`]. Spawned once
+/// at startup.
+pub async fn run(state: AppState) {
+    let mut ticker = tokio::time::interval(state.config.email_queue.poll_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = drain(&state).await {
+            warn!("Email queue run failed: {}", e);
+        }
+    }
+}
+
+/// Sends queued email until the queue is empty or this minute's budget is
+/// spent. Normal email stays queued during quiet hours.
+pub async fn drain(state: &AppState) -> Result<(), RedisError> {
+    let config = &state.config.email_queue;
+    let now = Utc::now().with_timezone(&campus_offset());
+    let mut redis = state.redis.clone();
+
+    for priority in [Priority::Critical, Priority::Normal] {
+        if !may_send(config, priority, now.time()) {
+            continue;
+        }
+        loop {
+            // The slot is taken before popping so parallel instances share one
+            // budget; it is handed back when there was nothing to send
+            let rate_key = format!("email_rate_{}", now.timestamp() / 60);
+            if let Some(max_per_minute) = config.max_per_minute {
+                let sent: u32 = redis.incr(&rate_key, 1).await?;
+                redis.expire::<_, ()>(&rate_key, 120).await?;
+                if sent > max_per_minute {
+                    redis.decr::<_, _, ()>(&rate_key, 1).await?;
+                    return Ok(());
+                }
+            }
+
+            let raw: Option<String> = redis.lpop(priority.queue_key(), None).await?;
+            let Some(raw) = raw else {
+                if config.max_per_minute.is_some() {
+                    redis.decr::<_, _, ()>(&rate_key, 1).await?;
+                }
+                break;
+            };
+            let Ok(mut email) = serde_json::from_str::<QueuedEmail>(&raw) else {
+                warn!("Dropping unreadable queued email: {}", raw);
+                continue;
+            };
+
+            if let Err(e) = send_email(
+                state.config.smtp.as_ref(),
+                &email.to,
+                &email.subject,
+                &email.body,
+            )
+            .await
+            {
+                email.attempts += 1;
+                if email.attempts >= config.max_attempts {
+                    warn!(
+                        "Dropping email to {} after {} attempts: {}",
+                        email.to, email.attempts, e
+                    );
+                } else {
+                    warn!("Failed to send queued email to {}: {}", email.to, e);
+                    enqueue(state, &email).await?;
+                }
+                // A failing relay is retried on the next run rather than
+                // spinning through the queue
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
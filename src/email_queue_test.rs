@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod tests {
+    use super::super::email_queue::{
+        EmailQueueConfig, Priority, QueuedEmail, QuietHours, may_send,
+    };
+    use chrono::NaiveTime;
+
+    fn time(h: u32, m: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_quiet_hours() {
+        assert_eq!(
+            QuietHours::parse("23:00-07:00"),
+            Some(QuietHours {
+                start: time(23, 0),
+                end: time(7, 0),
+            })
+        );
+        assert_eq!(
+            QuietHours::parse(" 12:30 - 13:30 "),
+            Some(QuietHours {
+                start: time(12, 30),
+                end: time(13, 30),
+            })
+        );
+        assert_eq!(QuietHours::parse("23:00"), None);
+        assert_eq!(QuietHours::parse("25:00-07:00"), None);
+        assert_eq!(QuietHours::parse("07:00-07:00"), None);
+    }
+
+    #[test]
+    fn test_quiet_hours_wrap_past_midnight() {
+        let quiet = QuietHours::parse("23:00-07:00").unwrap();
+        assert!(quiet.contains(time(23, 0)));
+        assert!(quiet.contains(time(2, 15)));
+        assert!(quiet.contains(time(6, 59)));
+        assert!(!quiet.contains(time(7, 0)));
+        assert!(!quiet.contains(time(22, 59)));
+    }
+
+    #[test]
+    fn test_quiet_hours_within_a_day() {
+        let quiet = QuietHours::parse("12:00-13:00").unwrap();
+        assert!(quiet.contains(time(12, 30)));
+        assert!(!quiet.contains(time(13, 0)));
+        assert!(!quiet.contains(time(11, 59)));
+    }
+
+    #[test]
+    fn test_only_critical_email_is_sent_in_quiet_hours() {
+        let config = EmailQueueConfig::default();
+        assert!(may_send(&config, Priority::Critical, time(2, 0)));
+        assert!(!may_send(&config, Priority::Normal, time(2, 0)));
+        assert!(may_send(&config, Priority::Normal, time(9, 0)));
+
+        let always = EmailQueueConfig {
+            quiet_hours: None,
+            ..EmailQueueConfig::default()
+        };
+        assert!(may_send(&always, Priority::Normal, time(2, 0)));
+    }
+
+    #[test]
+    fn test_queued_email_round_trips() {
+        let email = QueuedEmail {
+            to: "a@example.com".to_string(),
+            subject: "Key overdue".to_string(),
+            body: "Please return the key".to_string(),
+            priority: Priority::Normal,
+            attempts: 2,
+        };
+        let raw = serde_json::to_string(&email).unwrap();
+        assert!(raw.contains("\"priority\":\"normal\""));
+        assert_eq!(serde_json::from_str::<QueuedEmail>(&raw).unwrap(), email);
+    }
+}
@@ -0,0 +1,1057 @@
+use chrono::{Datelike, Timelike};
+use sea_orm::prelude::DateTimeWithTimeZone;
+
+use crate::{
+    availability::{AlternativeRoom, campus_offset},
+    closure_impact::{AffectedReservation, ClosureAction},
+    entities::{
+        announcement, classroom, key, key_cabinet_pin, key_transaction_log, reservation,
+        sea_orm_active_enums::{AnnouncementCategory, ReservationStatus, Role},
+        user,
+    },
+};
+
+#[derive(Clone)]
+pub struct EmailTemplateConfig {
+    /// Base URL of the frontend, used to link to detail pages. No link is
+    /// rendered when unset.
+    pub frontend_base_url: Option<String>,
+    /// Locale used for recipients that have not chosen one.
+    pub default_locale: Locale,
+}
+
+impl Default for EmailTemplateConfig {
+    fn default() -> Self {
+        Self {
+            frontend_base_url: None,
+            default_locale: Locale::En,
+        }
+    }
+}
+
+impl EmailTemplateConfig {
+    /// Locale of the recipient, falling back to the configured default.
+    pub fn locale_for(&self, user: &user::Model) -> Locale {
+        user.locale
+            .as_deref()
+            .and_then(Locale::from_tag)
+            .unwrap_or(self.default_locale)
+    }
+
+    fn link(&self, path: &str) -> Option<String> {
+        self.frontend_base_url
+            .as_ref()
+            .map(|base| format!("{}{}", base.trim_end_matches('/'), path))
+    }
+
+    pub fn reservation_link(&self, reservation_id: &str) -> Option<String> {
+        self.link(&format!("/reservations/{}", reservation_id))
+    }
+
+    pub fn feedback_link(&self, reservation_id: &str) -> Option<String> {
+        self.link(&format!("/reservations/{}/feedback", reservation_id))
+    }
+
+    pub fn invite_link(&self, token: &str) -> Option<String> {
+        self.link(&format!("/accept-invite/{}", token))
+    }
+
+    pub fn unlock_link(&self, token: &str) -> Option<String> {
+        self.link(&format!("/unlock-account/{}", token))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Locale {
+    En,
+    ZhTw,
+}
+
+impl Locale {
+    /// Parses a BCP 47 style tag such as `en`, `en-US` or `zh-TW`.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let tag = tag.trim().to_ascii_lowercase().replace('_', "-");
+        if tag == "zh" || tag.starts_with("zh-tw") || tag.starts_with("zh-hant") {
+            Some(Locale::ZhTw)
+        } else if tag == "en" || tag.starts_with("en-") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+}
+
+const EN_WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const EN_MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+const ZH_WEEKDAYS: [&str; 7] = ["一", "二", "三", "四", "五", "六", "日"];
+
+/// Formats a timestamp in the campus timezone, e.g. `Mon, 6 Jan 2025 08:00`
+/// or `2025年1月6日（一）08:00`.
+pub fn format_datetime(dt: DateTimeWithTimeZone, locale: Locale) -> String {
+    let dt = dt.with_timezone(&campus_offset());
+    let weekday = dt.weekday().num_days_from_monday() as usize;
+    match locale {
+        Locale::En => format!(
+            "{}, {} {} {} {:02}:{:02}",
+            EN_WEEKDAYS[weekday],
+            dt.day(),
+            EN_MONTHS[dt.month0() as usize],
+            dt.year(),
+            dt.hour(),
+            dt.minute()
+        ),
+        Locale::ZhTw => format!(
+            "{}年{}月{}日（{}）{:02}:{:02}",
+            dt.year(),
+            dt.month(),
+            dt.day(),
+            ZH_WEEKDAYS[weekday],
+            dt.hour(),
+            dt.minute()
+        ),
+    }
+}
+
+/// Formats a time range, collapsing the end to `HH:MM` when both ends fall on
+/// the same campus day.
+pub fn format_range(
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+    locale: Locale,
+) -> String {
+    let start_local = start.with_timezone(&campus_offset());
+    let end_local = end.with_timezone(&campus_offset());
+    let end_str = if start_local.date_naive() == end_local.date_naive() {
+        format!("{:02}:{:02}", end_local.hour(), end_local.minute())
+    } else {
+        format_datetime(end, locale)
+    };
+    format!("{} – {} (GMT+8)", format_datetime(start, locale), end_str)
+}
+
+pub fn status_label(status: &ReservationStatus, locale: Locale) -> &'static str {
+    match (status, locale) {
+        (ReservationStatus::Pending, Locale::En) => "Pending",
+        (ReservationStatus::Approved, Locale::En) => "Approved",
+        (ReservationStatus::Rejected, Locale::En) => "Rejected",
+        (ReservationStatus::Cancelled, Locale::En) => "Cancelled",
+        (ReservationStatus::NeedsRebooking, Locale::En) => "Needs rebooking",
+        (ReservationStatus::Expired, Locale::En) => "Expired",
+        (ReservationStatus::Completed, Locale::En) => "Completed",
+        (ReservationStatus::NoShow, Locale::En) => "No-show",
+        (ReservationStatus::Pending, Locale::ZhTw) => "審核中",
+        (ReservationStatus::Approved, Locale::ZhTw) => "已核准",
+        (ReservationStatus::Rejected, Locale::ZhTw) => "已拒絕",
+        (ReservationStatus::Cancelled, Locale::ZhTw) => "已取消",
+        (ReservationStatus::NeedsRebooking, Locale::ZhTw) => "需重新預約",
+        (ReservationStatus::Expired, Locale::ZhTw) => "已逾期",
+        (ReservationStatus::Completed, Locale::ZhTw) => "已完成",
+        (ReservationStatus::NoShow, Locale::ZhTw) => "未到場",
+    }
+}
+
+pub fn category_label(category: &AnnouncementCategory, locale: Locale) -> &'static str {
+    match (category, locale) {
+        (AnnouncementCategory::Maintenance, Locale::En) => "Maintenance",
+        (AnnouncementCategory::Policy, Locale::En) => "Policy",
+        (AnnouncementCategory::Event, Locale::En) => "Event",
+        (AnnouncementCategory::System, Locale::En) => "System",
+        (AnnouncementCategory::Maintenance, Locale::ZhTw) => "維護",
+        (AnnouncementCategory::Policy, Locale::ZhTw) => "規定",
+        (AnnouncementCategory::Event, Locale::ZhTw) => "活動",
+        (AnnouncementCategory::System, Locale::ZhTw) => "系統",
+    }
+}
+
+pub struct RenderedEmail {
+    pub subject: String,
+    pub body: String,
+}
+
+fn reservation_details(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> String {
+    let (room_label, time_label, purpose_label, link_label) = match locale {
+        Locale::En => ("Classroom", "Time", "Purpose", "Details"),
+        Locale::ZhTw => ("教室", "時間", "用途", "詳細資訊"),
+    };
+    let room = match classroom {
+        Some(c) => format!("{} ({})", c.name, c.location),
+        None => "-".to_string(),
+    };
+
+    let mut lines = vec![
+        format!("{}: {}", room_label, room),
+        format!(
+            "{}: {}",
+            time_label,
+            format_range(reservation.start_time, reservation.end_time, locale)
+        ),
+        format!("{}: {}", purpose_label, reservation.purpose),
+    ];
+    if let Some(link) = config.reservation_link(&reservation.id) {
+        lines.push(format!("{}: {}", link_label, link));
+    }
+    lines.join("\n")
+}
+
+/// Confirmation sent to the requester after submitting a reservation.
+pub fn reservation_created(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: "Reservation Created".to_string(),
+            body: format!(
+                "Your reservation request has been received and is waiting for review.\n\n{}",
+                details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: "預約已建立".to_string(),
+            body: format!("已收到您的預約申請，正在等待審核。\n\n{}", details),
+        },
+    }
+}
+
+/// Notification sent to admins when a new reservation needs review.
+pub fn reservation_review_requested(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!(
+                "New Reservation Request: {} ({})",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!("There is a new reservation request.\n\n{}", details),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!(
+                "新的預約申請：{}（{}）",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!("有一筆新的預約申請。\n\n{}", details),
+        },
+    }
+}
+
+/// Reminder sent to reviewers when the requester asks for a pending
+/// reservation to be looked at.
+pub fn reservation_review_reminder(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!(
+                "Reminder: Reservation Awaiting Review: {} ({})",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!(
+                "The requester is still waiting for a decision on this reservation, submitted {}.\n\n{}",
+                format_datetime(reservation.created_at, locale),
+                details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!(
+                "提醒：預約等待審核：{}（{}）",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!(
+                "申請人仍在等待此預約的審核結果，申請時間為 {}。\n\n{}",
+                format_datetime(reservation.created_at, locale),
+                details
+            ),
+        },
+    }
+}
+
+/// Sent to the other reviewers once a reservation for a room requiring two
+/// approvals got its first one.
+pub fn reservation_second_approval_requested(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!(
+                "Second Approval Needed: {} ({})",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!(
+                "This room requires approvals from two reviewers. The reservation below was approved once and needs a second approval from another reviewer before it is confirmed.\n\n{}",
+                details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!(
+                "需要第二位審核：{}（{}）",
+                room,
+                format_datetime(reservation.start_time, locale)
+            ),
+            body: format!(
+                "此教室的預約需經兩位審核人員核准。以下預約已獲得第一次核准，尚需另一位審核人員核准後才會確認。\n\n{}",
+                details
+            ),
+        },
+    }
+}
+
+/// Result of an admin review sent to the requester.
+pub fn reservation_reviewed(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    alternatives: &[AlternativeRoom],
+    locale: Locale,
+) -> RenderedEmail {
+    let mut details = reservation_details(config, reservation, classroom, locale);
+    let alternatives = alternatives_list(alternatives, locale);
+    if !alternatives.is_empty() {
+        details = format!("{}\n\n{}", details, alternatives);
+    }
+    let status = status_label(&reservation.status, locale);
+    let reason = match (&reservation.status, &reservation.reject_reason) {
+        (ReservationStatus::Rejected, Some(reason)) => match locale {
+            Locale::En => format!("\nReason: {}", reason),
+            Locale::ZhTw => format!("\n原因：{}", reason),
+        },
+        _ => String::new(),
+    };
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("Your reservation has been {}", status.to_lowercase()),
+            body: format!(
+                "Your reservation has been reviewed.\nStatus: {}{}\n\n{}",
+                status, reason, details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("您的預約{}", status),
+            body: format!(
+                "您的預約已審核。\n狀態：{}{}\n\n{}",
+                status, reason, details
+            ),
+        },
+    }
+}
+
+/// Sent to the requester once their reservation has ended, asking how it went.
+pub fn reservation_feedback_request(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    let link = config.feedback_link(&reservation.id);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("How was {}?", room),
+            body: format!(
+                "Your reservation has ended. Let us know if anything about the classroom or its equipment needs attention{}.\n\n{}",
+                link.map(|l| format!(": {}", l)).unwrap_or_default(),
+                details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("{} 使用情況如何？", room),
+            body: format!(
+                "您的預約已結束。若教室或設備有任何需要處理的問題，歡迎告訴我們{}。\n\n{}",
+                link.map(|l| format!("：{}", l)).unwrap_or_default(),
+                details
+            ),
+        },
+    }
+}
+
+/// Sent to the other side of a reservation's comment thread: reviewers
+/// when the requester comments, the requester when a reviewer does.
+pub fn reservation_commented(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    author_name: Option<&str>,
+    comment: &str,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    let author = author_name.unwrap_or("-");
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("New Comment on Reservation: {}", room),
+            body: format!("{} commented:\n\n{}\n\n{}", author, comment, details),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("預約有新留言：{}", room),
+            body: format!("{} 留言：\n\n{}\n\n{}", author, comment, details),
+        },
+    }
+}
+
+/// Alternatives as a bulleted list, or an empty string when there are none.
+pub fn alternatives_list(alternatives: &[AlternativeRoom], locale: Locale) -> String {
+    if alternatives.is_empty() {
+        return String::new();
+    }
+    let (heading, seats) = match locale {
+        Locale::En => ("Available alternatives", "seats"),
+        Locale::ZhTw => ("可改借的教室", "人"),
+    };
+    let rooms = alternatives
+        .iter()
+        .map(|room| {
+            format!(
+                "- {} ({}, {} {})",
+                room.name, room.location, room.capacity, seats
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{}:\n{}", heading, rooms)
+}
+
+/// One-time PIN for picking up the key of an approved reservation from the
+/// smart key cabinet.
+pub fn cabinet_pin_issued(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    pin: &key_cabinet_pin::Model,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    let window = format_range(pin.valid_from, pin.valid_until, locale);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: "Key Pickup PIN".to_string(),
+            body: format!(
+                "Pick up the key from slot {} of the key cabinet with PIN {}.\nThe PIN works once, between {}.\n\n{}",
+                pin.slot, pin.pin, window, details
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: "鑰匙取用密碼".to_string(),
+            body: format!(
+                "請至鑰匙櫃第 {} 格，輸入密碼 {} 取用鑰匙。\n密碼僅能使用一次，有效時間為 {}。\n\n{}",
+                pin.slot, pin.pin, window, details
+            ),
+        },
+    }
+}
+
+/// One-time code the requester shows at the counter to collect the key of an
+/// approved reservation.
+pub fn pickup_code_issued(
+    config: &EmailTemplateConfig,
+    reservation: &reservation::Model,
+    classroom: Option<&classroom::Model>,
+    key: Option<&key::Model>,
+    code: &str,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = reservation_details(config, reservation, classroom, locale);
+    match locale {
+        Locale::En => {
+            let key_line = match key {
+                Some(k) => format!("Key {} has been set aside for you.\n", k.key_number),
+                None => String::new(),
+            };
+            RenderedEmail {
+                subject: "Key Pickup Code".to_string(),
+                body: format!(
+                    "Show pickup code {} at the office to collect the classroom key.\n{}The code works once.\n\n{}",
+                    code, key_line, details
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let key_line = match key {
+                Some(k) => format!("已為您保留鑰匙 {}。\n", k.key_number),
+                None => String::new(),
+            };
+            RenderedEmail {
+                subject: "鑰匙領取碼".to_string(),
+                body: format!(
+                    "請至辦公室出示領取碼 {} 領取教室鑰匙。\n{}領取碼僅能使用一次。\n\n{}",
+                    code, key_line, details
+                ),
+            }
+        }
+    }
+}
+
+/// Reminder sent to the borrower once a key is past its return deadline.
+pub fn key_overdue(
+    log: &key_transaction_log::Model,
+    key: Option<&key::Model>,
+    classroom: Option<&classroom::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let key_number = key.map(|k| k.key_number.as_str()).unwrap_or("-");
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    let deadline = format_datetime(log.deadline, locale);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("Key {} is overdue", key_number),
+            body: format!(
+                "The key {} for {} was due back at {} (GMT+8) and has not been returned.\nPlease return it to the office as soon as possible. Keys that stay out may be recorded as an infraction.",
+                key_number, room, deadline
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("鑰匙 {} 已逾期未還", key_number),
+            body: format!(
+                "{} 的鑰匙 {} 應於 {}（GMT+8）前歸還，目前尚未歸還。\n請盡快將鑰匙歸還至辦公室，持續未歸還可能會被記錄違規。",
+                room, key_number, deadline
+            ),
+        },
+    }
+}
+
+/// Second notice sent to the borrower of a key that is still out, naming
+/// when it will be recorded as an infraction.
+pub fn key_overdue_second_notice(
+    log: &key_transaction_log::Model,
+    key: Option<&key::Model>,
+    classroom: Option<&classroom::Model>,
+    infraction_at: DateTimeWithTimeZone,
+    locale: Locale,
+) -> RenderedEmail {
+    let key_number = key.map(|k| k.key_number.as_str()).unwrap_or("-");
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    let deadline = format_datetime(log.deadline, locale);
+    let infraction_at = format_datetime(infraction_at, locale);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("Second notice: key {} is still overdue", key_number),
+            body: format!(
+                "The key {} for {} was due back at {} (GMT+8) and has still not been returned.\nIf it is not back at the office by {} (GMT+8), an infraction will be recorded and admins will be alerted.",
+                key_number, room, deadline, infraction_at
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("第二次通知：鑰匙 {} 仍未歸還", key_number),
+            body: format!(
+                "{} 的鑰匙 {} 應於 {}（GMT+8）前歸還，目前仍未歸還。\n若於 {}（GMT+8）前仍未歸還至辦公室，將記錄違規並通知管理員。",
+                room, key_number, deadline, infraction_at
+            ),
+        },
+    }
+}
+
+/// Alert sent to admins once an infraction was recorded for a key that is
+/// still not back.
+pub fn key_overdue_report(
+    log: &key_transaction_log::Model,
+    key: Option<&key::Model>,
+    classroom: Option<&classroom::Model>,
+    borrower: Option<&user::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let key_number = key.map(|k| k.key_number.as_str()).unwrap_or("-");
+    let room = classroom.map(|c| c.name.as_str()).unwrap_or("-");
+    let borrower = borrower
+        .map(|u| format!("{} ({}, {})", u.name, u.email, u.phone_number))
+        .unwrap_or_else(|| "-".to_string());
+    let borrowed_at = format_datetime(log.borrowed_at, locale);
+    let deadline = format_datetime(log.deadline, locale);
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: format!("Overdue key: {} ({})", key_number, room),
+            body: format!(
+                "A key is still not back after its return deadline and an infraction has been recorded for the borrower.\nKey: {}\nClassroom: {}\nBorrower: {}\nBorrowed at: {}\nDue: {}",
+                key_number, room, borrower, borrowed_at, deadline
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: format!("鑰匙逾期未還：{}（{}）", key_number, room),
+            body: format!(
+                "有鑰匙逾期仍未歸還，已為借用人記錄違規。\n鑰匙：{}\n教室：{}\n借用人：{}\n借出時間：{}\n歸還期限：{}",
+                key_number, room, borrower, borrowed_at, deadline
+            ),
+        },
+    }
+}
+
+/// One key on a borrow or return receipt.
+pub struct ReceiptLine<'a> {
+    pub log: &'a key_transaction_log::Model,
+    pub key: Option<&'a key::Model>,
+    pub classroom: Option<&'a classroom::Model>,
+}
+
+impl ReceiptLine<'_> {
+    fn key_and_room(&self) -> (&str, &str) {
+        (
+            self.key.map(|k| k.key_number.as_str()).unwrap_or("-"),
+            self.classroom.map(|c| c.name.as_str()).unwrap_or("-"),
+        )
+    }
+}
+
+fn handler_name(handled_by: Option<&user::Model>) -> &str {
+    handled_by.map(|u| u.name.as_str()).unwrap_or("-")
+}
+
+/// Receipt sent to the borrower when keys are handed out, kept as a record
+/// of the agreed deadline.
+pub fn key_borrow_receipt(
+    lines: &[ReceiptLine],
+    handled_by: Option<&user::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let handler = handler_name(handled_by);
+    match locale {
+        Locale::En => {
+            let items: Vec<String> = lines
+                .iter()
+                .map(|line| {
+                    let (key_number, room) = line.key_and_room();
+                    format!(
+                        "- Key {} ({}): borrowed {}, due back {} (GMT+8) [ref {}]",
+                        key_number,
+                        room,
+                        format_datetime(line.log.borrowed_at, locale),
+                        format_datetime(line.log.deadline, locale),
+                        line.log.id
+                    )
+                })
+                .collect();
+            RenderedEmail {
+                subject: "Key Borrow Receipt".to_string(),
+                body: format!(
+                    "You have borrowed the following keys:\n{}\nHanded out by: {}\n\nPlease keep this receipt in case of questions about the return.",
+                    items.join("\n"),
+                    handler
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let items: Vec<String> = lines
+                .iter()
+                .map(|line| {
+                    let (key_number, room) = line.key_and_room();
+                    format!(
+                        "- 鑰匙 {}（{}）：借出 {}，應於 {}（GMT+8）前歸還 [編號 {}]",
+                        key_number,
+                        room,
+                        format_datetime(line.log.borrowed_at, locale),
+                        format_datetime(line.log.deadline, locale),
+                        line.log.id
+                    )
+                })
+                .collect();
+            RenderedEmail {
+                subject: "鑰匙借用收據".to_string(),
+                body: format!(
+                    "您已借用以下鑰匙：\n{}\n經手人員：{}\n\n如對歸還有疑問，請保留此收據。",
+                    items.join("\n"),
+                    handler
+                ),
+            }
+        }
+    }
+}
+
+/// Receipt sent to the borrower when keys come back, recording the return
+/// time and whether it was on time.
+pub fn key_return_receipt(
+    lines: &[ReceiptLine],
+    handled_by: Option<&user::Model>,
+    locale: Locale,
+) -> RenderedEmail {
+    let handler = handler_name(handled_by);
+    match locale {
+        Locale::En => {
+            let items: Vec<String> = lines
+                .iter()
+                .map(|line| {
+                    let (key_number, room) = line.key_and_room();
+                    let returned_at = line
+                        .log
+                        .returned_at
+                        .map(|t| format_datetime(t, locale))
+                        .unwrap_or_else(|| "-".to_string());
+                    format!(
+                        "- Key {} ({}): returned {} (GMT+8), {} [ref {}]",
+                        key_number,
+                        room,
+                        returned_at,
+                        if line.log.on_time { "on time" } else { "late" },
+                        line.log.id
+                    )
+                })
+                .collect();
+            RenderedEmail {
+                subject: "Key Return Receipt".to_string(),
+                body: format!(
+                    "The following keys have been returned:\n{}\nReceived by: {}\n\nPlease keep this receipt in case of questions about the return.",
+                    items.join("\n"),
+                    handler
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let items: Vec<String> = lines
+                .iter()
+                .map(|line| {
+                    let (key_number, room) = line.key_and_room();
+                    let returned_at = line
+                        .log
+                        .returned_at
+                        .map(|t| format_datetime(t, locale))
+                        .unwrap_or_else(|| "-".to_string());
+                    format!(
+                        "- 鑰匙 {}（{}）：{}（GMT+8）歸還，{} [編號 {}]",
+                        key_number,
+                        room,
+                        returned_at,
+                        if line.log.on_time { "準時" } else { "逾期" },
+                        line.log.id
+                    )
+                })
+                .collect();
+            RenderedEmail {
+                subject: "鑰匙歸還收據".to_string(),
+                body: format!(
+                    "以下鑰匙已歸還：\n{}\n收件人員：{}\n\n如對歸還有疑問，請保留此收據。",
+                    items.join("\n"),
+                    handler
+                ),
+            }
+        }
+    }
+}
+
+/// Single notice covering every reservation of one requester that was hit by
+/// a closure or a classroom going out of service.
+pub fn reservations_affected(
+    config: &EmailTemplateConfig,
+    affected: &[(AffectedReservation, Option<classroom::Model>)],
+    action: ClosureAction,
+    reason: &str,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = affected
+        .iter()
+        .map(|(item, classroom)| {
+            let details =
+                reservation_details(config, &item.reservation, classroom.as_ref(), locale);
+            let alternatives = alternatives_list(&item.alternatives, locale);
+            if alternatives.is_empty() {
+                details
+            } else {
+                format!("{}\n{}", details, alternatives)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let count = affected.len();
+    match locale {
+        Locale::En => {
+            let (subject, outcome) = match action {
+                ClosureAction::Flag => (
+                    format!("{} of your reservations need attention", count),
+                    "They have been flagged for review and an administrator will follow up.",
+                ),
+                ClosureAction::Cancel => (
+                    format!("{} of your reservations have been cancelled", count),
+                    "They have been cancelled.",
+                ),
+                ClosureAction::Rebook => (
+                    format!("{} of your reservations need rebooking", count),
+                    "Please book another classroom; free rooms for the same time are listed where available.",
+                ),
+            };
+            RenderedEmail {
+                subject,
+                body: format!(
+                    "The classroom for the reservations below is unavailable.\nReason: {}\n\n{}\n\n{}",
+                    reason, outcome, details
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let (subject, outcome) = match action {
+                ClosureAction::Flag => (
+                    format!("您有 {} 筆預約需要留意", count),
+                    "這些預約已標記為待複審，管理員將與您聯繫。",
+                ),
+                ClosureAction::Cancel => {
+                    (format!("您有 {} 筆預約已取消", count), "這些預約已被取消。")
+                }
+                ClosureAction::Rebook => (
+                    format!("您有 {} 筆預約需要重新預約", count),
+                    "請改借其他教室，若有同時段可用的教室已列於下方。",
+                ),
+            };
+            RenderedEmail {
+                subject,
+                body: format!(
+                    "以下預約的教室目前無法使用。\n原因：{}\n\n{}\n\n{}",
+                    reason, outcome, details
+                ),
+            }
+        }
+    }
+}
+
+/// Regular announcement sent to users who have not muted its category.
+pub fn announcement_published(announcement: &announcement::Model, locale: Locale) -> RenderedEmail {
+    RenderedEmail {
+        subject: format!(
+            "[{}] {}",
+            category_label(&announcement.category, locale),
+            announcement.title
+        ),
+        body: announcement.content.clone(),
+    }
+}
+
+/// Emergency announcement, listing the recipient's reservations for the day so
+/// they know which ones are affected.
+pub fn emergency_announcement(
+    config: &EmailTemplateConfig,
+    announcement: &announcement::Model,
+    todays_reservations: &[(reservation::Model, Option<classroom::Model>)],
+    locale: Locale,
+) -> RenderedEmail {
+    let details = todays_reservations
+        .iter()
+        .map(|(reservation, classroom)| {
+            reservation_details(config, reservation, classroom.as_ref(), locale)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    match locale {
+        Locale::En => {
+            let affected = if todays_reservations.is_empty() {
+                String::new()
+            } else {
+                format!("\n\nYour reservations today:\n\n{}", details)
+            };
+            RenderedEmail {
+                subject: format!("[URGENT] {}", announcement.title),
+                body: format!("{}{}", announcement.content, affected),
+            }
+        }
+        Locale::ZhTw => {
+            let affected = if todays_reservations.is_empty() {
+                String::new()
+            } else {
+                format!("\n\n您今天的預約：\n\n{}", details)
+            };
+            RenderedEmail {
+                subject: format!("【緊急】{}", announcement.title),
+                body: format!("{}{}", announcement.content, affected),
+            }
+        }
+    }
+}
+
+/// Invitation for a staff member to finish registering an account that has
+/// its role and department already assigned.
+pub fn staff_invite(
+    config: &EmailTemplateConfig,
+    token: &str,
+    role: &Role,
+    department: &str,
+    expires_at: DateTimeWithTimeZone,
+    locale: Locale,
+) -> RenderedEmail {
+    let expires = format_datetime(expires_at, locale);
+    match locale {
+        Locale::En => {
+            let role = match role {
+                Role::Admin => "administrator",
+                Role::Assistant => "department assistant",
+                Role::User => "staff member",
+            };
+            let action = match config.invite_link(token) {
+                Some(link) => format!("Complete your registration here: {}", link),
+                None => format!(
+                    "Complete your registration with this invitation code: {}",
+                    token
+                ),
+            };
+            RenderedEmail {
+                subject: "Invitation to the Classroom Borrowing System".to_string(),
+                body: format!(
+                    "You have been invited to join the Classroom Borrowing System as {} of {}.\n{}\n\nThe invitation works once and expires at {} (GMT+8).",
+                    role, department, action, expires
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let role = match role {
+                Role::Admin => "管理員",
+                Role::Assistant => "系所助理",
+                Role::User => "職員",
+            };
+            let action = match config.invite_link(token) {
+                Some(link) => format!("請由此完成註冊：{}", link),
+                None => format!("請使用此邀請碼完成註冊：{}", token),
+            };
+            RenderedEmail {
+                subject: "教室借用系統邀請".to_string(),
+                body: format!(
+                    "您受邀以{}{}的身分加入教室借用系統。\n{}\n\n此邀請僅能使用一次，將於 {}（GMT+8）失效。",
+                    department, role, action, expires
+                ),
+            }
+        }
+    }
+}
+
+/// Sent when repeated failed logins lock an account, with the link or code
+/// that unlocks it.
+pub fn account_locked(
+    config: &EmailTemplateConfig,
+    token: &str,
+    expires_at: DateTimeWithTimeZone,
+    locale: Locale,
+) -> RenderedEmail {
+    let expires = format_datetime(expires_at, locale);
+    match locale {
+        Locale::En => {
+            let action = match config.unlock_link(token) {
+                Some(link) => format!("Unlock your account here: {}", link),
+                None => format!("Unlock your account with this code: {}", token),
+            };
+            RenderedEmail {
+                subject: "Your account has been locked".to_string(),
+                body: format!(
+                    "Your Classroom Borrowing System account was locked after too many failed login attempts.\n{}\n\nThe link works once and expires at {} (GMT+8). If you did not try to log in, reset your password after unlocking.",
+                    action, expires
+                ),
+            }
+        }
+        Locale::ZhTw => {
+            let action = match config.unlock_link(token) {
+                Some(link) => format!("請由此解除鎖定：{}", link),
+                None => format!("請使用此代碼解除鎖定：{}", token),
+            };
+            RenderedEmail {
+                subject: "您的帳號已被鎖定".to_string(),
+                body: format!(
+                    "由於登入失敗次數過多，您的教室借用系統帳號已被鎖定。\n{}\n\n此連結僅能使用一次，將於 {}（GMT+8）失效。若登入嘗試並非您本人所為，請於解除鎖定後重設密碼。",
+                    action, expires
+                ),
+            }
+        }
+    }
+}
+
+/// Sent to both sides when an admin hands a user's upcoming reservations to
+/// someone else. `received` is true for the new owner.
+pub fn reservations_transferred(
+    config: &EmailTemplateConfig,
+    transferred: &[(reservation::Model, Option<classroom::Model>)],
+    counterpart: &user::Model,
+    received: bool,
+    locale: Locale,
+) -> RenderedEmail {
+    let details = transferred
+        .iter()
+        .map(|(reservation, classroom)| {
+            reservation_details(config, reservation, classroom.as_ref(), locale)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let count = transferred.len();
+    match (locale, received) {
+        (Locale::En, true) => RenderedEmail {
+            subject: format!("{} reservations have been transferred to you", count),
+            body: format!(
+                "An administrator transferred the reservations below from {} to you. They keep their current status.\n\n{}",
+                counterpart.name, details
+            ),
+        },
+        (Locale::En, false) => RenderedEmail {
+            subject: format!("{} of your reservations have been transferred", count),
+            body: format!(
+                "An administrator transferred the reservations below to {}. You no longer hold them.\n\n{}",
+                counterpart.name, details
+            ),
+        },
+        (Locale::ZhTw, true) => RenderedEmail {
+            subject: format!("已有 {} 筆預約轉移給您", count),
+            body: format!(
+                "管理員已將以下預約由 {} 轉移給您，預約狀態維持不變。\n\n{}",
+                counterpart.name, details
+            ),
+        },
+        (Locale::ZhTw, false) => RenderedEmail {
+            subject: format!("您有 {} 筆預約已被轉移", count),
+            body: format!(
+                "管理員已將以下預約轉移給 {}，您已不再持有這些預約。\n\n{}",
+                counterpart.name, details
+            ),
+        },
+    }
+}
+
+/// Confirmation sent to the old address once an account has been deleted.
+pub fn account_deleted(cancelled_reservations: u64, locale: Locale) -> RenderedEmail {
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: "Your account has been deleted".to_string(),
+            body: format!(
+                "Your Classroom Borrowing System account has been deleted and your personal information removed. {} upcoming reservations were cancelled.\n\nIf you did not request this, contact an administrator.",
+                cancelled_reservations
+            ),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: "您的帳號已刪除".to_string(),
+            body: format!(
+                "您的教室借用系統帳號已刪除，個人資料已移除，並已取消 {} 筆尚未開始的預約。\n\n若此操作並非您本人申請，請聯繫管理員。",
+                cancelled_reservations
+            ),
+        },
+    }
+}
+
+pub fn black_list_ended(locale: Locale) -> RenderedEmail {
+    match locale {
+        Locale::En => RenderedEmail {
+            subject: "You can book classrooms again".to_string(),
+            body: "Your suspension from the Classroom Borrowing System has ended. You can reserve classrooms and borrow keys again.".to_string(),
+        },
+        Locale::ZhTw => RenderedEmail {
+            subject: "您已可重新借用教室".to_string(),
+            body: "您在教室借用系統的停權期間已結束，現在可以重新預約教室及借用鑰匙。".to_string(),
+        },
+    }
+}
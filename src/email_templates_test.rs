@@ -0,0 +1,116 @@
+#[cfg(test)]
+mod tests {
+    use super::super::email_templates::{
+        Locale, ReceiptLine, format_datetime, format_range, key_borrow_receipt, key_return_receipt,
+    };
+    use super::super::entities::{key, key_transaction_log, sea_orm_active_enums::KeyStatus};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_locale_from_tag() {
+        assert_eq!(Locale::from_tag("en"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("en-US"), Some(Locale::En));
+        assert_eq!(Locale::from_tag("zh-TW"), Some(Locale::ZhTw));
+        assert_eq!(Locale::from_tag("zh_tw"), Some(Locale::ZhTw));
+        assert_eq!(Locale::from_tag("fr"), None);
+    }
+
+    #[test]
+    fn test_format_datetime_in_campus_timezone() {
+        // 00:30Z is 08:30 in Taipei
+        let value = dt("2025-01-06T00:30:00Z");
+        assert_eq!(format_datetime(value, Locale::En), "Mon, 6 Jan 2025 08:30");
+        assert_eq!(
+            format_datetime(value, Locale::ZhTw),
+            "2025年1月6日（一）08:30"
+        );
+    }
+
+    #[test]
+    fn test_format_range_same_day() {
+        let start = dt("2025-01-06T08:00:00+08:00");
+        let end = dt("2025-01-06T10:00:00+08:00");
+        assert_eq!(
+            format_range(start, end, Locale::En),
+            "Mon, 6 Jan 2025 08:00 – 10:00 (GMT+8)"
+        );
+    }
+
+    #[test]
+    fn test_format_range_across_days() {
+        let start = dt("2025-01-06T22:00:00+08:00");
+        let end = dt("2025-01-07T01:00:00+08:00");
+        assert_eq!(
+            format_range(start, end, Locale::En),
+            "Mon, 6 Jan 2025 22:00 – Tue, 7 Jan 2025 01:00 (GMT+8)"
+        );
+    }
+
+    fn borrow_log() -> key_transaction_log::Model {
+        key_transaction_log::Model {
+            id: "log-1".to_string(),
+            reservation_id: Some("r1".to_string()),
+            key_id: Some("k1".to_string()),
+            borrowed_to: Some("u1".to_string()),
+            handled_by: Some("a1".to_string()),
+            borrowed_at: dt("2025-01-06T09:55:00+08:00"),
+            returned_at: None,
+            on_time: false,
+            created_at: dt("2025-01-06T09:55:00+08:00"),
+            deadline: dt("2025-01-06T12:00:00+08:00"),
+            overdue_at: None,
+            second_notice_at: None,
+            escalated_at: None,
+            return_requested_at: None,
+            borrow_id: None,
+        }
+    }
+
+    fn key_model() -> key::Model {
+        key::Model {
+            id: "k1".to_string(),
+            classroom_id: Some("room-1".to_string()),
+            key_number: "A101-1".to_string(),
+            status: KeyStatus::Borrowed,
+            cabinet_slot: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_borrow_receipt_lists_deadline_and_reference() {
+        let log = borrow_log();
+        let key = key_model();
+        let lines = [ReceiptLine {
+            log: &log,
+            key: Some(&key),
+            classroom: None,
+        }];
+        let email = key_borrow_receipt(&lines, None, Locale::En);
+        assert!(email.body.contains(
+            "- Key A101-1 (-): borrowed Mon, 6 Jan 2025 09:55, due back Mon, 6 Jan 2025 12:00 (GMT+8) [ref log-1]"
+        ));
+        assert!(email.body.contains("Handed out by: -"));
+    }
+
+    #[test]
+    fn test_return_receipt_marks_late_returns() {
+        let mut log = borrow_log();
+        log.returned_at = Some(dt("2025-01-06T12:30:00+08:00"));
+        let lines = [ReceiptLine {
+            log: &log,
+            key: None,
+            classroom: None,
+        }];
+        let email = key_return_receipt(&lines, None, Locale::En);
+        assert!(
+            email
+                .body
+                .contains("- Key - (-): returned Mon, 6 Jan 2025 12:30 (GMT+8), late [ref log-1]")
+        );
+    }
+}
@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
+use super::sea_orm_active_enums::{AnnouncementAudience, AnnouncementCategory};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -13,13 +14,39 @@ pub struct Model {
     pub title: String,
     #[sea_orm(column_type = "Text")]
     pub content: String,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub published_at: DateTimeWithTimeZone,
     pub created_by: Option<String>,
+    pub emergency: bool,
+    pub pinned: bool,
+    pub category: AnnouncementCategory,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub title_en: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub title_zh_tw: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content_en: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub content_zh_tw: Option<String>,
+    pub audience: AnnouncementAudience,
+    /// Classroom whose users an announcement for `classroom_users` is for
+    pub audience_classroom_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::announcement_edit::Entity")]
+    AnnouncementEdit,
+    #[sea_orm(
+        belongs_to = "super::classroom::Entity",
+        from = "Column::AudienceClassroomId",
+        to = "super::classroom::Column::Id",
+        on_update = "NoAction",
+        on_delete = "Cascade"
+    )]
+    Classroom,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::CreatedBy",
@@ -30,6 +57,18 @@ pub enum Relation {
     User,
 }
 
+impl Related<super::announcement_edit::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AnnouncementEdit.def()
+    }
+}
+
+impl Related<super::classroom::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Classroom.def()
+    }
+}
+
 impl Related<super::user::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::User.def()
@@ -12,10 +12,16 @@ pub struct Model {
     pub user_id: Option<String>,
     pub infraction_id: Option<String>,
     pub created_by: Option<String>,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
-    #[schema(value_type = Option<String>)]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
     pub end_at: Option<DateTimeWithTimeZone>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub end_notified_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
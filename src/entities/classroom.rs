@@ -18,22 +18,111 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub description: String,
     pub status: ClassroomStatus,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub updated_at: DateTimeWithTimeZone,
     #[sea_orm(column_type = "Text")]
     pub photo_id: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub status_reason: Option<String>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub status_from: Option<DateTimeWithTimeZone>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub status_until: Option<DateTimeWithTimeZone>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub name_en: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub name_zh_tw: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description_en: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub description_zh_tw: Option<String>,
+    /// Reservations need approvals from two different reviewers, e.g. for
+    /// large auditoriums
+    #[serde(default)]
+    pub requires_double_approval: bool,
+    /// Bumped by every edit; sent back with the next edit, it has that edit
+    /// refused with 409 when someone else saved in between. History
+    /// snapshots taken before versions existed have none
+    #[sea_orm(default_value = 1)]
+    #[serde(default)]
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::announcement::Entity")]
+    Announcement,
+    #[sea_orm(has_many = "super::classroom_assistant::Entity")]
+    ClassroomAssistant,
+    #[sea_orm(has_many = "super::classroom_closure::Entity")]
+    ClassroomClosure,
+    #[sea_orm(has_many = "super::classroom_history::Entity")]
+    ClassroomHistory,
+    #[sea_orm(has_many = "super::classroom_schedule::Entity")]
+    ClassroomSchedule,
+    #[sea_orm(has_many = "super::course_session::Entity")]
+    CourseSession,
+    #[sea_orm(has_many = "super::door_event::Entity")]
+    DoorEvent,
     #[sea_orm(has_many = "super::key::Entity")]
     Key,
     #[sea_orm(has_many = "super::reservation::Entity")]
     Reservation,
 }
 
+impl Related<super::announcement::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Announcement.def()
+    }
+}
+
+impl Related<super::classroom_assistant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClassroomAssistant.def()
+    }
+}
+
+impl Related<super::classroom_closure::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClassroomClosure.def()
+    }
+}
+
+impl Related<super::classroom_history::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClassroomHistory.def()
+    }
+}
+
+impl Related<super::classroom_schedule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClassroomSchedule.def()
+    }
+}
+
+impl Related<super::course_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CourseSession.def()
+    }
+}
+
+impl Related<super::door_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DoorEvent.def()
+    }
+}
+
 impl Related<super::key::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Key.def()
@@ -14,7 +14,9 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub description: String,
     pub created_by: Option<String>,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
 }
 
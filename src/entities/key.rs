@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
+use super::sea_orm_active_enums::KeyStatus;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
@@ -12,7 +13,14 @@ pub struct Model {
     pub classroom_id: Option<String>,
     #[sea_orm(column_type = "Text", unique)]
     pub key_number: String,
-    pub is_active: bool,
+    pub status: KeyStatus,
+    /// Slot in the smart key cabinet this key is stored in, if any
+    #[sea_orm(column_type = "Text", nullable)]
+    pub cabinet_slot: Option<String>,
+    /// Bumped by every edit; sent back with the next edit, it has that edit
+    /// refused with 409 when someone else saved in between
+    #[sea_orm(default_value = 1)]
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -25,6 +33,10 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     Classroom,
+    #[sea_orm(has_many = "super::key_cabinet_pin::Entity")]
+    KeyCabinetPin,
+    #[sea_orm(has_many = "super::key_pickup_code::Entity")]
+    KeyPickupCode,
     #[sea_orm(has_many = "super::key_transaction_log::Entity")]
     KeyTransactionLog,
 }
@@ -35,6 +47,18 @@ impl Related<super::classroom::Entity> for Entity {
     }
 }
 
+impl Related<super::key_cabinet_pin::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyCabinetPin.def()
+    }
+}
+
+impl Related<super::key_pickup_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyPickupCode.def()
+    }
+}
+
 impl Related<super::key_transaction_log::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::KeyTransactionLog.def()
@@ -13,15 +13,41 @@ pub struct Model {
     pub key_id: Option<String>,
     pub borrowed_to: Option<String>,
     pub handled_by: Option<String>,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub borrowed_at: DateTimeWithTimeZone,
-    #[schema(value_type = Option<String>)]
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
     pub returned_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(default_value = true)]
     pub on_time: bool,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub deadline: DateTimeWithTimeZone,
+    /// When the overdue job first noticed the key was not back in time
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub overdue_at: Option<DateTimeWithTimeZone>,
+    /// When the overdue job sent the borrower a second notice
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub second_notice_at: Option<DateTimeWithTimeZone>,
+    /// When the overdue job recorded an infraction for this borrow and
+    /// alerted admins
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub escalated_at: Option<DateTimeWithTimeZone>,
+    /// When the borrower said they handed the key back; staff confirm the
+    /// return by closing the log
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub return_requested_at: Option<DateTimeWithTimeZone>,
+    /// Parent borrow when several keys were handed out together
+    pub borrow_id: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -34,6 +60,14 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Key,
+    #[sea_orm(
+        belongs_to = "super::key_borrow::Entity",
+        from = "Column::BorrowId",
+        to = "super::key_borrow::Column::Id",
+        on_update = "NoAction",
+        on_delete = "SetNull"
+    )]
+    KeyBorrow,
     #[sea_orm(
         belongs_to = "super::reservation::Entity",
         from = "Column::ReservationId",
@@ -58,6 +92,8 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     User1,
+    #[sea_orm(has_many = "super::key_sync_action::Entity")]
+    KeySyncAction,
 }
 
 impl Related<super::key::Entity> for Entity {
@@ -66,6 +102,18 @@ impl Related<super::key::Entity> for Entity {
     }
 }
 
+impl Related<super::key_borrow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyBorrow.def()
+    }
+}
+
+impl Related<super::key_sync_action::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeySyncAction.def()
+    }
+}
+
 impl Related<super::reservation::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Reservation.def()
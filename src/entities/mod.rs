@@ -3,11 +3,29 @@
 pub mod prelude;
 
 pub mod announcement;
+pub mod announcement_edit;
+pub mod announcement_mute;
+pub mod audit_log;
 pub mod black_list;
 pub mod classroom;
+pub mod classroom_assistant;
+pub mod classroom_closure;
+pub mod classroom_history;
+pub mod classroom_schedule;
+pub mod course_session;
+pub mod domain_event;
+pub mod door_event;
 pub mod infraction;
 pub mod key;
+pub mod key_borrow;
+pub mod key_cabinet_pin;
+pub mod key_pickup_code;
+pub mod key_sync_action;
 pub mod key_transaction_log;
 pub mod reservation;
+pub mod reservation_approval;
+pub mod reservation_attachment;
+pub mod reservation_comment;
+pub mod reservation_note;
 pub mod sea_orm_active_enums;
 pub mod user;
@@ -1,10 +1,28 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.17
 
 pub use super::announcement::Entity as Announcement;
+pub use super::announcement_edit::Entity as AnnouncementEdit;
+pub use super::announcement_mute::Entity as AnnouncementMute;
+pub use super::audit_log::Entity as AuditLog;
 pub use super::black_list::Entity as BlackList;
 pub use super::classroom::Entity as Classroom;
+pub use super::classroom_assistant::Entity as ClassroomAssistant;
+pub use super::classroom_closure::Entity as ClassroomClosure;
+pub use super::classroom_history::Entity as ClassroomHistory;
+pub use super::classroom_schedule::Entity as ClassroomSchedule;
+pub use super::course_session::Entity as CourseSession;
+pub use super::domain_event::Entity as DomainEvent;
+pub use super::door_event::Entity as DoorEvent;
 pub use super::infraction::Entity as Infraction;
 pub use super::key::Entity as Key;
+pub use super::key_borrow::Entity as KeyBorrow;
+pub use super::key_cabinet_pin::Entity as KeyCabinetPin;
+pub use super::key_pickup_code::Entity as KeyPickupCode;
+pub use super::key_sync_action::Entity as KeySyncAction;
 pub use super::key_transaction_log::Entity as KeyTransactionLog;
 pub use super::reservation::Entity as Reservation;
+pub use super::reservation_approval::Entity as ReservationApproval;
+pub use super::reservation_attachment::Entity as ReservationAttachment;
+pub use super::reservation_comment::Entity as ReservationComment;
+pub use super::reservation_note::Entity as ReservationNote;
 pub use super::user::Entity as User;
@@ -14,7 +14,8 @@ pub struct Model {
     pub classroom_id: Option<String>,
     #[sea_orm(column_type = "Text")]
     pub purpose: String,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub start_time: DateTimeWithTimeZone,
     pub approved_by: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
@@ -22,8 +23,40 @@ pub struct Model {
     #[sea_orm(column_type = "Text", nullable)]
     pub cancel_reason: Option<String>,
     pub status: ReservationStatus,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub end_time: DateTimeWithTimeZone,
+    pub flagged_for_review: bool,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub flag_reason: Option<String>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub checked_in_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+    /// When an admin first approved or rejected the request; `approved_by`
+    /// holds that admin
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub reviewed_at: Option<DateTimeWithTimeZone>,
+    /// Event the room is booked for, used to spot several bookings for the
+    /// same event
+    #[sea_orm(column_type = "Text", nullable)]
+    pub event_name: Option<String>,
+    /// Bumped by every edit; sent back with the next edit, it has that edit
+    /// refused with 409 when someone else saved in between
+    #[sea_orm(default_value = 1)]
+    pub version: i32,
+    /// Shared by reservations of several rooms requested together for the
+    /// same slot; they are reviewed and cancelled together
+    pub group_id: Option<String>,
+    /// Who a reservation staff made for someone without an account is for
+    #[sea_orm(column_type = "Text", nullable)]
+    pub guest_name: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub guest_email: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -36,10 +69,26 @@ pub enum Relation {
         on_delete = "SetNull"
     )]
     Classroom,
+    #[sea_orm(has_many = "super::door_event::Entity")]
+    DoorEvent,
     #[sea_orm(has_many = "super::infraction::Entity")]
     Infraction,
+    #[sea_orm(has_many = "super::key_borrow::Entity")]
+    KeyBorrow,
+    #[sea_orm(has_many = "super::key_cabinet_pin::Entity")]
+    KeyCabinetPin,
+    #[sea_orm(has_many = "super::key_pickup_code::Entity")]
+    KeyPickupCode,
     #[sea_orm(has_many = "super::key_transaction_log::Entity")]
     KeyTransactionLog,
+    #[sea_orm(has_many = "super::reservation_approval::Entity")]
+    ReservationApproval,
+    #[sea_orm(has_many = "super::reservation_attachment::Entity")]
+    ReservationAttachment,
+    #[sea_orm(has_many = "super::reservation_comment::Entity")]
+    ReservationComment,
+    #[sea_orm(has_many = "super::reservation_note::Entity")]
+    ReservationNote,
     #[sea_orm(
         belongs_to = "super::user::Entity",
         from = "Column::ApprovedBy",
@@ -64,16 +113,64 @@ impl Related<super::classroom::Entity> for Entity {
     }
 }
 
+impl Related<super::door_event::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DoorEvent.def()
+    }
+}
+
 impl Related<super::infraction::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Infraction.def()
     }
 }
 
+impl Related<super::key_borrow::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyBorrow.def()
+    }
+}
+
+impl Related<super::key_cabinet_pin::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyCabinetPin.def()
+    }
+}
+
+impl Related<super::key_pickup_code::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::KeyPickupCode.def()
+    }
+}
+
 impl Related<super::key_transaction_log::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::KeyTransactionLog.def()
     }
 }
 
+impl Related<super::reservation_approval::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReservationApproval.def()
+    }
+}
+
+impl Related<super::reservation_attachment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReservationAttachment.def()
+    }
+}
+
+impl Related<super::reservation_comment::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReservationComment.def()
+    }
+}
+
+impl Related<super::reservation_note::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ReservationNote.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
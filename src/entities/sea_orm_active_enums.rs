@@ -4,6 +4,56 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "AnnouncementAudience"
+)]
+pub enum AnnouncementAudience {
+    #[sea_orm(string_value = "all")]
+    All,
+    #[sea_orm(string_value = "admins")]
+    Admins,
+    #[sea_orm(string_value = "blacklisted")]
+    Blacklisted,
+    #[sea_orm(string_value = "classroom_users")]
+    ClassroomUsers,
+}
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "AnnouncementCategory"
+)]
+pub enum AnnouncementCategory {
+    #[sea_orm(string_value = "maintenance")]
+    Maintenance,
+    #[sea_orm(string_value = "policy")]
+    Policy,
+    #[sea_orm(string_value = "event")]
+    Event,
+    #[sea_orm(string_value = "system")]
+    System,
+}
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "CabinetPinStatus")]
+pub enum CabinetPinStatus {
+    #[sea_orm(string_value = "issued")]
+    Issued,
+    #[sea_orm(string_value = "picked_up")]
+    PickedUp,
+    #[sea_orm(string_value = "revoked")]
+    Revoked,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
 #[derive(
     Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
 )]
@@ -15,6 +65,36 @@ pub enum ClassroomStatus {
     Occupied,
     #[sea_orm(string_value = "maintenance")]
     Maintenance,
+    #[sea_orm(string_value = "unavailable")]
+    Unavailable,
+}
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "DomainEventStatus")]
+pub enum DomainEventStatus {
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    #[sea_orm(string_value = "dispatched")]
+    Dispatched,
+    #[sea_orm(string_value = "failed")]
+    Failed,
+}
+#[derive(
+    Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "KeyStatus")]
+pub enum KeyStatus {
+    #[sea_orm(string_value = "active")]
+    Active,
+    #[sea_orm(string_value = "inactive")]
+    Inactive,
+    #[sea_orm(string_value = "borrowed")]
+    Borrowed,
+    #[sea_orm(string_value = "lost")]
+    Lost,
+    #[sea_orm(string_value = "retired")]
+    Retired,
 }
 #[derive(
     Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
@@ -27,6 +107,16 @@ pub enum ReservationStatus {
     Approved,
     #[sea_orm(string_value = "rejected")]
     Rejected,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+    #[sea_orm(string_value = "needs_rebooking")]
+    NeedsRebooking,
+    #[sea_orm(string_value = "expired")]
+    Expired,
+    #[sea_orm(string_value = "completed")]
+    Completed,
+    #[sea_orm(string_value = "no_show")]
+    NoShow,
 }
 #[derive(
     Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, ToSchema,
@@ -36,6 +126,8 @@ pub enum ReservationStatus {
 pub enum Role {
     #[sea_orm(string_value = "admin")]
     Admin,
+    #[sea_orm(string_value = "assistant")]
+    Assistant,
     #[sea_orm(string_value = "user")]
     User,
 }
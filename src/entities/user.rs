@@ -21,16 +21,41 @@ pub struct Model {
     #[sea_orm(column_type = "Text")]
     pub phone_number: String,
     pub role: Role,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
-    #[schema(value_type = String)]
+    #[sea_orm(default_expr = "Expr::current_timestamp()")]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub updated_at: DateTimeWithTimeZone,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub locale: Option<String>,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub email_undeliverable_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub email_undeliverable_reason: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub department: Option<String>,
+    #[sea_orm(column_type = "Text", nullable, unique)]
+    pub student_id: Option<String>,
+    /// Set once the account was deleted and its personal fields anonymized
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    /// Bumped by every edit; sent back with the next edit, it has that edit
+    /// refused with 409 when someone else saved in between
+    #[sea_orm(default_value = 1)]
+    pub version: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::announcement::Entity")]
     Announcement,
+    #[sea_orm(has_many = "super::announcement_mute::Entity")]
+    AnnouncementMute,
 }
 
 impl Related<super::announcement::Entity> for Entity {
@@ -39,4 +64,10 @@ impl Related<super::announcement::Entity> for Entity {
     }
 }
 
+impl Related<super::announcement_mute::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AnnouncementMute.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,222 @@
+use axum::{
+    Json,
+    body::to_bytes,
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::request_id;
+
+/// Error returned by handlers. Every error response carries an
+/// [`ErrorResponse`] body, so clients can tell errors apart by `code` instead
+/// of matching on messages.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppError {
+    BadRequest(String),
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    Gone(String),
+    Locked(String),
+    Unprocessable(String),
+    TooManyRequests {
+        message: String,
+        /// Seconds until trying again makes sense, sent as `Retry-After`
+        retry_after: Option<u64>,
+    },
+    Internal(String),
+    BadGateway(String),
+    ServiceUnavailable(String),
+    /// Errors raised outside the handlers, e.g. 405 or 415 from axum
+    Other(StatusCode, String),
+    /// Any of the others with machine-readable context
+    Detailed(Box<AppError>, Value),
+}
+
+/// Body of every error response.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct ErrorResponse {
+    /// Stable identifier of the kind of error, e.g. `not_found`
+    pub code: String,
+    /// Human-readable description, which may change between releases
+    pub message: String,
+    /// Context some errors add, e.g. which field conflicts
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+    /// Id of the failed request, also sent as `x-request-id`; worth quoting
+    /// when reporting the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl AppError {
+    /// Attaches `details` to the error.
+    pub fn with_details(self, details: impl Serialize) -> Self {
+        let base = match self {
+            Self::Detailed(error, _) => *error,
+            error => error,
+        };
+        Self::Detailed(
+            Box::new(base),
+            serde_json::to_value(details).unwrap_or(Value::Null),
+        )
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::BadRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::Gone(_) => StatusCode::GONE,
+            Self::Locked(_) => StatusCode::LOCKED,
+            Self::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::BadGateway(_) => StatusCode::BAD_GATEWAY,
+            Self::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Other(status, _) => *status,
+            Self::Detailed(error, _) => error.status(),
+        }
+    }
+
+    /// The variant matching `status`, so errors from outside the handlers
+    /// get the same `code` a handler would have used.
+    pub fn from_status(status: StatusCode, message: String) -> Self {
+        match status {
+            StatusCode::BAD_REQUEST => Self::BadRequest(message),
+            StatusCode::UNAUTHORIZED => Self::Unauthorized(message),
+            StatusCode::FORBIDDEN => Self::Forbidden(message),
+            StatusCode::NOT_FOUND => Self::NotFound(message),
+            StatusCode::CONFLICT => Self::Conflict(message),
+            StatusCode::GONE => Self::Gone(message),
+            StatusCode::LOCKED => Self::Locked(message),
+            StatusCode::UNPROCESSABLE_ENTITY => Self::Unprocessable(message),
+            StatusCode::TOO_MANY_REQUESTS => Self::TooManyRequests {
+                message,
+                retry_after: None,
+            },
+            StatusCode::INTERNAL_SERVER_ERROR => Self::Internal(message),
+            StatusCode::BAD_GATEWAY => Self::BadGateway(message),
+            StatusCode::SERVICE_UNAVAILABLE => Self::ServiceUnavailable(message),
+            status => Self::Other(status, message),
+        }
+    }
+
+    /// `snake_case` name of the status, e.g. `method_not_allowed` for 405.
+    pub fn code(&self) -> String {
+        let code = match self {
+            Self::BadRequest(_) => "bad_request",
+            Self::Unauthorized(_) => "unauthorized",
+            Self::Forbidden(_) => "forbidden",
+            Self::NotFound(_) => "not_found",
+            Self::Conflict(_) => "conflict",
+            Self::Gone(_) => "gone",
+            Self::Locked(_) => "locked",
+            Self::Unprocessable(_) => "unprocessable",
+            Self::TooManyRequests { .. } => "too_many_requests",
+            Self::Internal(_) => "internal",
+            Self::BadGateway(_) => "bad_gateway",
+            Self::ServiceUnavailable(_) => "service_unavailable",
+            Self::Other(status, _) => {
+                return status
+                    .canonical_reason()
+                    .unwrap_or("error")
+                    .to_lowercase()
+                    .replace([' ', '-'], "_");
+            }
+            Self::Detailed(error, _) => return error.code(),
+        };
+        code.to_string()
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::BadRequest(message)
+            | Self::Unauthorized(message)
+            | Self::Forbidden(message)
+            | Self::NotFound(message)
+            | Self::Conflict(message)
+            | Self::Gone(message)
+            | Self::Locked(message)
+            | Self::Unprocessable(message)
+            | Self::TooManyRequests { message, .. }
+            | Self::Internal(message)
+            | Self::BadGateway(message)
+            | Self::ServiceUnavailable(message)
+            | Self::Other(_, message) => message,
+            Self::Detailed(error, _) => error.message(),
+        }
+    }
+
+    fn retry_after(&self) -> Option<u64> {
+        match self {
+            Self::TooManyRequests { retry_after, .. } => *retry_after,
+            Self::Detailed(error, _) => error.retry_after(),
+            _ => None,
+        }
+    }
+
+    pub fn body(&self) -> ErrorResponse {
+        ErrorResponse {
+            code: self.code(),
+            message: self.message().to_string(),
+            details: match self {
+                Self::Detailed(_, details) => Some(details.clone()),
+                _ => None,
+            },
+            request_id: request_id::current(),
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status(), Json(self.body())).into_response();
+        if let Some(seconds) = self.retry_after() {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, seconds.into());
+        }
+        response
+    }
+}
+
+/// Error bodies read into the message of a converted error, at most.
+const MAX_PLAIN_ERROR_BYTES: usize = 16 * 1024;
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            let mime = value.split(';').next().unwrap_or_default().trim();
+            mime == "application/json" || mime.ends_with("+json")
+        })
+}
+
+/// Gives error responses that did not come from an [`AppError`], such as
+/// rejected extractors, unknown routes and the login layers, the same JSON
+/// body. Their text, if any, becomes the message.
+pub async fn json_errors(response: Response) -> Response {
+    let status = response.status();
+    if !(status.is_client_error() || status.is_server_error()) || is_json(response.headers()) {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let message = match to_bytes(body, MAX_PLAIN_ERROR_BYTES).await {
+        Ok(bytes) if !bytes.is_empty() => String::from_utf8_lossy(&bytes).into_owned(),
+        _ => status.canonical_reason().unwrap_or("Error").to_string(),
+    };
+    let mut response = AppError::from_status(status, message).into_response();
+    // Keep headers such as `Allow` or `Retry-After`
+    parts.headers.remove(header::CONTENT_TYPE);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    response.headers_mut().extend(parts.headers);
+    response
+}
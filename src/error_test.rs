@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::{Body, to_bytes},
+        http::{StatusCode, header},
+        response::{IntoResponse, Response},
+    };
+    use serde_json::{Value, json};
+
+    use super::super::error::{AppError, json_errors};
+
+    async fn json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_status_and_code_follow_variant() {
+        let cases = [
+            (AppError::BadRequest(String::new()), 400, "bad_request"),
+            (AppError::NotFound(String::new()), 404, "not_found"),
+            (AppError::Conflict(String::new()), 409, "conflict"),
+            (AppError::Unprocessable(String::new()), 422, "unprocessable"),
+            (AppError::Internal(String::new()), 500, "internal"),
+            (
+                AppError::Other(StatusCode::METHOD_NOT_ALLOWED, String::new()),
+                405,
+                "method_not_allowed",
+            ),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!(error.status().as_u16(), status);
+            assert_eq!(error.code(), code);
+        }
+        assert_eq!(
+            AppError::from_status(StatusCode::FORBIDDEN, "No".to_string()),
+            AppError::Forbidden("No".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_details_keep_status_and_code() {
+        let error = AppError::Conflict("Email is already used".to_string())
+            .with_details(json!({ "field": "email" }));
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            json(response).await,
+            json!({
+                "code": "conflict",
+                "message": "Email is already used",
+                "details": { "field": "email" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_too_many_requests_sets_retry_after() {
+        let response = AppError::TooManyRequests {
+            message: "Slow down".to_string(),
+            retry_after: Some(30),
+        }
+        .into_response();
+        assert_eq!(response.headers()[header::RETRY_AFTER], "30");
+        assert_eq!(json(response).await["details"], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn test_plain_text_errors_become_json() {
+        let plain = Response::builder()
+            .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+            .body(Body::from(
+                "Expected request with `Content-Type: application/json`",
+            ))
+            .unwrap();
+        let body = json(json_errors(plain).await).await;
+        assert_eq!(body["code"], "unsupported_media_type");
+        assert_eq!(
+            body["message"],
+            "Expected request with `Content-Type: application/json`"
+        );
+
+        let empty = StatusCode::UNAUTHORIZED.into_response();
+        let body = json(json_errors(empty).await).await;
+        assert_eq!(body["code"], "unauthorized");
+        assert_eq!(body["message"], "Unauthorized");
+
+        let ok = Response::new(Body::from("fine"));
+        let response = json_errors(ok).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
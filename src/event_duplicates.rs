@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+
+use chrono::Duration;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+
+/// Settings for spotting several reservations made for the same event, e.g.
+/// a club booking three rooms "just in case".
+#[derive(Clone)]
+pub struct EventDuplicatesConfig {
+    /// Reservations for the same event starting at most this far apart are
+    /// treated as duplicates
+    pub window: Duration,
+}
+
+impl Default for EventDuplicatesConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::hours(24),
+        }
+    }
+}
+
+/// Event names that differ only in case, punctuation or spacing compare
+/// equal, so "Robotics Club - Demo Day" matches "robotics club demo day".
+pub fn normalize_event_name(name: &str) -> Option<String> {
+    let normalized = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!normalized.is_empty()).then_some(normalized)
+}
+
+fn is_active(reservation: &reservation::Model) -> bool {
+    matches!(
+        reservation.status,
+        ReservationStatus::Pending | ReservationStatus::Approved
+    )
+}
+
+/// Reservations that look like they were made for the same event.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct EventGroup {
+    /// Normalized event name shared by the reservations
+    pub event_key: String,
+    /// Distinct requesters in the group
+    pub requesters: u64,
+    /// Distinct classrooms in the group
+    pub classrooms: u64,
+    /// Ordered by start time
+    pub reservations: Vec<reservation::Model>,
+}
+
+fn distinct(values: impl Iterator<Item = Option<String>>) -> u64 {
+    let mut values: Vec<String> = values.flatten().collect();
+    values.sort();
+    values.dedup();
+    values.len() as u64
+}
+
+/// Groups active reservations by normalized event name, splitting a name's
+/// reservations wherever consecutive start times are more than `window`
+/// apart. Only groups of two or more are returned, largest first.
+pub fn group_duplicates(
+    reservations: Vec<reservation::Model>,
+    window: Duration,
+) -> Vec<EventGroup> {
+    let mut by_event: BTreeMap<String, Vec<reservation::Model>> = BTreeMap::new();
+    for reservation in reservations.into_iter().filter(is_active) {
+        if let Some(key) = reservation
+            .event_name
+            .as_deref()
+            .and_then(normalize_event_name)
+        {
+            by_event.entry(key).or_default().push(reservation);
+        }
+    }
+
+    let mut groups = Vec::new();
+    for (event_key, mut items) in by_event {
+        items.sort_by_key(|r| r.start_time);
+        let mut clusters: Vec<Vec<reservation::Model>> = Vec::new();
+        for item in items {
+            match clusters.last_mut() {
+                Some(cluster) if item.start_time - cluster.last().unwrap().start_time <= window => {
+                    cluster.push(item)
+                }
+                _ => clusters.push(vec![item]),
+            }
+        }
+        for reservations in clusters.into_iter().filter(|c| c.len() > 1) {
+            groups.push(EventGroup {
+                event_key: event_key.clone(),
+                requesters: distinct(reservations.iter().map(|r| r.user_id.clone())),
+                classrooms: distinct(reservations.iter().map(|r| r.classroom_id.clone())),
+                reservations,
+            });
+        }
+    }
+
+    groups.sort_by(|a, b| {
+        b.reservations.len().cmp(&a.reservations.len()).then(
+            a.reservations[0]
+                .start_time
+                .cmp(&b.reservations[0].start_time),
+        )
+    });
+    groups
+}
+
+/// Other active reservations for the same event as `event_name`, starting
+/// within `window` of `start_time`.
+pub async fn find_duplicates(
+    db: &DatabaseConnection,
+    event_name: &str,
+    start_time: DateTimeWithTimeZone,
+    window: Duration,
+    exclude_id: Option<&str>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let Some(key) = normalize_event_name(event_name) else {
+        return Ok(Vec::new());
+    };
+    let mut stmt = reservation::Entity::find()
+        .filter(reservation::Column::EventName.is_not_null())
+        .filter(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .filter(reservation::Column::StartTime.gte(start_time - window))
+        .filter(reservation::Column::StartTime.lte(start_time + window));
+    if let Some(exclude_id) = exclude_id {
+        stmt = stmt.filter(reservation::Column::Id.ne(exclude_id));
+    }
+    Ok(stmt
+        .order_by_asc(reservation::Column::StartTime)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|r| r.event_name.as_deref().and_then(normalize_event_name) == Some(key.clone()))
+        .collect())
+}
+
+/// Review flag for a reservation with `duplicates` other reservations for the
+/// same event.
+pub fn duplicate_flag_reason(event_name: &str, duplicates: &[reservation::Model]) -> String {
+    let mut classrooms: Vec<&str> = duplicates
+        .iter()
+        .filter_map(|r| r.classroom_id.as_deref())
+        .collect();
+    classrooms.sort();
+    classrooms.dedup();
+    format!(
+        "Possible duplicate booking: {} other active reservation(s) for event \"{}\" in {} classroom(s)",
+        duplicates.len(),
+        event_name.trim(),
+        classrooms.len()
+    )
+}
@@ -0,0 +1,190 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+    use super::super::event_duplicates::{
+        duplicate_flag_reason, group_duplicates, normalize_event_name,
+    };
+    use super::super::test_support::reservation_at;
+    use chrono::Duration;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reservation(
+        id: &str,
+        user_id: &str,
+        classroom_id: &str,
+        event_name: Option<&str>,
+        start: &str,
+    ) -> reservation::Model {
+        let start_time = dt(start);
+        reservation::Model {
+            user_id: Some(user_id.to_string()),
+            classroom_id: Some(classroom_id.to_string()),
+            end_time: start_time + Duration::hours(2),
+            event_name: event_name.map(str::to_string),
+            ..reservation_at(id, start, start)
+        }
+    }
+
+    #[test]
+    fn test_normalize_ignores_case_punctuation_and_spacing() {
+        assert_eq!(
+            normalize_event_name("  Robotics Club -  Demo Day!"),
+            Some("robotics club demo day".to_string())
+        );
+        assert_eq!(
+            normalize_event_name("robotics club demo day"),
+            normalize_event_name("ROBOTICS-CLUB DEMO_DAY")
+        );
+        assert_eq!(
+            normalize_event_name("機器人社 成果展"),
+            Some("機器人社 成果展".to_string())
+        );
+        assert_eq!(normalize_event_name(" -- "), None);
+    }
+
+    #[test]
+    fn test_groups_same_event_across_users_and_rooms() {
+        let groups = group_duplicates(
+            vec![
+                reservation(
+                    "r1",
+                    "u1",
+                    "room-1",
+                    Some("Demo Day"),
+                    "2025-03-10T10:00:00+08:00",
+                ),
+                reservation(
+                    "r2",
+                    "u2",
+                    "room-2",
+                    Some("demo day"),
+                    "2025-03-10T10:00:00+08:00",
+                ),
+                reservation(
+                    "r3",
+                    "u1",
+                    "room-3",
+                    Some("Demo-Day"),
+                    "2025-03-10T13:00:00+08:00",
+                ),
+                reservation(
+                    "r4",
+                    "u3",
+                    "room-4",
+                    Some("Open House"),
+                    "2025-03-10T10:00:00+08:00",
+                ),
+                reservation("r5", "u3", "room-5", None, "2025-03-10T10:00:00+08:00"),
+            ],
+            Duration::hours(24),
+        );
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].event_key, "demo day");
+        assert_eq!(groups[0].requesters, 2);
+        assert_eq!(groups[0].classrooms, 3);
+        let ids: Vec<&str> = groups[0]
+            .reservations
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["r1", "r2", "r3"]);
+    }
+
+    #[test]
+    fn test_bookings_further_apart_than_window_are_split() {
+        let groups = group_duplicates(
+            vec![
+                reservation(
+                    "r1",
+                    "u1",
+                    "room-1",
+                    Some("Weekly meetup"),
+                    "2025-03-03T18:00:00+08:00",
+                ),
+                reservation(
+                    "r2",
+                    "u1",
+                    "room-1",
+                    Some("Weekly meetup"),
+                    "2025-03-10T18:00:00+08:00",
+                ),
+                reservation(
+                    "r3",
+                    "u2",
+                    "room-2",
+                    Some("Weekly meetup"),
+                    "2025-03-10T19:00:00+08:00",
+                ),
+            ],
+            Duration::hours(24),
+        );
+        assert_eq!(groups.len(), 1);
+        let ids: Vec<&str> = groups[0]
+            .reservations
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["r2", "r3"]);
+    }
+
+    #[test]
+    fn test_inactive_reservations_are_ignored() {
+        let mut rejected = reservation(
+            "r2",
+            "u2",
+            "room-2",
+            Some("Demo Day"),
+            "2025-03-10T10:00:00+08:00",
+        );
+        rejected.status = ReservationStatus::Rejected;
+        let groups = group_duplicates(
+            vec![
+                reservation(
+                    "r1",
+                    "u1",
+                    "room-1",
+                    Some("Demo Day"),
+                    "2025-03-10T10:00:00+08:00",
+                ),
+                rejected,
+            ],
+            Duration::hours(24),
+        );
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn test_flag_reason_counts_rooms() {
+        let duplicates = vec![
+            reservation(
+                "r1",
+                "u1",
+                "room-1",
+                Some("Demo Day"),
+                "2025-03-10T10:00:00+08:00",
+            ),
+            reservation(
+                "r2",
+                "u1",
+                "room-1",
+                Some("Demo Day"),
+                "2025-03-10T12:00:00+08:00",
+            ),
+            reservation(
+                "r3",
+                "u2",
+                "room-2",
+                Some("Demo Day"),
+                "2025-03-10T10:00:00+08:00",
+            ),
+        ];
+        assert_eq!(
+            duplicate_flag_reason(" Demo Day ", &duplicates),
+            "Possible duplicate booking: 3 other active reservation(s) for event \"Demo Day\" in 2 classroom(s)"
+        );
+    }
+}
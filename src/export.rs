@@ -0,0 +1,101 @@
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use futures_util::Stream;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::utils::parse_dt;
+
+/// Rows fetched from the database per chunk of a streamed export.
+pub const EXPORT_CHUNK_SIZE: u64 = 500;
+
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// Comma-separated values with a header row
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+/// Fields starting with a formula character are prefixed with `'` so
+/// spreadsheets do not evaluate them.
+pub fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+/// One CSV record terminated by CRLF.
+pub fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    let mut line = fields
+        .iter()
+        .map(|f| csv_field(f.as_ref()))
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Streams `chunks` as a file download named `<name>.<extension>`.
+pub fn download<S>(format: ExportFormat, name: &str, chunks: S) -> Response
+where
+    S: Stream<Item = Result<Bytes, std::io::Error>> + Send + 'static,
+{
+    let mut response = (StatusCode::OK, Body::from_stream(chunks)).into_response();
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    if let Ok(value) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"{}.{}\"",
+        name,
+        format.extension()
+    )) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    response
+}
+
+/// Parses an optional time filter, naming the parameter in the error.
+pub fn parse_filter(
+    value: &Option<String>,
+    name: &str,
+) -> Result<Option<DateTimeWithTimeZone>, String> {
+    match value {
+        Some(value) => parse_dt(value)
+            .map(Some)
+            .map_err(|_| format!("Invalid '{}'", name)),
+        None => Ok(None),
+    }
+}
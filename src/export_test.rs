@@ -0,0 +1,32 @@
+#[cfg(test)]
+mod tests {
+    use super::super::export::{ExportFormat, csv_field, csv_line};
+
+    #[test]
+    fn test_plain_fields_are_not_quoted() {
+        assert_eq!(csv_field("Alice Chen"), "Alice Chen");
+        assert_eq!(csv_line(&["a", "b", ""]), "a,b,\r\n");
+    }
+
+    #[test]
+    fn test_special_characters_are_quoted() {
+        assert_eq!(csv_field("Chen, Alice"), "\"Chen, Alice\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_formula_prefixes_are_neutralised() {
+        assert_eq!(csv_field("=SUM(A1:A2)"), "'=SUM(A1:A2)");
+        assert_eq!(csv_field("+886912345678"), "'+886912345678");
+        assert_eq!(csv_field("@cmd,x"), "\"'@cmd,x\"");
+    }
+
+    #[test]
+    fn test_format_defaults_to_csv() {
+        assert_eq!(ExportFormat::default(), ExportFormat::Csv);
+        let format: ExportFormat = serde_json::from_str("\"ndjson\"").unwrap();
+        assert_eq!(format.content_type(), "application/x-ndjson");
+        assert_eq!(format.extension(), "ndjson");
+    }
+}
@@ -0,0 +1,89 @@
+use std::str::FromStr;
+
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QuerySelect, Select,
+    prelude::Json as JsonValue,
+};
+use serde::Serialize;
+
+use crate::pagination::{self, PagedResponse, Pagination};
+
+/// A row of a list endpoint: the whole model, or only the columns asked
+/// for with `fields=`.
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+pub enum Row<M> {
+    Full(M),
+    Partial(JsonValue),
+}
+
+/// Parses a comma-separated `fields=` list into columns, e.g.
+/// `id,start_time,status`. `id` is always included so rows can be told
+/// apart.
+pub fn parse<C>(fields: &str, id: C) -> Result<Vec<C>, String>
+where
+    C: ColumnTrait + FromStr,
+{
+    let mut columns = vec![id];
+    for name in fields.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+        let column = C::from_str(name).map_err(|_| format!("Unknown field '{}'", name))?;
+        if !columns.iter().any(|c| c.as_str() == column.as_str()) {
+            columns.push(column);
+        }
+    }
+    Ok(columns)
+}
+
+/// Runs `select`, projecting it onto `columns` when given.
+pub async fn all<E>(
+    db: &DatabaseConnection,
+    select: Select<E>,
+    columns: Option<&[E::Column]>,
+) -> Result<Vec<Row<E::Model>>, DbErr>
+where
+    E: EntityTrait,
+{
+    Ok(match columns {
+        None => select.all(db).await?.into_iter().map(Row::Full).collect(),
+        Some(columns) => select
+            .select_only()
+            .columns(columns.iter().copied())
+            .into_json()
+            .all(db)
+            .await?
+            .into_iter()
+            .map(Row::Partial)
+            .collect(),
+    })
+}
+
+/// One page of `select`, projected onto `columns` when given.
+pub async fn page<E>(
+    db: &DatabaseConnection,
+    select: Select<E>,
+    columns: Option<&[E::Column]>,
+    pagination: Pagination,
+) -> Result<PagedResponse<Row<E::Model>>, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    let Some(columns) = columns else {
+        return Ok(pagination::fetch(db, select, pagination)
+            .await?
+            .map(Row::Full));
+    };
+    let paginator = select
+        .select_only()
+        .columns(columns.iter().copied())
+        .into_json()
+        .paginate(db, pagination.page_size);
+    let total = paginator.num_items().await?;
+    let items = paginator.fetch_page(pagination.page - 1).await?;
+    Ok(PagedResponse {
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        items: items.into_iter().map(Row::Partial).collect(),
+    })
+}
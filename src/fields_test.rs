@@ -0,0 +1,28 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::reservation;
+    use super::super::fields::parse;
+    use sea_orm::IdenStatic;
+
+    fn names(fields: &str) -> Result<Vec<&'static str>, String> {
+        parse(fields, reservation::Column::Id)
+            .map(|columns| columns.iter().map(|c| c.as_str()).collect())
+    }
+
+    #[test]
+    fn test_parse_always_includes_id() {
+        assert_eq!(
+            names("start_time, status,,"),
+            Ok(vec!["id", "start_time", "status"])
+        );
+        assert_eq!(names("id,status,status"), Ok(vec!["id", "status"]));
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_fields() {
+        assert_eq!(
+            names("status,password"),
+            Err("Unknown field 'password'".to_string())
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use axum::{
+    body::{Body, Bytes, to_bytes},
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Duration;
+use redis::{AsyncCommands, ExistenceCheck, RedisError, SetExpiry, SetOptions};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::{error::AppError, login_system::AuthSession, redis_breaker::RedisConnection};
+
+pub static IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+/// Set on responses replayed from an earlier request with the same key.
+pub static IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// Longest key accepted from a client.
+const MAX_KEY_LENGTH: usize = 255;
+/// Largest request body hashed to tell retries from different requests;
+/// matches the limit axum puts on extracted bodies.
+const MAX_REQUEST_BYTES: usize = 2 * 1024 * 1024;
+/// Largest response kept for replay. Bigger ones are returned but not kept.
+const MAX_STORED_BYTES: usize = 256 * 1024;
+
+#[derive(Clone)]
+pub struct IdempotencyConfig {
+    pub enabled: bool,
+    /// How long a response is replayed for retries with the same key
+    pub ttl: Duration,
+    /// How long a key stays claimed while its first request runs, in case
+    /// the server stops before storing the response
+    pub in_progress_ttl: Duration,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ttl: Duration::hours(24),
+            in_progress_ttl: Duration::minutes(1),
+        }
+    }
+}
+
+/// What is kept under a key: either a claim by the request still running,
+/// or its response.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Entry {
+    InProgress {
+        fingerprint: String,
+    },
+    Done {
+        fingerprint: String,
+        status: u16,
+        content_type: Option<String>,
+        location: Option<String>,
+        /// Hex encoded, so bodies need not be UTF-8
+        body: String,
+    },
+}
+
+impl Entry {
+    fn fingerprint(&self) -> &str {
+        match self {
+            Self::InProgress { fingerprint } | Self::Done { fingerprint, .. } => fingerprint,
+        }
+    }
+}
+
+/// The key sent in `Idempotency-Key`, or an error when it is not a
+/// reasonable one.
+pub fn key_from_header(value: &HeaderValue) -> Result<String, AppError> {
+    let key = value.to_str().unwrap_or_default();
+    let valid =
+        (1..=MAX_KEY_LENGTH).contains(&key.len()) && key.chars().all(|c| c.is_ascii_graphic());
+    match valid {
+        true => Ok(key.to_string()),
+        false => Err(AppError::BadRequest(format!(
+            "Idempotency-Key must be 1 to {} visible ASCII characters",
+            MAX_KEY_LENGTH
+        ))),
+    }
+}
+
+/// Tells a retry of a request from a different request sent with the same
+/// key.
+pub fn fingerprint(method: &Method, path_and_query: &str, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_str());
+    hasher.update(b" ");
+    hasher.update(path_and_query);
+    hasher.update(b"\n");
+    hasher.update(body);
+    hex::encode(hasher.finalize())
+}
+
+/// Keys are kept per user so one user can never be replayed another's
+/// response.
+fn redis_key(user_id: Option<&str>, key: &str) -> String {
+    format!("idempotency_{}_{}", user_id.unwrap_or("anonymous"), key)
+}
+
+/// Responses worth replaying. Server errors and rate limiting are left out
+/// so the retry runs again.
+pub fn is_stored(status: StatusCode) -> bool {
+    !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS
+}
+
+/// The stored response, as returned to a retry.
+pub fn replay(entry: &Entry) -> Option<Response> {
+    let Entry::Done {
+        status,
+        content_type,
+        location,
+        body,
+        ..
+    } = entry
+    else {
+        return None;
+    };
+    let mut response = Response::new(Body::from(hex::decode(body).ok()?));
+    *response.status_mut() = StatusCode::from_u16(*status).ok()?;
+    let headers = response.headers_mut();
+    for (name, value) in [
+        (header::CONTENT_TYPE, content_type),
+        (header::LOCATION, location),
+    ] {
+        if let Some(value) = value.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+            headers.insert(name, value);
+        }
+    }
+    headers.insert(
+        IDEMPOTENT_REPLAYED.clone(),
+        HeaderValue::from_static("true"),
+    );
+    Some(response)
+}
+
+/// Claims `key` for a new request. `None` means it was free and is now
+/// claimed; otherwise what is already kept under it.
+async fn claim(
+    redis: &mut RedisConnection,
+    key: &str,
+    fingerprint: &str,
+    ttl: Duration,
+) -> Result<Option<Entry>, RedisError> {
+    let entry = Entry::InProgress {
+        fingerprint: fingerprint.to_string(),
+    };
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::EX(ttl.num_seconds() as u64));
+    let set: Option<String> = redis
+        .set_options(key, serde_json::to_string(&entry).unwrap(), options)
+        .await?;
+    if set.is_some() {
+        return Ok(None);
+    }
+    // Gone again if it expired in between, which leaves the key free
+    let existing: Option<String> = redis.get(key).await?;
+    Ok(existing.and_then(|raw| serde_json::from_str(&raw).ok()))
+}
+
+/// Stores the response of a claimed key, or drops the claim when the
+/// response should not be replayed. Returns the response to send.
+async fn finish(
+    redis: &mut RedisConnection,
+    key: &str,
+    fingerprint: String,
+    response: Response,
+    ttl: Duration,
+) -> Response {
+    let status = response.status();
+    if !is_stored(status) {
+        if let Err(e) = redis.del::<_, ()>(key).await {
+            warn!("Failed to release idempotency key {}: {}", key, e);
+        }
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            warn!("Failed to read response for idempotency key {}: {}", key, e);
+            let _ = redis.del::<_, ()>(key).await;
+            return AppError::Internal("Failed to read the response".to_string()).into_response();
+        }
+    };
+    let header = |name: &HeaderName| {
+        parts
+            .headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let result = if body.len() <= MAX_STORED_BYTES {
+        let entry = Entry::Done {
+            fingerprint,
+            status: status.as_u16(),
+            content_type: header(&header::CONTENT_TYPE),
+            location: header(&header::LOCATION),
+            body: hex::encode(&body),
+        };
+        redis
+            .set_ex::<_, _, ()>(
+                key,
+                serde_json::to_string(&entry).unwrap(),
+                ttl.num_seconds() as u64,
+            )
+            .await
+    } else {
+        redis.del::<_, ()>(key).await
+    };
+    if let Err(e) = result {
+        warn!(
+            "Failed to store response for idempotency key {}: {}",
+            key, e
+        );
+    }
+    Response::from_parts(parts, Body::from(body))
+}
+
+/// Makes POST requests sent with an `Idempotency-Key` header safe to retry:
+/// the first runs and its response is kept, and retries with the same key
+/// get that response back instead of running again. Reusing a key for a
+/// different request is refused, as is a retry while the first is still
+/// running. Without Redis, requests simply run.
+pub async fn track(
+    State((mut redis, config)): State<(RedisConnection, IdempotencyConfig)>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !config.enabled || req.method() != Method::POST {
+        return next.run(req).await;
+    }
+    let Some(value) = req.headers().get(&IDEMPOTENCY_KEY) else {
+        return next.run(req).await;
+    };
+    let key = match key_from_header(value) {
+        Ok(key) => key,
+        Err(e) => return e.into_response(),
+    };
+    let user_id = req
+        .extensions()
+        .get::<AuthSession>()
+        .and_then(|session| session.user.as_ref())
+        .map(|user| user.id.clone());
+
+    let (parts, body) = req.into_parts();
+    let body: Bytes = match to_bytes(body, MAX_REQUEST_BYTES).await {
+        Ok(body) => body,
+        Err(_) => {
+            return AppError::Other(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "Request body is too large".to_string(),
+            )
+            .into_response();
+        }
+    };
+    let path_and_query = parts
+        .uri
+        .path_and_query()
+        .map(|p| p.as_str())
+        .unwrap_or_default();
+    let fingerprint = fingerprint(&parts.method, path_and_query, &body);
+    let req = Request::from_parts(parts, Body::from(body));
+
+    let key = redis_key(user_id.as_deref(), &key);
+    match claim(&mut redis, &key, &fingerprint, config.in_progress_ttl).await {
+        Ok(None) => {
+            let response = next.run(req).await;
+            finish(&mut redis, &key, fingerprint, response, config.ttl).await
+        }
+        Ok(Some(entry)) if entry.fingerprint() != fingerprint => AppError::Unprocessable(
+            "Idempotency-Key was already used for a different request".to_string(),
+        )
+        .into_response(),
+        Ok(Some(entry)) => replay(&entry).unwrap_or_else(|| {
+            AppError::Conflict(
+                "A request with this Idempotency-Key is still being processed".to_string(),
+            )
+            .into_response()
+        }),
+        Err(e) => {
+            warn!("Failed to check idempotency key {}: {}", key, e);
+            next.run(req).await
+        }
+    }
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{HeaderValue, Request, StatusCode},
+        middleware,
+        routing::post,
+    };
+    use tower::ServiceExt;
+
+    use super::super::{
+        idempotency::{
+            self, IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED, IdempotencyConfig, key_from_header,
+        },
+        memory_redis::MemoryRedis,
+        redis_breaker::RedisConnection,
+    };
+
+    fn app(calls: Arc<AtomicUsize>) -> Router {
+        let redis = RedisConnection::in_memory(MemoryRedis::default());
+        let created = calls.clone();
+        Router::new()
+            .route(
+                "/create",
+                post(move |body: String| async move {
+                    let n = created.fetch_add(1, Ordering::SeqCst) + 1;
+                    (StatusCode::CREATED, format!("{} #{}", body, n))
+                }),
+            )
+            .route(
+                "/fail",
+                post(move || async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    StatusCode::INTERNAL_SERVER_ERROR
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                (redis, IdempotencyConfig::default()),
+                idempotency::track,
+            ))
+    }
+
+    fn request(path: &str, key: &str, body: &str) -> Request<Body> {
+        Request::post(path)
+            .header(&IDEMPOTENCY_KEY, key)
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_key_from_header() {
+        let header = |value: &str| HeaderValue::from_str(value).unwrap();
+        assert_eq!(
+            key_from_header(&header("8c1f-42")).unwrap(),
+            "8c1f-42".to_string()
+        );
+        assert!(key_from_header(&header("")).is_err());
+        assert!(key_from_header(&header("has space")).is_err());
+        assert!(key_from_header(&header(&"a".repeat(256))).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_retry_replays_the_first_response() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone());
+
+        let first = app
+            .clone()
+            .oneshot(request("/create", "k1", "room"))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::CREATED);
+        assert!(first.headers().get(&IDEMPOTENT_REPLAYED).is_none());
+        let first_body = to_bytes(first.into_body(), usize::MAX).await.unwrap();
+
+        let retry = app
+            .clone()
+            .oneshot(request("/create", "k1", "room"))
+            .await
+            .unwrap();
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        assert_eq!(retry.headers()[&IDEMPOTENT_REPLAYED], "true");
+        let retry_body = to_bytes(retry.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(retry_body, first_body);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The same key for another request is refused
+        let other = app
+            .clone()
+            .oneshot(request("/create", "k1", "other room"))
+            .await
+            .unwrap();
+        assert_eq!(other.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        // Without a key every request runs
+        let plain = Request::post("/create").body(Body::from("room")).unwrap();
+        app.oneshot(plain).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_run_again() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let app = app(calls.clone());
+        for _ in 0..2 {
+            let response = app
+                .clone()
+                .oneshot(request("/fail", "k2", ""))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
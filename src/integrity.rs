@@ -0,0 +1,413 @@
+use std::collections::{BTreeMap, HashSet};
+
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    IntoActiveModel, QueryFilter, QuerySelect, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::entities::{
+    classroom, key, key_transaction_log, reservation,
+    sea_orm_active_enums::{ClassroomStatus, KeyStatus, Role},
+    user,
+};
+
+/// ID of the classroom, user and key that orphaned references are pointed
+/// at by [`RepairStrategy::Placeholder`].
+pub const PLACEHOLDER_ID: &str = "orphan-placeholder";
+
+/// A column pointing at another table that is checked for rows that no
+/// longer exist.
+#[derive(
+    Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Reference {
+    ReservationClassroom,
+    ReservationUser,
+    KeyLogKey,
+    KeyLogReservation,
+    KeyLogBorrowedTo,
+    KeyLogHandledBy,
+}
+
+impl Reference {
+    pub const ALL: [Self; 6] = [
+        Self::ReservationClassroom,
+        Self::ReservationUser,
+        Self::KeyLogKey,
+        Self::KeyLogReservation,
+        Self::KeyLogBorrowedTo,
+        Self::KeyLogHandledBy,
+    ];
+}
+
+/// How orphaned references are fixed.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairStrategy {
+    /// Clear the reference
+    Nullify,
+    /// Point the reference at a soft-deleted placeholder row, so reports
+    /// still count the row as belonging to something. Key log reservations
+    /// are cleared, as a placeholder reservation would skew statistics.
+    Placeholder,
+}
+
+/// What a repaired reference is set to.
+pub fn replacement(strategy: RepairStrategy, reference: Reference) -> Option<&'static str> {
+    match (strategy, reference) {
+        (RepairStrategy::Nullify, _) | (_, Reference::KeyLogReservation) => None,
+        (RepairStrategy::Placeholder, _) => Some(PLACEHOLDER_ID),
+    }
+}
+
+/// A row whose reference points at a row that does not exist.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct Orphan {
+    pub reference: Reference,
+    /// ID of the row holding the reference
+    pub row_id: String,
+    /// The ID it points at
+    pub missing_id: String,
+}
+
+/// The `(row id, reference)` pairs whose reference is not in `existing`.
+pub fn dangling(
+    reference: Reference,
+    rows: Vec<(String, Option<String>)>,
+    existing: &HashSet<String>,
+) -> Vec<Orphan> {
+    rows.into_iter()
+        .filter_map(|(row_id, target)| {
+            let target = target?;
+            (!existing.contains(&target)).then_some(Orphan {
+                reference,
+                row_id,
+                missing_id: target,
+            })
+        })
+        .collect()
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct ReferenceCount {
+    pub reference: Reference,
+    pub orphans: u64,
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct IntegrityReport {
+    pub total: u64,
+    /// Every checked reference, including those without orphans
+    pub by_reference: Vec<ReferenceCount>,
+    pub orphans: Vec<Orphan>,
+}
+
+pub fn report(orphans: Vec<Orphan>) -> IntegrityReport {
+    let mut counts: BTreeMap<Reference, u64> = Reference::ALL.iter().map(|r| (*r, 0)).collect();
+    for orphan in &orphans {
+        *counts.entry(orphan.reference).or_default() += 1;
+    }
+    IntegrityReport {
+        total: orphans.len() as u64,
+        by_reference: counts
+            .into_iter()
+            .map(|(reference, orphans)| ReferenceCount { reference, orphans })
+            .collect(),
+        orphans,
+    }
+}
+
+async fn ids<E, C>(db: &C, id: E::Column) -> Result<HashSet<String>, DbErr>
+where
+    E: EntityTrait,
+    C: ConnectionTrait,
+{
+    Ok(E::find()
+        .select_only()
+        .column(id)
+        .into_tuple::<String>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+async fn references<E, C>(
+    db: &C,
+    id: E::Column,
+    column: E::Column,
+) -> Result<Vec<(String, Option<String>)>, DbErr>
+where
+    E: EntityTrait,
+    C: ConnectionTrait,
+{
+    E::find()
+        .select_only()
+        .column(id)
+        .column(column)
+        .filter(column.is_not_null())
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+/// Every orphaned reference, by reference and then row.
+pub async fn scan<C: ConnectionTrait>(db: &C) -> Result<Vec<Orphan>, DbErr> {
+    use key_transaction_log::Column as Log;
+    use reservation::Column as Res;
+
+    let classrooms = ids::<classroom::Entity, _>(db, classroom::Column::Id).await?;
+    let users = ids::<user::Entity, _>(db, user::Column::Id).await?;
+    let keys = ids::<key::Entity, _>(db, key::Column::Id).await?;
+    let reservations = ids::<reservation::Entity, _>(db, Res::Id).await?;
+
+    let mut orphans = Vec::new();
+    for reference in Reference::ALL {
+        let (rows, existing) = match reference {
+            Reference::ReservationClassroom => (
+                references::<reservation::Entity, _>(db, Res::Id, Res::ClassroomId).await?,
+                &classrooms,
+            ),
+            Reference::ReservationUser => (
+                references::<reservation::Entity, _>(db, Res::Id, Res::UserId).await?,
+                &users,
+            ),
+            Reference::KeyLogKey => (
+                references::<key_transaction_log::Entity, _>(db, Log::Id, Log::KeyId).await?,
+                &keys,
+            ),
+            Reference::KeyLogReservation => (
+                references::<key_transaction_log::Entity, _>(db, Log::Id, Log::ReservationId)
+                    .await?,
+                &reservations,
+            ),
+            Reference::KeyLogBorrowedTo => (
+                references::<key_transaction_log::Entity, _>(db, Log::Id, Log::BorrowedTo).await?,
+                &users,
+            ),
+            Reference::KeyLogHandledBy => (
+                references::<key_transaction_log::Entity, _>(db, Log::Id, Log::HandledBy).await?,
+                &users,
+            ),
+        };
+        let mut found = dangling(reference, rows, existing);
+        found.sort_by(|a, b| a.row_id.cmp(&b.row_id));
+        orphans.extend(found);
+    }
+    Ok(orphans)
+}
+
+/// Points `column` of the given rows at `value`, bumping `version` as well
+/// when the entity carries one.
+async fn set_reference<E, C>(
+    db: &C,
+    id: E::Column,
+    column: E::Column,
+    version: Option<E::Column>,
+    row_ids: Vec<String>,
+    value: Option<&str>,
+) -> Result<(), DbErr>
+where
+    E: EntityTrait,
+    C: ConnectionTrait,
+{
+    let mut update = E::update_many().col_expr(column, Expr::value(value.map(str::to_string)));
+    if let Some(version) = version {
+        update = update.col_expr(version, Expr::col(version).add(1));
+    }
+    update.filter(id.is_in(row_ids)).exec(db).await?;
+    Ok(())
+}
+
+/// Inserts the soft-deleted placeholder classroom, user and key when they
+/// do not exist yet.
+async fn ensure_placeholders<C: ConnectionTrait>(
+    db: &C,
+    now: DateTimeWithTimeZone,
+) -> Result<(), DbErr> {
+    if classroom::Entity::find_by_id(PLACEHOLDER_ID)
+        .one(db)
+        .await?
+        .is_none()
+    {
+        classroom::Model {
+            id: PLACEHOLDER_ID.to_string(),
+            name: "Deleted classroom".to_string(),
+            location: String::new(),
+            capacity: 0,
+            description: "Stands in for classrooms that were removed while still referenced"
+                .to_string(),
+            status: ClassroomStatus::Unavailable,
+            created_at: now,
+            updated_at: now,
+            photo_id: String::new(),
+            status_reason: None,
+            status_from: None,
+            status_until: None,
+            deleted_at: Some(now),
+            name_en: None,
+            name_zh_tw: None,
+            description_en: None,
+            description_zh_tw: None,
+            requires_double_approval: false,
+            version: 1,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await?;
+    }
+    if user::Entity::find_by_id(PLACEHOLDER_ID)
+        .one(db)
+        .await?
+        .is_none()
+    {
+        // The empty password hash never verifies, so nobody can sign in
+        user::Model {
+            id: PLACEHOLDER_ID.to_string(),
+            username: PLACEHOLDER_ID.to_string(),
+            name: "Deleted user".to_string(),
+            email: format!("{}@deleted.invalid", PLACEHOLDER_ID),
+            password: String::new(),
+            phone_number: String::new(),
+            role: Role::User,
+            created_at: now,
+            updated_at: now,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: Some(now),
+            version: 1,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await?;
+    }
+    if key::Entity::find_by_id(PLACEHOLDER_ID)
+        .one(db)
+        .await?
+        .is_none()
+    {
+        key::Model {
+            id: PLACEHOLDER_ID.to_string(),
+            classroom_id: None,
+            key_number: PLACEHOLDER_ID.to_uppercase(),
+            status: KeyStatus::Retired,
+            cabinet_slot: None,
+            version: 1,
+        }
+        .into_active_model()
+        .reset_all()
+        .insert(db)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Scans and fixes every orphaned reference with `strategy` in one
+/// transaction, returning what was fixed.
+pub async fn repair(
+    db: &DatabaseConnection,
+    strategy: RepairStrategy,
+    now: DateTimeWithTimeZone,
+) -> Result<Vec<Orphan>, DbErr> {
+    use key_transaction_log::Column as Log;
+    use reservation::Column as Res;
+
+    let txn = db.begin().await?;
+    let orphans = scan(&txn).await?;
+    if strategy == RepairStrategy::Placeholder && !orphans.is_empty() {
+        ensure_placeholders(&txn, now).await?;
+    }
+
+    for reference in Reference::ALL {
+        let row_ids: Vec<String> = orphans
+            .iter()
+            .filter(|o| o.reference == reference)
+            .map(|o| o.row_id.clone())
+            .collect();
+        if row_ids.is_empty() {
+            continue;
+        }
+        let value = replacement(strategy, reference);
+        match reference {
+            Reference::ReservationClassroom => {
+                set_reference::<reservation::Entity, _>(
+                    &txn,
+                    Res::Id,
+                    Res::ClassroomId,
+                    Some(Res::Version),
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+            Reference::ReservationUser => {
+                set_reference::<reservation::Entity, _>(
+                    &txn,
+                    Res::Id,
+                    Res::UserId,
+                    Some(Res::Version),
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+            Reference::KeyLogKey => {
+                set_reference::<key_transaction_log::Entity, _>(
+                    &txn,
+                    Log::Id,
+                    Log::KeyId,
+                    None,
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+            Reference::KeyLogReservation => {
+                set_reference::<key_transaction_log::Entity, _>(
+                    &txn,
+                    Log::Id,
+                    Log::ReservationId,
+                    None,
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+            Reference::KeyLogBorrowedTo => {
+                set_reference::<key_transaction_log::Entity, _>(
+                    &txn,
+                    Log::Id,
+                    Log::BorrowedTo,
+                    None,
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+            Reference::KeyLogHandledBy => {
+                set_reference::<key_transaction_log::Entity, _>(
+                    &txn,
+                    Log::Id,
+                    Log::HandledBy,
+                    None,
+                    row_ids,
+                    value,
+                )
+                .await?
+            }
+        }
+    }
+
+    txn.commit().await?;
+    Ok(orphans)
+}
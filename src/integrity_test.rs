@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::super::integrity::{
+        Orphan, PLACEHOLDER_ID, Reference, RepairStrategy, dangling, replacement, report,
+    };
+
+    fn row(id: &str, target: Option<&str>) -> (String, Option<String>) {
+        (id.to_string(), target.map(str::to_string))
+    }
+
+    #[test]
+    fn test_dangling_skips_null_and_existing_references() {
+        let existing = HashSet::from(["c1".to_string()]);
+        let orphans = dangling(
+            Reference::ReservationClassroom,
+            vec![
+                row("r1", Some("c1")),
+                row("r2", None),
+                row("r3", Some("gone")),
+            ],
+            &existing,
+        );
+        assert_eq!(
+            orphans,
+            vec![Orphan {
+                reference: Reference::ReservationClassroom,
+                row_id: "r3".to_string(),
+                missing_id: "gone".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_report_counts_every_reference() {
+        let existing = HashSet::new();
+        let mut orphans = dangling(
+            Reference::KeyLogKey,
+            vec![row("l1", Some("k1")), row("l2", Some("k2"))],
+            &existing,
+        );
+        orphans.extend(dangling(
+            Reference::ReservationUser,
+            vec![row("r1", Some("u1"))],
+            &existing,
+        ));
+
+        let report = report(orphans);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.by_reference.len(), Reference::ALL.len());
+        let count = |reference| {
+            report
+                .by_reference
+                .iter()
+                .find(|c| c.reference == reference)
+                .unwrap()
+                .orphans
+        };
+        assert_eq!(count(Reference::KeyLogKey), 2);
+        assert_eq!(count(Reference::ReservationUser), 1);
+        assert_eq!(count(Reference::KeyLogHandledBy), 0);
+    }
+
+    #[test]
+    fn test_placeholder_strategy_clears_key_log_reservations() {
+        assert_eq!(
+            replacement(RepairStrategy::Nullify, Reference::ReservationClassroom),
+            None
+        );
+        assert_eq!(
+            replacement(RepairStrategy::Placeholder, Reference::ReservationClassroom),
+            Some(PLACEHOLDER_ID)
+        );
+        assert_eq!(
+            replacement(RepairStrategy::Placeholder, Reference::KeyLogBorrowedTo),
+            Some(PLACEHOLDER_ID)
+        );
+        assert_eq!(
+            replacement(RepairStrategy::Placeholder, Reference::KeyLogReservation),
+            None
+        );
+    }
+}
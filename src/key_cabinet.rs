@@ -0,0 +1,312 @@
+use std::sync::OnceLock;
+
+use chrono::{Duration, Utc};
+use nanoid::nanoid;
+use reqwest::Client;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_client::send_email_to_user,
+    email_templates,
+    entities::{
+        classroom, key, key_cabinet_pin, reservation,
+        sea_orm_active_enums::{CabinetPinStatus, KeyStatus},
+        user,
+    },
+    key_lifecycle::BORROW_WINDOW_GRACE_MINUTES,
+};
+
+static KEY_CABINET_CLIENT: OnceLock<Client> = OnceLock::new();
+
+/// Connection to the smart key cabinet. Without an API URL no PINs are
+/// issued and keys are handed out at the counter as before.
+#[derive(Clone)]
+pub struct KeyCabinetConfig {
+    /// e.g. `https://cabinet.example.edu/api`
+    pub api_base_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Secret the cabinet signs its callbacks with
+    pub webhook_secret: Option<Vec<u8>>,
+    pub webhook_tolerance: Duration,
+}
+
+impl Default for KeyCabinetConfig {
+    fn default() -> Self {
+        Self {
+            api_base_url: None,
+            api_key: None,
+            webhook_secret: None,
+            webhook_tolerance: Duration::minutes(5),
+        }
+    }
+}
+
+fn client() -> &'static Client {
+    KEY_CABINET_CLIENT.get_or_init(Client::new)
+}
+
+/// When the PIN for `reservation` opens the cabinet: from the start of the
+/// borrow window until the reservation ends.
+pub fn pin_window(
+    reservation: &reservation::Model,
+) -> (DateTimeWithTimeZone, DateTimeWithTimeZone) {
+    (
+        reservation.start_time - Duration::minutes(BORROW_WINDOW_GRACE_MINUTES),
+        reservation.end_time,
+    )
+}
+
+fn generate_pin() -> String {
+    const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+    nanoid!(6, &DIGITS)
+}
+
+/// An active cabinet key of the classroom that no other issued PIN covers
+/// during `[from, until)`.
+async fn choose_key(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+    from: DateTimeWithTimeZone,
+    until: DateTimeWithTimeZone,
+) -> Result<Option<key::Model>, DbErr> {
+    let taken: Vec<String> = key_cabinet_pin::Entity::find()
+        .filter(key_cabinet_pin::Column::Status.eq(CabinetPinStatus::Issued))
+        .filter(key_cabinet_pin::Column::ValidFrom.lt(until))
+        .filter(key_cabinet_pin::Column::ValidUntil.gt(from))
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|pin| pin.key_id)
+        .collect();
+
+    key::Entity::find()
+        .filter(key::Column::ClassroomId.eq(classroom_id))
+        .filter(key::Column::Status.eq(KeyStatus::Active))
+        .filter(key::Column::CabinetSlot.is_not_null())
+        .filter(key::Column::Id.is_not_in(taken))
+        .order_by_asc(key::Column::KeyNumber)
+        .one(db)
+        .await
+}
+
+#[derive(Serialize)]
+struct CabinetPinRequest<'a> {
+    pin: &'a str,
+    valid_from: String,
+    valid_until: String,
+    /// Echoed back in pickup callbacks
+    reference: &'a str,
+}
+
+async fn push_pin(config: &KeyCabinetConfig, pin: &key_cabinet_pin::Model) -> Result<(), String> {
+    let base_url = config.api_base_url.as_deref().unwrap_or_default();
+    let mut request = client()
+        .post(format!(
+            "{}/slots/{}/pins",
+            base_url.trim_end_matches('/'),
+            pin.slot
+        ))
+        .json(&CabinetPinRequest {
+            pin: &pin.pin,
+            valid_from: pin.valid_from.to_rfc3339(),
+            valid_until: pin.valid_until.to_rfc3339(),
+            reference: &pin.id,
+        });
+    if let Some(api_key) = &config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!("Cabinet API returned {}", resp.status())),
+        Err(e) => Err(format!("Cabinet API unreachable: {}", e)),
+    }
+}
+
+async fn delete_pin(config: &KeyCabinetConfig, pin: &key_cabinet_pin::Model) -> Result<(), String> {
+    let base_url = config.api_base_url.as_deref().unwrap_or_default();
+    let mut request = client().delete(format!(
+        "{}/slots/{}/pins/{}",
+        base_url.trim_end_matches('/'),
+        pin.slot,
+        pin.id
+    ));
+    if let Some(api_key) = &config.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!("Cabinet API returned {}", resp.status())),
+        Err(e) => Err(format!("Cabinet API unreachable: {}", e)),
+    }
+}
+
+/// Reserves a cabinet key for an approved reservation, sends its one-time PIN
+/// to the cabinet and emails it to the requester. Run in the background after
+/// approval; classrooms without cabinet keys are skipped.
+pub async fn issue_pin(state: AppState, reservation: reservation::Model) {
+    let config = &state.config.key_cabinet;
+    if config.api_base_url.is_none() {
+        return;
+    }
+    let Some(classroom_id) = reservation.classroom_id.clone() else {
+        return;
+    };
+
+    let (valid_from, valid_until) = pin_window(&reservation);
+    let key_model = match choose_key(&state.db, &classroom_id, valid_from, valid_until).await {
+        Ok(Some(key_model)) => key_model,
+        Ok(None) => return,
+        Err(e) => {
+            warn!(
+                "Failed to choose cabinet key for reservation {}: {}",
+                reservation.id, e
+            );
+            return;
+        }
+    };
+
+    let new_pin = key_cabinet_pin::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(Some(reservation.id.clone())),
+        key_id: Set(Some(key_model.id.clone())),
+        slot: Set(key_model.cabinet_slot.clone().unwrap_or_default()),
+        pin: Set(generate_pin()),
+        status: Set(CabinetPinStatus::Issued),
+        valid_from: Set(valid_from),
+        valid_until: Set(valid_until),
+        key_transaction_log_id: Set(None),
+        error: Set(None),
+        picked_up_at: Set(None),
+        created_at: NotSet,
+    };
+    let pin = match new_pin.insert(&state.db).await {
+        Ok(pin) => pin,
+        Err(e) => {
+            warn!(
+                "Failed to record cabinet PIN for reservation {}: {}",
+                reservation.id, e
+            );
+            return;
+        }
+    };
+
+    if let Err(error) = push_pin(config, &pin).await {
+        warn!(
+            "Failed to issue cabinet PIN for reservation {}: {}",
+            reservation.id, error
+        );
+        let mut failed: key_cabinet_pin::ActiveModel = pin.into();
+        failed.status = Set(CabinetPinStatus::Failed);
+        failed.error = Set(Some(error));
+        let _ = failed.update(&state.db).await;
+        return;
+    }
+
+    let Some(user_id) = &reservation.user_id else {
+        return;
+    };
+    let user_model = match user::Entity::find_by_id(user_id).one(&state.db).await {
+        Ok(Some(user_model)) => user_model,
+        _ => return,
+    };
+    let classroom_model = classroom::Entity::find_by_id(&classroom_id)
+        .one(&state.db)
+        .await
+        .unwrap_or(None);
+    let email = email_templates::cabinet_pin_issued(
+        &state.config.email_templates,
+        &reservation,
+        classroom_model.as_ref(),
+        &pin,
+        state.config.email_templates.locale_for(&user_model),
+    );
+    if let Err(e) = send_email_to_user(
+        state.config.smtp.as_ref(),
+        &user_model,
+        email.subject,
+        email.body,
+    )
+    .await
+    {
+        warn!("Failed to send cabinet PIN to {}: {}", user_model.id, e);
+    }
+}
+
+/// Withdraws every unused PIN of a reservation, e.g. after it was cancelled.
+pub async fn revoke_pins(state: AppState, reservation_id: String) {
+    let config = &state.config.key_cabinet;
+    if config.api_base_url.is_none() {
+        return;
+    }
+    let pins = match key_cabinet_pin::Entity::find()
+        .filter(key_cabinet_pin::Column::ReservationId.eq(&reservation_id))
+        .filter(key_cabinet_pin::Column::Status.eq(CabinetPinStatus::Issued))
+        .all(&state.db)
+        .await
+    {
+        Ok(pins) => pins,
+        Err(e) => {
+            warn!(
+                "Failed to load cabinet PINs of reservation {}: {}",
+                reservation_id, e
+            );
+            return;
+        }
+    };
+
+    for pin in pins {
+        // Keep the PIN marked as issued when the cabinet still accepts it, so
+        // a later pickup is recorded rather than lost
+        if let Err(error) = delete_pin(config, &pin).await {
+            warn!("Failed to revoke cabinet PIN {}: {}", pin.id, error);
+            continue;
+        }
+        let mut revoked: key_cabinet_pin::ActiveModel = pin.into();
+        revoked.status = Set(CabinetPinStatus::Revoked);
+        let _ = revoked.update(&state.db).await;
+    }
+}
+
+// ===============================
+//   Callbacks
+// ===============================
+#[derive(Deserialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CabinetEventKind {
+    /// A key was taken out with a PIN
+    Pickup,
+    /// Door alarms, stock checks and the like; ignored
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CabinetEvent {
+    #[serde(rename = "type")]
+    pub kind: CabinetEventKind,
+    /// The `reference` sent with the PIN
+    pub reference: String,
+    pub slot: String,
+    /// RFC 3339 time the slot was opened
+    pub occurred_at: String,
+}
+
+/// When a callback does not carry a usable time, the time it arrived is
+/// recorded instead.
+pub fn occurred_at(event: &CabinetEvent) -> DateTimeWithTimeZone {
+    event
+        .occurred_at
+        .parse::<DateTimeWithTimeZone>()
+        .unwrap_or_else(|_| Utc::now().fixed_offset())
+        .with_timezone(&campus_offset())
+}
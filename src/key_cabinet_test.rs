@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+    use super::super::key_cabinet::{CabinetEvent, CabinetEventKind, occurred_at, pin_window};
+    use super::super::test_support::reservation_at;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_pin_window_opens_with_borrow_grace() {
+        let reservation = reservation::Model {
+            classroom_id: Some("room-1".to_string()),
+            status: ReservationStatus::Approved,
+            ..reservation_at(
+                "r1",
+                "2025-03-10T10:00:00+08:00",
+                "2025-03-10T12:00:00+08:00",
+            )
+        };
+        assert_eq!(
+            pin_window(&reservation),
+            (
+                dt("2025-03-10T09:30:00+08:00"),
+                dt("2025-03-10T12:00:00+08:00")
+            )
+        );
+    }
+
+    #[test]
+    fn test_unknown_cabinet_events_are_ignored() {
+        let event: CabinetEvent = serde_json::from_str(
+            r#"{"type":"door_alarm","reference":"p1","slot":"A3","occurred_at":""}"#,
+        )
+        .unwrap();
+        assert_eq!(event.kind, CabinetEventKind::Other);
+    }
+
+    #[test]
+    fn test_pickup_time_is_taken_from_the_event() {
+        let event: CabinetEvent = serde_json::from_str(
+            r#"{"type":"pickup","reference":"p1","slot":"A3","occurred_at":"2025-03-10T01:45:00Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(event.kind, CabinetEventKind::Pickup);
+        assert_eq!(occurred_at(&event), dt("2025-03-10T09:45:00+08:00"));
+    }
+}
@@ -0,0 +1,311 @@
+use chrono::Duration;
+use sea_orm::{ColumnTrait, DbErr, EntityTrait, QueryFilter, prelude::DateTimeWithTimeZone};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    cache::CacheKey,
+    entities::{
+        key, key_transaction_log, reservation,
+        sea_orm_active_enums::{KeyStatus, ReservationStatus},
+    },
+    error::AppError,
+};
+
+// ===============================
+//   Key lifecycle
+// ===============================
+//
+//   Inactive ◄──► Active ──► Borrowed
+//                   ▲  ▲         │
+//                   │  └─return──┤
+//                   │            ▼
+//                   └─found── Lost ──► Retired
+//
+// Active/Inactive keys can also be reported lost, and any key that is not out
+// can be retired. Retired is final.
+
+/// Something that happens to a key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyEvent {
+    Borrow,
+    /// Handed back at the desk; also closes a lost report if the borrower had it
+    Return,
+    ReportLost,
+    Found,
+    Activate,
+    Deactivate,
+    Retire,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyTransitionError {
+    pub from: KeyStatus,
+    pub event: KeyEvent,
+}
+
+impl KeyTransitionError {
+    pub fn message(&self) -> String {
+        match (&self.from, self.event) {
+            (KeyStatus::Borrowed, KeyEvent::Borrow) => {
+                "Key is already borrowed and not yet returned".to_string()
+            }
+            (_, KeyEvent::Borrow) => format!("Key is {:?} and cannot be borrowed", self.from),
+            (_, KeyEvent::Return) => "Key is not currently borrowed".to_string(),
+            (from, event) => format!("Cannot apply {:?} to a {:?} key", event, from),
+        }
+    }
+}
+
+impl From<KeyTransitionError> for AppError {
+    fn from(error: KeyTransitionError) -> Self {
+        AppError::Conflict(error.message())
+    }
+}
+
+/// Status a key ends up in after `event`, or an error when the lifecycle does
+/// not allow the event from `from`.
+pub fn next_status(from: &KeyStatus, event: KeyEvent) -> Result<KeyStatus, KeyTransitionError> {
+    use KeyStatus::*;
+    let next = match (from, event) {
+        (Active, KeyEvent::Borrow) => Borrowed,
+        (Borrowed, KeyEvent::Return) | (Lost, KeyEvent::Return) => Active,
+        (Active, KeyEvent::ReportLost)
+        | (Inactive, KeyEvent::ReportLost)
+        | (Borrowed, KeyEvent::ReportLost) => Lost,
+        (Lost, KeyEvent::Found) => Active,
+        (Inactive, KeyEvent::Activate) => Active,
+        (Active, KeyEvent::Deactivate) => Inactive,
+        (Active, KeyEvent::Retire) | (Inactive, KeyEvent::Retire) | (Lost, KeyEvent::Retire) => {
+            Retired
+        }
+        _ => {
+            return Err(KeyTransitionError {
+                from: from.clone(),
+                event,
+            });
+        }
+    };
+    Ok(next)
+}
+
+/// Event an admin status edit stands for. `Borrowed` only comes from an
+/// actual borrow, so it has none.
+pub fn event_for_target(from: &KeyStatus, to: &KeyStatus) -> Option<KeyEvent> {
+    match to {
+        KeyStatus::Active if *from == KeyStatus::Lost => Some(KeyEvent::Found),
+        KeyStatus::Active => Some(KeyEvent::Activate),
+        KeyStatus::Inactive => Some(KeyEvent::Deactivate),
+        KeyStatus::Lost => Some(KeyEvent::ReportLost),
+        KeyStatus::Retired => Some(KeyEvent::Retire),
+        KeyStatus::Borrowed => None,
+    }
+}
+
+// ===============================
+//   Borrow validation
+// ===============================
+/// How far outside its reservation a key may still be handed over, to allow
+/// for early setup or arriving late.
+pub const BORROW_WINDOW_GRACE_MINUTES: i64 = 30;
+
+/// Why a reservation cannot be used to borrow a particular key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BorrowValidationError {
+    NotApproved { status: ReservationStatus },
+    WrongClassroom,
+    TooEarly { start_time: DateTimeWithTimeZone },
+    ReservationOver { end_time: DateTimeWithTimeZone },
+}
+
+impl BorrowValidationError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::NotApproved { status } => format!(
+                "Reservation is {:?}; only approved reservations can borrow keys",
+                status
+            ),
+            Self::WrongClassroom => "Key does not belong to the reserved classroom".to_string(),
+            Self::TooEarly { start_time } => format!(
+                "Reservation starts at {}; keys can be borrowed at most {} minutes before",
+                start_time.to_rfc3339(),
+                BORROW_WINDOW_GRACE_MINUTES
+            ),
+            Self::ReservationOver { end_time } => {
+                format!("Reservation ended at {}", end_time.to_rfc3339())
+            }
+        }
+    }
+}
+
+impl From<BorrowValidationError> for AppError {
+    fn from(error: BorrowValidationError) -> Self {
+        AppError::Unprocessable(error.message())
+    }
+}
+
+/// Checks that `reservation` entitles its holder to `key` at `borrowed_at`:
+/// it must be approved, for the key's classroom, and the borrow must fall
+/// within the reservation give or take [`BORROW_WINDOW_GRACE_MINUTES`].
+pub fn validate_borrow(
+    key: &key::Model,
+    reservation: &reservation::Model,
+    borrowed_at: DateTimeWithTimeZone,
+) -> Result<(), BorrowValidationError> {
+    if reservation.status != ReservationStatus::Approved {
+        return Err(BorrowValidationError::NotApproved {
+            status: reservation.status.clone(),
+        });
+    }
+    if key.classroom_id.is_none() || key.classroom_id != reservation.classroom_id {
+        return Err(BorrowValidationError::WrongClassroom);
+    }
+    let grace = Duration::minutes(BORROW_WINDOW_GRACE_MINUTES);
+    if borrowed_at < reservation.start_time - grace {
+        return Err(BorrowValidationError::TooEarly {
+            start_time: reservation.start_time,
+        });
+    }
+    if borrowed_at > reservation.end_time + grace {
+        return Err(BorrowValidationError::ReservationOver {
+            end_time: reservation.end_time,
+        });
+    }
+    Ok(())
+}
+
+// ===============================
+//   Multi-key returns
+// ===============================
+/// Why the keys asked for cannot be returned from a borrow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReturnSelectionError {
+    NotInBorrow(String),
+    AlreadyReturned(String),
+    NothingToReturn,
+}
+
+impl ReturnSelectionError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::NotInBorrow(key_id) => format!("Key {} is not part of this borrow", key_id),
+            Self::AlreadyReturned(key_id) => format!("Key {} already returned", key_id),
+            Self::NothingToReturn => "All keys of this borrow are already returned".to_string(),
+        }
+    }
+}
+
+impl From<ReturnSelectionError> for AppError {
+    fn from(error: ReturnSelectionError) -> Self {
+        AppError::BadRequest(error.message())
+    }
+}
+
+/// Picks the open items of a borrow to close. `key_ids` of `None` returns
+/// every key still out; otherwise each listed key must belong to the borrow
+/// and not be returned yet.
+pub fn select_returns<'a>(
+    items: &'a [key_transaction_log::Model],
+    key_ids: Option<&[String]>,
+) -> Result<Vec<&'a key_transaction_log::Model>, ReturnSelectionError> {
+    let selected: Vec<_> = match key_ids {
+        None => items.iter().filter(|i| i.returned_at.is_none()).collect(),
+        Some(key_ids) => {
+            let mut selected = Vec::with_capacity(key_ids.len());
+            for key_id in key_ids {
+                let item = items
+                    .iter()
+                    .find(|i| i.key_id.as_ref() == Some(key_id))
+                    .ok_or_else(|| ReturnSelectionError::NotInBorrow(key_id.clone()))?;
+                if item.returned_at.is_some() {
+                    return Err(ReturnSelectionError::AlreadyReturned(key_id.clone()));
+                }
+                if !selected
+                    .iter()
+                    .any(|s: &&key_transaction_log::Model| s.id == item.id)
+                {
+                    selected.push(item);
+                }
+            }
+            selected
+        }
+    };
+    if selected.is_empty() {
+        return Err(ReturnSelectionError::NothingToReturn);
+    }
+    Ok(selected)
+}
+
+// ===============================
+//   Classroom key summary
+// ===============================
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, Default, PartialEq, Eq)]
+pub struct KeySummary {
+    pub classroom_id: String,
+    /// Every key that has not been retired
+    pub total: u64,
+    pub available: u64,
+    /// Currently borrowed
+    pub out: u64,
+    pub lost: u64,
+    pub inactive: u64,
+}
+
+pub fn summarize<'a>(
+    classroom_id: &str,
+    statuses: impl IntoIterator<Item = &'a KeyStatus>,
+) -> KeySummary {
+    let mut summary = KeySummary {
+        classroom_id: classroom_id.to_string(),
+        ..Default::default()
+    };
+    for status in statuses {
+        match status {
+            KeyStatus::Active => summary.available += 1,
+            KeyStatus::Borrowed => summary.out += 1,
+            KeyStatus::Lost => summary.lost += 1,
+            KeyStatus::Inactive => summary.inactive += 1,
+            KeyStatus::Retired => continue,
+        }
+        summary.total += 1;
+    }
+    summary
+}
+
+async fn load_summary(state: &AppState, classroom_id: &str) -> Result<KeySummary, DbErr> {
+    let keys = key::Entity::find()
+        .filter(key::Column::ClassroomId.eq(classroom_id))
+        .all(&state.db)
+        .await?;
+    Ok(summarize(classroom_id, keys.iter().map(|k| &k.status)))
+}
+
+/// Recomputes the summary and stores it in the cache, dropping the cached
+/// views of the classroom that list its keys. Called after every key
+/// transition so dashboards never need to count keys themselves.
+pub async fn refresh_classroom_summary(state: &AppState, classroom_id: &str) {
+    state.cache.invalidate_classroom_keys(classroom_id).await;
+    let key = CacheKey::ClassroomKeySummary(classroom_id);
+    match load_summary(state, classroom_id).await {
+        Ok(summary) => state.cache.set(key, &summary).await,
+        Err(e) => {
+            warn!(
+                "Failed to compute key summary for classroom {}: {}",
+                classroom_id, e
+            );
+            state.cache.remove(&[key]).await;
+        }
+    }
+}
+
+/// Cached summary, recomputed on a miss.
+pub async fn classroom_summary(state: &AppState, classroom_id: &str) -> Result<KeySummary, DbErr> {
+    state
+        .cache
+        .get_or_load(CacheKey::ClassroomKeySummary(classroom_id), || {
+            load_summary(state, classroom_id)
+        })
+        .await
+}
@@ -0,0 +1,357 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::sea_orm_active_enums::Role;
+    use super::super::entities::sea_orm_active_enums::{KeyStatus, ReservationStatus};
+    use super::super::entities::{key, key_transaction_log, reservation};
+    use super::super::key_lifecycle::{
+        BorrowValidationError, KeyEvent, KeySummary, KeyTransitionError, ReturnSelectionError,
+        event_for_target, next_status, select_returns, summarize, validate_borrow,
+    };
+    use super::super::test_support::{
+        add_classroom, add_user, json, reservation_at, router, send, sign_in, state,
+    };
+    use axum::http::StatusCode;
+    use sea_orm::{
+        ActiveModelTrait, ActiveValue::Set, EntityTrait, Iterable, prelude::DateTimeWithTimeZone,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    const EVENTS: [KeyEvent; 7] = [
+        KeyEvent::Borrow,
+        KeyEvent::Return,
+        KeyEvent::ReportLost,
+        KeyEvent::Found,
+        KeyEvent::Activate,
+        KeyEvent::Deactivate,
+        KeyEvent::Retire,
+    ];
+
+    fn expected(from: &KeyStatus, event: KeyEvent) -> Option<KeyStatus> {
+        use KeyStatus::*;
+        match (from, event) {
+            (Active, KeyEvent::Borrow) => Some(Borrowed),
+            (Borrowed, KeyEvent::Return) | (Lost, KeyEvent::Return) => Some(Active),
+            (Active, KeyEvent::ReportLost)
+            | (Inactive, KeyEvent::ReportLost)
+            | (Borrowed, KeyEvent::ReportLost) => Some(Lost),
+            (Lost, KeyEvent::Found) => Some(Active),
+            (Inactive, KeyEvent::Activate) => Some(Active),
+            (Active, KeyEvent::Deactivate) => Some(Inactive),
+            (Active, KeyEvent::Retire)
+            | (Inactive, KeyEvent::Retire)
+            | (Lost, KeyEvent::Retire) => Some(Retired),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_transition_table_is_exhaustive() {
+        for from in KeyStatus::iter() {
+            for event in EVENTS {
+                let result = next_status(&from, event);
+                match expected(&from, event) {
+                    Some(to) => assert_eq!(result, Ok(to), "{:?} + {:?}", from, event),
+                    None => assert_eq!(
+                        result,
+                        Err(KeyTransitionError {
+                            from: from.clone(),
+                            event
+                        }),
+                        "{:?} + {:?}",
+                        from,
+                        event
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_retired_is_final() {
+        for event in EVENTS {
+            assert!(next_status(&KeyStatus::Retired, event).is_err());
+        }
+    }
+
+    #[test]
+    fn test_borrowed_key_cannot_be_borrowed_or_retired() {
+        let error = next_status(&KeyStatus::Borrowed, KeyEvent::Borrow).unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Key is already borrowed and not yet returned"
+        );
+        assert!(next_status(&KeyStatus::Borrowed, KeyEvent::Retire).is_err());
+    }
+
+    #[test]
+    fn test_event_for_target_matches_lifecycle() {
+        // Every admin edit to a different status either maps to an event the
+        // lifecycle accepts, is refused by it, or is a borrow (no event).
+        for from in KeyStatus::iter() {
+            for to in KeyStatus::iter() {
+                match event_for_target(&from, &to) {
+                    Some(event) => {
+                        if let Ok(next) = next_status(&from, event) {
+                            assert_eq!(next, to, "{:?} -> {:?}", from, to);
+                        }
+                    }
+                    None => assert_eq!(to, KeyStatus::Borrowed),
+                }
+            }
+        }
+        assert_eq!(
+            event_for_target(&KeyStatus::Lost, &KeyStatus::Active),
+            Some(KeyEvent::Found)
+        );
+        assert_eq!(
+            event_for_target(&KeyStatus::Inactive, &KeyStatus::Active),
+            Some(KeyEvent::Activate)
+        );
+    }
+
+    #[test]
+    fn test_summarize_counts_and_skips_retired() {
+        let statuses = [
+            KeyStatus::Active,
+            KeyStatus::Active,
+            KeyStatus::Borrowed,
+            KeyStatus::Lost,
+            KeyStatus::Inactive,
+            KeyStatus::Retired,
+        ];
+        assert_eq!(
+            summarize("room-1", statuses.iter()),
+            KeySummary {
+                classroom_id: "room-1".to_string(),
+                total: 5,
+                available: 2,
+                out: 1,
+                lost: 1,
+                inactive: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_summarize_empty() {
+        let summary = summarize("room-1", []);
+        assert_eq!(summary.total, 0);
+        assert_eq!(summary.classroom_id, "room-1");
+    }
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn key_in(classroom_id: Option<&str>) -> key::Model {
+        key::Model {
+            id: "key-1".to_string(),
+            classroom_id: classroom_id.map(str::to_string),
+            key_number: "A101-1".to_string(),
+            status: KeyStatus::Active,
+            cabinet_slot: None,
+            version: 1,
+        }
+    }
+
+    fn reservation_for(classroom_id: &str, status: ReservationStatus) -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some(classroom_id.to_string()),
+            status,
+            ..reservation_at(
+                "r1",
+                "2025-03-10T10:00:00+08:00",
+                "2025-03-10T12:00:00+08:00",
+            )
+        }
+    }
+
+    #[test]
+    fn test_borrow_needs_approved_reservation() {
+        for status in ReservationStatus::iter().filter(|s| *s != ReservationStatus::Approved) {
+            assert_eq!(
+                validate_borrow(
+                    &key_in(Some("room-1")),
+                    &reservation_for("room-1", status.clone()),
+                    dt("2025-03-10T10:00:00+08:00"),
+                ),
+                Err(BorrowValidationError::NotApproved { status })
+            );
+        }
+    }
+
+    #[test]
+    fn test_borrow_needs_key_of_reserved_classroom() {
+        let reservation = reservation_for("room-1", ReservationStatus::Approved);
+        let at = dt("2025-03-10T10:00:00+08:00");
+        assert_eq!(
+            validate_borrow(&key_in(Some("room-2")), &reservation, at),
+            Err(BorrowValidationError::WrongClassroom)
+        );
+        assert_eq!(
+            validate_borrow(&key_in(None), &reservation, at),
+            Err(BorrowValidationError::WrongClassroom)
+        );
+    }
+
+    #[test]
+    fn test_borrow_window_includes_grace() {
+        let key = key_in(Some("room-1"));
+        let reservation = reservation_for("room-1", ReservationStatus::Approved);
+        for at in [
+            "2025-03-10T09:30:00+08:00",
+            "2025-03-10T11:00:00+08:00",
+            "2025-03-10T12:30:00+08:00",
+        ] {
+            assert_eq!(
+                validate_borrow(&key, &reservation, dt(at)),
+                Ok(()),
+                "{}",
+                at
+            );
+        }
+        assert_eq!(
+            validate_borrow(&key, &reservation, dt("2025-03-10T09:29:00+08:00")),
+            Err(BorrowValidationError::TooEarly {
+                start_time: reservation.start_time
+            })
+        );
+        assert_eq!(
+            validate_borrow(&key, &reservation, dt("2025-03-10T12:31:00+08:00")),
+            Err(BorrowValidationError::ReservationOver {
+                end_time: reservation.end_time
+            })
+        );
+    }
+
+    fn item(key_id: &str, returned: bool) -> key_transaction_log::Model {
+        key_transaction_log::Model {
+            id: format!("log-{}", key_id),
+            reservation_id: Some("r1".to_string()),
+            key_id: Some(key_id.to_string()),
+            borrowed_to: Some("u1".to_string()),
+            handled_by: Some("admin".to_string()),
+            borrowed_at: dt("2025-03-10T10:00:00+08:00"),
+            returned_at: returned.then(|| dt("2025-03-10T11:00:00+08:00")),
+            on_time: returned,
+            created_at: dt("2025-03-10T10:00:00+08:00"),
+            deadline: dt("2025-03-10T12:00:00+08:00"),
+            overdue_at: None,
+            second_notice_at: None,
+            escalated_at: None,
+            return_requested_at: None,
+            borrow_id: Some("b1".to_string()),
+        }
+    }
+
+    fn ids(selected: &[&key_transaction_log::Model]) -> Vec<String> {
+        selected.iter().map(|i| i.id.clone()).collect()
+    }
+
+    #[test]
+    fn test_select_returns_defaults_to_keys_still_out() {
+        let items = [
+            item("door", false),
+            item("cabinet", true),
+            item("av", false),
+        ];
+        assert_eq!(
+            ids(&select_returns(&items, None).unwrap()),
+            ["log-door", "log-av"]
+        );
+    }
+
+    #[test]
+    fn test_select_returns_partial() {
+        let items = [item("door", false), item("cabinet", false)];
+        let key_ids = ["cabinet".to_string(), "cabinet".to_string()];
+        assert_eq!(
+            ids(&select_returns(&items, Some(&key_ids)).unwrap()),
+            ["log-cabinet"]
+        );
+    }
+
+    #[test]
+    fn test_select_returns_rejects_unknown_or_returned_keys() {
+        let items = [item("door", false), item("cabinet", true)];
+        assert_eq!(
+            select_returns(&items, Some(&["av".to_string()])),
+            Err(ReturnSelectionError::NotInBorrow("av".to_string()))
+        );
+        assert_eq!(
+            select_returns(&items, Some(&["cabinet".to_string()])),
+            Err(ReturnSelectionError::AlreadyReturned("cabinet".to_string()))
+        );
+        assert_eq!(
+            select_returns(&[item("door", true)], None),
+            Err(ReturnSelectionError::NothingToReturn)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_borrow_key_for_guest_reservation() {
+        let state = state().await;
+        add_user(&state, "admin", "admin@example.com", Role::Admin).await;
+        add_classroom(&state, "c1").await;
+        key::ActiveModel {
+            id: Set("k1".to_string()),
+            classroom_id: Set(Some("c1".to_string())),
+            key_number: Set("A-1".to_string()),
+            status: Set(KeyStatus::Active),
+            cabinet_slot: Set(None),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+        reservation::ActiveModel {
+            id: Set("r1".to_string()),
+            user_id: Set(None),
+            guest_name: Set(Some("Visiting speaker".to_string())),
+            classroom_id: Set(Some("c1".to_string())),
+            purpose: Set("Guest lecture".to_string()),
+            start_time: Set(dt("2025-03-10T09:00:00+08:00")),
+            end_time: Set(dt("2025-03-10T11:00:00+08:00")),
+            status: Set(ReservationStatus::Approved),
+            flagged_for_review: Set(false),
+            ..Default::default()
+        }
+        .insert(&state.db)
+        .await
+        .unwrap();
+        let app = router(state.clone());
+        let cookie = sign_in(&app, "admin@example.com").await;
+
+        let response = app
+            .oneshot(send(
+                "POST",
+                "/key/k1/borrow",
+                &cookie,
+                json!({
+                    "reservation_id": "r1",
+                    "borrowed_at": "2025-03-10T09:00:00+08:00",
+                    "deadline": "2025-03-10T11:00:00+08:00",
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["reservation_id"], "r1");
+        let log = key_transaction_log::Entity::find()
+            .one(&state.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(log.borrowed_to, None);
+        assert_eq!(
+            key::Entity::find_by_id("k1")
+                .one(&state.db)
+                .await
+                .unwrap()
+                .unwrap()
+                .status,
+            KeyStatus::Borrowed
+        );
+    }
+}
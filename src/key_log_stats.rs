@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::entities::key_transaction_log;
+
+/// Borrow outcomes of one key or one borrower.
+#[derive(Serialize, ToSchema, Clone, Debug, Default, PartialEq, Eq)]
+pub struct BorrowCounts {
+    pub borrows: u64,
+    pub returned_on_time: u64,
+    pub returned_late: u64,
+    /// Not returned yet and past the deadline
+    pub overdue: u64,
+    /// Not returned yet but still within the deadline
+    pub out: u64,
+}
+
+impl BorrowCounts {
+    fn add(&mut self, log: &key_transaction_log::Model, now: DateTimeWithTimeZone) {
+        self.borrows += 1;
+        match log.returned_at {
+            Some(_) if log.on_time => self.returned_on_time += 1,
+            Some(_) => self.returned_late += 1,
+            None if log.deadline < now => self.overdue += 1,
+            None => self.out += 1,
+        }
+    }
+
+    /// Late returns plus keys still overdue; what chronic late returners are
+    /// ranked by.
+    pub fn late(&self) -> u64 {
+        self.returned_late + self.overdue
+    }
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct KeyBorrowStats {
+    pub key_id: String,
+    /// `None` when the key has been deleted
+    pub key_number: Option<String>,
+    #[serde(flatten)]
+    pub counts: BorrowCounts,
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct UserBorrowStats {
+    pub user_id: String,
+    /// `None` when the user has been deleted
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub counts: BorrowCounts,
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct KeyLogStats {
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub from: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub to: DateTimeWithTimeZone,
+    pub total: BorrowCounts,
+    /// Most late returns first
+    pub per_key: Vec<KeyBorrowStats>,
+    /// Most late returns first
+    pub per_user: Vec<UserBorrowStats>,
+}
+
+/// Counts borrows per key and per borrower. Logs without a key or borrower
+/// only count towards the total.
+pub fn summarize(
+    logs: &[key_transaction_log::Model],
+    key_numbers: &HashMap<String, String>,
+    user_names: &HashMap<String, String>,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    now: DateTimeWithTimeZone,
+) -> KeyLogStats {
+    let mut total = BorrowCounts::default();
+    let mut per_key: HashMap<&str, BorrowCounts> = HashMap::new();
+    let mut per_user: HashMap<&str, BorrowCounts> = HashMap::new();
+    for log in logs {
+        total.add(log, now);
+        if let Some(key_id) = &log.key_id {
+            per_key.entry(key_id).or_default().add(log, now);
+        }
+        if let Some(user_id) = &log.borrowed_to {
+            per_user.entry(user_id).or_default().add(log, now);
+        }
+    }
+
+    let mut per_key: Vec<KeyBorrowStats> = per_key
+        .into_iter()
+        .map(|(key_id, counts)| KeyBorrowStats {
+            key_id: key_id.to_string(),
+            key_number: key_numbers.get(key_id).cloned(),
+            counts,
+        })
+        .collect();
+    per_key.sort_by(|a, b| {
+        b.counts
+            .late()
+            .cmp(&a.counts.late())
+            .then(b.counts.borrows.cmp(&a.counts.borrows))
+            .then(a.key_id.cmp(&b.key_id))
+    });
+
+    let mut per_user: Vec<UserBorrowStats> = per_user
+        .into_iter()
+        .map(|(user_id, counts)| UserBorrowStats {
+            user_id: user_id.to_string(),
+            name: user_names.get(user_id).cloned(),
+            counts,
+        })
+        .collect();
+    per_user.sort_by(|a, b| {
+        b.counts
+            .late()
+            .cmp(&a.counts.late())
+            .then(b.counts.borrows.cmp(&a.counts.borrows))
+            .then(a.user_id.cmp(&b.user_id))
+    });
+
+    KeyLogStats {
+        from,
+        to,
+        total,
+        per_key,
+        per_user,
+    }
+}
@@ -0,0 +1,97 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::entities::key_transaction_log;
+    use super::super::key_log_stats::{BorrowCounts, summarize};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn log(
+        id: &str,
+        key_id: &str,
+        user_id: &str,
+        returned_at: Option<&str>,
+        on_time: bool,
+    ) -> key_transaction_log::Model {
+        key_transaction_log::Model {
+            id: id.to_string(),
+            reservation_id: None,
+            key_id: Some(key_id.to_string()),
+            borrowed_to: Some(user_id.to_string()),
+            handled_by: None,
+            borrowed_at: dt("2025-03-10T09:00:00+08:00"),
+            returned_at: returned_at.map(dt),
+            on_time,
+            created_at: dt("2025-03-10T09:00:00+08:00"),
+            deadline: dt("2025-03-10T12:00:00+08:00"),
+            overdue_at: None,
+            second_notice_at: None,
+            escalated_at: None,
+            return_requested_at: None,
+            borrow_id: None,
+        }
+    }
+
+    #[test]
+    fn test_outcomes_are_counted_per_key_and_user() {
+        let mut logs = vec![
+            log("1", "k1", "alice", Some("2025-03-10T11:00:00+08:00"), true),
+            log("2", "k1", "bob", Some("2025-03-10T13:00:00+08:00"), false),
+            log("3", "k2", "bob", None, false),
+            log("4", "k2", "alice", None, false),
+        ];
+        // Still within its deadline at `now`
+        logs[3].deadline = dt("2025-03-11T12:00:00+08:00");
+        let keys = HashMap::from([("k1".to_string(), "A-101".to_string())]);
+        let names = HashMap::from([("bob".to_string(), "Bob".to_string())]);
+
+        let stats = summarize(
+            &logs,
+            &keys,
+            &names,
+            dt("2025-03-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+            dt("2025-03-10T18:00:00+08:00"),
+        );
+
+        assert_eq!(
+            stats.total,
+            BorrowCounts {
+                borrows: 4,
+                returned_on_time: 1,
+                returned_late: 1,
+                overdue: 1,
+                out: 1,
+            }
+        );
+        assert_eq!(stats.per_user[0].user_id, "bob");
+        assert_eq!(stats.per_user[0].name.as_deref(), Some("Bob"));
+        assert_eq!(stats.per_user[0].counts.late(), 2);
+        assert_eq!(stats.per_user[1].user_id, "alice");
+        assert_eq!(stats.per_user[1].name, None);
+        assert_eq!(stats.per_user[1].counts.late(), 0);
+    }
+
+    #[test]
+    fn test_ties_are_broken_by_borrow_count() {
+        let logs = vec![
+            log("1", "k1", "alice", Some("2025-03-10T13:00:00+08:00"), false),
+            log("2", "k2", "alice", Some("2025-03-10T13:00:00+08:00"), false),
+            log("3", "k2", "alice", Some("2025-03-10T11:00:00+08:00"), true),
+        ];
+        let stats = summarize(
+            &logs,
+            &HashMap::new(),
+            &HashMap::new(),
+            dt("2025-03-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+            dt("2025-03-10T18:00:00+08:00"),
+        );
+        let order: Vec<&str> = stats.per_key.iter().map(|k| k.key_id.as_str()).collect();
+        assert_eq!(order, ["k2", "k1"]);
+    }
+}
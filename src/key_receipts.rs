@@ -0,0 +1,100 @@
+use sea_orm::{ColumnTrait, DbErr, EntityTrait, QueryFilter};
+use tracing::warn;
+
+use crate::{
+    AppState,
+    email_queue::{Priority, queue_email_to_user},
+    email_templates::{self, ReceiptLine},
+    entities::{classroom, key, key_transaction_log, user},
+    live_events,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptKind {
+    Borrow,
+    Return,
+}
+
+/// Emails the borrower a receipt for `logs`, which were handed out or taken
+/// back together for one borrower. `handled_by` is the staff member at the
+/// counter; `None` for the key cabinet. Run in the background once the borrow
+/// or return is committed. The borrow or return is also pushed to live
+/// event streams.
+pub async fn send_receipt(
+    state: AppState,
+    kind: ReceiptKind,
+    logs: Vec<key_transaction_log::Model>,
+    handled_by: Option<String>,
+) {
+    live_events::push_keys(&state, kind, &logs).await;
+    let Some(borrower_id) = logs.first().and_then(|log| log.borrowed_to.clone()) else {
+        return;
+    };
+    if let Err(e) = deliver(&state, kind, &logs, &borrower_id, handled_by.as_deref()).await {
+        warn!("Failed to load key receipt for {}: {}", borrower_id, e);
+    }
+}
+
+async fn deliver(
+    state: &AppState,
+    kind: ReceiptKind,
+    logs: &[key_transaction_log::Model],
+    borrower_id: &str,
+    handled_by: Option<&str>,
+) -> Result<(), DbErr> {
+    let Some(borrower) = user::Entity::find_by_id(borrower_id).one(&state.db).await? else {
+        return Ok(());
+    };
+    let handler = match handled_by {
+        Some(handled_by) => user::Entity::find_by_id(handled_by).one(&state.db).await?,
+        None => None,
+    };
+
+    let key_ids: Vec<String> = logs.iter().filter_map(|log| log.key_id.clone()).collect();
+    let keys = key::Entity::find()
+        .filter(key::Column::Id.is_in(key_ids))
+        .all(&state.db)
+        .await?;
+    let classroom_ids: Vec<String> = keys.iter().filter_map(|k| k.classroom_id.clone()).collect();
+    let classrooms = classroom::Entity::find()
+        .filter(classroom::Column::Id.is_in(classroom_ids))
+        .all(&state.db)
+        .await?;
+
+    let lines: Vec<ReceiptLine> = logs
+        .iter()
+        .map(|log| {
+            let key = keys.iter().find(|k| Some(&k.id) == log.key_id.as_ref());
+            let classroom = key
+                .and_then(|k| k.classroom_id.as_ref())
+                .and_then(|id| classrooms.iter().find(|c| &c.id == id));
+            ReceiptLine {
+                log,
+                key,
+                classroom,
+            }
+        })
+        .collect();
+
+    let locale = state.config.email_templates.locale_for(&borrower);
+    let email = match kind {
+        ReceiptKind::Borrow => {
+            email_templates::key_borrow_receipt(&lines, handler.as_ref(), locale)
+        }
+        ReceiptKind::Return => {
+            email_templates::key_return_receipt(&lines, handler.as_ref(), locale)
+        }
+    };
+    if let Err(e) = queue_email_to_user(
+        state,
+        &borrower,
+        email.subject,
+        email.body,
+        Priority::Normal,
+    )
+    .await
+    {
+        warn!("Failed to send key receipt to {}: {}", borrower.id, e);
+    }
+    Ok(())
+}
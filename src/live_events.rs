@@ -0,0 +1,201 @@
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    announcement_audience::Reader,
+    domain_events::DomainEvent,
+    entities::{
+        announcement, key, key_transaction_log, reservation, reservation_comment,
+        sea_orm_active_enums::Role,
+    },
+    key_receipts::ReceiptKind,
+    permissions::{ClassroomScope, Permission, has_permission},
+};
+
+/// Events a slow client may fall behind by before it misses some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// An update pushed to signed-in clients over `GET /events` as it happens.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveEvent {
+    ReservationCreated {
+        reservation: reservation::Model,
+    },
+    ReservationReviewed {
+        reservation: reservation::Model,
+    },
+    ReservationCancelled {
+        reservation: reservation::Model,
+    },
+    ReservationCommented {
+        reservation: reservation::Model,
+        comment: reservation_comment::Model,
+    },
+    /// Keys handed out together to one borrower
+    KeyBorrowed {
+        logs: Vec<key_transaction_log::Model>,
+        /// Classrooms of the keys
+        classroom_ids: Vec<String>,
+    },
+    /// Keys taken back together from one borrower
+    KeyReturned {
+        logs: Vec<key_transaction_log::Model>,
+        classroom_ids: Vec<String>,
+    },
+    AnnouncementPublished {
+        announcement: announcement::Model,
+    },
+}
+
+impl LiveEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ReservationCreated { .. } => "reservation_created",
+            Self::ReservationReviewed { .. } => "reservation_reviewed",
+            Self::ReservationCancelled { .. } => "reservation_cancelled",
+            Self::ReservationCommented { .. } => "reservation_commented",
+            Self::KeyBorrowed { .. } => "key_borrowed",
+            Self::KeyReturned { .. } => "key_returned",
+            Self::AnnouncementPublished { .. } => "announcement_published",
+        }
+    }
+
+    /// The live counterpart of a domain event, for those dashboards show.
+    pub fn from_domain(event: &DomainEvent) -> Option<Self> {
+        match event {
+            DomainEvent::ReservationCreated { reservation } => Some(Self::ReservationCreated {
+                reservation: reservation.clone(),
+            }),
+            DomainEvent::ReservationReviewed { reservation, .. } => {
+                Some(Self::ReservationReviewed {
+                    reservation: reservation.clone(),
+                })
+            }
+            DomainEvent::ReservationCancelled { reservation } => Some(Self::ReservationCancelled {
+                reservation: reservation.clone(),
+            }),
+            DomainEvent::ReservationCommented {
+                reservation,
+                comment,
+            } => Some(Self::ReservationCommented {
+                reservation: reservation.clone(),
+                comment: comment.clone(),
+            }),
+            DomainEvent::EmergencyAnnounced { announcement } => Some(Self::AnnouncementPublished {
+                announcement: announcement.clone(),
+            }),
+            DomainEvent::ReservationPartiallyApproved { .. }
+            | DomainEvent::ReservationNudged { .. }
+            | DomainEvent::ReservationCompleted { .. } => None,
+        }
+    }
+}
+
+/// Fans events out to every open `GET /events` stream. Events sent while
+/// nobody is listening are dropped.
+#[derive(Clone)]
+pub struct LiveEvents {
+    sender: broadcast::Sender<LiveEvent>,
+}
+
+impl Default for LiveEvents {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+}
+
+impl LiveEvents {
+    pub fn send(&self, event: LiveEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LiveEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// Pushes a committed borrow or return of `logs` with the classrooms of
+/// their keys.
+pub async fn push_keys(state: &AppState, kind: ReceiptKind, logs: &[key_transaction_log::Model]) {
+    let key_ids: Vec<String> = logs.iter().filter_map(|log| log.key_id.clone()).collect();
+    let classroom_ids: Vec<Option<String>> = match key::Entity::find()
+        .select_only()
+        .column(key::Column::ClassroomId)
+        .filter(key::Column::Id.is_in(key_ids))
+        .into_tuple()
+        .all(&state.db)
+        .await
+    {
+        Ok(ids) => ids,
+        Err(e) => {
+            warn!("Failed to load classrooms for a live key event: {}", e);
+            Vec::new()
+        }
+    };
+    let mut classroom_ids: Vec<String> = classroom_ids.into_iter().flatten().collect();
+    classroom_ids.sort();
+    classroom_ids.dedup();
+    let logs = logs.to_vec();
+    state.live.send(match kind {
+        ReceiptKind::Borrow => LiveEvent::KeyBorrowed {
+            logs,
+            classroom_ids,
+        },
+        ReceiptKind::Return => LiveEvent::KeyReturned {
+            logs,
+            classroom_ids,
+        },
+    });
+}
+
+/// Who an event stream belongs to, as of when it was opened.
+pub struct Subscriber {
+    pub user_id: String,
+    pub role: Role,
+    /// Classrooms whose reservations and keys the user reviews
+    pub scope: ClassroomScope,
+    pub reader: Reader,
+}
+
+impl Subscriber {
+    fn reviews(&self, classroom_id: Option<&str>) -> bool {
+        has_permission(&self.role, Permission::ReviewReservations)
+            && self.scope.allows(classroom_id)
+    }
+
+    /// Whether the event is about the subscriber or something they look
+    /// after. Those who manage keys see every key event.
+    pub fn sees(&self, event: &LiveEvent) -> bool {
+        match event {
+            LiveEvent::ReservationCreated { reservation }
+            | LiveEvent::ReservationReviewed { reservation }
+            | LiveEvent::ReservationCancelled { reservation }
+            | LiveEvent::ReservationCommented { reservation, .. } => {
+                reservation.user_id.as_deref() == Some(self.user_id.as_str())
+                    || self.reviews(reservation.classroom_id.as_deref())
+            }
+            LiveEvent::KeyBorrowed {
+                logs,
+                classroom_ids,
+            }
+            | LiveEvent::KeyReturned {
+                logs,
+                classroom_ids,
+            } => {
+                has_permission(&self.role, Permission::ManageKeys)
+                    || logs
+                        .iter()
+                        .any(|log| log.borrowed_to.as_deref() == Some(self.user_id.as_str()))
+                    || classroom_ids.iter().any(|id| self.reviews(Some(id)))
+            }
+            LiveEvent::AnnouncementPublished { announcement } => self.reader.can_read(announcement),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    use super::super::announcement_audience::Reader;
+    use super::super::domain_events::DomainEvent;
+    use super::super::entities::{
+        announcement, key_transaction_log, reservation, reservation_comment,
+        sea_orm_active_enums::{AnnouncementAudience, AnnouncementCategory, Role},
+    };
+    use super::super::live_events::{LiveEvent, Subscriber};
+    use super::super::permissions::ClassroomScope;
+    use super::super::test_support::reservation_at;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn subscriber(user_id: &str, role: Role, scope: ClassroomScope) -> Subscriber {
+        Subscriber {
+            user_id: user_id.to_string(),
+            reader: Reader {
+                role: Some(role.clone()),
+                blacklisted: false,
+                classroom_ids: HashSet::new(),
+            },
+            role,
+            scope,
+        }
+    }
+
+    fn reservation(user_id: &str, classroom_id: &str) -> reservation::Model {
+        reservation::Model {
+            user_id: Some(user_id.to_string()),
+            classroom_id: Some(classroom_id.to_string()),
+            ..reservation_at(
+                "r1",
+                "2025-03-10T09:00:00+08:00",
+                "2025-03-10T11:00:00+08:00",
+            )
+        }
+    }
+
+    fn key_borrowed(borrowed_to: &str, classroom_id: &str) -> LiveEvent {
+        let at = dt("2025-03-10T09:00:00+08:00");
+        LiveEvent::KeyBorrowed {
+            logs: vec![key_transaction_log::Model {
+                id: "l1".to_string(),
+                reservation_id: None,
+                key_id: Some("k1".to_string()),
+                borrowed_to: Some(borrowed_to.to_string()),
+                handled_by: Some("admin".to_string()),
+                borrowed_at: at,
+                returned_at: None,
+                on_time: true,
+                created_at: at,
+                deadline: dt("2025-03-10T12:00:00+08:00"),
+                overdue_at: None,
+                second_notice_at: None,
+                escalated_at: None,
+                return_requested_at: None,
+                borrow_id: None,
+            }],
+            classroom_ids: vec![classroom_id.to_string()],
+        }
+    }
+
+    fn announcement(audience: AnnouncementAudience) -> announcement::Model {
+        announcement::Model {
+            id: "a1".to_string(),
+            title: "Closed".to_string(),
+            content: "Closed for maintenance".to_string(),
+            published_at: dt("2025-03-01T09:00:00+08:00"),
+            created_by: Some("admin".to_string()),
+            emergency: false,
+            pinned: false,
+            category: AnnouncementCategory::Maintenance,
+            title_en: None,
+            title_zh_tw: None,
+            content_en: None,
+            content_zh_tw: None,
+            audience,
+            audience_classroom_id: None,
+        }
+    }
+
+    #[test]
+    fn test_reservation_events_reach_owner_and_reviewers_in_scope() {
+        let event = LiveEvent::ReservationCreated {
+            reservation: reservation("alice", "c1"),
+        };
+        let only = |ids: &[&str]| ClassroomScope::Only(ids.iter().map(|s| s.to_string()).collect());
+
+        assert!(subscriber("alice", Role::User, only(&[])).sees(&event));
+        assert!(!subscriber("bob", Role::User, only(&[])).sees(&event));
+        assert!(subscriber("ta", Role::Assistant, only(&["c1"])).sees(&event));
+        assert!(!subscriber("ta", Role::Assistant, only(&["c2"])).sees(&event));
+        assert!(subscriber("admin", Role::Admin, ClassroomScope::All).sees(&event));
+    }
+
+    #[test]
+    fn test_comments_stay_between_owner_and_reviewers() {
+        let event = LiveEvent::ReservationCommented {
+            reservation: reservation("alice", "c1"),
+            comment: reservation_comment::Model {
+                id: "m1".to_string(),
+                reservation_id: "r1".to_string(),
+                author_id: Some("admin".to_string()),
+                body: "Could you move it an hour later?".to_string(),
+                created_at: dt("2025-03-02T09:00:00+08:00"),
+            },
+        };
+        let only = |ids: &[&str]| ClassroomScope::Only(ids.iter().map(|s| s.to_string()).collect());
+
+        assert_eq!(event.kind(), "reservation_commented");
+        assert!(subscriber("alice", Role::User, only(&[])).sees(&event));
+        assert!(!subscriber("bob", Role::User, only(&[])).sees(&event));
+        assert!(subscriber("ta", Role::Assistant, only(&["c1"])).sees(&event));
+        assert!(!subscriber("ta", Role::Assistant, only(&["c2"])).sees(&event));
+    }
+
+    #[test]
+    fn test_key_events_reach_key_managers_borrower_and_reviewers() {
+        let event = key_borrowed("alice", "c1");
+        let only = |ids: &[&str]| ClassroomScope::Only(ids.iter().map(|s| s.to_string()).collect());
+
+        assert!(subscriber("admin", Role::Admin, ClassroomScope::All).sees(&event));
+        assert!(subscriber("alice", Role::User, only(&[])).sees(&event));
+        assert!(!subscriber("bob", Role::User, only(&[])).sees(&event));
+        assert!(subscriber("ta", Role::Assistant, only(&["c1"])).sees(&event));
+        assert!(!subscriber("ta", Role::Assistant, only(&["c2"])).sees(&event));
+    }
+
+    #[test]
+    fn test_announcements_follow_their_audience() {
+        let user = subscriber("alice", Role::User, ClassroomScope::Only(Vec::new()));
+        let admin = subscriber("admin", Role::Admin, ClassroomScope::All);
+        let for_all = LiveEvent::AnnouncementPublished {
+            announcement: announcement(AnnouncementAudience::All),
+        };
+        let for_admins = LiveEvent::AnnouncementPublished {
+            announcement: announcement(AnnouncementAudience::Admins),
+        };
+
+        assert!(user.sees(&for_all));
+        assert!(!user.sees(&for_admins));
+        assert!(admin.sees(&for_admins));
+    }
+
+    #[test]
+    fn test_only_dashboard_domain_events_go_live() {
+        let created = DomainEvent::ReservationCreated {
+            reservation: reservation("alice", "c1"),
+        };
+        let live = LiveEvent::from_domain(&created).unwrap();
+        assert_eq!(live.kind(), "reservation_created");
+        let json = serde_json::to_value(&live).unwrap();
+        assert_eq!(json["type"], "reservation_created");
+        assert_eq!(json["reservation"]["id"], "r1");
+
+        let nudged = DomainEvent::ReservationNudged {
+            reservation: reservation("alice", "c1"),
+        };
+        assert_eq!(LiveEvent::from_domain(&nudged), None);
+    }
+}
@@ -0,0 +1,111 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+
+use crate::{
+    email_templates::Locale,
+    entities::{announcement, classroom},
+};
+
+/// The language picked from `Accept-Language`: the supported tag with the
+/// highest weight, or `None` when the header is missing or names no
+/// supported language.
+pub fn negotiate(accept_language: &str) -> Option<Locale> {
+    let mut best: Option<(Locale, f32)> = None;
+    for entry in accept_language.split(',') {
+        let mut parts = entry.split(';');
+        let tag = parts.next().unwrap_or_default();
+        let weight = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        // `q=0` means "not acceptable"; equal weights keep the earlier tag
+        if weight <= 0.0 || best.is_some_and(|(_, w)| w >= weight) {
+            continue;
+        }
+        if let Some(locale) = Locale::from_tag(tag) {
+            best = Some((locale, weight));
+        }
+    }
+    best.map(|(locale, _)| locale)
+}
+
+/// Language negotiated from the request's `Accept-Language` header.
+pub struct AcceptLanguage(pub Option<Locale>);
+
+impl<S: Send + Sync> FromRequestParts<S> for AcceptLanguage {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(
+            parts
+                .headers
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(negotiate),
+        ))
+    }
+}
+
+/// The translation for `locale`, unless it is missing or blank.
+fn translation<'a>(
+    locale: Locale,
+    en: &'a Option<String>,
+    zh_tw: &'a Option<String>,
+) -> Option<&'a String> {
+    match locale {
+        Locale::En => en.as_ref(),
+        Locale::ZhTw => zh_tw.as_ref(),
+    }
+    .filter(|text| !text.trim().is_empty())
+}
+
+/// Content with translated text fields. Localizing replaces the main fields
+/// with their translation where there is one; the untranslated text stays as
+/// it was entered.
+pub trait Localize {
+    fn localize(&mut self, locale: Locale);
+
+    fn localized(mut self, locale: Option<Locale>) -> Self
+    where
+        Self: Sized,
+    {
+        if let Some(locale) = locale {
+            self.localize(locale);
+        }
+        self
+    }
+}
+
+impl Localize for classroom::Model {
+    fn localize(&mut self, locale: Locale) {
+        if let Some(name) = translation(locale, &self.name_en, &self.name_zh_tw) {
+            self.name = name.clone();
+        }
+        if let Some(description) =
+            translation(locale, &self.description_en, &self.description_zh_tw)
+        {
+            self.description = description.clone();
+        }
+    }
+}
+
+impl Localize for announcement::Model {
+    fn localize(&mut self, locale: Locale) {
+        if let Some(title) = translation(locale, &self.title_en, &self.title_zh_tw) {
+            self.title = title.clone();
+        }
+        if let Some(content) = translation(locale, &self.content_en, &self.content_zh_tw) {
+            self.content = content.clone();
+        }
+    }
+}
+
+impl<T: Localize> Localize for Vec<T> {
+    fn localize(&mut self, locale: Locale) {
+        for item in self.iter_mut() {
+            item.localize(locale);
+        }
+    }
+}
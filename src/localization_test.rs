@@ -0,0 +1,59 @@
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+
+    use super::super::email_templates::Locale;
+    use super::super::entities::{
+        announcement,
+        sea_orm_active_enums::{AnnouncementAudience, AnnouncementCategory},
+    };
+    use super::super::localization::{Localize, negotiate};
+
+    fn announcement() -> announcement::Model {
+        announcement::Model {
+            id: "a".to_string(),
+            title: "停電通知".to_string(),
+            content: "A 棟明天停電".to_string(),
+            published_at: DateTime::parse_from_rfc3339("2025-03-10T10:00:00+08:00").unwrap(),
+            created_by: None,
+            emergency: false,
+            pinned: false,
+            category: AnnouncementCategory::System,
+            title_en: Some("Power outage".to_string()),
+            title_zh_tw: None,
+            content_en: Some("  ".to_string()),
+            content_zh_tw: None,
+            audience: AnnouncementAudience::All,
+            audience_classroom_id: None,
+        }
+    }
+
+    #[test]
+    fn picks_highest_weighted_supported_language() {
+        assert_eq!(negotiate("zh-TW"), Some(Locale::ZhTw));
+        assert_eq!(negotiate("fr-FR, en;q=0.8, zh-TW;q=0.5"), Some(Locale::En));
+        assert_eq!(negotiate("en;q=0.3, zh-Hant;q=0.9"), Some(Locale::ZhTw));
+        assert_eq!(negotiate("en-US, zh-TW"), Some(Locale::En));
+    }
+
+    #[test]
+    fn ignores_unsupported_and_refused_languages() {
+        assert_eq!(negotiate("fr, de;q=0.5"), None);
+        assert_eq!(negotiate("en;q=0, zh-TW;q=0.1"), Some(Locale::ZhTw));
+        assert_eq!(negotiate(""), None);
+    }
+
+    #[test]
+    fn uses_translations_that_are_filled_in() {
+        let localized = announcement().localized(Some(Locale::En));
+        assert_eq!(localized.title, "Power outage");
+        // A blank translation keeps the original text
+        assert_eq!(localized.content, "A 棟明天停電");
+    }
+
+    #[test]
+    fn keeps_original_without_translation_or_language() {
+        assert_eq!(announcement().localized(Some(Locale::ZhTw)), announcement());
+        assert_eq!(announcement().localized(None), announcement());
+    }
+}
@@ -0,0 +1,244 @@
+use std::{cmp::Reverse, net::IpAddr};
+
+use axum::http::HeaderMap;
+use chrono::{Duration, Utc};
+use nanoid::nanoid;
+use redis::{AsyncCommands, RedisError};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState, availability::campus_offset, email_client::send_email_to_user, email_templates,
+    entities::user, redis_breaker::RedisConnection,
+};
+
+const LOCKED_USERS_KEY: &str = "login_locked_users";
+
+/// Brute-force protection for `/user/login`.
+#[derive(Clone)]
+pub struct LoginGuardConfig {
+    /// Failed logins for one email within `window` before it is throttled
+    pub max_failures_per_email: u32,
+    /// Failed logins from one IP within `window` before it is throttled
+    pub max_failures_per_ip: u32,
+    pub window: Duration,
+    /// Lock the account once its email is throttled, until the owner follows
+    /// the emailed unlock link or an admin unlocks it
+    pub lock_accounts: bool,
+    pub unlock_token_ttl: Duration,
+    /// Take the client IP from `X-Forwarded-For`; only safe behind a proxy
+    /// that sets it
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for LoginGuardConfig {
+    fn default() -> Self {
+        Self {
+            max_failures_per_email: 5,
+            max_failures_per_ip: 20,
+            window: Duration::minutes(15),
+            lock_accounts: false,
+            unlock_token_ttl: Duration::hours(24),
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct LockoutState {
+    pub user_id: String,
+    pub email: String,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub locked_at: DateTimeWithTimeZone,
+    /// Failed logins that led to the lock
+    pub failures: u32,
+}
+
+fn email_failures_key(email: &str) -> String {
+    format!("login_failures_email_{}", email.trim().to_lowercase())
+}
+
+fn ip_failures_key(ip: &str) -> String {
+    format!("login_failures_ip_{}", ip)
+}
+
+fn lock_key(user_id: &str) -> String {
+    format!("login_lock_{}", user_id)
+}
+
+fn unlock_token_key(token: &str) -> String {
+    format!("login_unlock_{}", token)
+}
+
+/// The client address failures are counted against: the first
+/// `X-Forwarded-For` entry when trusted, otherwise the peer address.
+pub fn client_ip(headers: &HeaderMap, peer: IpAddr, trust_forwarded_for: bool) -> IpAddr {
+    if trust_forwarded_for
+        && let Some(forwarded) = headers
+            .get("X-Forwarded-For")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .and_then(|v| v.trim().parse().ok())
+    {
+        return forwarded;
+    }
+    peer
+}
+
+/// Seconds until a counter with `failures` and `ttl_seconds` left allows
+/// another attempt, or `None` while it is under `max`.
+pub fn retry_after(failures: u32, max: u32, ttl_seconds: i64) -> Option<u64> {
+    (failures >= max).then(|| ttl_seconds.max(1) as u64)
+}
+
+async fn counter(
+    redis: &mut RedisConnection,
+    key: &str,
+    max: u32,
+) -> Result<Option<u64>, RedisError> {
+    let failures: Option<u32> = redis.get(key).await?;
+    let Some(failures) = failures else {
+        return Ok(None);
+    };
+    let ttl: i64 = redis.ttl(key).await?;
+    Ok(retry_after(failures, max, ttl))
+}
+
+/// Seconds the caller has to wait before trying to log in as `email` from
+/// `ip`, or `None` if the attempt may go ahead.
+pub async fn throttled(
+    redis: &mut RedisConnection,
+    config: &LoginGuardConfig,
+    email: &str,
+    ip: IpAddr,
+) -> Result<Option<u64>, RedisError> {
+    let by_email = counter(
+        redis,
+        &email_failures_key(email),
+        config.max_failures_per_email,
+    )
+    .await?;
+    let by_ip = counter(
+        redis,
+        &ip_failures_key(&ip.to_string()),
+        config.max_failures_per_ip,
+    )
+    .await?;
+    Ok(by_email.max(by_ip))
+}
+
+/// Counts a failed login. Returns the failures for `email` in the current
+/// window.
+pub async fn record_failure(
+    redis: &mut RedisConnection,
+    config: &LoginGuardConfig,
+    email: &str,
+    ip: IpAddr,
+) -> Result<u32, RedisError> {
+    let window = config.window.num_seconds();
+    let mut email_failures = 0;
+    for key in [email_failures_key(email), ip_failures_key(&ip.to_string())] {
+        let failures: u32 = redis.incr(&key, 1).await?;
+        // The window starts at the first failure rather than sliding
+        if failures == 1 {
+            redis.expire::<_, ()>(&key, window).await?;
+        }
+        if email_failures == 0 {
+            email_failures = failures;
+        }
+    }
+    Ok(email_failures)
+}
+
+pub async fn clear_failures(redis: &mut RedisConnection, email: &str) -> Result<(), RedisError> {
+    redis.del(email_failures_key(email)).await
+}
+
+pub async fn lock_state(
+    redis: &mut RedisConnection,
+    user_id: &str,
+) -> Result<Option<LockoutState>, RedisError> {
+    let raw: Option<String> = redis.get(lock_key(user_id)).await?;
+    Ok(raw.and_then(|raw| serde_json::from_str(&raw).ok()))
+}
+
+/// Locks `user` and emails them a link to unlock the account.
+pub async fn lock(
+    state: &AppState,
+    redis: &mut RedisConnection,
+    user: &user::Model,
+    failures: u32,
+) -> Result<LockoutState, RedisError> {
+    let lockout = LockoutState {
+        user_id: user.id.clone(),
+        email: user.email.clone(),
+        locked_at: Utc::now().with_timezone(&campus_offset()),
+        failures,
+    };
+    redis
+        .set::<_, _, ()>(lock_key(&user.id), serde_json::to_string(&lockout).unwrap())
+        .await?;
+    redis.sadd::<_, _, ()>(LOCKED_USERS_KEY, &user.id).await?;
+
+    let token = nanoid!(32);
+    let ttl = state.config.login_guard.unlock_token_ttl;
+    redis
+        .set_ex::<_, _, ()>(unlock_token_key(&token), &user.id, ttl.num_seconds() as u64)
+        .await?;
+    let templates = &state.config.email_templates;
+    let email = email_templates::account_locked(
+        templates,
+        &token,
+        lockout.locked_at + ttl,
+        templates.locale_for(user),
+    );
+    if let Err(e) =
+        send_email_to_user(state.config.smtp.as_ref(), user, email.subject, email.body).await
+    {
+        warn!("Failed to send unlock email to {}: {}", user.id, e);
+    }
+    Ok(lockout)
+}
+
+/// Lifts the lock on an account and resets its failure count. Returns
+/// whether the account was locked.
+pub async fn unlock(redis: &mut RedisConnection, user_id: &str) -> Result<bool, RedisError> {
+    let Some(state) = lock_state(redis, user_id).await? else {
+        return Ok(false);
+    };
+    redis.del::<_, ()>(lock_key(user_id)).await?;
+    redis.srem::<_, _, ()>(LOCKED_USERS_KEY, user_id).await?;
+    clear_failures(redis, &state.email).await?;
+    Ok(true)
+}
+
+/// Unlocks the account an emailed unlock token was issued for. Tokens work
+/// once.
+pub async fn redeem_unlock_token(
+    redis: &mut RedisConnection,
+    token: &str,
+) -> Result<bool, RedisError> {
+    let user_id: Option<String> = redis.get_del(unlock_token_key(token)).await?;
+    match user_id {
+        Some(user_id) => unlock(redis, &user_id).await,
+        None => Ok(false),
+    }
+}
+
+/// Every locked account, most recently locked first.
+pub async fn locked_accounts(redis: &mut RedisConnection) -> Result<Vec<LockoutState>, RedisError> {
+    let user_ids: Vec<String> = redis.smembers(LOCKED_USERS_KEY).await?;
+    let mut locked = Vec::new();
+    for user_id in user_ids {
+        match lock_state(redis, &user_id).await? {
+            Some(state) => locked.push(state),
+            // Unlocked by deleting the key directly
+            None => redis.srem::<_, _, ()>(LOCKED_USERS_KEY, &user_id).await?,
+        }
+    }
+    locked.sort_by_key(|state| Reverse(state.locked_at));
+    Ok(locked)
+}
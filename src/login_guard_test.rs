@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use std::{net::IpAddr, sync::Arc};
+
+    use super::super::entities::sea_orm_active_enums::Role;
+    use super::super::login_guard::{client_ip, lock_state, retry_after};
+    use super::super::test_support::{add_user, login, router, state};
+    use axum::http::{HeaderMap, HeaderValue, StatusCode};
+    use tower::ServiceExt;
+
+    fn peer() -> IpAddr {
+        "10.0.0.5".parse().unwrap()
+    }
+
+    fn forwarded(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("X-Forwarded-For", HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_forwarded_for_ignored_unless_trusted() {
+        let headers = forwarded("203.0.113.7");
+        assert_eq!(client_ip(&headers, peer(), false), peer());
+        assert_eq!(
+            client_ip(&headers, peer(), true),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_forwarded_for_uses_first_hop() {
+        let headers = forwarded(" 203.0.113.7 , 10.0.0.1");
+        assert_eq!(
+            client_ip(&headers, peer(), true),
+            "203.0.113.7".parse::<IpAddr>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_invalid_forwarded_for_falls_back_to_peer() {
+        assert_eq!(client_ip(&forwarded("unknown"), peer(), true), peer());
+        assert_eq!(client_ip(&HeaderMap::new(), peer(), true), peer());
+    }
+
+    #[test]
+    fn test_retry_after_once_limit_reached() {
+        assert_eq!(retry_after(4, 5, 600), None);
+        assert_eq!(retry_after(5, 5, 600), Some(600));
+        assert_eq!(retry_after(7, 5, 30), Some(30));
+    }
+
+    #[test]
+    fn test_retry_after_is_at_least_one_second() {
+        // A key without a TTL reports -1
+        assert_eq!(retry_after(5, 5, -1), Some(1));
+        assert_eq!(retry_after(5, 5, 0), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_failures_in_any_case_lock_the_account() {
+        let mut state = state().await;
+        let mut config = (*state.config).clone();
+        config.login_guard.lock_accounts = true;
+        config.login_guard.max_failures_per_email = 2;
+        state.config = Arc::new(config);
+        add_user(&state, "u1", "alice@example.com", Role::User).await;
+        let app = router(state.clone());
+
+        for email in ["Alice@Example.com", " ALICE@example.com"] {
+            let response = app.clone().oneshot(login(email, "wrong")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let mut redis = state.redis.clone();
+        assert!(lock_state(&mut redis, "u1").await.unwrap().is_some());
+    }
+}
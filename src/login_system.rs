@@ -1,8 +1,16 @@
+use std::sync::Arc;
+
 use crate::{
-    argon_hasher::verify, constants::{REDIS_EXPIRY, get_redis_set_options}, entities::{self, prelude::*, sea_orm_active_enums::Role, *}
+    argon_hasher::Hasher,
+    cache::CacheKey,
+    constants::{REDIS_EXPIRY, get_redis_set_options},
+    entities::{self, prelude::*, *},
+    permissions::{Permission, has_permission},
+    redis_breaker::RedisConnection,
 };
 use axum_login::{AuthUser, AuthnBackend, AuthzBackend, UserId};
-use redis::{AsyncCommands, aio::MultiplexedConnection};
+use futures_util::future::BoxFuture;
+use redis::AsyncCommands;
 use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
 use serde::Deserialize;
 use tracing::warn;
@@ -28,15 +36,106 @@ impl AuthUser for entities::user::Model {
     }
 }
 
+/// Where users are cached between requests. Caching is best effort: errors
+/// are logged and treated as a miss, so a failing cache only costs a
+/// database query.
+pub trait UserCache: Send + Sync {
+    fn get<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Option<user::Model>>;
+    fn put<'a>(&'a self, user: &'a user::Model) -> BoxFuture<'a, ()>;
+    /// Drops the cached user so the next request reads it from the database
+    fn forget<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, ()>;
+}
+
+/// Caches users in Redis for `REDIS_EXPIRY`, refreshed on every read.
+pub struct RedisUserCache {
+    redis: RedisConnection,
+}
+
+impl RedisUserCache {
+    pub fn new(redis: RedisConnection) -> Self {
+        Self { redis }
+    }
+}
+
+impl UserCache for RedisUserCache {
+    fn get<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, Option<user::Model>> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let cached: Option<String> = match redis
+                .get_ex(CacheKey::User(user_id).to_string(), REDIS_EXPIRY)
+                .await
+            {
+                Ok(user) => user,
+                Err(e) => {
+                    warn!("Failed to get user {} from Redis cache: {}", user_id, e);
+                    None
+                }
+            };
+            cached.and_then(|s| serde_json::from_str::<user::Model>(&s).ok())
+        })
+    }
+
+    fn put<'a>(&'a self, user: &'a user::Model) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let result: Result<(), redis::RedisError> = redis
+                .set_options(
+                    CacheKey::User(&user.id).to_string(),
+                    serde_json::to_string(user).unwrap(),
+                    get_redis_set_options(),
+                )
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to cache user {} in Redis: {}", user.id, e);
+            }
+            // A lookup may have found the ID missing before the user existed
+            let _: Result<(), redis::RedisError> =
+                redis.del(CacheKey::MissingUser(&user.id).to_string()).await;
+        })
+    }
+
+    fn forget<'a>(&'a self, user_id: &'a str) -> BoxFuture<'a, ()> {
+        Box::pin(async move {
+            let mut redis = self.redis.clone();
+            let result: Result<(), redis::RedisError> =
+                redis.del(CacheKey::User(user_id).to_string()).await;
+            if let Err(e) = result {
+                warn!("Failed to drop cached user {}: {}", user_id, e);
+            }
+        })
+    }
+}
+
+/// Caches nothing; every lookup goes to the database, so tests can build an
+/// `AuthBackend` without Redis.
+#[cfg(test)]
+pub struct NoUserCache;
+
+#[cfg(test)]
+impl UserCache for NoUserCache {
+    fn get<'a>(&'a self, _user_id: &'a str) -> BoxFuture<'a, Option<user::Model>> {
+        Box::pin(async { None })
+    }
+
+    fn put<'a>(&'a self, _user: &'a user::Model) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+
+    fn forget<'a>(&'a self, _user_id: &'a str) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+}
+
 #[derive(Clone)]
 pub struct AuthBackend {
     db: DatabaseConnection,
-    redis: MultiplexedConnection,
+    cache: Arc<dyn UserCache>,
+    hasher: Hasher,
 }
 
 impl AuthBackend {
-    pub fn new(db: DatabaseConnection, redis: MultiplexedConnection) -> Self {
-        Self { db, redis }
+    pub fn new(db: DatabaseConnection, cache: Arc<dyn UserCache>, hasher: Hasher) -> Self {
+        Self { db, cache, hasher }
     }
 }
 
@@ -55,19 +154,13 @@ impl AuthnBackend for AuthBackend {
             .await?;
 
         if let Some(ref user) = user {
-            if verify(password.as_bytes(), &user.password).await.is_ok() {
-                // Cache user on successful login (ignore errors - caching is best effort)
-                let mut redis = self.redis.clone();
-                let result: Result<(), redis::RedisError> = redis
-                    .set_options(
-                        format!("user_{}", user.id),
-                        serde_json::to_string(user).unwrap(),
-                        get_redis_set_options(),
-                    )
-                    .await;
-                if let Err(e) = result {
-                    warn!("Failed to cache user {} in Redis during login: {}", user.id, e);
-                }
+            if self
+                .hasher
+                .verify(password.as_bytes(), &user.password)
+                .await
+                .is_ok()
+            {
+                self.cache.put(user).await;
                 return Ok(Some(user.clone()));
             }
         }
@@ -75,55 +168,26 @@ impl AuthnBackend for AuthBackend {
     }
 
     async fn get_user(&self, user_id: &UserId<Self>) -> Result<Option<Self::User>, Self::Error> {
-        // Clone connection once for this handler
-        let mut redis = self.redis.clone();
-        
-        // Try to get from cache first
-        let cached_user: Option<String> = match redis
-            .get_ex(format!("user_{}", user_id.to_owned()), REDIS_EXPIRY)
-            .await
-        {
-            Ok(user) => user,
-            Err(e) => {
-                warn!("Failed to get user {} from Redis cache: {}", user_id, e);
-                None
-            }
-        };
-        
-        if let Some(user_str) = cached_user {
-            if let Ok(user) = serde_json::from_str::<entities::user::Model>(&user_str) {
-                return Ok(Some(user));
-            }
+        if let Some(user) = self.cache.get(user_id).await {
+            return Ok(Some(user));
         }
 
-        // Fallback to database
         let user = User::find_by_id(user_id.to_owned()).one(&self.db).await?;
-
-        // Cache the result for future requests (ignore errors - caching is best effort)
         if let Some(user) = &user {
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("user_{}", user_id.to_owned()),
-                    serde_json::to_string(user).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache user {} in Redis: {}", user_id, e);
-            }
+            self.cache.put(user).await;
         }
         Ok(user)
     }
 }
 
 impl AuthzBackend for AuthBackend {
-    type Permission = Role;
+    type Permission = Permission;
 
     async fn has_perm(
         &self,
         user: &Self::User,
         perm: Self::Permission,
     ) -> Result<bool, Self::Error> {
-        Ok(user.role == perm)
+        Ok(has_permission(&user.role, perm))
     }
 }
@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::super::{
+        argon_hasher::{Argon2Config, Hasher},
+        entities::{sea_orm_active_enums::Role, user},
+        login_system::{NoUserCache, UserCache},
+    };
+
+    fn hasher() -> Hasher {
+        Hasher::new(Argon2Config {
+            secret_key: b"test-secret".to_vec(),
+            iterations: 1,
+            parallelism: 1,
+            memory_cost: 64,
+        })
+    }
+
+    fn user() -> user::Model {
+        let now = Utc::now().fixed_offset();
+        user::Model {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            name: "Alice".to_string(),
+            email: "alice@example.com".to_string(),
+            password: String::new(),
+            phone_number: "0912345678".to_string(),
+            role: Role::User,
+            created_at: now,
+            updated_at: now,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    #[tokio::test]
+    async fn hasher_verifies_its_own_hashes() {
+        let hasher = hasher();
+        let hash = hasher.hash("correct horse").await.unwrap();
+        assert_eq!(hasher.verify("correct horse", &hash).await, Ok(true));
+        assert!(hasher.verify("wrong horse", &hash).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn hashers_with_different_secrets_do_not_agree() {
+        let hash = hasher().hash("correct horse").await.unwrap();
+        let other = Hasher::new(Argon2Config {
+            secret_key: b"other-secret".to_vec(),
+            iterations: 1,
+            parallelism: 1,
+            memory_cost: 64,
+        });
+        assert!(other.verify("correct horse", &hash).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn malformed_hash_is_rejected_without_panicking() {
+        assert!(hasher().verify("anything", "").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn no_user_cache_always_misses() {
+        let cache = NoUserCache;
+        let user = user();
+        cache.put(&user).await;
+        assert!(cache.get(&user.id).await.is_none());
+        cache.forget(&user.id).await;
+    }
+}
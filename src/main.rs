@@ -2,99 +2,396 @@
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-use std::net::SocketAddr;
-
-use axum::{Router, extract::Path, response::IntoResponse, routing::get};
-use axum_login::AuthManagerLayerBuilder;
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Extension, Json, Router,
+    extract::State,
+    http::{StatusCode, header},
+    middleware,
+    response::IntoResponse,
+    routing::get,
+};
+use axum_login::{AuthManagerLayerBuilder, permission_required};
 use dotenv::dotenv;
-use nanoid::nanoid;
-use redis::aio::MultiplexedConnection;
 use sea_orm::{Database, DatabaseConnection};
-use std::env;
+use serde::Serialize;
 use tower::ServiceBuilder;
-use tower_sessions::{
-    Expiry, SessionManagerLayer,
-    cookie::{SameSite, time::Duration},
-};
+use tower_sessions::{Expiry, MemoryStore, SessionManagerLayer, SessionStore};
 use tower_sessions_redis_store::{
     RedisStore,
     fred::prelude::{ClientLike, Config, Pool, Server, ServerConfig},
 };
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use utoipa::OpenApi;
-use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::server::{Server as ApiServer, ServerBuilder};
 use utoipa_scalar::{Scalar, Servable};
 
+mod account_deletion;
+#[cfg(test)]
+mod account_deletion_test;
+mod announcement_audience;
+#[cfg(test)]
+mod announcement_audience_test;
 mod argon_hasher;
+mod audit;
+#[cfg(test)]
+mod audit_test;
+mod availability;
+#[cfg(test)]
+mod availability_test;
+mod bans;
+#[cfg(test)]
+mod bans_test;
+mod cache;
+#[cfg(test)]
+mod cache_test;
+mod check_in;
+#[cfg(test)]
+mod check_in_test;
+mod classroom_history;
+#[cfg(test)]
+mod classroom_history_test;
+mod classroom_overview;
+#[cfg(test)]
+mod classroom_overview_test;
+mod closure_impact;
+mod concurrency;
+#[cfg(test)]
+mod concurrency_test;
+mod config;
+#[cfg(test)]
+mod config_test;
+mod constants;
+mod cors;
+#[cfg(test)]
+mod cors_test;
+mod datetime;
+#[cfg(test)]
+mod datetime_test;
+mod domain_events;
+#[cfg(test)]
+mod domain_events_test;
+mod door_events;
+#[cfg(test)]
+mod door_events_test;
+mod double_approval;
+#[cfg(test)]
+mod double_approval_test;
+mod email_change;
+#[cfg(test)]
+mod email_change_test;
 mod email_client;
+mod email_events;
+#[cfg(test)]
+mod email_events_test;
+mod email_queue;
+#[cfg(test)]
+mod email_queue_test;
+mod email_templates;
+#[cfg(test)]
+mod email_templates_test;
 mod entities;
+mod error;
+#[cfg(test)]
+mod error_test;
+mod event_duplicates;
+#[cfg(test)]
+mod event_duplicates_test;
+mod export;
+#[cfg(test)]
+mod export_test;
+mod fields;
+#[cfg(test)]
+mod fields_test;
+mod idempotency;
+#[cfg(test)]
+mod idempotency_test;
+mod integrity;
+#[cfg(test)]
+mod integrity_test;
+mod key_cabinet;
+#[cfg(test)]
+mod key_cabinet_test;
+mod key_lifecycle;
+#[cfg(test)]
+mod key_lifecycle_test;
+mod key_log_stats;
+#[cfg(test)]
+mod key_log_stats_test;
+mod key_receipts;
+mod live_events;
+#[cfg(test)]
+mod live_events_test;
+mod localization;
+#[cfg(test)]
+mod localization_test;
+mod login_guard;
+#[cfg(test)]
+mod login_guard_test;
 mod login_system;
+#[cfg(test)]
+mod login_system_test;
+mod memory_mode;
+#[cfg(test)]
+mod memory_mode_test;
+mod memory_redis;
+#[cfg(test)]
+mod memory_redis_test;
+mod merge_patch;
+#[cfg(test)]
+mod merge_patch_test;
+mod migration;
+#[cfg(test)]
+mod migration_test;
+mod overdue;
+#[cfg(test)]
+mod overdue_test;
+mod pagination;
+#[cfg(test)]
+mod pagination_test;
+mod password_reset;
+#[cfg(test)]
+mod password_reset_test;
+mod permissions;
+#[cfg(test)]
+mod permissions_test;
+mod pickup;
+#[cfg(test)]
+mod pickup_test;
+mod policy_simulation;
+#[cfg(test)]
+mod policy_simulation_test;
+mod public_stats;
+#[cfg(test)]
+mod public_stats_test;
+mod quota;
+#[cfg(test)]
+mod quota_test;
+mod rate_limit;
+#[cfg(test)]
+mod rate_limit_test;
+mod redis_breaker;
+#[cfg(test)]
+mod redis_breaker_test;
+mod reporting;
+#[cfg(test)]
+mod reporting_test;
+mod request_id;
+#[cfg(test)]
+mod request_id_test;
+mod reservation_completion;
+#[cfg(test)]
+mod reservation_completion_test;
+mod reservation_groups;
+#[cfg(test)]
+mod reservation_groups_test;
+mod reservation_lifecycle;
+#[cfg(test)]
+mod reservation_lifecycle_test;
+mod reservation_transfer;
+#[cfg(test)]
+mod reservation_transfer_test;
+mod retention;
+mod review_lock;
+#[cfg(test)]
+mod review_lock_test;
+mod review_nudge;
+#[cfg(test)]
+mod review_nudge_test;
 mod routes;
+mod seed;
+#[cfg(test)]
+mod seed_test;
+mod server_timing;
+#[cfg(test)]
+mod server_timing_test;
+mod sessions;
+mod slots;
+#[cfg(test)]
+mod slots_test;
+mod soft_launch;
+#[cfg(test)]
+mod soft_launch_test;
+mod student_id;
+#[cfg(test)]
+mod student_id_test;
+mod system_info;
+#[cfg(test)]
+mod system_info_test;
+#[cfg(test)]
+mod test_support;
+mod timetable;
+#[cfg(test)]
+mod timetable_test;
+mod user_conflicts;
+#[cfg(test)]
+mod user_conflicts_test;
 mod utils;
-mod constants;
 #[cfg(test)]
 mod utils_test;
+mod versioning;
+#[cfg(test)]
+mod versioning_test;
+mod walk_in;
+#[cfg(test)]
+mod walk_in_test;
+mod webhook;
+#[cfg(test)]
+mod webhook_test;
+mod workload;
+#[cfg(test)]
+mod workload_test;
 
-use argon_hasher::hash;
-use login_system::AuthBackend;
-use routes::announcement::announcement_router;
-use routes::black_list::black_list_router;
-use routes::classroom::classroom_router;
-use routes::infraction::infraction_router;
-use routes::key::key_router;
-use routes::password::password_router;
-use routes::reservation::reservation_router;
-use routes::user::user_router;
+use argon_hasher::Hasher;
+use cache::{CacheService, RedisCache};
+use login_system::{AuthBackend, RedisUserCache, UserCache};
+use routes::webhooks::WebhooksAddon;
 
-use crate::email_client::{EmailClientConfig, set_email_client_config};
+use crate::concurrency::{ConcurrencyLimits, render_metrics};
+use crate::live_events::LiveEvents;
+use crate::memory_redis::MemoryRedis;
+use crate::permissions::Permission;
+use crate::redis_breaker::{RedisBreakerConfig, RedisConnection};
+use crate::server_timing::Dependency;
 
 #[utoipa::path(
     get,
-    description = "Returns the Argon2 hash of the provided password",
+    description = "Returns a greeting message",
     tags = ["Root"],
-    path = "/argon2/{password}",
+    path = "/",
     responses(
-        (status = 200, description = "Returns the Argon2 hash of the provided password", body = String),
+        (status = 200, description = "Returns a greeting message", body = String),
     ),
-    params(
-        ("password" = String, Path, description = "The password to be hashed"),
-    )
 )]
-async fn argon2(Path(password): Path<String>) -> impl IntoResponse {
-    let hash = hash(password.as_bytes()).await.unwrap();
-    hash
+async fn root() -> impl IntoResponse {
+    "Hello, World!"
 }
 
 #[utoipa::path(
     get,
-    description = "Returns a newly generated NanoID",
+    description = "Returns concurrency limiter and cache metrics in the Prometheus text format",
     tags = ["Root"],
-    path = "/nanoid",
+    path = "/metrics",
     responses(
-        (status = 200, description = "Returns a newly generated NanoID", body = String),
+        (status = 200, description = "Returns concurrency limiter and cache metrics", body = String, content_type = "text/plain"),
     ),
 )]
-async fn nanoid() -> impl IntoResponse {
-    nanoid!()
+async fn metrics(Extension(limits): Extension<Arc<ConcurrencyLimits>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        render_metrics(&limits) + &cache::render_metrics(),
+    )
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct HealthResponse {
+    /// `ok`, `degraded` when Redis is down and caching and rate limiting are
+    /// bypassed, or `unavailable` when the database cannot be reached
+    status: &'static str,
+    database: bool,
+    redis: bool,
 }
 
 #[utoipa::path(
     get,
-    description = "Returns a greeting message",
+    description = "Reports whether the database and Redis are reachable. Redis being down only degrades the service, so it still answers 200",
     tags = ["Root"],
-    path = "/",
+    path = "/health",
     responses(
-        (status = 200, description = "Returns a greeting message", body = String),
+        (status = 200, description = "The service is up, possibly without Redis", body = HealthResponse),
+        (status = 503, description = "The database is unreachable", body = HealthResponse),
     ),
 )]
-async fn root() -> impl IntoResponse {
-    "Hello, World!"
+async fn health(State(state): State<AppState>) -> impl IntoResponse {
+    let health = check_health(&state).await;
+    (health.code(), Json(health))
+}
+
+/// Pings the database and Redis.
+async fn check_health(state: &AppState) -> HealthResponse {
+    let database = state.db.ping().await.is_ok();
+    // Skip the ping while the circuit is open so health checks do not keep
+    // waiting on an outage
+    let redis = !state.redis.is_degraded() && {
+        let mut redis = state.redis.clone();
+        redis::cmd("PING")
+            .query_async::<String>(&mut redis)
+            .await
+            .is_ok()
+    };
+    let status = match (database, redis) {
+        (false, _) => "unavailable",
+        (true, false) => "degraded",
+        (true, true) => "ok",
+    };
+    HealthResponse {
+        status,
+        database,
+        redis,
+    }
+}
+
+impl HealthResponse {
+    fn code(&self) -> StatusCode {
+        if self.database {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+    }
+}
+
+#[derive(Serialize, utoipa::ToSchema)]
+struct InternalHealthResponse {
+    #[serde(flatten)]
+    health: HealthResponse,
+    /// Whether the Redis circuit breaker is open and Redis is being skipped
+    redis_circuit_open: bool,
+    /// Whether the database, Redis and sessions are kept in process memory
+    in_memory: bool,
+    /// Whether the `/admin/debug` tools are served
+    debug_routes: bool,
+    version: &'static str,
+}
+
+#[utoipa::path(
+    get,
+    description = "Health of the service with the details operators need and the public check leaves out: whether Redis is bypassed, which stores are in use and whether debug tools are enabled",
+    tags = ["Root"],
+    path = "/internal/health",
+    responses(
+        (status = 200, description = "The service is up, possibly without Redis", body = InternalHealthResponse),
+        (status = 401, description = "Unauthorized", body = error::ErrorResponse),
+        (status = 403, description = "Forbidden", body = error::ErrorResponse),
+        (status = 503, description = "The database is unreachable", body = InternalHealthResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+async fn internal_health(State(state): State<AppState>) -> impl IntoResponse {
+    let health = check_health(&state).await;
+    (
+        health.code(),
+        Json(InternalHealthResponse {
+            health,
+            redis_circuit_open: state.redis.is_degraded(),
+            in_memory: state.config.in_memory,
+            debug_routes: state.config.debug.enabled,
+            version: env!("CARGO_PKG_VERSION"),
+        }),
+    )
 }
 
 #[derive(Clone)]
 struct AppState {
     db: DatabaseConnection,
-    redis: MultiplexedConnection,
+    redis: RedisConnection,
+    hasher: Hasher,
+    user_cache: Arc<dyn UserCache>,
+    cache: Arc<dyn CacheService>,
+    live: LiveEvents,
+    config: Arc<config::Config>,
 }
 
 struct SecurityAddon;
@@ -105,215 +402,61 @@ impl utoipa::Modify for SecurityAddon {
             components.add_security_scheme(
                 "session_cookie",
                 SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("id"))),
-            )
+            );
+            components.add_security_scheme(
+                "door_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+            components.add_security_scheme(
+                "reporting_token",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
         }
     }
 }
 
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Blacklist", description = "Blacklist endpoints")
-    ),
-    paths(
-        routes::black_list::create_black_list,
-        routes::black_list::update_black_list,
-        routes::black_list::list_black_list,
-        routes::black_list::get_black_list,
-        routes::black_list::delete_black_list,
-    ),
-    components(schemas(
-        entities::black_list::Model,
-        routes::black_list::UpdateBlackListBody,
-    ))
-)]
-struct BlacklistApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Password Reset", description = "Password reset endpoints")
-    ),
-    paths(
-        routes::password::forgot_password,
-        routes::password::verify_code,
-        routes::password::reset_password,
-    ),
-    components(schemas(
-        routes::password::ForgotPasswordBody,
-        routes::password::VerifyCodeBody,
-        routes::password::VerifyCodeResponse,
-        routes::password::ResetPasswordBody,
-    ))
-)]
-struct PasswordApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Infraction", description = "Infraction endpoints")
-    ),
-    paths(
-        routes::infraction::create_infraction,
-        routes::infraction::update_infraction,
-        routes::infraction::delete_infraction,
-        routes::infraction::list_infractions,
-        routes::infraction::get_infraction,
-    ),
-    components(schemas(
-        entities::infraction::Model,
-        routes::infraction::CreateInfractionBody,
-        routes::infraction::UpdateInfractionBody,
-    ))
-)]
-struct InfractionApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Announcement", description = "Announcement endpoints")
-    ),
-    paths(
-        routes::announcement::create_announcement,
-        routes::announcement::list_announcements,
-        routes::announcement::get_announcement,
-        routes::announcement::delete_announcement,
-    ),
-    components(schemas(
-        entities::announcement::Model,
-        routes::announcement::CreateAnnouncementBody,
-    ))
-)]
-struct AnnouncementApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Key", description = "Key endpoints")
-    ),
-    paths(
-        routes::key::create_key,
-        routes::key::update_key,
-        routes::key::delete_key,
-        routes::key::borrow_key,
-        routes::key::return_key,
-        routes::key::list_key_logs,
-        routes::key::list_key_logs_by_key
-    ),
-    components(schemas(
-        entities::key::Model,
-        entities::classroom::Model,
-        routes::key::CreateKeyBody,
-        routes::key::UpdateKeyBody,
-        routes::key::KeyResponse,
-        routes::key::BorrowKeyBody,
-        routes::key::ReturnKeyBody,
-        routes::key::KeyLogListQuery,
-        routes::key::KeyTransactionLogResponse
-    ))
-)]
-struct KeyApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Reservation", description = "Reservation endpoints")
-    ),
-    paths(
-        routes::reservation::review_reservation,
-        routes::reservation::create_reservation,
-        routes::reservation::update_reservation,
-        routes::reservation::get_reservations,
-        routes::reservation::get_all_reservations_for_self,
-        routes::reservation::admin_list_reservations,
-        routes::reservation::admin_get_reservation_by_id,
-        routes::reservation::cancel_reservation,
-        routes::reservation::get_self_reservations_filtered
-    ),
-    components(schemas(
-        entities::reservation::Model,
-        entities::sea_orm_active_enums::ReservationStatus,
-        routes::reservation::ReviewReservationBody,
-        routes::reservation::CreateReservationBody,
-        routes::reservation::UpdateReservationBody,
-        routes::reservation::GetReservationsQuery,
-        routes::reservation::SelfListQuery,
-        routes::reservation::AdminListQuery,
-        routes::reservation::PagedReservations
-    ))
-)]
-struct ReservationApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "User", description = "User endpoints")
-    ),
-    paths(
-        routes::user::register,
-        routes::user::login,
-        routes::user::logout,
-        routes::user::profile,
-        routes::user::get_user,
-        routes::user::update_password,
-        routes::user::update_profile
-    ),
-    components(schemas(
-        entities::user::Model,
-        entities::sea_orm_active_enums::Role,
-        login_system::Credentials,
-        routes::user::RegisterBody,
-        routes::user::UpdatePasswordBody,
-        routes::user::UserResponse,
-        routes::user::UpdateProfileBody
-    ))
-)]
-struct UserApi;
-
-#[derive(OpenApi)]
-#[openapi(
-    tags(
-        (name = "Classroom", description = "Classroom endpoints")
-    ),
-    paths(
-        routes::classroom::create_classroom,
-        routes::classroom::get_classroom,
-        routes::classroom::list_classrooms,
-        routes::classroom::update_classroom,
-        routes::classroom::update_classroom_photo,
-        routes::classroom::delete_classroom
-    ),
-    components(schemas(
-        routes::classroom::CreateClassroomBody,
-        entities::classroom::Model,
-        entities::sea_orm_active_enums::ClassroomStatus,
-        routes::classroom::GetClassroomResponse,
-        routes::classroom::GetClassroomKeyResponse,
-        routes::classroom::GetClassroomReservationResponse,
-        routes::classroom::GetClassroomKeyReservationResponse,
-        routes::classroom::UpdateClassroomBody,
-        routes::classroom::UpdateClassroomPhotoBody,
-        entities::key::Model,
-        entities::reservation::Model,
-    ))
-)]
-struct ClassroomApi;
+/// Builds the OpenAPI `servers` list for the current environment so the Scalar
+/// "try it" requests target the right host. Falls back to the relative paths
+/// used before when no public base URL is configured.
+fn api_servers(
+    public_base_url: Option<String>,
+    environment: &str,
+    version: &str,
+) -> Vec<ApiServer> {
+    match public_base_url {
+        Some(url) => vec![
+            ServerBuilder::new()
+                .url(url.trim_end_matches('/'))
+                .description(Some(format!("{} API v{}", environment, version)))
+                .build(),
+        ],
+        None => vec![
+            ServerBuilder::new()
+                .url("/api")
+                .description(Some(format!("Base API path when hosting (v{})", version)))
+                .build(),
+            ServerBuilder::new()
+                .url("/")
+                .description(Some(format!(
+                    "Base API path when running on local (v{})",
+                    version
+                )))
+                .build(),
+        ],
+    }
+}
 
 #[derive(OpenApi)]
 #[openapi(
-    nest((path = "/user", api = UserApi), (path = "/classroom", api = ClassroomApi), (path = "/reservation", api = ReservationApi), (path = "/key", api = KeyApi), (path = "/announcement", api = AnnouncementApi), (path = "/infraction", api = InfractionApi), (path = "/black_list", api = BlacklistApi), (path = "/password", api = PasswordApi) ),
     tags((name = "Root", description = "Root endpoints")),
     paths(
         root,
-        nanoid,
-        argon2,
+        metrics,
+        health,
+        internal_health,
     ),
-    modifiers(&SecurityAddon),
+    modifiers(&SecurityAddon, &WebhooksAddon),
     info(title = "Classroom Borrowing API", version = "1.0"),
-    servers(
-        (url = "/api", description = "Base API path when hosting"),
-        (url = "/", description = "Base API path when running on local")
-    ),
     components(
         schemas(
             entities::user::Model,
@@ -351,11 +494,24 @@ struct ClassroomApi;
             routes::password::VerifyCodeBody,
             routes::password::VerifyCodeResponse,
             routes::password::ResetPasswordBody,
+            HealthResponse,
+            InternalHealthResponse,
+            error::ErrorResponse,
         )
     )
 )]
 struct ApiDoc;
 
+/// The full API document: the root routes plus every module from
+/// `routes::modules` under its path.
+fn api_doc() -> utoipa::openapi::OpenApi {
+    routes::modules()
+        .into_iter()
+        .fold(ApiDoc::openapi(), |doc, module| {
+            doc.nest(module.path, (module.api)())
+        })
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
@@ -368,41 +524,195 @@ async fn main() {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let password_hashing_secret =
-        env::var("PASSWORD_HASHING_SECRET").expect("PASSWORD_HASHING_SECRET must be set");
+    let source = match config::Source::load() {
+        Ok(source) => source,
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if std::env::args().any(|arg| arg == "--migrate-only") {
+        return migrate_only(&source).await;
+    }
+    let config = match config::Config::from_source(&source) {
+        Ok(config) => Arc::new(config),
+        Err(e) => {
+            tracing::error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    if std::env::args().any(|arg| arg == "--seed") {
+        let demo = !std::env::args().any(|arg| arg == "--no-demo");
+        return seed_only(&source, &config, demo).await;
+    }
+    system_info::mark_started();
+    let in_memory = config.in_memory;
+    if in_memory {
+        tracing::warn!("IN_MEMORY is set: data lives in this process only and is lost on exit");
+    }
+
+    let hasher = Hasher::new(config.argon2.clone());
 
-    let argon2_config = argon_hasher::Argon2Config {
-        iterations: 4,
-        parallelism: 4,
-        memory_cost: 512,
-        secret_key: password_hashing_secret.into_bytes(),
+    // Serializers cannot reach `AppState`, so timestamps read the format
+    // from here
+    datetime::set_datetime_config(config.datetime.clone());
+
+    let (pool, redis_connection) = if in_memory {
+        (None, RedisConnection::in_memory(MemoryRedis::default()))
+    } else {
+        let (pool, redis_connection) =
+            connect_redis(&config.redis, config.redis_breaker.clone()).await;
+        (Some(pool), redis_connection)
     };
 
-    argon_hasher::set_config(argon2_config);
+    let mut db = match &config.database_url {
+        Some(database_url) => Database::connect(database_url).await.unwrap(),
+        None => memory_mode::connect_db().await.unwrap(),
+    };
+    if config.run_migrations {
+        migration::run(&db)
+            .await
+            .expect("Failed to apply migrations");
+    } else {
+        let pending = migration::pending(&db).await;
+        if !pending.is_empty() {
+            tracing::warn!(
+                "{} migration(s) are pending ({}); apply them with --migrate-only or RUN_MIGRATIONS=true",
+                pending.len(),
+                pending.join(", ")
+            );
+        }
+    }
+    db.set_metric_callback(|info| server_timing::record(Dependency::Db, info.elapsed));
 
-    let email_client_config = EmailClientConfig {
-        smtp_server: env::var("SMTP_SERVER").expect("SMTP_SERVER must be set"),
-        smtp_port: env::var("SMTP_PORT")
-            .expect("SMTP_PORT must be set")
-            .parse()
-            .unwrap(),
-        username: env::var("SMTP_USERNAME").expect("SMTP_USERNAME must be set"),
-        password: env::var("SMTP_PASSWORD").expect("SMTP_PASSWORD must be set"),
+    let user_cache: Arc<dyn UserCache> = Arc::new(RedisUserCache::new(redis_connection.clone()));
+    let cache: Arc<dyn CacheService> = Arc::new(RedisCache::new(redis_connection.clone()));
+
+    let app_state = AppState {
+        db: db,
+        redis: redis_connection,
+        hasher,
+        user_cache,
+        cache,
+        live: LiveEvents::default(),
+        config: config.clone(),
     };
 
-    set_email_client_config(email_client_config);
+    tokio::spawn(overdue::run(app_state.clone()));
+    tokio::spawn(email_queue::run(app_state.clone()));
+    tokio::spawn(bans::run(app_state.clone()));
+    tokio::spawn(reservation_completion::run(app_state.clone()));
+
+    let mut api_doc = api_doc();
+    api_doc.servers = Some(api_servers(
+        config.public_base_url.clone(),
+        &config.app_environment,
+        &api_doc.info.version,
+    ));
+
+    let app = match pool {
+        Some(pool) => app(app_state, RedisStore::new(pool), api_doc),
+        None => app(app_state, MemoryStore::default(), api_doc),
+    };
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    tracing::debug!("listening on {addr}");
+
+    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+}
+
+/// `--migrate-only`: applies pending migrations to `DATABASE_URL` and exits,
+/// so a fresh database can be set up without Redis, SMTP or anything else
+/// the server needs.
+async fn migrate_only(source: &config::Source) {
+    let Ok(database_url) = source.var("DATABASE_URL") else {
+        tracing::error!("DATABASE_URL must be set");
+        std::process::exit(1);
+    };
+    let db = Database::connect(&database_url).await.unwrap();
+    match migration::run(&db).await {
+        Ok(ran) if ran.is_empty() => tracing::info!("The database is up to date"),
+        Ok(ran) => tracing::info!("Applied {} migration(s)", ran.len()),
+        Err(e) => {
+            tracing::error!("Failed to apply migrations: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
+/// `--seed`: applies pending migrations, creates the first admin from
+/// `SEED_ADMIN_*` and, unless `--no-demo` is given, demo classrooms and
+/// announcements, then exits. Passwords are hashed with the server's
+/// settings so the admin can log in.
+async fn seed_only(source: &config::Source, config: &config::Config, demo: bool) {
+    if config.in_memory {
+        tracing::error!("--seed needs a database; IN_MEMORY data would be lost on exit");
+        std::process::exit(1);
+    }
+    let setting = |name: &str| source.var(name).ok().filter(|v| !v.trim().is_empty());
+    let Some(email) = setting("SEED_ADMIN_EMAIL") else {
+        tracing::error!("SEED_ADMIN_EMAIL must be set");
+        std::process::exit(1);
+    };
+    let admin = seed::AdminSeed {
+        email,
+        username: setting("SEED_ADMIN_USERNAME").unwrap_or_else(|| "admin".to_string()),
+        name: setting("SEED_ADMIN_NAME").unwrap_or_else(|| "Administrator".to_string()),
+        password: setting("SEED_ADMIN_PASSWORD"),
+    };
+
+    let database_url = config.database_url.as_deref().unwrap();
+    let db = Database::connect(database_url).await.unwrap();
+    if let Err(e) = migration::run(&db).await {
+        tracing::error!("Failed to apply migrations: {}", e);
+        std::process::exit(1);
+    }
+    let hasher = Hasher::new(config.argon2.clone());
+    let report = match seed::seed(&db, &hasher, admin, demo).await {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::error!("Failed to seed the database: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match report.admin {
+        Some(seed::SeededAdmin {
+            email,
+            generated_password: Some(password),
+        }) => tracing::warn!(
+            "Created admin {} with password {}; change it after logging in",
+            email,
+            password
+        ),
+        Some(admin) => tracing::info!("Created admin {}", admin.email),
+        None => tracing::info!("An admin exists already; none was created"),
+    }
+    tracing::info!(
+        "Created {} demo classroom(s), {} key(s) and {} announcement(s)",
+        report.classrooms,
+        report.keys,
+        report.announcements
+    );
+}
+
+/// The Redis pool backing sessions and the connection used for everything
+/// else.
+async fn connect_redis(
+    settings: &config::RedisSettings,
+    breaker: RedisBreakerConfig,
+) -> (Pool, RedisConnection) {
     let redis_pool_config = Config {
         server: ServerConfig::Centralized {
             server: Server {
-                host: env::var("REDIS_IP")
-                    .unwrap_or_else(|_| "localhost".into())
-                    .parse()
-                    .unwrap(),
-                port: env::var("REDIS_PORT")
-                    .unwrap_or_else(|_| "6379".into())
-                    .parse()
-                    .unwrap(),
+                host: settings.host.as_str().into(),
+                port: settings.port,
             },
         },
         ..Default::default()
@@ -411,60 +721,71 @@ async fn main() {
     let _ = pool.connect();
     pool.wait_for_connect().await.unwrap();
 
-    let redis_client = redis::Client::open(format!(
-        "redis://{}:{}",
-        env::var("REDIS_IP").unwrap(),
-        env::var("REDIS_PORT").unwrap()
-    ))
-    .unwrap();
-    let redis_connection = redis_client
-        .get_multiplexed_async_connection()
-        .await
-        .unwrap();
-
-    let session_store = RedisStore::new(pool);
-    let session_layer = SessionManagerLayer::new(session_store)
-        .with_secure(false)
-        .with_expiry(Expiry::OnInactivity(Duration::days(1)))
-        .with_same_site(SameSite::Lax);
+    let redis_client = redis::Client::open(settings.url()).unwrap();
+    let redis_connection = RedisConnection::new(
+        redis_client
+            .get_multiplexed_async_connection()
+            .await
+            .unwrap(),
+        breaker,
+    );
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let db = Database::connect(&database_url).await.unwrap();
+    (pool, redis_connection)
+}
 
-    let auth_backend = AuthBackend::new(db.clone(), redis_connection.clone());
+/// All routes and layers, with sessions kept in `session_store`.
+fn app<S: SessionStore + Clone>(
+    app_state: AppState,
+    session_store: S,
+    api_doc: utoipa::openapi::OpenApi,
+) -> Router {
+    let cookie = &app_state.config.session_cookie;
+    let session_layer = SessionManagerLayer::new(session_store)
+        .with_secure(cookie.secure)
+        .with_expiry(Expiry::OnInactivity(cookie.idle_timeout))
+        .with_same_site(cookie.same_site);
+    let auth_backend = AuthBackend::new(
+        app_state.db.clone(),
+        app_state.user_cache.clone(),
+        app_state.hasher.clone(),
+    );
     let auth_layer = AuthManagerLayerBuilder::new(auth_backend, session_layer).build();
+    let config = app_state.config.clone();
+    let limits = Arc::new(ConcurrencyLimits::new(&config.concurrency));
 
-    let image_service_ip = env::var("IMAGE_SERVICE_IP").expect("IMAGE_SERVICE_IP must be set");
-    let image_service_api_key =
-        env::var("IMAGE_SERVICE_API_KEY").expect("IMAGE_SERVICE_API_KEY must be set");
-
-    let app_state = AppState {
-        db: db,
-        redis: redis_connection,
-    };
-
-    let app = Router::new()
+    let router = Router::new()
         .route("/", get(root))
-        .route("/nanoid", get(nanoid))
-        .route("/argon2/{password}", get(argon2))
-        .nest("/user", user_router())
-        .nest(
-            "/classroom",
-            classroom_router(image_service_ip, image_service_api_key),
-        )
-        .nest("/reservation", reservation_router())
-        .nest("/key", key_router())
-        .nest("/announcement", announcement_router())
-        .nest("/infraction", infraction_router())
-        .nest("/black_list", black_list_router())
-        .nest("/password", password_router())
-        .with_state(app_state)
-        .merge(Scalar::with_url("/docs", ApiDoc::openapi()))
-        .layer(ServiceBuilder::new().layer(auth_layer));
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
-    tracing::debug!("listening on {addr}");
-
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+        .route("/metrics", get(metrics))
+        .route("/health", get(health))
+        .route(
+            "/internal/health",
+            get(internal_health)
+                .route_layer(permission_required!(AuthBackend, Permission::ManageSystem)),
+        );
+    routes::modules()
+        .into_iter()
+        .fold(router, |router, module| {
+            router.nest(module.path, (module.router)())
+        })
+        .with_state(app_state.clone())
+        .merge(Scalar::with_url("/docs", api_doc))
+        // For route layers, which run without the router state
+        .layer(Extension(config.clone()))
+        .layer(Extension(limits))
+        // Inside the auth layer, so keys are kept per signed-in user
+        .layer(middleware::from_fn_with_state(
+            (app_state.redis, config.idempotency.clone()),
+            idempotency::track,
+        ))
+        .layer(ServiceBuilder::new().layer(auth_layer))
+        // Outside the auth layer, so preflights are answered without a
+        // session and refusals still carry the CORS headers browsers need
+        // to read them
+        .layer(cors::layer(&config.cors))
+        .layer(middleware::map_response(error::json_errors))
+        .layer(middleware::from_fn_with_state(
+            config.server_timing.clone(),
+            server_timing::track,
+        ))
+        .layer(middleware::from_fn(request_id::track))
 }
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use sea_orm::{ConnectOptions, Database, DatabaseConnection, DbErr};
+
+use crate::migration;
+
+/// Keeps the single SQLite connection, and with it the database, open for
+/// as long as the process runs.
+const KEEP_CONNECTION: Duration = Duration::from_secs(u32::MAX as u64);
+
+/// An empty SQLite database in memory, set up by the migrations.
+/// Queries run through the same entities as on Postgres; the few that use
+/// Postgres-only SQL, such as announcement search with `q=`, fail here.
+pub async fn connect_db() -> Result<DatabaseConnection, DbErr> {
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options
+        .max_connections(1)
+        .min_connections(1)
+        .idle_timeout(KEEP_CONNECTION)
+        .max_lifetime(KEEP_CONNECTION)
+        .sqlx_logging(false);
+    let db = Database::connect(options).await?;
+
+    migration::run(&db).await?;
+    Ok(db)
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Request, StatusCode, header},
+    };
+    use tower::ServiceExt;
+
+    use super::super::{
+        api_doc,
+        entities::sea_orm_active_enums::Role,
+        routes,
+        test_support::{add_user, json, login, router, sign_in, state},
+    };
+
+    #[tokio::test]
+    async fn serves_requests_without_postgres_or_redis() {
+        let app = router(state().await);
+        let response = app
+            .clone()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["status"], "ok");
+
+        let response = app
+            .oneshot(login("nobody@example.com", "wrong"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(json(response).await["code"], "unauthorized");
+    }
+
+    #[tokio::test]
+    async fn debug_tools_are_not_public() {
+        let app = router(state().await);
+        for uri in ["/argon2/secret", "/nanoid"] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+        let response = app
+            .oneshot(
+                Request::get("/internal/health")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn sessions_survive_between_requests() {
+        let state = state().await;
+        add_user(&state, "alice", "alice@example.com", Role::User).await;
+        let app = router(state);
+        let cookie = sign_in(&app, "alice@example.com").await;
+
+        let response = app
+            .oneshot(
+                Request::get("/user/profile")
+                    .header(header::COOKIE, cookie)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(json(response).await["username"], "alice");
+    }
+
+    #[test]
+    fn test_every_mounted_module_is_documented() {
+        let doc = api_doc();
+        for module in routes::modules() {
+            let api = (module.api)();
+            assert!(!api.paths.paths.is_empty(), "{} has no docs", module.path);
+            for path in api.paths.paths.keys() {
+                let mounted = format!("{}{}", module.path, path);
+                assert!(
+                    doc.paths.paths.contains_key(&mounted),
+                    "{} missing",
+                    mounted
+                );
+            }
+        }
+    }
+}
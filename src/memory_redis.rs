@@ -0,0 +1,349 @@
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use redis::{Arg, Cmd, ErrorKind, RedisError, Value};
+
+/// Commands the in-process store understands: the ones the application
+/// sends. Anything else is answered with an error like an unknown command
+/// would be.
+const COMMANDS: [&str; 21] = [
+    "PING", "GET", "MGET", "SET", "SETEX", "DEL", "EXISTS", "INCR", "INCRBY", "DECR", "DECRBY",
+    "GETEX", "GETDEL", "EXPIRE", "TTL", "SADD", "SREM", "SMEMBERS", "RPUSH", "LPOP", "FLUSHALL",
+];
+
+enum Data {
+    String(Vec<u8>),
+    Set(BTreeSet<Vec<u8>>),
+    List(VecDeque<Vec<u8>>),
+}
+
+struct Entry {
+    data: Data,
+    expires_at: Option<Instant>,
+}
+
+/// A stand-in for a Redis server kept in process memory, for running the
+/// application without Redis in tests and demos. Keys expire like in Redis;
+/// callers pass the current time so expiry can be tested without waiting.
+#[derive(Clone, Default)]
+pub struct MemoryRedis {
+    entries: Arc<Mutex<HashMap<Vec<u8>, Entry>>>,
+}
+
+fn error(message: &'static str) -> RedisError {
+    RedisError::from((ErrorKind::ResponseError, message))
+}
+
+fn wrong_type() -> RedisError {
+    error("WRONGTYPE Operation against a key holding the wrong kind of value")
+}
+
+fn number<T: std::str::FromStr>(arg: &[u8]) -> Result<T, RedisError> {
+    std::str::from_utf8(arg)
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| error("ERR value is not an integer or out of range"))
+}
+
+/// When a key expires given an `EX`/`PX`/`EXAT`/`PXAT` option and its value.
+fn expiry(option: &str, value: &[u8], now: Instant) -> Result<Instant, RedisError> {
+    let amount: u64 = number(value)?;
+    let from_epoch = |at: Duration| {
+        let since_epoch = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        now + at.saturating_sub(since_epoch)
+    };
+    match option {
+        "EX" => Ok(now + Duration::from_secs(amount)),
+        "PX" => Ok(now + Duration::from_millis(amount)),
+        "EXAT" => Ok(from_epoch(Duration::from_secs(amount))),
+        "PXAT" => Ok(from_epoch(Duration::from_millis(amount))),
+        _ => Err(error("ERR syntax error")),
+    }
+}
+
+fn bulk(bytes: &[u8]) -> Value {
+    Value::BulkString(bytes.to_vec())
+}
+
+impl MemoryRedis {
+    /// Runs one command given as its arguments, name first.
+    pub fn execute(&self, args: &[Vec<u8>], now: Instant) -> Result<Value, RedisError> {
+        let Some((name, args)) = args.split_first() else {
+            return Err(error("ERR empty command"));
+        };
+        let name = String::from_utf8_lossy(name).to_uppercase();
+        if !COMMANDS.contains(&name.as_str()) {
+            return Err(error("ERR unknown command"));
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires_at.is_none_or(|at| at > now));
+        let arg = |i: usize| {
+            args.get(i)
+                .map(Vec::as_slice)
+                .ok_or_else(|| error("ERR wrong number of arguments"))
+        };
+
+        match name.as_str() {
+            "PING" => Ok(Value::SimpleString("PONG".to_string())),
+            "FLUSHALL" => {
+                entries.clear();
+                Ok(Value::Okay)
+            }
+            "GET" => match entries.get(arg(0)?) {
+                Some(Entry {
+                    data: Data::String(value),
+                    ..
+                }) => Ok(bulk(value)),
+                Some(_) => Err(wrong_type()),
+                None => Ok(Value::Nil),
+            },
+            "MGET" => {
+                arg(0)?;
+                Ok(Value::Array(
+                    args.iter()
+                        .map(|key| match entries.get(key.as_slice()) {
+                            Some(Entry {
+                                data: Data::String(value),
+                                ..
+                            }) => bulk(value),
+                            _ => Value::Nil,
+                        })
+                        .collect(),
+                ))
+            }
+            "SETEX" => {
+                let expires_at = Some(expiry("EX", arg(1)?, now)?);
+                entries.insert(
+                    arg(0)?.to_vec(),
+                    Entry {
+                        data: Data::String(arg(2)?.to_vec()),
+                        expires_at,
+                    },
+                );
+                Ok(Value::Okay)
+            }
+            "SET" => {
+                let key = arg(0)?.to_vec();
+                let value = arg(1)?.to_vec();
+                let mut expires_at = None;
+                let mut keep_ttl = false;
+                let mut only_if_missing = false;
+                let mut only_if_present = false;
+                let mut return_old = false;
+                let mut i = 2;
+                while i < args.len() {
+                    let option = String::from_utf8_lossy(&args[i]).to_uppercase();
+                    match option.as_str() {
+                        "NX" => only_if_missing = true,
+                        "XX" => only_if_present = true,
+                        "GET" => return_old = true,
+                        "KEEPTTL" => keep_ttl = true,
+                        "EX" | "PX" | "EXAT" | "PXAT" => {
+                            expires_at = Some(expiry(&option, arg(i + 1)?, now)?);
+                            i += 1;
+                        }
+                        _ => return Err(error("ERR syntax error")),
+                    }
+                    i += 1;
+                }
+                let old = match entries.get(&key) {
+                    Some(Entry {
+                        data: Data::String(old),
+                        expires_at,
+                    }) => Some((old.clone(), *expires_at)),
+                    Some(_) if return_old => return Err(wrong_type()),
+                    Some(entry) => Some((Vec::new(), entry.expires_at)),
+                    None => None,
+                };
+                let reply = |old: Option<Vec<u8>>| match (return_old, old) {
+                    (true, Some(old)) => Value::BulkString(old),
+                    (true, None) => Value::Nil,
+                    (false, _) => Value::Okay,
+                };
+                if (only_if_missing && old.is_some()) || (only_if_present && old.is_none()) {
+                    return Ok(match return_old {
+                        true => reply(old.map(|(value, _)| value)),
+                        false => Value::Nil,
+                    });
+                }
+                if keep_ttl {
+                    expires_at = old.as_ref().and_then(|(_, at)| *at);
+                }
+                entries.insert(
+                    key,
+                    Entry {
+                        data: Data::String(value),
+                        expires_at,
+                    },
+                );
+                Ok(reply(old.map(|(value, _)| value)))
+            }
+            "DEL" | "EXISTS" => {
+                let count = args
+                    .iter()
+                    .filter(|key| match name.as_str() {
+                        "DEL" => entries.remove(key.as_slice()).is_some(),
+                        _ => entries.contains_key(key.as_slice()),
+                    })
+                    .count();
+                Ok(Value::Int(count as i64))
+            }
+            "INCR" | "INCRBY" | "DECR" | "DECRBY" => {
+                let amount = match name.as_str() {
+                    "INCRBY" | "DECRBY" => number::<i64>(arg(1)?)?,
+                    _ => 1,
+                };
+                let step = if name.starts_with("INCR") {
+                    amount
+                } else {
+                    -amount
+                };
+                let entry = entries.entry(arg(0)?.to_vec()).or_insert(Entry {
+                    data: Data::String(b"0".to_vec()),
+                    expires_at: None,
+                });
+                let Data::String(value) = &mut entry.data else {
+                    return Err(wrong_type());
+                };
+                let next = number::<i64>(value)? + step;
+                *value = next.to_string().into_bytes();
+                Ok(Value::Int(next))
+            }
+            "GETEX" => {
+                let key = arg(0)?;
+                let option = args
+                    .get(1)
+                    .map(|o| String::from_utf8_lossy(o).to_uppercase());
+                let new_expiry = match option.as_deref() {
+                    None => None,
+                    Some("PERSIST") => Some(None),
+                    Some(option) => Some(Some(expiry(option, arg(2)?, now)?)),
+                };
+                match entries.get_mut(key) {
+                    Some(entry) => {
+                        let Data::String(value) = &entry.data else {
+                            return Err(wrong_type());
+                        };
+                        let value = bulk(value);
+                        if let Some(at) = new_expiry {
+                            entry.expires_at = at;
+                        }
+                        Ok(value)
+                    }
+                    None => Ok(Value::Nil),
+                }
+            }
+            "GETDEL" => match entries.remove(arg(0)?) {
+                Some(Entry {
+                    data: Data::String(value),
+                    ..
+                }) => Ok(Value::BulkString(value)),
+                Some(entry) => {
+                    entries.insert(arg(0)?.to_vec(), entry);
+                    Err(wrong_type())
+                }
+                None => Ok(Value::Nil),
+            },
+            "EXPIRE" => {
+                let at = expiry("EX", arg(1)?, now)?;
+                match entries.get_mut(arg(0)?) {
+                    Some(entry) => {
+                        entry.expires_at = Some(at);
+                        Ok(Value::Int(1))
+                    }
+                    None => Ok(Value::Int(0)),
+                }
+            }
+            "TTL" => Ok(Value::Int(match entries.get(arg(0)?) {
+                None => -2,
+                Some(Entry {
+                    expires_at: None, ..
+                }) => -1,
+                Some(Entry {
+                    expires_at: Some(at),
+                    ..
+                }) => at.duration_since(now).as_secs_f64().round() as i64,
+            })),
+            "SADD" | "SREM" => {
+                let key = arg(0)?.to_vec();
+                arg(1)?;
+                let entry = entries.entry(key.clone()).or_insert(Entry {
+                    data: Data::Set(BTreeSet::new()),
+                    expires_at: None,
+                });
+                let Data::Set(members) = &mut entry.data else {
+                    return Err(wrong_type());
+                };
+                let changed = args[1..]
+                    .iter()
+                    .filter(|member| match name.as_str() {
+                        "SADD" => members.insert(member.to_vec()),
+                        _ => members.remove(member.as_slice()),
+                    })
+                    .count();
+                if members.is_empty() {
+                    entries.remove(&key);
+                }
+                Ok(Value::Int(changed as i64))
+            }
+            "SMEMBERS" => match entries.get(arg(0)?) {
+                Some(Entry {
+                    data: Data::Set(members),
+                    ..
+                }) => Ok(Value::Array(members.iter().map(|m| bulk(m)).collect())),
+                Some(_) => Err(wrong_type()),
+                None => Ok(Value::Array(Vec::new())),
+            },
+            "RPUSH" => {
+                arg(1)?;
+                let entry = entries.entry(arg(0)?.to_vec()).or_insert(Entry {
+                    data: Data::List(VecDeque::new()),
+                    expires_at: None,
+                });
+                let Data::List(items) = &mut entry.data else {
+                    return Err(wrong_type());
+                };
+                items.extend(args[1..].iter().cloned());
+                Ok(Value::Int(items.len() as i64))
+            }
+            "LPOP" => {
+                let key = arg(0)?.to_vec();
+                let count = args.get(1).map(|c| number::<usize>(c)).transpose()?;
+                let Some(entry) = entries.get_mut(&key) else {
+                    return Ok(Value::Nil);
+                };
+                let Data::List(items) = &mut entry.data else {
+                    return Err(wrong_type());
+                };
+                let popped: Vec<Value> = (0..count.unwrap_or(1))
+                    .map_while(|_| items.pop_front())
+                    .map(Value::BulkString)
+                    .collect();
+                if items.is_empty() {
+                    entries.remove(&key);
+                }
+                Ok(match count {
+                    Some(_) => Value::Array(popped),
+                    None => popped.into_iter().next().unwrap_or(Value::Nil),
+                })
+            }
+            _ => unreachable!("{} is listed in COMMANDS", name),
+        }
+    }
+
+    pub fn execute_cmd(&self, cmd: &Cmd) -> Result<Value, RedisError> {
+        let args: Vec<Vec<u8>> = cmd
+            .args_iter()
+            .filter_map(|arg| match arg {
+                Arg::Simple(bytes) => Some(bytes.to_vec()),
+                Arg::Cursor => None,
+            })
+            .collect();
+        self.execute(&args, Instant::now())
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use redis::Value;
+
+    use super::super::memory_redis::MemoryRedis;
+
+    fn run(store: &MemoryRedis, now: Instant, args: &[&str]) -> Value {
+        let args: Vec<Vec<u8>> = args.iter().map(|a| a.as_bytes().to_vec()).collect();
+        store.execute(&args, now).unwrap()
+    }
+
+    fn bulk(value: &str) -> Value {
+        Value::BulkString(value.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn keys_expire_like_in_redis() {
+        let store = MemoryRedis::default();
+        let now = Instant::now();
+        assert_eq!(
+            run(&store, now, &["SET", "a", "1", "EX", "60"]),
+            Value::Okay
+        );
+        assert_eq!(run(&store, now, &["TTL", "a"]), Value::Int(60));
+        assert_eq!(
+            run(&store, now + Duration::from_secs(59), &["GET", "a"]),
+            bulk("1")
+        );
+        assert_eq!(
+            run(&store, now + Duration::from_secs(60), &["GET", "a"]),
+            Value::Nil
+        );
+        assert_eq!(run(&store, now, &["TTL", "a"]), Value::Int(-2));
+    }
+
+    #[test]
+    fn set_options_and_counters() {
+        let store = MemoryRedis::default();
+        let now = Instant::now();
+        assert_eq!(run(&store, now, &["SET", "a", "1", "NX"]), Value::Okay);
+        assert_eq!(run(&store, now, &["SET", "a", "2", "NX"]), Value::Nil);
+        assert_eq!(run(&store, now, &["SET", "a", "3", "GET"]), bulk("1"));
+        assert_eq!(run(&store, now, &["INCRBY", "a", "2"]), Value::Int(5));
+        assert_eq!(run(&store, now, &["DECR", "a"]), Value::Int(4));
+        assert_eq!(run(&store, now, &["GETDEL", "a"]), bulk("4"));
+        assert_eq!(run(&store, now, &["EXISTS", "a"]), Value::Int(0));
+        assert!(
+            store
+                .execute(&[b"HGETALL".to_vec(), b"a".to_vec()], now)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn sets_and_lists() {
+        let store = MemoryRedis::default();
+        let now = Instant::now();
+        assert_eq!(
+            run(&store, now, &["SADD", "s", "b", "a", "b"]),
+            Value::Int(2)
+        );
+        assert_eq!(
+            run(&store, now, &["SMEMBERS", "s"]),
+            Value::Array(vec![bulk("a"), bulk("b")])
+        );
+        assert!(
+            store
+                .execute(&[b"RPUSH".to_vec(), b"s".to_vec(), b"x".to_vec()], now)
+                .is_err()
+        );
+        assert_eq!(
+            run(&store, now, &["RPUSH", "l", "1", "2", "3"]),
+            Value::Int(3)
+        );
+        assert_eq!(run(&store, now, &["LPOP", "l"]), bulk("1"));
+        assert_eq!(
+            run(&store, now, &["LPOP", "l", "5"]),
+            Value::Array(vec![bulk("2"), bulk("3")])
+        );
+        assert_eq!(run(&store, now, &["EXISTS", "l"]), Value::Int(0));
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Deserializer};
+
+/// One field of a JSON Merge Patch (RFC 7396) body: left out, explicitly
+/// `null`, or a new value. Fields must be marked `#[serde(default)]` so a
+/// missing one comes out as [`Patch::Absent`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Patch<T> {
+    /// Leave the field untouched
+    #[default]
+    Absent,
+    /// Clear the field
+    Null,
+    Value(T),
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<T>::deserialize(deserializer)? {
+            Some(value) => Patch::Value(value),
+            None => Patch::Null,
+        })
+    }
+}
+
+impl<T> Patch<T> {
+    /// The new value of a field that cannot be cleared; `None` leaves it
+    /// untouched.
+    pub fn required(self, field: &str) -> Result<Option<T>, String> {
+        match self {
+            Patch::Absent => Ok(None),
+            Patch::Null => Err(format!("'{}' cannot be null", field)),
+            Patch::Value(value) => Ok(Some(value)),
+        }
+    }
+
+    /// The new value of an optional field: `None` leaves it untouched,
+    /// `Some(None)` clears it.
+    pub fn optional(self) -> Option<Option<T>> {
+        match self {
+            Patch::Absent => None,
+            Patch::Null => Some(None),
+            Patch::Value(value) => Some(Some(value)),
+        }
+    }
+}
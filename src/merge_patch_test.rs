@@ -0,0 +1,44 @@
+#[cfg(test)]
+mod tests {
+    use super::super::merge_patch::Patch;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Body {
+        #[serde(default)]
+        name: Patch<String>,
+        #[serde(default)]
+        note: Patch<String>,
+    }
+
+    #[test]
+    fn test_absent_null_and_value_are_distinguished() {
+        let body: Body = serde_json::from_str(r#"{"note": null}"#).unwrap();
+        assert_eq!(body.name, Patch::Absent);
+        assert_eq!(body.note, Patch::Null);
+
+        let body: Body = serde_json::from_str(r#"{"name": "Lab"}"#).unwrap();
+        assert_eq!(body.name, Patch::Value("Lab".to_string()));
+        assert_eq!(body.note, Patch::Absent);
+    }
+
+    #[test]
+    fn test_required_fields_reject_null() {
+        assert_eq!(Patch::<i32>::Absent.required("capacity"), Ok(None));
+        assert_eq!(Patch::Value(30).required("capacity"), Ok(Some(30)));
+        assert_eq!(
+            Patch::<i32>::Null.required("capacity"),
+            Err("'capacity' cannot be null".to_string())
+        );
+    }
+
+    #[test]
+    fn test_optional_fields_can_be_cleared() {
+        assert_eq!(Patch::<String>::Absent.optional(), None);
+        assert_eq!(Patch::<String>::Null.optional(), Some(None));
+        assert_eq!(
+            Patch::Value("x".to_string()).optional(),
+            Some(Some("x".to_string()))
+        );
+    }
+}
@@ -0,0 +1,1591 @@
+use std::collections::HashSet;
+
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ConnectionTrait, DatabaseConnection, DatabaseTransaction, DbBackend, DbErr, EntityTrait,
+    Schema, Statement, TransactionTrait,
+    sea_query::{
+        Alias, ColumnDef, Expr, ForeignKey, ForeignKeyAction, ForeignKeyCreateStatement, Index,
+        Table, TableCreateStatement, extension::postgres::Type,
+    },
+};
+use tracing::info;
+
+/// Migrations already applied, kept in the same table `sea-orm-migration`
+/// uses so its CLI can take over.
+mod applied {
+    use sea_orm::entity::prelude::*;
+
+    #[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel)]
+    #[sea_orm(table_name = "seaql_migrations")]
+    pub struct Model {
+        #[sea_orm(primary_key, auto_increment = false)]
+        pub version: String,
+        pub applied_at: i64,
+    }
+
+    #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+    pub enum Relation {}
+
+    impl ActiveModelBehavior for ActiveModel {}
+}
+
+type Up = for<'a> fn(&'a DatabaseTransaction) -> BoxFuture<'a, Result<(), DbErr>>;
+
+/// A schema change, applied once in its own transaction.
+pub struct Migration {
+    /// Applied in order of name, so names start with the date they were
+    /// written, e.g. `m20250101_000001_create_tables`
+    pub name: &'static str,
+    pub(crate) up: Up,
+}
+
+/// Every migration, oldest first. Add new ones at the end.
+pub fn migrations() -> Vec<Migration> {
+    vec![
+        Migration {
+            name: "m20250101_000001_create_tables",
+            up: |db| Box::pin(create_tables(db)),
+        },
+        Migration {
+            name: "m20261017_000001_create_audit_log",
+            up: |db| Box::pin(create_audit_log(db)),
+        },
+        Migration {
+            name: "m20261017_000002_add_versions",
+            up: |db| Box::pin(add_versions(db)),
+        },
+        Migration {
+            name: "m20261017_000003_create_reservation_attachments",
+            up: |db| Box::pin(create_reservation_attachments(db)),
+        },
+        Migration {
+            name: "m20261017_000004_create_reservation_comments",
+            up: |db| Box::pin(create_reservation_comments(db)),
+        },
+        Migration {
+            name: "m20261017_000005_add_reservation_groups",
+            up: |db| Box::pin(add_reservation_groups(db)),
+        },
+        Migration {
+            name: "m20261017_000006_add_reservation_guests",
+            up: |db| Box::pin(add_reservation_guests(db)),
+        },
+        Migration {
+            name: "m20261017_000007_create_opening_hours",
+            up: |db| Box::pin(create_opening_hours(db)),
+        },
+        Migration {
+            name: "m20261017_000008_add_user_locale",
+            up: |db| Box::pin(add_user_locale(db)),
+        },
+        Migration {
+            name: "m20261017_000009_create_key_sync_actions",
+            up: |db| Box::pin(create_key_sync_actions(db)),
+        },
+        Migration {
+            name: "m20261017_000010_add_review_flags",
+            up: |db| Box::pin(add_review_flags(db)),
+        },
+        Migration {
+            name: "m20261017_000011_add_classroom_status_window",
+            up: |db| Box::pin(add_classroom_status_window(db)),
+        },
+        Migration {
+            name: "m20261017_000012_add_emergency_announcements",
+            up: |db| Box::pin(add_emergency_announcements(db)),
+        },
+        Migration {
+            name: "m20261017_000013_add_classroom_deleted_at",
+            up: |db| Box::pin(add_classroom_deleted_at(db)),
+        },
+        Migration {
+            name: "m20261017_000014_add_closure_statuses",
+            up: |db| Box::pin(add_closure_statuses(db)),
+        },
+        Migration {
+            name: "m20261017_000015_add_announcement_categories",
+            up: |db| Box::pin(add_announcement_categories(db)),
+        },
+        Migration {
+            name: "m20261017_000016_add_lifecycle_statuses",
+            up: |db| Box::pin(add_lifecycle_statuses(db)),
+        },
+        Migration {
+            name: "m20261017_000017_add_key_status",
+            up: |db| Box::pin(add_key_status(db)),
+        },
+        Migration {
+            name: "m20261017_000018_add_check_in",
+            up: |db| Box::pin(add_check_in(db)),
+        },
+        Migration {
+            name: "m20261017_000019_add_undeliverable_emails",
+            up: |db| Box::pin(add_undeliverable_emails(db)),
+        },
+        Migration {
+            name: "m20261017_000020_create_key_cabinet_pins",
+            up: |db| Box::pin(create_key_cabinet_pins(db)),
+        },
+        Migration {
+            name: "m20261017_000021_add_overdue_tracking",
+            up: |db| Box::pin(add_overdue_tracking(db)),
+        },
+        Migration {
+            name: "m20261017_000022_add_return_requests",
+            up: |db| Box::pin(add_return_requests(db)),
+        },
+        Migration {
+            name: "m20261017_000023_create_course_sessions",
+            up: |db| Box::pin(create_course_sessions(db)),
+        },
+        Migration {
+            name: "m20261017_000024_add_user_department",
+            up: |db| Box::pin(add_user_department(db)),
+        },
+        Migration {
+            name: "m20261017_000025_create_key_borrows",
+            up: |db| Box::pin(create_key_borrows(db)),
+        },
+        Migration {
+            name: "m20261017_000026_add_review_times",
+            up: |db| Box::pin(add_review_times(db)),
+        },
+        Migration {
+            name: "m20261017_000027_create_key_pickup_codes",
+            up: |db| Box::pin(create_key_pickup_codes(db)),
+        },
+        Migration {
+            name: "m20261017_000028_create_domain_events",
+            up: |db| Box::pin(create_domain_events(db)),
+        },
+        Migration {
+            name: "m20261017_000029_add_student_id",
+            up: |db| Box::pin(add_student_id(db)),
+        },
+        Migration {
+            name: "m20261017_000030_create_reservation_notes",
+            up: |db| Box::pin(create_reservation_notes(db)),
+        },
+        Migration {
+            name: "m20261017_000031_add_event_name",
+            up: |db| Box::pin(add_event_name(db)),
+        },
+        Migration {
+            name: "m20261017_000032_create_classroom_history",
+            up: |db| Box::pin(create_classroom_history(db)),
+        },
+        Migration {
+            name: "m20261017_000033_add_assistants",
+            up: |db| Box::pin(add_assistants(db)),
+        },
+        Migration {
+            name: "m20261017_000034_create_door_events",
+            up: |db| Box::pin(create_door_events(db)),
+        },
+        Migration {
+            name: "m20261017_000035_add_user_deleted_at",
+            up: |db| Box::pin(add_user_deleted_at(db)),
+        },
+        Migration {
+            name: "m20261017_000036_add_ban_end_notices",
+            up: |db| Box::pin(add_ban_end_notices(db)),
+        },
+        Migration {
+            name: "m20261017_000037_create_announcement_edits",
+            up: |db| Box::pin(create_announcement_edits(db)),
+        },
+        Migration {
+            name: "m20261017_000038_add_translations",
+            up: |db| Box::pin(add_translations(db)),
+        },
+        Migration {
+            name: "m20261017_000039_add_second_notices",
+            up: |db| Box::pin(add_second_notices(db)),
+        },
+        Migration {
+            name: "m20261017_000040_add_announcement_audiences",
+            up: |db| Box::pin(add_announcement_audiences(db)),
+        },
+        Migration {
+            name: "m20261017_000041_add_double_approval",
+            up: |db| Box::pin(add_double_approval(db)),
+        },
+    ]
+}
+
+// ===============================
+//   Building blocks
+// ===============================
+/// Column `name`; its type and constraints are chained onto it.
+fn column(name: &str) -> ColumnDef {
+    ColumnDef::new(Alias::new(name))
+}
+
+/// The string primary key every table has.
+fn id() -> ColumnDef {
+    let mut def = column("id");
+    def.string().not_null().primary_key();
+    def
+}
+
+/// A column of the Postgres enum type `type_name`; SQLite stores it as text.
+fn enum_column(name: &str, type_name: &str, values: &[&str]) -> ColumnDef {
+    let mut def = column(name);
+    def.enumeration(
+        Alias::new(type_name),
+        values.iter().map(|value| Alias::new(*value)),
+    );
+    def
+}
+
+/// `table.column` referencing the `id` of `to`.
+fn reference(
+    table: &str,
+    column: &str,
+    to: &str,
+    on_delete: ForeignKeyAction,
+) -> ForeignKeyCreateStatement {
+    ForeignKey::create()
+        .name(format!("fk-{}-{}", table, column))
+        .from(Alias::new(table), Alias::new(column))
+        .to(Alias::new(to), Alias::new("id"))
+        .on_delete(on_delete)
+        .on_update(ForeignKeyAction::NoAction)
+        .to_owned()
+}
+
+/// Creates the table unless it exists, so databases set up before
+/// migrations existed are taken over as they are.
+async fn create_table(
+    db: &DatabaseTransaction,
+    mut statement: TableCreateStatement,
+) -> Result<(), DbErr> {
+    db.execute(statement.if_not_exists()).await?;
+    Ok(())
+}
+
+/// Creates a Postgres enum type unless it exists. Only Postgres has enum
+/// types, and no `CREATE TYPE IF NOT EXISTS`.
+async fn create_enum(db: &DatabaseTransaction, name: &str, values: &[&str]) -> Result<(), DbErr> {
+    let backend = db.get_database_backend();
+    if backend != DbBackend::Postgres {
+        return Ok(());
+    }
+    let exists = db
+        .query_one_raw(Statement::from_sql_and_values(
+            backend,
+            "SELECT 1 FROM pg_type WHERE typname = $1",
+            [name.into()],
+        ))
+        .await?
+        .is_some();
+    if !exists {
+        let statement = Type::create()
+            .as_enum(Alias::new(name))
+            .values(values.iter().map(|value| Alias::new(*value)))
+            .to_owned();
+        db.execute(&statement).await?;
+    }
+    Ok(())
+}
+
+/// Adds values to a Postgres enum type. They can be stored once the
+/// migration commits, which is before the next one starts.
+async fn add_enum_values(
+    db: &DatabaseTransaction,
+    name: &str,
+    values: &[&str],
+) -> Result<(), DbErr> {
+    if db.get_database_backend() != DbBackend::Postgres {
+        return Ok(());
+    }
+    for value in values {
+        let statement = Type::alter()
+            .name(Alias::new(name))
+            .add_value(Alias::new(*value))
+            .if_not_exists();
+        db.execute(&statement).await?;
+    }
+    Ok(())
+}
+
+/// Adds `column` to `table` unless the table has it already.
+async fn add_column(
+    db: &DatabaseTransaction,
+    table: &str,
+    column: &mut ColumnDef,
+) -> Result<(), DbErr> {
+    if has_column(db, table, &column.get_column_name()).await? {
+        return Ok(());
+    }
+    let statement = Table::alter()
+        .table(Alias::new(table))
+        .add_column(column)
+        .to_owned();
+    db.execute(&statement).await?;
+    Ok(())
+}
+
+/// Adds the nullable `column` referencing the `id` of `to`. SQLite cannot
+/// add a foreign key to a table, only a column declaring one.
+async fn add_reference(
+    db: &DatabaseTransaction,
+    table: &str,
+    name: &str,
+    to: &str,
+    on_delete: ForeignKeyAction,
+) -> Result<(), DbErr> {
+    if has_column(db, table, name).await? {
+        return Ok(());
+    }
+    let mut def = column(name);
+    def.string().null();
+    if db.get_database_backend() == DbBackend::Sqlite {
+        let action = match on_delete {
+            ForeignKeyAction::Cascade => "CASCADE",
+            ForeignKeyAction::SetNull => "SET NULL",
+            ForeignKeyAction::SetDefault => "SET DEFAULT",
+            ForeignKeyAction::Restrict => "RESTRICT",
+            _ => "NO ACTION",
+        };
+        def.extra(format!(
+            "REFERENCES \"{}\" (\"id\") ON DELETE {}",
+            to, action
+        ));
+        return add_column(db, table, &mut def).await;
+    }
+    add_column(db, table, &mut def).await?;
+    db.execute(&reference(table, name, to, on_delete)).await?;
+    Ok(())
+}
+
+// ===============================
+//   Migrations
+// ===============================
+/// The schema as it was before migrations existed: users, classrooms,
+/// reservations, keys and their log, announcements, infractions and the
+/// blacklist. Frozen; later changes are migrations of their own.
+async fn create_tables(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let roles = ["admin", "user"];
+    let classroom_statuses = ["available", "occupied", "maintenance"];
+    let reservation_statuses = ["pending", "approved", "rejected"];
+    create_enum(db, "Role", &roles).await?;
+    create_enum(db, "ClassroomStatus", &classroom_statuses).await?;
+    create_enum(db, "ReservationStatus", &reservation_statuses).await?;
+
+    // Tables come after the tables they reference
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("user"))
+            .col(id())
+            .col(column("username").text().not_null().unique_key())
+            .col(column("name").text().not_null())
+            .col(column("email").text().not_null().unique_key())
+            .col(column("password").text().not_null())
+            .col(column("phone_number").text().not_null())
+            .col(enum_column("role", "Role", &roles).not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(
+                column("updated_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("classroom"))
+            .col(id())
+            .col(column("name").text().not_null())
+            .col(column("location").text().not_null())
+            .col(column("capacity").integer().not_null())
+            .col(column("description").text().not_null())
+            .col(enum_column("status", "ClassroomStatus", &classroom_statuses).not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(
+                column("updated_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("photo_id").text().not_null())
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("reservation"))
+            .col(id())
+            .col(column("user_id").string().null())
+            .col(column("classroom_id").string().null())
+            .col(column("purpose").text().not_null())
+            .col(column("start_time").timestamp_with_time_zone().not_null())
+            .col(column("approved_by").string().null())
+            .col(column("reject_reason").text().null())
+            .col(column("cancel_reason").text().null())
+            .col(enum_column("status", "ReservationStatus", &reservation_statuses).not_null())
+            .col(column("end_time").timestamp_with_time_zone().not_null())
+            .foreign_key(&mut reference(
+                "reservation",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "reservation",
+                "approved_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "reservation",
+                "user_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key"))
+            .col(id())
+            .col(column("classroom_id").string().null())
+            .col(column("key_number").text().not_null().unique_key())
+            .col(column("is_active").boolean().not_null())
+            .foreign_key(&mut reference(
+                "key",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key_transaction_log"))
+            .col(id())
+            .col(column("reservation_id").string().null())
+            .col(column("key_id").string().null())
+            .col(column("borrowed_to").string().null())
+            .col(column("handled_by").string().null())
+            .col(column("borrowed_at").timestamp_with_time_zone().not_null())
+            .col(column("returned_at").timestamp_with_time_zone().null())
+            .col(column("on_time").boolean().not_null().default(true))
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("deadline").timestamp_with_time_zone().not_null())
+            .foreign_key(&mut reference(
+                "key_transaction_log",
+                "key_id",
+                "key",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_transaction_log",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_transaction_log",
+                "borrowed_to",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_transaction_log",
+                "handled_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("announcement"))
+            .col(id())
+            .col(column("title").text().not_null())
+            .col(column("content").text().not_null())
+            .col(
+                column("published_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("created_by").string().null())
+            .foreign_key(&mut reference(
+                "announcement",
+                "created_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("infraction"))
+            .col(id())
+            .col(column("user_id").string().null())
+            .col(column("reservation_id").string().null())
+            .col(column("description").text().not_null())
+            .col(column("created_by").string().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "infraction",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "infraction",
+                "created_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "infraction",
+                "user_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("black_list"))
+            .col(id())
+            .col(column("user_id").string().null())
+            .col(column("infraction_id").string().null())
+            .col(column("created_by").string().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("end_at").timestamp_with_time_zone().null())
+            .foreign_key(&mut reference(
+                "black_list",
+                "infraction_id",
+                "infraction",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "black_list",
+                "created_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "black_list",
+                "user_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn create_audit_log(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("audit_log"))
+            .col(id())
+            .col(column("actor_id").string().null())
+            .col(column("action").text().not_null())
+            .col(column("target_type").text().not_null())
+            .col(column("target_id").text().not_null())
+            .col(column("before").json_binary().null())
+            .col(column("after").json_binary().null())
+            .col(column("ip").text().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "audit_log",
+                "actor_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// Adds the `version` column edits are checked against.
+async fn add_versions(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    for table in ["classroom", "key", "reservation", "user"] {
+        add_column(db, table, column("version").integer().not_null().default(1)).await?;
+    }
+    Ok(())
+}
+
+async fn create_reservation_attachments(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("reservation_attachment"))
+            .col(id())
+            .col(column("reservation_id").string().not_null())
+            .col(column("file_id").string().not_null())
+            .col(column("file_name").string().not_null())
+            .col(column("content_type").string().null())
+            .col(column("size").big_integer().not_null())
+            .col(column("uploaded_by").string().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "reservation_attachment",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "reservation_attachment",
+                "uploaded_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn create_reservation_comments(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("reservation_comment"))
+            .col(id())
+            .col(column("reservation_id").string().not_null())
+            .col(column("author_id").string().null())
+            .col(column("body").text().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "reservation_comment",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "reservation_comment",
+                "author_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// Adds the `group_id` linking reservations of several rooms booked in one
+/// request.
+async fn add_reservation_groups(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "reservation", column("group_id").string().null()).await
+}
+
+/// Adds the contact of guests staff book for, who have no account.
+async fn add_reservation_guests(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    for name in ["guest_name", "guest_email"] {
+        add_column(db, "reservation", column(name).text().null()).await?;
+    }
+    Ok(())
+}
+
+/// Weekly opening hours and one-off closures of classrooms.
+async fn create_opening_hours(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("classroom_schedule"))
+            .col(id())
+            .col(column("classroom_id").string().not_null())
+            .col(column("weekday").small_integer().not_null())
+            .col(column("open_time").time().not_null())
+            .col(column("close_time").time().not_null())
+            .foreign_key(&mut reference(
+                "classroom_schedule",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("classroom_closure"))
+            .col(id())
+            .col(column("classroom_id").string().not_null())
+            .col(column("start_at").timestamp_with_time_zone().not_null())
+            .col(column("end_at").timestamp_with_time_zone().not_null())
+            .col(column("reason").text().not_null())
+            .col(column("created_by").string().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "classroom_closure",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "classroom_closure",
+                "created_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// The language users get their emails in.
+async fn add_user_locale(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "user", column("locale").text().null()).await
+}
+
+/// Actions synced from the key desk app, so re-sent ones are applied once.
+async fn create_key_sync_actions(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key_sync_action"))
+            .col(id())
+            .col(column("action").text().not_null())
+            .col(column("key_transaction_log_id").string().null())
+            .col(column("handled_by").string().null())
+            .col(column("recorded_at").timestamp_with_time_zone().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "key_sync_action",
+                "key_transaction_log_id",
+                "key_transaction_log",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_sync_action",
+                "handled_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// The reason a classroom is out of service, and the flag on reservations
+/// an admin should look at because of it.
+async fn add_review_flags(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "classroom", column("status_reason").text().null()).await?;
+    add_column(
+        db,
+        "reservation",
+        column("flagged_for_review")
+            .boolean()
+            .not_null()
+            .default(false),
+    )
+    .await?;
+    add_column(db, "reservation", column("flag_reason").text().null()).await
+}
+
+/// When a classroom is out of service, and the `unavailable` status.
+async fn add_classroom_status_window(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_enum_values(db, "ClassroomStatus", &["unavailable"]).await?;
+    for name in ["status_from", "status_until"] {
+        add_column(
+            db,
+            "classroom",
+            column(name).timestamp_with_time_zone().null(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn add_emergency_announcements(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    for name in ["emergency", "pinned"] {
+        add_column(
+            db,
+            "announcement",
+            column(name).boolean().not_null().default(false),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Soft deletion of classrooms.
+async fn add_classroom_deleted_at(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "classroom",
+        column("deleted_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+/// Statuses of reservations cancelled or sent for rebooking by a closure.
+async fn add_closure_statuses(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_enum_values(db, "ReservationStatus", &["cancelled", "needs_rebooking"]).await
+}
+
+/// Announcement categories, and the categories each user muted.
+async fn add_announcement_categories(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let categories = ["maintenance", "policy", "event", "system"];
+    create_enum(db, "AnnouncementCategory", &categories).await?;
+    add_column(
+        db,
+        "announcement",
+        enum_column("category", "AnnouncementCategory", &categories)
+            .not_null()
+            .default("system"),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("announcement_mute"))
+            .col(column("user_id").string().not_null())
+            .col(enum_column("category", "AnnouncementCategory", &categories).not_null())
+            .primary_key(
+                Index::create()
+                    .col(Alias::new("user_id"))
+                    .col(Alias::new("category")),
+            )
+            .foreign_key(&mut reference(
+                "announcement_mute",
+                "user_id",
+                "user",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// The statuses the reservation lifecycle ends in.
+async fn add_lifecycle_statuses(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_enum_values(
+        db,
+        "ReservationStatus",
+        &["expired", "completed", "no_show"],
+    )
+    .await
+}
+
+/// Replaces the `is_active` flag of keys with a lifecycle status. Keys with
+/// an open log entry are out on loan.
+async fn add_key_status(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_enum(
+        db,
+        "KeyStatus",
+        &["active", "inactive", "borrowed", "lost", "retired"],
+    )
+    .await?;
+    if !has_column(db, "key", "is_active").await? {
+        return Ok(());
+    }
+    add_column(
+        db,
+        "key",
+        enum_column(
+            "status",
+            "KeyStatus",
+            &["active", "inactive", "borrowed", "lost", "retired"],
+        )
+        .not_null()
+        .default("active"),
+    )
+    .await?;
+    // Literals rather than bound values, which Postgres would not cast to
+    // the enum type
+    db.execute_unprepared(r#"UPDATE "key" SET "status" = 'inactive' WHERE NOT "is_active""#)
+        .await?;
+    db.execute_unprepared(
+        r#"UPDATE "key" SET "status" = 'borrowed' WHERE "is_active" AND EXISTS (
+            SELECT 1 FROM "key_transaction_log" AS "log"
+            WHERE "log"."key_id" = "key"."id" AND "log"."returned_at" IS NULL
+        )"#,
+    )
+    .await?;
+    let statement = Table::alter()
+        .table(Alias::new("key"))
+        .drop_column(Alias::new("is_active"))
+        .to_owned();
+    db.execute(&statement).await?;
+    Ok(())
+}
+
+/// When the requester checked in through the classroom QR code.
+async fn add_check_in(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "reservation",
+        column("checked_in_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+/// Addresses that bounced or complained, which are not emailed again.
+async fn add_undeliverable_emails(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "user",
+        column("email_undeliverable_at")
+            .timestamp_with_time_zone()
+            .null(),
+    )
+    .await?;
+    add_column(
+        db,
+        "user",
+        column("email_undeliverable_reason").text().null(),
+    )
+    .await
+}
+
+/// Cabinet slots of keys and the PINs issued to open them.
+async fn create_key_cabinet_pins(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let statuses = ["issued", "picked_up", "revoked", "failed"];
+    create_enum(db, "CabinetPinStatus", &statuses).await?;
+    add_column(db, "key", column("cabinet_slot").text().null()).await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key_cabinet_pin"))
+            .col(id())
+            .col(column("reservation_id").string().null())
+            .col(column("key_id").string().null())
+            .col(column("slot").text().not_null())
+            .col(column("pin").text().not_null())
+            .col(enum_column("status", "CabinetPinStatus", &statuses).not_null())
+            .col(column("valid_from").timestamp_with_time_zone().not_null())
+            .col(column("valid_until").timestamp_with_time_zone().not_null())
+            .col(column("key_transaction_log_id").string().null())
+            .col(column("error").text().null())
+            .col(column("picked_up_at").timestamp_with_time_zone().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "key_cabinet_pin",
+                "key_id",
+                "key",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_cabinet_pin",
+                "key_transaction_log_id",
+                "key_transaction_log",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_cabinet_pin",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// Where the overdue job is with each late key.
+async fn add_overdue_tracking(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    for name in ["overdue_at", "escalated_at"] {
+        add_column(
+            db,
+            "key_transaction_log",
+            column(name).timestamp_with_time_zone().null(),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// When a borrower said they handed a key back.
+async fn add_return_requests(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "key_transaction_log",
+        column("return_requested_at")
+            .timestamp_with_time_zone()
+            .null(),
+    )
+    .await
+}
+
+/// Courses imported from the timetable.
+async fn create_course_sessions(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("course_session"))
+            .col(id())
+            .col(column("classroom_id").string().not_null())
+            .col(column("course_name").text().not_null())
+            .col(column("weekday").small_integer().not_null())
+            .col(column("start_time").time().not_null())
+            .col(column("end_time").time().not_null())
+            .col(column("term_start").date().not_null())
+            .col(column("term_end").date().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "course_session",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn add_user_department(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "user", column("department").text().null()).await
+}
+
+/// Several keys handed out together, and the log entries they group.
+async fn create_key_borrows(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key_borrow"))
+            .col(id())
+            .col(column("reservation_id").string().null())
+            .col(column("borrowed_to").string().null())
+            .col(column("handled_by").string().null())
+            .col(column("borrowed_at").timestamp_with_time_zone().not_null())
+            .col(column("deadline").timestamp_with_time_zone().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "key_borrow",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await?;
+    add_reference(
+        db,
+        "key_transaction_log",
+        "borrow_id",
+        "key_borrow",
+        ForeignKeyAction::SetNull,
+    )
+    .await
+}
+
+/// When reservations were requested and first reviewed. SQLite only takes
+/// a `CURRENT_TIMESTAMP` default on an empty table, which is what memory
+/// mode starts from.
+async fn add_review_times(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "reservation",
+        column("created_at")
+            .timestamp_with_time_zone()
+            .not_null()
+            .default(Expr::current_timestamp()),
+    )
+    .await?;
+    add_column(
+        db,
+        "reservation",
+        column("reviewed_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+async fn create_key_pickup_codes(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("key_pickup_code"))
+            .col(id())
+            .col(column("reservation_id").string().not_null())
+            .col(column("key_id").string().null())
+            .col(column("code").text().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("used_at").timestamp_with_time_zone().null())
+            .col(column("revoked_at").timestamp_with_time_zone().null())
+            .col(column("key_transaction_log_id").string().null())
+            .foreign_key(&mut reference(
+                "key_pickup_code",
+                "key_id",
+                "key",
+                ForeignKeyAction::SetNull,
+            ))
+            .foreign_key(&mut reference(
+                "key_pickup_code",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn create_domain_events(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let statuses = ["pending", "dispatched", "failed"];
+    create_enum(db, "DomainEventStatus", &statuses).await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("domain_event"))
+            .col(id())
+            .col(column("kind").text().not_null())
+            .col(column("subject_id").string().null())
+            .col(column("payload").json_binary().not_null())
+            .col(enum_column("status", "DomainEventStatus", &statuses).not_null())
+            .col(column("attempts").integer().not_null())
+            .col(column("error").text().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .col(column("dispatched_at").timestamp_with_time_zone().null())
+            .to_owned(),
+    )
+    .await
+}
+
+/// Student IDs, unique among the users that have one. SQLite cannot add a
+/// unique column, so uniqueness comes from an index.
+async fn add_student_id(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "user", column("student_id").text().null()).await?;
+    let statement = Index::create()
+        .name("idx-user-student_id")
+        .table(Alias::new("user"))
+        .col(Alias::new("student_id"))
+        .unique()
+        .if_not_exists()
+        .to_owned();
+    db.execute(&statement).await?;
+    Ok(())
+}
+
+async fn create_reservation_notes(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("reservation_note"))
+            .col(id())
+            .col(column("reservation_id").string().not_null())
+            .col(column("author_id").string().null())
+            .col(column("body").text().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "reservation_note",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "reservation_note",
+                "author_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn add_event_name(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(db, "reservation", column("event_name").text().null()).await
+}
+
+async fn create_classroom_history(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("classroom_history"))
+            .col(id())
+            .col(column("classroom_id").string().not_null())
+            .col(column("snapshot").json_binary().not_null())
+            .col(column("valid_from").timestamp_with_time_zone().not_null())
+            .foreign_key(&mut reference(
+                "classroom_history",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// The assistant role and the classrooms each assistant looks after.
+async fn add_assistants(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_enum_values(db, "Role", &["assistant"]).await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("classroom_assistant"))
+            .col(id())
+            .col(column("user_id").string().not_null())
+            .col(column("classroom_id").string().not_null())
+            .col(column("granted_by").string().null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "classroom_assistant",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "classroom_assistant",
+                "user_id",
+                "user",
+                ForeignKeyAction::Cascade,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn create_door_events(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("door_event"))
+            .col(id())
+            .col(column("classroom_id").string().not_null())
+            .col(column("door_id").string().null())
+            .col(column("reservation_id").string().null())
+            .col(column("opened_at").timestamp_with_time_zone().not_null())
+            .col(column("early_access").boolean().not_null())
+            .col(
+                column("created_at")
+                    .timestamp_with_time_zone()
+                    .not_null()
+                    .default(Expr::current_timestamp()),
+            )
+            .foreign_key(&mut reference(
+                "door_event",
+                "classroom_id",
+                "classroom",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "door_event",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// Deletion of accounts, which keeps the anonymized row.
+async fn add_user_deleted_at(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "user",
+        column("deleted_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+/// When users were told their ban is over.
+async fn add_ban_end_notices(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "black_list",
+        column("end_notified_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+async fn create_announcement_edits(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("announcement_edit"))
+            .col(id())
+            .col(column("announcement_id").string().not_null())
+            .col(column("editor_id").string().null())
+            .col(column("edited_at").timestamp_with_time_zone().not_null())
+            .col(column("previous_title").text().not_null())
+            .col(column("previous_content").text().not_null())
+            .foreign_key(&mut reference(
+                "announcement_edit",
+                "announcement_id",
+                "announcement",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "announcement_edit",
+                "editor_id",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+/// English and Traditional Chinese names and texts of classrooms and
+/// announcements.
+async fn add_translations(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let columns = [
+        ("classroom", "name_en"),
+        ("classroom", "name_zh_tw"),
+        ("classroom", "description_en"),
+        ("classroom", "description_zh_tw"),
+        ("announcement", "title_en"),
+        ("announcement", "title_zh_tw"),
+        ("announcement", "content_en"),
+        ("announcement", "content_zh_tw"),
+    ];
+    for (table, name) in columns {
+        add_column(db, table, column(name).text().null()).await?;
+    }
+    Ok(())
+}
+
+/// The second step of the overdue reminder ladder.
+async fn add_second_notices(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "key_transaction_log",
+        column("second_notice_at").timestamp_with_time_zone().null(),
+    )
+    .await
+}
+
+/// Who announcements are for.
+async fn add_announcement_audiences(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    let audiences = ["all", "admins", "blacklisted", "classroom_users"];
+    create_enum(db, "AnnouncementAudience", &audiences).await?;
+    add_column(
+        db,
+        "announcement",
+        enum_column("audience", "AnnouncementAudience", &audiences)
+            .not_null()
+            .default("all"),
+    )
+    .await?;
+    add_reference(
+        db,
+        "announcement",
+        "audience_classroom_id",
+        "classroom",
+        ForeignKeyAction::Cascade,
+    )
+    .await
+}
+
+/// Classrooms whose reservations need two approvals, and the approvals.
+async fn add_double_approval(db: &DatabaseTransaction) -> Result<(), DbErr> {
+    add_column(
+        db,
+        "classroom",
+        column("requires_double_approval")
+            .boolean()
+            .not_null()
+            .default(false),
+    )
+    .await?;
+    create_table(
+        db,
+        Table::create()
+            .table(Alias::new("reservation_approval"))
+            .col(id())
+            .col(column("reservation_id").string().not_null())
+            .col(column("approved_by").string().null())
+            .col(column("approved_at").timestamp_with_time_zone().not_null())
+            .foreign_key(&mut reference(
+                "reservation_approval",
+                "reservation_id",
+                "reservation",
+                ForeignKeyAction::Cascade,
+            ))
+            .foreign_key(&mut reference(
+                "reservation_approval",
+                "approved_by",
+                "user",
+                ForeignKeyAction::SetNull,
+            ))
+            .to_owned(),
+    )
+    .await
+}
+
+async fn has_column(db: &DatabaseTransaction, table: &str, column: &str) -> Result<bool, DbErr> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DbBackend::Sqlite => "SELECT 1 FROM pragma_table_info(?) WHERE name = ?",
+        _ => {
+            "SELECT 1 FROM information_schema.columns \
+             WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2"
+        }
+    };
+    Ok(db
+        .query_one_raw(Statement::from_sql_and_values(
+            backend,
+            sql,
+            [table.into(), column.into()],
+        ))
+        .await?
+        .is_some())
+}
+
+/// Names of migrations not applied to `db` yet, oldest first. A database
+/// without the migrations table has all of them pending.
+pub async fn pending(db: &DatabaseConnection) -> Vec<&'static str> {
+    let applied: HashSet<String> = applied::Entity::find()
+        .all(db)
+        .await
+        .map(|rows| rows.into_iter().map(|row| row.version).collect())
+        .unwrap_or_default();
+    migrations()
+        .into_iter()
+        .map(|migration| migration.name)
+        .filter(|name| !applied.contains(*name))
+        .collect()
+}
+
+async fn applied_versions(db: &impl ConnectionTrait) -> Result<HashSet<String>, DbErr> {
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(
+        schema
+            .create_table_from_entity(applied::Entity)
+            .if_not_exists(),
+    )
+    .await?;
+    Ok(applied::Entity::find()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|row| row.version)
+        .collect())
+}
+
+/// Applies the pending migrations and returns their names. Servers starting
+/// at the same time on Postgres take turns, so each migration runs once.
+pub async fn run(db: &DatabaseConnection) -> Result<Vec<&'static str>, DbErr> {
+    let mut ran = Vec::new();
+    for migration in migrations() {
+        let txn = db.begin().await?;
+        if txn.get_database_backend() == DbBackend::Postgres {
+            // Held until the transaction ends; the number names the lock
+            txn.execute_unprepared("SELECT pg_advisory_xact_lock(7274728115)")
+                .await?;
+        }
+        if applied_versions(&txn).await?.contains(migration.name) {
+            txn.commit().await?;
+            continue;
+        }
+
+        info!("Applying migration {}", migration.name);
+        (migration.up)(&txn).await?;
+        applied::ActiveModel {
+            version: Set(migration.name.to_string()),
+            applied_at: Set(Utc::now().timestamp()),
+        }
+        .insert(&txn)
+        .await?;
+        txn.commit().await?;
+        ran.push(migration.name);
+    }
+    Ok(ran)
+}
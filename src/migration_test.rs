@@ -0,0 +1,107 @@
+#[cfg(test)]
+mod tests {
+    use sea_orm::{
+        ConnectOptions, ConnectionTrait, Database, EntityTrait, PaginatorTrait, TransactionTrait,
+    };
+
+    use super::super::{
+        entities::{
+            key, key_transaction_log, reservation_attachment, reservation_comment,
+            reservation_note, sea_orm_active_enums::KeyStatus, user,
+        },
+        memory_mode,
+        migration::{migrations, pending, run},
+    };
+
+    #[tokio::test]
+    async fn test_fresh_database_is_migrated_once() {
+        let mut options = ConnectOptions::new("sqlite::memory:");
+        options.max_connections(1).sqlx_logging(false);
+        let db = Database::connect(options).await.unwrap();
+
+        let all: Vec<_> = migrations().iter().map(|m| m.name).collect();
+        assert_eq!(pending(&db).await, all);
+        assert_eq!(run(&db).await.unwrap(), all);
+        assert!(pending(&db).await.is_empty());
+        assert!(run(&db).await.unwrap().is_empty());
+
+        assert_eq!(user::Entity::find().count(&db).await.unwrap(), 0);
+        assert_eq!(
+            key_transaction_log::Entity::find()
+                .count(&db)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            reservation_note::Entity::find().count(&db).await.unwrap(),
+            0
+        );
+        assert_eq!(
+            reservation_comment::Entity::find()
+                .count(&db)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            reservation_attachment::Entity::find()
+                .count(&db)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_database_from_before_migrations_is_upgraded() {
+        let mut options = ConnectOptions::new("sqlite::memory:");
+        options.max_connections(1).sqlx_logging(false);
+        let db = Database::connect(options).await.unwrap();
+
+        // The schema as it was before migrations existed, with one key put
+        // away and one out on loan
+        let txn = db.begin().await.unwrap();
+        (migrations()[0].up)(&txn).await.unwrap();
+        txn.commit().await.unwrap();
+        db.execute_unprepared(
+            r#"INSERT INTO "key" ("id", "key_number", "is_active") VALUES
+                ('k1', 'A-1', FALSE), ('k2', 'A-2', TRUE), ('k3', 'A-3', TRUE);
+            INSERT INTO "key_transaction_log" ("id", "key_id", "borrowed_at", "deadline")
+                VALUES ('l1', 'k2', '2026-10-17T08:00:00Z', '2026-10-17T10:00:00Z')"#,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(run(&db).await.unwrap().len(), migrations().len());
+        let status = |id: &'static str| {
+            let db = &db;
+            async move {
+                key::Entity::find_by_id(id)
+                    .one(db)
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .status
+            }
+        };
+        assert_eq!(status("k1").await, KeyStatus::Inactive);
+        assert_eq!(status("k2").await, KeyStatus::Borrowed);
+        assert_eq!(status("k3").await, KeyStatus::Active);
+    }
+
+    #[test]
+    fn test_migrations_are_in_order() {
+        let names: Vec<_> = migrations().iter().map(|m| m.name).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+    }
+
+    #[tokio::test]
+    async fn test_memory_mode_runs_the_migrations() {
+        let db = memory_mode::connect_db().await.unwrap();
+        assert!(pending(&db).await.is_empty());
+    }
+}
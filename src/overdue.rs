@@ -0,0 +1,302 @@
+use chrono::{Duration, Utc};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::Expr,
+};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_queue::{Priority, queue_email_to_user},
+    email_templates,
+    entities::{classroom, infraction, key, key_transaction_log, sea_orm_active_enums::Role, user},
+};
+
+/// Settings for the job that chases keys not returned by their deadline.
+/// Each step of the ladder is measured from the deadline and taken at most
+/// once per borrow, in order.
+#[derive(Clone)]
+pub struct OverdueConfig {
+    /// How often open borrows are scanned
+    pub scan_interval: std::time::Duration,
+    /// How long after the deadline the borrower is first reminded
+    pub reminder_delay: Duration,
+    /// How long after the deadline the borrower gets a second notice
+    pub second_notice_delay: Duration,
+    /// How long after the deadline an infraction is recorded for the borrower
+    /// and admins are alerted
+    pub infraction_grace: Duration,
+}
+
+impl Default for OverdueConfig {
+    fn default() -> Self {
+        Self {
+            scan_interval: std::time::Duration::from_secs(5 * 60),
+            reminder_delay: Duration::zero(),
+            second_notice_delay: Duration::hours(24),
+            infraction_grace: Duration::hours(72),
+        }
+    }
+}
+
+/// The escalation ladder for an unreturned key. Each step is recorded on the
+/// transaction log when taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverdueStep {
+    /// Reminder to the borrower; recorded in `overdue_at`
+    Reminder,
+    /// Second notice to the borrower; recorded in `second_notice_at`
+    SecondNotice,
+    /// Infraction for the borrower and alert to admins; recorded in
+    /// `escalated_at`
+    Escalation,
+}
+
+impl OverdueStep {
+    const LADDER: [Self; 3] = [Self::Reminder, Self::SecondNotice, Self::Escalation];
+
+    /// How long after the deadline the step is due.
+    pub fn delay(self, config: &OverdueConfig) -> Duration {
+        match self {
+            Self::Reminder => config.reminder_delay,
+            Self::SecondNotice => config.second_notice_delay,
+            Self::Escalation => config.infraction_grace,
+        }
+    }
+
+    fn column(self) -> key_transaction_log::Column {
+        match self {
+            Self::Reminder => key_transaction_log::Column::OverdueAt,
+            Self::SecondNotice => key_transaction_log::Column::SecondNoticeAt,
+            Self::Escalation => key_transaction_log::Column::EscalatedAt,
+        }
+    }
+
+    fn taken_at(self, log: &key_transaction_log::Model) -> Option<DateTimeWithTimeZone> {
+        match self {
+            Self::Reminder => log.overdue_at,
+            Self::SecondNotice => log.second_notice_at,
+            Self::Escalation => log.escalated_at,
+        }
+    }
+}
+
+/// The step to take now for a borrow, if any: the first one not taken yet,
+/// once its delay has passed. Returned keys are left alone, and a scan that
+/// falls behind takes the missed steps one at a time.
+pub fn next_step(
+    log: &key_transaction_log::Model,
+    now: DateTimeWithTimeZone,
+    config: &OverdueConfig,
+) -> Option<OverdueStep> {
+    if log.returned_at.is_some() {
+        return None;
+    }
+    let step = OverdueStep::LADDER
+        .into_iter()
+        .find(|step| step.taken_at(log).is_none())?;
+    (log.deadline + step.delay(config) <= now).then_some(step)
+}
+
+/// Description of the infraction recorded for an unreturned key.
+pub fn infraction_description(
+    log: &key_transaction_log::Model,
+    key: Option<&key::Model>,
+) -> String {
+    format!(
+        "Key {} not returned; due {}",
+        key.map(|k| k.key_number.as_str()).unwrap_or("-"),
+        log.deadline
+            .with_timezone(&campus_offset())
+            .format("%Y-%m-%d %H:%M (GMT+8)")
+    )
+}
+
+/// Scans open borrows every [`OverdueConfig::scan_interval`]. Spawned once at
+/// startup.
+pub async fn run(state: AppState) {
+    let mut ticker = tokio::time::interval(state.config.overdue.scan_interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = scan(&state).await {
+            warn!("Overdue key scan failed: {}", e);
+        }
+    }
+}
+
+/// Moves each open borrow past its deadline one step up the ladder when the
+/// step is due: reminder, second notice, then infraction and admin alert.
+pub async fn scan(state: &AppState) -> Result<(), DbErr> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let config = &state.config.overdue;
+    let earliest = OverdueStep::LADDER
+        .into_iter()
+        .map(|step| step.delay(config))
+        .min()
+        .unwrap_or_default();
+
+    let open = key_transaction_log::Entity::find()
+        .filter(key_transaction_log::Column::ReturnedAt.is_null())
+        .filter(key_transaction_log::Column::EscalatedAt.is_null())
+        .filter(key_transaction_log::Column::Deadline.lte(now - earliest))
+        .all(&state.db)
+        .await?;
+    for log in open {
+        match next_step(&log, now, config) {
+            Some(OverdueStep::Escalation) => escalate(state, &log, now).await?,
+            Some(step) => {
+                // Claimed before notifying so a failing mail server does not
+                // cause a notice on every scan, and parallel instances only
+                // send once
+                if !claim(&state.db, &log, step, now).await? {
+                    continue;
+                }
+                info!("Key transaction {} is overdue ({:?})", log.id, step);
+                notify_borrower(state, &log, step, config).await;
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Records `step` as taken for `log`, unless the key came back or another
+/// scan took it first.
+async fn claim<C: ConnectionTrait>(
+    db: &C,
+    log: &key_transaction_log::Model,
+    step: OverdueStep,
+    now: DateTimeWithTimeZone,
+) -> Result<bool, DbErr> {
+    let claimed = key_transaction_log::Entity::update_many()
+        .col_expr(step.column(), Expr::value(now))
+        .filter(key_transaction_log::Column::Id.eq(&log.id))
+        .filter(step.column().is_null())
+        .filter(key_transaction_log::Column::ReturnedAt.is_null())
+        .exec(db)
+        .await?;
+    Ok(claimed.rows_affected > 0)
+}
+
+/// The key, its classroom and the borrower of `log`, where they still exist.
+async fn overdue_context(
+    state: &AppState,
+    log: &key_transaction_log::Model,
+) -> (
+    Option<key::Model>,
+    Option<classroom::Model>,
+    Option<user::Model>,
+) {
+    let key_model = match &log.key_id {
+        Some(key_id) => key::Entity::find_by_id(key_id)
+            .one(&state.db)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    let classroom_model = match key_model.as_ref().and_then(|k| k.classroom_id.as_ref()) {
+        Some(classroom_id) => classroom::Entity::find_by_id(classroom_id)
+            .one(&state.db)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    let borrower = match &log.borrowed_to {
+        Some(user_id) => user::Entity::find_by_id(user_id)
+            .one(&state.db)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    (key_model, classroom_model, borrower)
+}
+
+async fn notify_borrower(
+    state: &AppState,
+    log: &key_transaction_log::Model,
+    step: OverdueStep,
+    config: &OverdueConfig,
+) {
+    let (key_model, classroom_model, borrower) = overdue_context(state, log).await;
+    let Some(borrower) = borrower else {
+        return;
+    };
+    let locale = state.config.email_templates.locale_for(&borrower);
+    let email = match step {
+        OverdueStep::SecondNotice => email_templates::key_overdue_second_notice(
+            log,
+            key_model.as_ref(),
+            classroom_model.as_ref(),
+            log.deadline + config.infraction_grace,
+            locale,
+        ),
+        _ => {
+            email_templates::key_overdue(log, key_model.as_ref(), classroom_model.as_ref(), locale)
+        }
+    };
+    if let Err(e) = queue_email_to_user(
+        state,
+        &borrower,
+        email.subject,
+        email.body,
+        Priority::Normal,
+    )
+    .await
+    {
+        warn!("Failed to send overdue notice to {}: {}", borrower.id, e);
+    }
+}
+
+async fn escalate(
+    state: &AppState,
+    log: &key_transaction_log::Model,
+    now: DateTimeWithTimeZone,
+) -> Result<(), DbErr> {
+    let txn = state.db.begin().await?;
+    if !claim(&txn, log, OverdueStep::Escalation, now).await? {
+        return txn.rollback().await;
+    }
+    let (key_model, classroom_model, borrower) = overdue_context(state, log).await;
+    let new_infraction = infraction::ActiveModel {
+        id: Set(nanoid!()),
+        user_id: Set(log.borrowed_to.clone()),
+        reservation_id: Set(log.reservation_id.clone()),
+        description: Set(infraction_description(log, key_model.as_ref())),
+        created_by: Set(None),
+        created_at: NotSet,
+    };
+    let infraction = new_infraction.insert(&txn).await?;
+    txn.commit().await?;
+
+    info!(
+        "Recorded infraction {} for overdue key transaction {}",
+        infraction.id, log.id
+    );
+
+    let admins = user::Entity::find()
+        .filter(user::Column::Role.eq(Role::Admin))
+        .all(&state.db)
+        .await?;
+    for admin in &admins {
+        let email = email_templates::key_overdue_report(
+            log,
+            key_model.as_ref(),
+            classroom_model.as_ref(),
+            borrower.as_ref(),
+            state.config.email_templates.locale_for(admin),
+        );
+        if let Err(e) =
+            queue_email_to_user(state, admin, email.subject, email.body, Priority::Normal).await
+        {
+            warn!("Failed to send overdue alert to {}: {}", admin.id, e);
+        }
+    }
+    Ok(())
+}
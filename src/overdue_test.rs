@@ -0,0 +1,144 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{key, key_transaction_log, sea_orm_active_enums::KeyStatus};
+    use super::super::overdue::{OverdueConfig, OverdueStep, infraction_description, next_step};
+    use chrono::Duration;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn open_log() -> key_transaction_log::Model {
+        key_transaction_log::Model {
+            id: "log-1".to_string(),
+            reservation_id: Some("r1".to_string()),
+            key_id: Some("k1".to_string()),
+            borrowed_to: Some("u1".to_string()),
+            handled_by: None,
+            borrowed_at: dt("2025-03-10T09:55:00+08:00"),
+            returned_at: None,
+            on_time: false,
+            created_at: dt("2025-03-10T09:55:00+08:00"),
+            deadline: dt("2025-03-10T12:00:00+08:00"),
+            overdue_at: Some(dt("2025-03-10T12:05:00+08:00")),
+            second_notice_at: None,
+            escalated_at: None,
+            return_requested_at: None,
+            borrow_id: None,
+        }
+    }
+
+    fn step_at(log: &key_transaction_log::Model, now: &str) -> Option<OverdueStep> {
+        next_step(log, dt(now), &OverdueConfig::default())
+    }
+
+    #[test]
+    fn test_reminder_is_due_at_deadline() {
+        let mut log = open_log();
+        log.overdue_at = None;
+        assert_eq!(step_at(&log, "2025-03-10T11:59:00+08:00"), None);
+        assert_eq!(
+            step_at(&log, "2025-03-10T12:00:00+08:00"),
+            Some(OverdueStep::Reminder)
+        );
+    }
+
+    #[test]
+    fn test_second_notice_waits_a_day() {
+        let log = open_log();
+        assert_eq!(step_at(&log, "2025-03-11T11:59:00+08:00"), None);
+        assert_eq!(
+            step_at(&log, "2025-03-11T12:00:00+08:00"),
+            Some(OverdueStep::SecondNotice)
+        );
+    }
+
+    #[test]
+    fn test_escalation_waits_for_grace_period() {
+        let mut log = open_log();
+        log.second_notice_at = Some(dt("2025-03-11T12:05:00+08:00"));
+        assert_eq!(step_at(&log, "2025-03-13T11:59:00+08:00"), None);
+        assert_eq!(
+            step_at(&log, "2025-03-13T12:00:00+08:00"),
+            Some(OverdueStep::Escalation)
+        );
+    }
+
+    #[test]
+    fn test_missed_steps_are_taken_in_order() {
+        let mut log = open_log();
+        log.overdue_at = None;
+        assert_eq!(
+            step_at(&log, "2025-03-20T12:00:00+08:00"),
+            Some(OverdueStep::Reminder)
+        );
+        log.overdue_at = Some(dt("2025-03-20T12:00:00+08:00"));
+        assert_eq!(
+            step_at(&log, "2025-03-20T12:05:00+08:00"),
+            Some(OverdueStep::SecondNotice)
+        );
+    }
+
+    #[test]
+    fn test_steps_follow_config() {
+        let config = OverdueConfig {
+            reminder_delay: Duration::hours(1),
+            second_notice_delay: Duration::hours(6),
+            infraction_grace: Duration::hours(12),
+            ..OverdueConfig::default()
+        };
+        let mut log = open_log();
+        log.overdue_at = None;
+        assert_eq!(
+            next_step(&log, dt("2025-03-10T12:59:00+08:00"), &config),
+            None
+        );
+        assert_eq!(
+            next_step(&log, dt("2025-03-10T13:00:00+08:00"), &config),
+            Some(OverdueStep::Reminder)
+        );
+        log.overdue_at = Some(dt("2025-03-10T13:00:00+08:00"));
+        log.second_notice_at = Some(dt("2025-03-10T18:00:00+08:00"));
+        assert_eq!(
+            next_step(&log, dt("2025-03-11T00:00:00+08:00"), &config),
+            Some(OverdueStep::Escalation)
+        );
+    }
+
+    #[test]
+    fn test_returned_or_escalated_logs_are_not_escalated() {
+        let now = "2025-03-20T12:00:00+08:00";
+
+        let mut returned = open_log();
+        returned.returned_at = Some(dt("2025-03-11T08:00:00+08:00"));
+        assert_eq!(step_at(&returned, now), None);
+
+        let mut escalated = open_log();
+        escalated.second_notice_at = Some(dt("2025-03-11T12:05:00+08:00"));
+        escalated.escalated_at = Some(dt("2025-03-13T12:05:00+08:00"));
+        assert_eq!(step_at(&escalated, now), None);
+    }
+
+    #[test]
+    fn test_infraction_description_names_key_and_deadline() {
+        let key = key::Model {
+            id: "k1".to_string(),
+            classroom_id: Some("room-1".to_string()),
+            key_number: "A-101".to_string(),
+            status: KeyStatus::Borrowed,
+            cabinet_slot: None,
+            version: 1,
+        };
+        let mut log = open_log();
+        log.deadline = dt("2025-03-10T04:00:00Z");
+        assert_eq!(
+            infraction_description(&log, Some(&key)),
+            "Key A-101 not returned; due 2025-03-10 12:00 (GMT+8)"
+        );
+        assert_eq!(
+            infraction_description(&log, None),
+            "Key - not returned; due 2025-03-10 12:00 (GMT+8)"
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use axum::{
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+    response::{IntoResponse, Response},
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, Order, PaginatorTrait, QueryOrder, Select,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+pub const DEFAULT_PAGE_SIZE: u64 = 20;
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// The `page` and `page_size` query parameters, for documenting list
+/// endpoints. Handlers take [`Pagination`].
+#[derive(Deserialize, IntoParams)]
+pub struct PageParams {
+    /// Page number, starting at 1 (default 1)
+    pub page: Option<u64>,
+    /// Items per page (default 20, max 100)
+    pub page_size: Option<u64>,
+}
+
+/// The page a list endpoint should return. Out-of-range values are clamped
+/// rather than rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pagination {
+    pub page: u64,
+    pub page_size: u64,
+}
+
+impl Pagination {
+    pub fn new(page: Option<u64>, page_size: Option<u64>) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            page_size: page_size
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Pagination {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(params) = Query::<PageParams>::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        Ok(Self::new(params.page, params.page_size))
+    }
+}
+
+/// One page of a list endpoint.
+#[derive(Serialize, ToSchema, Debug)]
+pub struct PagedResponse<T> {
+    pub page: u64,
+    pub page_size: u64,
+    /// Items across all pages
+    pub total: u64,
+    pub items: Vec<T>,
+}
+
+impl<T> PagedResponse<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> PagedResponse<U> {
+        PagedResponse {
+            page: self.page,
+            page_size: self.page_size,
+            total: self.total,
+            items: self.items.into_iter().map(f).collect(),
+        }
+    }
+}
+
+/// Fetches one page of `select` with the total count.
+pub async fn fetch<E>(
+    db: &DatabaseConnection,
+    select: Select<E>,
+    pagination: Pagination,
+) -> Result<PagedResponse<E::Model>, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    let paginator = select.paginate(db, pagination.page_size);
+    let total = paginator.num_items().await?;
+    let items = paginator.fetch_page(pagination.page - 1).await?;
+    Ok(PagedResponse {
+        page: pagination.page,
+        page_size: pagination.page_size,
+        total,
+        items,
+    })
+}
+
+/// The `sort` query parameter, for list endpoints without their own query
+/// struct.
+#[derive(Deserialize, IntoParams)]
+pub struct SortParams {
+    /// `field`, `field:asc` or `field:desc`; `asc` or `desc` alone keeps the
+    /// default field
+    pub sort: Option<String>,
+}
+
+/// Sort order of a list endpoint, picked from the fields it allows sorting
+/// by.
+#[derive(Clone, Debug)]
+pub struct SortSpec<C> {
+    pub column: C,
+    pub order: Order,
+}
+
+impl<C: ColumnTrait> SortSpec<C> {
+    /// Parses `sort` as `field`, `field:asc` or `field:desc` against
+    /// `fields`, or `asc`/`desc` alone to change only the order of the
+    /// default. Fields sort ascending unless told otherwise.
+    pub fn parse(
+        sort: Option<&str>,
+        fields: &[(&str, C)],
+        default: C,
+        default_order: Order,
+    ) -> Result<Self, String> {
+        let Some(sort) = sort.map(str::trim).filter(|s| !s.is_empty()) else {
+            return Ok(Self {
+                column: default,
+                order: default_order,
+            });
+        };
+        let (field, order) = match sort.split_once(':') {
+            Some((field, order)) => (Some(field.trim()), Some(order.trim())),
+            None if sort.eq_ignore_ascii_case("asc") || sort.eq_ignore_ascii_case("desc") => {
+                (None, Some(sort))
+            }
+            None => (Some(sort), None),
+        };
+        let order = match order {
+            None => Order::Asc,
+            Some(o) if o.eq_ignore_ascii_case("asc") => Order::Asc,
+            Some(o) if o.eq_ignore_ascii_case("desc") => Order::Desc,
+            Some(_) => return Err("Invalid 'sort' order, use asc or desc".to_string()),
+        };
+        let column = match field {
+            None => default,
+            Some(field) => match fields.iter().find(|(name, _)| *name == field) {
+                Some((_, column)) => *column,
+                None => {
+                    let allowed: Vec<&str> = fields.iter().map(|(name, _)| *name).collect();
+                    return Err(format!(
+                        "Cannot sort by '{}', use one of: {}",
+                        field,
+                        allowed.join(", ")
+                    ));
+                }
+            },
+        };
+        Ok(Self { column, order })
+    }
+
+    pub fn apply<E>(self, select: Select<E>) -> Select<E>
+    where
+        E: EntityTrait<Column = C>,
+    {
+        select.order_by(self.column, self.order)
+    }
+}
@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use sea_orm::{IdenStatic, Order};
+
+    use super::super::{
+        entities::key_transaction_log::Column,
+        pagination::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, PagedResponse, Pagination, SortSpec},
+    };
+
+    const FIELDS: [(&str, Column); 2] = [
+        ("borrowed_at", Column::BorrowedAt),
+        ("deadline", Column::Deadline),
+    ];
+
+    fn parse(sort: Option<&str>) -> Result<(&'static str, Order), String> {
+        SortSpec::parse(sort, &FIELDS, Column::BorrowedAt, Order::Desc)
+            .map(|spec| (spec.column.as_str(), spec.order))
+    }
+
+    #[test]
+    fn defaults_to_first_page() {
+        assert_eq!(
+            Pagination::new(None, None),
+            Pagination {
+                page: 1,
+                page_size: DEFAULT_PAGE_SIZE,
+            }
+        );
+    }
+
+    #[test]
+    fn clamps_out_of_range_values() {
+        assert_eq!(
+            Pagination::new(Some(0), Some(0)),
+            Pagination {
+                page: 1,
+                page_size: 1,
+            }
+        );
+        assert_eq!(
+            Pagination::new(Some(3), Some(10_000)).page_size,
+            MAX_PAGE_SIZE
+        );
+    }
+
+    #[test]
+    fn missing_sort_uses_default() {
+        assert_eq!(parse(None), Ok(("borrowed_at", Order::Desc)));
+        assert_eq!(parse(Some("  ")), Ok(("borrowed_at", Order::Desc)));
+    }
+
+    #[test]
+    fn field_alone_sorts_ascending() {
+        assert_eq!(parse(Some("deadline")), Ok(("deadline", Order::Asc)));
+    }
+
+    #[test]
+    fn field_with_order() {
+        assert_eq!(parse(Some("deadline:desc")), Ok(("deadline", Order::Desc)));
+        assert_eq!(
+            parse(Some("borrowed_at:ASC")),
+            Ok(("borrowed_at", Order::Asc))
+        );
+    }
+
+    #[test]
+    fn order_alone_keeps_default_field() {
+        assert_eq!(parse(Some("asc")), Ok(("borrowed_at", Order::Asc)));
+    }
+
+    #[test]
+    fn rejects_fields_outside_whitelist() {
+        assert_eq!(
+            parse(Some("password")),
+            Err("Cannot sort by 'password', use one of: borrowed_at, deadline".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_order() {
+        assert_eq!(
+            parse(Some("deadline:sideways")),
+            Err("Invalid 'sort' order, use asc or desc".to_string())
+        );
+    }
+
+    #[test]
+    fn map_keeps_page_metadata() {
+        let page = PagedResponse {
+            page: 2,
+            page_size: 10,
+            total: 12,
+            items: vec![1, 2],
+        }
+        .map(|n| n * 10);
+        assert_eq!((page.page, page.page_size, page.total), (2, 10, 12));
+        assert_eq!(page.items, vec![10, 20]);
+    }
+}
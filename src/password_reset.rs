@@ -0,0 +1,41 @@
+use chrono::Duration;
+use nanoid::nanoid;
+
+/// Shortest and longest code `PASSWORD_RESET_CODE_LENGTH` accepts.
+pub const MIN_CODE_LENGTH: usize = 4;
+pub const MAX_CODE_LENGTH: usize = 12;
+
+/// Settings for the emailed password reset code.
+#[derive(Clone)]
+pub struct PasswordResetConfig {
+    /// Digits in the code
+    pub code_length: usize,
+    pub code_ttl: Duration,
+    /// How long the token handed out for a verified code stays valid
+    pub token_ttl: Duration,
+    /// Wrong guesses after which the code is invalidated and a new one has
+    /// to be requested
+    pub max_verify_attempts: u32,
+}
+
+impl Default for PasswordResetConfig {
+    fn default() -> Self {
+        Self {
+            code_length: 6,
+            code_ttl: Duration::minutes(10),
+            token_ttl: Duration::minutes(15),
+            max_verify_attempts: 5,
+        }
+    }
+}
+
+pub fn gen_code(length: usize) -> String {
+    const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+    nanoid!(length, &DIGITS)
+}
+
+/// Whether a code with `failed_attempts` wrong guesses so far must be
+/// thrown away.
+pub fn attempts_exhausted(failed_attempts: u32, max_verify_attempts: u32) -> bool {
+    failed_attempts >= max_verify_attempts
+}
@@ -0,0 +1,21 @@
+#[cfg(test)]
+mod tests {
+    use super::super::password_reset::{attempts_exhausted, gen_code};
+
+    #[test]
+    fn test_gen_code_uses_configured_length() {
+        for length in [4, 6, 12] {
+            let code = gen_code(length);
+            assert_eq!(code.len(), length);
+            assert!(code.chars().all(|c| c.is_ascii_digit()));
+        }
+    }
+
+    #[test]
+    fn test_code_invalidated_after_max_attempts() {
+        assert!(!attempts_exhausted(1, 5));
+        assert!(!attempts_exhausted(4, 5));
+        assert!(attempts_exhausted(5, 5));
+        assert!(attempts_exhausted(1, 1));
+    }
+}
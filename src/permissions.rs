@@ -0,0 +1,127 @@
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, Select,
+};
+
+use crate::entities::{classroom_assistant, reservation, sea_orm_active_enums::Role, user};
+
+/// Capabilities checked by `permission_required!`. Roles map to a fixed set
+/// of them in [`permissions_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    /// Review reservations, add notes and look for duplicate bookings.
+    /// Assistants only see reservations for the classrooms they were granted
+    ReviewReservations,
+    ManageClassrooms,
+    ManageKeys,
+    ManageUsers,
+    ManageAnnouncements,
+    ViewStatistics,
+    /// Storage overview, domain event replay and other operator tools
+    ManageSystem,
+}
+
+pub const ALL_PERMISSIONS: [Permission; 7] = [
+    Permission::ReviewReservations,
+    Permission::ManageClassrooms,
+    Permission::ManageKeys,
+    Permission::ManageUsers,
+    Permission::ManageAnnouncements,
+    Permission::ViewStatistics,
+    Permission::ManageSystem,
+];
+
+pub fn permissions_for(role: &Role) -> &'static [Permission] {
+    match role {
+        Role::Admin => &ALL_PERMISSIONS,
+        Role::Assistant => &[Permission::ReviewReservations],
+        Role::User => &[],
+    }
+}
+
+pub fn has_permission(role: &Role, permission: Permission) -> bool {
+    permissions_for(role).contains(&permission)
+}
+
+/// Classrooms whose reservations a reviewer may see and review.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClassroomScope {
+    All,
+    Only(Vec<String>),
+}
+
+impl ClassroomScope {
+    pub fn allows(&self, classroom_id: Option<&str>) -> bool {
+        match self {
+            Self::All => true,
+            Self::Only(ids) => classroom_id.is_some_and(|id| ids.iter().any(|i| i == id)),
+        }
+    }
+
+    /// Restricts a reservation query to the scope.
+    pub fn apply(&self, query: Select<reservation::Entity>) -> Select<reservation::Entity> {
+        match self {
+            Self::All => query,
+            Self::Only(ids) => query.filter(reservation::Column::ClassroomId.is_in(ids.clone())),
+        }
+    }
+}
+
+/// Classrooms an assistant was granted, oldest grant first.
+pub async fn assistant_classrooms(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<Vec<classroom_assistant::Model>, DbErr> {
+    classroom_assistant::Entity::find()
+        .filter(classroom_assistant::Column::UserId.eq(user_id))
+        .order_by_asc(classroom_assistant::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+pub async fn review_scope(
+    db: &DatabaseConnection,
+    user: &user::Model,
+) -> Result<ClassroomScope, DbErr> {
+    match user.role {
+        Role::Admin => Ok(ClassroomScope::All),
+        Role::Assistant => Ok(ClassroomScope::Only(
+            assistant_classrooms(db, &user.id)
+                .await?
+                .into_iter()
+                .map(|grant| grant.classroom_id)
+                .collect(),
+        )),
+        Role::User => Ok(ClassroomScope::Only(Vec::new())),
+    }
+}
+
+/// Whether `user` may see a reservation's attachments and comments: its
+/// owner, or a reviewer whose scope covers its classroom.
+pub async fn can_view_reservation(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    reservation: &reservation::Model,
+) -> Result<bool, DbErr> {
+    if reservation.user_id.as_deref() == Some(user.id.as_str()) {
+        return Ok(true);
+    }
+    Ok(review_scope(db, user)
+        .await?
+        .allows(reservation.classroom_id.as_deref()))
+}
+
+/// Assistants granted `classroom_id`, for review notifications.
+pub async fn assistants_for_classroom(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+) -> Result<Vec<user::Model>, DbErr> {
+    Ok(classroom_assistant::Entity::find()
+        .filter(classroom_assistant::Column::ClassroomId.eq(classroom_id))
+        .find_also_related(user::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(_, user)| user)
+        .filter(|user| user.role == Role::Assistant)
+        .collect())
+}
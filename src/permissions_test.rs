@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::super::entities::{reservation, sea_orm_active_enums::Role, user};
+    use super::super::memory_mode;
+    use super::super::permissions::{
+        ALL_PERMISSIONS, ClassroomScope, Permission, can_view_reservation, has_permission,
+        permissions_for,
+    };
+    use super::super::test_support::reservation_at;
+
+    fn user(id: &str, role: Role) -> user::Model {
+        let now = Utc::now().fixed_offset();
+        user::Model {
+            id: id.to_string(),
+            username: id.to_string(),
+            name: id.to_string(),
+            email: format!("{}@example.com", id),
+            password: String::new(),
+            phone_number: "0912345678".to_string(),
+            role,
+            created_at: now,
+            updated_at: now,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    fn reservation(user_id: &str) -> reservation::Model {
+        let now = Utc::now().fixed_offset();
+        reservation::Model {
+            user_id: Some(user_id.to_string()),
+            classroom_id: Some("r101".to_string()),
+            start_time: now,
+            end_time: now,
+            created_at: now,
+            ..reservation_at(
+                "r1",
+                "2025-03-10T09:00:00+08:00",
+                "2025-03-10T11:00:00+08:00",
+            )
+        }
+    }
+
+    #[test]
+    fn test_admin_has_every_permission() {
+        for permission in ALL_PERMISSIONS {
+            assert!(has_permission(&Role::Admin, permission));
+        }
+    }
+
+    #[test]
+    fn test_assistant_can_only_review_reservations() {
+        assert_eq!(
+            permissions_for(&Role::Assistant),
+            &[Permission::ReviewReservations]
+        );
+        assert!(!has_permission(&Role::Assistant, Permission::ManageUsers));
+        assert!(!has_permission(&Role::Assistant, Permission::ManageKeys));
+    }
+
+    #[test]
+    fn test_user_has_no_permissions() {
+        assert!(permissions_for(&Role::User).is_empty());
+    }
+
+    #[test]
+    fn test_classroom_scope() {
+        assert!(ClassroomScope::All.allows(Some("r101")));
+        assert!(ClassroomScope::All.allows(None));
+
+        let scope = ClassroomScope::Only(vec!["r101".to_string(), "r102".to_string()]);
+        assert!(scope.allows(Some("r102")));
+        assert!(!scope.allows(Some("r201")));
+        // Reservations whose classroom was deleted are left to admins
+        assert!(!scope.allows(None));
+        assert!(!ClassroomScope::Only(Vec::new()).allows(Some("r101")));
+    }
+
+    #[tokio::test]
+    async fn test_only_the_owner_and_reviewers_see_a_reservation() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let booking = reservation("alice");
+        let may_view = |user| {
+            let db = db.clone();
+            let booking = booking.clone();
+            async move { can_view_reservation(&db, &user, &booking).await.unwrap() }
+        };
+        assert!(may_view(user("alice", Role::User)).await);
+        assert!(may_view(user("admin", Role::Admin)).await);
+        assert!(!may_view(user("bob", Role::User)).await);
+        // Not granted the reservation's classroom
+        assert!(!may_view(user("carol", Role::Assistant)).await);
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::Utc;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    sea_query::Expr,
+};
+use tracing::warn;
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_client::send_email_to_user,
+    email_templates,
+    entities::{
+        classroom, key, key_pickup_code, reservation,
+        sea_orm_active_enums::{KeyStatus, ReservationStatus},
+        user,
+    },
+    error::AppError,
+};
+
+/// Pickup codes for keys handed out at the counter. When enabled, approving
+/// a reservation sets a key aside and emails the requester a one-time code
+/// that staff check when handing the key over.
+#[derive(Clone, Default)]
+pub struct PickupConfig {
+    pub enabled: bool,
+}
+
+fn generate_code() -> String {
+    const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+    nanoid!(6, &DIGITS)
+}
+
+/// Why a key cannot be handed over for a reservation with a pickup code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PickupError {
+    CodeRequired,
+    WrongCode,
+    /// The code sets aside another key
+    WrongKey {
+        key_id: String,
+    },
+}
+
+impl PickupError {
+    pub fn message(&self) -> String {
+        match self {
+            Self::CodeRequired => "This reservation needs its pickup code".to_string(),
+            Self::WrongCode => "Pickup code does not match".to_string(),
+            Self::WrongKey { key_id } => {
+                format!(
+                    "The pickup code sets aside key {} for this reservation",
+                    key_id
+                )
+            }
+        }
+    }
+}
+
+impl From<PickupError> for AppError {
+    fn from(error: PickupError) -> Self {
+        let message = error.message();
+        match error {
+            PickupError::WrongKey { .. } => AppError::Unprocessable(message),
+            _ => AppError::Forbidden(message),
+        }
+    }
+}
+
+/// Checks a code given at the counter against the reservation's active
+/// `code`. Reservations without one need no code. A code that sets aside a
+/// key must be used to borrow that key.
+pub fn verify(
+    code: Option<&key_pickup_code::Model>,
+    given: Option<&str>,
+    key_ids: &[String],
+) -> Result<(), PickupError> {
+    let Some(code) = code else {
+        return Ok(());
+    };
+    match given.map(str::trim) {
+        None | Some("") => return Err(PickupError::CodeRequired),
+        Some(given) if given != code.code => return Err(PickupError::WrongCode),
+        Some(_) => {}
+    }
+    match &code.key_id {
+        Some(key_id) if !key_ids.contains(key_id) => Err(PickupError::WrongKey {
+            key_id: key_id.clone(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// The unused, unrevoked pickup code of a reservation, if any.
+pub async fn active_code<C: ConnectionTrait>(
+    db: &C,
+    reservation_id: &str,
+) -> Result<Option<key_pickup_code::Model>, DbErr> {
+    key_pickup_code::Entity::find()
+        .filter(key_pickup_code::Column::ReservationId.eq(reservation_id))
+        .filter(key_pickup_code::Column::UsedAt.is_null())
+        .filter(key_pickup_code::Column::RevokedAt.is_null())
+        .order_by_desc(key_pickup_code::Column::CreatedAt)
+        .one(db)
+        .await
+}
+
+/// Marks `code` as used by the borrow recorded in `key_transaction_log_id`.
+pub async fn mark_used<C: ConnectionTrait>(
+    db: &C,
+    code: key_pickup_code::Model,
+    key_transaction_log_id: &str,
+) -> Result<(), DbErr> {
+    let mut active: key_pickup_code::ActiveModel = code.into();
+    active.used_at = Set(Some(Utc::now().with_timezone(&campus_offset())));
+    active.key_transaction_log_id = Set(Some(key_transaction_log_id.to_string()));
+    active.update(db).await.map(|_| ())
+}
+
+/// An active counter key of the classroom that no other approved
+/// reservation overlapping this one has set aside.
+async fn choose_key(
+    db: &DatabaseConnection,
+    reservation: &reservation::Model,
+    classroom_id: &str,
+) -> Result<Option<key::Model>, DbErr> {
+    let overlapping: Vec<String> = reservation::Entity::find()
+        .filter(reservation::Column::ClassroomId.eq(classroom_id))
+        .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+        .filter(reservation::Column::Id.ne(&reservation.id))
+        .filter(reservation::Column::StartTime.lt(reservation.end_time))
+        .filter(reservation::Column::EndTime.gt(reservation.start_time))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|r| r.id)
+        .collect();
+    let taken: Vec<String> = key_pickup_code::Entity::find()
+        .filter(key_pickup_code::Column::ReservationId.is_in(overlapping))
+        .filter(key_pickup_code::Column::UsedAt.is_null())
+        .filter(key_pickup_code::Column::RevokedAt.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|code| code.key_id)
+        .collect();
+
+    key::Entity::find()
+        .filter(key::Column::ClassroomId.eq(classroom_id))
+        .filter(key::Column::Status.eq(KeyStatus::Active))
+        .filter(key::Column::CabinetSlot.is_null())
+        .filter(key::Column::Id.is_not_in(taken))
+        .order_by_asc(key::Column::KeyNumber)
+        .one(db)
+        .await
+}
+
+/// Revokes any active code of `reservation`, sets a key aside and records a
+/// new code. The code is still issued, without a key, when every key of the
+/// classroom is taken.
+pub async fn create_code(
+    db: &DatabaseConnection,
+    reservation: &reservation::Model,
+) -> Result<(key_pickup_code::Model, Option<key::Model>), DbErr> {
+    revoke_active(db, &reservation.id).await?;
+
+    let key_model = match &reservation.classroom_id {
+        Some(classroom_id) => choose_key(db, reservation, classroom_id).await?,
+        None => None,
+    };
+    let code = key_pickup_code::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(reservation.id.clone()),
+        key_id: Set(key_model.as_ref().map(|k| k.id.clone())),
+        code: Set(generate_code()),
+        created_at: NotSet,
+        used_at: Set(None),
+        revoked_at: Set(None),
+        key_transaction_log_id: Set(None),
+    }
+    .insert(db)
+    .await?;
+    Ok((code, key_model))
+}
+
+async fn revoke_active(db: &DatabaseConnection, reservation_id: &str) -> Result<(), DbErr> {
+    key_pickup_code::Entity::update_many()
+        .col_expr(
+            key_pickup_code::Column::RevokedAt,
+            Expr::value(Utc::now().with_timezone(&campus_offset())),
+        )
+        .filter(key_pickup_code::Column::ReservationId.eq(reservation_id))
+        .filter(key_pickup_code::Column::UsedAt.is_null())
+        .filter(key_pickup_code::Column::RevokedAt.is_null())
+        .exec(db)
+        .await
+        .map(|_| ())
+}
+
+/// Emails the pickup code to the requester.
+pub async fn send_code(
+    state: &AppState,
+    reservation: &reservation::Model,
+    code: &key_pickup_code::Model,
+    key_model: Option<&key::Model>,
+) {
+    let Some(user_id) = &reservation.user_id else {
+        return;
+    };
+    let user_model = match user::Entity::find_by_id(user_id).one(&state.db).await {
+        Ok(Some(user_model)) => user_model,
+        _ => return,
+    };
+    let classroom_model = match &reservation.classroom_id {
+        Some(classroom_id) => classroom::Entity::find_by_id(classroom_id)
+            .one(&state.db)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+    let email = email_templates::pickup_code_issued(
+        &state.config.email_templates,
+        reservation,
+        classroom_model.as_ref(),
+        key_model,
+        &code.code,
+        state.config.email_templates.locale_for(&user_model),
+    );
+    if let Err(e) = send_email_to_user(
+        state.config.smtp.as_ref(),
+        &user_model,
+        email.subject,
+        email.body,
+    )
+    .await
+    {
+        warn!("Failed to send pickup code to {}: {}", user_model.id, e);
+    }
+}
+
+/// Issues and emails a pickup code for an approved reservation. Run in the
+/// background after approval; does nothing unless pickup codes are enabled.
+pub async fn issue_code(state: AppState, reservation: reservation::Model) {
+    if !state.config.pickup.enabled {
+        return;
+    }
+    match create_code(&state.db, &reservation).await {
+        Ok((code, key_model)) => send_code(&state, &reservation, &code, key_model.as_ref()).await,
+        Err(e) => warn!(
+            "Failed to issue pickup code for reservation {}: {}",
+            reservation.id, e
+        ),
+    }
+}
+
+/// Withdraws the unused pickup code of a reservation, e.g. after it was
+/// cancelled, freeing the key set aside for it.
+pub async fn revoke_codes(state: AppState, reservation_id: String) {
+    if let Err(e) = revoke_active(&state.db, &reservation_id).await {
+        warn!(
+            "Failed to revoke pickup code of reservation {}: {}",
+            reservation_id, e
+        );
+    }
+}
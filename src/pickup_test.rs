@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::key_pickup_code;
+    use super::super::pickup::{PickupError, verify};
+
+    fn code(key_id: Option<&str>) -> key_pickup_code::Model {
+        key_pickup_code::Model {
+            id: "c1".to_string(),
+            reservation_id: "r1".to_string(),
+            key_id: key_id.map(str::to_string),
+            code: "042917".to_string(),
+            created_at: "2025-03-10T09:00:00+08:00".parse().unwrap(),
+            used_at: None,
+            revoked_at: None,
+            key_transaction_log_id: None,
+        }
+    }
+
+    #[test]
+    fn test_reservation_without_code_needs_none() {
+        assert_eq!(verify(None, None, &["k1".to_string()]), Ok(()));
+    }
+
+    #[test]
+    fn test_missing_or_wrong_code_is_rejected() {
+        let code = code(Some("k1"));
+        let keys = ["k1".to_string()];
+        assert_eq!(
+            verify(Some(&code), None, &keys),
+            Err(PickupError::CodeRequired)
+        );
+        assert_eq!(
+            verify(Some(&code), Some("  "), &keys),
+            Err(PickupError::CodeRequired)
+        );
+        assert_eq!(
+            verify(Some(&code), Some("123456"), &keys),
+            Err(PickupError::WrongCode)
+        );
+    }
+
+    #[test]
+    fn test_code_must_collect_the_key_set_aside() {
+        let code = code(Some("k1"));
+        assert_eq!(
+            verify(Some(&code), Some("042917"), &["k2".to_string()]),
+            Err(PickupError::WrongKey {
+                key_id: "k1".to_string()
+            })
+        );
+        assert_eq!(
+            verify(
+                Some(&code),
+                Some(" 042917 "),
+                &["k2".to_string(), "k1".to_string()]
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_code_without_key_accepts_any_key() {
+        let code = code(None);
+        assert_eq!(
+            verify(Some(&code), Some("042917"), &["k9".to_string()]),
+            Ok(())
+        );
+    }
+}
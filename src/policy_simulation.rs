@@ -0,0 +1,193 @@
+use sea_orm::{DatabaseConnection, DbErr, prelude::DateTimeWithTimeZone};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    availability::{campus_offset, find_unavailability},
+    bans,
+    config::Config,
+    event_duplicates, quota, slots,
+};
+
+/// The checks a new reservation goes through, in the order they are applied.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRule {
+    /// The requester has a blacklist record in force
+    Blacklist,
+    /// Times are moved onto, or must already be on, the slot grid
+    SlotAlignment,
+    /// The classroom is out of service, closed, outside opening hours,
+    /// used by a course or already booked
+    Availability,
+    /// Active reservation count and hours per user
+    Quota,
+    /// Other bookings for the same event around the same time
+    DuplicateEvent,
+}
+
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleEffect {
+    Passed,
+    /// The request would be refused
+    Rejected,
+    /// The request would go through with different times
+    Adjusted,
+    /// The request would go through but be flagged for reviewers
+    Flagged,
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct RuleResult {
+    pub rule: PolicyRule,
+    pub effect: RuleEffect,
+    /// What the requester or reviewer would be told; absent when the rule
+    /// passed
+    pub message: Option<String>,
+}
+
+impl RuleResult {
+    fn passed(rule: PolicyRule) -> Self {
+        Self {
+            rule,
+            effect: RuleEffect::Passed,
+            message: None,
+        }
+    }
+
+    fn fired(rule: PolicyRule, effect: RuleEffect, message: String) -> Self {
+        Self {
+            rule,
+            effect,
+            message: Some(message),
+        }
+    }
+}
+
+/// What would happen to the request as a whole. Reservations that get
+/// through are never approved automatically; they wait for review.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SimulatedOutcome {
+    Rejected,
+    Pending,
+    PendingFlagged,
+}
+
+/// The outcome once every rule has been applied: any rejection refuses the
+/// request, otherwise a flag marks it for closer review.
+pub fn outcome(results: &[RuleResult]) -> SimulatedOutcome {
+    if results.iter().any(|r| r.effect == RuleEffect::Rejected) {
+        SimulatedOutcome::Rejected
+    } else if results.iter().any(|r| r.effect == RuleEffect::Flagged) {
+        SimulatedOutcome::PendingFlagged
+    } else {
+        SimulatedOutcome::Pending
+    }
+}
+
+/// A reservation request to run through the rules without creating it.
+pub struct SimulatedRequest<'a> {
+    pub user_id: &'a str,
+    pub classroom_id: &'a str,
+    pub start: DateTimeWithTimeZone,
+    pub end: DateTimeWithTimeZone,
+    pub event_name: Option<&'a str>,
+}
+
+/// Applies the same rules as creating a reservation, with the current
+/// settings and data, but keeps going after a rejection so every rule that
+/// would fire is reported.
+pub async fn simulate(
+    db: &DatabaseConnection,
+    config: &Config,
+    request: &SimulatedRequest<'_>,
+) -> Result<Vec<RuleResult>, DbErr> {
+    let mut results = Vec::new();
+
+    let now = chrono::Utc::now().with_timezone(&campus_offset());
+    let active_bans = bans::active_for_user(db, request.user_id, now).await?;
+    results.push(match bans::banned_message(&active_bans) {
+        Some(message) => RuleResult::fired(PolicyRule::Blacklist, RuleEffect::Rejected, message),
+        None => RuleResult::passed(PolicyRule::Blacklist),
+    });
+
+    // Later rules see the times the reservation would actually be stored with
+    let (start, end) = match slots::align(&config.slots, request.start, request.end) {
+        Ok((start, end, Some(adjustment))) => {
+            results.push(RuleResult::fired(
+                PolicyRule::SlotAlignment,
+                RuleEffect::Adjusted,
+                format!(
+                    "Times would be moved to {} - {} to fit {}-minute slots",
+                    start, end, adjustment.granularity_minutes
+                ),
+            ));
+            (start, end)
+        }
+        Ok((start, end, None)) => {
+            results.push(RuleResult::passed(PolicyRule::SlotAlignment));
+            (start, end)
+        }
+        Err(message) => {
+            results.push(RuleResult::fired(
+                PolicyRule::SlotAlignment,
+                RuleEffect::Rejected,
+                message,
+            ));
+            (request.start, request.end)
+        }
+    };
+
+    results.push(
+        match find_unavailability(db, request.classroom_id, start, end).await? {
+            Some(reason) => RuleResult::fired(
+                PolicyRule::Availability,
+                RuleEffect::Rejected,
+                reason.message(),
+            ),
+            None => RuleResult::passed(PolicyRule::Availability),
+        },
+    );
+
+    let hours = (end - start).num_minutes() as f64 / 60.0;
+    results.push(
+        match quota::enforce_quota(
+            db,
+            &config.quota,
+            request.user_id,
+            quota::QuotaUsage {
+                active_reservations: 1,
+                active_hours: hours,
+            },
+        )
+        .await?
+        {
+            Ok(()) => RuleResult::passed(PolicyRule::Quota),
+            Err(message) => RuleResult::fired(PolicyRule::Quota, RuleEffect::Rejected, message),
+        },
+    );
+
+    let event_name = request
+        .event_name
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    let duplicates = match event_name {
+        Some(name) => {
+            event_duplicates::find_duplicates(db, name, start, config.event_duplicates.window, None)
+                .await?
+        }
+        None => Vec::new(),
+    };
+    results.push(match event_name {
+        Some(name) if !duplicates.is_empty() => RuleResult::fired(
+            PolicyRule::DuplicateEvent,
+            RuleEffect::Flagged,
+            event_duplicates::duplicate_flag_reason(name, &duplicates),
+        ),
+        _ => RuleResult::passed(PolicyRule::DuplicateEvent),
+    });
+
+    Ok(results)
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use super::super::policy_simulation::{
+        PolicyRule, RuleEffect, RuleResult, SimulatedOutcome, outcome,
+    };
+
+    fn result(rule: PolicyRule, effect: RuleEffect) -> RuleResult {
+        RuleResult {
+            rule,
+            effect,
+            message: None,
+        }
+    }
+
+    #[test]
+    fn passing_request_waits_for_review() {
+        let results = [
+            result(PolicyRule::Blacklist, RuleEffect::Passed),
+            result(PolicyRule::SlotAlignment, RuleEffect::Adjusted),
+            result(PolicyRule::Quota, RuleEffect::Passed),
+        ];
+        assert_eq!(outcome(&results), SimulatedOutcome::Pending);
+    }
+
+    #[test]
+    fn flag_marks_request_for_review() {
+        let results = [
+            result(PolicyRule::Quota, RuleEffect::Passed),
+            result(PolicyRule::DuplicateEvent, RuleEffect::Flagged),
+        ];
+        assert_eq!(outcome(&results), SimulatedOutcome::PendingFlagged);
+    }
+
+    #[test]
+    fn any_rejection_wins_over_flags() {
+        let results = [
+            result(PolicyRule::Blacklist, RuleEffect::Passed),
+            result(PolicyRule::Availability, RuleEffect::Rejected),
+            result(PolicyRule::DuplicateEvent, RuleEffect::Flagged),
+        ];
+        assert_eq!(outcome(&results), SimulatedOutcome::Rejected);
+    }
+}
@@ -0,0 +1,212 @@
+use chrono::{Datelike, TimeZone, Timelike};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QuerySelect,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset,
+    entities::{classroom, reservation, sea_orm_active_enums::ReservationStatus},
+};
+
+/// Groups backed by fewer distinct users than `min_group_size` are left out
+/// of public statistics (k-anonymity).
+#[derive(Clone)]
+pub struct PublicStatsConfig {
+    pub min_group_size: u64,
+}
+
+impl Default for PublicStatsConfig {
+    fn default() -> Self {
+        Self { min_group_size: 5 }
+    }
+}
+
+/// Statuses that count as a booking that actually took place or will.
+const COUNTED_STATUSES: [ReservationStatus; 3] = [
+    ReservationStatus::Approved,
+    ReservationStatus::Completed,
+    ReservationStatus::NoShow,
+];
+
+/// The only facts about a reservation that feed into public statistics.
+pub struct BookingRow {
+    pub building: String,
+    pub user_id: Option<String>,
+    pub start_time: DateTimeWithTimeZone,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct BuildingMonthStat {
+    pub building: String,
+    /// Campus-local month, `YYYY-MM`
+    pub month: String,
+    pub bookings: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct HourStat {
+    /// Campus-local hour of the start time, 0-23
+    pub hour: u32,
+    pub bookings: u64,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq, Eq)]
+pub struct PublicStats {
+    /// Groups with fewer distinct users than this are suppressed
+    pub min_group_size: u64,
+    pub buildings_per_month: Vec<BuildingMonthStat>,
+    /// Busiest first
+    pub busiest_hours: Vec<HourStat>,
+    /// Number of groups left out because they were too small to publish
+    pub suppressed_groups: u64,
+}
+
+#[derive(Default)]
+struct Group {
+    bookings: u64,
+    users: BTreeSet<String>,
+}
+
+impl Group {
+    fn add(&mut self, user_id: &Option<String>) {
+        self.bookings += 1;
+        // Reservations whose user was deleted count as one anonymous user
+        self.users.insert(user_id.clone().unwrap_or_default());
+    }
+
+    fn publishable(&self, min_group_size: u64) -> bool {
+        self.users.len() as u64 >= min_group_size
+    }
+}
+
+/// Aggregates bookings per building/month and per hour of day, dropping every
+/// group that does not involve at least `min_group_size` distinct users.
+pub fn aggregate(rows: &[BookingRow], min_group_size: u64) -> PublicStats {
+    let mut by_building_month: BTreeMap<(String, String), Group> = BTreeMap::new();
+    let mut by_hour: BTreeMap<u32, Group> = BTreeMap::new();
+
+    for row in rows {
+        let local = row.start_time.with_timezone(&campus_offset());
+        let month = format!("{:04}-{:02}", local.year(), local.month());
+        by_building_month
+            .entry((row.building.clone(), month))
+            .or_default()
+            .add(&row.user_id);
+        by_hour.entry(local.hour()).or_default().add(&row.user_id);
+    }
+
+    let mut suppressed_groups = 0;
+    let mut buildings_per_month = Vec::new();
+    for ((building, month), group) in by_building_month {
+        if group.publishable(min_group_size) {
+            buildings_per_month.push(BuildingMonthStat {
+                building,
+                month,
+                bookings: group.bookings,
+            });
+        } else {
+            suppressed_groups += 1;
+        }
+    }
+
+    let mut busiest_hours = Vec::new();
+    for (hour, group) in by_hour {
+        if group.publishable(min_group_size) {
+            busiest_hours.push(HourStat {
+                hour,
+                bookings: group.bookings,
+            });
+        } else {
+            suppressed_groups += 1;
+        }
+    }
+    busiest_hours.sort_by(|a, b| b.bookings.cmp(&a.bookings).then(a.hour.cmp(&b.hour)));
+
+    PublicStats {
+        min_group_size,
+        buildings_per_month,
+        busiest_hours,
+        suppressed_groups,
+    }
+}
+
+/// Start of a campus-local month.
+pub fn month_start(year: i32, month: u32) -> Option<DateTimeWithTimeZone> {
+    campus_offset()
+        .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+        .single()
+}
+
+/// Start of the campus-local month given as `YYYY-MM`.
+pub fn parse_month(s: &str) -> Option<DateTimeWithTimeZone> {
+    let (year, month) = s.trim().split_once('-')?;
+    month_start(year.parse().ok()?, month.parse().ok()?)
+}
+
+fn month_index(dt: DateTimeWithTimeZone) -> i32 {
+    let local = dt.with_timezone(&campus_offset());
+    local.year() * 12 + local.month0() as i32
+}
+
+/// Start of the month `months` after (or before, when negative) the one
+/// `start` falls in.
+pub fn add_months(start: DateTimeWithTimeZone, months: i32) -> DateTimeWithTimeZone {
+    let index = month_index(start) + months;
+    month_start(index.div_euclid(12), index.rem_euclid(12) as u32 + 1)
+        .expect("first of the month always exists")
+}
+
+/// Whole months from the month of `from` to the month of `to`.
+pub fn months_between(from: DateTimeWithTimeZone, to: DateTimeWithTimeZone) -> i32 {
+    month_index(to) - month_index(from)
+}
+
+/// Loads the bookings starting in `[from, to)` and aggregates them. Only the
+/// building, user id and start time are read from the database.
+pub async fn load_public_stats(
+    db: &DatabaseConnection,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    min_group_size: u64,
+) -> Result<PublicStats, DbErr> {
+    let reservations: Vec<(Option<String>, Option<String>, DateTimeWithTimeZone)> =
+        reservation::Entity::find()
+            .select_only()
+            .column(reservation::Column::ClassroomId)
+            .column(reservation::Column::UserId)
+            .column(reservation::Column::StartTime)
+            .filter(reservation::Column::Status.is_in(COUNTED_STATUSES))
+            .filter(reservation::Column::StartTime.gte(from))
+            .filter(reservation::Column::StartTime.lt(to))
+            .into_tuple()
+            .all(db)
+            .await?;
+
+    let buildings: BTreeMap<String, String> = classroom::Entity::find()
+        .select_only()
+        .column(classroom::Column::Id)
+        .column(classroom::Column::Location)
+        .into_tuple::<(String, String)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    let rows: Vec<BookingRow> = reservations
+        .into_iter()
+        .filter_map(|(classroom_id, user_id, start_time)| {
+            let building = buildings.get(&classroom_id?)?.clone();
+            Some(BookingRow {
+                building,
+                user_id,
+                start_time,
+            })
+        })
+        .collect();
+
+    Ok(aggregate(&rows, min_group_size))
+}
@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use super::super::public_stats::{
+        BookingRow, BuildingMonthStat, HourStat, add_months, aggregate, months_between, parse_month,
+    };
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn row(building: &str, user: &str, start: &str) -> BookingRow {
+        BookingRow {
+            building: building.to_string(),
+            user_id: Some(user.to_string()),
+            start_time: dt(start),
+        }
+    }
+
+    #[test]
+    fn test_small_groups_are_suppressed() {
+        let mut rows: Vec<BookingRow> = (0..3)
+            .map(|i| row("A", &format!("u{}", i), "2025-03-10T09:00:00+08:00"))
+            .collect();
+        // One user booking building B many times is still one person
+        rows.extend((0..5).map(|_| row("B", "solo", "2025-03-11T14:00:00+08:00")));
+
+        let stats = aggregate(&rows, 3);
+        assert_eq!(
+            stats.buildings_per_month,
+            vec![BuildingMonthStat {
+                building: "A".to_string(),
+                month: "2025-03".to_string(),
+                bookings: 3,
+            }]
+        );
+        assert_eq!(
+            stats.busiest_hours,
+            vec![HourStat {
+                hour: 9,
+                bookings: 3
+            }]
+        );
+        // B/2025-03 and the 14:00 bucket
+        assert_eq!(stats.suppressed_groups, 2);
+    }
+
+    #[test]
+    fn test_groups_are_split_by_campus_month_and_hour() {
+        let rows = vec![
+            // 2025-03-31 23:30 UTC is already April on campus
+            row("A", "u1", "2025-03-31T23:30:00+00:00"),
+            row("A", "u2", "2025-04-02T07:15:00+08:00"),
+            row("A", "u3", "2025-03-15T10:00:00+08:00"),
+        ];
+        let stats = aggregate(&rows, 1);
+        assert_eq!(
+            stats.buildings_per_month,
+            vec![
+                BuildingMonthStat {
+                    building: "A".to_string(),
+                    month: "2025-03".to_string(),
+                    bookings: 1,
+                },
+                BuildingMonthStat {
+                    building: "A".to_string(),
+                    month: "2025-04".to_string(),
+                    bookings: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            stats.busiest_hours,
+            vec![
+                HourStat {
+                    hour: 7,
+                    bookings: 2
+                },
+                HourStat {
+                    hour: 10,
+                    bookings: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_busiest_hours_sorted_by_bookings() {
+        let mut rows = Vec::new();
+        for (hour, count) in [(8, 1), (13, 3), (10, 2)] {
+            for i in 0..count {
+                rows.push(row(
+                    "A",
+                    &format!("u{}", i),
+                    &format!("2025-03-10T{:02}:00:00+08:00", hour),
+                ));
+            }
+        }
+        let hours: Vec<u32> = aggregate(&rows, 1)
+            .busiest_hours
+            .iter()
+            .map(|h| h.hour)
+            .collect();
+        assert_eq!(hours, vec![13, 10, 8]);
+    }
+
+    #[test]
+    fn test_month_helpers() {
+        let march = parse_month("2025-03").unwrap();
+        assert_eq!(march, dt("2025-03-01T00:00:00+08:00"));
+        assert_eq!(add_months(march, 10), dt("2026-01-01T00:00:00+08:00"));
+        assert_eq!(add_months(march, -3), dt("2024-12-01T00:00:00+08:00"));
+        assert_eq!(months_between(march, add_months(march, 14)), 14);
+        assert!(parse_month("2025-13").is_none());
+        assert!(parse_month("March").is_none());
+    }
+}
@@ -0,0 +1,163 @@
+use axum::http::HeaderName;
+use chrono::Utc;
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+
+/// Response header with the message of a [`QuotaWarning`], for lists whose
+/// body has no room for one.
+pub static QUOTA_WARNING: HeaderName = HeaderName::from_static("quota-warning");
+/// Response header with the reservations left before the count limit.
+pub static QUOTA_REMAINING_RESERVATIONS: HeaderName =
+    HeaderName::from_static("quota-remaining-reservations");
+/// Response header with the hours left before the hours limit.
+pub static QUOTA_REMAINING_HOURS: HeaderName = HeaderName::from_static("quota-remaining-hours");
+
+/// Per-user limits on upcoming (pending or approved) reservations. A limit
+/// left as `None` is not enforced.
+#[derive(Clone, Default)]
+pub struct QuotaConfig {
+    pub max_active_reservations: Option<u64>,
+    pub max_active_hours: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QuotaUsage {
+    pub active_reservations: u64,
+    pub active_hours: f64,
+}
+
+impl QuotaUsage {
+    pub fn from_reservations(reservations: &[reservation::Model]) -> Self {
+        let active_hours = reservations
+            .iter()
+            .map(|r| (r.end_time - r.start_time).num_minutes().max(0) as f64 / 60.0)
+            .sum();
+        Self {
+            active_reservations: reservations.len() as u64,
+            active_hours,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct QuotaWarning {
+    pub max_active_reservations: Option<u64>,
+    pub remaining_reservations: Option<u64>,
+    pub max_active_hours: Option<f64>,
+    pub remaining_hours: Option<f64>,
+    pub message: String,
+}
+
+impl QuotaWarning {
+    /// The warning as response headers: its message, plus the remaining
+    /// count of each limit that is configured.
+    pub fn headers(&self) -> Vec<(&'static HeaderName, String)> {
+        let mut headers = vec![(&QUOTA_WARNING, self.message.clone())];
+        if let Some(remaining) = self.remaining_reservations {
+            headers.push((&QUOTA_REMAINING_RESERVATIONS, remaining.to_string()));
+        }
+        if let Some(remaining) = self.remaining_hours {
+            headers.push((&QUOTA_REMAINING_HOURS, remaining.to_string()));
+        }
+        headers
+    }
+}
+
+/// Returns an error message when adding the `requested` reservations, one
+/// per classroom of a request, would exceed the configured quota.
+pub fn check_quota(
+    config: &QuotaConfig,
+    usage: QuotaUsage,
+    requested: QuotaUsage,
+) -> Result<(), String> {
+    if let Some(max) = config.max_active_reservations
+        && usage.active_reservations + requested.active_reservations > max
+    {
+        return Err(format!(
+            "Reservation quota exceeded: at most {} active reservations allowed",
+            max
+        ));
+    }
+    if let Some(max) = config.max_active_hours
+        && usage.active_hours + requested.active_hours > max
+    {
+        return Err(format!(
+            "Reservation quota exceeded: at most {} reserved hours allowed",
+            max
+        ));
+    }
+    Ok(())
+}
+
+/// Builds a warning when the user is within one reservation or one hour of a
+/// limit, so clients can warn before requests start failing.
+pub fn quota_warning(config: &QuotaConfig, usage: QuotaUsage) -> Option<QuotaWarning> {
+    let remaining_reservations = config
+        .max_active_reservations
+        .map(|max| max.saturating_sub(usage.active_reservations));
+    let remaining_hours = config
+        .max_active_hours
+        .map(|max| (max - usage.active_hours).max(0.0));
+
+    let near_count = remaining_reservations.is_some_and(|r| r <= 1);
+    let near_hours = remaining_hours.is_some_and(|h| h <= 1.0);
+    if !near_count && !near_hours {
+        return None;
+    }
+
+    let message = match (remaining_reservations, remaining_hours) {
+        (Some(0), _) | (_, Some(0.0)) => "Reservation quota reached".to_string(),
+        _ => "Reservation quota almost reached".to_string(),
+    };
+
+    Some(QuotaWarning {
+        max_active_reservations: config.max_active_reservations,
+        remaining_reservations,
+        max_active_hours: config.max_active_hours,
+        remaining_hours,
+        message,
+    })
+}
+
+/// Upcoming reservations that count towards the user's quota.
+pub async fn active_reservations(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    reservation::Entity::find()
+        .filter(reservation::Column::UserId.eq(user_id))
+        .filter(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .filter(reservation::Column::EndTime.gt(Utc::now()))
+        .all(db)
+        .await
+}
+
+pub async fn usage_for_user(db: &DatabaseConnection, user_id: &str) -> Result<QuotaUsage, DbErr> {
+    let reservations = active_reservations(db, user_id).await?;
+    Ok(QuotaUsage::from_reservations(&reservations))
+}
+
+pub async fn enforce_quota(
+    db: &DatabaseConnection,
+    config: &QuotaConfig,
+    user_id: &str,
+    requested: QuotaUsage,
+) -> Result<Result<(), String>, DbErr> {
+    let usage = usage_for_user(db, user_id).await?;
+    Ok(check_quota(config, usage, requested))
+}
+
+pub async fn warning_for_user(
+    db: &DatabaseConnection,
+    config: &QuotaConfig,
+    user_id: &str,
+) -> Result<Option<QuotaWarning>, DbErr> {
+    let usage = usage_for_user(db, user_id).await?;
+    Ok(quota_warning(config, usage))
+}
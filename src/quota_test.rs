@@ -0,0 +1,117 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::http::StatusCode;
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::super::entities::sea_orm_active_enums::Role;
+    use super::super::quota::{QuotaConfig, QuotaUsage, check_quota, quota_warning};
+    use super::super::test_support::{add_classroom, add_user, json, router, send, sign_in, state};
+
+    fn config() -> QuotaConfig {
+        QuotaConfig {
+            max_active_reservations: Some(3),
+            max_active_hours: Some(10.0),
+        }
+    }
+
+    fn usage(active_reservations: u64, active_hours: f64) -> QuotaUsage {
+        QuotaUsage {
+            active_reservations,
+            active_hours,
+        }
+    }
+
+    #[test]
+    fn test_unlimited_quota_never_blocks_or_warns() {
+        let config = QuotaConfig::default();
+        assert!(check_quota(&config, usage(100, 500.0), usage(1, 10.0)).is_ok());
+        assert!(quota_warning(&config, usage(100, 500.0)).is_none());
+    }
+
+    #[test]
+    fn test_check_quota_count_limit() {
+        assert!(check_quota(&config(), usage(2, 2.0), usage(1, 1.0)).is_ok());
+        assert!(check_quota(&config(), usage(3, 2.0), usage(1, 1.0)).is_err());
+        // Each classroom of a request counts
+        assert!(check_quota(&config(), usage(1, 2.0), usage(2, 2.0)).is_ok());
+        assert!(check_quota(&config(), usage(2, 2.0), usage(2, 2.0)).is_err());
+    }
+
+    #[test]
+    fn test_check_quota_hour_limit() {
+        assert!(check_quota(&config(), usage(0, 8.0), usage(1, 2.0)).is_ok());
+        assert!(check_quota(&config(), usage(0, 8.0), usage(1, 2.5)).is_err());
+    }
+
+    #[test]
+    fn test_no_warning_with_room_left() {
+        assert!(quota_warning(&config(), usage(1, 4.0)).is_none());
+    }
+
+    #[test]
+    fn test_warning_one_reservation_left() {
+        let warning = quota_warning(&config(), usage(2, 4.0)).unwrap();
+        assert_eq!(warning.remaining_reservations, Some(1));
+        assert_eq!(warning.remaining_hours, Some(6.0));
+    }
+
+    #[test]
+    fn test_warning_one_hour_left() {
+        let warning = quota_warning(&config(), usage(1, 9.5)).unwrap();
+        assert_eq!(warning.remaining_hours, Some(0.5));
+    }
+
+    #[test]
+    fn test_warning_when_quota_reached() {
+        let warning = quota_warning(&config(), usage(3, 4.0)).unwrap();
+        assert_eq!(warning.remaining_reservations, Some(0));
+        assert_eq!(warning.message, "Reservation quota reached");
+    }
+
+    #[tokio::test]
+    async fn test_own_list_stays_an_array_with_the_warning_in_a_header() {
+        let mut state = state().await;
+        let mut config = (*state.config).clone();
+        config.quota.max_active_reservations = Some(2);
+        state.config = Arc::new(config);
+        add_user(&state, "u1", "u1@example.com", Role::User).await;
+        add_classroom(&state, "c1").await;
+        let app = router(state);
+        let cookie = sign_in(&app, "u1@example.com").await;
+
+        let created = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/reservation",
+                &cookie,
+                json!({
+                    "classroom_id": "c1",
+                    "purpose": "Study group",
+                    "start_time": "2030-03-11T09:00:00+08:00",
+                    "end_time": "2030-03-11T11:00:00+08:00",
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(created.status(), StatusCode::CREATED);
+
+        let response = app
+            .oneshot(send("GET", "/reservation/self/list", &cookie, json!({})))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["quota-warning"],
+            "Reservation quota almost reached"
+        );
+        assert_eq!(response.headers()["quota-remaining-reservations"], "1");
+        // No hours limit is configured
+        assert!(!response.headers().contains_key("quota-remaining-hours"));
+        let list = json(response).await;
+        assert_eq!(list.as_array().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+use std::net::IpAddr;
+
+use axum::http::HeaderMap;
+use chrono::Duration;
+use redis::{AsyncCommands, RedisError};
+use tracing::warn;
+
+use crate::{AppState, error::AppError, login_guard::client_ip, redis_breaker::RedisConnection};
+
+/// Requests allowed per window for one endpoint. `0` turns a limit off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EndpointLimit {
+    pub per_ip: u32,
+    /// Counted against the signed-in user, or the email in the request body
+    /// where nobody is signed in yet
+    pub per_user: u32,
+}
+
+/// Request rate limits for endpoints that scripts like to hammer. These
+/// count every request, unlike `login_guard`, which counts failed logins.
+#[derive(Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// Counters start at the first request and reset after this long
+    pub window: Duration,
+    pub register: EndpointLimit,
+    pub login: EndpointLimit,
+    pub forgot_password: EndpointLimit,
+    pub create_reservation: EndpointLimit,
+    /// Take the client IP from `X-Forwarded-For`; only safe behind a proxy
+    /// that sets it
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window: Duration::minutes(10),
+            register: EndpointLimit {
+                per_ip: 10,
+                per_user: 3,
+            },
+            login: EndpointLimit {
+                per_ip: 60,
+                per_user: 20,
+            },
+            forgot_password: EndpointLimit {
+                per_ip: 10,
+                per_user: 3,
+            },
+            create_reservation: EndpointLimit {
+                per_ip: 60,
+                per_user: 20,
+            },
+            trust_forwarded_for: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Endpoint {
+    Register,
+    Login,
+    ForgotPassword,
+    CreateReservation,
+}
+
+impl Endpoint {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Register => "register",
+            Self::Login => "login",
+            Self::ForgotPassword => "forgot_password",
+            Self::CreateReservation => "create_reservation",
+        }
+    }
+
+    pub fn limit(self, config: &RateLimitConfig) -> EndpointLimit {
+        match self {
+            Self::Register => config.register,
+            Self::Login => config.login,
+            Self::ForgotPassword => config.forgot_password,
+            Self::CreateReservation => config.create_reservation,
+        }
+    }
+}
+
+fn ip_key(endpoint: Endpoint, ip: IpAddr) -> String {
+    format!("rate_limit_{}_ip_{}", endpoint.name(), ip)
+}
+
+fn user_key(endpoint: Endpoint, user: &str) -> String {
+    format!(
+        "rate_limit_{}_user_{}",
+        endpoint.name(),
+        user.trim().to_lowercase()
+    )
+}
+
+/// Seconds until a window holding `count` requests and `ttl_seconds` left
+/// lets another one through, or `None` while it is within `max`.
+pub fn retry_after(count: u32, max: u32, ttl_seconds: i64) -> Option<u64> {
+    (max > 0 && count > max).then(|| ttl_seconds.max(1) as u64)
+}
+
+/// Counts a request against `key` and returns how long to wait when it
+/// goes over `max`.
+pub async fn hit(
+    redis: &mut RedisConnection,
+    key: &str,
+    max: u32,
+    window: Duration,
+) -> Result<Option<u64>, RedisError> {
+    if max == 0 {
+        return Ok(None);
+    }
+    let count: u32 = redis.incr(key, 1).await?;
+    if count == 1 {
+        redis.expire::<_, ()>(key, window.num_seconds()).await?;
+    }
+    if count <= max {
+        return Ok(None);
+    }
+    let ttl: i64 = redis.ttl(key).await?;
+    Ok(retry_after(count, max, ttl))
+}
+
+/// Counts a request to `endpoint` from the client in `headers` and `peer`,
+/// and `user` if known, turning it away with 429 and `Retry-After` once a
+/// limit is reached. Fails open so a Redis outage does not block sign-ups
+/// and logins.
+pub async fn check(
+    state: &AppState,
+    endpoint: Endpoint,
+    headers: &HeaderMap,
+    peer: IpAddr,
+    user: Option<&str>,
+) -> Result<(), AppError> {
+    let config = &state.config.rate_limit;
+    if !config.enabled {
+        return Ok(());
+    }
+    let limit = endpoint.limit(config);
+    let ip = client_ip(headers, peer, config.trust_forwarded_for);
+    let mut redis = state.redis.clone();
+
+    let mut wait = None;
+    let mut counters = vec![(ip_key(endpoint, ip), limit.per_ip)];
+    if let Some(user) = user.filter(|user| !user.trim().is_empty()) {
+        counters.push((user_key(endpoint, user), limit.per_user));
+    }
+    for (key, max) in counters {
+        match hit(&mut redis, &key, max, config.window).await {
+            Ok(retry_after) => wait = wait.max(retry_after),
+            Err(e) => warn!(
+                "Failed to count {} request from {}: {}",
+                endpoint.name(),
+                ip,
+                e
+            ),
+        }
+    }
+
+    match wait {
+        Some(retry_after) => {
+            warn!("Rate limited {} request from {}", endpoint.name(), ip);
+            Err(AppError::TooManyRequests {
+                message: "Too many requests, try again later".to_string(),
+                retry_after: Some(retry_after),
+            })
+        }
+        None => Ok(()),
+    }
+}
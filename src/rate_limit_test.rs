@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use axum::http::HeaderMap;
+    use chrono::Duration;
+
+    use super::super::{
+        error::AppError,
+        memory_redis::MemoryRedis,
+        rate_limit::{Endpoint, check, hit, retry_after},
+        redis_breaker::RedisConnection,
+        test_support,
+    };
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 7));
+
+    #[test]
+    fn test_retry_after() {
+        assert_eq!(retry_after(3, 3, 120), None);
+        assert_eq!(retry_after(4, 3, 120), Some(120));
+        // A key about to expire still asks for a second
+        assert_eq!(retry_after(4, 3, 0), Some(1));
+        // 0 is no limit
+        assert_eq!(retry_after(1000, 0, 120), None);
+    }
+
+    #[tokio::test]
+    async fn test_hit_counts_within_window() {
+        let mut redis = RedisConnection::in_memory(MemoryRedis::default());
+        let window = Duration::minutes(10);
+        for _ in 0..2 {
+            assert_eq!(hit(&mut redis, "k", 2, window).await.unwrap(), None);
+        }
+        let wait = hit(&mut redis, "k", 2, window).await.unwrap().unwrap();
+        assert!(wait > 590 && wait <= 600);
+        // Other keys count on their own
+        assert_eq!(hit(&mut redis, "other", 2, window).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_check_limits_per_user_with_retry_after() {
+        let state = test_support::state().await;
+        let headers = HeaderMap::new();
+        let per_user = Endpoint::ForgotPassword
+            .limit(&state.config.rate_limit)
+            .per_user;
+        for _ in 0..per_user {
+            check(
+                &state,
+                Endpoint::ForgotPassword,
+                &headers,
+                IP,
+                Some("a@example.com"),
+            )
+            .await
+            .unwrap();
+        }
+
+        let err = check(
+            &state,
+            Endpoint::ForgotPassword,
+            &headers,
+            IP,
+            Some(" A@example.com"),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            AppError::TooManyRequests {
+                retry_after: Some(_),
+                ..
+            }
+        ));
+        // The same address is still fine for another email and endpoint
+        check(
+            &state,
+            Endpoint::ForgotPassword,
+            &headers,
+            IP,
+            Some("b@example.com"),
+        )
+        .await
+        .unwrap();
+        check(&state, Endpoint::Login, &headers, IP, Some("a@example.com"))
+            .await
+            .unwrap();
+    }
+}
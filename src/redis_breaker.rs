@@ -0,0 +1,215 @@
+use std::{
+    io,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use redis::{
+    Cmd, ErrorKind, Pipeline, RedisError, RedisFuture, Value,
+    aio::{ConnectionLike, MultiplexedConnection},
+};
+use tracing::{info, warn};
+
+use crate::{
+    memory_redis::MemoryRedis,
+    server_timing::{Dependency, measure},
+};
+
+/// When Redis is considered down and how long it is left alone.
+#[derive(Clone)]
+pub struct RedisBreakerConfig {
+    /// Consecutive connection failures or timeouts that open the circuit
+    pub failure_threshold: u32,
+    /// How long calls fail fast once the circuit is open, before one is let
+    /// through to probe Redis again
+    pub open_for: Duration,
+    /// Longest a single command may take before it counts as a failure
+    pub timeout: Duration,
+}
+
+impl Default for RedisBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            open_for: Duration::from_secs(30),
+            timeout: Duration::from_millis(500),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BreakerState {
+    failures: u32,
+    opened_at: Option<Instant>,
+}
+
+/// Tracks whether Redis is reachable. Callers pass the current time so the
+/// state changes can be tested without waiting.
+pub struct CircuitBreaker {
+    config: RedisBreakerConfig,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: RedisBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Whether a call may go to Redis. Once `open_for` has passed calls are
+    /// let through again; the first failure among them reopens the circuit.
+    pub fn allows(&self, now: Instant) -> bool {
+        match self.state.lock().unwrap().opened_at {
+            Some(opened_at) => now.duration_since(opened_at) >= self.config.open_for,
+            None => true,
+        }
+    }
+
+    /// Whether Redis is currently treated as down.
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().opened_at.is_some()
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.opened_at.take().is_some() {
+            info!("Redis is reachable again, leaving degraded mode");
+        }
+        state.failures = 0;
+    }
+
+    pub fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        state.failures += 1;
+        if state.failures < self.config.failure_threshold {
+            return;
+        }
+        if state.opened_at.is_none() {
+            warn!(
+                "Redis failed {} times in a row, running without it for {}s",
+                state.failures,
+                self.config.open_for.as_secs()
+            );
+        }
+        state.opened_at = Some(now);
+    }
+}
+
+/// Errors that say Redis itself is unreachable or slow, as opposed to a
+/// command being rejected.
+pub fn is_outage(error: &RedisError) -> bool {
+    error.is_io_error() || error.is_unrecoverable_error()
+}
+
+/// A Redis connection behind a [`CircuitBreaker`]. Commands time out after
+/// [`RedisBreakerConfig::timeout`] and fail straight away while the circuit
+/// is open, so callers that treat Redis errors as a cache miss or fail open
+/// stop waiting on an outage.
+#[derive(Clone)]
+pub struct RedisConnection {
+    inner: Backend,
+    breaker: Arc<CircuitBreaker>,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Server(MultiplexedConnection),
+    /// In-process store used in memory mode
+    Memory(MemoryRedis),
+}
+
+impl RedisConnection {
+    pub fn new(inner: MultiplexedConnection, config: RedisBreakerConfig) -> Self {
+        Self {
+            inner: Backend::Server(inner),
+            breaker: Arc::new(CircuitBreaker::new(config)),
+        }
+    }
+
+    /// A connection to an in-process store instead of a Redis server.
+    pub fn in_memory(store: MemoryRedis) -> Self {
+        Self {
+            inner: Backend::Memory(store),
+            breaker: Arc::new(CircuitBreaker::new(RedisBreakerConfig::default())),
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.breaker.is_open()
+    }
+
+    async fn guard<T>(
+        &self,
+        request: impl Future<Output = Result<T, RedisError>>,
+    ) -> Result<T, RedisError> {
+        if !self.breaker.allows(Instant::now()) {
+            return Err(RedisError::from((
+                ErrorKind::IoError,
+                "Redis is unavailable, circuit open",
+            )));
+        }
+        let result = match measure(
+            Dependency::Redis,
+            tokio::time::timeout(self.breaker.config.timeout, request),
+        )
+        .await
+        {
+            Ok(result) => result,
+            Err(_) => Err(RedisError::from(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "Redis command timed out",
+            ))),
+        };
+        match &result {
+            Err(e) if is_outage(e) => self.breaker.record_failure(Instant::now()),
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            match &self.inner {
+                Backend::Server(inner) => {
+                    let mut inner = inner.clone();
+                    self.guard(inner.req_packed_command(cmd)).await
+                }
+                Backend::Memory(store) => store.execute_cmd(cmd),
+            }
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            match &self.inner {
+                Backend::Server(inner) => {
+                    let mut inner = inner.clone();
+                    self.guard(inner.req_packed_commands(cmd, offset, count))
+                        .await
+                }
+                Backend::Memory(store) => cmd
+                    .cmd_iter()
+                    .skip(offset)
+                    .take(count)
+                    .map(|cmd| store.execute_cmd(cmd))
+                    .collect(),
+            }
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        match &self.inner {
+            Backend::Server(inner) => inner.get_db(),
+            Backend::Memory(_) => 0,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use redis::{ErrorKind, RedisError};
+
+    use super::super::redis_breaker::{CircuitBreaker, RedisBreakerConfig, is_outage};
+
+    fn breaker() -> CircuitBreaker {
+        CircuitBreaker::new(RedisBreakerConfig {
+            failure_threshold: 3,
+            open_for: Duration::from_secs(30),
+            timeout: Duration::from_millis(500),
+        })
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker();
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        assert!(!breaker.is_open());
+        assert!(breaker.allows(now));
+        breaker.record_failure(now);
+        assert!(breaker.is_open());
+        assert!(!breaker.allows(now + Duration::from_secs(29)));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = breaker();
+        let now = Instant::now();
+        breaker.record_failure(now);
+        breaker.record_failure(now);
+        breaker.record_success();
+        breaker.record_failure(now);
+        assert!(!breaker.is_open());
+    }
+
+    #[test]
+    fn probes_again_after_open_period() {
+        let breaker = breaker();
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record_failure(now);
+        }
+        let later = now + Duration::from_secs(30);
+        assert!(breaker.allows(later));
+
+        // A failed probe keeps Redis out for another period
+        breaker.record_failure(later);
+        assert!(!breaker.allows(later + Duration::from_secs(1)));
+
+        breaker.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.allows(later + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn only_connection_errors_count_as_outages() {
+        let io = RedisError::from(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "timed out",
+        ));
+        assert!(is_outage(&io));
+        let wrong_type = RedisError::from((ErrorKind::TypeError, "wrong type"));
+        assert!(!is_outage(&wrong_type));
+    }
+}
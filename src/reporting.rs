@@ -0,0 +1,281 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use axum::{
+    Extension,
+    extract::Request,
+    http::HeaderMap,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset,
+    config::Config,
+    door_events::{bearer_token, constant_time_eq},
+    entities::{classroom, reservation, sea_orm_active_enums::ReservationStatus},
+    error::AppError,
+    pagination::PagedResponse,
+    public_stats::{add_months, months_between},
+};
+
+/// Settings for the read-only `/reporting/v1` API that external BI tools
+/// read from. Without tokens the API is disabled.
+#[derive(Clone, Default)]
+pub struct ReportingConfig {
+    pub tokens: Vec<String>,
+}
+
+/// Parses a comma-separated list of tokens.
+pub fn parse_tokens(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether a configured token is presented as `Authorization: Bearer <token>`.
+pub fn authorize(tokens: &[String], headers: &HeaderMap) -> bool {
+    let Some(presented) = bearer_token(headers) else {
+        return false;
+    };
+    tokens
+        .iter()
+        .any(|token| constant_time_eq(token.as_bytes(), presented.as_bytes()))
+}
+
+/// Route layer for the reporting API. Reporting tokens only open this API,
+/// and sessions do not open it.
+pub async fn require_token(
+    Extension(config): Extension<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let config = &config.reporting;
+    if config.tokens.is_empty() {
+        return AppError::ServiceUnavailable("Reporting API is not configured".to_string())
+            .into_response();
+    }
+    if !authorize(&config.tokens, req.headers()) {
+        return AppError::Unauthorized("Invalid token".to_string()).into_response();
+    }
+    next.run(req).await
+}
+
+/// Times in the reporting API are RFC 3339 in campus time, whatever the rest
+/// of the API uses.
+fn timestamp(dt: DateTimeWithTimeZone) -> String {
+    dt.with_timezone(&campus_offset()).to_rfc3339()
+}
+
+/// One page of a reporting list. Kept apart from the internal page type so
+/// the contract does not move with it.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct PageV1<T> {
+    /// Page number, starting at 1
+    pub page: u64,
+    pub page_size: u64,
+    /// Items across all pages
+    pub total: u64,
+    pub items: Vec<T>,
+}
+
+impl<T> PageV1<T> {
+    pub fn from_paged<U>(paged: PagedResponse<U>, f: impl FnMut(U) -> T) -> Self {
+        Self {
+            page: paged.page,
+            page_size: paged.page_size,
+            total: paged.total,
+            items: paged.items.into_iter().map(f).collect(),
+        }
+    }
+}
+
+/// Reservation status in the reporting API. New internal statuses have to
+/// be mapped here before they can be released.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReservationStatusV1 {
+    Pending,
+    Approved,
+    Rejected,
+    Cancelled,
+    NeedsRebooking,
+    Expired,
+    Completed,
+    NoShow,
+}
+
+impl From<&ReservationStatus> for ReservationStatusV1 {
+    fn from(status: &ReservationStatus) -> Self {
+        match status {
+            ReservationStatus::Pending => Self::Pending,
+            ReservationStatus::Approved => Self::Approved,
+            ReservationStatus::Rejected => Self::Rejected,
+            ReservationStatus::Cancelled => Self::Cancelled,
+            ReservationStatus::NeedsRebooking => Self::NeedsRebooking,
+            ReservationStatus::Expired => Self::Expired,
+            ReservationStatus::Completed => Self::Completed,
+            ReservationStatus::NoShow => Self::NoShow,
+        }
+    }
+}
+
+/// A reservation in the reporting API. Free text such as the purpose is left
+/// out, and users only appear by ID.
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct ReservationV1 {
+    pub id: String,
+    pub classroom_id: Option<String>,
+    pub user_id: Option<String>,
+    pub status: ReservationStatusV1,
+    pub start_time: String,
+    pub end_time: String,
+    pub created_at: String,
+    pub reviewed_at: Option<String>,
+    pub checked_in_at: Option<String>,
+}
+
+impl From<reservation::Model> for ReservationV1 {
+    fn from(reservation: reservation::Model) -> Self {
+        Self {
+            status: (&reservation.status).into(),
+            start_time: timestamp(reservation.start_time),
+            end_time: timestamp(reservation.end_time),
+            created_at: timestamp(reservation.created_at),
+            reviewed_at: reservation.reviewed_at.map(timestamp),
+            checked_in_at: reservation.checked_in_at.map(timestamp),
+            id: reservation.id,
+            classroom_id: reservation.classroom_id,
+            user_id: reservation.user_id,
+        }
+    }
+}
+
+/// Reservations that count as the room being used.
+pub const USED_STATUSES: [ReservationStatus; 3] = [
+    ReservationStatus::Approved,
+    ReservationStatus::Completed,
+    ReservationStatus::NoShow,
+];
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct ClassroomUtilizationV1 {
+    pub classroom_id: String,
+    pub name: String,
+    pub location: String,
+    pub capacity: i32,
+    /// Approved, completed and no-show reservations starting in the window
+    pub reservations: u64,
+    /// Booked time of those reservations
+    pub reserved_hours: f64,
+    pub no_shows: u64,
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq)]
+pub struct UtilizationV1 {
+    pub from: String,
+    pub to: String,
+    /// Every classroom, including unused ones, by ID
+    pub classrooms: Vec<ClassroomUtilizationV1>,
+}
+
+/// Per-classroom use of the rooms, from the reservations starting in
+/// `[from, to)` that have one of [`USED_STATUSES`].
+pub fn utilization(
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    classrooms: &[classroom::Model],
+    reservations: &[reservation::Model],
+) -> UtilizationV1 {
+    let mut rows: BTreeMap<&str, ClassroomUtilizationV1> = classrooms
+        .iter()
+        .map(|c| {
+            (
+                c.id.as_str(),
+                ClassroomUtilizationV1 {
+                    classroom_id: c.id.clone(),
+                    name: c.name.clone(),
+                    location: c.location.clone(),
+                    capacity: c.capacity,
+                    reservations: 0,
+                    reserved_hours: 0.0,
+                    no_shows: 0,
+                },
+            )
+        })
+        .collect();
+    for reservation in reservations {
+        if !USED_STATUSES.contains(&reservation.status)
+            || reservation.start_time < from
+            || reservation.start_time >= to
+        {
+            continue;
+        }
+        let Some(row) = reservation
+            .classroom_id
+            .as_deref()
+            .and_then(|id| rows.get_mut(id))
+        else {
+            continue;
+        };
+        row.reservations += 1;
+        row.reserved_hours +=
+            (reservation.end_time - reservation.start_time).num_minutes() as f64 / 60.0;
+        if reservation.status == ReservationStatus::NoShow {
+            row.no_shows += 1;
+        }
+    }
+    UtilizationV1 {
+        from: timestamp(from),
+        to: timestamp(to),
+        classrooms: rows.into_values().collect(),
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, PartialEq, Eq)]
+pub struct InfractionMonthV1 {
+    /// `YYYY-MM`, campus time
+    pub month: String,
+    pub infractions: u64,
+    /// Distinct users with an infraction in the month
+    pub users: u64,
+    /// Blacklist entries created in the month
+    pub bans: u64,
+}
+
+/// Infraction counts for every month from `from` up to but not including
+/// `to`, both month starts, including months without any.
+pub fn infraction_months(
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+    infractions: &[(Option<String>, DateTimeWithTimeZone)],
+    bans: &[DateTimeWithTimeZone],
+) -> Vec<InfractionMonthV1> {
+    (0..months_between(from, to).max(0))
+        .map(|i| {
+            let start = add_months(from, i);
+            let end = add_months(from, i + 1);
+            let in_month = |at: &DateTimeWithTimeZone| start <= *at && *at < end;
+            let month_infractions: Vec<_> =
+                infractions.iter().filter(|(_, at)| in_month(at)).collect();
+            let users: HashSet<&str> = month_infractions
+                .iter()
+                .filter_map(|(user_id, _)| user_id.as_deref())
+                .collect();
+            InfractionMonthV1 {
+                month: start.format("%Y-%m").to_string(),
+                infractions: month_infractions.len() as u64,
+                users: users.len() as u64,
+                bans: bans.iter().filter(|at| in_month(at)).count() as u64,
+            }
+        })
+        .collect()
+}
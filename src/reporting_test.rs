@@ -0,0 +1,193 @@
+#[cfg(test)]
+mod tests {
+    use axum::http::{HeaderMap, HeaderValue, header::AUTHORIZATION};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    use super::super::entities::{
+        classroom, reservation,
+        sea_orm_active_enums::{ClassroomStatus, ReservationStatus},
+    };
+    use super::super::reporting::{
+        InfractionMonthV1, ReservationStatusV1, ReservationV1, authorize, infraction_months,
+        parse_tokens, utilization,
+    };
+    use super::super::test_support::reservation_at;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn room(id: &str) -> classroom::Model {
+        classroom::Model {
+            id: id.to_string(),
+            name: id.to_uppercase(),
+            location: "Building A".to_string(),
+            capacity: 40,
+            description: String::new(),
+            status: ClassroomStatus::Available,
+            created_at: dt("2025-01-01T00:00:00+08:00"),
+            updated_at: dt("2025-01-01T00:00:00+08:00"),
+            photo_id: String::new(),
+            status_reason: None,
+            status_from: None,
+            status_until: None,
+            deleted_at: None,
+            name_en: None,
+            name_zh_tw: None,
+            description_en: None,
+            description_zh_tw: None,
+            requires_double_approval: false,
+            version: 1,
+        }
+    }
+
+    fn reservation(
+        id: &str,
+        classroom_id: &str,
+        status: ReservationStatus,
+        start: &str,
+        end: &str,
+    ) -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some(classroom_id.to_string()),
+            status,
+            ..reservation_at(id, start, end)
+        }
+    }
+
+    #[test]
+    fn test_authorize_accepts_only_configured_bearer_tokens() {
+        let tokens = parse_tokens(" bi-1, ,bi-2 ");
+        assert_eq!(tokens, ["bi-1", "bi-2"]);
+
+        let mut headers = HeaderMap::new();
+        assert!(!authorize(&tokens, &headers));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer bi-2"));
+        assert!(authorize(&tokens, &headers));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer bi-3"));
+        assert!(!authorize(&tokens, &headers));
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("bi-1"));
+        assert!(!authorize(&tokens, &headers));
+    }
+
+    #[test]
+    fn test_reservation_v1_uses_campus_time_and_drops_free_text() {
+        let mut model = reservation(
+            "r1",
+            "c1",
+            ReservationStatus::NoShow,
+            "2025-03-10T01:00:00+00:00",
+            "2025-03-10T03:00:00+00:00",
+        );
+        model.reject_reason = Some("private".to_string());
+        let v1 = ReservationV1::from(model);
+        assert_eq!(v1.status, ReservationStatusV1::NoShow);
+        assert_eq!(v1.start_time, "2025-03-10T09:00:00+08:00");
+        assert_eq!(v1.end_time, "2025-03-10T11:00:00+08:00");
+
+        let json = serde_json::to_value(&v1).unwrap();
+        assert_eq!(json["status"], "no_show");
+        assert!(json.get("purpose").is_none());
+        assert!(json.get("reject_reason").is_none());
+    }
+
+    #[test]
+    fn test_utilization_counts_used_reservations_in_window() {
+        let classrooms = [room("c2"), room("c1")];
+        let reservations = [
+            reservation(
+                "r1",
+                "c1",
+                ReservationStatus::Approved,
+                "2025-03-10T09:00:00+08:00",
+                "2025-03-10T10:30:00+08:00",
+            ),
+            reservation(
+                "r2",
+                "c1",
+                ReservationStatus::NoShow,
+                "2025-03-11T09:00:00+08:00",
+                "2025-03-11T10:00:00+08:00",
+            ),
+            // Not used, outside the window or in a deleted room
+            reservation(
+                "r3",
+                "c1",
+                ReservationStatus::Cancelled,
+                "2025-03-12T09:00:00+08:00",
+                "2025-03-12T10:00:00+08:00",
+            ),
+            reservation(
+                "r4",
+                "c1",
+                ReservationStatus::Completed,
+                "2025-04-01T09:00:00+08:00",
+                "2025-04-01T10:00:00+08:00",
+            ),
+            reservation(
+                "r5",
+                "gone",
+                ReservationStatus::Completed,
+                "2025-03-12T09:00:00+08:00",
+                "2025-03-12T10:00:00+08:00",
+            ),
+        ];
+        let report = utilization(
+            dt("2025-03-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+            &classrooms,
+            &reservations,
+        );
+        assert_eq!(report.from, "2025-03-01T00:00:00+08:00");
+        let ids: Vec<&str> = report
+            .classrooms
+            .iter()
+            .map(|c| c.classroom_id.as_str())
+            .collect();
+        assert_eq!(ids, ["c1", "c2"]);
+        assert_eq!(report.classrooms[0].reservations, 2);
+        assert_eq!(report.classrooms[0].reserved_hours, 2.5);
+        assert_eq!(report.classrooms[0].no_shows, 1);
+        assert_eq!(report.classrooms[1].reservations, 0);
+    }
+
+    #[test]
+    fn test_infraction_months_include_empty_months() {
+        let infractions = [
+            (Some("u1".to_string()), dt("2025-01-05T10:00:00+08:00")),
+            (Some("u1".to_string()), dt("2025-01-20T10:00:00+08:00")),
+            (Some("u2".to_string()), dt("2025-01-31T23:30:00+08:00")),
+            (None, dt("2025-03-01T00:00:00+08:00")),
+        ];
+        let bans = [dt("2025-01-21T10:00:00+08:00")];
+        let months = infraction_months(
+            dt("2025-01-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+            &infractions,
+            &bans,
+        );
+        assert_eq!(
+            months,
+            vec![
+                InfractionMonthV1 {
+                    month: "2025-01".to_string(),
+                    infractions: 3,
+                    users: 2,
+                    bans: 1,
+                },
+                InfractionMonthV1 {
+                    month: "2025-02".to_string(),
+                    infractions: 0,
+                    users: 0,
+                    bans: 0,
+                },
+                InfractionMonthV1 {
+                    month: "2025-03".to_string(),
+                    infractions: 1,
+                    users: 0,
+                    bans: 0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use nanoid::nanoid;
+
+pub static REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest id taken over from a client or proxy.
+const MAX_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+    static CURRENT: String;
+}
+
+/// Id of the request being handled, or `None` outside a request, e.g. in
+/// background jobs.
+pub fn current() -> Option<String> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// The id sent by a client or proxy in `x-request-id`, if it looks like
+/// one; anything else is replaced so ids stay safe to log.
+pub fn from_header(value: Option<&HeaderValue>) -> Option<String> {
+    let id = value?.to_str().ok()?;
+    let valid = (1..=MAX_ID_LENGTH).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then(|| id.to_string())
+}
+
+/// Gives every request an id, taken from `x-request-id` or generated, which
+/// the request's span, its error responses and the `x-request-id` response
+/// header carry, so a failure a user reports can be found in the logs.
+pub async fn track(mut req: Request, next: Next) -> Response {
+    let id = from_header(req.headers().get(&REQUEST_ID)).unwrap_or_else(|| nanoid!());
+    let value = HeaderValue::from_str(&id).expect("request ids are ASCII");
+    req.headers_mut().insert(REQUEST_ID.clone(), value.clone());
+    let mut response = CURRENT.scope(id, next.run(req)).await;
+    response.headers_mut().insert(REQUEST_ID.clone(), value);
+    response
+}
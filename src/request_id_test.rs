@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod tests {
+    use axum::{
+        Router,
+        body::{Body, to_bytes},
+        http::{HeaderValue, Request},
+        middleware,
+        routing::get,
+    };
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::super::{
+        error::{AppError, json_errors},
+        request_id::{self, REQUEST_ID, from_header},
+    };
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/fail",
+                get(|| async { AppError::NotFound("Nothing here".to_string()) }),
+            )
+            .route(
+                "/id",
+                get(|| async { request_id::current().unwrap_or_default() }),
+            )
+            .layer(middleware::map_response(json_errors))
+            .layer(middleware::from_fn(request_id::track))
+    }
+
+    #[test]
+    fn test_only_plain_ids_are_taken_over() {
+        let header = |value: &str| HeaderValue::from_str(value).unwrap();
+        assert_eq!(
+            from_header(Some(&header("req-123_abc.def:1"))),
+            Some("req-123_abc.def:1".to_string())
+        );
+        assert_eq!(from_header(Some(&header(""))), None);
+        assert_eq!(from_header(Some(&header("has space"))), None);
+        assert_eq!(from_header(Some(&header(&"a".repeat(129)))), None);
+        assert_eq!(from_header(None), None);
+    }
+
+    #[tokio::test]
+    async fn test_error_bodies_carry_the_request_id() {
+        let response = app()
+            .oneshot(Request::get("/fail").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let id = response.headers()[&REQUEST_ID]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(!id.is_empty());
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["request_id"], id.as_str());
+        assert_eq!(body["code"], "not_found");
+    }
+
+    #[tokio::test]
+    async fn test_incoming_id_is_kept() {
+        let response = app()
+            .oneshot(
+                Request::get("/id")
+                    .header(&REQUEST_ID, "from-proxy-1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[&REQUEST_ID], "from-proxy-1");
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&bytes[..], b"from-proxy-1");
+    }
+}
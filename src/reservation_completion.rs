@@ -0,0 +1,95 @@
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, DbErr, EntityTrait, QueryFilter, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait},
+};
+use tokio::time::MissedTickBehavior;
+use tracing::{info, warn};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    domain_events::{self, DomainEvent},
+    entities::{reservation, sea_orm_active_enums::ReservationStatus},
+    reservation_groups,
+    reservation_lifecycle::{self, Actor},
+};
+
+/// How often ended reservations are looked for to mark them completed.
+const COMPLETION_SCAN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+/// Whether the system may mark `reservation` completed at `now`: it was
+/// approved and its time is over. Checking in is not required.
+pub fn is_due(reservation: &reservation::Model, now: DateTimeWithTimeZone) -> bool {
+    reservation_lifecycle::check_transition(
+        &reservation.status,
+        &ReservationStatus::Completed,
+        Actor::System,
+        reservation.start_time,
+        reservation.end_time,
+        now.to_utc(),
+    )
+    .is_ok()
+}
+
+/// Completes ended reservations every [`COMPLETION_SCAN_INTERVAL`]. Spawned
+/// once at startup.
+pub async fn run(state: AppState) {
+    let mut ticker = tokio::time::interval(COMPLETION_SCAN_INTERVAL);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = complete_ended(&state).await {
+            warn!("Reservation completion scan failed: {}", e);
+        }
+    }
+}
+
+/// Marks approved reservations whose end time has passed as completed,
+/// together with the rooms booked with them, and publishes
+/// [`DomainEvent::ReservationCompleted`] for each, which asks the requester
+/// for feedback.
+pub async fn complete_ended(state: &AppState) -> Result<(), DbErr> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let ended = reservation::Entity::find()
+        .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+        .filter(reservation::Column::EndTime.lte(now))
+        .all(&state.db)
+        .await?;
+    for reservation in ended.into_iter().filter(|r| is_due(r, now)) {
+        // The whole group is completed in one transaction. Reads go through
+        // it too, as memory mode has a single connection
+        let txn = state.db.begin().await?;
+        let mut completed = Vec::new();
+        for mut member in reservation_groups::members(&txn, &reservation).await? {
+            // Claimed first so parallel instances and admins completing it
+            // by hand only trigger one feedback request
+            let claimed = reservation::Entity::update_many()
+                .col_expr(
+                    reservation::Column::Status,
+                    Expr::value(ReservationStatus::Completed),
+                )
+                .col_expr(
+                    reservation::Column::Version,
+                    Expr::col(reservation::Column::Version).add(1),
+                )
+                .filter(reservation::Column::Id.eq(&member.id))
+                .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+                .exec(&txn)
+                .await?;
+            if claimed.rows_affected == 0 {
+                continue;
+            }
+            member.status = ReservationStatus::Completed;
+            member.version += 1;
+            completed.push(member);
+        }
+        txn.commit().await?;
+        for reservation in completed {
+            info!("Reservation {} completed", reservation.id);
+            domain_events::publish(state, DomainEvent::ReservationCompleted { reservation }).await;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,57 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, FixedOffset};
+
+    use super::super::test_support::reservation_at;
+    use super::super::{
+        entities::{reservation, sea_orm_active_enums::ReservationStatus},
+        reservation_completion::is_due,
+    };
+
+    fn at(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn reservation(status: ReservationStatus) -> reservation::Model {
+        reservation::Model {
+            approved_by: Some("a1".to_string()),
+            status,
+            reviewed_at: Some(at("2025-03-01T10:00:00+08:00")),
+            ..reservation_at(
+                "r1",
+                "2025-03-03T10:00:00+08:00",
+                "2025-03-03T12:00:00+08:00",
+            )
+        }
+    }
+
+    #[test]
+    fn approved_reservation_is_due_once_it_ends() {
+        let approved = reservation(ReservationStatus::Approved);
+        assert!(!is_due(&approved, approved.end_time - Duration::seconds(1)));
+        assert!(is_due(&approved, approved.end_time));
+    }
+
+    #[test]
+    fn checked_in_reservation_is_due_too() {
+        let mut approved = reservation(ReservationStatus::Approved);
+        approved.checked_in_at = Some(approved.start_time);
+        assert!(is_due(&approved, approved.end_time + Duration::hours(1)));
+    }
+
+    #[test]
+    fn only_approved_reservations_are_completed() {
+        for status in [
+            ReservationStatus::Pending,
+            ReservationStatus::Rejected,
+            ReservationStatus::Cancelled,
+            ReservationStatus::NeedsRebooking,
+            ReservationStatus::Expired,
+            ReservationStatus::Completed,
+            ReservationStatus::NoShow,
+        ] {
+            let r = reservation(status);
+            assert!(!is_due(&r, r.end_time + Duration::hours(1)));
+        }
+    }
+}
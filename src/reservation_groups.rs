@@ -0,0 +1,73 @@
+use sea_orm::{ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder};
+
+use crate::{constants::MAX_ROOMS_PER_RESERVATION, entities::reservation};
+
+/// Classrooms a reservation request asks for: `classroom_id` for one room,
+/// or `classroom_ids` for several rooms in the same slot. Returns an error
+/// message when the request names none, both or too many.
+pub fn requested_rooms(
+    classroom_id: Option<String>,
+    classroom_ids: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let rooms = match (classroom_id, classroom_ids.is_empty()) {
+        (Some(classroom_id), true) => return Ok(vec![classroom_id]),
+        (None, false) => classroom_ids,
+        (None, true) => return Err("'classroom_id' or 'classroom_ids' is required".to_string()),
+        (Some(_), false) => {
+            return Err("Give either 'classroom_id' or 'classroom_ids', not both".to_string());
+        }
+    };
+    if rooms.len() > MAX_ROOMS_PER_RESERVATION {
+        return Err(format!(
+            "At most {} classrooms can be reserved together",
+            MAX_ROOMS_PER_RESERVATION
+        ));
+    }
+    if rooms
+        .iter()
+        .enumerate()
+        .any(|(i, room)| rooms[..i].contains(room))
+    {
+        return Err("'classroom_ids' lists a classroom twice".to_string());
+    }
+    Ok(rooms)
+}
+
+/// The reservation and the others booked with it, the given one first. A
+/// reservation outside a group is returned alone.
+pub async fn members<C: ConnectionTrait>(
+    db: &C,
+    reservation: &reservation::Model,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let Some(group_id) = &reservation.group_id else {
+        return Ok(vec![reservation.clone()]);
+    };
+    let others = reservation::Entity::find()
+        .filter(reservation::Column::GroupId.eq(group_id))
+        .filter(reservation::Column::Id.ne(&reservation.id))
+        .order_by_asc(reservation::Column::Id)
+        .all(db)
+        .await?;
+    Ok(std::iter::once(reservation.clone()).chain(others).collect())
+}
+
+/// The reservations together with every other member of their groups, in
+/// order and without repeats. Bulk changes go through this so a group never
+/// ends up half changed.
+pub async fn with_members<C: ConnectionTrait>(
+    db: &C,
+    reservations: Vec<reservation::Model>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let mut expanded: Vec<reservation::Model> = Vec::with_capacity(reservations.len());
+    for reservation in reservations {
+        if expanded.iter().any(|r| r.id == reservation.id) {
+            continue;
+        }
+        for member in members(db, &reservation).await? {
+            if !expanded.iter().any(|r| r.id == member.id) {
+                expanded.push(member);
+            }
+        }
+    }
+    Ok(expanded)
+}
@@ -0,0 +1,363 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{Router, http::StatusCode};
+    use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait, prelude::DateTimeWithTimeZone};
+    use serde_json::{Value, json};
+    use tower::ServiceExt;
+
+    use super::super::{
+        AppState,
+        entities::{
+            reservation,
+            sea_orm_active_enums::{ReservationStatus, Role},
+        },
+        memory_mode,
+        reservation_groups::{members, requested_rooms},
+        review_lock,
+        test_support::{add_classroom, add_user, json, router, send, sign_in, state},
+    };
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn rooms(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn test_requested_rooms() {
+        assert_eq!(
+            requested_rooms(Some("c1".to_string()), Vec::new()),
+            Ok(rooms(&["c1"]))
+        );
+        assert_eq!(
+            requested_rooms(None, rooms(&["c1", "c2", "c3"])),
+            Ok(rooms(&["c1", "c2", "c3"]))
+        );
+        assert!(requested_rooms(None, Vec::new()).is_err());
+        assert!(requested_rooms(Some("c1".to_string()), rooms(&["c2"])).is_err());
+        assert!(requested_rooms(None, rooms(&["c1", "c2", "c1"])).is_err());
+        assert!(requested_rooms(None, rooms(&["c1", "c2", "c3", "c4"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_members_start_with_the_given_reservation() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let mut saved = Vec::new();
+        for (id, group_id) in [("r1", Some("g1")), ("r2", Some("g1")), ("r3", None)] {
+            let model = reservation::ActiveModel {
+                id: Set(id.to_string()),
+                purpose: Set("Robotics demo day".to_string()),
+                start_time: Set(dt("2025-03-10T09:00:00+08:00")),
+                end_time: Set(dt("2025-03-10T11:00:00+08:00")),
+                status: Set(ReservationStatus::Pending),
+                flagged_for_review: Set(false),
+                group_id: Set(group_id.map(str::to_string)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            saved.push(model);
+        }
+
+        let ids = |list: Vec<reservation::Model>| -> Vec<String> {
+            list.into_iter().map(|r| r.id).collect()
+        };
+        assert_eq!(
+            ids(members(&db, &saved[1]).await.unwrap()),
+            rooms(&["r2", "r1"])
+        );
+        assert_eq!(ids(members(&db, &saved[2]).await.unwrap()), rooms(&["r3"]));
+    }
+
+    /// A requester, two admins and three classrooms, with their cookies.
+    async fn campus() -> (AppState, Router, String, String, String) {
+        let state = state().await;
+        add_user(&state, "u1", "u1@example.com", Role::User).await;
+        add_user(&state, "a1", "a1@example.com", Role::Admin).await;
+        add_user(&state, "a2", "a2@example.com", Role::Admin).await;
+        for id in ["c1", "c2", "c3"] {
+            add_classroom(&state, id).await;
+        }
+        let app = router(state.clone());
+        let requester = sign_in(&app, "u1@example.com").await;
+        let admin = sign_in(&app, "a1@example.com").await;
+        let other_admin = sign_in(&app, "a2@example.com").await;
+        (state, app, requester, admin, other_admin)
+    }
+
+    /// Books `rooms` together from 09:00 to 11:00 on `day` and returns the
+    /// IDs of the reservations made.
+    async fn book(
+        app: &Router,
+        cookie: &str,
+        rooms: &[&str],
+        day: &str,
+    ) -> (StatusCode, Vec<String>) {
+        let response = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/reservation",
+                cookie,
+                json!({
+                    "classroom_ids": rooms,
+                    "purpose": "Robotics demo day",
+                    "start_time": format!("{}T09:00:00+08:00", day),
+                    "end_time": format!("{}T11:00:00+08:00", day),
+                }),
+            ))
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = json(response).await;
+        let ids = std::iter::once(&body)
+            .chain(body["linked_reservations"].as_array().into_iter().flatten())
+            .filter_map(|reservation| reservation["id"].as_str().map(str::to_string))
+            .collect();
+        (status, ids)
+    }
+
+    async fn review(app: &Router, cookie: &str, id: &str, body: Value) -> StatusCode {
+        app.clone()
+            .oneshot(send(
+                "PUT",
+                &format!("/reservation/{}/review", id),
+                cookie,
+                body,
+            ))
+            .await
+            .unwrap()
+            .status()
+    }
+
+    async fn statuses(state: &AppState, ids: &[String]) -> Vec<ReservationStatus> {
+        let mut statuses = Vec::new();
+        for id in ids {
+            let reservation = reservation::Entity::find_by_id(id)
+                .one(&state.db)
+                .await
+                .unwrap()
+                .unwrap();
+            statuses.push(reservation.status);
+        }
+        statuses
+    }
+
+    #[tokio::test]
+    async fn test_a_claim_on_any_member_holds_up_the_group() {
+        let (state, app, requester, admin, other_admin) = campus().await;
+        let (status, ids) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(ids.len(), 2);
+
+        let claim = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                &format!("/reservation/admin/{}/claim", ids[1]),
+                &other_admin,
+                json!({}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(claim.status(), StatusCode::OK);
+
+        let approve = json!({ "status": "Approved" });
+        assert_eq!(
+            review(&app, &admin, &ids[0], approve.clone()).await,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            statuses(&state, &ids).await,
+            vec![ReservationStatus::Pending, ReservationStatus::Pending]
+        );
+
+        // The claimant reviews through the other room and lets go of both
+        assert_eq!(
+            review(&app, &other_admin, &ids[0], approve).await,
+            StatusCode::OK
+        );
+        let mut redis = state.redis.clone();
+        for id in &ids {
+            assert!(
+                review_lock::current(&mut redis, id)
+                    .await
+                    .unwrap()
+                    .is_none()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reviewing_one_member_changes_the_whole_group() {
+        let (state, app, requester, admin, _) = campus().await;
+        use ReservationStatus::*;
+
+        let (_, rejected) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        let reject = json!({ "status": "Rejected", "reject_reason": "Exam week" });
+        assert_eq!(
+            review(&app, &admin, &rejected[1], reject).await,
+            StatusCode::OK
+        );
+        assert_eq!(statuses(&state, &rejected).await, vec![Rejected, Rejected]);
+
+        let (_, group) = book(&app, &requester, &["c2", "c3"], "2030-03-11").await;
+        let approve = json!({ "status": "Approved" });
+        assert_eq!(
+            review(&app, &admin, &group[1], approve).await,
+            StatusCode::OK
+        );
+        assert_eq!(statuses(&state, &group).await, vec![Approved, Approved]);
+
+        let cancel = app
+            .clone()
+            .oneshot(send(
+                "DELETE",
+                &format!("/reservation/{}", group[0]),
+                &requester,
+                json!({}),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(cancel.status(), StatusCode::OK);
+        assert_eq!(statuses(&state, &group).await, vec![Cancelled, Cancelled]);
+    }
+
+    #[tokio::test]
+    async fn test_a_member_that_cannot_change_holds_back_the_others() {
+        let (state, app, requester, admin, _) = campus().await;
+        let (_, group) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        let cancelled = reservation::Entity::find_by_id(&group[1])
+            .one(&state.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut cancelled: reservation::ActiveModel = cancelled.into();
+        cancelled.status = Set(ReservationStatus::Cancelled);
+        cancelled.update(&state.db).await.unwrap();
+
+        assert_eq!(
+            review(&app, &admin, &group[0], json!({ "status": "Approved" })).await,
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            statuses(&state, &group).await,
+            vec![ReservationStatus::Pending, ReservationStatus::Cancelled]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_a_conflict_in_any_room_rejects_the_whole_request() {
+        let (state, app, requester, admin, _) = campus().await;
+        let (_, booked) = book(&app, &requester, &["c2"], "2030-03-11").await;
+        assert_eq!(
+            review(&app, &admin, &booked[0], json!({ "status": "Approved" })).await,
+            StatusCode::OK
+        );
+
+        let (status, _) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        // Nothing was booked in the free room either
+        assert_eq!(
+            reservation::Entity::find()
+                .all(&state.db)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_a_group_counts_once_per_room_against_the_quota() {
+        let (mut state, _, _, _, _) = campus().await;
+        let mut config = (*state.config).clone();
+        config.quota.max_active_reservations = Some(3);
+        state.config = Arc::new(config);
+        let app = router(state.clone());
+        let requester = sign_in(&app, "u1@example.com").await;
+
+        let (status, group) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(group.len(), 2);
+        // Two more rooms would make four active reservations
+        let (status, _) = book(&app, &requester, &["c1", "c2"], "2030-03-12").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let (status, _) = book(&app, &requester, &["c3"], "2030-03-12").await;
+        assert_eq!(status, StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_describing_one_member_describes_the_whole_group() {
+        let (state, app, requester, _, _) = campus().await;
+        let (_, group) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PATCH",
+                &format!("/reservation/{}", group[1]),
+                &requester,
+                json!({ "purpose": "Robotics finals", "event_name": "Robotics Cup" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await["linked_reservations"][0]["id"],
+            json!(group[0])
+        );
+        for id in &group {
+            let reservation = reservation::Entity::find_by_id(id)
+                .one(&state.db)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(reservation.purpose, "Robotics finals");
+            assert_eq!(reservation.event_name.as_deref(), Some("Robotics Cup"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_closing_one_room_cancels_the_whole_group() {
+        let (state, app, requester, admin, _) = campus().await;
+        let (_, group) = book(&app, &requester, &["c1", "c2"], "2030-03-11").await;
+        let (_, other) = book(&app, &requester, &["c3"], "2030-03-11").await;
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PUT",
+                "/classroom/c1/status",
+                &admin,
+                json!({
+                    "status": "Maintenance",
+                    "reason": "Flooded",
+                    "reservation_action": "cancel",
+                }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            json(response).await["affected_reservations"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            statuses(&state, &group).await,
+            vec![ReservationStatus::Cancelled, ReservationStatus::Cancelled]
+        );
+        assert_eq!(
+            statuses(&state, &other).await,
+            vec![ReservationStatus::Pending]
+        );
+    }
+}
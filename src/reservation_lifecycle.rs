@@ -0,0 +1,154 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use sea_orm::prelude::DateTimeWithTimeZone;
+
+use crate::{
+    entities::{reservation, sea_orm_active_enums::ReservationStatus},
+    error::AppError,
+};
+
+// ===============================
+//   Reservation lifecycle
+// ===============================
+//
+//   Pending ──► Approved ──► Completed
+//      │  │        │  └────► NoShow
+//      │  │        └───────► Cancelled
+//      │  ├──► Rejected
+//      │  ├──► Cancelled
+//      │  └──► Expired
+//      └─────► NeedsRebooking ──► Cancelled / Expired   (closures, status changes)
+//
+// Every other status is final.
+
+/// Who is asking for a status change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Actor {
+    /// The user who made the reservation
+    Owner,
+    Admin,
+    /// Background jobs and closure/status-change workflows
+    System,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TransitionError {
+    /// There is no edge between the two statuses
+    NotAllowed {
+        from: ReservationStatus,
+        to: ReservationStatus,
+    },
+    /// The edge exists but this actor may not take it
+    Forbidden { to: ReservationStatus, actor: Actor },
+    /// The edge is only open once the reservation has started/ended
+    TooEarly { to: ReservationStatus },
+    /// Owners can only cancel before the reservation starts
+    AlreadyStarted,
+}
+
+impl TransitionError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            TransitionError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            _ => StatusCode::CONFLICT,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            TransitionError::NotAllowed { from, to } => {
+                format!("Cannot change reservation from {:?} to {:?}", from, to)
+            }
+            TransitionError::Forbidden { to, actor } => {
+                format!("{:?} may not change reservation to {:?}", actor, to)
+            }
+            TransitionError::TooEarly { to } => {
+                format!("Reservation cannot be marked {:?} yet", to)
+            }
+            TransitionError::AlreadyStarted => {
+                "Reservation has already started and can no longer be cancelled".to_string()
+            }
+        }
+    }
+}
+
+impl From<TransitionError> for AppError {
+    fn from(error: TransitionError) -> Self {
+        AppError::from_status(error.status_code(), error.message())
+    }
+}
+
+/// Actors allowed to move a reservation from `from` to `to`; empty when the
+/// lifecycle has no such edge.
+pub fn allowed_actors(from: &ReservationStatus, to: &ReservationStatus) -> &'static [Actor] {
+    use ReservationStatus::*;
+    match (from, to) {
+        (Pending, Approved) | (Pending, Rejected) => &[Actor::Admin],
+        (Pending, Cancelled) | (Approved, Cancelled) | (NeedsRebooking, Cancelled) => {
+            &[Actor::Owner, Actor::Admin, Actor::System]
+        }
+        (Pending, Expired) | (NeedsRebooking, Expired) => &[Actor::System],
+        (Approved, Completed) | (Approved, NoShow) => &[Actor::Admin, Actor::System],
+        (Pending, NeedsRebooking) | (Approved, NeedsRebooking) => &[Actor::System],
+        _ => &[],
+    }
+}
+
+/// Checks that `actor` may move a reservation spanning `[start, end)` from
+/// `from` to `to` at `now`.
+pub fn check_transition(
+    from: &ReservationStatus,
+    to: &ReservationStatus,
+    actor: Actor,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+    now: DateTime<Utc>,
+) -> Result<(), TransitionError> {
+    let actors = allowed_actors(from, to);
+    if actors.is_empty() {
+        return Err(TransitionError::NotAllowed {
+            from: from.clone(),
+            to: to.clone(),
+        });
+    }
+    if !actors.contains(&actor) {
+        return Err(TransitionError::Forbidden {
+            to: to.clone(),
+            actor,
+        });
+    }
+
+    match to {
+        ReservationStatus::Expired | ReservationStatus::NoShow if now < start => {
+            Err(TransitionError::TooEarly { to: to.clone() })
+        }
+        ReservationStatus::Completed if now < end => {
+            Err(TransitionError::TooEarly { to: to.clone() })
+        }
+        ReservationStatus::Cancelled if actor == Actor::Owner && now >= start => {
+            Err(TransitionError::AlreadyStarted)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// [`check_transition`] for a stored reservation, evaluated now.
+pub fn check(
+    reservation: &reservation::Model,
+    to: &ReservationStatus,
+    actor: Actor,
+) -> Result<(), TransitionError> {
+    check_transition(
+        &reservation.status,
+        to,
+        actor,
+        reservation.start_time,
+        reservation.end_time,
+        Utc::now(),
+    )
+}
+
+/// Owners may only edit the details of a reservation that is still pending.
+pub fn is_editable(status: &ReservationStatus) -> bool {
+    *status == ReservationStatus::Pending
+}
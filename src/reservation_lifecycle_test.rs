@@ -0,0 +1,263 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::sea_orm_active_enums::ReservationStatus;
+    use super::super::reservation_lifecycle::{
+        Actor, TransitionError, allowed_actors, check_transition, is_editable,
+    };
+    use axum::http::StatusCode;
+    use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+    use sea_orm::Iterable;
+
+    const ACTORS: [Actor; 3] = [Actor::Owner, Actor::Admin, Actor::System];
+
+    fn start() -> DateTime<FixedOffset> {
+        FixedOffset::east_opt(8 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2025, 3, 10, 9, 0, 0)
+            .unwrap()
+    }
+
+    fn end() -> DateTime<FixedOffset> {
+        start() + Duration::hours(2)
+    }
+
+    fn before_start() -> DateTime<Utc> {
+        (start() - Duration::hours(1)).with_timezone(&Utc)
+    }
+
+    fn during() -> DateTime<Utc> {
+        (start() + Duration::hours(1)).with_timezone(&Utc)
+    }
+
+    fn after_end() -> DateTime<Utc> {
+        (end() + Duration::hours(1)).with_timezone(&Utc)
+    }
+
+    /// The full transition table: every edge with the actors that may take it.
+    fn expected(from: &ReservationStatus, to: &ReservationStatus) -> Vec<Actor> {
+        use ReservationStatus::*;
+        match (from, to) {
+            (Pending, Approved) | (Pending, Rejected) => vec![Actor::Admin],
+            (Pending, Cancelled) | (Approved, Cancelled) | (NeedsRebooking, Cancelled) => {
+                ACTORS.to_vec()
+            }
+            (Pending, Expired) | (NeedsRebooking, Expired) => vec![Actor::System],
+            (Approved, Completed) | (Approved, NoShow) => vec![Actor::Admin, Actor::System],
+            (Pending, NeedsRebooking) | (Approved, NeedsRebooking) => vec![Actor::System],
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn test_transition_table_is_exhaustive() {
+        for from in ReservationStatus::iter() {
+            for to in ReservationStatus::iter() {
+                assert_eq!(
+                    allowed_actors(&from, &to).to_vec(),
+                    expected(&from, &to),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_pair_and_actor_after_end() {
+        // After the reservation has ended every time guard is satisfied, so
+        // only the owner's cancellation window can still refuse an edge.
+        for from in ReservationStatus::iter() {
+            for to in ReservationStatus::iter() {
+                for actor in ACTORS {
+                    let result = check_transition(&from, &to, actor, start(), end(), after_end());
+                    let actors = expected(&from, &to);
+                    let expected_result = if actors.is_empty() {
+                        Err(TransitionError::NotAllowed {
+                            from: from.clone(),
+                            to: to.clone(),
+                        })
+                    } else if !actors.contains(&actor) {
+                        Err(TransitionError::Forbidden {
+                            to: to.clone(),
+                            actor,
+                        })
+                    } else if to == ReservationStatus::Cancelled && actor == Actor::Owner {
+                        Err(TransitionError::AlreadyStarted)
+                    } else {
+                        Ok(())
+                    };
+                    assert_eq!(
+                        result, expected_result,
+                        "{:?} -> {:?} by {:?}",
+                        from, to, actor
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_final_statuses_have_no_outgoing_edges() {
+        use ReservationStatus::*;
+        for from in [Rejected, Cancelled, Expired, Completed, NoShow] {
+            for to in ReservationStatus::iter() {
+                assert!(
+                    allowed_actors(&from, &to).is_empty(),
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_no_self_transitions() {
+        for status in ReservationStatus::iter() {
+            assert!(allowed_actors(&status, &status).is_empty(), "{:?}", status);
+        }
+    }
+
+    #[test]
+    fn test_expired_requires_start() {
+        let pending = ReservationStatus::Pending;
+        let expired = ReservationStatus::Expired;
+        assert_eq!(
+            check_transition(
+                &pending,
+                &expired,
+                Actor::System,
+                start(),
+                end(),
+                before_start()
+            ),
+            Err(TransitionError::TooEarly {
+                to: expired.clone()
+            })
+        );
+        assert!(
+            check_transition(&pending, &expired, Actor::System, start(), end(), during()).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_no_show_requires_start() {
+        let approved = ReservationStatus::Approved;
+        let no_show = ReservationStatus::NoShow;
+        assert_eq!(
+            check_transition(
+                &approved,
+                &no_show,
+                Actor::Admin,
+                start(),
+                end(),
+                before_start()
+            ),
+            Err(TransitionError::TooEarly {
+                to: no_show.clone()
+            })
+        );
+        assert!(
+            check_transition(&approved, &no_show, Actor::Admin, start(), end(), during()).is_ok()
+        );
+    }
+
+    #[test]
+    fn test_completed_requires_end() {
+        let approved = ReservationStatus::Approved;
+        let completed = ReservationStatus::Completed;
+        for now in [before_start(), during()] {
+            assert_eq!(
+                check_transition(&approved, &completed, Actor::System, start(), end(), now),
+                Err(TransitionError::TooEarly {
+                    to: completed.clone()
+                })
+            );
+        }
+        assert!(
+            check_transition(
+                &approved,
+                &completed,
+                Actor::System,
+                start(),
+                end(),
+                end().with_timezone(&Utc)
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_owner_cancels_only_before_start() {
+        let cancelled = ReservationStatus::Cancelled;
+        for from in [ReservationStatus::Pending, ReservationStatus::Approved] {
+            assert!(
+                check_transition(
+                    &from,
+                    &cancelled,
+                    Actor::Owner,
+                    start(),
+                    end(),
+                    before_start()
+                )
+                .is_ok()
+            );
+            assert_eq!(
+                check_transition(&from, &cancelled, Actor::Owner, start(), end(), during()),
+                Err(TransitionError::AlreadyStarted)
+            );
+            // Admins and closures can still cancel a running reservation
+            assert!(
+                check_transition(&from, &cancelled, Actor::Admin, start(), end(), during()).is_ok()
+            );
+            assert!(
+                check_transition(&from, &cancelled, Actor::System, start(), end(), during())
+                    .is_ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_review_decisions_before_start() {
+        let pending = ReservationStatus::Pending;
+        for to in [ReservationStatus::Approved, ReservationStatus::Rejected] {
+            assert!(
+                check_transition(&pending, &to, Actor::Admin, start(), end(), before_start())
+                    .is_ok()
+            );
+            assert!(matches!(
+                check_transition(&pending, &to, Actor::Owner, start(), end(), before_start()),
+                Err(TransitionError::Forbidden { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_error_status_codes() {
+        let forbidden = TransitionError::Forbidden {
+            to: ReservationStatus::Approved,
+            actor: Actor::Owner,
+        };
+        assert_eq!(forbidden.status_code(), StatusCode::FORBIDDEN);
+        for error in [
+            TransitionError::NotAllowed {
+                from: ReservationStatus::Rejected,
+                to: ReservationStatus::Approved,
+            },
+            TransitionError::TooEarly {
+                to: ReservationStatus::Completed,
+            },
+            TransitionError::AlreadyStarted,
+        ] {
+            assert_eq!(error.status_code(), StatusCode::CONFLICT);
+        }
+    }
+
+    #[test]
+    fn test_only_pending_is_editable() {
+        for status in ReservationStatus::iter() {
+            assert_eq!(is_editable(&status), status == ReservationStatus::Pending);
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::Utc;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    TransactionTrait, prelude::DateTimeWithTimeZone,
+};
+
+use crate::{
+    entities::{reservation, sea_orm_active_enums::ReservationStatus},
+    reservation_groups, versioning,
+};
+
+/// Reservations that can be handed to another user, e.g. when their holder
+/// graduates: approved or still pending, and not yet started.
+pub fn is_transferable(reservation: &reservation::Model, now: DateTimeWithTimeZone) -> bool {
+    matches!(
+        reservation.status,
+        ReservationStatus::Approved | ReservationStatus::Pending
+    ) && reservation.start_time > now
+}
+
+/// Picks the reservations to transfer out of a user's transferable ones:
+/// all of them, or those with the requested IDs. Fails with the first
+/// requested ID that is not among them.
+pub fn select(
+    transferable: Vec<reservation::Model>,
+    requested: Option<&[String]>,
+) -> Result<Vec<reservation::Model>, String> {
+    let Some(requested) = requested else {
+        return Ok(transferable);
+    };
+    if let Some(missing) = requested
+        .iter()
+        .find(|id| !transferable.iter().any(|r| &r.id == *id))
+    {
+        return Err(missing.clone());
+    }
+    Ok(transferable
+        .into_iter()
+        .filter(|r| requested.contains(&r.id))
+        .collect())
+}
+
+/// A user's transferable reservations, soonest first.
+pub async fn transferable(
+    db: &DatabaseConnection,
+    user_id: &str,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let now = Utc::now().fixed_offset();
+    Ok(reservation::Entity::find()
+        .filter(reservation::Column::UserId.eq(user_id))
+        .filter(reservation::Column::StartTime.gt(now))
+        .order_by_asc(reservation::Column::StartTime)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|r| is_transferable(r, now))
+        .collect())
+}
+
+/// Reassigns the reservations, and the rooms booked together with them, to
+/// `to_user_id` in one transaction.
+pub async fn transfer(
+    db: &DatabaseConnection,
+    reservations: Vec<reservation::Model>,
+    to_user_id: &str,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let reservations = reservation_groups::with_members(&txn, reservations).await?;
+    let mut transferred = Vec::new();
+    for reservation in reservations {
+        let mut active: reservation::ActiveModel = reservation.into();
+        active.user_id = Set(Some(to_user_id.to_string()));
+        transferred
+            .push(versioning::update(&txn, active, reservation::Column::Version, None).await?);
+    }
+    txn.commit().await?;
+    Ok(transferred)
+}
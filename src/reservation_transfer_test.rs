@@ -0,0 +1,72 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+    use super::super::reservation_transfer::{is_transferable, select};
+    use super::super::test_support::reservation_at;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reservation(id: &str, start: &str, status: ReservationStatus) -> reservation::Model {
+        reservation::Model {
+            user_id: Some("president".to_string()),
+            classroom_id: Some("r101".to_string()),
+            status,
+            end_time: dt(start) + chrono::Duration::hours(2),
+            ..reservation_at(id, start, start)
+        }
+    }
+
+    #[test]
+    fn test_only_upcoming_approved_or_pending_are_transferable() {
+        let now = dt("2025-06-01T12:00:00+08:00");
+        let upcoming = "2025-06-10T18:00:00+08:00";
+        assert!(is_transferable(
+            &reservation("a", upcoming, ReservationStatus::Approved),
+            now
+        ));
+        assert!(is_transferable(
+            &reservation("p", upcoming, ReservationStatus::Pending),
+            now
+        ));
+        assert!(!is_transferable(
+            &reservation("c", upcoming, ReservationStatus::Cancelled),
+            now
+        ));
+        assert!(!is_transferable(
+            &reservation(
+                "past",
+                "2025-05-20T18:00:00+08:00",
+                ReservationStatus::Approved
+            ),
+            now
+        ));
+    }
+
+    #[test]
+    fn test_select_all_or_requested() {
+        let transferable = vec![
+            reservation(
+                "a",
+                "2025-06-10T18:00:00+08:00",
+                ReservationStatus::Approved,
+            ),
+            reservation(
+                "b",
+                "2025-06-17T18:00:00+08:00",
+                ReservationStatus::Approved,
+            ),
+        ];
+        assert_eq!(select(transferable.clone(), None).unwrap().len(), 2);
+
+        let requested = ["b".to_string()];
+        let selected = select(transferable.clone(), Some(&requested)).unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, "b");
+
+        let requested = ["a".to_string(), "other".to_string()];
+        assert_eq!(select(transferable, Some(&requested)).unwrap_err(), "other");
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{Duration, Utc};
+use redis::AsyncCommands;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IdenStatic, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::entities::{
+    announcement, black_list, classroom_closure, infraction, key_sync_action, key_transaction_log,
+    reservation,
+};
+use crate::redis_breaker::RedisConnection;
+
+/// How long rows are kept before an archival job should move them out of
+/// the live tables. `None` keeps a table forever.
+#[derive(Clone)]
+pub struct RetentionConfig {
+    pub reservation: Option<Duration>,
+    pub key_transaction_log: Option<Duration>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            reservation: Some(Duration::days(730)),
+            key_transaction_log: Some(Duration::days(365)),
+        }
+    }
+}
+
+/// Outcome of the last archival run for a table, stored in Redis under
+/// [`archival_job_key`] by the job itself.
+#[derive(Serialize, Deserialize, ToSchema, Clone)]
+pub struct ArchivalRun {
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub finished_at: DateTimeWithTimeZone,
+    pub rows_archived: u64,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+pub fn archival_job_key(table: &str) -> String {
+    format!("archival_job_{}", table)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TableOverview {
+    pub table: &'static str,
+    pub row_count: u64,
+    /// Column the age of a row is measured by
+    pub age_column: &'static str,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub oldest: Option<DateTimeWithTimeZone>,
+    /// Configured retention in days, if the table has one
+    pub retention_days: Option<i64>,
+    /// Rows older than the retention period, i.e. due for archival
+    pub rows_past_retention: Option<u64>,
+    /// `None` when no archival job has reported for this table yet
+    pub last_archival_run: Option<ArchivalRun>,
+}
+
+async fn table_overview<E>(
+    db: &DatabaseConnection,
+    redis: &mut RedisConnection,
+    table: &'static str,
+    age_column: E::Column,
+    retention: Option<Duration>,
+) -> Result<TableOverview, DbErr>
+where
+    E: EntityTrait,
+    E::Model: Sync,
+{
+    let row_count = E::find().count(db).await?;
+    let oldest: Option<DateTimeWithTimeZone> = E::find()
+        .select_only()
+        .column(age_column)
+        .order_by_asc(age_column)
+        .into_tuple()
+        .one(db)
+        .await?;
+
+    let rows_past_retention = match retention {
+        Some(retention) => Some(
+            E::find()
+                .filter(age_column.lt(Utc::now() - retention))
+                .count(db)
+                .await?,
+        ),
+        None => None,
+    };
+
+    // A missing or unreadable status is reported as "never ran" rather than
+    // failing the whole overview
+    let last_archival_run = redis
+        .get::<_, Option<String>>(archival_job_key(table))
+        .await
+        .ok()
+        .flatten()
+        .and_then(|s| serde_json::from_str(&s).ok());
+
+    Ok(TableOverview {
+        table,
+        row_count,
+        age_column: age_column.as_str(),
+        oldest,
+        retention_days: retention.map(|r| r.num_days()),
+        rows_past_retention,
+        last_archival_run,
+    })
+}
+
+/// Row counts, oldest rows and archival status for every table that only
+/// ever grows.
+pub async fn storage_overview(
+    db: &DatabaseConnection,
+    redis: &mut RedisConnection,
+    config: &RetentionConfig,
+) -> Result<Vec<TableOverview>, DbErr> {
+    Ok(vec![
+        table_overview::<reservation::Entity>(
+            db,
+            redis,
+            "reservation",
+            reservation::Column::StartTime,
+            config.reservation,
+        )
+        .await?,
+        table_overview::<key_transaction_log::Entity>(
+            db,
+            redis,
+            "key_transaction_log",
+            key_transaction_log::Column::BorrowedAt,
+            config.key_transaction_log,
+        )
+        .await?,
+        table_overview::<key_sync_action::Entity>(
+            db,
+            redis,
+            "key_sync_action",
+            key_sync_action::Column::CreatedAt,
+            None,
+        )
+        .await?,
+        table_overview::<infraction::Entity>(
+            db,
+            redis,
+            "infraction",
+            infraction::Column::CreatedAt,
+            None,
+        )
+        .await?,
+        table_overview::<black_list::Entity>(
+            db,
+            redis,
+            "black_list",
+            black_list::Column::CreatedAt,
+            None,
+        )
+        .await?,
+        table_overview::<announcement::Entity>(
+            db,
+            redis,
+            "announcement",
+            announcement::Column::PublishedAt,
+            None,
+        )
+        .await?,
+        table_overview::<classroom_closure::Entity>(
+            db,
+            redis,
+            "classroom_closure",
+            classroom_closure::Column::CreatedAt,
+            None,
+        )
+        .await?,
+    ])
+}
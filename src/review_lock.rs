@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use redis::{AsyncCommands, RedisError, SetExpiry, SetOptions};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset, entities::user, error::AppError, redis_breaker::RedisConnection,
+};
+
+/// Claims reviewers take on a reservation while they look at it, so two
+/// admins do not review it at once.
+#[derive(Clone)]
+pub struct ReviewLockConfig {
+    /// How long a claim lasts without a heartbeat; reviewers who close the
+    /// page release it by letting it run out
+    pub ttl: Duration,
+}
+
+impl Default for ReviewLockConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::seconds(90),
+        }
+    }
+}
+
+/// A reviewer's claim on a reservation.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct ReviewLock {
+    pub reviewer_id: String,
+    pub reviewer_name: String,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub claimed_at: DateTimeWithTimeZone,
+    /// Claim again before this to keep the lock
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+/// Another reviewer holds the claim.
+#[derive(Debug, PartialEq)]
+pub struct HeldByOther(pub ReviewLock);
+
+impl From<HeldByOther> for AppError {
+    fn from(HeldByOther(lock): HeldByOther) -> Self {
+        AppError::Conflict(format!(
+            "This reservation is being reviewed by {}",
+            lock.reviewer_name
+        ))
+        .with_details(lock)
+    }
+}
+
+fn lock_key(reservation_id: &str) -> String {
+    format!("review_lock_{}", reservation_id)
+}
+
+/// Fails when `existing` belongs to someone other than `reviewer_id`.
+pub fn check(existing: Option<&ReviewLock>, reviewer_id: &str) -> Result<(), HeldByOther> {
+    match existing {
+        Some(lock) if lock.reviewer_id != reviewer_id => Err(HeldByOther(lock.clone())),
+        _ => Ok(()),
+    }
+}
+
+/// The claim a reviewer gets or extends at `now`. A heartbeat keeps the
+/// time of the first claim.
+pub fn next_lock(
+    existing: Option<&ReviewLock>,
+    reviewer: &user::Model,
+    now: DateTimeWithTimeZone,
+    ttl: Duration,
+) -> ReviewLock {
+    ReviewLock {
+        reviewer_id: reviewer.id.clone(),
+        reviewer_name: reviewer.name.clone(),
+        claimed_at: existing.map_or(now, |lock| lock.claimed_at),
+        expires_at: now + ttl,
+    }
+}
+
+fn parse(raw: Option<String>) -> Option<ReviewLock> {
+    raw.and_then(|raw| serde_json::from_str(&raw).ok())
+}
+
+pub async fn current(
+    redis: &mut RedisConnection,
+    reservation_id: &str,
+) -> Result<Option<ReviewLock>, RedisError> {
+    Ok(parse(redis.get(lock_key(reservation_id)).await?))
+}
+
+#[derive(Debug)]
+pub enum ClaimError {
+    Held(HeldByOther),
+    Redis(RedisError),
+}
+
+impl From<RedisError> for ClaimError {
+    fn from(e: RedisError) -> Self {
+        Self::Redis(e)
+    }
+}
+
+/// Claims a reservation for `reviewer`, or extends their claim when called
+/// again as a heartbeat.
+pub async fn claim(
+    redis: &mut RedisConnection,
+    ttl: Duration,
+    reservation_id: &str,
+    reviewer: &user::Model,
+) -> Result<ReviewLock, ClaimError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let existing = current(redis, reservation_id).await?;
+    check(existing.as_ref(), &reviewer.id).map_err(ClaimError::Held)?;
+
+    let lock = next_lock(existing.as_ref(), reviewer, now, ttl);
+    let mut options =
+        SetOptions::default().with_expiration(SetExpiry::EX(ttl.num_seconds() as u64));
+    if existing.is_none() {
+        // Two reviewers opening the reservation at once: only one gets it
+        options = options.conditional_set(redis::ExistenceCheck::NX);
+    }
+    let set: Option<String> = redis
+        .set_options(
+            lock_key(reservation_id),
+            serde_json::to_string(&lock).unwrap(),
+            options,
+        )
+        .await?;
+    if set.is_none() {
+        let winner = current(redis, reservation_id).await?;
+        check(winner.as_ref(), &reviewer.id).map_err(ClaimError::Held)?;
+    }
+    Ok(lock)
+}
+
+/// Drops the claim of `reviewer_id`, leaving claims of others alone.
+pub async fn release(
+    redis: &mut RedisConnection,
+    reservation_id: &str,
+    reviewer_id: &str,
+) -> Result<(), RedisError> {
+    let existing = current(redis, reservation_id).await?;
+    if existing.is_some_and(|lock| lock.reviewer_id == reviewer_id) {
+        let _: () = redis.del(lock_key(reservation_id)).await?;
+    }
+    Ok(())
+}
+
+/// Current claims on the given reservations, by reservation id.
+pub async fn holders(
+    redis: &mut RedisConnection,
+    reservation_ids: &[String],
+) -> Result<HashMap<String, ReviewLock>, RedisError> {
+    if reservation_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let keys: Vec<String> = reservation_ids.iter().map(|id| lock_key(id)).collect();
+    let raw: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(redis).await?;
+    Ok(reservation_ids
+        .iter()
+        .cloned()
+        .zip(raw)
+        .filter_map(|(id, raw)| Some((id, parse(raw)?)))
+        .collect())
+}
@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::super::{
+        entities::{sea_orm_active_enums::Role, user},
+        error::AppError,
+        review_lock::{HeldByOther, ReviewLock, check, next_lock},
+    };
+
+    fn reviewer(id: &str, name: &str) -> user::Model {
+        let now = Utc::now().fixed_offset();
+        user::Model {
+            id: id.to_string(),
+            username: id.to_string(),
+            name: name.to_string(),
+            email: format!("{}@example.com", id),
+            password: String::new(),
+            phone_number: String::new(),
+            role: Role::Admin,
+            created_at: now,
+            updated_at: now,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: None,
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn heartbeat_extends_the_claim_and_keeps_when_it_started() {
+        let alice = reviewer("a1", "Alice");
+        let start = Utc::now().fixed_offset();
+        let first = next_lock(None, &alice, start, Duration::seconds(90));
+        assert_eq!(first.expires_at, start + Duration::seconds(90));
+
+        let later = start + Duration::seconds(60);
+        assert_eq!(check(Some(&first), &alice.id), Ok(()));
+        let renewed = next_lock(Some(&first), &alice, later, Duration::seconds(90));
+        assert_eq!(renewed.claimed_at, start);
+        assert_eq!(renewed.expires_at, later + Duration::seconds(90));
+    }
+
+    #[test]
+    fn others_cannot_take_a_held_claim() {
+        let alice = reviewer("a1", "Alice");
+        let lock: ReviewLock = next_lock(
+            None,
+            &alice,
+            Utc::now().fixed_offset(),
+            Duration::seconds(90),
+        );
+        assert_eq!(check(None, "b2"), Ok(()));
+        assert_eq!(check(Some(&lock), "b2"), Err(HeldByOther(lock.clone())));
+
+        let error = AppError::from(HeldByOther(lock));
+        assert_eq!(error.code(), "conflict");
+        assert_eq!(
+            error.message(),
+            "This reservation is being reviewed by Alice"
+        );
+        assert_eq!(error.body().details.unwrap()["reviewer_id"], "a1");
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::Duration;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    prelude::DateTimeWithTimeZone,
+};
+
+use crate::entities::{domain_event, reservation, sea_orm_active_enums::ReservationStatus};
+
+/// How long a requester has to wait between reminders for one reservation.
+pub fn cooldown() -> Duration {
+    Duration::hours(24)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum NudgeRejection {
+    NotRequester,
+    NotPending,
+    /// A reminder was sent recently; the next one is allowed from this time
+    TooSoon(DateTimeWithTimeZone),
+}
+
+/// When the requester may send the next reminder after one at `last_nudge`.
+pub fn next_nudge_at(last_nudge: Option<DateTimeWithTimeZone>) -> Option<DateTimeWithTimeZone> {
+    last_nudge.map(|at| at + cooldown())
+}
+
+/// Whether `user_id` may remind reviewers of `reservation` at `now`. Only
+/// the requester can, only while it waits for review, and at most once per
+/// [`cooldown`].
+pub fn check(
+    reservation: &reservation::Model,
+    user_id: &str,
+    last_nudge: Option<DateTimeWithTimeZone>,
+    now: DateTimeWithTimeZone,
+) -> Result<(), NudgeRejection> {
+    if reservation.user_id.as_deref() != Some(user_id) {
+        return Err(NudgeRejection::NotRequester);
+    }
+    if reservation.status != ReservationStatus::Pending {
+        return Err(NudgeRejection::NotPending);
+    }
+    match next_nudge_at(last_nudge) {
+        Some(next) if next > now => Err(NudgeRejection::TooSoon(next)),
+        _ => Ok(()),
+    }
+}
+
+/// When reviewers were last reminded of a reservation. Reminders are
+/// recorded as `reservation_nudged` domain events.
+pub async fn last_nudge(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+) -> Result<Option<DateTimeWithTimeZone>, DbErr> {
+    Ok(domain_event::Entity::find()
+        .filter(domain_event::Column::Kind.eq("reservation_nudged"))
+        .filter(domain_event::Column::SubjectId.eq(reservation_id))
+        .order_by_desc(domain_event::Column::CreatedAt)
+        .limit(1)
+        .one(db)
+        .await?
+        .map(|event| event.created_at))
+}
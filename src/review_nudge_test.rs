@@ -0,0 +1,74 @@
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    use super::super::test_support::reservation_at;
+    use super::super::{
+        entities::{reservation, sea_orm_active_enums::ReservationStatus},
+        review_nudge::{NudgeRejection, check, next_nudge_at},
+    };
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reservation(status: ReservationStatus) -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some("room-1".to_string()),
+            status,
+            ..reservation_at(
+                "r1",
+                "2025-03-10T10:00:00+08:00",
+                "2025-03-10T12:00:00+08:00",
+            )
+        }
+    }
+
+    #[test]
+    fn first_nudge_is_allowed() {
+        let now = dt("2025-03-03T09:00:00+08:00");
+        assert_eq!(
+            check(&reservation(ReservationStatus::Pending), "u1", None, now),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn only_the_requester_can_nudge() {
+        let now = dt("2025-03-03T09:00:00+08:00");
+        assert_eq!(
+            check(&reservation(ReservationStatus::Pending), "u2", None, now),
+            Err(NudgeRejection::NotRequester)
+        );
+    }
+
+    #[test]
+    fn reviewed_reservations_cannot_be_nudged() {
+        let now = dt("2025-03-03T09:00:00+08:00");
+        for status in [
+            ReservationStatus::Approved,
+            ReservationStatus::Rejected,
+            ReservationStatus::Cancelled,
+        ] {
+            assert_eq!(
+                check(&reservation(status), "u1", None, now),
+                Err(NudgeRejection::NotPending)
+            );
+        }
+    }
+
+    #[test]
+    fn second_nudge_waits_a_day() {
+        let last = dt("2025-03-03T09:00:00+08:00");
+        let next = next_nudge_at(Some(last)).unwrap();
+        assert_eq!(next, last + Duration::hours(24));
+
+        let pending = reservation(ReservationStatus::Pending);
+        assert_eq!(
+            check(&pending, "u1", Some(last), next - Duration::minutes(1)),
+            Err(NudgeRejection::TooSoon(next))
+        );
+        assert_eq!(check(&pending, "u1", Some(last), next), Ok(()));
+    }
+}
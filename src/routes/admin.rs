@@ -0,0 +1,345 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::permission_required;
+use chrono::Utc;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    entities::{classroom, user},
+    error::{AppError, ErrorResponse},
+    integrity::{self, IntegrityReport, RepairStrategy, report},
+    login_system::AuthBackend,
+    permissions::Permission,
+    policy_simulation::{RuleResult, SimulatedOutcome, SimulatedRequest, outcome, simulate},
+    retention::{TableOverview, storage_overview},
+    routes::{
+        assistant::assistant_router, debug::debug_router, domain_event::domain_event_router,
+        login_lockout::login_lockout_router, reservation_transfer::reservation_transfer_router,
+        user::UserResponse,
+    },
+    system_info::{SystemInfo, system_info},
+    utils::parse_dt,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct StorageOverviewResponse {
+    pub tables: Vec<TableOverview>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Row counts, oldest records and archival job status for the tables that grow over time, so operators can tell when retention jobs should run and whether they are keeping up.",
+    path = "/storage-overview",
+    responses(
+        (status = 200, description = "Storage overview", body = StorageOverviewResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to load storage overview", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_storage_overview(State(state): State<AppState>) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    match storage_overview(&state.db, &mut redis, &state.config.retention).await {
+        Ok(tables) => {
+            Ok((StatusCode::OK, Json(StorageOverviewResponse { tables })).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to load storage overview".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Which build is running and how: version and git commit, the switches it runs with, the versions of the database and Redis it is connected to, and how long it has been up. Include this when reporting a bug.",
+    path = "/system-info",
+    responses(
+        (status = 200, description = "System information", body = SystemInfo),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_system_info(State(state): State<AppState>) -> Json<SystemInfo> {
+    Json(system_info(&state.db, &state.redis, &state.config).await)
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Users whose email address bounced or reported our mail as spam. No emails are sent to them until they change their address, so staff should follow up in person.",
+    path = "/undeliverable-emails",
+    responses(
+        (status = 200, description = "Users with undeliverable addresses, most recent first", body = Vec<UserResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch users", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_undeliverable_emails(
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    match user::Entity::find()
+        .filter(user::Column::EmailUndeliverableAt.is_not_null())
+        .order_by_desc(user::Column::EmailUndeliverableAt)
+        .all(&state.db)
+        .await
+    {
+        Ok(users) => Ok((
+            StatusCode::OK,
+            Json(
+                users
+                    .into_iter()
+                    .map(UserResponse::from)
+                    .collect::<Vec<_>>(),
+            ),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch users".to_string())),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct SimulatePolicyBody {
+    /// The user the reservation would be made by
+    pub user_id: String,
+    pub classroom_id: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub event_name: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SimulatePolicyResponse {
+    pub outcome: SimulatedOutcome,
+    /// Every rule in the order it is applied, including those that passed
+    pub rules: Vec<RuleResult>,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Admin"],
+    description = "Runs a hypothetical reservation request through the rules applied when reservations are created (blacklist, slot alignment, classroom availability including closures, quota and duplicate events) with the current settings, and reports which would fire and the overall outcome. Unlike a real request, evaluation does not stop at the first rejection. Nothing is created.",
+    path = "/policy/simulate",
+    request_body(content = SimulatePolicyBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Simulation result", body = SimulatePolicyResponse),
+        (status = 400, description = "Invalid times", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User or classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to run the simulation", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn simulate_policy(
+    State(state): State<AppState>,
+    Json(body): Json<SimulatePolicyBody>,
+) -> Result<Response, AppError> {
+    let start = match parse_dt(&body.start_time) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid start_time".to_string())),
+    };
+    let end = match parse_dt(&body.end_time) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid end_time".to_string())),
+    };
+    if start >= end {
+        return Err(AppError::BadRequest(
+            "'start_time' must be < 'end_time'".to_string(),
+        ));
+    }
+
+    match user::Entity::find_by_id(&body.user_id).one(&state.db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    }
+    match classroom::Entity::find_by_id(&body.classroom_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(c)) if c.deleted_at.is_none() => {}
+        Ok(_) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    }
+
+    let request = SimulatedRequest {
+        user_id: &body.user_id,
+        classroom_id: &body.classroom_id,
+        start,
+        end,
+        event_name: body.event_name.as_deref(),
+    };
+    match simulate(&state.db, &state.config, &request).await {
+        Ok(rules) => Ok((
+            StatusCode::OK,
+            Json(SimulatePolicyResponse {
+                outcome: outcome(&rules),
+                rules,
+            }),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to run the simulation".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Scans for references to rows that no longer exist, left behind by past hard deletes: reservations whose classroom or user is gone, and key logs whose key, reservation, borrower or handler is gone. Nothing is changed.",
+    path = "/integrity",
+    responses(
+        (status = 200, description = "Orphaned references", body = IntegrityReport),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to scan references", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_integrity_report(State(state): State<AppState>) -> Result<Response, AppError> {
+    match integrity::scan(&state.db).await {
+        Ok(orphans) => Ok((StatusCode::OK, Json(report(orphans))).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to scan references".to_string())),
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct RepairIntegrityBody {
+    pub strategy: RepairStrategy,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct RepairIntegrityResponse {
+    pub strategy: RepairStrategy,
+    /// The references that were fixed
+    pub repaired: IntegrityReport,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Admin"],
+    description = "Fixes every orphaned reference reported by `GET /integrity` in one transaction. `nullify` clears the references; `placeholder` points them at a soft-deleted placeholder classroom, user or key with the ID `orphan-placeholder`, created when needed, except key log reservations, which are cleared.",
+    path = "/integrity/repair",
+    request_body(content = RepairIntegrityBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "References repaired", body = RepairIntegrityResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to repair references; nothing was changed", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn repair_integrity(
+    State(state): State<AppState>,
+    Json(body): Json<RepairIntegrityBody>,
+) -> Result<Response, AppError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    match integrity::repair(&state.db, body.strategy, now).await {
+        Ok(orphans) => Ok((
+            StatusCode::OK,
+            Json(RepairIntegrityResponse {
+                strategy: body.strategy,
+                repaired: report(orphans),
+            }),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to repair references".to_string(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Admin", description = "Operational endpoints for administrators")
+    ),
+    paths(
+        get_storage_overview,
+        get_system_info,
+        list_undeliverable_emails,
+        simulate_policy,
+        get_integrity_report,
+        repair_integrity,
+        crate::routes::debug::hash_password,
+        crate::routes::debug::generate_nanoid,
+        crate::routes::assistant::list_assistant_classrooms,
+        crate::routes::assistant::grant_assistant_classroom,
+        crate::routes::assistant::revoke_assistant_classroom,
+        crate::routes::domain_event::list_domain_events,
+        crate::routes::domain_event::replay_domain_event,
+        crate::routes::login_lockout::list_lockouts,
+        crate::routes::login_lockout::get_lockout,
+        crate::routes::login_lockout::delete_lockout,
+        crate::routes::reservation_transfer::list_transferable_reservations,
+        crate::routes::reservation_transfer::transfer_reservations,
+    ),
+    components(schemas(
+        StorageOverviewResponse,
+        crate::system_info::SystemInfo,
+        crate::system_info::BuildInfo,
+        crate::system_info::Flags,
+        crate::system_info::DependencyVersions,
+        SimulatePolicyBody,
+        SimulatePolicyResponse,
+        crate::policy_simulation::RuleResult,
+        crate::policy_simulation::PolicyRule,
+        crate::policy_simulation::RuleEffect,
+        crate::policy_simulation::SimulatedOutcome,
+        RepairIntegrityBody,
+        RepairIntegrityResponse,
+        crate::routes::debug::HashPasswordBody,
+        crate::integrity::IntegrityReport,
+        crate::integrity::ReferenceCount,
+        crate::integrity::Orphan,
+        crate::integrity::Reference,
+        crate::integrity::RepairStrategy,
+        crate::routes::assistant::AssistantClassroom,
+        crate::retention::TableOverview,
+        crate::retention::ArchivalRun,
+        crate::routes::domain_event::DomainEventQuery,
+        crate::pagination::PagedResponse<crate::entities::domain_event::Model>,
+        crate::entities::domain_event::Model,
+        crate::entities::sea_orm_active_enums::DomainEventStatus,
+        crate::login_guard::LockoutState,
+        crate::routes::reservation_transfer::TransferReservationsBody,
+        crate::routes::reservation_transfer::TransferReservationsResponse,
+    ))
+)]
+pub struct AdminApi;
+
+pub fn admin_router() -> Router<AppState> {
+    Router::new()
+        .route("/storage-overview", get(get_storage_overview))
+        .route("/system-info", get(get_system_info))
+        .route("/undeliverable-emails", get(list_undeliverable_emails))
+        .route("/policy/simulate", post(simulate_policy))
+        .route("/integrity", get(get_integrity_report))
+        .route("/integrity/repair", post(repair_integrity))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageSystem))
+        .merge(assistant_router())
+        .merge(debug_router())
+        .merge(domain_event_router())
+        .merge(login_lockout_router())
+        .merge(reservation_transfer_router())
+}
@@ -1,116 +1,599 @@
+use std::collections::BTreeMap;
+
 use crate::{
     AppState,
-    entities::{announcement, sea_orm_active_enums::Role},
+    announcement_audience::{self, Reader, RecipientPreview, Target},
+    availability::campus_offset,
+    domain_events::{self, DomainEvent},
+    email_queue::{Priority, queue_email_to_user},
+    email_templates::{self, EmailTemplateConfig, RenderedEmail},
+    entities::{
+        announcement, announcement_edit, classroom, reservation,
+        sea_orm_active_enums::{AnnouncementAudience, AnnouncementCategory, ReservationStatus},
+        user,
+    },
+    error::{AppError, ErrorResponse},
+    live_events::LiveEvent,
+    localization::{AcceptLanguage, Localize},
     login_system::{AuthBackend, AuthSession},
+    pagination::{PageParams, PagedResponse, Pagination, fetch},
+    permissions::Permission,
+    utils::{contains_pattern, json_with_etag},
 };
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{delete, get, post},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
 };
 use axum_login::permission_required;
+use chrono::{Duration, Utc};
 use nanoid::nanoid;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    EntityTrait, ModelTrait,
+    ColumnTrait, Condition, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
+    TransactionTrait,
+    sea_query::{Expr, extension::postgres::PgExpr},
 };
-use serde::Deserialize;
-use utoipa::ToSchema;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreateAnnouncementBody {
     pub title: String,
     pub content: String,
+    pub title_en: Option<String>,
+    pub title_zh_tw: Option<String>,
+    pub content_en: Option<String>,
+    pub content_zh_tw: Option<String>,
+    /// Pinned announcements are listed first
+    #[serde(default)]
+    pub pinned: bool,
+    /// Emails every user immediately and pins the announcement
+    #[serde(default)]
+    pub emergency: bool,
+    /// Defaults to `system`
+    pub category: Option<AnnouncementCategory>,
+    /// Who the announcement is for; defaults to `All`
+    pub audience: Option<AnnouncementAudience>,
+    /// Classroom whose users a `ClassroomUsers` announcement is for
+    pub audience_classroom_id: Option<String>,
+    /// Email the audience; defaults to true. Emergency announcements are
+    /// always emailed.
+    pub send_email: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateAnnouncementBody {
+    pub title: String,
+    pub content: String,
+    /// Translations; left out ones are cleared
+    pub title_en: Option<String>,
+    pub title_zh_tw: Option<String>,
+    pub content_en: Option<String>,
+    pub content_zh_tw: Option<String>,
+    /// Left as is when omitted
+    pub pinned: Option<bool>,
+    /// Left as is when omitted
+    pub category: Option<AnnouncementCategory>,
+    /// Left as is when omitted
+    pub audience: Option<AnnouncementAudience>,
+    /// Required when `audience` is changed to `ClassroomUsers`
+    pub audience_classroom_id: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CreateAnnouncementQuery {
+    /// Render the email instead of creating and sending the announcement
+    #[serde(default)]
+    pub dry_run: bool,
+    /// User the dry run renders the email for; defaults to the caller
+    pub sample_user_id: Option<String>,
+}
+
+/// The email one recipient would get.
+#[derive(Serialize, ToSchema)]
+pub struct SampleEmail {
+    pub user_id: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AnnouncementDryRun {
+    pub recipients: RecipientPreview,
+    pub sample: SampleEmail,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AnnouncementAudienceBody {
+    /// Defaults to `system`
+    pub category: Option<AnnouncementCategory>,
+    #[serde(default)]
+    pub emergency: bool,
+    /// Defaults to `All`
+    pub audience: Option<AnnouncementAudience>,
+    pub audience_classroom_id: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListAnnouncementsQuery {
+    /// Only announcements of this category
+    pub category: Option<AnnouncementCategory>,
+    /// Words to look for in the title or content
+    pub q: Option<String>,
 }
 
 #[utoipa::path(
     post,
     tags = ["Announcement"],
-    description = "Create a new announcement",
+    description = "Create a new announcement and email it, in batches, to users in its audience who have not muted its category. Targeted announcements are only listed to their audience. `send_email: false` publishes without emailing. Emergency announcements are pinned and emailed to every user regardless of preferences, together with their reservations for the day, and published as an `emergency_announced` webhook event; only emergency emails are sent during quiet hours. With `dry_run`, nothing is created or sent: the response has the resolved recipients and the email as the sample user would get it.",
     path = "",
+    params(CreateAnnouncementQuery),
     request_body(content = CreateAnnouncementBody, content_type = "application/json"),
     responses(
         (status = 201, description = "Announcement created successfully", body = announcement::Model),
+        (status = 200, description = "Dry run: nothing was created or sent", body = AnnouncementDryRun),
+        (status = 400, description = "ClassroomUsers audience without a classroom", body = ErrorResponse),
+        (status = 404, description = "Sample user or audience classroom not found", body = ErrorResponse),
     )
 )]
 pub async fn create_announcement(
     session: AuthSession,
     State(state): State<AppState>,
+    Query(query): Query<CreateAnnouncementQuery>,
     Json(body): Json<CreateAnnouncementBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user = match session.user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+    let target = match checked_target(
+        &state.db,
+        body.audience.clone(),
+        body.audience_classroom_id.clone(),
+    )
+    .await
+    {
+        Ok(target) => target,
+        Err(e) => return Err(e),
     };
+    if query.dry_run {
+        return Ok(dry_run(&state, user, query.sample_user_id, body, target)
+            .await
+            .into_response());
+    }
+    let send_email = body.send_email.unwrap_or(true) || body.emergency;
     let new_announcement = announcement::ActiveModel {
         id: Set(nanoid!()),
         title: Set(body.title),
         content: Set(body.content),
         published_at: NotSet,
         created_by: Set(Some(user.id)),
+        emergency: Set(body.emergency),
+        pinned: Set(body.pinned || body.emergency),
+        category: Set(body.category.unwrap_or(AnnouncementCategory::System)),
+        title_en: Set(body.title_en),
+        title_zh_tw: Set(body.title_zh_tw),
+        content_en: Set(body.content_en),
+        content_zh_tw: Set(body.content_zh_tw),
+        audience: Set(target.audience),
+        audience_classroom_id: Set(target.classroom_id),
     };
 
     match new_announcement.insert(&state.db).await {
-        Ok(announcement) => (StatusCode::CREATED, Json(announcement)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to create announcement",
-        )
-            .into_response(),
+        Ok(announcement) if announcement.emergency => {
+            // Goes out like other notifications: stored, replayable and
+            // posted to webhooks as well as emailed and streamed
+            let event = DomainEvent::EmergencyAnnounced {
+                announcement: announcement.clone(),
+            };
+            let state = state.clone();
+            tokio::spawn(async move { domain_events::publish(&state, event).await });
+            Ok((StatusCode::CREATED, Json(announcement)).into_response())
+        }
+        Ok(announcement) => {
+            state.live.send(LiveEvent::AnnouncementPublished {
+                announcement: announcement.clone(),
+            });
+            if send_email {
+                let (state, announcement) = (state.clone(), announcement.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = broadcast(&state, &announcement).await {
+                        warn!(
+                            "Failed to broadcast announcement {}: {}",
+                            announcement.id, e
+                        );
+                    }
+                });
+            }
+            Ok((StatusCode::CREATED, Json(announcement)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to create announcement".to_string(),
+        )),
+    }
+}
+
+/// The target from request fields, checking that its classroom exists.
+async fn checked_target(
+    db: &DatabaseConnection,
+    audience: Option<AnnouncementAudience>,
+    classroom_id: Option<String>,
+) -> Result<Target, AppError> {
+    let target = Target::new(audience, classroom_id)
+        .map_err(|message| AppError::BadRequest(message.to_string()))?;
+    if let Some(classroom_id) = &target.classroom_id {
+        match classroom::Entity::find_by_id(classroom_id).one(db).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+            Err(_) => return Err(AppError::Internal("Failed to fetch classroom".to_string())),
+        }
+    }
+    Ok(target)
+}
+
+async fn dry_run(
+    state: &AppState,
+    caller: user::Model,
+    sample_user_id: Option<String>,
+    body: CreateAnnouncementBody,
+    target: Target,
+) -> Result<Response, AppError> {
+    let sample_user = match sample_user_id {
+        Some(id) => match user::Entity::find_by_id(&id).one(&state.db).await {
+            Ok(Some(sample_user)) => sample_user,
+            Ok(None) => return Err(AppError::NotFound("Sample user not found".to_string())),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch user".to_string()));
+            }
+        },
+        None => caller.clone(),
+    };
+    // Rendered from an unsaved announcement, exactly as it would be sent
+    let announcement = announcement::Model {
+        id: String::new(),
+        title: body.title,
+        content: body.content,
+        published_at: Utc::now().with_timezone(&campus_offset()),
+        created_by: Some(caller.id),
+        emergency: body.emergency,
+        pinned: body.pinned || body.emergency,
+        category: body.category.unwrap_or(AnnouncementCategory::System),
+        title_en: body.title_en,
+        title_zh_tw: body.title_zh_tw,
+        content_en: body.content_en,
+        content_zh_tw: body.content_zh_tw,
+        audience: target.audience.clone(),
+        audience_classroom_id: target.classroom_id.clone(),
+    };
+    let audience = match announcement_audience::load(
+        &state.db,
+        &announcement.category,
+        announcement.emergency,
+        &target,
+    )
+    .await
+    {
+        Ok(audience) => audience,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to resolve recipients".to_string(),
+            ));
+        }
+    };
+    let todays_reservations = if announcement.emergency {
+        todays_reservations(&state.db).await
+    } else {
+        BTreeMap::new()
+    };
+    let email = render(
+        &state.config.email_templates,
+        &announcement,
+        &sample_user,
+        &todays_reservations,
+    );
+    Ok((
+        StatusCode::OK,
+        Json(AnnouncementDryRun {
+            recipients: audience.preview(),
+            sample: SampleEmail {
+                user_id: sample_user.id,
+                to: sample_user.email,
+                subject: email.subject,
+                body: email.body,
+            },
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Announcement"],
+    description = "How many users in the audience a broadcast would email, per role, and how many are left out because they muted the category or their address bounced. Nothing is sent; check this before broadcasting to the whole campus.",
+    path = "/preview",
+    request_body(content = AnnouncementAudienceBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Resolved recipients", body = RecipientPreview),
+        (status = 400, description = "ClassroomUsers audience without a classroom", body = ErrorResponse),
+        (status = 404, description = "Audience classroom not found", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to resolve recipients", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn preview_recipients(
+    State(state): State<AppState>,
+    Json(body): Json<AnnouncementAudienceBody>,
+) -> Result<Response, AppError> {
+    let target = match checked_target(&state.db, body.audience, body.audience_classroom_id).await {
+        Ok(target) => target,
+        Err(e) => return Err(e),
+    };
+    let category = body.category.unwrap_or(AnnouncementCategory::System);
+    match announcement_audience::load(&state.db, &category, body.emergency, &target).await {
+        Ok(audience) => Ok((StatusCode::OK, Json(audience.preview())).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to resolve recipients".to_string(),
+        )),
     }
 }
 
 #[utoipa::path(
     get,
     tags = ["Announcement"],
-    description = "Get all announcements",
+    description = "Get announcements, pinned first and then newest first. Targeted announcements are only listed to their audience; those who manage announcements see all of them. `q` matches whole words of the title or content, or any part of them for text without spaces such as Chinese. `title` and `content` are translated to the language asked for with `Accept-Language` (en or zh-TW) where a translation exists; `q` searches the untranslated text.",
     path = "",
+    params(
+        ListAnnouncementsQuery,
+        PageParams,
+        ("Accept-Language" = Option<String>, Header, description = "Preferred language, e.g. `zh-TW` or `en;q=0.8`"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a page fetched before; answered with 304 while it is unchanged")
+    ),
     responses(
-        (status = 200, description = "Announcements fetched successfully", body = Vec<announcement::Model>),
+        (status = 200, description = "Announcements fetched successfully", body = PagedResponse<announcement::Model>),
+        (status = 304, description = "The page is unchanged since the `ETag` sent in `If-None-Match`"),
+        (status = 500, description = "Failed to fetch announcements", body = ErrorResponse)
     )
 )]
-pub async fn list_announcements(State(state): State<AppState>) -> impl IntoResponse {
-    let announcements = match announcement::Entity::find().all(&state.db).await {
-        Ok(announcements) => announcements,
+pub async fn list_announcements(
+    session: AuthSession,
+    State(state): State<AppState>,
+    AcceptLanguage(locale): AcceptLanguage,
+    headers: HeaderMap,
+    pagination: Pagination,
+    Query(query): Query<ListAnnouncementsQuery>,
+) -> Result<Response, AppError> {
+    let reader = match Reader::load(&state.db, session.user.as_ref()).await {
+        Ok(reader) => reader,
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch announcements",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch announcements".to_string(),
+            ));
         }
     };
-    (StatusCode::OK, Json(announcements)).into_response()
+    let mut find_query = announcement::Entity::find().filter(reader.visible_condition());
+    if let Some(category) = query.category {
+        find_query = find_query.filter(announcement::Column::Category.eq(category));
+    }
+    if let Some(q) = query.q.as_deref().map(str::trim).filter(|q| !q.is_empty()) {
+        find_query = find_query.filter(search_condition(q));
+    }
+    let find_query = find_query
+        .order_by_desc(announcement::Column::Pinned)
+        .order_by_desc(announcement::Column::PublishedAt)
+        .order_by_asc(announcement::Column::Id);
+
+    match fetch(&state.db, find_query, pagination).await {
+        Ok(page) => Ok(json_with_etag(
+            &headers,
+            &page.map(|announcement| announcement.localized(locale)),
+        )),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch announcements".to_string(),
+        )),
+    }
+}
+
+/// Full-text match over title and content. Postgres does not split Chinese
+/// into words, so a plain substring match is accepted as well.
+fn search_condition(q: &str) -> Condition {
+    let pattern = contains_pattern(q);
+    Condition::any()
+        .add(Expr::cust_with_values(
+            "to_tsvector('simple', \"title\" || ' ' || \"content\") @@ websearch_to_tsquery('simple', $1)",
+            [q],
+        ))
+        .add(Expr::col(announcement::Column::Title).ilike(&pattern))
+        .add(Expr::col(announcement::Column::Content).ilike(&pattern))
 }
 
 #[utoipa::path(
     get,
     tags = ["Announcement"],
-    description = "Get announcement by ID",
+    description = "Get announcement by ID, translated to the language asked for with `Accept-Language` where a translation exists. Targeted announcements are not found for readers outside their audience.",
     path = "/{id}",
+    params(
+        ("Accept-Language" = Option<String>, Header, description = "Preferred language, e.g. `zh-TW` or `en;q=0.8`")
+    ),
     responses(
         (status = 200, description = "Announcement fetched successfully", body = announcement::Model),
     )
 )]
 pub async fn get_announcement(
+    session: AuthSession,
     State(state): State<AppState>,
+    AcceptLanguage(locale): AcceptLanguage,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let announcement = match announcement::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(announcement)) => announcement,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Announcement not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Announcement not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch announcement".to_string(),
+            ));
+        }
+    };
+    match Reader::load(&state.db, session.user.as_ref()).await {
+        Ok(reader) if reader.can_read(&announcement) => {}
+        Ok(_) => return Err(AppError::NotFound("Announcement not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch announcement".to_string(),
+            ));
+        }
+    }
+    Ok((StatusCode::OK, Json(announcement.localized(locale))).into_response())
+}
+
+#[utoipa::path(
+    put,
+    tags = ["Announcement"],
+    description = "Update an announcement in place, keeping its ID and publish time. The previous title and content are kept in its edit history. Nobody is emailed again, including users a changed audience newly reaches.",
+    path = "/{id}",
+    request_body(content = UpdateAnnouncementBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Announcement updated successfully", body = announcement::Model),
+        (status = 400, description = "ClassroomUsers audience without a classroom", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Announcement not found", body = ErrorResponse),
+        (status = 500, description = "Failed to update announcement", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn update_announcement(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<UpdateAnnouncementBody>,
+) -> Result<Response, AppError> {
+    let user = match session.user {
+        Some(u) => u,
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+    let target = match body.audience {
+        Some(audience) => {
+            match checked_target(&state.db, Some(audience), body.audience_classroom_id).await {
+                Ok(target) => Some(target),
+                Err(e) => return Err(e),
+            }
+        }
+        None => None,
+    };
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to update announcement".to_string(),
+            ));
+        }
+    };
+    let previous = match announcement::Entity::find_by_id(&id).one(&txn).await {
+        Ok(Some(announcement)) => announcement,
+        Ok(None) => return Err(AppError::NotFound("Announcement not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch announcement".to_string(),
+            ));
+        }
+    };
+
+    // Only wording changes are history; pinning and recategorising are not
+    if previous.title != body.title || previous.content != body.content {
+        let edit = announcement_edit::ActiveModel {
+            id: Set(nanoid!()),
+            announcement_id: Set(previous.id.clone()),
+            editor_id: Set(Some(user.id)),
+            edited_at: Set(Utc::now().with_timezone(&campus_offset())),
+            previous_title: Set(previous.title.clone()),
+            previous_content: Set(previous.content.clone()),
+        };
+        if edit.insert(&txn).await.is_err() {
+            return Err(AppError::Internal(
+                "Failed to record announcement edit".to_string(),
+            ));
+        }
+    }
+
+    let mut active: announcement::ActiveModel = previous.into();
+    active.title = Set(body.title);
+    active.content = Set(body.content);
+    active.title_en = Set(body.title_en);
+    active.title_zh_tw = Set(body.title_zh_tw);
+    active.content_en = Set(body.content_en);
+    active.content_zh_tw = Set(body.content_zh_tw);
+    if let Some(pinned) = body.pinned {
+        active.pinned = Set(pinned);
+    }
+    if let Some(category) = body.category {
+        active.category = Set(category);
+    }
+    if let Some(target) = target {
+        active.audience = Set(target.audience);
+        active.audience_classroom_id = Set(target.classroom_id);
+    }
+    let updated = match active.update(&txn).await {
+        Ok(updated) => updated,
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch announcement",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to update announcement".to_string(),
+            ));
         }
     };
-    (StatusCode::OK, Json(announcement)).into_response()
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to update announcement".to_string(),
+        ));
+    }
+    Ok((StatusCode::OK, Json(updated)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Announcement"],
+    description = "Earlier versions of an announcement's title and content, newest first, with who replaced them and when",
+    path = "/{id}/history",
+    responses(
+        (status = 200, description = "Edit history fetched successfully", body = Vec<announcement_edit::Model>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Announcement not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch edit history", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_announcement_history(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    match announcement::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::NotFound("Announcement not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch announcement".to_string(),
+            ));
+        }
+    }
+    match announcement_edit::Entity::find()
+        .filter(announcement_edit::Column::AnnouncementId.eq(&id))
+        .order_by_desc(announcement_edit::Column::EditedAt)
+        .all(&state.db)
+        .await
+    {
+        Ok(edits) => Ok((StatusCode::OK, Json(edits)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch edit history".to_string(),
+        )),
+    }
 }
 
 #[utoipa::path(
@@ -125,33 +608,212 @@ pub async fn get_announcement(
 pub async fn delete_announcement(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let announcement = match announcement::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(announcement)) => announcement,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Announcement not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Announcement not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch announcement",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch announcement".to_string(),
+            ));
         }
     };
     match announcement.delete(&state.db).await {
-        Ok(_) => (StatusCode::OK, "Announcement deleted successfully").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to delete announcement",
+        Ok(_) => Ok((StatusCode::OK, "Announcement deleted successfully").into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to delete announcement".to_string(),
+        )),
+    }
+}
+
+/// Recipients queued at a time by [`broadcast`], with a pause in between so
+/// a campus-wide announcement does not hold up other email being queued.
+const BROADCAST_BATCH_SIZE: usize = 200;
+const BROADCAST_BATCH_PAUSE: std::time::Duration = std::time::Duration::from_secs(1);
+
+type TodaysReservations = BTreeMap<String, Vec<(reservation::Model, Option<classroom::Model>)>>;
+
+/// Pending/approved reservations for the current campus day by user, for
+/// emergency emails.
+async fn todays_reservations(db: &DatabaseConnection) -> TodaysReservations {
+    let today = Utc::now().with_timezone(&campus_offset()).date_naive();
+    let day_start = today
+        .and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(campus_offset())
+        .unwrap();
+    let day_end = day_start + Duration::days(1);
+
+    let todays_reservations = reservation::Entity::find()
+        .filter(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .filter(reservation::Column::StartTime.lt(day_end))
+        .filter(reservation::Column::EndTime.gt(day_start))
+        .order_by_asc(reservation::Column::StartTime)
+        .all(db)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to fetch today's reservations: {}", e);
+            Vec::new()
+        });
+    let classrooms = classroom::Entity::find()
+        .filter(
+            classroom::Column::Id.is_in(
+                todays_reservations
+                    .iter()
+                    .filter_map(|r| r.classroom_id.clone()),
+            ),
         )
-            .into_response(),
+        .all(db)
+        .await
+        .unwrap_or_default();
+
+    let mut by_user: TodaysReservations = BTreeMap::new();
+    for reservation_model in todays_reservations {
+        let Some(user_id) = reservation_model.user_id.clone() else {
+            continue;
+        };
+        let classroom_model = classrooms
+            .iter()
+            .find(|c| reservation_model.classroom_id.as_deref() == Some(c.id.as_str()))
+            .cloned();
+        by_user
+            .entry(user_id)
+            .or_default()
+            .push((reservation_model, classroom_model));
+    }
+    by_user
+}
+
+/// The email `user_model` gets for `announcement`.
+fn render(
+    templates: &EmailTemplateConfig,
+    announcement: &announcement::Model,
+    user_model: &user::Model,
+    todays_reservations: &TodaysReservations,
+) -> RenderedEmail {
+    let locale = templates.locale_for(user_model);
+    let announcement = announcement.clone().localized(Some(locale));
+    if announcement.emergency {
+        let affected: Vec<_> = todays_reservations
+            .get(&user_model.id)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+            .iter()
+            .map(|(reservation, classroom)| {
+                (
+                    reservation.clone(),
+                    classroom.clone().map(|c| c.localized(Some(locale))),
+                )
+            })
+            .collect();
+        email_templates::emergency_announcement(templates, &announcement, &affected, locale)
+    } else {
+        email_templates::announcement_published(&announcement, locale)
+    }
+}
+
+/// Emails an announcement to its audience in batches of
+/// [`BROADCAST_BATCH_SIZE`]. Regular announcements skip users who muted the
+/// category; emergency ones bypass any opt-outs, are sent as critical and list
+/// the recipient's pending/approved reservations for the current campus day.
+/// Emergency announcements reach this through
+/// [`DomainEvent::EmergencyAnnounced`].
+pub async fn broadcast(state: &AppState, announcement: &announcement::Model) -> Result<(), String> {
+    let audience = announcement_audience::load(
+        &state.db,
+        &announcement.category,
+        announcement.emergency,
+        &Target::of(announcement),
+    )
+    .await
+    .map_err(|e| format!("Failed to fetch users: {}", e))?;
+    let (todays_reservations, priority) = if announcement.emergency {
+        (todays_reservations(&state.db).await, Priority::Critical)
+    } else {
+        (BTreeMap::new(), Priority::Normal)
+    };
+
+    let mut errors = Vec::new();
+    let batches = audience.recipients.chunks(BROADCAST_BATCH_SIZE);
+    let batch_count = batches.len();
+    for (index, batch) in batches.enumerate() {
+        if index > 0 {
+            tokio::time::sleep(BROADCAST_BATCH_PAUSE).await;
+        }
+        for user_model in batch {
+            let email = render(
+                &state.config.email_templates,
+                announcement,
+                user_model,
+                &todays_reservations,
+            );
+            if let Err(e) =
+                queue_email_to_user(state, user_model, email.subject, email.body, priority).await
+            {
+                errors.push(format!("{}: {}", user_model.id, e));
+            }
+        }
+        info!(
+            "Queued batch {}/{} of announcement {}",
+            index + 1,
+            batch_count,
+            announcement.id
+        );
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Failed to email {}", errors.join(", ")))
     }
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Announcement", description = "Announcement endpoints")
+    ),
+    paths(
+        create_announcement,
+        preview_recipients,
+        list_announcements,
+        get_announcement,
+        update_announcement,
+        get_announcement_history,
+        delete_announcement,
+    ),
+    components(schemas(
+        crate::entities::announcement::Model,
+        crate::entities::announcement_edit::Model,
+        CreateAnnouncementBody,
+        UpdateAnnouncementBody,
+        crate::pagination::PagedResponse<crate::entities::announcement::Model>,
+        AnnouncementAudienceBody,
+        AnnouncementDryRun,
+        SampleEmail,
+        crate::announcement_audience::RecipientPreview,
+        crate::announcement_audience::RoleCount,
+        crate::entities::sea_orm_active_enums::AnnouncementCategory,
+        crate::entities::sea_orm_active_enums::AnnouncementAudience,
+    ))
+)]
+pub struct AnnouncementApi;
+
 pub fn announcement_router() -> Router<AppState> {
     let admin_only_route = Router::new()
         .route("/", post(create_announcement))
-        .route("/{id}", delete(delete_announcement))
-        .route_layer(permission_required!(AuthBackend, Role::Admin));
+        .route("/preview", post(preview_recipients))
+        .route(
+            "/{id}",
+            put(update_announcement).delete(delete_announcement),
+        )
+        .route("/{id}/history", get(get_announcement_history))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageAnnouncements
+        ));
 
     Router::new()
         .route("/", get(list_announcements))
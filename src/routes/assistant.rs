@@ -0,0 +1,291 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, put},
+};
+use axum_login::permission_required;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    audit::{AuditContext, Target, user_snapshot},
+    entities::{classroom, classroom_assistant, sea_orm_active_enums::Role, user},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::{Permission, assistant_classrooms},
+    versioning,
+};
+
+/// A classroom whose reservations an assistant may review.
+#[derive(Serialize, ToSchema)]
+pub struct AssistantClassroom {
+    pub classroom: classroom::Model,
+    pub granted_by: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub granted_at: DateTimeWithTimeZone,
+}
+
+async fn with_classrooms(
+    db: &DatabaseConnection,
+    grants: Vec<classroom_assistant::Model>,
+) -> Result<Vec<AssistantClassroom>, DbErr> {
+    let mut classrooms = Vec::new();
+    for grant in grants {
+        if let Some(classroom) = classroom::Entity::find_by_id(&grant.classroom_id)
+            .one(db)
+            .await?
+        {
+            classrooms.push(AssistantClassroom {
+                classroom,
+                granted_by: grant.granted_by,
+                granted_at: grant.created_at,
+            });
+        }
+    }
+    Ok(classrooms)
+}
+
+/// Saves a role change and drops the cached user, so the new permissions
+/// apply on the user's next request.
+async fn set_role(
+    state: &AppState,
+    audit: &AuditContext,
+    user: user::Model,
+    role: Role,
+) -> Result<(), DbErr> {
+    let user_id = user.id.clone();
+    let previous = user_snapshot(&user);
+    let mut active: user::ActiveModel = user.into();
+    active.role = Set(role);
+    let updated = versioning::update(&state.db, active, user::Column::Version, None).await?;
+    state.user_cache.forget(&user_id).await;
+    audit
+        .log(
+            &state.db,
+            Target::User,
+            &user_id,
+            "role_change",
+            Some(previous),
+            Some(user_snapshot(&updated)),
+        )
+        .await;
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Classrooms a department assistant may review reservations for",
+    path = "/assistants/{user_id}/classrooms",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Granted classrooms, oldest grant first", body = Vec<AssistantClassroom>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch classrooms", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_assistant_classrooms(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Response, AppError> {
+    match user::Entity::find_by_id(&user_id).one(&state.db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    }
+    let grants = match assistant_classrooms(&state.db, &user_id).await {
+        Ok(grants) => grants,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classrooms".to_string()));
+        }
+    };
+    match with_classrooms(&state.db, grants).await {
+        Ok(classrooms) => Ok((StatusCode::OK, Json(classrooms)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch classrooms".to_string())),
+    }
+}
+
+#[utoipa::path(
+    put,
+    tags = ["Admin"],
+    description = "Let a user review reservations for a classroom. A regular user becomes a department assistant, who can review reservations for their granted classrooms but cannot manage users, keys or classrooms.",
+    path = "/assistants/{user_id}/classrooms/{classroom_id}",
+    params(
+        ("user_id" = String, Path, description = "User ID"),
+        ("classroom_id" = String, Path, description = "Classroom ID")
+    ),
+    responses(
+        (status = 200, description = "The classroom was already granted", body = AssistantClassroom),
+        (status = 201, description = "Classroom granted", body = AssistantClassroom),
+        (status = 400, description = "The user is an admin", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User or classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to grant classroom", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn grant_assistant_classroom(
+    session: AuthSession,
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path((user_id, classroom_id)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    let admin = session.user.unwrap();
+    let assistant = match user::Entity::find_by_id(&user_id).one(&state.db).await {
+        Ok(Some(assistant)) => assistant,
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    };
+    if assistant.role == Role::Admin {
+        return Err(AppError::BadRequest(
+            "Admins can already review every classroom".to_string(),
+        ));
+    }
+    let classroom = match classroom::Entity::find_by_id(&classroom_id)
+        .filter(classroom::Column::DeletedAt.is_null())
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(classroom)) => classroom,
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    };
+
+    let existing = classroom_assistant::Entity::find()
+        .filter(classroom_assistant::Column::UserId.eq(&user_id))
+        .filter(classroom_assistant::Column::ClassroomId.eq(&classroom_id))
+        .one(&state.db)
+        .await;
+    let (status, grant) = match existing {
+        Ok(Some(grant)) => (StatusCode::OK, grant),
+        Ok(None) => {
+            let grant = classroom_assistant::ActiveModel {
+                id: Set(nanoid!()),
+                user_id: Set(user_id.clone()),
+                classroom_id: Set(classroom_id),
+                granted_by: Set(Some(admin.id)),
+                created_at: NotSet,
+            };
+            match grant.insert(&state.db).await {
+                Ok(grant) => (StatusCode::CREATED, grant),
+                Err(_) => {
+                    return Err(AppError::Internal("Failed to grant classroom".to_string()));
+                }
+            }
+        }
+        Err(_) => {
+            return Err(AppError::Internal("Failed to grant classroom".to_string()));
+        }
+    };
+
+    if assistant.role == Role::User
+        && set_role(&state, &audit, assistant, Role::Assistant)
+            .await
+            .is_err()
+    {
+        return Err(AppError::Internal("Failed to grant classroom".to_string()));
+    }
+
+    Ok((
+        status,
+        Json(AssistantClassroom {
+            classroom,
+            granted_by: grant.granted_by,
+            granted_at: grant.created_at,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    delete,
+    tags = ["Admin"],
+    description = "Stop a department assistant from reviewing reservations for a classroom. An assistant left without classrooms becomes a regular user again.",
+    path = "/assistants/{user_id}/classrooms/{classroom_id}",
+    params(
+        ("user_id" = String, Path, description = "User ID"),
+        ("classroom_id" = String, Path, description = "Classroom ID")
+    ),
+    responses(
+        (status = 204, description = "Classroom revoked"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "The classroom was not granted to the user", body = ErrorResponse),
+        (status = 500, description = "Failed to revoke classroom", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn revoke_assistant_classroom(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path((user_id, classroom_id)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    match classroom_assistant::Entity::delete_many()
+        .filter(classroom_assistant::Column::UserId.eq(&user_id))
+        .filter(classroom_assistant::Column::ClassroomId.eq(&classroom_id))
+        .exec(&state.db)
+        .await
+    {
+        Ok(result) if result.rows_affected == 0 => {
+            return Err(AppError::NotFound("Classroom was not granted".to_string()));
+        }
+        Ok(_) => {}
+        Err(_) => {
+            return Err(AppError::Internal("Failed to revoke classroom".to_string()));
+        }
+    }
+
+    let remaining = assistant_classrooms(&state.db, &user_id).await;
+    let assistant = user::Entity::find_by_id(&user_id).one(&state.db).await;
+    match (remaining, assistant) {
+        (Ok(remaining), Ok(Some(assistant)))
+            if remaining.is_empty() && assistant.role == Role::Assistant =>
+        {
+            if set_role(&state, &audit, assistant, Role::User)
+                .await
+                .is_err()
+            {
+                return Err(AppError::Internal("Failed to revoke classroom".to_string()));
+            }
+        }
+        (Ok(_), Ok(_)) => {}
+        _ => {
+            return Err(AppError::Internal("Failed to revoke classroom".to_string()));
+        }
+    }
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub fn assistant_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/assistants/{user_id}/classrooms",
+            get(list_assistant_classrooms),
+        )
+        .route(
+            "/assistants/{user_id}/classrooms/{classroom_id}",
+            put(grant_assistant_classroom).delete(revoke_assistant_classroom),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers))
+}
@@ -0,0 +1,113 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+
+use crate::{
+    AppState,
+    entities::audit_log,
+    error::{AppError, ErrorResponse},
+    export::parse_filter,
+    login_system::AuthBackend,
+    pagination::{PageParams, PagedResponse, Pagination, fetch},
+    permissions::Permission,
+};
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct AuditLogQuery {
+    /// Changes made by this admin
+    pub actor_id: Option<String>,
+    /// e.g. `classroom.update` or `user.role_change`
+    pub action: Option<String>,
+    /// `classroom`, `key`, `reservation`, `black_list` or `user`
+    pub target_type: Option<String>,
+    /// Changes to this record
+    pub target_id: Option<String>,
+    /// Recorded at or after this time
+    pub from: Option<String>,
+    /// Recorded before this time
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Audit"],
+    description = "Changes made by admins, newest first, with who made them, from which address and the record before and after. Filters combine with AND.",
+    path = "/admin/list",
+    params(AuditLogQuery, PageParams),
+    responses(
+        (status = 200, description = "Audit log entries", body = PagedResponse<audit_log::Model>),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch the audit log", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    Query(query): Query<AuditLogQuery>,
+    pagination: Pagination,
+) -> Result<Response, AppError> {
+    let mut find_query = audit_log::Entity::find();
+
+    if let Some(actor_id) = query.actor_id {
+        find_query = find_query.filter(audit_log::Column::ActorId.eq(actor_id));
+    }
+    if let Some(action) = query.action {
+        find_query = find_query.filter(audit_log::Column::Action.eq(action));
+    }
+    if let Some(target_type) = query.target_type {
+        find_query = find_query.filter(audit_log::Column::TargetType.eq(target_type));
+    }
+    if let Some(target_id) = query.target_id {
+        find_query = find_query.filter(audit_log::Column::TargetId.eq(target_id));
+    }
+    match parse_filter(&query.from, "from") {
+        Ok(Some(from)) => find_query = find_query.filter(audit_log::Column::CreatedAt.gte(from)),
+        Ok(None) => {}
+        Err(message) => return Err(AppError::BadRequest(message)),
+    }
+    match parse_filter(&query.to, "to") {
+        Ok(Some(to)) => find_query = find_query.filter(audit_log::Column::CreatedAt.lt(to)),
+        Ok(None) => {}
+        Err(message) => return Err(AppError::BadRequest(message)),
+    }
+
+    let find_query = find_query
+        .order_by_desc(audit_log::Column::CreatedAt)
+        .order_by_desc(audit_log::Column::Id);
+    match fetch(&state.db, find_query, pagination).await {
+        Ok(entries) => Ok((StatusCode::OK, Json(entries)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch the audit log".to_string(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Audit", description = "Record of changes made by administrators")
+    ),
+    paths(list_audit_log),
+    components(schemas(
+        AuditLogQuery,
+        crate::pagination::PagedResponse<crate::entities::audit_log::Model>,
+        crate::entities::audit_log::Model,
+    ))
+)]
+pub struct AuditApi;
+
+pub fn audit_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/list", get(list_audit_log))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageSystem))
+}
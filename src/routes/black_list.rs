@@ -1,26 +1,53 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
 };
-use axum_login::permission_required;
+use axum_login::{login_required, permission_required};
+use chrono::Utc;
 use nanoid::nanoid;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    EntityTrait, ModelTrait,
+    ColumnTrait, Condition, EntityTrait, ModelTrait, Order, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
 };
-use serde::Deserialize;
-use utoipa::ToSchema;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    entities::{black_list, sea_orm_active_enums::Role},
+    audit::{AuditContext, Target, snapshot},
+    availability::campus_offset,
+    bans::{active_condition, active_for_user, is_active},
+    entities::{black_list, infraction},
+    error::{AppError, ErrorResponse},
     login_system::{AuthBackend, AuthSession},
+    pagination::{PageParams, PagedResponse, Pagination, SortSpec, fetch},
+    permissions::Permission,
+    utils::parse_dt_field,
 };
 
+#[derive(Serialize, ToSchema)]
+pub struct BlackListResponse {
+    #[serde(flatten)]
+    pub record: black_list::Model,
+    /// Whether the ban still applies, i.e. it has no `end_at` or ends in the
+    /// future
+    pub is_active: bool,
+}
+
+impl BlackListResponse {
+    pub fn new(record: black_list::Model, now: DateTimeWithTimeZone) -> Self {
+        Self {
+            is_active: is_active(&record, now),
+            record,
+        }
+    }
+}
+
 // =========================
 //   CREATE BLACKLIST (Admin)
 // =========================
@@ -39,26 +66,27 @@ pub struct CreateBlackListBody {
     request_body(content = CreateBlackListBody, content_type = "application/json"),
     responses(
         (status = 201, description = "Blacklist record created", body = black_list::Model),
-        (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Bad request"),
-        (status = 500, description = "Failed to create blacklist record")
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 500, description = "Failed to create blacklist record", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn create_black_list(
     session: AuthSession,
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(body): Json<CreateBlackListBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let admin = match session.user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
     };
 
     let end_at_parsed = match body.end_at {
-        Some(s) => match s.parse() {
+        Some(s) => match parse_dt_field(&s, "end_at") {
             Ok(dt) => Some(dt),
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid end_at format").into_response(),
+            Err(message) => return Err(AppError::BadRequest(message)),
         },
         None => None,
     };
@@ -70,40 +98,85 @@ pub async fn create_black_list(
         created_by: Set(Some(admin.id)),
         created_at: NotSet,
         end_at: Set(end_at_parsed),
+        end_notified_at: NotSet,
     };
 
     match new_record.insert(&state.db).await {
-        Ok(model) => (StatusCode::CREATED, Json(model)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to create blacklist record",
-        )
-            .into_response(),
+        Ok(model) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::BlackList,
+                    &model.id,
+                    "create",
+                    None,
+                    Some(snapshot(&model)),
+                )
+                .await;
+            Ok((StatusCode::CREATED, Json(model)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to create blacklist record".to_string(),
+        )),
     }
 }
 
 // =========================
 //   RETRIEVE BLACKLIST
 // =========================
+#[derive(Deserialize, IntoParams)]
+pub struct ListBlackListQuery {
+    /// Only records still in force (`true`) or already ended (`false`)
+    pub active: Option<bool>,
+    /// `created_at` (default) or `end_at`, optionally with `:asc` or `:desc`
+    pub sort: Option<String>,
+}
+
 #[utoipa::path(
     get,
     tags = ["BlackList"],
-    description = "Get all blacklist records",
+    description = "Get blacklist records, newest first unless sorted by `created_at` or `end_at`",
     path = "",
+    params(ListBlackListQuery, PageParams),
     responses(
-        (status = 200, description = "List of blacklist records", body = Vec<black_list::Model>),
-        (status = 500, description = "Failed to fetch blacklist records", body = String)
+        (status = 200, description = "List of blacklist records", body = PagedResponse<BlackListResponse>),
+        (status = 400, description = "Invalid sort", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch blacklist records", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
-pub async fn list_black_list(State(state): State<AppState>) -> impl IntoResponse {
-    match black_list::Entity::find().all(&state.db).await {
-        Ok(list) => (StatusCode::OK, Json(list)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch blacklist records",
-        )
-            .into_response(),
+pub async fn list_black_list(
+    State(state): State<AppState>,
+    pagination: Pagination,
+    Query(params): Query<ListBlackListQuery>,
+) -> Result<Response, AppError> {
+    let sort = match SortSpec::parse(
+        params.sort.as_deref(),
+        &[
+            ("created_at", black_list::Column::CreatedAt),
+            ("end_at", black_list::Column::EndAt),
+        ],
+        black_list::Column::CreatedAt,
+        Order::Desc,
+    ) {
+        Ok(sort) => sort,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let now = Utc::now().with_timezone(&campus_offset());
+    let mut select = black_list::Entity::find();
+    match params.active {
+        Some(true) => select = select.filter(active_condition(now)),
+        Some(false) => select = select.filter(Condition::not(active_condition(now))),
+        None => {}
+    }
+    match fetch(&state.db, sort.apply(select), pagination).await {
+        Ok(list) => {
+            let list = list.map(|record| BlackListResponse::new(record, now));
+            Ok((StatusCode::OK, Json(list)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch blacklist records".to_string(),
+        )),
     }
 }
 
@@ -114,27 +187,120 @@ pub async fn list_black_list(State(state): State<AppState>) -> impl IntoResponse
     path = "/{id}",
     params(("id" = String, Path, description = "Blacklist ID")),
     responses(
-        (status = 200, description = "Blacklist record", body = black_list::Model),
-        (status = 404, description = "Blacklist record not found", body = String),
-        (status = 500, description = "Failed to fetch blacklist record", body = String)
+        (status = 200, description = "Blacklist record", body = BlackListResponse),
+        (status = 404, description = "Blacklist record not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch blacklist record", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn get_black_list(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     match black_list::Entity::find_by_id(id).one(&state.db).await {
-        Ok(Some(model)) => (StatusCode::OK, Json(model)).into_response(),
-        Ok(None) => (StatusCode::NOT_FOUND, "Blacklist record not found").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch blacklist record",
-        )
-            .into_response(),
+        Ok(Some(model)) => {
+            let now = Utc::now().with_timezone(&campus_offset());
+            Ok((StatusCode::OK, Json(BlackListResponse::new(model, now))).into_response())
+        }
+        Ok(None) => Err(AppError::NotFound("Blacklist record not found".to_string())),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch blacklist record".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["BlackList"],
+    description = "Bans of a user still in force, newest first. Empty when the user may book classrooms and borrow keys; the same check runs when they do.",
+    path = "/user/{user_id}/active",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Active blacklist records", body = Vec<BlackListResponse>),
+        (status = 500, description = "Failed to fetch blacklist records", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_active_for_user(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Response, AppError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    match active_for_user(&state.db, &user_id, now).await {
+        Ok(records) => {
+            let records: Vec<BlackListResponse> = records
+                .into_iter()
+                .map(|record| BlackListResponse::new(record, now))
+                .collect();
+            Ok((StatusCode::OK, Json(records)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch blacklist records".to_string(),
+        )),
     }
 }
 
+/// A user's own blacklist record, without the staff who created it.
+#[derive(Serialize, ToSchema)]
+pub struct SelfBlackListEntry {
+    pub id: String,
+    /// Description of the infraction that led to the ban
+    pub reason: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+    /// `None` for a permanent ban
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub end_at: Option<DateTimeWithTimeZone>,
+    pub is_active: bool,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["BlackList"],
+    description = "The logged-in user's blacklist records, newest first, with the infraction behind each and when it ends. While one is active the user cannot reserve classrooms or borrow keys.",
+    path = "/self",
+    responses(
+        (status = 200, description = "Own blacklist records", body = Vec<SelfBlackListEntry>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch blacklist records", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_self_black_list(
+    session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+    let records = match black_list::Entity::find()
+        .filter(black_list::Column::UserId.eq(&user.id))
+        .order_by_desc(black_list::Column::CreatedAt)
+        .find_also_related(infraction::Entity)
+        .all(&state.db)
+        .await
+    {
+        Ok(records) => records,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch blacklist records".to_string(),
+            ));
+        }
+    };
+    let now = Utc::now().with_timezone(&campus_offset());
+    let entries: Vec<SelfBlackListEntry> = records
+        .into_iter()
+        .map(|(record, infraction)| SelfBlackListEntry {
+            is_active: is_active(&record, now),
+            id: record.id,
+            reason: infraction.map(|i| i.description),
+            created_at: record.created_at,
+            end_at: record.end_at,
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(entries)).into_response())
+}
+
 // =========================
 //   UPDATE BLACKLIST (Admin)
 // =========================
@@ -154,25 +320,27 @@ pub struct UpdateBlackListBody {
     params(("id" = String, Path, description = "Blacklist ID")),
     responses(
         (status = 200, description = "Blacklist record updated", body = black_list::Model),
-        (status = 404, description = "Blacklist record not found", body = String),
-        (status = 400, description = "Bad request", body = String),
-        (status = 500, description = "Failed to update blacklist record", body = String)
+        (status = 404, description = "Blacklist record not found", body = ErrorResponse),
+        (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 500, description = "Failed to update blacklist record", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn update_black_list(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
     Json(body): Json<UpdateBlackListBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let Some(model) = black_list::Entity::find_by_id(id)
         .one(&state.db)
         .await
         .unwrap_or(None)
     else {
-        return (StatusCode::NOT_FOUND, "Blacklist record not found").into_response();
+        return Err(AppError::NotFound("Blacklist record not found".to_string()));
     };
 
+    let previous = model.clone();
     let mut active: black_list::ActiveModel = model.into();
 
     if let Some(uid) = body.user_id {
@@ -182,20 +350,30 @@ pub async fn update_black_list(
         active.infraction_id = Set(Some(infraction_id));
     }
     if let Some(end_at_str) = body.end_at {
-        let end_at_parsed = match end_at_str.parse() {
+        let end_at_parsed = match parse_dt_field(&end_at_str, "end_at") {
             Ok(dt) => dt,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid end_at format").into_response(),
+            Err(message) => return Err(AppError::BadRequest(message)),
         };
         active.end_at = Set(Some(end_at_parsed));
     }
 
     match active.update(&state.db).await {
-        Ok(updated) => (StatusCode::OK, Json(updated)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update blacklist record",
-        )
-            .into_response(),
+        Ok(updated) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::BlackList,
+                    &updated.id,
+                    "update",
+                    Some(snapshot(&previous)),
+                    Some(snapshot(&updated)),
+                )
+                .await;
+            Ok((StatusCode::OK, Json(updated)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to update blacklist record".to_string(),
+        )),
     }
 }
 
@@ -210,42 +388,84 @@ pub async fn update_black_list(
     params(("id" = String, Path, description = "Blacklist ID")),
     responses(
         (status = 200, description = "Blacklist record deleted", body = String),
-        (status = 404, description = "Blacklist record not found", body = String),
-        (status = 500, description = "Failed to delete blacklist record", body = String)
+        (status = 404, description = "Blacklist record not found", body = ErrorResponse),
+        (status = 500, description = "Failed to delete blacklist record", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn delete_black_list(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let Some(model) = black_list::Entity::find_by_id(id)
         .one(&state.db)
         .await
         .unwrap_or(None)
     else {
-        return (StatusCode::NOT_FOUND, "Blacklist record not found").into_response();
+        return Err(AppError::NotFound("Blacklist record not found".to_string()));
     };
 
+    let previous = model.clone();
     match model.delete(&state.db).await {
-        Ok(_) => (StatusCode::OK, "Blacklist record deleted").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to delete blacklist record",
-        )
-            .into_response(),
+        Ok(_) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::BlackList,
+                    &previous.id,
+                    "delete",
+                    Some(snapshot(&previous)),
+                    None,
+                )
+                .await;
+            Ok((StatusCode::OK, "Blacklist record deleted").into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to delete blacklist record".to_string(),
+        )),
     }
 }
 
 // =========================
 //   ROUTER
 // =========================
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Blacklist", description = "Blacklist endpoints")
+    ),
+    paths(
+        create_black_list,
+        update_black_list,
+        list_black_list,
+        get_black_list,
+        list_active_for_user,
+        list_self_black_list,
+        delete_black_list,
+    ),
+    components(schemas(
+        crate::entities::black_list::Model,
+        UpdateBlackListBody,
+        BlackListResponse,
+        SelfBlackListEntry,
+        crate::pagination::PagedResponse<BlackListResponse>,
+    ))
+)]
+pub struct BlacklistApi;
+
 pub fn black_list_router() -> Router<AppState> {
+    let self_route = Router::new()
+        .route("/self", get(list_self_black_list))
+        .route_layer(login_required!(AuthBackend));
+
     Router::new()
         .route("/", post(create_black_list))
         .route("/", get(list_black_list))
         .route("/{id}", get(get_black_list))
+        .route("/user/{user_id}/active", get(list_active_for_user))
         .route("/{id}", put(update_black_list))
         .route("/{id}", delete(delete_black_list))
-        .route_layer(permission_required!(AuthBackend, Role::Admin))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers))
+        .merge(self_route)
 }
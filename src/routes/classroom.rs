@@ -1,48 +1,61 @@
-use std::sync::{Arc, OnceLock};
+use std::sync::LazyLock;
 
-use crate::entities::sea_orm_active_enums::{ClassroomStatus, Role};
+use crate::concurrency::limit_photo_uploads;
+use crate::entities::sea_orm_active_enums::ClassroomStatus;
 use crate::entities::{key, reservation};
-use crate::{entities::classroom, login_system::AuthBackend};
+use crate::routes::classroom_check_in::classroom_check_in_router;
+use crate::routes::classroom_schedule::classroom_schedule_router;
+use crate::routes::classroom_status::classroom_status_router;
+use crate::routes::timetable::timetable_router;
+use crate::{entities::classroom, login_system::AuthBackend, permissions::Permission};
 use axum::extract::Query;
+use axum::middleware;
 use axum::routing::{delete, post, put};
 use axum::{
     Json, Router,
-    body::Bytes,
+    body::{Body, Bytes},
     extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{IntoResponse, Response},
     routing::get,
 };
 use axum_login::permission_required;
 use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use chrono::Utc;
 use nanoid::nanoid;
-use redis::AsyncCommands;
 use reqwest::multipart::Part;
 use reqwest::{Client, multipart};
 use sea_orm::ModelTrait;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    EntityTrait,
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
 };
 use serde::{Deserialize, Serialize};
 use tracing::warn;
-use utoipa::ToSchema;
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    constants::{REDIS_EXPIRY, get_redis_set_options},
-    utils::{
-        classroom_key, classroom_with_keys_and_reservations_key, classroom_with_keys_key,
-        classroom_with_reservations_key,
-    },
+    audit::{AuditContext, Target, snapshot},
+    availability::campus_offset,
+    cache::CacheKey,
+    classroom_history,
+    classroom_overview::{ClassroomOverview, load_overviews},
+    constants::PHOTO_CACHE_MAX_AGE_SECONDS,
+    email_templates::Locale,
+    error::{AppError, ErrorResponse},
+    fields,
+    key_lifecycle::{KeySummary, classroom_summary},
+    localization::{AcceptLanguage, Localize},
+    merge_patch::Patch,
+    server_timing::{Dependency, measure},
+    utils::{etag_matches, json_with_etag, parse_dt},
+    versioning,
 };
 
-const CLASSROOMS_LIST_KEY: &str = "classrooms:list";
-
-static IMAGE_SERVICE_API_KEY: OnceLock<String> = OnceLock::new();
-static IMAGE_SERVICE_IP: OnceLock<String> = OnceLock::new();
-static IMAGE_SERVICE_CLIENT: OnceLock<Arc<Client>> = OnceLock::new();
+pub static IMAGE_SERVICE_CLIENT: LazyLock<Client> = LazyLock::new(Client::new);
 
 #[derive(TryFromMultipart, ToSchema)]
 pub struct CreateClassroomBody {
@@ -50,11 +63,32 @@ pub struct CreateClassroomBody {
     capacity: i32,
     location: String,
     description: String,
+    name_en: Option<String>,
+    name_zh_tw: Option<String>,
+    description_en: Option<String>,
+    description_zh_tw: Option<String>,
+    /// Reservations need approvals from two different reviewers
+    requires_double_approval: Option<bool>,
     #[form_data(limit = "5MB")]
     #[schema(value_type = String, format = "binary")]
     photo: FieldData<Bytes>,
 }
 
+#[derive(Deserialize, ToSchema)]
+pub struct ListClassroomsQuery {
+    /// Comma-separated columns to return, e.g. `id,name,status`
+    fields: Option<String>,
+}
+
+/// A classroom in the list, with the badges the room picker shows.
+#[derive(Serialize, ToSchema)]
+pub struct ClassroomListItem {
+    #[serde(flatten)]
+    classroom: classroom::Model,
+    #[serde(flatten)]
+    overview: ClassroomOverview,
+}
+
 #[derive(Deserialize, ToSchema)]
 pub struct GetClassroomQuery {
     with_keys: Option<bool>,
@@ -67,6 +101,70 @@ pub struct UpdateClassroomBody {
     capacity: i32,
     location: String,
     description: String,
+    /// Translations; left out ones are cleared
+    name_en: Option<String>,
+    name_zh_tw: Option<String>,
+    description_en: Option<String>,
+    description_zh_tw: Option<String>,
+    /// Reservations need approvals from two different reviewers; left out
+    /// turns it off
+    #[serde(default)]
+    requires_double_approval: bool,
+    /// `version` of the classroom this edit was made on; refused with 409
+    /// when someone else saved it since
+    version: Option<i32>,
+}
+
+/// JSON Merge Patch of a classroom: left-out fields stay as they are. None
+/// of them can be cleared, so `null` is rejected.
+#[derive(Deserialize, ToSchema)]
+pub struct PatchClassroomBody {
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    name: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<i32>)]
+    capacity: Patch<i32>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    location: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    description: Patch<String>,
+    /// Translations can be cleared with `null`
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    name_en: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    name_zh_tw: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    description_en: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    description_zh_tw: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<bool>)]
+    requires_double_approval: Patch<bool>,
+    /// `version` of the classroom this edit was made on; refused with 409
+    /// when someone else saved it since
+    version: Option<i32>,
+}
+
+/// Changes to a classroom; `None` leaves a field untouched.
+struct ClassroomChanges {
+    name: Option<String>,
+    capacity: Option<i32>,
+    location: Option<String>,
+    description: Option<String>,
+    name_en: Option<Option<String>>,
+    name_zh_tw: Option<Option<String>>,
+    description_en: Option<Option<String>>,
+    description_zh_tw: Option<Option<String>>,
+    requires_double_approval: Option<bool>,
+    /// Version the changes were made on, if the client sent one
+    version: Option<i32>,
 }
 
 #[derive(TryFromMultipart, ToSchema)]
@@ -112,52 +210,49 @@ pub enum GetClassroomResponse {
     request_body(content = CreateClassroomBody, content_type = "multipart/form-data"),
     responses(
         (status = 201, description = "Classroom created successfully", body = classroom::Model),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 503, description = "Too many uploads in progress, retry later", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn create_classroom(
     State(state): State<AppState>,
+    audit: AuditContext,
     TypedMultipart(CreateClassroomBody {
         name,
         capacity,
         location,
         description,
+        name_en,
+        name_zh_tw,
+        description_en,
+        description_zh_tw,
+        requires_double_approval,
         photo,
     }): TypedMultipart<CreateClassroomBody>,
-) -> impl IntoResponse {
-    let url = IMAGE_SERVICE_IP
-        .get()
-        .expect("IMAGE_SERVICE_IP not set")
-        .clone();
-    let key = IMAGE_SERVICE_API_KEY
-        .get()
-        .expect("IMAGE_SERVICE_API_KEY not set")
-        .clone();
-    let client = IMAGE_SERVICE_CLIENT
-        .get()
-        .expect("IMAGE_SERVICE_CLIENT not set")
-        .clone();
+) -> Result<Response, AppError> {
+    let url = state.config.image_service.url.clone();
+    let key = state.config.image_service.api_key.clone();
+    let client = IMAGE_SERVICE_CLIENT.clone();
 
     let body = multipart::Form::new().part(
         "image",
         Part::bytes(photo.contents.to_vec()).file_name(photo.metadata.file_name.unwrap()),
     );
 
-    let response = match client
+    let request = client
         .post(format!("{}/", url))
         .multipart(body)
         .header("key", key)
-        .send()
-        .await
-    {
+        .send();
+    let response = match measure(Dependency::ImageService, request).await {
         Ok(resp) => match resp.status() {
             StatusCode::CREATED => resp.text().await.unwrap(),
             _ => {
-                return (StatusCode::BAD_REQUEST, resp.text().await.unwrap()).into_response();
+                return Err(AppError::BadRequest(resp.text().await.unwrap()));
             }
         },
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to upload image").into_response();
+            return Err(AppError::Internal("Failed to upload image".to_string()));
         }
     };
 
@@ -171,140 +266,196 @@ pub async fn create_classroom(
         updated_at: NotSet,
         description: Set(description),
         photo_id: Set(response),
+        status_reason: NotSet,
+        status_from: NotSet,
+        status_until: NotSet,
+        deleted_at: NotSet,
+        name_en: Set(name_en),
+        name_zh_tw: Set(name_zh_tw),
+        description_en: Set(description_en),
+        description_zh_tw: Set(description_zh_tw),
+        requires_double_approval: Set(requires_double_approval.unwrap_or(false)),
+        version: NotSet,
     };
 
-    match new_classroom.insert(&state.db).await {
-        Ok(classroom) => {
-            // Cache the new classroom
-            let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    classroom_key(&classroom.id),
-                    serde_json::to_string(&classroom).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache classroom {} in Redis: {}", classroom.id, e);
-            }
-            // Invalidate classrooms list cache
-            let _: Result<(), redis::RedisError> = redis.del(CLASSROOMS_LIST_KEY).await;
-
-            (StatusCode::CREATED, Json(classroom)).into_response()
+    // The classroom, its first history version and the audit entry are saved
+    // together; if that fails the uploaded photo is removed again
+    let photo_id = new_classroom.photo_id.clone().unwrap();
+    let classroom = match insert_classroom(&state.db, &audit, new_classroom).await {
+        Ok(classroom) => classroom,
+        Err(e) => {
+            warn!("Failed to create classroom: {}", e);
+            delete_photo(&state, &photo_id).await;
+            return Err(AppError::Internal("Failed to create classroom".to_string()));
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to create classroom",
+    };
+
+    state.cache.invalidate_classroom(&classroom.id).await;
+    state
+        .cache
+        .set(CacheKey::Classroom(&classroom.id), &classroom)
+        .await;
+
+    Ok((StatusCode::CREATED, Json(classroom)).into_response())
+}
+
+async fn insert_classroom(
+    db: &DatabaseConnection,
+    audit: &AuditContext,
+    new_classroom: classroom::ActiveModel,
+) -> Result<classroom::Model, DbErr> {
+    let txn = db.begin().await?;
+    let classroom = new_classroom.insert(&txn).await?;
+    classroom_history::record(&txn, None, &classroom).await?;
+    audit
+        .record(
+            &txn,
+            Target::Classroom,
+            &classroom.id,
+            "create",
+            None,
+            Some(snapshot(&classroom)),
         )
-            .into_response(),
+        .await?;
+    txn.commit().await?;
+    Ok(classroom)
+}
+
+/// Removes a photo from the image service, undoing an upload whose
+/// classroom could not be saved. Failures are only logged.
+async fn delete_photo(state: &AppState, photo_id: &str) {
+    let request = IMAGE_SERVICE_CLIENT
+        .delete(format!("{}/{}", state.config.image_service.url, photo_id))
+        .header("key", state.config.image_service.api_key.clone())
+        .send();
+    match measure(Dependency::ImageService, request).await {
+        Ok(resp) if resp.status().is_success() || resp.status() == StatusCode::NOT_FOUND => {}
+        Ok(resp) => warn!(
+            "Failed to delete orphaned photo {}: image service returned {}",
+            photo_id,
+            resp.status()
+        ),
+        Err(e) => warn!("Failed to delete orphaned photo {}: {}", photo_id, e),
     }
 }
 
 #[utoipa::path(
     get,
     tags = ["Classroom"],
-    description = "Get list of classroom",
+    description = "Get list of classroom. `name` and `description` are translated to the language asked for with `Accept-Language` (en or zh-TW) where a translation exists, except when picking columns with `fields`.",
     path = "",
+    params(
+        ("fields" = Option<String>, Query, description = "Comma-separated columns to return, e.g. `id,name,status`; `id` is always included"),
+        ("Accept-Language" = Option<String>, Header, description = "Preferred language, e.g. `zh-TW` or `en;q=0.8`"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a list fetched before; answered with 304 while it is unchanged")
+    ),
     responses(
-        (status = 200, description = "List of classrooms", body = Vec<classroom::Model>),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 200, description = "List of classrooms with their key counts and next free slot; rows picked with `fields` have neither", body = Vec<ClassroomListItem>),
+        (status = 304, description = "The list is unchanged since the `ETag` sent in `If-None-Match`"),
+        (status = 400, description = "Unknown field", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
-pub async fn list_classrooms(State(state): State<AppState>) -> impl IntoResponse {
-    // Clone connection once for this handler
-    let mut redis = state.redis.clone();
-
-    // Try to get from cache first
-    let cached_classrooms: Option<String> =
-        match redis.get_ex(CLASSROOMS_LIST_KEY, REDIS_EXPIRY).await {
-            Ok(classrooms) => classrooms,
-            Err(e) => {
-                warn!("Failed to get classrooms list from Redis cache: {}", e);
-                None
-            }
+pub async fn list_classrooms(
+    State(state): State<AppState>,
+    AcceptLanguage(locale): AcceptLanguage,
+    headers: HeaderMap,
+    Query(query): Query<ListClassroomsQuery>,
+) -> Result<Response, AppError> {
+    // Projections skip the cache, which holds whole classrooms
+    if let Some(fields) = query.fields.as_deref() {
+        let columns = match fields::parse(fields, classroom::Column::Id) {
+            Ok(columns) => columns,
+            Err(message) => return Err(AppError::BadRequest(message)),
+        };
+        let find_query = classroom::Entity::find().filter(classroom::Column::DeletedAt.is_null());
+        return match fields::all(&state.db, find_query, Some(&columns)).await {
+            Ok(classrooms) => Ok(json_with_etag(&headers, &classrooms)),
+            Err(_) => Err(AppError::Internal("Failed to fetch classrooms".to_string())),
         };
-
-    if let Some(classrooms_str) = cached_classrooms {
-        if let Ok(classrooms) = serde_json::from_str::<Vec<classroom::Model>>(&classrooms_str) {
-            return (StatusCode::OK, Json(classrooms)).into_response();
-        }
     }
 
-    // Fallback to database
-    match classroom::Entity::find().all(&state.db).await {
-        Ok(classrooms) => {
-            // Cache the result for future requests
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    CLASSROOMS_LIST_KEY,
-                    serde_json::to_string(&classrooms).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache classrooms list in Redis: {}", e);
-            }
-            (StatusCode::OK, Json(classrooms)).into_response()
+    let classrooms = match state
+        .cache
+        .get_or_load(CacheKey::ClassroomList, || {
+            classroom::Entity::find()
+                .filter(classroom::Column::DeletedAt.is_null())
+                .all(&state.db)
+        })
+        .await
+    {
+        Ok(classrooms) => classrooms,
+        Err(_) => return Err(AppError::Internal("Failed to fetch classrooms".to_string())),
+    };
+
+    // Key counts and free slots change too often to cache with the list
+    let now = Utc::now().with_timezone(&campus_offset());
+    let mut overviews = match load_overviews(&state.db, &classrooms, now).await {
+        Ok(overviews) => overviews,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch classroom availability".to_string(),
+            ));
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch classrooms",
-        )
-            .into_response(),
-    }
+    };
+    let items: Vec<ClassroomListItem> = classrooms
+        .localized(locale)
+        .into_iter()
+        .map(|classroom| ClassroomListItem {
+            overview: overviews.remove(&classroom.id).unwrap_or_default(),
+            classroom,
+        })
+        .collect();
+    Ok(json_with_etag(&headers, &items))
 }
 
 #[utoipa::path(
     get,
     tags = ["Classroom"],
-    description = "Get classroom by ID with optional related data.",
+    description = "Get classroom by ID with optional related data. `name` and `description` are translated to the language asked for with `Accept-Language` (en or zh-TW) where a translation exists.",
     path = "/{id}",
     params(
         ("id" = String, Path, description = "Classroom ID"),
         ("with_keys" = Option<bool>, Query),
-        ("with_reservations" = Option<bool>, Query)
+        ("with_reservations" = Option<bool>, Query),
+        ("Accept-Language" = Option<String>, Header, description = "Preferred language, e.g. `zh-TW` or `en;q=0.8`")
     ),
     responses(
         (status = 200, body = GetClassroomResponse),
-        (status = 404, description = "Classroom not found"),
-        (status = 500, description = "Internal server error"),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn get_classroom(
     Query(query): Query<GetClassroomQuery>,
     State(state): State<AppState>,
+    AcceptLanguage(locale): AcceptLanguage,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let GetClassroomQuery {
         with_keys,
         with_reservations,
     } = query;
 
-    // Clone connection once for this handler
-    let mut redis = state.redis.clone();
-
     // Determine cache key based on query parameters
     let cache_key = match (with_keys, with_reservations) {
-        (Some(true), Some(true)) => classroom_with_keys_and_reservations_key(&id),
-        (Some(true), _) => classroom_with_keys_key(&id),
-        (_, Some(true)) => classroom_with_reservations_key(&id),
-        _ => classroom_key(&id),
+        (Some(true), Some(true)) => CacheKey::ClassroomWithKeysAndReservations(&id),
+        (Some(true), _) => CacheKey::ClassroomWithKeys(&id),
+        (_, Some(true)) => CacheKey::ClassroomWithReservations(&id),
+        _ => CacheKey::Classroom(&id),
     };
 
     // Try to get from cache first
-    let cached_data: Option<String> = match redis.get_ex(&cache_key, REDIS_EXPIRY).await {
-        Ok(data) => data,
-        Err(e) => {
-            warn!("Failed to get classroom {} from Redis cache: {}", id, e);
-            None
-        }
-    };
-
-    if let Some(data_str) = cached_data {
-        // Try to parse as the appropriate response type
-        if let Ok(response) = serde_json::from_str::<serde_json::Value>(&data_str) {
-            return (StatusCode::OK, Json(response)).into_response();
-        }
+    if let Some(response) = state.cache.get::<serde_json::Value>(cache_key).await {
+        return Ok((StatusCode::OK, Json(localize_response(response, locale))).into_response());
+    }
+    // IDs found not to exist are answered without asking the database again
+    if state
+        .cache
+        .is_missing(CacheKey::MissingClassroom(&id))
+        .await
+    {
+        return Err(AppError::NotFound("Classroom not found".to_string()));
     }
 
     // Fallback to database
@@ -333,21 +484,14 @@ pub async fn get_classroom(
                                 "reservations": reservations,
                             });
                             // Cache the response
-                            let _: Result<(), redis::RedisError> = redis
-                                .set_options(
-                                    &cache_key,
-                                    serde_json::to_string(&response).unwrap(),
-                                    get_redis_set_options(),
-                                )
-                                .await;
-                            return (StatusCode::OK, Json(response)).into_response();
+                            state.cache.set(cache_key, &response).await;
+                            return Ok((StatusCode::OK, Json(localize_response(response, locale)))
+                                .into_response());
                         }
                         _ => {
-                            return (
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                "Failed to fetch classroom with keys and reservations",
-                            )
-                                .into_response();
+                            return Err(AppError::Internal(
+                                "Failed to fetch classroom with keys and reservations".to_string(),
+                            ));
                         }
                     }
                 }
@@ -363,21 +507,14 @@ pub async fn get_classroom(
                                 "keys": keys,
                             });
                             // Cache the response
-                            let _: Result<(), redis::RedisError> = redis
-                                .set_options(
-                                    &cache_key,
-                                    serde_json::to_string(&response).unwrap(),
-                                    get_redis_set_options(),
-                                )
-                                .await;
-                            return (StatusCode::OK, Json(response)).into_response();
+                            state.cache.set(cache_key, &response).await;
+                            return Ok((StatusCode::OK, Json(localize_response(response, locale)))
+                                .into_response());
                         }
                         Err(_) => {
-                            return (
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                "Failed to fetch classroom with keys",
-                            )
-                                .into_response();
+                            return Err(AppError::Internal(
+                                "Failed to fetch classroom with keys".to_string(),
+                            ));
                         }
                     }
                 }
@@ -393,47 +530,51 @@ pub async fn get_classroom(
                                 "reservations": reservations,
                             });
                             // Cache the response
-                            let _: Result<(), redis::RedisError> = redis
-                                .set_options(
-                                    &cache_key,
-                                    serde_json::to_string(&response).unwrap(),
-                                    get_redis_set_options(),
-                                )
-                                .await;
-                            return (StatusCode::OK, Json(response)).into_response();
+                            state.cache.set(cache_key, &response).await;
+                            return Ok((StatusCode::OK, Json(localize_response(response, locale)))
+                                .into_response());
                         }
                         Err(_) => {
-                            return (
-                                StatusCode::INTERNAL_SERVER_ERROR,
-                                "Failed to fetch classroom with reservations",
-                            )
-                                .into_response();
+                            return Err(AppError::Internal(
+                                "Failed to fetch classroom with reservations".to_string(),
+                            ));
                         }
                     }
                 }
                 _ => {
                     // Cache the basic classroom
-                    let result: Result<(), redis::RedisError> = redis
-                        .set_options(
-                            &cache_key,
-                            serde_json::to_string(&classroom).unwrap(),
-                            get_redis_set_options(),
-                        )
-                        .await;
-                    if let Err(e) = result {
-                        warn!("Failed to cache classroom {} in Redis: {}", id, e);
-                    }
-                    (StatusCode::OK, Json(classroom)).into_response()
+                    state.cache.set(cache_key, &classroom).await;
+                    Ok((StatusCode::OK, Json(classroom.localized(locale))).into_response())
                 }
             }
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Classroom not found").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch classroom",
-        )
-            .into_response(),
+        Ok(None) => {
+            state
+                .cache
+                .mark_missing(CacheKey::MissingClassroom(&id))
+                .await;
+            Err(AppError::NotFound("Classroom not found".to_string()))
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch classroom".to_string())),
+    }
+}
+
+/// Translates the classroom in a cached or freshly built `get_classroom`
+/// response, which is either the classroom itself or has it under
+/// `classroom`.
+fn localize_response(mut response: serde_json::Value, locale: Option<Locale>) -> serde_json::Value {
+    let Some(locale) = locale else {
+        return response;
+    };
+    let target = match response.get_mut("classroom") {
+        Some(classroom) => classroom,
+        None => &mut response,
+    };
+    if let Ok(mut classroom) = serde_json::from_value::<classroom::Model>(target.clone()) {
+        classroom.localize(locale);
+        *target = serde_json::to_value(classroom).unwrap();
     }
+    response
 }
 
 // =========================
@@ -443,73 +584,245 @@ pub async fn get_classroom(
 #[utoipa::path(
     put,
     tags = ["Classroom"],
-    description = "Update classroom",
+    description = "Replace a classroom's details; every field is required. Use PATCH to change single fields.",
     path = "/{id}",
     request_body(content = UpdateClassroomBody, content_type = "application/json"),
     responses(
         (status = 200, description = "Classroom updated successfully", body = classroom::Model),
-        (status = 404, description = "Classroom not found"),
-        (status = 500, description = "Failed to update classroom")
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 409, description = "Someone else saved the classroom since `version`; `details.current_version` is the version now", body = ErrorResponse),
+        (status = 500, description = "Failed to update classroom", body = ErrorResponse)
     )
 )]
 pub async fn update_classroom(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
     Json(body): Json<UpdateClassroomBody>,
-) -> impl IntoResponse {
-    match classroom::Entity::find_by_id(id).one(&state.db).await {
+) -> Result<Response, AppError> {
+    let changes = ClassroomChanges {
+        name: Some(body.name),
+        capacity: Some(body.capacity),
+        location: Some(body.location),
+        description: Some(body.description),
+        name_en: Some(body.name_en),
+        name_zh_tw: Some(body.name_zh_tw),
+        description_en: Some(body.description_en),
+        description_zh_tw: Some(body.description_zh_tw),
+        requires_double_approval: Some(body.requires_double_approval),
+        version: body.version,
+    };
+    apply_classroom_changes(&state, &audit, id, changes).await
+}
+
+#[utoipa::path(
+    patch,
+    tags = ["Classroom"],
+    description = "Partially update a classroom with a JSON Merge Patch: left-out fields are untouched. No field can be cleared, so `null` is rejected.",
+    path = "/{id}",
+    request_body(content = PatchClassroomBody, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Classroom updated successfully", body = classroom::Model),
+        (status = 400, description = "A field was set to null", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 409, description = "Someone else saved the classroom since `version`; `details.current_version` is the version now", body = ErrorResponse),
+        (status = 500, description = "Failed to update classroom", body = ErrorResponse)
+    )
+)]
+pub async fn patch_classroom(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    Json(body): Json<PatchClassroomBody>,
+) -> Result<Response, AppError> {
+    let changes = match (
+        body.name.required("name"),
+        body.capacity.required("capacity"),
+        body.location.required("location"),
+        body.description.required("description"),
+        body.requires_double_approval
+            .required("requires_double_approval"),
+    ) {
+        (Ok(name), Ok(capacity), Ok(location), Ok(description), Ok(requires_double_approval)) => {
+            ClassroomChanges {
+                name,
+                capacity,
+                location,
+                description,
+                name_en: body.name_en.optional(),
+                name_zh_tw: body.name_zh_tw.optional(),
+                description_en: body.description_en.optional(),
+                description_zh_tw: body.description_zh_tw.optional(),
+                requires_double_approval,
+                version: body.version,
+            }
+        }
+        (Err(message), ..)
+        | (_, Err(message), ..)
+        | (_, _, Err(message), ..)
+        | (.., Err(message), _)
+        | (.., Err(message)) => {
+            return Err(AppError::BadRequest(message));
+        }
+    };
+    apply_classroom_changes(&state, &audit, id, changes).await
+}
+
+async fn apply_classroom_changes(
+    state: &AppState,
+    audit: &AuditContext,
+    id: String,
+    changes: ClassroomChanges,
+) -> Result<Response, AppError> {
+    match classroom::Entity::find_by_id(id)
+        .filter(classroom::Column::DeletedAt.is_null())
+        .one(&state.db)
+        .await
+    {
         Ok(Some(classroom_model)) => {
+            versioning::check(changes.version, classroom_model.version)?;
+            let previous = classroom_model.clone();
             let mut classroom: classroom::ActiveModel = classroom_model.into();
 
-            classroom.name = Set(body.name);
-            classroom.capacity = Set(body.capacity);
-            classroom.location = Set(body.location);
-            classroom.description = Set(body.description);
+            if let Some(name) = changes.name {
+                classroom.name = Set(name);
+            }
+            if let Some(capacity) = changes.capacity {
+                classroom.capacity = Set(capacity);
+            }
+            if let Some(location) = changes.location {
+                classroom.location = Set(location);
+            }
+            if let Some(description) = changes.description {
+                classroom.description = Set(description);
+            }
+            if let Some(name_en) = changes.name_en {
+                classroom.name_en = Set(name_en);
+            }
+            if let Some(name_zh_tw) = changes.name_zh_tw {
+                classroom.name_zh_tw = Set(name_zh_tw);
+            }
+            if let Some(description_en) = changes.description_en {
+                classroom.description_en = Set(description_en);
+            }
+            if let Some(description_zh_tw) = changes.description_zh_tw {
+                classroom.description_zh_tw = Set(description_zh_tw);
+            }
+            if let Some(requires_double_approval) = changes.requires_double_approval {
+                classroom.requires_double_approval = Set(requires_double_approval);
+            }
 
-            match classroom.update(&state.db).await {
+            match versioning::update(
+                &state.db,
+                classroom,
+                classroom::Column::Version,
+                Some(previous.version),
+            )
+            .await
+            {
                 Ok(updated) => {
-                    // Update cache and invalidate related caches
-                    let mut redis = state.redis.clone();
-                    let result: Result<(), redis::RedisError> = redis
-                        .set_options(
-                            classroom_key(&updated.id),
-                            serde_json::to_string(&updated).unwrap(),
-                            get_redis_set_options(),
-                        )
-                        .await;
-                    if let Err(e) = result {
+                    if let Err(e) =
+                        classroom_history::record(&state.db, Some(&previous), &updated).await
+                    {
                         warn!(
-                            "Failed to update cache for classroom {} in Redis: {}",
+                            "Failed to record history of classroom {}: {}",
                             updated.id, e
                         );
                     }
-                    // Invalidate all related caches for this classroom
-                    let _: Result<(), redis::RedisError> =
-                        redis.del(classroom_with_keys_key(&updated.id)).await;
-                    let _: Result<(), redis::RedisError> = redis
-                        .del(classroom_with_reservations_key(&updated.id))
+                    audit
+                        .log(
+                            &state.db,
+                            Target::Classroom,
+                            &updated.id,
+                            "update",
+                            Some(snapshot(&previous)),
+                            Some(snapshot(&updated)),
+                        )
                         .await;
-                    let _: Result<(), redis::RedisError> = redis
-                        .del(classroom_with_keys_and_reservations_key(&updated.id))
+                    state.cache.invalidate_classroom(&updated.id).await;
+                    state
+                        .cache
+                        .set(CacheKey::Classroom(&updated.id), &updated)
                         .await;
-                    // Invalidate classrooms list cache
-                    let _: Result<(), redis::RedisError> = redis.del(CLASSROOMS_LIST_KEY).await;
 
-                    (StatusCode::OK, Json(updated)).into_response()
+                    Ok((StatusCode::OK, Json(updated)).into_response())
                 }
-                Err(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to update classroom",
-                )
-                    .into_response(),
+                Err(DbErr::RecordNotUpdated) => Err(versioning::stale(None)),
+                Err(_) => Err(AppError::Internal("Failed to update classroom".to_string())),
             }
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Classroom not found").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update classroom",
+        Ok(None) => Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => Err(AppError::Internal("Failed to update classroom".to_string())),
+    }
+}
+
+// =========================
+//   CLASSROOM AS OF
+// =========================
+
+#[derive(Deserialize, ToSchema)]
+pub struct ClassroomAsOfQuery {
+    /// ISO8601 time to look at
+    pub timestamp: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClassroomAsOf {
+    pub classroom: classroom::Model,
+    /// When this configuration took effect; `None` when the classroom has
+    /// not changed since its configuration started being versioned
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub valid_from: Option<DateTimeWithTimeZone>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Admin: the classroom's configuration (capacity, status, description, deletion) as it was at `timestamp`, e.g. when looking into an old infraction.",
+    path = "/{id}/as-of",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        ("timestamp" = String, Query, description = "ISO8601 time to look at")
+    ),
+    responses(
+        (status = 200, description = "Classroom as it was at the time", body = ClassroomAsOf),
+        (status = 400, description = "Invalid timestamp", body = ErrorResponse),
+        (status = 404, description = "Classroom not found or not created yet at that time", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch classroom history", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_classroom_as_of(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ClassroomAsOfQuery>,
+) -> Result<Response, AppError> {
+    let Ok(timestamp) = parse_dt(&query.timestamp) else {
+        return Err(AppError::BadRequest("Invalid timestamp".to_string()));
+    };
+    let current = match classroom::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(current)) => current,
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    };
+    match classroom_history::as_of(&state.db, current, timestamp).await {
+        Ok(Some((classroom, valid_from))) => Ok((
+            StatusCode::OK,
+            Json(ClassroomAsOf {
+                classroom,
+                valid_from,
+            }),
         )
-            .into_response(),
+            .into_response()),
+        Ok(None) => Err(AppError::NotFound(
+            "Classroom did not exist at that time".to_string(),
+        )),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch classroom history".to_string(),
+        )),
     }
 }
 
@@ -531,28 +844,30 @@ pub async fn update_classroom(
     ),
     responses(
         (status = 200, description = "Photo updated successfully", body = classroom::Model),
-        (status = 404, description = "Classroom not found"),
-        (status = 500, description = "Failed to update classroom photo")
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 503, description = "Too many uploads in progress, retry later", body = ErrorResponse),
+        (status = 500, description = "Failed to update classroom photo", body = ErrorResponse)
     )
 )]
 pub async fn update_classroom_photo(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
     TypedMultipart(UpdateClassroomPhotoBody { photo }): TypedMultipart<UpdateClassroomPhotoBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let Some(classroom_model) = classroom::Entity::find_by_id(id)
         .one(&state.db)
         .await
         .unwrap_or(None)
     else {
-        return (StatusCode::NOT_FOUND, "Classroom not found").into_response();
+        return Err(AppError::NotFound("Classroom not found".to_string()));
     };
 
     let current_photo_id = &classroom_model.photo_id;
 
-    let base_url = IMAGE_SERVICE_IP.get().unwrap().clone();
-    let key = IMAGE_SERVICE_API_KEY.get().unwrap().clone();
-    let client = IMAGE_SERVICE_CLIENT.get().unwrap().clone();
+    let base_url = state.config.image_service.url.clone();
+    let key = state.config.image_service.api_key.clone();
+    let client = IMAGE_SERVICE_CLIENT.clone();
 
     let form = multipart::Form::new().part(
         "image",
@@ -561,57 +876,170 @@ pub async fn update_classroom_photo(
 
     let url = format!("{}/{}", base_url, current_photo_id);
 
-    let upload_result = client
-        .put(url)
-        .multipart(form)
-        .header("key", key)
-        .send()
-        .await;
+    let upload_result = measure(
+        Dependency::ImageService,
+        client.put(url).multipart(form).header("key", key).send(),
+    )
+    .await;
 
     match upload_result {
         Ok(resp) => {
             if resp.status().is_success() {
-                // Update cache and invalidate related caches
-                let mut redis = state.redis.clone();
-                let result: Result<(), redis::RedisError> = redis
-                    .set_options(
-                        classroom_key(&classroom_model.id),
-                        serde_json::to_string(&classroom_model).unwrap(),
-                        get_redis_set_options(),
-                    )
-                    .await;
-                if let Err(e) = result {
-                    warn!(
-                        "Failed to update cache for classroom {} in Redis: {}",
-                        classroom_model.id, e
-                    );
-                }
-                // Invalidate all related caches for this classroom
-                let _: Result<(), redis::RedisError> = redis
-                    .del(classroom_with_keys_key(&classroom_model.id))
-                    .await;
-                let _: Result<(), redis::RedisError> = redis
-                    .del(classroom_with_reservations_key(&classroom_model.id))
-                    .await;
-                let _: Result<(), redis::RedisError> = redis
-                    .del(classroom_with_keys_and_reservations_key(
+                // The photo keeps its ID, so the row itself is unchanged
+                audit
+                    .log(
+                        &state.db,
+                        Target::Classroom,
                         &classroom_model.id,
-                    ))
+                        "photo_update",
+                        None,
+                        None,
+                    )
                     .await;
-                // Invalidate classrooms list cache
-                let _: Result<(), redis::RedisError> = redis.del(CLASSROOMS_LIST_KEY).await;
+                state.cache.invalidate_classroom(&classroom_model.id).await;
 
-                (StatusCode::OK, Json(classroom_model)).into_response()
+                Ok((StatusCode::OK, Json(classroom_model)).into_response())
             } else {
-                (StatusCode::BAD_REQUEST, resp.text().await.unwrap()).into_response()
+                Err(AppError::BadRequest(resp.text().await.unwrap()))
             }
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to upload new photo",
+        Err(_) => Err(AppError::Internal("Failed to upload new photo".to_string())),
+    }
+}
+
+// =========================
+//   GET CLASSROOM PHOTO
+// =========================
+
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Stream the classroom photo from the image service. Responses carry Cache-Control and an ETag; send If-None-Match to get 304 when the photo is unchanged.",
+    path = "/{id}/photo",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
+    ),
+    responses(
+        (status = 200, description = "Photo bytes", content_type = "image/*"),
+        (status = 304, description = "Photo unchanged"),
+        (status = 404, description = "Classroom or photo not found", body = ErrorResponse),
+        (status = 502, description = "Image service unavailable", body = ErrorResponse)
+    )
+)]
+pub async fn get_classroom_photo(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let Some(classroom_model) = classroom::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .unwrap_or(None)
+        .filter(|c| c.deleted_at.is_none())
+    else {
+        return Err(AppError::NotFound("Classroom not found".to_string()));
+    };
+
+    let base_url = state.config.image_service.url.clone();
+    let key = state.config.image_service.api_key.clone();
+    let client = IMAGE_SERVICE_CLIENT.clone();
+
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+
+    let mut request = client
+        .get(format!("{}/{}", base_url, classroom_model.photo_id))
+        .header("key", key);
+    if let Some(value) = &if_none_match {
+        request = request.header(header::IF_NONE_MATCH, value);
+    }
+
+    let resp = match measure(Dependency::ImageService, request.send()).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            warn!(
+                "Failed to fetch photo {} from image service: {}",
+                classroom_model.photo_id, e
+            );
+            return Err(AppError::BadGateway(
+                "Image service unavailable".to_string(),
+            ));
+        }
+    };
+
+    // Prefer the image service's own validator; otherwise derive one from the
+    // photo id and the upstream size/modification time, which change when the
+    // photo is replaced in place.
+    let upstream_header = |name| {
+        resp.headers()
+            .get(name)
+            .and_then(|v: &HeaderValue| v.to_str().ok())
+            .map(str::to_string)
+    };
+    let last_modified = upstream_header(header::LAST_MODIFIED);
+    let etag = upstream_header(header::ETAG).unwrap_or_else(|| {
+        let version = last_modified
+            .clone()
+            .or_else(|| resp.content_length().map(|len| len.to_string()))
+            .unwrap_or_default();
+        format!("W/\"{}-{}\"", classroom_model.photo_id, version)
+    });
+    let cache_control = format!("public, max-age={}", PHOTO_CACHE_MAX_AGE_SECONDS);
+
+    let not_modified = resp.status() == StatusCode::NOT_MODIFIED
+        || if_none_match.is_some_and(|value| etag_matches(&value, &etag));
+    if not_modified {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, cache_control)],
         )
-            .into_response(),
+            .into_response());
+    }
+
+    match resp.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => {
+            return Err(AppError::NotFound("Photo not found".to_string()));
+        }
+        status => {
+            warn!(
+                "Image service returned {} for photo {}",
+                status, classroom_model.photo_id
+            );
+            return Err(AppError::BadGateway(
+                "Image service unavailable".to_string(),
+            ));
+        }
     }
+
+    let content_type = upstream_header(header::CONTENT_TYPE)
+        .unwrap_or_else(|| "application/octet-stream".to_string());
+    let mut response_headers = HeaderMap::new();
+    for (name, value) in [
+        (header::CONTENT_TYPE, Some(content_type)),
+        (header::ETAG, Some(etag)),
+        (header::CACHE_CONTROL, Some(cache_control)),
+        (header::LAST_MODIFIED, last_modified),
+    ] {
+        if let Some(value) = value
+            && let Ok(value) = HeaderValue::from_str(&value)
+        {
+            response_headers.insert(name, value);
+        }
+    }
+    if let Some(len) = resp.content_length() {
+        response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+    }
+
+    Ok((
+        StatusCode::OK,
+        response_headers,
+        Body::from_stream(resp.bytes_stream()),
+    )
+        .into_response())
 }
 
 // =========================
@@ -621,101 +1049,284 @@ pub async fn update_classroom_photo(
 #[utoipa::path(
     delete,
     tags = ["Classroom"],
-    description = "Delete classroom",
+    description = "Soft-delete classroom; it disappears from listings and can no longer be booked",
     path = "/{id}",
     responses(
         (status = 200, description = "Classroom deleted successfully"),
-        (status = 404, description = "Classroom not found"),
-        (status = 500, description = "Failed to delete classroom")
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to delete classroom", body = ErrorResponse)
     )
 )]
 pub async fn delete_classroom(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let classroom_model = match classroom::Entity::find_by_id(id).one(&state.db).await {
         Ok(Some(c)) => c,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Classroom not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch classroom",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
         }
     };
 
-    let photo_id = &classroom_model.photo_id;
+    if classroom_model.deleted_at.is_some() {
+        return Err(AppError::NotFound("Classroom not found".to_string()));
+    }
 
-    let base_url = IMAGE_SERVICE_IP.get().unwrap().clone();
-    let key = IMAGE_SERVICE_API_KEY.get().unwrap().clone();
-    let client = IMAGE_SERVICE_CLIENT.get().unwrap().clone();
+    // Soft delete keeps the row (and its photo) so reservation history stays
+    // intact and the classroom can be restored later
+    let previous = classroom_model.clone();
+    let mut classroom: classroom::ActiveModel = classroom_model.into();
+    classroom.deleted_at = Set(Some(Utc::now().fixed_offset()));
 
-    let image_delete_url = format!("{}/{}", base_url, photo_id);
+    match versioning::update(&state.db, classroom, classroom::Column::Version, None).await {
+        Ok(deleted) => {
+            if let Err(e) = classroom_history::record(&state.db, Some(&previous), &deleted).await {
+                warn!(
+                    "Failed to record history of classroom {}: {}",
+                    deleted.id, e
+                );
+            }
+            audit
+                .log(
+                    &state.db,
+                    Target::Classroom,
+                    &deleted.id,
+                    "delete",
+                    Some(snapshot(&previous)),
+                    Some(snapshot(&deleted)),
+                )
+                .await;
+            state.cache.invalidate_classroom(&deleted.id).await;
+            Ok((StatusCode::OK, "Classroom deleted successfully").into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to delete classroom".to_string())),
+    }
+}
 
-    let delete_image_result = client
-        .delete(image_delete_url)
-        .header("key", key)
-        .send()
-        .await;
+// =========================
+//   RESTORE CLASSROOM
+// =========================
 
-    if delete_image_result.is_err() {
-        println!("WARN: Failed to delete classroom image on image server.");
+#[utoipa::path(
+    post,
+    tags = ["Classroom"],
+    description = "Restore a soft-deleted classroom",
+    path = "/{id}/restore",
+    params(
+        ("id" = String, Path, description = "Classroom ID")
+    ),
+    responses(
+        (status = 200, description = "Classroom restored successfully", body = classroom::Model),
+        (status = 400, description = "Classroom is not deleted", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to restore classroom", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn restore_classroom(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let classroom_model = match classroom::Entity::find_by_id(id).one(&state.db).await {
+        Ok(Some(c)) => c,
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    };
+
+    if classroom_model.deleted_at.is_none() {
+        return Err(AppError::BadRequest("Classroom is not deleted".to_string()));
     }
 
-    // Save classroom ID before deleting (delete consumes the model)
-    let classroom_id = classroom_model.id.clone();
-
-    match classroom_model.delete(&state.db).await {
-        Ok(_) => {
-            // Invalidate all caches for this classroom
-            let mut redis = state.redis.clone();
-            let _: Result<(), redis::RedisError> = redis.del(classroom_key(&classroom_id)).await;
-            let _: Result<(), redis::RedisError> =
-                redis.del(classroom_with_keys_key(&classroom_id)).await;
-            let _: Result<(), redis::RedisError> = redis
-                .del(classroom_with_reservations_key(&classroom_id))
-                .await;
-            let _: Result<(), redis::RedisError> = redis
-                .del(classroom_with_keys_and_reservations_key(&classroom_id))
+    let previous = classroom_model.clone();
+    let mut classroom: classroom::ActiveModel = classroom_model.into();
+    classroom.deleted_at = Set(None);
+
+    match versioning::update(&state.db, classroom, classroom::Column::Version, None).await {
+        Ok(restored) => {
+            if let Err(e) = classroom_history::record(&state.db, Some(&previous), &restored).await {
+                warn!(
+                    "Failed to record history of classroom {}: {}",
+                    restored.id, e
+                );
+            }
+            audit
+                .log(
+                    &state.db,
+                    Target::Classroom,
+                    &restored.id,
+                    "restore",
+                    Some(snapshot(&previous)),
+                    Some(snapshot(&restored)),
+                )
                 .await;
-            // Invalidate classrooms list cache
-            let _: Result<(), redis::RedisError> = redis.del(CLASSROOMS_LIST_KEY).await;
+            state.cache.invalidate_classroom(&restored.id).await;
+            Ok((StatusCode::OK, Json(restored)).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to restore classroom".to_string(),
+        )),
+    }
+}
+
+// =========================
+//   KEY SUMMARY
+// =========================
+
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Key counts for a classroom (total, available, out, lost, inactive). Served from a cache refreshed on every key transition.",
+    path = "/{id}/key-summary",
+    params(
+        ("id" = String, Path, description = "Classroom ID")
+    ),
+    responses(
+        (status = 200, description = "Key summary", body = KeySummary),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch key summary", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_classroom_key_summary(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let summary = match classroom_summary(&state, &id).await {
+        Ok(summary) => summary,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch key summary".to_string(),
+            ));
+        }
+    };
 
-            (StatusCode::OK, "Classroom deleted successfully").into_response()
+    // An empty summary is also what an unknown id looks like
+    if summary.total == 0 {
+        match classroom::Entity::find_by_id(&id).one(&state.db).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+            }
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to delete classroom",
-        )
-            .into_response(),
     }
+
+    Ok((StatusCode::OK, Json(summary)).into_response())
 }
 
-pub fn classroom_router(
-    image_service_url: String,
-    image_service_api_key: String,
-) -> Router<AppState> {
-    IMAGE_SERVICE_IP
-        .set(image_service_url)
-        .expect("IMAGE_SERVICE_IP already set");
-    IMAGE_SERVICE_API_KEY
-        .set(image_service_api_key)
-        .expect("IMAGE_SERVICE_API_KEY already set");
-    let client_arc = Arc::new(Client::new());
-    IMAGE_SERVICE_CLIENT
-        .set(client_arc)
-        .expect("IMAGE_SERVICE_CLIENT already set");
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Classroom", description = "Classroom endpoints")
+    ),
+    paths(
+        create_classroom,
+        get_classroom,
+        list_classrooms,
+        update_classroom,
+        patch_classroom,
+        update_classroom_photo,
+        get_classroom_photo,
+        get_classroom_key_summary,
+        get_classroom_as_of,
+        crate::routes::classroom_check_in::get_classroom_qrcode,
+        crate::routes::classroom_check_in::check_in_classroom,
+        delete_classroom,
+        restore_classroom,
+        crate::routes::classroom_schedule::get_schedule,
+        crate::routes::classroom_schedule::update_schedule,
+        crate::routes::classroom_schedule::create_closure,
+        crate::routes::classroom_schedule::update_closure,
+        crate::routes::classroom_schedule::delete_closure,
+        crate::routes::classroom_schedule::get_availability,
+        crate::routes::classroom_status::bulk_update_status,
+        crate::routes::classroom_status::update_status,
+        crate::routes::timetable::get_timetable,
+        crate::routes::timetable::import_timetable,
+        crate::routes::timetable::list_collisions,
+        crate::routes::timetable::resolve_collisions
+    ),
+    components(schemas(
+        CreateClassroomBody,
+        crate::entities::classroom::Model,
+        crate::entities::sea_orm_active_enums::ClassroomStatus,
+        GetClassroomResponse,
+        GetClassroomKeyResponse,
+        ClassroomListItem,
+        crate::classroom_overview::ClassroomOverview,
+        GetClassroomReservationResponse,
+        GetClassroomKeyReservationResponse,
+        UpdateClassroomBody,
+        PatchClassroomBody,
+        ListClassroomsQuery,
+        ClassroomAsOf,
+        UpdateClassroomPhotoBody,
+        crate::entities::key::Model,
+        crate::entities::reservation::Model,
+        crate::entities::classroom_schedule::Model,
+        crate::entities::classroom_closure::Model,
+        crate::routes::classroom_schedule::OpeningHoursBody,
+        crate::routes::classroom_schedule::UpdateScheduleBody,
+        crate::routes::classroom_schedule::CreateClosureBody,
+        crate::routes::classroom_schedule::UpdateClosureBody,
+        crate::routes::classroom_schedule::ClassroomScheduleResponse,
+        crate::routes::classroom_schedule::BusySlot,
+        crate::routes::classroom_schedule::AvailabilityResponse,
+        crate::routes::classroom_status::BulkStatusBody,
+        crate::routes::classroom_status::UpdateClassroomStatusBody,
+        crate::routes::classroom_status::ClassroomStatusChangeResponse,
+        crate::routes::classroom_schedule::ClosureCreatedResponse,
+        crate::routes::classroom_schedule::UnavailableDetails,
+        crate::closure_impact::ClosureAction,
+        crate::closure_impact::AffectedReservation,
+        crate::availability::AlternativeRoom,
+        crate::key_lifecycle::KeySummary,
+        crate::routes::classroom_check_in::QrCodeFormat,
+        crate::routes::classroom_check_in::CheckInResponse,
+        crate::entities::course_session::Model,
+        crate::routes::timetable::CourseSessionBody,
+        crate::routes::timetable::ImportTimetableBody,
+        crate::routes::timetable::TimetableImportResponse,
+        crate::routes::timetable::CollisionQuery,
+        crate::routes::timetable::ResolveCollisionsBody,
+        crate::routes::timetable::ResolveCollisionsResponse,
+        crate::timetable::Collision,
+    ))
+)]
+pub struct ClassroomApi;
 
+pub fn classroom_router() -> Router<AppState> {
     let admin_only_route = Router::new()
-        .route("/", post(create_classroom))
-        .route("/{id}", put(update_classroom))
-        .route("/{id}/photo", put(update_classroom_photo))
+        .route(
+            "/",
+            post(create_classroom).layer(middleware::from_fn(limit_photo_uploads)),
+        )
+        .route("/{id}", put(update_classroom).patch(patch_classroom))
+        .route(
+            "/{id}/photo",
+            put(update_classroom_photo).layer(middleware::from_fn(limit_photo_uploads)),
+        )
         .route("/{id}", delete(delete_classroom))
-        .route_layer(permission_required!(AuthBackend, Role::Admin));
+        .route("/{id}/restore", post(restore_classroom))
+        .route("/{id}/key-summary", get(get_classroom_key_summary))
+        .route("/{id}/as-of", get(get_classroom_as_of))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageClassrooms
+        ));
 
     Router::new()
         .route("/", get(list_classrooms))
         .route("/{id}", get(get_classroom))
+        .route("/{id}/photo", get(get_classroom_photo))
         .merge(admin_only_route)
+        .merge(classroom_schedule_router())
+        .merge(classroom_status_router())
+        .merge(classroom_check_in_router())
+        .merge(timetable_router())
 }
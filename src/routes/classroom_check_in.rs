@@ -0,0 +1,275 @@
+use std::io::Cursor;
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::{login_required, permission_required};
+use chrono::Utc;
+use image::{ImageFormat, Luma};
+use qrcode::{QrCode, render::svg};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    check_in::{self, CheckInTokenError, can_check_in, check_in_url},
+    entities::{classroom, reservation, sea_orm_active_enums::ReservationStatus},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::Permission,
+    versioning,
+};
+
+const QR_CODE_MIN_SIZE: u32 = 256;
+
+// ===============================
+//   Query parameters
+// ===============================
+#[derive(Deserialize, ToSchema, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QrCodeFormat {
+    #[default]
+    Png,
+    Svg,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct QrCodeQuery {
+    /// `png` (default) or `svg`
+    pub format: Option<QrCodeFormat>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct CheckInQuery {
+    /// Unix timestamp after which the link stops working
+    pub expires: i64,
+    /// Signature generated with the QR code
+    pub sig: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CheckInResponse {
+    pub reservation: reservation::Model,
+    /// False when the reservation had already been checked into
+    pub newly_checked_in: bool,
+}
+
+// ===============================
+//   Handlers
+// ===============================
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "QR code to post in the classroom. It encodes a signed check-in link that expires after the configured TTL, so regenerate it before then.",
+    path = "/{id}/qrcode",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        QrCodeQuery
+    ),
+    responses(
+        (status = 200, description = "QR code image", content_type = "image/png"),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 503, description = "Check-in is not configured", body = ErrorResponse),
+        (status = 500, description = "Failed to generate QR code", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_classroom_qrcode(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<QrCodeQuery>,
+) -> Result<Response, AppError> {
+    let config = &state.config.check_in;
+    let (Some(secret), Some(base_url)) = (&config.secret, &config.public_base_url) else {
+        return Err(AppError::ServiceUnavailable(
+            "Check-in is not configured".to_string(),
+        ));
+    };
+
+    match classroom::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(c)) if c.deleted_at.is_none() => {}
+        Ok(_) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    }
+
+    let expires = (Utc::now() + config.token_ttl).timestamp();
+    let url = check_in_url(base_url, secret, &id, expires);
+    let code = match QrCode::new(url.as_bytes()) {
+        Ok(code) => code,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to generate QR code".to_string()));
+        }
+    };
+
+    // The signature inside expires, so the image must not be cached
+    match query.format.unwrap_or_default() {
+        QrCodeFormat::Svg => {
+            let image = code
+                .render::<svg::Color>()
+                .min_dimensions(QR_CODE_MIN_SIZE, QR_CODE_MIN_SIZE)
+                .build();
+            Ok((
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "image/svg+xml"),
+                    (header::CACHE_CONTROL, "no-store"),
+                ],
+                image,
+            )
+                .into_response())
+        }
+        QrCodeFormat::Png => {
+            let image = code
+                .render::<Luma<u8>>()
+                .min_dimensions(QR_CODE_MIN_SIZE, QR_CODE_MIN_SIZE)
+                .build();
+            let mut bytes = Vec::new();
+            if image
+                .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+                .is_err()
+            {
+                return Err(AppError::Internal("Failed to generate QR code".to_string()));
+            }
+            Ok((
+                StatusCode::OK,
+                [
+                    (header::CONTENT_TYPE, "image/png"),
+                    (header::CACHE_CONTROL, "no-store"),
+                ],
+                bytes,
+            )
+                .into_response())
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Target of the classroom QR code. Marks the caller's approved reservation in this classroom that is currently running (or starts within the early check-in window) as checked in. Repeated scans are no-ops.",
+    path = "/{id}/check-in",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        CheckInQuery
+    ),
+    responses(
+        (status = 200, description = "Checked in", body = CheckInResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Invalid signature", body = ErrorResponse),
+        (status = 404, description = "No reservation to check into right now", body = ErrorResponse),
+        (status = 410, description = "QR code expired", body = ErrorResponse),
+        (status = 503, description = "Check-in is not configured", body = ErrorResponse),
+        (status = 500, description = "Failed to check in", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn check_in_classroom(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<CheckInQuery>,
+) -> Result<Response, AppError> {
+    let user = match session.user {
+        Some(u) => u,
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+
+    let config = &state.config.check_in;
+    let Some(secret) = &config.secret else {
+        return Err(AppError::ServiceUnavailable(
+            "Check-in is not configured".to_string(),
+        ));
+    };
+
+    let now = Utc::now();
+    match check_in::verify(secret, &id, query.expires, &query.sig, now) {
+        Ok(()) => {}
+        Err(CheckInTokenError::Expired) => {
+            return Err(AppError::Gone("QR code has expired".to_string()));
+        }
+        Err(CheckInTokenError::InvalidSignature) => {
+            return Err(AppError::Forbidden("Invalid check-in link".to_string()));
+        }
+    }
+
+    let candidates = match reservation::Entity::find()
+        .filter(reservation::Column::ClassroomId.eq(&id))
+        .filter(reservation::Column::UserId.eq(&user.id))
+        .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+        .filter(reservation::Column::EndTime.gt(now))
+        .filter(reservation::Column::StartTime.lte(now + config.early_window))
+        .order_by_asc(reservation::Column::StartTime)
+        .all(&state.db)
+        .await
+    {
+        Ok(list) => list,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
+
+    let Some(reservation_model) = candidates
+        .into_iter()
+        .find(|r| can_check_in(r, now, config.early_window))
+    else {
+        return Err(AppError::NotFound(
+            "No reservation to check into in this classroom right now".to_string(),
+        ));
+    };
+
+    if reservation_model.checked_in_at.is_some() {
+        return Ok((
+            StatusCode::OK,
+            Json(CheckInResponse {
+                reservation: reservation_model,
+                newly_checked_in: false,
+            }),
+        )
+            .into_response());
+    }
+
+    let mut active: reservation::ActiveModel = reservation_model.into();
+    active.checked_in_at = Set(Some(now.with_timezone(&campus_offset())));
+
+    match versioning::update(&state.db, active, reservation::Column::Version, None).await {
+        Ok(updated) => {
+            state.cache.invalidate_reservation(&updated).await;
+            Ok((
+                StatusCode::OK,
+                Json(CheckInResponse {
+                    reservation: updated,
+                    newly_checked_in: true,
+                }),
+            )
+                .into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to check in".to_string())),
+    }
+}
+
+pub fn classroom_check_in_router() -> Router<AppState> {
+    let admin_only_route = Router::new()
+        .route("/{id}/qrcode", get(get_classroom_qrcode))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageClassrooms
+        ));
+
+    let login_required_route = Router::new()
+        .route("/{id}/check-in", get(check_in_classroom))
+        .route_layer(login_required!(AuthBackend));
+
+    Router::new()
+        .merge(admin_only_route)
+        .merge(login_required_route)
+}
@@ -0,0 +1,645 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
+};
+use axum_login::permission_required;
+use chrono::{NaiveTime, Utc};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, ModelTrait, QueryFilter, QueryOrder, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::{
+        AlternativeRoom, Unavailability, approved_reservations_in_window, closures_in_window,
+        find_unavailability, suggest_alternatives, within_opening_hours,
+    },
+    closure_impact::{AffectedReservation, ClosureAction, apply_action, find_affected, finish},
+    constants::MAX_ALTERNATIVE_ROOMS,
+    entities::{classroom, classroom_closure, classroom_schedule},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::Permission,
+    utils::parse_dt,
+};
+
+// ===============================
+//   Request / Response bodies
+// ===============================
+#[derive(Deserialize, ToSchema)]
+pub struct OpeningHoursBody {
+    /// 0 = Monday ... 6 = Sunday
+    pub weekday: i16,
+    /// "HH:MM" or "HH:MM:SS" in campus time
+    pub open_time: String,
+    /// "HH:MM" or "HH:MM:SS" in campus time
+    pub close_time: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateScheduleBody {
+    pub hours: Vec<OpeningHoursBody>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateClosureBody {
+    pub start_at: String,
+    pub end_at: String,
+    pub reason: String,
+    /// What to do with pending/approved reservations during the closure
+    #[serde(default)]
+    pub reservation_action: ClosureAction,
+}
+
+/// `details` of the error returned when a classroom cannot be booked for the
+/// requested window.
+#[derive(Serialize, ToSchema)]
+pub struct UnavailableDetails {
+    /// Up to 5 similar classrooms that are free for the same window
+    pub alternatives: Vec<AlternativeRoom>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClosureCreatedResponse {
+    #[serde(flatten)]
+    pub closure: classroom_closure::Model,
+    /// Pending/approved reservations during the closure, after the action was applied
+    pub affected_reservations: Vec<AffectedReservation>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateClosureBody {
+    pub start_at: Option<String>,
+    pub end_at: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClassroomScheduleResponse {
+    pub hours: Vec<classroom_schedule::Model>,
+    pub closures: Vec<classroom_closure::Model>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AvailabilityQuery {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BusySlot {
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub start_time: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub end_time: DateTimeWithTimeZone,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct AvailabilityResponse {
+    pub available: bool,
+    pub within_opening_hours: bool,
+    pub closures: Vec<classroom_closure::Model>,
+    pub busy: Vec<BusySlot>,
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    let raw = s.trim();
+    NaiveTime::parse_from_str(raw, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(raw, "%H:%M"))
+        .ok()
+}
+
+async fn classroom_exists(state: &AppState, id: &str) -> Result<bool, AppError> {
+    match classroom::Entity::find_by_id(id)
+        .filter(classroom::Column::DeletedAt.is_null())
+        .one(&state.db)
+        .await
+    {
+        Ok(found) => Ok(found.is_some()),
+        Err(_) => Err(AppError::Internal("Failed to fetch classroom".to_string())),
+    }
+}
+
+// ===============================
+//   Get Schedule
+// ===============================
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Get weekly opening hours and upcoming closures of a classroom",
+    path = "/{id}/schedule",
+    params(("id" = String, Path, description = "Classroom ID")),
+    responses(
+        (status = 200, description = "Classroom schedule", body = ClassroomScheduleResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch schedule", body = ErrorResponse)
+    )
+)]
+pub async fn get_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    match classroom_exists(&state, &id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(e) => return Err(e),
+    }
+
+    let hours = match classroom_schedule::Entity::find()
+        .filter(classroom_schedule::Column::ClassroomId.eq(&id))
+        .order_by_asc(classroom_schedule::Column::Weekday)
+        .order_by_asc(classroom_schedule::Column::OpenTime)
+        .all(&state.db)
+        .await
+    {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch schedule".to_string()));
+        }
+    };
+
+    let closures = match classroom_closure::Entity::find()
+        .filter(classroom_closure::Column::ClassroomId.eq(&id))
+        .filter(classroom_closure::Column::EndAt.gt(Utc::now()))
+        .order_by_asc(classroom_closure::Column::StartAt)
+        .all(&state.db)
+        .await
+    {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch closures".to_string()));
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(ClassroomScheduleResponse { hours, closures }),
+    )
+        .into_response())
+}
+
+// ===============================
+//   Replace Opening Hours (Admin)
+// ===============================
+#[utoipa::path(
+    put,
+    tags = ["Classroom"],
+    description = "Replace the weekly opening hours of a classroom. An empty list means always open.",
+    path = "/{id}/schedule",
+    request_body(content = UpdateScheduleBody, content_type = "application/json"),
+    params(("id" = String, Path, description = "Classroom ID")),
+    responses(
+        (status = 200, description = "Opening hours updated", body = Vec<classroom_schedule::Model>),
+        (status = 400, description = "Invalid opening hours", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to update opening hours", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn update_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<UpdateScheduleBody>,
+) -> Result<Response, AppError> {
+    match classroom_exists(&state, &id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(e) => return Err(e),
+    }
+
+    let mut new_hours = Vec::with_capacity(body.hours.len());
+    for entry in body.hours {
+        if !(0..=6).contains(&entry.weekday) {
+            return Err(AppError::BadRequest(
+                "Invalid 'weekday', expected 0-6".to_string(),
+            ));
+        }
+        let Some(open_time) = parse_time(&entry.open_time) else {
+            return Err(AppError::BadRequest("Invalid 'open_time'".to_string()));
+        };
+        let Some(close_time) = parse_time(&entry.close_time) else {
+            return Err(AppError::BadRequest("Invalid 'close_time'".to_string()));
+        };
+        if open_time >= close_time {
+            return Err(AppError::BadRequest(
+                "'open_time' must be < 'close_time'".to_string(),
+            ));
+        }
+        new_hours.push(classroom_schedule::ActiveModel {
+            id: Set(nanoid!()),
+            classroom_id: Set(id.clone()),
+            weekday: Set(entry.weekday),
+            open_time: Set(open_time),
+            close_time: Set(close_time),
+        });
+    }
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to update opening hours".to_string(),
+            ));
+        }
+    };
+
+    if classroom_schedule::Entity::delete_many()
+        .filter(classroom_schedule::Column::ClassroomId.eq(&id))
+        .exec(&txn)
+        .await
+        .is_err()
+    {
+        return Err(AppError::Internal(
+            "Failed to update opening hours".to_string(),
+        ));
+    }
+
+    let mut saved = Vec::with_capacity(new_hours.len());
+    for hours in new_hours {
+        match hours.insert(&txn).await {
+            Ok(model) => saved.push(model),
+            Err(_) => {
+                return Err(AppError::Internal(
+                    "Failed to update opening hours".to_string(),
+                ));
+            }
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to update opening hours".to_string(),
+        ));
+    }
+
+    Ok((StatusCode::OK, Json(saved)).into_response())
+}
+
+// ===============================
+//   Create Closure (Admin)
+// ===============================
+#[utoipa::path(
+    post,
+    tags = ["Classroom"],
+    description = "Add a one-off closure (holiday, maintenance) to a classroom. Pending/approved reservations during the closure are flagged, cancelled or sent for rebooking (reservation_action) and their owners notified.",
+    path = "/{id}/schedule/closures",
+    request_body(content = CreateClosureBody, content_type = "application/json"),
+    params(("id" = String, Path, description = "Classroom ID")),
+    responses(
+        (status = 201, description = "Closure created", body = ClosureCreatedResponse),
+        (status = 400, description = "Invalid closure", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to create closure", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn create_closure(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<CreateClosureBody>,
+) -> Result<Response, AppError> {
+    let admin = match session.user {
+        Some(u) => u,
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+
+    match classroom_exists(&state, &id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(e) => return Err(e),
+    }
+
+    let start_at = match parse_dt(&body.start_at) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid start_at".to_string())),
+    };
+    let end_at = match parse_dt(&body.end_at) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid end_at".to_string())),
+    };
+    if start_at >= end_at {
+        return Err(AppError::BadRequest(
+            "'start_at' must be < 'end_at'".to_string(),
+        ));
+    }
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let new_closure = classroom_closure::ActiveModel {
+        id: Set(nanoid!()),
+        classroom_id: Set(id.clone()),
+        start_at: Set(start_at),
+        end_at: Set(end_at),
+        reason: Set(body.reason.clone()),
+        created_by: Set(Some(admin.id)),
+        created_at: NotSet,
+    };
+
+    let closure = match new_closure.insert(&txn).await {
+        Ok(model) => model,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to create closure".to_string()));
+        }
+    };
+
+    let updated = match find_affected(&txn, vec![id], start_at, Some(end_at)).await {
+        Ok(affected) => apply_action(&txn, affected, body.reservation_action, &body.reason).await,
+        Err(e) => Err(e),
+    };
+    let updated = match updated {
+        Ok(updated) => updated,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to update affected reservations".to_string(),
+            ));
+        }
+    };
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal("Failed to create closure".to_string()));
+    }
+
+    let affected_reservations = finish(&state, updated, body.reservation_action, body.reason).await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(ClosureCreatedResponse {
+            closure,
+            affected_reservations,
+        }),
+    )
+        .into_response())
+}
+
+// ===============================
+//   Update Closure (Admin)
+// ===============================
+#[utoipa::path(
+    put,
+    tags = ["Classroom"],
+    description = "Update a classroom closure",
+    path = "/{id}/schedule/closures/{closure_id}",
+    request_body(content = UpdateClosureBody, content_type = "application/json"),
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        ("closure_id" = String, Path, description = "Closure ID")
+    ),
+    responses(
+        (status = 200, description = "Closure updated", body = classroom_closure::Model),
+        (status = 400, description = "Invalid closure", body = ErrorResponse),
+        (status = 404, description = "Closure not found", body = ErrorResponse),
+        (status = 500, description = "Failed to update closure", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn update_closure(
+    State(state): State<AppState>,
+    Path((id, closure_id)): Path<(String, String)>,
+    Json(body): Json<UpdateClosureBody>,
+) -> Result<Response, AppError> {
+    let closure = match classroom_closure::Entity::find_by_id(&closure_id)
+        .filter(classroom_closure::Column::ClassroomId.eq(&id))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(c)) => c,
+        Ok(None) => return Err(AppError::NotFound("Closure not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch closure".to_string()));
+        }
+    };
+
+    let mut start_at = closure.start_at;
+    let mut end_at = closure.end_at;
+    if let Some(s) = body.start_at {
+        start_at = match parse_dt(&s) {
+            Ok(v) => v,
+            Err(_) => return Err(AppError::BadRequest("Invalid start_at".to_string())),
+        };
+    }
+    if let Some(s) = body.end_at {
+        end_at = match parse_dt(&s) {
+            Ok(v) => v,
+            Err(_) => return Err(AppError::BadRequest("Invalid end_at".to_string())),
+        };
+    }
+    if start_at >= end_at {
+        return Err(AppError::BadRequest(
+            "'start_at' must be < 'end_at'".to_string(),
+        ));
+    }
+
+    let mut active: classroom_closure::ActiveModel = closure.into();
+    active.start_at = Set(start_at);
+    active.end_at = Set(end_at);
+    if let Some(reason) = body.reason {
+        active.reason = Set(reason);
+    }
+
+    match active.update(&state.db).await {
+        Ok(model) => Ok((StatusCode::OK, Json(model)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to update closure".to_string())),
+    }
+}
+
+// ===============================
+//   Delete Closure (Admin)
+// ===============================
+#[utoipa::path(
+    delete,
+    tags = ["Classroom"],
+    description = "Delete a classroom closure",
+    path = "/{id}/schedule/closures/{closure_id}",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        ("closure_id" = String, Path, description = "Closure ID")
+    ),
+    responses(
+        (status = 200, description = "Closure deleted"),
+        (status = 404, description = "Closure not found", body = ErrorResponse),
+        (status = 500, description = "Failed to delete closure", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn delete_closure(
+    State(state): State<AppState>,
+    Path((id, closure_id)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    let closure = match classroom_closure::Entity::find_by_id(&closure_id)
+        .filter(classroom_closure::Column::ClassroomId.eq(&id))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(c)) => c,
+        Ok(None) => return Err(AppError::NotFound("Closure not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch closure".to_string()));
+        }
+    };
+
+    match closure.delete(&state.db).await {
+        Ok(_) => Ok((StatusCode::OK, "Closure deleted successfully").into_response()),
+        Err(_) => Err(AppError::Internal("Failed to delete closure".to_string())),
+    }
+}
+
+// ===============================
+//   Availability
+// ===============================
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Check whether a classroom can be booked for a time window, taking opening hours, closures and approved reservations into account",
+    path = "/{id}/availability",
+    params(
+        ("id" = String, Path, description = "Classroom ID"),
+        ("from" = String, Query, description = "Window start, ISO8601 or 'YYYY-MM-DD HH:MM'"),
+        ("to" = String, Query, description = "Window end, ISO8601 or 'YYYY-MM-DD HH:MM'")
+    ),
+    responses(
+        (status = 200, description = "Availability of the classroom", body = AvailabilityResponse),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to check availability", body = ErrorResponse)
+    )
+)]
+pub async fn get_availability(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<AvailabilityQuery>,
+) -> Result<Response, AppError> {
+    let from = match parse_dt(&query.from) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid 'from'".to_string())),
+    };
+    let to = match parse_dt(&query.to) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid 'to'".to_string())),
+    };
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be < 'to'".to_string()));
+    }
+
+    match classroom_exists(&state, &id).await {
+        Ok(true) => {}
+        Ok(false) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(e) => return Err(e),
+    }
+
+    let hours = match classroom_schedule::Entity::find()
+        .filter(classroom_schedule::Column::ClassroomId.eq(&id))
+        .all(&state.db)
+        .await
+    {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to check availability".to_string(),
+            ));
+        }
+    };
+
+    let (closures, reservations) = match (
+        closures_in_window(&state.db, &id, from, to).await,
+        approved_reservations_in_window(&state.db, &id, from, to).await,
+    ) {
+        (Ok(c), Ok(r)) => (c, r),
+        _ => {
+            return Err(AppError::Internal(
+                "Failed to check availability".to_string(),
+            ));
+        }
+    };
+
+    let within_opening_hours = within_opening_hours(&hours, from, to);
+    let busy: Vec<BusySlot> = reservations
+        .into_iter()
+        .map(|r| BusySlot {
+            start_time: r.start_time,
+            end_time: r.end_time,
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(AvailabilityResponse {
+            available: within_opening_hours && closures.is_empty() && busy.is_empty(),
+            within_opening_hours,
+            closures,
+            busy,
+        }),
+    )
+        .into_response())
+}
+
+/// Returns a 4xx error when the classroom cannot be booked for the window.
+pub async fn reject_if_unavailable(
+    state: &AppState,
+    classroom_id: &str,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Option<AppError> {
+    match find_unavailability(&state.db, classroom_id, start, end).await {
+        Ok(None) => None,
+        Ok(Some(reason)) => {
+            let error = match reason {
+                Unavailability::OutsideOpeningHours => AppError::BadRequest(reason.message()),
+                _ => AppError::Conflict(reason.message()),
+            };
+            let alternatives = match classroom::Entity::find_by_id(classroom_id)
+                .one(&state.db)
+                .await
+            {
+                Ok(Some(original)) => {
+                    suggest_alternatives(&state.db, &original, start, end, MAX_ALTERNATIVE_ROOMS)
+                        .await
+                        .unwrap_or_default()
+                }
+                _ => Vec::new(),
+            };
+            Some(error.with_details(UnavailableDetails { alternatives }))
+        }
+        Err(_) => Some(AppError::Internal(
+            "Failed to check classroom availability".to_string(),
+        )),
+    }
+}
+
+pub fn classroom_schedule_router() -> Router<AppState> {
+    let admin_only_route = Router::new()
+        .route("/{id}/schedule", put(update_schedule))
+        .route("/{id}/schedule/closures", post(create_closure))
+        .route("/{id}/schedule/closures/{closure_id}", put(update_closure))
+        .route(
+            "/{id}/schedule/closures/{closure_id}",
+            delete(delete_closure),
+        )
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageClassrooms
+        ));
+
+    Router::new()
+        .route("/{id}/schedule", get(get_schedule))
+        .route("/{id}/availability", get(get_availability))
+        .merge(admin_only_route)
+}
@@ -0,0 +1,371 @@
+use std::collections::BTreeSet;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::put,
+};
+use axum_login::permission_required;
+use chrono::Utc;
+use sea_orm::{
+    ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    audit::{AuditContext, Target, snapshot},
+    availability::{campus_offset, is_out_of_service},
+    classroom_history,
+    closure_impact::{AffectedReservation, ClosureAction, apply_action, find_affected, finish},
+    entities::{classroom, sea_orm_active_enums::ClassroomStatus},
+    error::{AppError, ErrorResponse},
+    login_system::AuthBackend,
+    permissions::Permission,
+    utils::parse_dt,
+    versioning,
+};
+
+// ===============================
+//   Request / Response bodies
+// ===============================
+#[derive(Deserialize, ToSchema)]
+pub struct BulkStatusBody {
+    pub classroom_ids: Vec<String>,
+    pub status: ClassroomStatus,
+    /// Shown to affected users, e.g. "Typhoon day, building closed"
+    pub reason: String,
+    /// Start of the affected window; defaults to now
+    pub from: Option<String>,
+    /// End of the affected window; open-ended when omitted
+    pub to: Option<String>,
+    /// What to do with pending/approved reservations in the window
+    #[serde(default)]
+    pub reservation_action: ClosureAction,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateClassroomStatusBody {
+    pub status: ClassroomStatus,
+    /// Required for Maintenance/Unavailable
+    pub reason: Option<String>,
+    /// Start of the out-of-service period; defaults to now
+    pub from: Option<String>,
+    /// End of the out-of-service period; open-ended when omitted
+    pub to: Option<String>,
+    /// What to do with pending/approved reservations in the period
+    #[serde(default)]
+    pub reservation_action: ClosureAction,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ClassroomStatusChangeResponse {
+    pub classrooms: Vec<classroom::Model>,
+    /// Pending/approved reservations inside the window, after the action was applied
+    pub affected_reservations: Vec<AffectedReservation>,
+}
+
+/// Validated status change shared by the single and bulk endpoints.
+pub(crate) struct StatusChange {
+    pub status: ClassroomStatus,
+    pub reason: Option<String>,
+    pub from: DateTimeWithTimeZone,
+    pub to: Option<DateTimeWithTimeZone>,
+    pub reservation_action: ClosureAction,
+}
+
+impl StatusChange {
+    pub(crate) fn parse(
+        status: ClassroomStatus,
+        reason: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+        reservation_action: ClosureAction,
+    ) -> Result<Self, AppError> {
+        let reason = reason
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(str::to_string);
+        if reason.is_none() && is_out_of_service(&status) {
+            return Err(AppError::BadRequest("reason is required".to_string()));
+        }
+
+        let from = match from {
+            Some(s) => match parse_dt(s) {
+                Ok(dt) => dt,
+                Err(_) => {
+                    return Err(AppError::BadRequest("Invalid from".to_string()));
+                }
+            },
+            None => Utc::now().with_timezone(&campus_offset()),
+        };
+        let to = match to {
+            Some(s) => match parse_dt(s) {
+                Ok(dt) => Some(dt),
+                Err(_) => return Err(AppError::BadRequest("Invalid to".to_string())),
+            },
+            None => None,
+        };
+        if let Some(to) = to
+            && to <= from
+        {
+            return Err(AppError::BadRequest(
+                "from must be earlier than to".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            status,
+            reason,
+            from,
+            to,
+            reservation_action,
+        })
+    }
+}
+
+// ===============================
+//   Shared workflow
+// ===============================
+
+/// Sets the status of every classroom in one transaction. When the new status
+/// takes the rooms out of service, the pending/approved reservations that
+/// overlap the window are flagged, cancelled or sent for rebooking, and their
+/// owners emailed afterwards.
+pub(crate) async fn apply_status_change(
+    state: &AppState,
+    audit: &AuditContext,
+    classroom_ids: &[String],
+    change: StatusChange,
+) -> Result<ClassroomStatusChangeResponse, AppError> {
+    let ids: Vec<String> = classroom_ids
+        .iter()
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let classrooms = match classroom::Entity::find()
+        .filter(classroom::Column::Id.is_in(ids.clone()))
+        .all(&txn)
+        .await
+    {
+        Ok(list) => list,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classrooms".to_string()));
+        }
+    };
+
+    if classrooms.len() != ids.len() {
+        let missing: Vec<&str> = ids
+            .iter()
+            .filter(|id| !classrooms.iter().any(|c| &c.id == *id))
+            .map(String::as_str)
+            .collect();
+        return Err(AppError::NotFound(format!(
+            "Classroom not found: {}",
+            missing.join(", ")
+        )));
+    }
+
+    // The window is only meaningful while the room is out of service
+    let (status_from, status_until) = if is_out_of_service(&change.status) {
+        (Some(change.from), change.to)
+    } else {
+        (None, None)
+    };
+    let status_reason = match change.status {
+        ClassroomStatus::Available => None,
+        _ => change.reason.clone(),
+    };
+
+    let mut updated_classrooms = Vec::with_capacity(classrooms.len());
+    for classroom_model in classrooms {
+        let previous = classroom_model.clone();
+        let mut active: classroom::ActiveModel = classroom_model.into();
+        active.status = Set(change.status.clone());
+        active.status_reason = Set(status_reason.clone());
+        active.status_from = Set(status_from);
+        active.status_until = Set(status_until);
+        let updated = match versioning::update(&txn, active, classroom::Column::Version, None).await
+        {
+            Ok(updated) => updated,
+            Err(_) => {
+                return Err(AppError::Internal("Failed to update classroom".to_string()));
+            }
+        };
+        if classroom_history::record(&txn, Some(&previous), &updated)
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal(
+                "Failed to record classroom history".to_string(),
+            ));
+        }
+        if audit
+            .record(
+                &txn,
+                Target::Classroom,
+                &updated.id,
+                "status_update",
+                Some(snapshot(&previous)),
+                Some(snapshot(&updated)),
+            )
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal(
+                "Failed to record the change in the audit log".to_string(),
+            ));
+        }
+        updated_classrooms.push(updated);
+    }
+
+    let mut updated_reservations = Vec::new();
+    if is_out_of_service(&change.status) {
+        let reason = change.reason.as_deref().unwrap_or_default();
+        let result = match find_affected(&txn, ids.clone(), change.from, change.to).await {
+            Ok(affected) => apply_action(&txn, affected, change.reservation_action, reason).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(updated) => updated_reservations = updated,
+            Err(_) => {
+                return Err(AppError::Internal(
+                    "Failed to update affected reservations".to_string(),
+                ));
+            }
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to commit status change".to_string(),
+        ));
+    }
+
+    for classroom_model in &updated_classrooms {
+        state.cache.invalidate_classroom(&classroom_model.id).await;
+    }
+
+    let affected_reservations = finish(
+        state,
+        updated_reservations,
+        change.reservation_action,
+        change.reason.unwrap_or_default(),
+    )
+    .await;
+
+    Ok(ClassroomStatusChangeResponse {
+        classrooms: updated_classrooms,
+        affected_reservations,
+    })
+}
+
+// ===============================
+//   Handlers
+// ===============================
+#[utoipa::path(
+    put,
+    tags = ["Classroom"],
+    description = "Change the status of several classrooms at once (e.g. a whole building closed for a typhoon). Applied in one transaction; pending/approved reservations in the window are flagged, cancelled or sent for rebooking (reservation_action) and their owners notified.",
+    path = "/bulk-status",
+    request_body(content = BulkStatusBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Status changed", body = ClassroomStatusChangeResponse),
+        (status = 400, description = "Invalid body", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to change status", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn bulk_update_status(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(body): Json<BulkStatusBody>,
+) -> Result<Response, AppError> {
+    if body.classroom_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "classroom_ids must not be empty".to_string(),
+        ));
+    }
+
+    let change = match StatusChange::parse(
+        body.status,
+        Some(&body.reason),
+        body.from.as_deref(),
+        body.to.as_deref(),
+        body.reservation_action,
+    ) {
+        Ok(change) => change,
+        Err(e) => return Err(e),
+    };
+
+    match apply_status_change(&state, &audit, &body.classroom_ids, change).await {
+        Ok(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+#[utoipa::path(
+    put,
+    tags = ["Classroom"],
+    description = "Put a classroom into Maintenance/Unavailable (or back to Available) with a reason and optional time range. While out of service, new reservations overlapping the range are rejected; pending/approved reservations in the range are flagged, cancelled or sent for rebooking (reservation_action) and their owners notified.",
+    path = "/{id}/status",
+    params(
+        ("id" = String, Path, description = "Classroom ID")
+    ),
+    request_body(content = UpdateClassroomStatusBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Status changed", body = ClassroomStatusChangeResponse),
+        (status = 400, description = "Invalid body", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to change status", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn update_status(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    Json(body): Json<UpdateClassroomStatusBody>,
+) -> Result<Response, AppError> {
+    let change = match StatusChange::parse(
+        body.status,
+        body.reason.as_deref(),
+        body.from.as_deref(),
+        body.to.as_deref(),
+        body.reservation_action,
+    ) {
+        Ok(change) => change,
+        Err(e) => return Err(e),
+    };
+
+    match apply_status_change(&state, &audit, &[id], change).await {
+        Ok(result) => Ok((StatusCode::OK, Json(result)).into_response()),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn classroom_status_router() -> Router<AppState> {
+    Router::new()
+        .route("/bulk-status", put(bulk_update_status))
+        .route("/{id}/status", put(update_status))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageClassrooms
+        ))
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use axum::{
+    Extension, Json, Router,
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::permission_required;
+use nanoid::nanoid;
+use serde::Deserialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    config::Config,
+    error::{AppError, ErrorResponse},
+    login_system::AuthBackend,
+    permissions::Permission,
+};
+
+/// Developer tools under `/admin/debug`. Even when enabled only system
+/// administrators can call them.
+#[derive(Clone, Default)]
+pub struct DebugConfig {
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct HashPasswordBody {
+    pub password: String,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Admin"],
+    description = "Returns the Argon2 hash of a password as it would be stored, e.g. to seed an account by hand. Only served when debug routes are enabled.",
+    path = "/debug/argon2",
+    request_body = HashPasswordBody,
+    responses(
+        (status = 200, description = "Argon2 hash of the password", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Debug routes are disabled", body = ErrorResponse),
+        (status = 500, description = "Failed to hash the password", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn hash_password(
+    State(state): State<AppState>,
+    Json(body): Json<HashPasswordBody>,
+) -> Result<Response, AppError> {
+    match state.hasher.hash(body.password.as_bytes()).await {
+        Ok(hash) => Ok((StatusCode::OK, hash).into_response()),
+        Err(e) => {
+            warn!("Failed to hash password: {}", e);
+            Err(AppError::Internal(
+                "Failed to hash the password".to_string(),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Returns a newly generated NanoID. Only served when debug routes are enabled.",
+    path = "/debug/nanoid",
+    responses(
+        (status = 200, description = "A new NanoID", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Debug routes are disabled", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn generate_nanoid() -> impl IntoResponse {
+    nanoid!()
+}
+
+/// Answers 404 unless [`DebugConfig::enabled`], before asking for a session,
+/// so disabled tools look like they do not exist.
+async fn require_enabled(
+    Extension(config): Extension<Arc<Config>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if !config.debug.enabled {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(req).await
+}
+
+pub fn debug_router() -> Router<AppState> {
+    Router::new()
+        .route("/debug/argon2", post(hash_password))
+        .route("/debug/nanoid", get(generate_nanoid))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageSystem))
+        .route_layer(middleware::from_fn(require_enabled))
+}
@@ -0,0 +1,131 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::permission_required;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState, domain_events,
+    entities::{domain_event, sea_orm_active_enums::DomainEventStatus},
+    error::{AppError, ErrorResponse},
+    export::parse_filter,
+    login_system::AuthBackend,
+    pagination::{PageParams, PagedResponse, Pagination, fetch},
+    permissions::Permission,
+};
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct DomainEventQuery {
+    /// e.g. `reservation_created`
+    pub kind: Option<String>,
+    pub status: Option<DomainEventStatus>,
+    /// Events about this record, e.g. a reservation ID
+    pub subject_id: Option<String>,
+    /// Recorded at or after this time
+    pub from: Option<String>,
+    /// Recorded before this time
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Recorded domain events, newest first, with the payload handed to their consumers and the outcome of the last delivery. Filters combine with AND.",
+    path = "/events",
+    params(DomainEventQuery, PageParams),
+    responses(
+        (status = 200, description = "Domain events", body = PagedResponse<domain_event::Model>),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch events", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_domain_events(
+    State(state): State<AppState>,
+    Query(query): Query<DomainEventQuery>,
+    pagination: Pagination,
+) -> Result<Response, AppError> {
+    let mut find_query = domain_event::Entity::find();
+
+    if let Some(kind) = query.kind {
+        find_query = find_query.filter(domain_event::Column::Kind.eq(kind));
+    }
+    if let Some(status) = query.status {
+        find_query = find_query.filter(domain_event::Column::Status.eq(status));
+    }
+    if let Some(subject_id) = query.subject_id {
+        find_query = find_query.filter(domain_event::Column::SubjectId.eq(subject_id));
+    }
+    match parse_filter(&query.from, "from") {
+        Ok(Some(from)) => find_query = find_query.filter(domain_event::Column::CreatedAt.gte(from)),
+        Ok(None) => {}
+        Err(message) => return Err(AppError::BadRequest(message)),
+    }
+    match parse_filter(&query.to, "to") {
+        Ok(Some(to)) => find_query = find_query.filter(domain_event::Column::CreatedAt.lt(to)),
+        Ok(None) => {}
+        Err(message) => return Err(AppError::BadRequest(message)),
+    }
+
+    let find_query = find_query
+        .order_by_desc(domain_event::Column::CreatedAt)
+        .order_by_desc(domain_event::Column::Id);
+    match fetch(&state.db, find_query, pagination).await {
+        Ok(events) => Ok((StatusCode::OK, Json(events)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch events".to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Admin"],
+    description = "Hand a failed event to its consumers again with its stored payload. People who were notified the first time may be notified again.",
+    path = "/events/{id}/replay",
+    params(("id" = String, Path, description = "Event ID")),
+    responses(
+        (status = 200, description = "Event after the new delivery attempt", body = domain_event::Model),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Event not found", body = ErrorResponse),
+        (status = 409, description = "Only failed events can be replayed", body = ErrorResponse),
+        (status = 500, description = "Failed to replay event", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn replay_domain_event(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let stored = match domain_event::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(stored)) => stored,
+        Ok(None) => return Err(AppError::NotFound("Event not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch event".to_string()));
+        }
+    };
+    if stored.status != DomainEventStatus::Failed {
+        return Err(AppError::Conflict(
+            "Only failed events can be replayed".to_string(),
+        ));
+    }
+
+    match domain_events::dispatch(&state, stored).await {
+        Ok(updated) => Ok((StatusCode::OK, Json(updated)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to replay event".to_string())),
+    }
+}
+
+pub fn domain_event_router() -> Router<AppState> {
+    Router::new()
+        .route("/events", get(list_domain_events))
+        .route("/events/{id}/replay", post(replay_domain_event))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageSystem))
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, QueryFilter,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    AppState,
+    door_events::{authorize, correlate},
+    entities::{classroom, door_event, reservation},
+    error::{AppError, ErrorResponse},
+    utils::parse_dt_field,
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct DoorOpenEvent {
+    pub classroom_id: String,
+    /// Reader that saw the door open, for rooms with several doors
+    pub door_id: Option<String>,
+    /// When the door was opened, e.g. `2025-03-10T09:52:00+08:00`
+    pub opened_at: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct DoorEventsBody {
+    /// Readers may batch events, e.g. after being offline
+    pub events: Vec<DoorOpenEvent>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct DoorEventsResponse {
+    pub recorded: u64,
+    /// Recorded events that belong to a reservation
+    pub matched: u64,
+    /// Events that were already recorded, e.g. resent after a timeout
+    pub duplicates: u64,
+    /// Classrooms in the batch that do not exist; their events are dropped
+    pub unknown_classrooms: Vec<String>,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["IoT"],
+    description = "Door-open events from the access control system. Each event is matched to the reservation running in the classroom at the time, or to the next one when the door was opened within the grace period before it (flagged as early access). The matched events show when a room was actually used, in the reservation detail and the attendance statistics. Authenticate with `Authorization: Bearer <token>`; a token may be limited to some classrooms.",
+    path = "/door-events",
+    request_body(content = DoorEventsBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Events processed", body = DoorEventsResponse),
+        (status = 400, description = "Invalid event time", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 403, description = "Token not allowed for a classroom in the batch", body = ErrorResponse),
+        (status = 503, description = "Door events are not configured", body = ErrorResponse),
+        (status = 500, description = "Failed to record events", body = ErrorResponse)
+    ),
+    security(("door_token" = []))
+)]
+pub async fn receive_door_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<DoorEventsBody>,
+) -> Result<Response, AppError> {
+    let config = &state.config.door_events;
+    if config.tokens.is_empty() {
+        return Err(AppError::ServiceUnavailable(
+            "Door events are not configured".to_string(),
+        ));
+    }
+    let Some(token) = authorize(&config.tokens, &headers) else {
+        return Err(AppError::Unauthorized("Invalid token".to_string()));
+    };
+
+    let mut events = Vec::new();
+    for event in body.events {
+        if !token.allows(&event.classroom_id) {
+            return Err(AppError::Forbidden(format!(
+                "Token is not allowed for classroom {}",
+                event.classroom_id
+            )));
+        }
+        match parse_dt_field(&event.opened_at, "opened_at") {
+            Ok(opened_at) => events.push((event, opened_at)),
+            Err(message) => return Err(AppError::BadRequest(message)),
+        }
+    }
+
+    let mut response = DoorEventsResponse {
+        recorded: 0,
+        matched: 0,
+        duplicates: 0,
+        unknown_classrooms: Vec::new(),
+    };
+    let mut known: HashMap<String, bool> = HashMap::new();
+    for (event, opened_at) in events {
+        let exists = match known.get(&event.classroom_id) {
+            Some(exists) => *exists,
+            None => match classroom::Entity::find_by_id(&event.classroom_id)
+                .one(&state.db)
+                .await
+            {
+                Ok(found) => {
+                    known.insert(event.classroom_id.clone(), found.is_some());
+                    found.is_some()
+                }
+                Err(_) => {
+                    return Err(AppError::Internal("Failed to record events".to_string()));
+                }
+            },
+        };
+        if !exists {
+            if !response.unknown_classrooms.contains(&event.classroom_id) {
+                response.unknown_classrooms.push(event.classroom_id);
+            }
+            continue;
+        }
+
+        let mut duplicate = door_event::Entity::find()
+            .filter(door_event::Column::ClassroomId.eq(&event.classroom_id))
+            .filter(door_event::Column::OpenedAt.eq(opened_at));
+        duplicate = match &event.door_id {
+            Some(door_id) => duplicate.filter(door_event::Column::DoorId.eq(door_id)),
+            None => duplicate.filter(door_event::Column::DoorId.is_null()),
+        };
+        match duplicate.one(&state.db).await {
+            Ok(Some(_)) => {
+                response.duplicates += 1;
+                continue;
+            }
+            Ok(None) => {}
+            Err(_) => {
+                return Err(AppError::Internal("Failed to record events".to_string()));
+            }
+        }
+
+        // Reservations running at the time or starting within the grace period
+        let candidates = match reservation::Entity::find()
+            .filter(reservation::Column::ClassroomId.eq(&event.classroom_id))
+            .filter(reservation::Column::StartTime.lte(opened_at + config.grace))
+            .filter(reservation::Column::EndTime.gt(opened_at))
+            .all(&state.db)
+            .await
+        {
+            Ok(candidates) => candidates,
+            Err(_) => {
+                return Err(AppError::Internal("Failed to record events".to_string()));
+            }
+        };
+        let matched = correlate(&candidates, opened_at, config.grace);
+
+        let record = door_event::ActiveModel {
+            id: Set(nanoid!()),
+            classroom_id: Set(event.classroom_id),
+            door_id: Set(event.door_id),
+            reservation_id: Set(matched.map(|r| r.id.clone())),
+            opened_at: Set(opened_at),
+            early_access: Set(matched.is_some_and(|r| opened_at < r.start_time)),
+            created_at: NotSet,
+        };
+        if record.insert(&state.db).await.is_err() {
+            return Err(AppError::Internal("Failed to record events".to_string()));
+        }
+        response.recorded += 1;
+        if matched.is_some() {
+            response.matched += 1;
+        }
+    }
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "IoT", description = "Events from building devices")
+    ),
+    paths(
+        receive_door_events,
+    ),
+    components(schemas(
+        DoorEventsBody,
+        DoorOpenEvent,
+        DoorEventsResponse,
+    ))
+)]
+pub struct IotApi;
+
+pub fn door_event_router() -> Router<AppState> {
+    Router::new().route("/door-events", post(receive_door_events))
+}
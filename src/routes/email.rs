@@ -0,0 +1,144 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::Utc;
+use sea_orm::{
+    ColumnTrait, EntityTrait, QueryFilter,
+    sea_query::{Expr, ExprTrait, Func},
+};
+use tracing::info;
+use utoipa::OpenApi;
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_events::{EmailEventsBody, EmailEventsResponse, undeliverable_reason},
+    entities::user,
+    error::{AppError, ErrorResponse},
+    webhook::verify_request,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Email-Signature";
+
+#[utoipa::path(
+    post,
+    tags = ["Email"],
+    description = "Webhook for the mail provider's bounce and complaint callbacks. The raw body must be signed in the `X-Email-Signature` header as `t=<unix seconds>,v1=<hex HMAC-SHA256 of \"t.body\">`. Hard bounces and complaints flag the user's address as undeliverable, which stops further emails to it until the user changes their email.",
+    path = "/events",
+    request_body(content = EmailEventsBody, content_type = "application/json"),
+    params(
+        ("X-Email-Signature" = String, Header, description = "Signature of the raw body")
+    ),
+    responses(
+        (status = 200, description = "Events processed", body = EmailEventsResponse),
+        (status = 400, description = "Invalid payload", body = ErrorResponse),
+        (status = 401, description = "Missing, stale or invalid signature", body = ErrorResponse),
+        (status = 503, description = "Webhook is not configured", body = ErrorResponse),
+        (status = 500, description = "Failed to process events", body = ErrorResponse)
+    )
+)]
+pub async fn receive_email_events(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let config = &state.config.email_events;
+    let Some(secret) = &config.secret else {
+        return Err(AppError::ServiceUnavailable(
+            "Email webhook is not configured".to_string(),
+        ));
+    };
+
+    if let Err(e) = verify_request(secret, &headers, SIGNATURE_HEADER, &body, config.tolerance) {
+        return Err(e.into());
+    }
+
+    let payload: EmailEventsBody = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(_) => return Err(AppError::BadRequest("Invalid payload".to_string())),
+    };
+
+    let now = Utc::now().with_timezone(&campus_offset());
+    let mut flagged = 0;
+    for event in &payload.events {
+        let Some(reason) = undeliverable_reason(event) else {
+            continue;
+        };
+        let email = event.email.trim();
+
+        // Only the first bounce is recorded so the flag keeps its original date
+        let users = match user::Entity::find()
+            .filter(
+                Expr::expr(Func::lower(Expr::col(user::Column::Email))).eq(email.to_lowercase()),
+            )
+            .filter(user::Column::EmailUndeliverableAt.is_null())
+            .all(&state.db)
+            .await
+        {
+            Ok(users) => users,
+            Err(_) => {
+                return Err(AppError::Internal("Failed to process events".to_string()));
+            }
+        };
+
+        for user_model in users {
+            let result = user::Entity::update_many()
+                .col_expr(user::Column::EmailUndeliverableAt, Expr::value(now))
+                .col_expr(
+                    user::Column::EmailUndeliverableReason,
+                    Expr::value(reason.clone()),
+                )
+                .col_expr(
+                    user::Column::Version,
+                    Expr::col(user::Column::Version).add(1),
+                )
+                .filter(user::Column::Id.eq(&user_model.id))
+                .exec(&state.db)
+                .await;
+            if result.is_err() {
+                return Err(AppError::Internal("Failed to process events".to_string()));
+            }
+            state.user_cache.forget(&user_model.id).await;
+            info!(
+                "Flagged email of user {} as undeliverable ({})",
+                user_model.id, reason
+            );
+            flagged += 1;
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(EmailEventsResponse {
+            received: payload.events.len(),
+            flagged,
+        }),
+    )
+        .into_response())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Email", description = "Callbacks from the mail provider")
+    ),
+    paths(
+        receive_email_events,
+    ),
+    components(schemas(
+        crate::email_events::EmailEventsBody,
+        crate::email_events::EmailEvent,
+        crate::email_events::EmailEventKind,
+        crate::email_events::EmailEventsResponse,
+    ))
+)]
+pub struct EmailApi;
+
+pub fn email_router() -> Router<AppState> {
+    Router::new().route("/events", post(receive_email_events))
+}
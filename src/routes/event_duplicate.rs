@@ -0,0 +1,115 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use chrono::{Duration, Utc};
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    entities::{reservation, sea_orm_active_enums::ReservationStatus},
+    error::{AppError, ErrorResponse},
+    event_duplicates::{EventGroup, group_duplicates},
+    export::parse_filter,
+    login_system::{AuthBackend, AuthSession},
+    permissions::{Permission, review_scope},
+};
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct EventDuplicatesQuery {
+    /// Reservations starting at or after this time; defaults to now
+    pub from: Option<String>,
+    /// Reservations starting before this time; defaults to 30 days after `from`
+    pub to: Option<String>,
+    /// How far apart the starts of bookings for one event may be; defaults to
+    /// the configured window
+    pub window_hours: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Admin: active reservations grouped by normalized event name, to spot the same event booked in several rooms or by several people. Bookings for one event whose starts are further apart than the window form separate groups. Assistants only see reservations for their classrooms.",
+    path = "/admin/event-duplicates",
+    params(EventDuplicatesQuery),
+    responses(
+        (status = 200, description = "Groups of two or more reservations, largest first", body = Vec<EventGroup>),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_event_duplicates(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Query(query): Query<EventDuplicatesQuery>,
+) -> Result<Response, AppError> {
+    let from = match parse_filter(&query.from, "from") {
+        Ok(from) => from.unwrap_or_else(|| Utc::now().with_timezone(&campus_offset())),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let to = match parse_filter(&query.to, "to") {
+        Ok(to) => to.unwrap_or(from + Duration::days(30)),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if from >= to {
+        return Err(AppError::BadRequest(
+            "'from' must be before 'to'".to_string(),
+        ));
+    }
+    let window = match query.window_hours {
+        Some(hours) if hours < 0 => {
+            return Err(AppError::BadRequest(
+                "'window_hours' must not be negative".to_string(),
+            ));
+        }
+        Some(hours) => Duration::hours(hours),
+        None => state.config.event_duplicates.window,
+    };
+
+    let scope = match review_scope(&state.db, &session.user.unwrap()).await {
+        Ok(scope) => scope,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
+    match scope
+        .apply(reservation::Entity::find())
+        .filter(reservation::Column::EventName.is_not_null())
+        .filter(
+            reservation::Column::Status
+                .is_in([ReservationStatus::Pending, ReservationStatus::Approved]),
+        )
+        .filter(reservation::Column::StartTime.gte(from))
+        .filter(reservation::Column::StartTime.lt(to))
+        .all(&state.db)
+        .await
+    {
+        Ok(reservations) => {
+            Ok((StatusCode::OK, Json(group_duplicates(reservations, window))).into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservations".to_string(),
+        )),
+    }
+}
+
+pub fn event_duplicate_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/event-duplicates", get(list_event_duplicates))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ReviewReservations
+        ))
+}
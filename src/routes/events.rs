@@ -0,0 +1,100 @@
+use std::convert::Infallible;
+
+use axum::{
+    Router,
+    extract::State,
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use axum_login::login_required;
+use futures_util::stream;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::OpenApi;
+
+use crate::{
+    AppState,
+    announcement_audience::Reader,
+    error::{AppError, ErrorResponse},
+    live_events::Subscriber,
+    login_system::{AuthBackend, AuthSession},
+    permissions::review_scope,
+};
+
+#[utoipa::path(
+    get,
+    tags = ["Events"],
+    description = "Server-sent events for live dashboards: `reservation_created`, `reservation_reviewed`, `reservation_commented`, `key_borrowed`, `key_returned` and `announcement_published`, each with a JSON `LiveEvent` as data. Users get events about their own reservations and keys and the announcements they can read; reviewers also get those for the classrooms they review, and key managers every key event. What a stream may see is decided when it is opened. A `lagged` event means some events were missed and the client should refetch.",
+    path = "",
+    responses(
+        (status = 200, description = "Event stream", content_type = "text/event-stream"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to open the event stream", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn stream_events(
+    session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+    let (scope, reader) = match (
+        review_scope(&state.db, &user).await,
+        Reader::load(&state.db, Some(&user)).await,
+    ) {
+        (Ok(scope), Ok(reader)) => (scope, reader),
+        _ => {
+            return Err(AppError::Internal(
+                "Failed to open the event stream".to_string(),
+            ));
+        }
+    };
+    let subscriber = Subscriber {
+        user_id: user.id,
+        role: user.role,
+        scope,
+        reader,
+    };
+
+    let receiver = state.live.subscribe();
+    let events = stream::unfold(
+        (receiver, subscriber),
+        |(mut receiver, subscriber)| async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) if subscriber.sees(&event) => Event::default()
+                        .event(event.kind())
+                        .json_data(&event)
+                        .expect("live events serialize"),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        Event::default().event("lagged").data(missed.to_string())
+                    }
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((Ok::<_, Infallible>(event), (receiver, subscriber)));
+            }
+        },
+    );
+    Ok(Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Events", description = "Live updates pushed to signed-in clients")
+    ),
+    paths(stream_events),
+    components(schemas(crate::live_events::LiveEvent))
+)]
+pub struct EventsApi;
+
+pub fn events_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(stream_events))
+        .route_layer(login_required!(AuthBackend))
+}
@@ -1,23 +1,26 @@
 use axum::{
     Json, Router,
-    extract::{Path, State},
+    extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
 };
 use axum_login::{login_required, permission_required};
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, EntityTrait, ModelTrait, QueryFilter,
+    ColumnTrait, EntityTrait, ModelTrait, Order, QueryFilter,
 };
 use serde::Deserialize;
-use utoipa::ToSchema;
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    entities::{infraction, sea_orm_active_enums::Role},
+    entities::infraction,
+    error::{AppError, ErrorResponse},
     login_system::{AuthBackend, AuthSession},
+    pagination::{PageParams, PagedResponse, Pagination, SortParams, SortSpec, fetch},
+    permissions::Permission,
 };
 use nanoid::nanoid;
 
@@ -47,7 +50,7 @@ pub async fn create_infraction(
     session: AuthSession,
     State(state): State<AppState>,
     Json(body): Json<CreateInfractionBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user = session.user.unwrap();
     let new_infraction = infraction::ActiveModel {
         id: Set(nanoid!()),
@@ -58,12 +61,10 @@ pub async fn create_infraction(
         created_at: NotSet,
     };
     match new_infraction.insert(&state.db).await {
-        Ok(infraction) => (StatusCode::CREATED, Json(infraction)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to create infraction",
-        )
-            .into_response(),
+        Ok(infraction) => Ok((StatusCode::CREATED, Json(infraction)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to create infraction".to_string(),
+        )),
     }
 }
 
@@ -81,27 +82,21 @@ pub async fn update_infraction(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(body): Json<UpdateInfractionBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let infraction = match infraction::Entity::find_by_id(id).one(&state.db).await {
         Ok(Some(infraction)) => infraction,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Infraction not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Infraction not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch infraction",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to fetch infraction".to_string()));
         }
     };
     let mut updated_infraction: infraction::ActiveModel = infraction.into();
     updated_infraction.description = Set(body.description);
     match updated_infraction.update(&state.db).await {
-        Ok(infraction) => (StatusCode::OK, Json(infraction)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update infraction",
-        )
-            .into_response(),
+        Ok(infraction) => Ok((StatusCode::OK, Json(infraction)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to update infraction".to_string(),
+        )),
     }
 }
 
@@ -117,25 +112,19 @@ pub async fn update_infraction(
 pub async fn delete_infraction(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let infraction = match infraction::Entity::find_by_id(id).one(&state.db).await {
         Ok(Some(infraction)) => infraction,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Infraction not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Infraction not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch infraction",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to fetch infraction".to_string()));
         }
     };
     match infraction.delete(&state.db).await {
-        Ok(_) => (StatusCode::OK, "Infraction deleted successfully").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to delete infraction",
-        )
-            .into_response(),
+        Ok(_) => Ok((StatusCode::OK, "Infraction deleted successfully").into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to delete infraction".to_string(),
+        )),
     }
 }
 
@@ -151,58 +140,83 @@ pub async fn delete_infraction(
 pub async fn get_infraction(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let infraction = match infraction::Entity::find_by_id(id).one(&state.db).await {
         Ok(Some(infraction)) => infraction,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Infraction not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Infraction not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch infraction",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to fetch infraction".to_string()));
         }
     };
-    (StatusCode::OK, Json(infraction)).into_response()
+    Ok((StatusCode::OK, Json(infraction)).into_response())
 }
 
 #[utoipa::path(
     get,
     tags = ["Infraction"],
-    description = "Get all infractions for self",
+    description = "Get infractions for self, newest first unless sorted by `created_at:asc`",
     path = "",
+    params(PageParams, SortParams),
     responses(
-        (status = 200, description = "Infractions fetched successfully", body = Vec<infraction::Model>),
+        (status = 200, description = "Infractions fetched successfully", body = PagedResponse<infraction::Model>),
+        (status = 400, description = "Invalid sort", body = ErrorResponse),
     )
 )]
 pub async fn list_infractions(
     session: AuthSession,
     State(state): State<AppState>,
-) -> impl IntoResponse {
+    pagination: Pagination,
+    Query(params): Query<SortParams>,
+) -> Result<Response, AppError> {
     let user = session.user.unwrap();
-    let infractions = match infraction::Entity::find()
-        .filter(infraction::Column::UserId.eq(user.id))
-        .all(&state.db)
-        .await
-    {
+    let sort = match SortSpec::parse(
+        params.sort.as_deref(),
+        &[("created_at", infraction::Column::CreatedAt)],
+        infraction::Column::CreatedAt,
+        Order::Desc,
+    ) {
+        Ok(sort) => sort,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let select = infraction::Entity::find().filter(infraction::Column::UserId.eq(user.id));
+    let infractions = match fetch(&state.db, sort.apply(select), pagination).await {
         Ok(infractions) => infractions,
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch infractions",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch infractions".to_string(),
+            ));
         }
     };
-    (StatusCode::OK, Json(infractions)).into_response()
+    Ok((StatusCode::OK, Json(infractions)).into_response())
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Infraction", description = "Infraction endpoints")
+    ),
+    paths(
+        create_infraction,
+        update_infraction,
+        delete_infraction,
+        list_infractions,
+        get_infraction,
+    ),
+    components(schemas(
+        crate::entities::infraction::Model,
+        CreateInfractionBody,
+        UpdateInfractionBody,
+        crate::pagination::PagedResponse<crate::entities::infraction::Model>,
+    ))
+)]
+pub struct InfractionApi;
+
 pub fn infraction_router() -> Router<AppState> {
     let admin_only_route = Router::new()
         .route("/", post(create_infraction))
         .route("/{id}", put(update_infraction))
         .route("/{id}", delete(delete_infraction))
-        .route_layer(permission_required!(AuthBackend, Role::Admin));
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers));
 
     let login_required_route = Router::new()
         .route("/", get(list_infractions))
@@ -0,0 +1,328 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_login::permission_required;
+use chrono::{Duration, Utc};
+use nanoid::nanoid;
+use redis::{AsyncCommands, RedisError, SetExpiry, SetOptions};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, Condition, EntityTrait, QueryFilter,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    email_client::send_email,
+    email_templates::staff_invite,
+    entities::{sea_orm_active_enums::Role, user},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::Permission,
+    redis_breaker::RedisConnection,
+    routes::user::UserResponse,
+    soft_launch,
+    user_conflicts::UniqueField,
+};
+
+const INVITE_TTL_SECONDS: u64 = 7 * 24 * 60 * 60; // 7 days
+
+fn invite_key(token: &str) -> String {
+    format!("user_invite:{}", token)
+}
+
+#[derive(Serialize, Deserialize)]
+struct InviteData {
+    email: String,
+    role: Role,
+    department: String,
+    invited_by: String,
+    expires_at: i64, // Unix timestamp
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct InviteBody {
+    pub email: String,
+    /// Role the account is created with
+    pub role: Role,
+    pub department: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct InviteResponse {
+    pub email: String,
+    pub role: Role,
+    pub department: String,
+    /// Single-use token for `/accept-invite/{token}`
+    pub token: String,
+    /// Registration page for the invitee; `None` when no frontend URL is
+    /// configured
+    pub link: Option<String>,
+    /// `false` when the invitation email could not be sent; the link or token
+    /// can still be handed over another way
+    pub email_sent: bool,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AcceptInviteBody {
+    pub username: String,
+    pub password: String,
+    pub phone_number: String,
+    pub name: String,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["User"],
+    description = "Invite a staff member by email. The invitation pre-assigns the role and department and is accepted once through `/accept-invite/{token}` within 7 days; no student ID is required.",
+    path = "/admin/invite",
+    request_body(content = InviteBody, content_type = "application/json"),
+    responses(
+        (status = 201, description = "Invitation created", body = InviteResponse),
+        (status = 400, description = "Invalid email or department", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden, or the email domain is not open for registration yet", body = ErrorResponse),
+        (status = 409, description = "A user with this email already exists", body = ErrorResponse),
+        (status = 500, description = "Failed to create invitation", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn invite_user(
+    State(state): State<AppState>,
+    session: AuthSession,
+    Json(body): Json<InviteBody>,
+) -> Result<Response, AppError> {
+    let admin = session.user.unwrap();
+    let email = body.email.trim().to_string();
+    let department = body.department.trim().to_string();
+    if !email.contains('@') {
+        return Err(AppError::BadRequest("Invalid email".to_string()));
+    }
+    if department.is_empty() {
+        return Err(AppError::BadRequest("Department is required".to_string()));
+    }
+    if let Err(e) = soft_launch::check(&state.config.soft_launch, &email, None) {
+        return Err(AppError::Forbidden(e.message().to_string()));
+    }
+
+    match user::Entity::find()
+        .filter(user::Column::Email.eq(&email))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(_)) => {
+            return Err(AppError::Conflict(
+                "A user with this email already exists".to_string(),
+            ));
+        }
+        Ok(None) => {}
+        Err(_) => {
+            return Err(AppError::Internal("Failed to query user".to_string()));
+        }
+    }
+
+    let token = nanoid!(32);
+    let expires_at =
+        (Utc::now() + Duration::seconds(INVITE_TTL_SECONDS as i64)).with_timezone(&campus_offset());
+    let data = InviteData {
+        email: email.clone(),
+        role: body.role.clone(),
+        department: department.clone(),
+        invited_by: admin.id,
+        expires_at: expires_at.timestamp(),
+    };
+
+    let mut redis = state.redis.clone();
+    let result: Result<(), RedisError> = redis
+        .set_options(
+            invite_key(&token),
+            serde_json::to_string(&data).unwrap(),
+            SetOptions::default().with_expiration(SetExpiry::EX(INVITE_TTL_SECONDS)),
+        )
+        .await;
+    if let Err(e) = result {
+        warn!("Failed to store invitation for {} in Redis: {}", email, e);
+        return Err(AppError::Internal(
+            "Failed to create invitation".to_string(),
+        ));
+    }
+
+    let templates = &state.config.email_templates;
+    let message = staff_invite(
+        templates,
+        &token,
+        &body.role,
+        &department,
+        expires_at,
+        templates.default_locale,
+    );
+    let email_sent = match send_email(
+        state.config.smtp.as_ref(),
+        &email,
+        &message.subject,
+        &message.body,
+    )
+    .await
+    {
+        Ok(_) => true,
+        Err(e) => {
+            warn!("Failed to send invitation to {}: {}", email, e);
+            false
+        }
+    };
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InviteResponse {
+            email,
+            role: body.role,
+            department,
+            link: templates.invite_link(&token),
+            token,
+            email_sent,
+            expires_at,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["User"],
+    description = "Complete registration from an invitation. The account gets the invited email, role and department; the invitation cannot be used again.",
+    path = "/accept-invite/{token}",
+    params(("token" = String, Path, description = "Invitation token")),
+    request_body(content = AcceptInviteBody, content_type = "application/json"),
+    responses(
+        (status = 201, description = "User registered successfully", body = UserResponse),
+        (status = 403, description = "The invited email domain is no longer open for registration", body = ErrorResponse),
+        (status = 404, description = "Invitation not found, used or expired", body = ErrorResponse),
+        (status = 409, description = "Username or email already taken; `details.field` names which", body = ErrorResponse),
+        (status = 500, description = "Failed to create user", body = ErrorResponse),
+    )
+)]
+pub async fn accept_invite(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(body): Json<AcceptInviteBody>,
+) -> Result<Response, AppError> {
+    // GETDEL so two concurrent requests cannot both consume the invitation
+    let mut redis = state.redis.clone();
+    let raw: Option<String> = match redis.get_del(invite_key(&token)).await {
+        Ok(raw) => raw,
+        Err(e) => {
+            warn!("Failed to read invitation from Redis: {}", e);
+            return Err(AppError::Internal("Failed to read invitation".to_string()));
+        }
+    };
+    let Some(data) = raw.and_then(|raw| serde_json::from_str::<InviteData>(&raw).ok()) else {
+        return Err(AppError::NotFound("Invitation not found".to_string()));
+    };
+    let remaining = data.expires_at - Utc::now().timestamp();
+    if remaining <= 0 {
+        return Err(AppError::NotFound("Invitation not found".to_string()));
+    }
+    // Invitations sent before the allowlist was narrowed stay consumed
+    if let Err(e) = soft_launch::check(&state.config.soft_launch, &data.email, None) {
+        return Err(AppError::Forbidden(e.message().to_string()));
+    }
+
+    // Puts the invitation back so the invitee can retry after a failure that
+    // is not theirs to fix, e.g. a taken username
+    let restore = |mut redis: RedisConnection| {
+        let data = serde_json::to_string(&data).unwrap();
+        let key = invite_key(&token);
+        async move {
+            let result: Result<(), RedisError> = redis
+                .set_options(
+                    key,
+                    data,
+                    SetOptions::default().with_expiration(SetExpiry::EX(remaining as u64)),
+                )
+                .await;
+            if let Err(e) = result {
+                warn!("Failed to restore invitation in Redis: {}", e);
+            }
+        }
+    };
+
+    match user::Entity::find()
+        .filter(
+            Condition::any()
+                .add(user::Column::Username.eq(&body.username))
+                .add(user::Column::Email.eq(&data.email)),
+        )
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(existing)) => {
+            // An account registered with the invited email in the meantime
+            // makes the invitation useless, so it stays consumed
+            if existing.email == data.email {
+                return Err(UniqueField::Email.into());
+            }
+            restore(redis).await;
+            return Err(UniqueField::Username.into());
+        }
+        Ok(None) => {}
+        Err(_) => {
+            restore(redis).await;
+            return Err(AppError::Internal("Failed to query user".to_string()));
+        }
+    }
+
+    let hashed_password = state.hasher.hash(body.password).await.unwrap();
+
+    let new_user = user::ActiveModel {
+        id: Set(nanoid!()),
+        username: Set(body.username),
+        email: Set(data.email.clone()),
+        password: Set(hashed_password),
+        phone_number: Set(body.phone_number),
+        role: Set(data.role.clone()),
+        created_at: NotSet,
+        updated_at: NotSet,
+        name: Set(body.name),
+        locale: NotSet,
+        email_undeliverable_at: NotSet,
+        email_undeliverable_reason: NotSet,
+        department: Set(Some(data.department.clone())),
+        student_id: NotSet,
+        deleted_at: NotSet,
+        version: NotSet,
+    };
+
+    match new_user.insert(&state.db).await {
+        Ok(user) => {
+            state.user_cache.put(&user).await;
+
+            Ok((StatusCode::CREATED, Json(UserResponse::from(user))).into_response())
+        }
+        Err(_) => {
+            restore(redis).await;
+            Err(AppError::Internal("Failed to create user".to_string()))
+        }
+    }
+}
+
+pub fn invite_router() -> Router<AppState> {
+    let admin_only_route = Router::new()
+        .route("/admin/invite", post(invite_user))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers));
+
+    Router::new()
+        .route("/accept-invite/{token}", post(accept_invite))
+        .merge(admin_only_route)
+}
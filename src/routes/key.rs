@@ -2,36 +2,64 @@ use axum::{
     Json, Router,
     extract::{Path, Query, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
 };
-use axum_login::permission_required;
+use axum_login::{login_required, permission_required};
+use chrono::Utc;
 use nanoid::nanoid;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    ColumnTrait, DbErr, EntityTrait, ModelTrait, Order, QueryFilter, QueryOrder, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
 };
 use serde::{Deserialize, Serialize};
-use utoipa::{IntoParams, ToSchema};
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    entities::{classroom, key, key_transaction_log, reservation, sea_orm_active_enums::Role},
+    audit::{AuditContext, Target, snapshot},
+    availability::campus_offset,
+    bans,
+    entities::{classroom, key, key_transaction_log, reservation, sea_orm_active_enums::KeyStatus},
+    error::{AppError, ErrorResponse},
+    key_lifecycle::{
+        KeyEvent, event_for_target, next_status, refresh_classroom_summary, validate_borrow,
+    },
+    key_receipts::{self, ReceiptKind},
     login_system::{AuthBackend, AuthSession},
+    pagination::{PageParams, PagedResponse, Pagination, SortSpec, fetch},
+    permissions::Permission,
+    pickup,
+    routes::{
+        key_borrow::key_borrow_router, key_cabinet::key_cabinet_router,
+        key_log_export::key_log_export_router, key_sync::key_sync_router,
+    },
+    utils::parse_dt_field,
+    versioning,
 };
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreateKeyBody {
     pub key_number: String,
     pub classroom_id: String,
+    /// Smart key cabinet slot the key is stored in
+    pub cabinet_slot: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct UpdateKeyBody {
     pub key_number: String,
     pub classroom_id: String,
-    pub is_active: bool,
+    /// Move the key through its lifecycle (activate, deactivate, found, lost,
+    /// retire); keys only become Borrowed through the borrow endpoint
+    pub status: Option<KeyStatus>,
+    /// Smart key cabinet slot; an empty string takes the key out of the cabinet
+    pub cabinet_slot: Option<String>,
+    /// `version` of the key this edit was made on; refused with 409 when
+    /// someone else saved it since
+    pub version: Option<i32>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -39,6 +67,13 @@ pub struct BorrowKeyBody {
     pub reservation_id: String,
     pub borrowed_at: String,
     pub deadline: String,
+    /// Keys handed out together through `/borrow`, e.g. a door key and a
+    /// cabinet key; `/{id}/borrow` takes the key from the path instead
+    #[serde(default)]
+    pub key_ids: Vec<String>,
+    /// Code emailed to the requester on approval; required when the
+    /// reservation has one
+    pub pickup_code: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
@@ -52,7 +87,10 @@ pub struct KeyResponse {
     pub id: String,
     pub key_number: String,
     pub classroom_id: Option<String>,
-    pub is_active: bool,
+    pub status: KeyStatus,
+    pub cabinet_slot: Option<String>,
+    /// Send back with an edit to have it refused if someone saved in between
+    pub version: i32,
 }
 
 impl From<key::Model> for KeyResponse {
@@ -61,7 +99,9 @@ impl From<key::Model> for KeyResponse {
             id: model.id,
             key_number: model.key_number,
             classroom_id: model.classroom_id,
-            is_active: model.is_active,
+            status: model.status,
+            cabinet_slot: model.cabinet_slot,
+            version: model.version,
         }
     }
 }
@@ -73,12 +113,26 @@ pub struct KeyTransactionLogResponse {
     pub key_id: Option<String>,
     pub borrowed_to: Option<String>,
     pub handled_by: Option<String>,
-    pub borrowed_at: String,
-    pub deadline: String,
-    pub returned_at: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub borrowed_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub deadline: DateTimeWithTimeZone,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub returned_at: Option<DateTimeWithTimeZone>,
     pub returned: bool,
     pub on_time: Option<bool>,
-    pub created_at: String,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+    /// Set when the borrower asked for the return to be confirmed
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub return_requested_at: Option<DateTimeWithTimeZone>,
+    /// Parent borrow when several keys were handed out together
+    pub borrow_id: Option<String>,
 }
 
 impl From<key_transaction_log::Model> for KeyTransactionLogResponse {
@@ -90,25 +144,54 @@ impl From<key_transaction_log::Model> for KeyTransactionLogResponse {
             key_id: m.key_id,
             borrowed_to: m.borrowed_to,
             handled_by: m.handled_by,
-            borrowed_at: m.borrowed_at.to_string(),
-            deadline: m.deadline.to_string(),
-            returned_at: m.returned_at.map(|t| t.to_string()),
+            borrowed_at: m.borrowed_at,
+            deadline: m.deadline,
+            returned_at: m.returned_at,
             returned,
             on_time: Some(m.on_time),
-            created_at: m.created_at.to_string(),
+            created_at: m.created_at,
+            return_requested_at: m.return_requested_at,
+            borrow_id: m.borrow_id,
         }
     }
 }
 
+#[derive(Serialize, ToSchema)]
+pub struct BorrowedKeyResponse {
+    pub log: KeyTransactionLogResponse,
+    /// `None` when the key has since been deleted
+    pub key: Option<KeyResponse>,
+    /// Whether the deadline has passed
+    pub overdue: bool,
+}
+
 #[derive(Deserialize, ToSchema, IntoParams)]
 pub struct KeyLogListQuery {
     pub reservation_id: Option<String>,
     pub returned: Option<bool>,
-    pub page: Option<u64>,
-    pub page_size: Option<u64>,
+    /// Only logs whose borrower did (or did not) ask for a return confirmation
+    pub return_requested: Option<bool>,
+    /// `borrowed_at` (default), `deadline` or `returned_at`, optionally with
+    /// `:asc` or `:desc` (default `desc`)
     pub sort: Option<String>,
 }
 
+const LOG_SORT_FIELDS: [(&str, key_transaction_log::Column); 3] = [
+    ("borrowed_at", key_transaction_log::Column::BorrowedAt),
+    ("deadline", key_transaction_log::Column::Deadline),
+    ("returned_at", key_transaction_log::Column::ReturnedAt),
+];
+
+fn parse_log_sort(sort: Option<&str>) -> Result<SortSpec<key_transaction_log::Column>, AppError> {
+    SortSpec::parse(
+        sort,
+        &LOG_SORT_FIELDS,
+        key_transaction_log::Column::BorrowedAt,
+        Order::Desc,
+    )
+    .map_err(AppError::BadRequest)
+}
+
 #[utoipa::path(
     post,
     tags = ["Key"],
@@ -117,27 +200,24 @@ pub struct KeyLogListQuery {
     request_body(content = CreateKeyBody, content_type = "application/json"),
     responses(
         (status = 201, description = "Key created successfully", body = KeyResponse),
-        (status = 404, description = "Classroom not found"),
-        (status = 400, description = "Key number already exists"),
-        (status = 500, description = "Failed to create key")
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 400, description = "Key number already exists", body = ErrorResponse),
+        (status = 500, description = "Failed to create key", body = ErrorResponse)
     )
 )]
 pub async fn create_key(
     State(state): State<AppState>,
+    audit: AuditContext,
     Json(body): Json<CreateKeyBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     match classroom::Entity::find_by_id(&body.classroom_id)
         .one(&state.db)
         .await
     {
         Ok(Some(_)) => {}
-        Ok(None) => return (StatusCode::NOT_FOUND, "Classroom not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to query classroom",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to query classroom".to_string()));
         }
     }
 
@@ -147,14 +227,14 @@ pub async fn create_key(
         .await
     {
         Ok(Some(_)) => {
-            return (StatusCode::BAD_REQUEST, "This key_number already exists").into_response();
+            return Err(AppError::BadRequest(
+                "This key_number already exists".to_string(),
+            ));
         }
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to check key duplication",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to check key duplication".to_string(),
+            ));
         }
         _ => {}
     }
@@ -163,15 +243,30 @@ pub async fn create_key(
         id: Set(nanoid!()),
         key_number: Set(body.key_number),
         classroom_id: Set(Some(body.classroom_id)),
-        is_active: Set(true),
+        status: Set(KeyStatus::Active),
+        cabinet_slot: Set(body.cabinet_slot.filter(|slot| !slot.is_empty())),
+        version: NotSet,
     };
 
     match new_key.insert(&state.db).await {
         Ok(model) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::Key,
+                    &model.id,
+                    "create",
+                    None,
+                    Some(snapshot(&model)),
+                )
+                .await;
+            if let Some(classroom_id) = &model.classroom_id {
+                refresh_classroom_summary(&state, classroom_id).await;
+            }
             let resp = KeyResponse::from(model);
-            (StatusCode::CREATED, Json(resp)).into_response()
+            Ok((StatusCode::CREATED, Json(resp)).into_response())
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create key").into_response(),
+        Err(_) => Err(AppError::Internal("Failed to create key".to_string())),
     }
 }
 
@@ -186,36 +281,35 @@ pub async fn create_key(
     ),
     responses(
         (status = 200, description = "Key updated successfully", body = KeyResponse),
-        (status = 404, description = "Key or classroom not found"),
-        (status = 400, description = "Key number already exists"),
-        (status = 500, description = "Failed to update key")
+        (status = 404, description = "Key or classroom not found", body = ErrorResponse),
+        (status = 400, description = "Key number already exists or status is Borrowed", body = ErrorResponse),
+        (status = 409, description = "Status change not allowed from the key's current status, or someone else saved the key since `version`; `details.current_version` is the version now", body = ErrorResponse),
+        (status = 500, description = "Failed to update key", body = ErrorResponse)
     )
 )]
 pub async fn update_key(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
     Json(body): Json<UpdateKeyBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let key_model = match key::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(k)) => k,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Key not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch key").into_response();
+            return Err(AppError::Internal("Failed to fetch key".to_string()));
         }
     };
+    versioning::check(body.version, key_model.version)?;
 
     match classroom::Entity::find_by_id(&body.classroom_id)
         .one(&state.db)
         .await
     {
         Ok(Some(_)) => {}
-        Ok(None) => return (StatusCode::NOT_FOUND, "Classroom not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to query classroom",
-            )
-                .into_response();
+            return Err(AppError::Internal("Failed to query classroom".to_string()));
         }
     }
 
@@ -226,29 +320,77 @@ pub async fn update_key(
         .await
     {
         Ok(Some(_)) => {
-            return (StatusCode::BAD_REQUEST, "This key_number already exists").into_response();
+            return Err(AppError::BadRequest(
+                "This key_number already exists".to_string(),
+            ));
         }
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to check key duplication",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to check key duplication".to_string(),
+            ));
         }
         _ => {}
     }
 
+    let next = match &body.status {
+        Some(to) if *to != key_model.status => {
+            let Some(event) = event_for_target(&key_model.status, to) else {
+                return Err(AppError::BadRequest(
+                    "Keys can only be borrowed through the borrow endpoint".to_string(),
+                ));
+            };
+            match next_status(&key_model.status, event) {
+                Ok(next) => Some(next),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        _ => None,
+    };
+
+    let previous = key_model.clone();
+    let previous_classroom_id = key_model.classroom_id.clone();
     let mut key_active: key::ActiveModel = key_model.into();
     key_active.key_number = Set(body.key_number);
     key_active.classroom_id = Set(Some(body.classroom_id));
-    key_active.is_active = Set(body.is_active);
+    if let Some(next) = next {
+        key_active.status = Set(next);
+    }
+    if let Some(slot) = body.cabinet_slot {
+        key_active.cabinet_slot = Set(Some(slot).filter(|slot| !slot.is_empty()));
+    }
 
-    match key_active.update(&state.db).await {
+    match versioning::update(
+        &state.db,
+        key_active,
+        key::Column::Version,
+        Some(previous.version),
+    )
+    .await
+    {
         Ok(updated) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::Key,
+                    &updated.id,
+                    "update",
+                    Some(snapshot(&previous)),
+                    Some(snapshot(&updated)),
+                )
+                .await;
+            if let Some(classroom_id) = &previous_classroom_id
+                && previous_classroom_id != updated.classroom_id
+            {
+                refresh_classroom_summary(&state, classroom_id).await;
+            }
+            if let Some(classroom_id) = &updated.classroom_id {
+                refresh_classroom_summary(&state, classroom_id).await;
+            }
             let resp = KeyResponse::from(updated);
-            (StatusCode::OK, Json(resp)).into_response()
+            Ok((StatusCode::OK, Json(resp)).into_response())
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to update key").into_response(),
+        Err(DbErr::RecordNotUpdated) => Err(versioning::stale(None)),
+        Err(_) => Err(AppError::Internal("Failed to update key".to_string())),
     }
 }
 
@@ -262,32 +404,50 @@ pub async fn update_key(
     ),
     responses(
         (status = 200, description = "Key deleted successfully"),
-        (status = 404, description = "Key not found"),
-        (status = 500, description = "Failed to delete key")
+        (status = 404, description = "Key not found", body = ErrorResponse),
+        (status = 500, description = "Failed to delete key", body = ErrorResponse)
     )
 )]
 pub async fn delete_key(
     State(state): State<AppState>,
+    audit: AuditContext,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let key_model = match key::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(k)) => k,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Key not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch key").into_response();
+            return Err(AppError::Internal("Failed to fetch key".to_string()));
         }
     };
 
+    let previous = key_model.clone();
+    let classroom_id = key_model.classroom_id.clone();
     match key_model.delete(&state.db).await {
-        Ok(_) => (StatusCode::OK, "Key deleted successfully").into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to delete key").into_response(),
+        Ok(_) => {
+            audit
+                .log(
+                    &state.db,
+                    Target::Key,
+                    &previous.id,
+                    "delete",
+                    Some(snapshot(&previous)),
+                    None,
+                )
+                .await;
+            if let Some(classroom_id) = &classroom_id {
+                refresh_classroom_summary(&state, classroom_id).await;
+            }
+            Ok((StatusCode::OK, "Key deleted successfully").into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to delete key".to_string())),
     }
 }
 
 #[utoipa::path(
     post,
     tags = ["Key"],
-    description = "Borrow a key",
+    description = "Borrow a key. The borrower is emailed a receipt.",
     path = "/{id}/borrow",
     request_body(content = BorrowKeyBody, content_type = "application/json"),
     params(
@@ -295,9 +455,12 @@ pub async fn delete_key(
     ),
     responses(
         (status = 200, description = "Key borrowed successfully"),
-        (status = 404, description = "Key or reservation not found"),
-        (status = 400, description = "Key is not active"),
-        (status = 500, description = "Failed to borrow key")
+        (status = 400, description = "Invalid borrowed_at or deadline, or key_ids given; use /borrow instead", body = ErrorResponse),
+        (status = 403, description = "Pickup code missing or wrong, or the reservation holder is blacklisted", body = ErrorResponse),
+        (status = 404, description = "Key or reservation not found", body = ErrorResponse),
+        (status = 409, description = "Key is not available to borrow", body = ErrorResponse),
+        (status = 422, description = "Reservation is not approved, is for another classroom, or is not running at borrowed_at, or its pickup code sets aside another key", body = ErrorResponse),
+        (status = 500, description = "Failed to borrow key", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
@@ -306,57 +469,141 @@ pub async fn borrow_key(
     Path(id): Path<String>,
     session: AuthSession,
     Json(body): Json<BorrowKeyBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
+    if !body.key_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "Use /borrow to borrow several keys at once".to_string(),
+        ));
+    }
+
     let key_model = match key::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(k)) => k,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Key not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch key").into_response();
+            return Err(AppError::Internal("Failed to fetch key".to_string()));
         }
     };
 
-    if !key_model.is_active {
-        return (StatusCode::BAD_REQUEST, "Key is not active").into_response();
-    }
+    let next = match next_status(&key_model.status, KeyEvent::Borrow) {
+        Ok(next) => next,
+        Err(e) => return Err(e.into()),
+    };
 
     let reservation_model = match reservation::Entity::find_by_id(&body.reservation_id)
         .one(&state.db)
         .await
     {
         Ok(Some(r)) => r,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Reservation not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch reservation",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
         }
     };
+    if let Some(user_id) = &reservation_model.user_id {
+        match bans::ensure_not_banned(&state.db, user_id).await {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => return Err(AppError::Forbidden(message)),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to check blacklist".to_string()));
+            }
+        }
+    }
+
+    let borrowed_at = match parse_dt_field(&body.borrowed_at, "borrowed_at") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let deadline = match parse_dt_field(&body.deadline, "deadline") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if let Err(e) = validate_borrow(&key_model, &reservation_model, borrowed_at) {
+        return Err(e.into());
+    }
+    let pickup_code = match pickup::active_code(&state.db, &reservation_model.id).await {
+        Ok(code) => code,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch pickup code".to_string(),
+            ));
+        }
+    };
+    if let Err(e) = pickup::verify(
+        pickup_code.as_ref(),
+        body.pickup_code.as_deref(),
+        std::slice::from_ref(&id),
+    ) {
+        return Err(e.into());
+    }
 
     let new_key_transaction_log = key_transaction_log::ActiveModel {
         id: Set(nanoid!()),
         reservation_id: Set(Some(body.reservation_id)),
         key_id: Set(Some(id)),
-        borrowed_to: Set(Some(reservation_model.user_id.unwrap())),
+        borrowed_to: Set(reservation_model.user_id.clone()),
         handled_by: Set(Some(session.user.unwrap().id)),
-        borrowed_at: Set(body.borrowed_at.parse().unwrap()),
-        deadline: Set(body.deadline.parse().unwrap()),
+        borrowed_at: Set(borrowed_at),
+        deadline: Set(deadline),
         returned_at: NotSet,
         on_time: NotSet,
         created_at: NotSet,
+        overdue_at: NotSet,
+        second_notice_at: NotSet,
+        escalated_at: NotSet,
+        return_requested_at: NotSet,
+        borrow_id: NotSet,
+    };
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let log = match new_key_transaction_log.insert(&txn).await {
+        Ok(model) => model,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to borrow key".to_string()));
+        }
     };
+    if let Some(code) = pickup_code
+        && pickup::mark_used(&txn, code, &log.id).await.is_err()
+    {
+        return Err(AppError::Internal("Failed to borrow key".to_string()));
+    }
+
+    let classroom_id = key_model.classroom_id.clone();
+    let mut key_active: key::ActiveModel = key_model.into();
+    key_active.status = Set(next);
+    if versioning::update(&txn, key_active, key::Column::Version, None)
+        .await
+        .is_err()
+        || txn.commit().await.is_err()
+    {
+        return Err(AppError::Internal("Failed to borrow key".to_string()));
+    }
 
-    match new_key_transaction_log.insert(&state.db).await {
-        Ok(model) => (StatusCode::OK, Json(KeyTransactionLogResponse::from(model))).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to borrow key").into_response(),
+    if let Some(classroom_id) = &classroom_id {
+        refresh_classroom_summary(&state, classroom_id).await;
     }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Borrow,
+        vec![log.clone()],
+        log.handled_by.clone(),
+    ));
+    Ok((StatusCode::OK, Json(KeyTransactionLogResponse::from(log))).into_response())
 }
 
 #[utoipa::path(
     post,
     tags = ["Key"],
-    description = "Return a key",
+    description = "Return a key. The borrower is emailed a receipt.",
     path = "/{id}/return",
     request_body(content = ReturnKeyBody, content_type = "application/json"),
     params(
@@ -364,39 +611,63 @@ pub async fn borrow_key(
     ),
     responses(
         (status = 200, description = "Key returned successfully"),
-        (status = 404, description = "Key transaction log not found"),
-        (status = 500, description = "Failed to return key")
+        (status = 400, description = "Invalid returned_at, or key already returned", body = ErrorResponse),
+        (status = 404, description = "Key transaction log not found", body = ErrorResponse),
+        (status = 409, description = "Key cannot be returned from its current status", body = ErrorResponse),
+        (status = 500, description = "Failed to return key", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn return_key(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    session: AuthSession,
     Json(body): Json<ReturnKeyBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let key_transaction_log_model = match key_transaction_log::Entity::find_by_id(&id)
         .one(&state.db)
         .await
     {
         Ok(Some(k)) => k,
         Ok(None) => {
-            return (StatusCode::NOT_FOUND, "Key transaction log not found").into_response();
+            return Err(AppError::NotFound(
+                "Key transaction log not found".to_string(),
+            ));
         }
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch key transaction log",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch key transaction log".to_string(),
+            ));
         }
     };
 
     if key_transaction_log_model.returned_at.is_some() {
-        return (StatusCode::BAD_REQUEST, "Key already returned").into_response();
+        return Err(AppError::BadRequest("Key already returned".to_string()));
     }
 
+    // The key may have been deleted since; the log is still closed then
+    let key_model = match &key_transaction_log_model.key_id {
+        Some(key_id) => match key::Entity::find_by_id(key_id).one(&state.db).await {
+            Ok(key_model) => key_model,
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch key".to_string()));
+            }
+        },
+        None => None,
+    };
+    let next = match &key_model {
+        Some(key_model) => match next_status(&key_model.status, KeyEvent::Return) {
+            Ok(next) => Some(next),
+            Err(e) => return Err(e.into()),
+        },
+        None => None,
+    };
+
     let deadline = key_transaction_log_model.deadline;
-    let returned_at_parsed = body.returned_at.parse().unwrap();
+    let returned_at_parsed = match parse_dt_field(&body.returned_at, "returned_at") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
 
     let mut key_transaction_log_active: key_transaction_log::ActiveModel =
         key_transaction_log_model.into();
@@ -405,9 +676,95 @@ pub async fn return_key(
         .on_time
         .unwrap_or_else(|| returned_at_parsed <= deadline));
 
-    match key_transaction_log_active.update(&state.db).await {
-        Ok(model) => (StatusCode::OK, Json(KeyTransactionLogResponse::from(model))).into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to return key").into_response(),
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let log = match key_transaction_log_active.update(&txn).await {
+        Ok(model) => model,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to return key".to_string()));
+        }
+    };
+
+    let mut classroom_id = None;
+    if let (Some(key_model), Some(next)) = (key_model, next) {
+        classroom_id = key_model.classroom_id.clone();
+        let mut key_active: key::ActiveModel = key_model.into();
+        key_active.status = Set(next);
+        if versioning::update(&txn, key_active, key::Column::Version, None)
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal("Failed to return key".to_string()));
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal("Failed to return key".to_string()));
+    }
+
+    if let Some(classroom_id) = &classroom_id {
+        refresh_classroom_summary(&state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Return,
+        vec![log.clone()],
+        session.user.map(|u| u.id),
+    ));
+    Ok((StatusCode::OK, Json(KeyTransactionLogResponse::from(log))).into_response())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Report a key as lost. A borrowed key keeps its open transaction log so it can still be returned if the borrower finds it.",
+    path = "/{id}/report-lost",
+    params(
+        ("id" = String, Path, description = "Key ID")
+    ),
+    responses(
+        (status = 200, description = "Key marked as lost", body = KeyResponse),
+        (status = 404, description = "Key not found", body = ErrorResponse),
+        (status = 409, description = "Key cannot be reported lost from its current status", body = ErrorResponse),
+        (status = 500, description = "Failed to update key", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn report_key_lost(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let key_model = match key::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(k)) => k,
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch key".to_string()));
+        }
+    };
+
+    let next = match next_status(&key_model.status, KeyEvent::ReportLost) {
+        Ok(next) => next,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut key_active: key::ActiveModel = key_model.into();
+    key_active.status = Set(next);
+
+    match versioning::update(&state.db, key_active, key::Column::Version, None).await {
+        Ok(updated) => {
+            if let Some(classroom_id) = &updated.classroom_id {
+                refresh_classroom_summary(&state, classroom_id).await;
+            }
+            Ok((StatusCode::OK, Json(KeyResponse::from(updated))).into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to update key".to_string())),
     }
 }
 
@@ -417,18 +774,21 @@ pub async fn return_key(
     description = "List key borrow/return transaction logs (admin)",
     path = "/logs",
     params(
-        KeyLogListQuery
+        KeyLogListQuery,
+        PageParams
     ),
     responses(
-        (status = 200, description = "Logs fetched successfully", body = Vec<KeyTransactionLogResponse>),
-        (status = 500, description = "Failed to fetch logs")
+        (status = 200, description = "Logs fetched successfully", body = PagedResponse<KeyTransactionLogResponse>),
+        (status = 400, description = "Invalid sort", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch logs", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn list_key_logs(
     State(state): State<AppState>,
     Query(q): Query<KeyLogListQuery>,
-) -> impl IntoResponse {
+    pagination: Pagination,
+) -> Result<Response, AppError> {
     let mut stmt = key_transaction_log::Entity::find();
 
     if let Some(reservation_id) = &q.reservation_id {
@@ -443,32 +803,26 @@ pub async fn list_key_logs(
         }
     }
 
-    // sort
-    let sort_desc = q
-        .sort
-        .as_deref()
-        .unwrap_or("desc")
-        .eq_ignore_ascii_case("desc");
-    stmt = if sort_desc {
-        stmt.order_by_desc(key_transaction_log::Column::BorrowedAt)
-    } else {
-        stmt.order_by_asc(key_transaction_log::Column::BorrowedAt)
-    };
+    if let Some(return_requested) = q.return_requested {
+        if return_requested {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnRequestedAt.is_not_null());
+        } else {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnRequestedAt.is_null());
+        }
+    }
 
-    // pagination
-    let page = q.page.unwrap_or(1).max(1);
-    let page_size = q.page_size.unwrap_or(20).clamp(1, 200);
+    match parse_log_sort(q.sort.as_deref()) {
+        Ok(sort) => stmt = sort.apply(stmt),
+        Err(e) => return Err(e),
+    }
 
-    let paginator = stmt.paginate(&state.db, page_size);
-    let models = match paginator.fetch_page(page - 1).await {
-        Ok(v) => v,
-        Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch logs").into_response();
+    match fetch(&state.db, stmt, pagination).await {
+        Ok(logs) => {
+            let resp: PagedResponse<KeyTransactionLogResponse> = logs.map(Into::into);
+            Ok((StatusCode::OK, Json(resp)).into_response())
         }
-    };
-
-    let resp: Vec<KeyTransactionLogResponse> = models.into_iter().map(Into::into).collect();
-    (StatusCode::OK, Json(resp)).into_response()
+        Err(_) => Err(AppError::Internal("Failed to fetch logs".to_string())),
+    }
 }
 
 #[utoipa::path(
@@ -478,12 +832,14 @@ pub async fn list_key_logs(
     path = "/{id}/logs",
     params(
         ("id" = String, Path, description = "Key ID"),
-        KeyLogListQuery
+        KeyLogListQuery,
+        PageParams
     ),
     responses(
-        (status = 200, description = "Logs fetched successfully", body = Vec<KeyTransactionLogResponse>),
-        (status = 404, description = "Key not found"),
-        (status = 500, description = "Failed to fetch logs")
+        (status = 200, description = "Logs fetched successfully", body = PagedResponse<KeyTransactionLogResponse>),
+        (status = 400, description = "Invalid sort", body = ErrorResponse),
+        (status = 404, description = "Key not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch logs", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
@@ -491,17 +847,18 @@ pub async fn list_key_logs_by_key(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Query(q): Query<KeyLogListQuery>,
-) -> impl IntoResponse {
+    pagination: Pagination,
+) -> Result<Response, AppError> {
     match key::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(_)) => {}
-        Ok(None) => return (StatusCode::NOT_FOUND, "Key not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query key").into_response();
+            return Err(AppError::Internal("Failed to query key".to_string()));
         }
     }
 
-    let mut stmt = key_transaction_log::Entity::find()
-        .filter(key_transaction_log::Column::KeyId.eq(&id));
+    let mut stmt =
+        key_transaction_log::Entity::find().filter(key_transaction_log::Column::KeyId.eq(&id));
 
     if let Some(reservation_id) = &q.reservation_id {
         stmt = stmt.filter(key_transaction_log::Column::ReservationId.eq(reservation_id));
@@ -515,34 +872,192 @@ pub async fn list_key_logs_by_key(
         }
     }
 
-    let sort_desc = q
-        .sort
-        .as_deref()
-        .unwrap_or("desc")
-        .eq_ignore_ascii_case("desc");
-    stmt = if sort_desc {
-        stmt.order_by_desc(key_transaction_log::Column::BorrowedAt)
-    } else {
-        stmt.order_by_asc(key_transaction_log::Column::BorrowedAt)
+    if let Some(return_requested) = q.return_requested {
+        if return_requested {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnRequestedAt.is_not_null());
+        } else {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnRequestedAt.is_null());
+        }
+    }
+
+    match parse_log_sort(q.sort.as_deref()) {
+        Ok(sort) => stmt = sort.apply(stmt),
+        Err(e) => return Err(e),
+    }
+
+    match fetch(&state.db, stmt, pagination).await {
+        Ok(logs) => {
+            let resp: PagedResponse<KeyTransactionLogResponse> = logs.map(Into::into);
+            Ok((StatusCode::OK, Json(resp)).into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch logs".to_string())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Key"],
+    description = "Keys the logged-in user has borrowed and not returned yet, soonest deadline first",
+    path = "/self/borrowed",
+    responses(
+        (status = 200, description = "Open key borrows", body = Vec<BorrowedKeyResponse>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch borrowed keys", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_self_borrowed_keys(
+    session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+
+    let logs = match key_transaction_log::Entity::find()
+        .filter(key_transaction_log::Column::BorrowedTo.eq(&user.id))
+        .filter(key_transaction_log::Column::ReturnedAt.is_null())
+        .order_by_asc(key_transaction_log::Column::Deadline)
+        .find_also_related(key::Entity)
+        .all(&state.db)
+        .await
+    {
+        Ok(logs) => logs,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch borrowed keys".to_string(),
+            ));
+        }
     };
 
-    let page = q.page.unwrap_or(1).max(1);
-    let page_size = q.page_size.unwrap_or(20).clamp(1, 200);
+    let now = Utc::now();
+    let resp: Vec<BorrowedKeyResponse> = logs
+        .into_iter()
+        .map(|(log, key_model)| BorrowedKeyResponse {
+            overdue: log.deadline < now,
+            log: log.into(),
+            key: key_model.map(Into::into),
+        })
+        .collect();
+    Ok((StatusCode::OK, Json(resp)).into_response())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Tell staff that a borrowed key was handed back. The log stays open until an admin confirms the return through `/key/{id}/return`; admins find pending requests with `return_requested=true` on `/key/logs`. Asking again keeps the original request time.",
+    path = "/transaction/{id}/request-return",
+    params(
+        ("id" = String, Path, description = "Key transaction log ID")
+    ),
+    responses(
+        (status = 200, description = "Return requested", body = KeyTransactionLogResponse),
+        (status = 400, description = "Key already returned", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Key transaction log not found", body = ErrorResponse),
+        (status = 500, description = "Failed to request return", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn request_key_return(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
 
-    let paginator = stmt.paginate(&state.db, page_size);
-    let models = match paginator.fetch_page(page - 1).await {
-        Ok(v) => v,
+    // Other users' logs are reported as missing rather than forbidden
+    let log = match key_transaction_log::Entity::find_by_id(&id)
+        .filter(key_transaction_log::Column::BorrowedTo.eq(&user.id))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(log)) => log,
+        Ok(None) => {
+            return Err(AppError::NotFound(
+                "Key transaction log not found".to_string(),
+            ));
+        }
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch logs").into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch key transaction log".to_string(),
+            ));
         }
     };
 
-    let resp: Vec<KeyTransactionLogResponse> = models.into_iter().map(Into::into).collect();
-    (StatusCode::OK, Json(resp)).into_response()
+    if log.returned_at.is_some() {
+        return Err(AppError::BadRequest("Key already returned".to_string()));
+    }
+    if log.return_requested_at.is_some() {
+        return Ok((StatusCode::OK, Json(KeyTransactionLogResponse::from(log))).into_response());
+    }
+
+    let mut log_active: key_transaction_log::ActiveModel = log.into();
+    log_active.return_requested_at = Set(Some(Utc::now().with_timezone(&campus_offset())));
+    match log_active.update(&state.db).await {
+        Ok(log) => Ok((StatusCode::OK, Json(KeyTransactionLogResponse::from(log))).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to request return".to_string())),
+    }
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Key", description = "Key endpoints")
+    ),
+    paths(
+        create_key,
+        update_key,
+        delete_key,
+        borrow_key,
+        return_key,
+        report_key_lost,
+        list_key_logs,
+        list_key_logs_by_key,
+        list_self_borrowed_keys,
+        request_key_return,
+        crate::routes::key_borrow::borrow_keys,
+        crate::routes::key_borrow::get_key_borrow,
+        crate::routes::key_borrow::return_borrowed_keys,
+        crate::routes::key_log_export::export_key_logs,
+        crate::routes::key_log_export::get_key_log_stats,
+        crate::routes::key_sync::sync_key_actions,
+        crate::routes::key_cabinet::receive_cabinet_event
+    ),
+    components(schemas(
+        crate::entities::key::Model,
+        crate::entities::classroom::Model,
+        CreateKeyBody,
+        UpdateKeyBody,
+        KeyResponse,
+        BorrowKeyBody,
+        ReturnKeyBody,
+        KeyLogListQuery,
+        KeyTransactionLogResponse,
+        crate::pagination::PagedResponse<KeyTransactionLogResponse>,
+        BorrowedKeyResponse,
+        crate::routes::key_borrow::ReturnKeysBody,
+        crate::routes::key_borrow::KeyBorrowResponse,
+        crate::routes::key_log_export::KeyLogExportQuery,
+        crate::routes::key_log_export::KeyLogStatsQuery,
+        crate::key_log_stats::KeyLogStats,
+        crate::key_log_stats::BorrowCounts,
+        crate::key_log_stats::KeyBorrowStats,
+        crate::key_log_stats::UserBorrowStats,
+        crate::routes::key_sync::KeySyncActionKind,
+        crate::routes::key_sync::KeySyncActionBody,
+        crate::routes::key_sync::KeySyncBody,
+        crate::routes::key_sync::KeySyncStatus,
+        crate::routes::key_sync::KeySyncResult,
+        crate::routes::key_sync::KeySyncResponse,
+        crate::routes::key_cabinet::CabinetEventResponse,
+        crate::key_cabinet::CabinetEvent,
+        crate::key_cabinet::CabinetEventKind,
+        crate::entities::sea_orm_active_enums::KeyStatus
+    ))
+)]
+pub struct KeyApi;
+
 pub fn key_router() -> Router<AppState> {
-    Router::new()
+    let admin_only_route = Router::new()
         .route("/", post(create_key))
         .route("/logs", get(list_key_logs))
         .route("/{id}", put(update_key))
@@ -550,5 +1065,19 @@ pub fn key_router() -> Router<AppState> {
         .route("/{id}/logs", get(list_key_logs_by_key))
         .route("/{id}/borrow", post(borrow_key))
         .route("/{id}/return", post(return_key))
-        .route_layer(permission_required!(AuthBackend, Role::Admin))
+        .route("/{id}/report-lost", post(report_key_lost))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageKeys));
+
+    let login_required_route = Router::new()
+        .route("/self/borrowed", get(list_self_borrowed_keys))
+        .route("/transaction/{id}/request-return", post(request_key_return))
+        .route_layer(login_required!(AuthBackend));
+
+    Router::new()
+        .merge(admin_only_route)
+        .merge(login_required_route)
+        .merge(key_borrow_router())
+        .merge(key_log_export_router())
+        .merge(key_sync_router())
+        .merge(key_cabinet_router())
 }
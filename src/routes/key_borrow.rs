@@ -0,0 +1,477 @@
+use std::collections::HashSet;
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::permission_required;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState, bans,
+    entities::{key, key_borrow, key_transaction_log, reservation},
+    error::{AppError, ErrorResponse},
+    key_lifecycle::{
+        KeyEvent, next_status, refresh_classroom_summary, select_returns, validate_borrow,
+    },
+    key_receipts::{self, ReceiptKind},
+    login_system::{AuthBackend, AuthSession},
+    permissions::Permission,
+    pickup,
+    routes::key::{BorrowKeyBody, KeyTransactionLogResponse},
+    utils::parse_dt_field,
+    versioning,
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct ReturnKeysBody {
+    pub returned_at: String,
+    pub on_time: Option<bool>,
+    /// Keys handed back now; every key still out when omitted
+    pub key_ids: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeyBorrowResponse {
+    pub id: String,
+    pub reservation_id: Option<String>,
+    pub borrowed_to: Option<String>,
+    pub handled_by: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub borrowed_at: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub deadline: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+    /// Whether every key has been returned
+    pub returned: bool,
+    /// One transaction log per key, carrying its own return status
+    pub items: Vec<KeyTransactionLogResponse>,
+}
+
+impl KeyBorrowResponse {
+    fn new(borrow: key_borrow::Model, items: Vec<key_transaction_log::Model>) -> Self {
+        Self {
+            id: borrow.id,
+            reservation_id: borrow.reservation_id,
+            borrowed_to: borrow.borrowed_to,
+            handled_by: borrow.handled_by,
+            borrowed_at: borrow.borrowed_at,
+            deadline: borrow.deadline,
+            created_at: borrow.created_at,
+            returned: items.iter().all(|i| i.returned_at.is_some()),
+            items: items
+                .into_iter()
+                .map(KeyTransactionLogResponse::from)
+                .collect(),
+        }
+    }
+}
+
+async fn borrow_items(
+    db: &DatabaseConnection,
+    borrow_id: &str,
+) -> Result<Vec<key_transaction_log::Model>, DbErr> {
+    key_transaction_log::Entity::find()
+        .filter(key_transaction_log::Column::BorrowId.eq(borrow_id))
+        .order_by_asc(key_transaction_log::Column::Id)
+        .all(db)
+        .await
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Borrow several keys of one classroom together, e.g. a door key and a cabinet key. Creates one borrow with a transaction log per key; either every key is handed out or none is. The borrower is emailed one receipt for all keys.",
+    path = "/borrow",
+    request_body(content = BorrowKeyBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Keys borrowed successfully", body = KeyBorrowResponse),
+        (status = 400, description = "Invalid borrowed_at or deadline, or no or duplicate keys given", body = ErrorResponse),
+        (status = 403, description = "Pickup code missing or wrong, or the reservation holder is blacklisted", body = ErrorResponse),
+        (status = 404, description = "Key or reservation not found", body = ErrorResponse),
+        (status = 409, description = "A key is not available to borrow", body = ErrorResponse),
+        (status = 422, description = "The reservation does not entitle the borrower to a key, or its pickup code sets aside a key not listed", body = ErrorResponse),
+        (status = 500, description = "Failed to borrow keys", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn borrow_keys(
+    State(state): State<AppState>,
+    session: AuthSession,
+    Json(body): Json<BorrowKeyBody>,
+) -> Result<Response, AppError> {
+    if body.key_ids.is_empty() {
+        return Err(AppError::BadRequest(
+            "key_ids must not be empty".to_string(),
+        ));
+    }
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = body.key_ids.iter().find(|id| !seen.insert(*id)) {
+        return Err(AppError::BadRequest(format!(
+            "Key {} is listed more than once",
+            duplicate
+        )));
+    }
+
+    let borrowed_at = match parse_dt_field(&body.borrowed_at, "borrowed_at") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let deadline = match parse_dt_field(&body.deadline, "deadline") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+
+    let reservation_model = match reservation::Entity::find_by_id(&body.reservation_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    if let Some(user_id) = &reservation_model.user_id {
+        match bans::ensure_not_banned(&state.db, user_id).await {
+            Ok(Ok(())) => {}
+            Ok(Err(message)) => return Err(AppError::Forbidden(message)),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to check blacklist".to_string()));
+            }
+        }
+    }
+
+    let keys = match key::Entity::find()
+        .filter(key::Column::Id.is_in(body.key_ids.clone()))
+        .all(&state.db)
+        .await
+    {
+        Ok(keys) => keys,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch keys".to_string()));
+        }
+    };
+
+    // Validated in request order so the error names the first offending key
+    let mut to_borrow = Vec::with_capacity(body.key_ids.len());
+    for key_id in &body.key_ids {
+        let Some(key_model) = keys.iter().find(|k| &k.id == key_id) else {
+            return Err(AppError::NotFound(format!("Key {} not found", key_id)));
+        };
+        let next = match next_status(&key_model.status, KeyEvent::Borrow) {
+            Ok(next) => next,
+            Err(e) => {
+                return Err(AppError::Conflict(format!(
+                    "Key {}: {}",
+                    key_model.key_number,
+                    e.message()
+                )));
+            }
+        };
+        if let Err(e) = validate_borrow(key_model, &reservation_model, borrowed_at) {
+            return Err(AppError::Unprocessable(format!(
+                "Key {}: {}",
+                key_model.key_number,
+                e.message()
+            )));
+        }
+        to_borrow.push((key_model.clone(), next));
+    }
+    let pickup_code = match pickup::active_code(&state.db, &reservation_model.id).await {
+        Ok(code) => code,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch pickup code".to_string(),
+            ));
+        }
+    };
+    if let Err(e) = pickup::verify(
+        pickup_code.as_ref(),
+        body.pickup_code.as_deref(),
+        &body.key_ids,
+    ) {
+        return Err(e.into());
+    }
+
+    let borrowed_to = reservation_model.user_id.clone();
+    let handled_by = session.user.unwrap().id;
+    let new_borrow = key_borrow::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(Some(reservation_model.id.clone())),
+        borrowed_to: Set(borrowed_to.clone()),
+        handled_by: Set(Some(handled_by.clone())),
+        borrowed_at: Set(borrowed_at),
+        deadline: Set(deadline),
+        created_at: NotSet,
+    };
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let borrow = match new_borrow.insert(&txn).await {
+        Ok(borrow) => borrow,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to borrow keys".to_string()));
+        }
+    };
+
+    let mut items = Vec::with_capacity(to_borrow.len());
+    let mut classroom_ids = HashSet::new();
+    for (key_model, next) in to_borrow {
+        let new_log = key_transaction_log::ActiveModel {
+            id: Set(nanoid!()),
+            reservation_id: Set(Some(reservation_model.id.clone())),
+            key_id: Set(Some(key_model.id.clone())),
+            borrowed_to: Set(borrowed_to.clone()),
+            handled_by: Set(Some(handled_by.clone())),
+            borrowed_at: Set(borrowed_at),
+            deadline: Set(deadline),
+            returned_at: NotSet,
+            on_time: NotSet,
+            created_at: NotSet,
+            overdue_at: NotSet,
+            second_notice_at: NotSet,
+            escalated_at: NotSet,
+            return_requested_at: NotSet,
+            borrow_id: Set(Some(borrow.id.clone())),
+        };
+        match new_log.insert(&txn).await {
+            Ok(log) => items.push(log),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to borrow keys".to_string()));
+            }
+        }
+
+        if let Some(classroom_id) = &key_model.classroom_id {
+            classroom_ids.insert(classroom_id.clone());
+        }
+        let mut key_active: key::ActiveModel = key_model.into();
+        key_active.status = Set(next);
+        if versioning::update(&txn, key_active, key::Column::Version, None)
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal("Failed to borrow keys".to_string()));
+        }
+    }
+
+    // The code is tied to the borrow through the first key's log
+    if let (Some(code), Some(first)) = (pickup_code, items.first())
+        && pickup::mark_used(&txn, code, &first.id).await.is_err()
+    {
+        return Err(AppError::Internal("Failed to borrow keys".to_string()));
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal("Failed to borrow keys".to_string()));
+    }
+
+    for classroom_id in &classroom_ids {
+        refresh_classroom_summary(&state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Borrow,
+        items.clone(),
+        borrow.handled_by.clone(),
+    ));
+    Ok((StatusCode::OK, Json(KeyBorrowResponse::new(borrow, items))).into_response())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Key"],
+    description = "Get a multi-key borrow with the return status of each key",
+    path = "/borrow/{id}",
+    params(
+        ("id" = String, Path, description = "Borrow ID")
+    ),
+    responses(
+        (status = 200, description = "Borrow fetched successfully", body = KeyBorrowResponse),
+        (status = 404, description = "Borrow not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch borrow", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_key_borrow(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let borrow = match key_borrow::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(borrow)) => borrow,
+        Ok(None) => return Err(AppError::NotFound("Borrow not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch borrow".to_string()));
+        }
+    };
+    match borrow_items(&state.db, &borrow.id).await {
+        Ok(items) => {
+            Ok((StatusCode::OK, Json(KeyBorrowResponse::new(borrow, items))).into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch borrow".to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Return some or all keys of a multi-key borrow. Keys not listed stay out; the borrow counts as returned once every key is back. The borrower is emailed a receipt for the keys returned.",
+    path = "/borrow/{id}/return",
+    request_body(content = ReturnKeysBody, content_type = "application/json"),
+    params(
+        ("id" = String, Path, description = "Borrow ID")
+    ),
+    responses(
+        (status = 200, description = "Keys returned successfully", body = KeyBorrowResponse),
+        (status = 400, description = "Invalid returned_at, or a key is not part of the borrow or already returned", body = ErrorResponse),
+        (status = 404, description = "Borrow not found", body = ErrorResponse),
+        (status = 409, description = "A key cannot be returned from its current status", body = ErrorResponse),
+        (status = 500, description = "Failed to return keys", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn return_borrowed_keys(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    session: AuthSession,
+    Json(body): Json<ReturnKeysBody>,
+) -> Result<Response, AppError> {
+    let borrow = match key_borrow::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(borrow)) => borrow,
+        Ok(None) => return Err(AppError::NotFound("Borrow not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch borrow".to_string()));
+        }
+    };
+    let items = match borrow_items(&state.db, &borrow.id).await {
+        Ok(items) => items,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch borrow".to_string()));
+        }
+    };
+
+    let returned_at = match parse_dt_field(&body.returned_at, "returned_at") {
+        Ok(dt) => dt,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let selected = match select_returns(&items, body.key_ids.as_deref()) {
+        Ok(selected) => selected,
+        Err(e) => return Err(e.into()),
+    };
+
+    // Keys deleted since the borrow have their log closed all the same
+    let key_ids: Vec<String> = selected.iter().filter_map(|i| i.key_id.clone()).collect();
+    let keys = match key::Entity::find()
+        .filter(key::Column::Id.is_in(key_ids))
+        .all(&state.db)
+        .await
+    {
+        Ok(keys) => keys,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch keys".to_string()));
+        }
+    };
+    let mut key_updates = Vec::with_capacity(keys.len());
+    for key_model in keys {
+        match next_status(&key_model.status, KeyEvent::Return) {
+            Ok(next) => key_updates.push((key_model, next)),
+            Err(e) => {
+                return Err(AppError::Conflict(format!(
+                    "Key {}: {}",
+                    key_model.key_number,
+                    e.message()
+                )));
+            }
+        }
+    }
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let mut returned = Vec::with_capacity(selected.len());
+    for item in selected {
+        let deadline = item.deadline;
+        let mut item_active: key_transaction_log::ActiveModel = item.clone().into();
+        item_active.returned_at = Set(Some(returned_at));
+        item_active.on_time = Set(body.on_time.unwrap_or(returned_at <= deadline));
+        match item_active.update(&txn).await {
+            Ok(item) => returned.push(item),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to return keys".to_string()));
+            }
+        }
+    }
+
+    let mut classroom_ids = HashSet::new();
+    for (key_model, next) in key_updates {
+        if let Some(classroom_id) = &key_model.classroom_id {
+            classroom_ids.insert(classroom_id.clone());
+        }
+        let mut key_active: key::ActiveModel = key_model.into();
+        key_active.status = Set(next);
+        if versioning::update(&txn, key_active, key::Column::Version, None)
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal("Failed to return keys".to_string()));
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal("Failed to return keys".to_string()));
+    }
+
+    for classroom_id in &classroom_ids {
+        refresh_classroom_summary(&state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Return,
+        returned,
+        session.user.map(|u| u.id),
+    ));
+    match borrow_items(&state.db, &borrow.id).await {
+        Ok(items) => {
+            Ok((StatusCode::OK, Json(KeyBorrowResponse::new(borrow, items))).into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch borrow".to_string())),
+    }
+}
+
+pub fn key_borrow_router() -> Router<AppState> {
+    Router::new()
+        .route("/borrow", post(borrow_keys))
+        .route("/borrow/{id}", get(get_key_borrow))
+        .route("/borrow/{id}/return", post(return_borrowed_keys))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageKeys))
+}
@@ -0,0 +1,232 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    EntityTrait, TransactionTrait,
+};
+use serde::Serialize;
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    entities::{
+        key, key_cabinet_pin, key_transaction_log, reservation,
+        sea_orm_active_enums::CabinetPinStatus,
+    },
+    error::{AppError, ErrorResponse},
+    key_cabinet::{CabinetEvent, CabinetEventKind, occurred_at},
+    key_lifecycle::{KeyEvent, next_status, refresh_classroom_summary},
+    key_receipts::{self, ReceiptKind},
+    versioning,
+    webhook::verify_request,
+};
+
+pub const SIGNATURE_HEADER: &str = "X-Cabinet-Signature";
+
+#[derive(Serialize, ToSchema)]
+pub struct CabinetEventResponse {
+    /// Borrow recorded for a pickup; `None` for ignored events
+    pub key_transaction_log_id: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Callback from the smart key cabinet. A `pickup` event records the borrow for the reservation the PIN was issued to, with no staff involved. The raw body must be signed in the `X-Cabinet-Signature` header as `t=<unix seconds>,v1=<hex HMAC-SHA256 of \"t.body\">`. Repeated deliveries of the same pickup are no-ops.",
+    path = "/cabinet/events",
+    request_body(content = CabinetEvent, content_type = "application/json"),
+    params(
+        ("X-Cabinet-Signature" = String, Header, description = "Signature of the raw body")
+    ),
+    responses(
+        (status = 200, description = "Event processed", body = CabinetEventResponse),
+        (status = 400, description = "Invalid payload", body = ErrorResponse),
+        (status = 401, description = "Missing, stale or invalid signature", body = ErrorResponse),
+        (status = 404, description = "Unknown PIN reference", body = ErrorResponse),
+        (status = 409, description = "Key cannot be borrowed from its current status", body = ErrorResponse),
+        (status = 422, description = "Slot does not match the PIN", body = ErrorResponse),
+        (status = 503, description = "Key cabinet is not configured", body = ErrorResponse),
+        (status = 500, description = "Failed to record pickup", body = ErrorResponse)
+    )
+)]
+pub async fn receive_cabinet_event(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let config = &state.config.key_cabinet;
+    let Some(secret) = &config.webhook_secret else {
+        return Err(AppError::ServiceUnavailable(
+            "Key cabinet is not configured".to_string(),
+        ));
+    };
+    if let Err(e) = verify_request(
+        secret,
+        &headers,
+        SIGNATURE_HEADER,
+        &body,
+        config.webhook_tolerance,
+    ) {
+        return Err(e.into());
+    }
+
+    let event: CabinetEvent = match serde_json::from_slice(&body) {
+        Ok(event) => event,
+        Err(_) => return Err(AppError::BadRequest("Invalid payload".to_string())),
+    };
+    if event.kind != CabinetEventKind::Pickup {
+        return Ok((
+            StatusCode::OK,
+            Json(CabinetEventResponse {
+                key_transaction_log_id: None,
+            }),
+        )
+            .into_response());
+    }
+
+    let pin = match key_cabinet_pin::Entity::find_by_id(&event.reference)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(pin)) => pin,
+        Ok(None) => return Err(AppError::NotFound("Unknown PIN reference".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch PIN".to_string()));
+        }
+    };
+    if pin.slot != event.slot {
+        return Err(AppError::Unprocessable(
+            "Slot does not match the PIN".to_string(),
+        ));
+    }
+    if pin.status == CabinetPinStatus::PickedUp {
+        return Ok((
+            StatusCode::OK,
+            Json(CabinetEventResponse {
+                key_transaction_log_id: pin.key_transaction_log_id,
+            }),
+        )
+            .into_response());
+    }
+    // The key is physically out, so the borrow is recorded even if the PIN
+    // was revoked in the meantime
+    if pin.status != CabinetPinStatus::Issued {
+        warn!(
+            "Pickup reported for {:?} cabinet PIN {}",
+            pin.status, pin.id
+        );
+    }
+
+    let (Some(key_id), Some(reservation_id)) = (&pin.key_id, &pin.reservation_id) else {
+        return Err(AppError::NotFound(
+            "Key or reservation of the PIN no longer exists".to_string(),
+        ));
+    };
+    let key_model = match key::Entity::find_by_id(key_id).one(&state.db).await {
+        Ok(Some(k)) => k,
+        Ok(None) => return Err(AppError::NotFound("Key not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch key".to_string()));
+        }
+    };
+    let reservation_model = match reservation::Entity::find_by_id(reservation_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+
+    let next = match next_status(&key_model.status, KeyEvent::Borrow) {
+        Ok(next) => next,
+        Err(e) => return Err(e.into()),
+    };
+
+    let picked_up_at = occurred_at(&event);
+    let new_log = key_transaction_log::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(Some(reservation_model.id.clone())),
+        key_id: Set(Some(key_model.id.clone())),
+        borrowed_to: Set(reservation_model.user_id.clone()),
+        handled_by: Set(None),
+        borrowed_at: Set(picked_up_at),
+        deadline: Set(reservation_model.end_time),
+        returned_at: NotSet,
+        on_time: NotSet,
+        created_at: NotSet,
+        overdue_at: NotSet,
+        second_notice_at: NotSet,
+        escalated_at: NotSet,
+        return_requested_at: NotSet,
+        borrow_id: NotSet,
+    };
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    let log = match new_log.insert(&txn).await {
+        Ok(log) => log,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to record pickup".to_string()));
+        }
+    };
+
+    let classroom_id = key_model.classroom_id.clone();
+    let mut key_active: key::ActiveModel = key_model.into();
+    key_active.status = Set(next);
+
+    let mut pin_active: key_cabinet_pin::ActiveModel = pin.into();
+    pin_active.status = Set(CabinetPinStatus::PickedUp);
+    pin_active.picked_up_at = Set(Some(picked_up_at));
+    pin_active.key_transaction_log_id = Set(Some(log.id.clone()));
+
+    if versioning::update(&txn, key_active, key::Column::Version, None)
+        .await
+        .is_err()
+        || pin_active.update(&txn).await.is_err()
+        || txn.commit().await.is_err()
+    {
+        return Err(AppError::Internal("Failed to record pickup".to_string()));
+    }
+
+    if let Some(classroom_id) = &classroom_id {
+        refresh_classroom_summary(&state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Borrow,
+        vec![log.clone()],
+        None,
+    ));
+    Ok((
+        StatusCode::OK,
+        Json(CabinetEventResponse {
+            key_transaction_log_id: Some(log.id),
+        }),
+    )
+        .into_response())
+}
+
+pub fn key_cabinet_router() -> Router<AppState> {
+    Router::new().route("/cabinet/events", post(receive_cabinet_event))
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use chrono::{Duration, Utc};
+use futures_util::stream;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Select, prelude::DateTimeWithTimeZone,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    concurrency::limit_reports,
+    entities::{key, key_transaction_log, user},
+    error::{AppError, ErrorResponse},
+    export::{EXPORT_CHUNK_SIZE, ExportFormat, csv_line, download, parse_filter},
+    key_log_stats::{KeyLogStats, summarize},
+    login_system::AuthBackend,
+    permissions::Permission,
+};
+
+/// Window `/logs/stats` covers when `from` is omitted.
+const DEFAULT_STATS_DAYS: i64 = 90;
+
+const CSV_HEADER: [&str; 13] = [
+    "id",
+    "key_id",
+    "key_number",
+    "classroom_id",
+    "reservation_id",
+    "borrowed_to",
+    "borrower_name",
+    "handled_by",
+    "borrowed_at",
+    "deadline",
+    "returned_at",
+    "on_time",
+    "overdue_at",
+];
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct KeyLogExportQuery {
+    /// Borrowed at or after this time
+    pub from: Option<String>,
+    /// Borrowed before this time
+    pub to: Option<String>,
+    pub key_id: Option<String>,
+    /// Borrower user ID
+    pub user_id: Option<String>,
+    pub returned: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct KeyLogStatsQuery {
+    /// Borrowed at or after this time; defaults to 90 days before `to`
+    pub from: Option<String>,
+    /// Borrowed before this time; defaults to now
+    pub to: Option<String>,
+}
+
+fn filtered_logs(q: &KeyLogExportQuery) -> Result<Select<key_transaction_log::Entity>, String> {
+    let mut stmt = key_transaction_log::Entity::find();
+    if let Some(from) = parse_filter(&q.from, "from")? {
+        stmt = stmt.filter(key_transaction_log::Column::BorrowedAt.gte(from));
+    }
+    if let Some(to) = parse_filter(&q.to, "to")? {
+        stmt = stmt.filter(key_transaction_log::Column::BorrowedAt.lt(to));
+    }
+    if let Some(key_id) = &q.key_id {
+        stmt = stmt.filter(key_transaction_log::Column::KeyId.eq(key_id));
+    }
+    if let Some(user_id) = &q.user_id {
+        stmt = stmt.filter(key_transaction_log::Column::BorrowedTo.eq(user_id));
+    }
+    if let Some(returned) = q.returned {
+        if returned {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnedAt.is_not_null());
+        } else {
+            stmt = stmt.filter(key_transaction_log::Column::ReturnedAt.is_null());
+        }
+    }
+    Ok(stmt)
+}
+
+async fn key_numbers(
+    db: &DatabaseConnection,
+    ids: Vec<String>,
+) -> Result<HashMap<String, (String, Option<String>)>, DbErr> {
+    Ok(key::Entity::find()
+        .select_only()
+        .column(key::Column::Id)
+        .column(key::Column::KeyNumber)
+        .column(key::Column::ClassroomId)
+        .filter(key::Column::Id.is_in(ids))
+        .into_tuple::<(String, String, Option<String>)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(id, key_number, classroom_id)| (id, (key_number, classroom_id)))
+        .collect())
+}
+
+async fn user_names(
+    db: &DatabaseConnection,
+    ids: Vec<String>,
+) -> Result<HashMap<String, String>, DbErr> {
+    Ok(user::Entity::find()
+        .select_only()
+        .column(user::Column::Id)
+        .column(user::Column::Name)
+        .filter(user::Column::Id.is_in(ids))
+        .into_tuple::<(String, String)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+async fn csv_rows(
+    db: &DatabaseConnection,
+    logs: &[key_transaction_log::Model],
+) -> Result<String, DbErr> {
+    let keys = key_numbers(db, logs.iter().filter_map(|l| l.key_id.clone()).collect()).await?;
+    let names = user_names(
+        db,
+        logs.iter().filter_map(|l| l.borrowed_to.clone()).collect(),
+    )
+    .await?;
+
+    let mut out = String::new();
+    for log in logs {
+        let key = log.key_id.as_ref().and_then(|id| keys.get(id));
+        out.push_str(&csv_line(&[
+            log.id.clone(),
+            log.key_id.clone().unwrap_or_default(),
+            key.map(|(number, _)| number.clone()).unwrap_or_default(),
+            key.and_then(|(_, classroom_id)| classroom_id.clone())
+                .unwrap_or_default(),
+            log.reservation_id.clone().unwrap_or_default(),
+            log.borrowed_to.clone().unwrap_or_default(),
+            log.borrowed_to
+                .as_ref()
+                .and_then(|id| names.get(id).cloned())
+                .unwrap_or_default(),
+            log.handled_by.clone().unwrap_or_default(),
+            log.borrowed_at.to_rfc3339(),
+            log.deadline.to_rfc3339(),
+            log.returned_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            match log.returned_at {
+                Some(_) => log.on_time.to_string(),
+                None => String::new(),
+            },
+            log.overdue_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+        ]));
+    }
+    Ok(out)
+}
+
+struct ExportCursor {
+    db: DatabaseConnection,
+    query: Select<key_transaction_log::Entity>,
+    last: Option<(DateTimeWithTimeZone, String)>,
+    first_chunk: bool,
+    done: bool,
+}
+
+/// Next chunk of the export in borrow order, keyed on `(borrowed_at, id)` so
+/// borrows recorded during the download do not shift the pages.
+async fn next_chunk(
+    mut cursor: ExportCursor,
+) -> Option<(Result<Bytes, std::io::Error>, ExportCursor)> {
+    if cursor.done {
+        return None;
+    }
+
+    let mut stmt = cursor
+        .query
+        .clone()
+        .order_by_asc(key_transaction_log::Column::BorrowedAt)
+        .order_by_asc(key_transaction_log::Column::Id)
+        .limit(EXPORT_CHUNK_SIZE);
+    if let Some((borrowed_at, id)) = &cursor.last {
+        stmt = stmt.filter(
+            Condition::any()
+                .add(key_transaction_log::Column::BorrowedAt.gt(*borrowed_at))
+                .add(
+                    Condition::all()
+                        .add(key_transaction_log::Column::BorrowedAt.eq(*borrowed_at))
+                        .add(key_transaction_log::Column::Id.gt(id.as_str())),
+                ),
+        );
+    }
+    let chunk = match stmt.all(&cursor.db).await {
+        Ok(logs) => {
+            cursor.done = (logs.len() as u64) < EXPORT_CHUNK_SIZE;
+            cursor.last = logs.last().map(|l| (l.borrowed_at, l.id.clone()));
+            csv_rows(&cursor.db, &logs).await
+        }
+        Err(e) => Err(e),
+    };
+    let chunk = match chunk {
+        Ok(chunk) => chunk,
+        Err(e) => {
+            cursor.done = true;
+            return Some((Err(std::io::Error::other(e.to_string())), cursor));
+        }
+    };
+
+    let mut out = String::new();
+    if cursor.first_chunk {
+        out.push_str(&csv_line(&CSV_HEADER));
+        cursor.first_chunk = false;
+    }
+    out.push_str(&chunk);
+    Some((Ok(Bytes::from(out)), cursor))
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Key"],
+    description = "Stream key transaction logs as CSV in borrow order, with key numbers and borrower names resolved. Shares the report concurrency limit.",
+    path = "/logs/export",
+    params(KeyLogExportQuery),
+    responses(
+        (status = 200, description = "Key log export", content_type = "text/csv"),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn export_key_logs(
+    State(state): State<AppState>,
+    Query(q): Query<KeyLogExportQuery>,
+) -> Result<Response, AppError> {
+    let query = match filtered_logs(&q) {
+        Ok(query) => query,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let cursor = ExportCursor {
+        db: state.db.clone(),
+        query,
+        last: None,
+        first_chunk: true,
+        done: false,
+    };
+    Ok(download(
+        ExportFormat::Csv,
+        "key-logs",
+        stream::unfold(cursor, next_chunk),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Key"],
+    description = "Borrow counts with on-time and late returns per key and per borrower for keys borrowed in `[from, to)`. Keys still out past their deadline count as overdue. Both lists are ordered by late returns plus overdue keys, so chronic late returners come first.",
+    path = "/logs/stats",
+    params(KeyLogStatsQuery),
+    responses(
+        (status = 200, description = "Key log statistics", body = KeyLogStats),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse),
+        (status = 500, description = "Failed to compute statistics", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_key_log_stats(
+    State(state): State<AppState>,
+    Query(q): Query<KeyLogStatsQuery>,
+) -> Result<Response, AppError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let to = match parse_filter(&q.to, "to") {
+        Ok(to) => to.unwrap_or(now),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let from = match parse_filter(&q.from, "from") {
+        Ok(from) => from.unwrap_or(to - Duration::days(DEFAULT_STATS_DAYS)),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be < 'to'".to_string()));
+    }
+
+    let logs = match key_transaction_log::Entity::find()
+        .filter(key_transaction_log::Column::BorrowedAt.gte(from))
+        .filter(key_transaction_log::Column::BorrowedAt.lt(to))
+        .all(&state.db)
+        .await
+    {
+        Ok(logs) => logs,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to compute statistics".to_string(),
+            ));
+        }
+    };
+
+    let keys = key_numbers(
+        &state.db,
+        logs.iter().filter_map(|l| l.key_id.clone()).collect(),
+    )
+    .await;
+    let names = user_names(
+        &state.db,
+        logs.iter().filter_map(|l| l.borrowed_to.clone()).collect(),
+    )
+    .await;
+    let (keys, names) = match (keys, names) {
+        (Ok(keys), Ok(names)) => (keys, names),
+        _ => {
+            return Err(AppError::Internal(
+                "Failed to compute statistics".to_string(),
+            ));
+        }
+    };
+    let key_numbers = keys
+        .into_iter()
+        .map(|(id, (key_number, _))| (id, key_number))
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(summarize(&logs, &key_numbers, &names, from, to, now)),
+    )
+        .into_response())
+}
+
+pub fn key_log_export_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/logs/export",
+            get(export_key_logs).layer(middleware::from_fn(limit_reports)),
+        )
+        .route(
+            "/logs/stats",
+            get(get_key_log_stats).layer(middleware::from_fn(limit_reports)),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageKeys))
+}
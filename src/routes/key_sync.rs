@@ -0,0 +1,472 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_login::permission_required;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState, bans,
+    entities::{key, key_sync_action, key_transaction_log, reservation},
+    error::{AppError, ErrorResponse},
+    key_lifecycle::{KeyEvent, next_status, refresh_classroom_summary, validate_borrow},
+    key_receipts::{self, ReceiptKind},
+    login_system::{AuthBackend, AuthSession},
+    permissions::Permission,
+    routes::key::KeyTransactionLogResponse,
+    utils::parse_dt_field,
+    versioning,
+};
+
+// ===============================
+//   Request / Response bodies
+// ===============================
+#[derive(Deserialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySyncActionKind {
+    Borrow,
+    Return,
+}
+
+impl KeySyncActionKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            KeySyncActionKind::Borrow => "borrow",
+            KeySyncActionKind::Return => "return",
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct KeySyncActionBody {
+    /// Generated by the desk app; re-sending the same id is a no-op
+    pub client_action_id: String,
+    pub action: KeySyncActionKind,
+    pub key_id: String,
+    /// When the action happened at the desk (ISO8601 or "YYYY-MM-DD HH:MM")
+    pub recorded_at: String,
+    /// Required for borrow
+    pub reservation_id: Option<String>,
+    /// Borrow only; defaults to the reservation's end time
+    pub deadline: Option<String>,
+    /// Return only; defaults to comparing recorded_at with the deadline
+    pub on_time: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct KeySyncBody {
+    pub actions: Vec<KeySyncActionBody>,
+}
+
+#[derive(Serialize, ToSchema, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySyncStatus {
+    Applied,
+    Duplicate,
+    Failed,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeySyncResult {
+    pub client_action_id: String,
+    pub status: KeySyncStatus,
+    pub log: Option<KeyTransactionLogResponse>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct KeySyncResponse {
+    pub applied: usize,
+    pub duplicates: usize,
+    pub failed: usize,
+    /// One entry per submitted action, in submission order
+    pub results: Vec<KeySyncResult>,
+}
+
+enum SyncError {
+    Rejected(String),
+    Database(&'static str),
+}
+
+impl SyncError {
+    fn message(self) -> String {
+        match self {
+            SyncError::Rejected(message) => message,
+            SyncError::Database(message) => message.to_string(),
+        }
+    }
+}
+
+// ===============================
+//   Action application
+// ===============================
+async fn open_log_for_key<C: ConnectionTrait>(
+    db: &C,
+    key_id: &str,
+) -> Result<Option<key_transaction_log::Model>, SyncError> {
+    key_transaction_log::Entity::find()
+        .filter(key_transaction_log::Column::KeyId.eq(key_id))
+        .filter(key_transaction_log::Column::ReturnedAt.is_null())
+        .order_by_desc(key_transaction_log::Column::BorrowedAt)
+        .one(db)
+        .await
+        .map_err(|_| SyncError::Database("Failed to fetch key transaction log"))
+}
+
+async fn apply_borrow(
+    state: &AppState,
+    handled_by: &str,
+    action: &KeySyncActionBody,
+    recorded_at: DateTimeWithTimeZone,
+) -> Result<key_transaction_log::Model, SyncError> {
+    let key_model = match key::Entity::find_by_id(&action.key_id).one(&state.db).await {
+        Ok(Some(k)) => k,
+        Ok(None) => return Err(SyncError::Rejected("Key not found".to_string())),
+        Err(_) => return Err(SyncError::Database("Failed to fetch key")),
+    };
+
+    let next = next_status(&key_model.status, KeyEvent::Borrow)
+        .map_err(|e| SyncError::Rejected(e.message()))?;
+
+    let Some(reservation_id) = &action.reservation_id else {
+        return Err(SyncError::Rejected(
+            "reservation_id is required for borrow".to_string(),
+        ));
+    };
+
+    let reservation_model = match reservation::Entity::find_by_id(reservation_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err(SyncError::Rejected("Reservation not found".to_string())),
+        Err(_) => return Err(SyncError::Database("Failed to fetch reservation")),
+    };
+
+    validate_borrow(&key_model, &reservation_model, recorded_at)
+        .map_err(|e| SyncError::Rejected(e.message()))?;
+    if let Some(user_id) = &reservation_model.user_id {
+        bans::ensure_not_banned(&state.db, user_id)
+            .await
+            .map_err(|_| SyncError::Database("Failed to check blacklist"))?
+            .map_err(SyncError::Rejected)?;
+    }
+
+    let deadline = match &action.deadline {
+        Some(deadline) => parse_dt_field(deadline, "deadline").map_err(SyncError::Rejected)?,
+        None => reservation_model.end_time,
+    };
+
+    if deadline <= recorded_at {
+        return Err(SyncError::Rejected(
+            "deadline must be after recorded_at".to_string(),
+        ));
+    }
+
+    let txn = state
+        .db
+        .begin()
+        .await
+        .map_err(|_| SyncError::Database("Failed to start transaction"))?;
+
+    // Checked inside the transaction, and the key is only updated at the
+    // version read above, so a borrow committed in between is not doubled
+    if open_log_for_key(&txn, &action.key_id).await?.is_some() {
+        return Err(SyncError::Rejected(
+            "Key is already borrowed and not yet returned".to_string(),
+        ));
+    }
+
+    let new_log = key_transaction_log::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(Some(reservation_model.id)),
+        key_id: Set(Some(key_model.id.clone())),
+        borrowed_to: Set(reservation_model.user_id),
+        handled_by: Set(Some(handled_by.to_string())),
+        borrowed_at: Set(recorded_at),
+        deadline: Set(deadline),
+        returned_at: NotSet,
+        on_time: NotSet,
+        created_at: NotSet,
+        overdue_at: NotSet,
+        second_notice_at: NotSet,
+        escalated_at: NotSet,
+        return_requested_at: NotSet,
+        borrow_id: NotSet,
+    };
+
+    let log = new_log
+        .insert(&txn)
+        .await
+        .map_err(|_| SyncError::Database("Failed to borrow key"))?;
+
+    let classroom_id = key_model.classroom_id.clone();
+    let version = key_model.version;
+    let mut key_active: key::ActiveModel = key_model.into();
+    key_active.status = Set(next);
+    versioning::update(&txn, key_active, key::Column::Version, Some(version))
+        .await
+        .map_err(|e| match e {
+            DbErr::RecordNotUpdated => {
+                SyncError::Rejected("Key changed while borrowing it; sync again".to_string())
+            }
+            _ => SyncError::Database("Failed to borrow key"),
+        })?;
+
+    record_action(&txn, handled_by, action, recorded_at, &log.id).await?;
+
+    txn.commit()
+        .await
+        .map_err(|_| SyncError::Database("Failed to commit key sync"))?;
+
+    if let Some(classroom_id) = &classroom_id {
+        refresh_classroom_summary(state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Borrow,
+        vec![log.clone()],
+        log.handled_by.clone(),
+    ));
+    Ok(log)
+}
+
+async fn apply_return(
+    state: &AppState,
+    handled_by: &str,
+    action: &KeySyncActionBody,
+    recorded_at: DateTimeWithTimeZone,
+) -> Result<key_transaction_log::Model, SyncError> {
+    let open_log = match open_log_for_key(&state.db, &action.key_id).await? {
+        Some(log) => log,
+        None => {
+            return Err(SyncError::Rejected(
+                "Key is not currently borrowed".to_string(),
+            ));
+        }
+    };
+
+    if recorded_at < open_log.borrowed_at {
+        return Err(SyncError::Rejected(
+            "Return is recorded before the key was borrowed".to_string(),
+        ));
+    }
+
+    let key_model = match key::Entity::find_by_id(&action.key_id).one(&state.db).await {
+        Ok(Some(k)) => k,
+        Ok(None) => return Err(SyncError::Rejected("Key not found".to_string())),
+        Err(_) => return Err(SyncError::Database("Failed to fetch key")),
+    };
+    let next = next_status(&key_model.status, KeyEvent::Return)
+        .map_err(|e| SyncError::Rejected(e.message()))?;
+
+    let deadline = open_log.deadline;
+    let txn = state
+        .db
+        .begin()
+        .await
+        .map_err(|_| SyncError::Database("Failed to start transaction"))?;
+
+    let mut log_active: key_transaction_log::ActiveModel = open_log.into();
+    log_active.returned_at = Set(Some(recorded_at));
+    log_active.on_time = Set(action.on_time.unwrap_or(recorded_at <= deadline));
+
+    let log = log_active
+        .update(&txn)
+        .await
+        .map_err(|_| SyncError::Database("Failed to return key"))?;
+
+    let classroom_id = key_model.classroom_id.clone();
+    let mut key_active: key::ActiveModel = key_model.into();
+    key_active.status = Set(next);
+    versioning::update(&txn, key_active, key::Column::Version, None)
+        .await
+        .map_err(|_| SyncError::Database("Failed to return key"))?;
+
+    record_action(&txn, handled_by, action, recorded_at, &log.id).await?;
+
+    txn.commit()
+        .await
+        .map_err(|_| SyncError::Database("Failed to commit key sync"))?;
+
+    if let Some(classroom_id) = &classroom_id {
+        refresh_classroom_summary(state, classroom_id).await;
+    }
+    tokio::spawn(key_receipts::send_receipt(
+        state.clone(),
+        ReceiptKind::Return,
+        vec![log.clone()],
+        Some(handled_by.to_string()),
+    ));
+    Ok(log)
+}
+
+async fn record_action(
+    txn: &sea_orm::DatabaseTransaction,
+    handled_by: &str,
+    action: &KeySyncActionBody,
+    recorded_at: DateTimeWithTimeZone,
+    log_id: &str,
+) -> Result<(), SyncError> {
+    let record = key_sync_action::ActiveModel {
+        id: Set(action.client_action_id.clone()),
+        action: Set(action.action.as_str().to_string()),
+        key_transaction_log_id: Set(Some(log_id.to_string())),
+        handled_by: Set(Some(handled_by.to_string())),
+        recorded_at: Set(recorded_at),
+        created_at: NotSet,
+    };
+
+    record
+        .insert(txn)
+        .await
+        .map(|_| ())
+        .map_err(|_| SyncError::Database("Failed to record sync action"))
+}
+
+async fn apply_action(
+    state: &AppState,
+    handled_by: &str,
+    action: &KeySyncActionBody,
+    recorded_at: DateTimeWithTimeZone,
+) -> KeySyncResult {
+    let client_action_id = action.client_action_id.clone();
+
+    // Already synced: report the original outcome instead of applying it twice
+    match key_sync_action::Entity::find_by_id(&action.client_action_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(existing)) => {
+            let log = match existing.key_transaction_log_id {
+                Some(log_id) => key_transaction_log::Entity::find_by_id(log_id)
+                    .one(&state.db)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(KeyTransactionLogResponse::from),
+                None => None,
+            };
+            return KeySyncResult {
+                client_action_id,
+                status: KeySyncStatus::Duplicate,
+                log,
+                error: None,
+            };
+        }
+        Ok(None) => {}
+        Err(_) => {
+            return KeySyncResult {
+                client_action_id,
+                status: KeySyncStatus::Failed,
+                log: None,
+                error: Some("Failed to check sync history".to_string()),
+            };
+        }
+    }
+
+    let outcome = match action.action {
+        KeySyncActionKind::Borrow => apply_borrow(state, handled_by, action, recorded_at).await,
+        KeySyncActionKind::Return => apply_return(state, handled_by, action, recorded_at).await,
+    };
+
+    match outcome {
+        Ok(log) => KeySyncResult {
+            client_action_id,
+            status: KeySyncStatus::Applied,
+            log: Some(KeyTransactionLogResponse::from(log)),
+            error: None,
+        },
+        Err(e) => KeySyncResult {
+            client_action_id,
+            status: KeySyncStatus::Failed,
+            log: None,
+            error: Some(e.message()),
+        },
+    }
+}
+
+// ===============================
+//   Handler
+// ===============================
+#[utoipa::path(
+    post,
+    tags = ["Key"],
+    description = "Apply a batch of borrow/return actions recorded offline by the desk app. Actions are applied in recorded_at order (ties keep submission order); each client_action_id is applied at most once.",
+    path = "/sync",
+    request_body(content = KeySyncBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Batch processed; see per-action results", body = KeySyncResponse),
+        (status = 400, description = "Empty batch", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn sync_key_actions(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Json(body): Json<KeySyncBody>,
+) -> Result<Response, AppError> {
+    let user = match session.user {
+        Some(u) => u,
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+
+    if body.actions.is_empty() {
+        return Err(AppError::BadRequest("No actions to sync".to_string()));
+    }
+
+    let mut results: Vec<Option<KeySyncResult>> = body.actions.iter().map(|_| None).collect();
+    let mut ordered = Vec::new();
+
+    for (index, action) in body.actions.iter().enumerate() {
+        match parse_dt_field(&action.recorded_at, "recorded_at") {
+            Ok(recorded_at) => ordered.push((index, recorded_at)),
+            Err(message) => {
+                results[index] = Some(KeySyncResult {
+                    client_action_id: action.client_action_id.clone(),
+                    status: KeySyncStatus::Failed,
+                    log: None,
+                    error: Some(message),
+                });
+            }
+        }
+    }
+
+    // Stable sort keeps submission order for actions recorded at the same instant
+    ordered.sort_by_key(|(_, recorded_at)| *recorded_at);
+
+    for (index, recorded_at) in ordered {
+        let result = apply_action(&state, &user.id, &body.actions[index], recorded_at).await;
+        results[index] = Some(result);
+    }
+
+    let results: Vec<KeySyncResult> = results.into_iter().flatten().collect();
+    let count = |status: KeySyncStatus| results.iter().filter(|r| r.status == status).count();
+
+    let response = KeySyncResponse {
+        applied: count(KeySyncStatus::Applied),
+        duplicates: count(KeySyncStatus::Duplicate),
+        failed: count(KeySyncStatus::Failed),
+        results,
+    };
+
+    Ok((StatusCode::OK, Json(response)).into_response())
+}
+
+pub fn key_sync_router() -> Router<AppState> {
+    Router::new()
+        .route("/sync", post(sync_key_actions))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageKeys))
+}
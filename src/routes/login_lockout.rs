@@ -0,0 +1,115 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use tracing::warn;
+
+use crate::{
+    AppState,
+    error::{AppError, ErrorResponse},
+    login_guard::{self, LockoutState},
+    login_system::AuthBackend,
+    permissions::Permission,
+};
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Accounts locked after too many failed logins. They stay locked until the owner follows the emailed unlock link, resets their password, or an admin unlocks them.",
+    path = "/lockouts",
+    responses(
+        (status = 200, description = "Locked accounts, most recently locked first", body = Vec<LockoutState>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch locked accounts", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_lockouts(State(state): State<AppState>) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    match login_guard::locked_accounts(&mut redis).await {
+        Ok(locked) => Ok((StatusCode::OK, Json(locked)).into_response()),
+        Err(e) => {
+            warn!("Failed to fetch locked accounts: {}", e);
+            Err(AppError::Internal(
+                "Failed to fetch locked accounts".to_string(),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Lockout state of one account",
+    path = "/lockouts/{user_id}",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "The account is locked", body = LockoutState),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "The account is not locked", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch lockout state", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_lockout(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    match login_guard::lock_state(&mut redis, &user_id).await {
+        Ok(Some(lockout)) => Ok((StatusCode::OK, Json(lockout)).into_response()),
+        Ok(None) => Err(AppError::NotFound("Account is not locked".to_string())),
+        Err(e) => {
+            warn!("Failed to fetch lockout of user {}: {}", user_id, e);
+            Err(AppError::Internal(
+                "Failed to fetch lockout state".to_string(),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    tags = ["Admin"],
+    description = "Unlock an account and reset its failed login count",
+    path = "/lockouts/{user_id}",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Account unlocked"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "The account is not locked", body = ErrorResponse),
+        (status = 500, description = "Failed to unlock account", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn delete_lockout(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    match login_guard::unlock(&mut redis, &user_id).await {
+        Ok(true) => Ok((StatusCode::OK, "Account unlocked").into_response()),
+        Ok(false) => Err(AppError::NotFound("Account is not locked".to_string())),
+        Err(e) => {
+            warn!("Failed to unlock user {}: {}", user_id, e);
+            Err(AppError::Internal("Failed to unlock account".to_string()))
+        }
+    }
+}
+
+pub fn login_lockout_router() -> Router<AppState> {
+    Router::new()
+        .route("/lockouts", get(list_lockouts))
+        .route(
+            "/lockouts/{user_id}",
+            get(get_lockout).delete(delete_lockout),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers))
+}
@@ -1,8 +1,111 @@
+pub mod admin;
 pub mod announcement;
+pub mod assistant;
+pub mod audit;
 pub mod black_list;
 pub mod classroom;
+pub mod classroom_check_in;
+pub mod classroom_schedule;
+pub mod classroom_status;
+pub mod debug;
+pub mod domain_event;
+pub mod door_event;
+pub mod email;
+pub mod event_duplicate;
+pub mod events;
 pub mod infraction;
+pub mod invite;
 pub mod key;
+pub mod key_borrow;
+pub mod key_cabinet;
+pub mod key_log_export;
+pub mod key_sync;
+pub mod login_lockout;
 pub mod password;
+pub mod pickup_code;
+pub mod reporting;
 pub mod reservation;
+pub mod reservation_attachment;
+pub mod reservation_comment;
+pub mod reservation_note;
+pub mod reservation_transfer;
+pub mod review_lock;
+pub mod review_nudge;
+pub mod second_approval;
+pub mod stats;
+pub mod timetable;
 pub mod user;
+pub mod user_export;
+pub mod webhooks;
+
+use axum::Router;
+use utoipa::{OpenApi, openapi};
+
+use crate::AppState;
+
+/// A router and the OpenAPI document of its routes, nested under the same
+/// path by `app` and `api_doc`, so routes cannot be mounted without their
+/// docs.
+pub struct ApiModule {
+    pub path: &'static str,
+    pub router: fn() -> Router<AppState>,
+    pub api: fn() -> openapi::OpenApi,
+}
+
+pub fn modules() -> [ApiModule; 16] {
+    let module = |path, router, api| ApiModule { path, router, api };
+    [
+        module("/user", user::user_router, user::UserApi::openapi),
+        module(
+            "/classroom",
+            classroom::classroom_router,
+            classroom::ClassroomApi::openapi,
+        ),
+        module(
+            "/reservation",
+            reservation::reservation_router,
+            reservation::ReservationApi::openapi,
+        ),
+        module("/key", key::key_router, key::KeyApi::openapi),
+        module(
+            "/announcement",
+            announcement::announcement_router,
+            announcement::AnnouncementApi::openapi,
+        ),
+        module(
+            "/infraction",
+            infraction::infraction_router,
+            infraction::InfractionApi::openapi,
+        ),
+        module(
+            "/black_list",
+            black_list::black_list_router,
+            black_list::BlacklistApi::openapi,
+        ),
+        module(
+            "/password",
+            password::password_router,
+            password::PasswordApi::openapi,
+        ),
+        module("/stats", stats::stats_router, stats::StatsApi::openapi),
+        module("/admin", admin::admin_router, admin::AdminApi::openapi),
+        module("/audit", audit::audit_router, audit::AuditApi::openapi),
+        module("/email", email::email_router, email::EmailApi::openapi),
+        module(
+            "/iot",
+            door_event::door_event_router,
+            door_event::IotApi::openapi,
+        ),
+        module("/events", events::events_router, events::EventsApi::openapi),
+        module(
+            "/reporting/v1",
+            reporting::reporting_router,
+            reporting::ReportingApi::openapi,
+        ),
+        module(
+            "/webhooks",
+            webhooks::webhooks_router,
+            webhooks::WebhooksApi::openapi,
+        ),
+    ]
+}
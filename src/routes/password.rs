@@ -1,24 +1,40 @@
-use axum::{Json, Router, extract::State, http::StatusCode, response::IntoResponse, routing::post};
-use chrono::{Duration, Utc};
+use std::net::SocketAddr;
+
+use axum::{
+    Json, Router,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use chrono::Utc;
 use nanoid::nanoid;
-use redis::{AsyncCommands, RedisError, SetOptions, SetExpiry};
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
+use redis::{AsyncCommands, RedisError, SetExpiry, SetOptions};
+use sea_orm::{ActiveValue::Set, ColumnTrait, EntityTrait, QueryFilter};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
-use utoipa::ToSchema;
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
-    AppState, argon_hasher, email_client::send_email, entities::user,
+    AppState,
+    email_client::send_email,
+    entities::user,
+    error::{AppError, ErrorResponse},
+    login_guard,
+    password_reset::{attempts_exhausted, gen_code},
+    rate_limit::{self, Endpoint},
+    sessions, versioning,
 };
 
-const CODE_TTL_SECONDS: u64 = 10 * 60; // 10 minutes
-const TOKEN_TTL_SECONDS: u64 = 15 * 60; // 15 minutes
-
 // Redis key prefixes
 fn code_key(email: &str) -> String {
     format!("password_reset:code:{}", email)
 }
 
+fn attempts_key(email: &str) -> String {
+    format!("password_reset:attempts:{}", email)
+}
+
 fn token_key(email: &str) -> String {
     format!("password_reset:token:{}", email)
 }
@@ -35,11 +51,6 @@ struct TokenData {
     expires_at: i64, // Unix timestamp
 }
 
-fn gen_6_digit_code() -> String {
-    const DIGITS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
-    nanoid!(6, &DIGITS)
-}
-
 #[derive(Deserialize, ToSchema)]
 pub struct ForgotPasswordBody {
     pub email: String,
@@ -67,19 +78,30 @@ pub struct ResetPasswordBody {
 #[utoipa::path(
     post,
     tags = ["Password"],
-    description = "Forgot password: input email, send 6-digit code. Always returns 200 to avoid email enumeration.",
+    description = "Forgot password: input email, send a numeric code (6 digits unless configured otherwise). Always returns 200 to avoid email enumeration.",
     path = "/forgot",
     request_body(content = ForgotPasswordBody, content_type = "application/json"),
     responses(
         (status = 200, description = "If email exists, code has been sent", body = String),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 429, description = "Too many requests from this address or for this email; see `Retry-After`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn forgot_password(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<ForgotPasswordBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let email = body.email.trim().to_string();
+    rate_limit::check(
+        &state,
+        Endpoint::ForgotPassword,
+        &headers,
+        peer.ip(),
+        Some(&email),
+    )
+    .await?;
 
     // Check if user exists (but always return 200 to avoid email enumeration)
     let exists = match user::Entity::find()
@@ -90,14 +112,15 @@ pub async fn forgot_password(
         Ok(Some(_)) => true,
         Ok(None) => false,
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query user").into_response();
+            return Err(AppError::Internal("Failed to query user".to_string()));
         }
     };
 
     if exists {
-        let code = gen_6_digit_code();
+        let config = &state.config.password_reset;
+        let code = gen_code(config.code_length);
         let now = Utc::now();
-        let expires_at = (now + Duration::minutes(CODE_TTL_SECONDS as i64 / 60)).timestamp();
+        let expires_at = (now + config.code_ttl).timestamp();
 
         let code_data = CodeData {
             code: code.clone(),
@@ -110,7 +133,8 @@ pub async fn forgot_password(
             .set_options(
                 code_key(&email),
                 serde_json::to_string(&code_data).unwrap(),
-                SetOptions::default().with_expiration(SetExpiry::EX(CODE_TTL_SECONDS)),
+                SetOptions::default()
+                    .with_expiration(SetExpiry::EX(config.code_ttl.num_seconds() as u64)),
             )
             .await;
 
@@ -119,50 +143,53 @@ pub async fn forgot_password(
                 "Failed to store password reset code for {} in Redis: {}",
                 email, e
             );
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to create reset record",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to create reset record".to_string(),
+            ));
         }
 
-        // Also delete any existing token for this email (cleanup)
+        // Also delete any existing token for this email (cleanup), and start
+        // counting failed attempts afresh for the new code
         let _: Result<(), RedisError> = redis.del(token_key(&email)).await;
+        let _: Result<(), RedisError> = redis.del(attempts_key(&email)).await;
 
         let subject = "Password Reset Verification Code";
         let content = format!(
             "Your password reset verification code is: {code}\n\nThis code will expire in {} minutes.",
-            CODE_TTL_SECONDS / 60
+            config.code_ttl.num_minutes()
         );
 
-        if send_email(&email, subject, content).await.is_err() {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to send email").into_response();
+        if send_email(state.config.smtp.as_ref(), &email, subject, content)
+            .await
+            .is_err()
+        {
+            return Err(AppError::Internal("Failed to send email".to_string()));
         }
     }
 
-    (
+    Ok((
         StatusCode::OK,
         "If the email exists, a reset code has been sent.",
     )
-        .into_response()
+        .into_response())
 }
 
 #[utoipa::path(
     post,
     tags = ["Password"],
-    description = "Verify code: returns reset_token for final reset step.",
+    description = "Verify code: returns reset_token for final reset step. After too many wrong codes the code is invalidated and a new one has to be requested.",
     path = "/verify",
     request_body(content = VerifyCodeBody, content_type = "application/json"),
     responses(
         (status = 200, description = "Code verified", body = VerifyCodeResponse),
-        (status = 400, description = "Invalid or expired code", body = String),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 400, description = "Invalid or expired code", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn verify_code(
     State(state): State<AppState>,
     Json(body): Json<VerifyCodeBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let email = body.email.trim().to_owned();
     let code = body.code.trim().to_string();
     let now = Utc::now().timestamp();
@@ -176,7 +203,7 @@ pub async fn verify_code(
                 "Failed to get password reset code for {} from Redis: {}",
                 email, e
             );
-            return (StatusCode::BAD_REQUEST, "Invalid or expired code").into_response();
+            return Err(AppError::BadRequest("Invalid or expired code".to_string()));
         }
     };
 
@@ -188,20 +215,47 @@ pub async fn verify_code(
                     "Failed to parse password reset code data for {}: {}",
                     email, e
                 );
-                return (StatusCode::BAD_REQUEST, "Invalid or expired code").into_response();
+                return Err(AppError::BadRequest("Invalid or expired code".to_string()));
             }
         },
-        None => return (StatusCode::BAD_REQUEST, "Invalid or expired code").into_response(),
+        None => return Err(AppError::BadRequest("Invalid or expired code".to_string())),
     };
 
-    // Verify code and expiration
-    if code_data.code != code || code_data.expires_at <= now {
-        return (StatusCode::BAD_REQUEST, "Invalid or expired code").into_response();
+    let config = &state.config.password_reset;
+    if code_data.expires_at <= now {
+        return Err(AppError::BadRequest("Invalid or expired code".to_string()));
+    }
+    if code_data.code != code {
+        // Count the wrong guess; the code's TTL bounds the counter
+        let failed: Result<u32, RedisError> = redis.incr(attempts_key(&email), 1).await;
+        let failed = match failed {
+            Ok(failed) => failed,
+            Err(e) => {
+                warn!(
+                    "Failed to count password reset attempt for {} in Redis: {}",
+                    email, e
+                );
+                return Err(AppError::BadRequest("Invalid or expired code".to_string()));
+            }
+        };
+        if failed == 1 {
+            let _: Result<(), RedisError> = redis
+                .expire(attempts_key(&email), config.code_ttl.num_seconds())
+                .await;
+        }
+        if attempts_exhausted(failed, config.max_verify_attempts) {
+            let _: Result<(), RedisError> = redis.del(code_key(&email)).await;
+            let _: Result<(), RedisError> = redis.del(attempts_key(&email)).await;
+            return Err(AppError::BadRequest(
+                "Too many failed attempts, request a new code".to_string(),
+            ));
+        }
+        return Err(AppError::BadRequest("Invalid or expired code".to_string()));
     }
 
     // Generate reset token
     let reset_token = nanoid!(32);
-    let expires_at = (Utc::now() + Duration::minutes(TOKEN_TTL_SECONDS as i64 / 60)).timestamp();
+    let expires_at = (Utc::now() + config.token_ttl).timestamp();
 
     let token_data = TokenData {
         token: reset_token.clone(),
@@ -213,7 +267,8 @@ pub async fn verify_code(
         .set_options(
             token_key(&email),
             serde_json::to_string(&token_data).unwrap(),
-            SetOptions::default().with_expiration(SetExpiry::EX(TOKEN_TTL_SECONDS)),
+            SetOptions::default()
+                .with_expiration(SetExpiry::EX(config.token_ttl.num_seconds() as u64)),
         )
         .await;
 
@@ -222,45 +277,42 @@ pub async fn verify_code(
             "Failed to store password reset token for {} in Redis: {}",
             email, e
         );
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update reset record",
-        )
-            .into_response();
+        return Err(AppError::Internal(
+            "Failed to update reset record".to_string(),
+        ));
     }
 
     // Delete the code (prevent reuse)
     let _: Result<(), RedisError> = redis.del(code_key(&email)).await;
+    let _: Result<(), RedisError> = redis.del(attempts_key(&email)).await;
 
-    (StatusCode::OK, Json(VerifyCodeResponse { reset_token })).into_response()
+    Ok((StatusCode::OK, Json(VerifyCodeResponse { reset_token })).into_response())
 }
 
 #[utoipa::path(
     post,
     tags = ["Password"],
-    description = "Reset password using reset_token.",
+    description = "Reset password using reset_token. Every session of the user is logged out and a login lockout is lifted.",
     path = "/reset",
     request_body(content = ResetPasswordBody, content_type = "application/json"),
     responses(
         (status = 200, description = "Password reset successfully", body = String),
-        (status = 400, description = "Bad request", body = String),
-        (status = 404, description = "User not found", body = String),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 400, description = "Bad request", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn reset_password(
     State(state): State<AppState>,
     Json(body): Json<ResetPasswordBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let email = body.email.trim().to_owned();
     let token = body.reset_token.trim().to_string();
 
     if body.new_password != body.confirm {
-        return (
-            StatusCode::BAD_REQUEST,
-            "New password and confirm password are not same",
-        )
-            .into_response();
+        return Err(AppError::BadRequest(
+            "New password and confirm password are not same".to_string(),
+        ));
     }
 
     let now = Utc::now().timestamp();
@@ -274,7 +326,9 @@ pub async fn reset_password(
                 "Failed to get password reset token for {} from Redis: {}",
                 email, e
             );
-            return (StatusCode::BAD_REQUEST, "Invalid or expired reset token").into_response();
+            return Err(AppError::BadRequest(
+                "Invalid or expired reset token".to_string(),
+            ));
         }
     };
 
@@ -286,17 +340,23 @@ pub async fn reset_password(
                     "Failed to parse password reset token data for {}: {}",
                     email, e
                 );
-                return (StatusCode::BAD_REQUEST, "Invalid or expired reset token").into_response();
+                return Err(AppError::BadRequest(
+                    "Invalid or expired reset token".to_string(),
+                ));
             }
         },
         None => {
-            return (StatusCode::BAD_REQUEST, "Invalid or expired reset token").into_response();
+            return Err(AppError::BadRequest(
+                "Invalid or expired reset token".to_string(),
+            ));
         }
     };
 
     // Verify token and expiration
     if token_data.token != token || token_data.expires_at <= now {
-        return (StatusCode::BAD_REQUEST, "Invalid or expired reset token").into_response();
+        return Err(AppError::BadRequest(
+            "Invalid or expired reset token".to_string(),
+        ));
     }
 
     // Find user in database
@@ -306,9 +366,9 @@ pub async fn reset_password(
         .await
     {
         Ok(Some(u)) => u,
-        Ok(None) => return (StatusCode::NOT_FOUND, "User not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to query user").into_response();
+            return Err(AppError::Internal("Failed to query user".to_string()));
         }
     };
 
@@ -316,10 +376,10 @@ pub async fn reset_password(
     let user_id = u.id.clone();
 
     // Hash new password
-    let new_hash = match argon_hasher::hash(body.new_password.as_bytes()).await {
+    let new_hash = match state.hasher.hash(body.new_password.as_bytes()).await {
         Ok(h) => h,
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to hash password").into_response();
+            return Err(AppError::Internal("Failed to hash password".to_string()));
         }
     };
 
@@ -327,23 +387,47 @@ pub async fn reset_password(
     let mut ua: user::ActiveModel = u.into();
     ua.password = Set(new_hash);
 
-    if ua.update(&state.db).await.is_err() {
-        return (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update password",
-        )
-            .into_response();
+    if versioning::update(&state.db, ua, user::Column::Version, None)
+        .await
+        .is_err()
+    {
+        return Err(AppError::Internal("Failed to update password".to_string()));
     }
 
-    // Invalidate user cache in Redis (password changed)
-    let _: Result<(), RedisError> = redis.del(format!("user_{}", user_id)).await;
+    // Log out every session and invalidate the cached user (password changed)
+    if let Err(e) = sessions::revoke_all(&mut redis, &user_id, None).await {
+        warn!("Failed to revoke sessions of user {}: {}", user_id, e);
+    }
+    // Resetting proves the user owns the address an unlock link goes to
+    if let Err(e) = login_guard::unlock(&mut redis, &user_id).await {
+        warn!("Failed to unlock user {}: {}", user_id, e);
+    }
 
     // Delete reset token from Redis (successful reset)
     let _: Result<(), RedisError> = redis.del(token_key(&email)).await;
 
-    (StatusCode::OK, "Password reset successfully").into_response()
+    Ok((StatusCode::OK, "Password reset successfully").into_response())
 }
 
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Password Reset", description = "Password reset endpoints")
+    ),
+    paths(
+        forgot_password,
+        verify_code,
+        reset_password,
+    ),
+    components(schemas(
+        ForgotPasswordBody,
+        VerifyCodeBody,
+        VerifyCodeResponse,
+        ResetPasswordBody,
+    ))
+)]
+pub struct PasswordApi;
+
 pub fn password_router() -> Router<AppState> {
     Router::new()
         .route("/forgot", post(forgot_password))
@@ -0,0 +1,143 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use sea_orm::EntityTrait;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    entities::{key, key_pickup_code, reservation, sea_orm_active_enums::ReservationStatus},
+    error::{AppError, ErrorResponse},
+    login_system::AuthBackend,
+    permissions::Permission,
+    pickup,
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct PickupCodeResponse {
+    pub code: key_pickup_code::Model,
+    /// Key set aside for the reservation, if any
+    pub key: Option<key::Model>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Show the active pickup code of a reservation and the key set aside for it.",
+    path = "/{id}/pickup-code",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 200, description = "Active pickup code", body = PickupCodeResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation has no active pickup code", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch pickup code", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_pickup_code(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let code = match pickup::active_code(&state.db, &id).await {
+        Ok(Some(code)) => code,
+        Ok(None) => {
+            return Err(AppError::NotFound(
+                "Reservation has no active pickup code".to_string(),
+            ));
+        }
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch pickup code".to_string(),
+            ));
+        }
+    };
+    let key_model = match &code.key_id {
+        Some(key_id) => match key::Entity::find_by_id(key_id).one(&state.db).await {
+            Ok(key_model) => key_model,
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch key".to_string()));
+            }
+        },
+        None => None,
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(PickupCodeResponse {
+            code,
+            key: key_model,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Replace the pickup code of an approved reservation, e.g. when the requester lost the email. The old code stops working, a key is set aside again and the new code is emailed to the requester. Works whether or not pickup codes are issued on approval.",
+    path = "/{id}/pickup-code",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 201, description = "New pickup code", body = PickupCodeResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 409, description = "Reservation is not approved", body = ErrorResponse),
+        (status = 500, description = "Failed to issue pickup code", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn regenerate_pickup_code(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let reservation_model = match reservation::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(reservation_model)) => reservation_model,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    if reservation_model.status != ReservationStatus::Approved {
+        return Err(AppError::Conflict(
+            "Only approved reservations have a pickup code".to_string(),
+        ));
+    }
+
+    let (code, key_model) = match pickup::create_code(&state.db, &reservation_model).await {
+        Ok(created) => created,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to issue pickup code".to_string(),
+            ));
+        }
+    };
+    pickup::send_code(&state, &reservation_model, &code, key_model.as_ref()).await;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(PickupCodeResponse {
+            code,
+            key: key_model,
+        }),
+    )
+        .into_response())
+}
+
+pub fn pickup_code_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/{id}/pickup-code",
+            get(get_pickup_code).post(regenerate_pickup_code),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageKeys))
+}
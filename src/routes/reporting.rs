@@ -0,0 +1,278 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use chrono::{Datelike, Duration, Utc};
+use sea_orm::{
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect, prelude::DateTimeWithTimeZone,
+};
+use serde::Deserialize;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    concurrency::limit_reports,
+    entities::{black_list, classroom, infraction, reservation},
+    error::{AppError, ErrorResponse},
+    export::parse_filter,
+    pagination::{PageParams, Pagination, fetch},
+    public_stats::{add_months, month_start, months_between, parse_month},
+    reporting::{
+        InfractionMonthV1, PageV1, ReservationV1, USED_STATUSES, UtilizationV1, infraction_months,
+        require_token, utilization,
+    },
+};
+
+/// Window the time-ranged endpoints cover when `from` is omitted.
+const DEFAULT_WINDOW_DAYS: i64 = 30;
+
+/// Longest window `/utilization` may cover.
+const MAX_WINDOW_DAYS: i64 = 366;
+
+/// Longest period `/infractions` may cover.
+const MAX_INFRACTION_MONTHS: i32 = 36;
+
+#[derive(Deserialize, IntoParams)]
+pub struct ReportingWindowQuery {
+    /// Reservations starting at or after this time; defaults to 30 days
+    /// before `to`
+    pub from: Option<String>,
+    /// Reservations starting before this time; defaults to now
+    pub to: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ReportingMonthsQuery {
+    /// First month to include, `YYYY-MM`; defaults to 11 months before `to`
+    pub from: Option<String>,
+    /// Last month to include, `YYYY-MM`; defaults to the current month
+    pub to: Option<String>,
+}
+
+fn parse_window(
+    query: &ReportingWindowQuery,
+) -> Result<(DateTimeWithTimeZone, DateTimeWithTimeZone), String> {
+    let to = parse_filter(&query.to, "to")?
+        .unwrap_or_else(|| Utc::now().with_timezone(&campus_offset()));
+    let from =
+        parse_filter(&query.from, "from")?.unwrap_or(to - Duration::days(DEFAULT_WINDOW_DAYS));
+    if from >= to {
+        return Err("'from' must be < 'to'".to_string());
+    }
+    Ok((from, to))
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reporting"],
+    description = "Reservations starting in `[from, to)`, in every status, ordered by start time and then ID so pages stay stable while reading through them. Part of the versioned reporting contract: fields are only ever added. Authenticate with a reporting token as `Authorization: Bearer <token>`.",
+    path = "/reservations",
+    params(ReportingWindowQuery, PageParams),
+    responses(
+        (status = 200, description = "Reservations", body = PageV1<ReservationV1>),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 503, description = "Reporting is not configured, or too many reports running", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
+    ),
+    security(("reporting_token" = []))
+)]
+pub async fn list_reporting_reservations(
+    State(state): State<AppState>,
+    pagination: Pagination,
+    Query(query): Query<ReportingWindowQuery>,
+) -> Result<Response, AppError> {
+    let (from, to) = match parse_window(&query) {
+        Ok(window) => window,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let select = reservation::Entity::find()
+        .filter(reservation::Column::StartTime.gte(from))
+        .filter(reservation::Column::StartTime.lt(to))
+        .order_by_asc(reservation::Column::StartTime)
+        .order_by_asc(reservation::Column::Id);
+    match fetch(&state.db, select, pagination).await {
+        Ok(page) => Ok((
+            StatusCode::OK,
+            Json(PageV1::from_paged(page, ReservationV1::from)),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservations".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reporting"],
+    description = "Per-classroom utilization for reservations starting in `[from, to)`: approved, completed and no-show reservations and their booked hours. Covers at most 366 days. Part of the versioned reporting contract.",
+    path = "/utilization",
+    params(ReportingWindowQuery),
+    responses(
+        (status = 200, description = "Utilization", body = UtilizationV1),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 503, description = "Reporting is not configured, or too many reports running", body = ErrorResponse),
+        (status = 500, description = "Failed to compute utilization", body = ErrorResponse)
+    ),
+    security(("reporting_token" = []))
+)]
+pub async fn get_reporting_utilization(
+    State(state): State<AppState>,
+    Query(query): Query<ReportingWindowQuery>,
+) -> Result<Response, AppError> {
+    let (from, to) = match parse_window(&query) {
+        Ok(window) => window,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if to - from > Duration::days(MAX_WINDOW_DAYS) {
+        return Err(AppError::BadRequest(format!(
+            "At most {} days can be requested",
+            MAX_WINDOW_DAYS
+        )));
+    }
+    let classrooms = classroom::Entity::find()
+        .filter(classroom::Column::DeletedAt.is_null())
+        .all(&state.db)
+        .await;
+    let reservations = reservation::Entity::find()
+        .filter(reservation::Column::Status.is_in(USED_STATUSES))
+        .filter(reservation::Column::StartTime.gte(from))
+        .filter(reservation::Column::StartTime.lt(to))
+        .all(&state.db)
+        .await;
+    match (classrooms, reservations) {
+        (Ok(classrooms), Ok(reservations)) => Ok((
+            StatusCode::OK,
+            Json(utilization(from, to, &classrooms, &reservations)),
+        )
+            .into_response()),
+        _ => Err(AppError::Internal(
+            "Failed to compute utilization".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reporting"],
+    description = "Infractions, distinct users with infractions and blacklist entries per month, with a row for every month in the range. Part of the versioned reporting contract.",
+    path = "/infractions",
+    params(ReportingMonthsQuery),
+    responses(
+        (status = 200, description = "Infractions per month", body = Vec<InfractionMonthV1>),
+        (status = 400, description = "Invalid month range", body = ErrorResponse),
+        (status = 401, description = "Missing or unknown token", body = ErrorResponse),
+        (status = 503, description = "Reporting is not configured, or too many reports running", body = ErrorResponse),
+        (status = 500, description = "Failed to compute infraction counts", body = ErrorResponse)
+    ),
+    security(("reporting_token" = []))
+)]
+pub async fn get_reporting_infractions(
+    State(state): State<AppState>,
+    Query(query): Query<ReportingMonthsQuery>,
+) -> Result<Response, AppError> {
+    let to_month = match query.to.as_deref() {
+        Some(s) => match parse_month(s) {
+            Some(dt) => dt,
+            None => {
+                return Err(AppError::BadRequest(
+                    "Invalid to, expected YYYY-MM".to_string(),
+                ));
+            }
+        },
+        None => {
+            let now = Utc::now().with_timezone(&campus_offset());
+            month_start(now.year(), now.month()).expect("current month exists")
+        }
+    };
+    let from_month = match query.from.as_deref() {
+        Some(s) => match parse_month(s) {
+            Some(dt) => dt,
+            None => {
+                return Err(AppError::BadRequest(
+                    "Invalid from, expected YYYY-MM".to_string(),
+                ));
+            }
+        },
+        None => add_months(to_month, -11),
+    };
+    let span = months_between(from_month, to_month) + 1;
+    if span < 1 {
+        return Err(AppError::BadRequest(
+            "from must not be after to".to_string(),
+        ));
+    }
+    if span > MAX_INFRACTION_MONTHS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} months can be requested",
+            MAX_INFRACTION_MONTHS
+        )));
+    }
+
+    let end = add_months(to_month, 1);
+    let infractions = infraction::Entity::find()
+        .select_only()
+        .column(infraction::Column::UserId)
+        .column(infraction::Column::CreatedAt)
+        .filter(infraction::Column::CreatedAt.gte(from_month))
+        .filter(infraction::Column::CreatedAt.lt(end))
+        .into_tuple()
+        .all(&state.db)
+        .await;
+    let bans = black_list::Entity::find()
+        .select_only()
+        .column(black_list::Column::CreatedAt)
+        .filter(black_list::Column::CreatedAt.gte(from_month))
+        .filter(black_list::Column::CreatedAt.lt(end))
+        .into_tuple()
+        .all(&state.db)
+        .await;
+    match (infractions, bans) {
+        (Ok(infractions), Ok(bans)) => Ok((
+            StatusCode::OK,
+            Json(infraction_months(from_month, end, &infractions, &bans)),
+        )
+            .into_response()),
+        _ => Err(AppError::Internal(
+            "Failed to compute infraction counts".to_string(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Reporting", description = "Versioned read-only API for external BI tools")
+    ),
+    paths(
+        list_reporting_reservations,
+        get_reporting_utilization,
+        get_reporting_infractions,
+    ),
+    components(schemas(
+        crate::reporting::PageV1<crate::reporting::ReservationV1>,
+        crate::reporting::ReservationV1,
+        crate::reporting::ReservationStatusV1,
+        crate::reporting::UtilizationV1,
+        crate::reporting::ClassroomUtilizationV1,
+        crate::reporting::InfractionMonthV1,
+    ))
+)]
+pub struct ReportingApi;
+
+pub fn reporting_router() -> Router<AppState> {
+    Router::new()
+        .route("/reservations", get(list_reporting_reservations))
+        .route("/utilization", get(get_reporting_utilization))
+        .route("/infractions", get(get_reporting_infractions))
+        .route_layer(middleware::from_fn(limit_reports))
+        // Checked before taking a report slot
+        .route_layer(middleware::from_fn(require_token))
+}
@@ -1,37 +1,101 @@
+use std::net::SocketAddr;
+
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
     routing::{delete, get, post, put},
 };
 use axum_login::{login_required, permission_required};
-use redis::AsyncCommands;
+use chrono::Utc;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder,
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, Order, QueryFilter,
+    TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
-use string_builder::Builder;
 use tracing::warn;
-use utoipa::ToSchema;
+use utoipa::{OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    constants::{REDIS_EXPIRY, get_redis_set_options},
-    email_client::send_email,
+    audit::{AuditContext, Target, snapshot},
+    availability::{AlternativeRoom, DateShorthand, campus_offset, suggest_alternatives},
+    bans,
+    cache::CacheKey,
+    constants::MAX_ALTERNATIVE_ROOMS,
+    domain_events::{self, DomainEvent},
+    door_events::{ActualUsage, usage_for},
+    double_approval::{self, ApprovalStep},
     entities::{
-        reservation,
-        sea_orm_active_enums::{ReservationStatus, Role},
+        classroom, reservation, reservation_attachment, sea_orm_active_enums::ReservationStatus,
         user,
     },
+    error::{AppError, ErrorResponse},
+    event_duplicates,
+    fields::{self, Row},
+    key_cabinet,
     login_system::{AuthBackend, AuthSession},
-    utils::parse_dt,
+    merge_patch::Patch,
+    pagination::{PageParams, PagedResponse, Pagination, SortSpec},
+    permissions::{Permission, can_view_reservation, review_scope},
+    pickup,
+    quota::{QuotaUsage, QuotaWarning, enforce_quota, warning_for_user},
+    rate_limit::{self, Endpoint},
+    redis_breaker::RedisConnection,
+    reservation_groups,
+    reservation_lifecycle::{self, Actor},
+    review_lock::{self, ReviewLock},
+    routes::{
+        classroom_schedule::reject_if_unavailable,
+        event_duplicate::event_duplicate_router,
+        pickup_code::pickup_code_router,
+        reservation_attachment::{attachments_for, reservation_attachment_router},
+        reservation_comment::reservation_comment_router,
+        reservation_note::{ReservationNote, notes_for, reservation_note_router},
+        review_lock::review_lock_router,
+        review_nudge::review_nudge_router,
+        second_approval::second_approval_router,
+    },
+    slots::{self, TimeAdjustment},
+    utils::{json_with_etag, parse_dt},
+    versioning,
+    walk_in::{self, BookedFor},
 };
 
 use nanoid::nanoid;
 
+/// Returned when an assistant acts on a reservation for a classroom they were
+/// not granted.
+pub const OUT_OF_SCOPE: &str = "Reservation is for a classroom you were not assigned";
+
+/// The reservation, if `user` may see its attachments and comments.
+pub async fn visible_reservation(
+    db: &DatabaseConnection,
+    user: &user::Model,
+    id: &str,
+) -> Result<reservation::Model, AppError> {
+    let reservation = match reservation::Entity::find_by_id(id).one(db).await {
+        Ok(Some(reservation)) => reservation,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    match can_view_reservation(db, user, &reservation).await {
+        Ok(true) => Ok(reservation),
+        // Someone else's reservation is not revealed to exist
+        Ok(false) => Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservation".to_string(),
+        )),
+    }
+}
+
 // ===============================
 //   Admin List Query
 // ===============================
@@ -42,20 +106,38 @@ pub struct AdminListQuery {
     pub user_id: Option<String>,
     pub from: Option<String>,
     pub to: Option<String>,
-    pub sort: Option<String>,   // asc|desc (default desc)
-    pub page: Option<u64>,      // default 1
-    pub page_size: Option<u64>, // default 20, max 100
+    /// Instead of `from` and `to`
+    pub date: Option<DateShorthand>,
+    pub flagged: Option<bool>,
+    pub sort: Option<String>,
+    /// Comma-separated columns to return, e.g. `id,start_time,status`
+    pub fields: Option<String>,
 }
 
-// ===============================
-//   Paged Response
-// ===============================
-#[derive(Serialize, ToSchema)]
-pub struct PagedReservations {
-    pub page: u64,
-    pub page_size: u64,
-    pub total: u64,
-    pub items: Vec<reservation::Model>,
+const DATE_WITH_RANGE: &str = "'date' cannot be combined with 'from' or 'to'";
+
+/// Fields reservation lists can be sorted by.
+const SORT_FIELDS: [(&str, reservation::Column); 3] = [
+    ("start_time", reservation::Column::StartTime),
+    ("end_time", reservation::Column::EndTime),
+    ("created_at", reservation::Column::CreatedAt),
+];
+
+/// Sort order asked for with `sort=`, newest start first by default.
+fn parse_sort(sort: Option<&str>) -> Result<SortSpec<reservation::Column>, String> {
+    SortSpec::parse(
+        sort,
+        &SORT_FIELDS,
+        reservation::Column::StartTime,
+        Order::Desc,
+    )
+}
+
+/// Columns asked for with `fields=`, or `None` for whole reservations.
+fn parse_fields(fields: Option<&str>) -> Result<Option<Vec<reservation::Column>>, String> {
+    fields
+        .map(|fields| fields::parse(fields, reservation::Column::Id))
+        .transpose()
 }
 
 // ===============================
@@ -63,123 +145,424 @@ pub struct PagedReservations {
 // ===============================
 #[derive(Deserialize, ToSchema)]
 pub struct CreateReservationBody {
-    pub classroom_id: String,
+    /// The classroom to reserve; use `classroom_ids` instead for several
+    #[serde(default)]
+    pub classroom_id: Option<String>,
+    /// Classrooms reserved together for the same slot, e.g. adjacent rooms
+    /// for a large event. They are approved, rejected and cancelled together
+    #[serde(default)]
+    pub classroom_ids: Vec<String>,
     pub purpose: String,
     pub start_time: String,
     pub end_time: String,
+    /// Event the room is for, e.g. "Robotics Club demo day"; other bookings
+    /// for the same event around the same time are flagged for review
+    pub event_name: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedReservation {
+    #[serde(flatten)]
+    pub reservation: reservation::Model,
+    /// The reservations of the other classrooms in `classroom_ids`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub linked_reservations: Vec<reservation::Model>,
+    /// Present when the user is within one reservation or one hour of their quota
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quota_warning: Option<QuotaWarning>,
+    /// Present when the requested times were moved onto slot boundaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_adjustment: Option<TimeAdjustment>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UpdatedReservation {
+    #[serde(flatten)]
+    pub reservation: reservation::Model,
+    /// The other rooms booked together with it, which take the same
+    /// `purpose` and `event_name`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub linked_reservations: Vec<reservation::Model>,
+    /// Present when the requested times were moved onto slot boundaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_adjustment: Option<TimeAdjustment>,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct GetReservationsQuery {
     pub status: Option<ReservationStatus>,
+    pub fields: Option<String>,
 }
 
 #[utoipa::path(
     post,
     tags = ["Reservation"],
-    description = "Submit a classroom reservation request",
+    description = "Submit a classroom reservation request. A request naming an `event_name` that other active reservations already use around the same time is flagged for review as a possible duplicate booking. When a slot granularity is configured, times off the slot grid are widened to the enclosing slots and reported in `time_adjustment`, or rejected if rounding is turned off. Several rooms for the same slot are requested with `classroom_ids`; each room gets its own reservation, all of them must be free and each counts towards the quota.",
     path = "",
     request_body(content = CreateReservationBody, content_type = "application/json"),
     responses(
-        (status = 201, description = "Reservation created", body = reservation::Model),
-        (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Failed to create reservation")
+        (status = 201, description = "Reservation created", body = CreatedReservation),
+        (status = 400, description = "Invalid time or outside opening hours", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "User is blacklisted", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 409, description = "Classroom closed or already booked; `details.alternatives` suggests other rooms", body = ErrorResponse),
+        (status = 422, description = "Reservation quota exceeded", body = ErrorResponse),
+        (status = 429, description = "Too many reservation requests from this user or address; see `Retry-After`", body = ErrorResponse),
+        (status = 500, description = "Failed to create reservation", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn create_reservation(
     session: AuthSession,
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<CreateReservationBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user = session.user.unwrap();
+    rate_limit::check(
+        &state,
+        Endpoint::CreateReservation,
+        &headers,
+        peer.ip(),
+        Some(&user.id),
+    )
+    .await?;
+    match bans::ensure_not_banned(&state.db, &user.id).await {
+        Ok(Ok(())) => {}
+        Ok(Err(message)) => return Err(AppError::Forbidden(message)),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to check blacklist".to_string()));
+        }
+    }
 
+    let rooms = match reservation_groups::requested_rooms(body.classroom_id, body.classroom_ids) {
+        Ok(rooms) => rooms,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
     let start_dt = match parse_dt(&body.start_time) {
         Ok(v) => v,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid start_time").into_response(),
+        Err(_) => return Err(AppError::BadRequest("Invalid start_time".to_string())),
     };
     let end_dt = match parse_dt(&body.end_time) {
         Ok(v) => v,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid end_time").into_response(),
+        Err(_) => return Err(AppError::BadRequest("Invalid end_time".to_string())),
     };
+    if start_dt >= end_dt {
+        return Err(AppError::BadRequest(
+            "'start_time' must be < 'end_time'".to_string(),
+        ));
+    }
+    let (start_dt, end_dt, time_adjustment) =
+        match slots::align(&state.config.slots, start_dt, end_dt) {
+            Ok(aligned) => aligned,
+            Err(message) => return Err(AppError::BadRequest(message)),
+        };
 
-    let new_reservation = reservation::ActiveModel {
-        id: Set(nanoid!()),
-        user_id: Set(Some(user.id)),
-        classroom_id: Set(Some(body.classroom_id)),
-        purpose: Set(body.purpose),
-        start_time: Set(start_dt),
-        end_time: Set(end_dt),
-        approved_by: NotSet,
-        reject_reason: NotSet,
-        cancel_reason: NotSet,
-        status: Set(ReservationStatus::Pending),
-    };
-
-    match new_reservation.insert(&state.db).await {
-        Ok(model) => {
-            // Cache the new reservation
-            let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("reservation_{}", model.id),
-                    serde_json::to_string(&model).unwrap(),
-                    get_redis_set_options(),
+    // Soft-deleted classrooms can no longer be booked
+    for classroom_id in &rooms {
+        match classroom::Entity::find_by_id(classroom_id)
+            .one(&state.db)
+            .await
+        {
+            Ok(Some(c)) if c.deleted_at.is_none() => {}
+            Ok(_) => return Err(AppError::NotFound("Classroom not found".to_string())),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+            }
+        }
+    }
+
+    for classroom_id in &rooms {
+        if let Some(error) = reject_if_unavailable(&state, classroom_id, start_dt, end_dt).await {
+            return Err(error);
+        }
+    }
+
+    let hours = (end_dt - start_dt).num_minutes() as f64 / 60.0;
+    let requested = QuotaUsage {
+        active_reservations: rooms.len() as u64,
+        active_hours: hours * rooms.len() as f64,
+    };
+    match enforce_quota(&state.db, &state.config.quota, &user.id, requested).await {
+        Ok(Ok(())) => {}
+        Ok(Err(message)) => return Err(AppError::Unprocessable(message)),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to check quota".to_string()));
+        }
+    }
+
+    let event_name = body
+        .event_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+    let mut flag_reason = None;
+    if let Some(name) = &event_name {
+        match event_duplicates::find_duplicates(
+            &state.db,
+            name,
+            start_dt,
+            state.config.event_duplicates.window,
+            None,
+        )
+        .await
+        {
+            Ok(duplicates) if !duplicates.is_empty() => {
+                flag_reason = Some(event_duplicates::duplicate_flag_reason(name, &duplicates));
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to check duplicate bookings for {}: {}", name, e),
+        }
+    }
+
+    // Rooms booked together share a group and are reviewed as one
+    let group_id = (rooms.len() > 1).then(|| nanoid!());
+    let new_reservations = rooms
+        .into_iter()
+        .map(|classroom_id| reservation::ActiveModel {
+            id: Set(nanoid!()),
+            user_id: Set(Some(user.id.clone())),
+            classroom_id: Set(Some(classroom_id)),
+            purpose: Set(body.purpose.clone()),
+            start_time: Set(start_dt),
+            end_time: Set(end_dt),
+            approved_by: NotSet,
+            reject_reason: NotSet,
+            cancel_reason: NotSet,
+            status: Set(ReservationStatus::Pending),
+            flagged_for_review: Set(flag_reason.is_some()),
+            flag_reason: Set(flag_reason.clone()),
+            checked_in_at: NotSet,
+            created_at: NotSet,
+            reviewed_at: NotSet,
+            event_name: Set(event_name.clone()),
+            version: NotSet,
+            group_id: Set(group_id.clone()),
+            guest_name: NotSet,
+            guest_email: NotSet,
+        })
+        .collect();
+
+    match insert_reservations(&state.db, new_reservations).await {
+        Ok(models) => {
+            for model in &models {
+                state.cache.invalidate_reservation(model).await;
+                state
+                    .cache
+                    .set(CacheKey::Reservation(&model.id), model)
+                    .await;
+
+                domain_events::publish(
+                    &state,
+                    DomainEvent::ReservationCreated {
+                        reservation: model.clone(),
+                    },
                 )
                 .await;
-            if let Err(e) = result {
-                warn!("Failed to cache reservation {} in Redis: {}", model.id, e);
-            }
-            // Invalidate user's reservation list cache
-            if let Some(user_id) = &model.user_id {
-                let _: Result<(), redis::RedisError> =
-                    redis.del(format!("reservations_user_{}", user_id)).await;
             }
 
-            let _ = send_email(
-                user.email,
-                "Reservation Created",
-                format!(
-                    "Your reservation has been created. Reservation ID: {}",
-                    model.id
-                ),
+            let quota_warning = warning_for_user(&state.db, &state.config.quota, &user.id)
+                .await
+                .unwrap_or(None);
+
+            let mut models = models.into_iter();
+            Ok((
+                StatusCode::CREATED,
+                Json(CreatedReservation {
+                    reservation: models.next().unwrap(),
+                    linked_reservations: models.collect(),
+                    quota_warning,
+                    time_adjustment,
+                }),
             )
-            .await
-            .unwrap();
+                .into_response())
+        }
+        Err(_) => Err(AppError::Internal(
+            "Failed to create reservation".to_string(),
+        )),
+    }
+}
 
-            match user::Entity::find()
-                .filter(user::Column::Role.eq(Role::Admin))
-                .all(&state.db)
-                .await
-            {
-                Ok(admins) => {
-                    for admin in admins {
-                        let _ = send_email(
-                            admin.email,
-                            format!("New Reservation Request: {}", model.id),
-                            format!(
-                                "There is a new reservation request. Reservation ID: {}",
-                                model.id
-                            ),
-                        )
-                        .await
-                        .unwrap();
-                    }
-                }
-                Err(_) => {
-                    return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch admins")
-                        .into_response();
-                }
+#[derive(Deserialize, ToSchema)]
+pub struct AdminCreateReservationBody {
+    pub classroom_id: String,
+    /// Registered user the reservation is for; leave out for a guest
+    pub user_id: Option<String>,
+    /// Guest without an account, e.g. someone booking by phone; required
+    /// without `user_id`
+    pub guest_name: Option<String>,
+    pub guest_email: Option<String>,
+    pub purpose: String,
+    pub start_time: String,
+    pub end_time: String,
+    pub event_name: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Admin: book a classroom for a user or for a guest without an account, e.g. a walk-in or phone booking. The reservation is approved straight away with the caller as reviewer. Quotas and blacklists do not apply, but the classroom must be free. Assistants can book their own classrooms only.",
+    path = "/admin",
+    request_body(content = AdminCreateReservationBody, content_type = "application/json"),
+    responses(
+        (status = 201, description = "Reservation created and approved", body = reservation::Model),
+        (status = 400, description = "Invalid time, outside opening hours, or not exactly one of `user_id` and `guest_name`", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Assistant not granted the classroom", body = ErrorResponse),
+        (status = 404, description = "Classroom or user not found", body = ErrorResponse),
+        (status = 409, description = "Classroom closed or already booked; `details.alternatives` suggests other rooms", body = ErrorResponse),
+        (status = 500, description = "Failed to create reservation", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn admin_create_reservation(
+    session: AuthSession,
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(body): Json<AdminCreateReservationBody>,
+) -> Result<Response, AppError> {
+    let staff = session.user.unwrap();
+    let booked_for = match walk_in::booked_for(body.user_id, body.guest_name, body.guest_email) {
+        Ok(booked_for) => booked_for,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if let BookedFor::User(user_id) = &booked_for {
+        match user::Entity::find_by_id(user_id).one(&state.db).await {
+            Ok(Some(u)) if u.deleted_at.is_none() => {}
+            Ok(_) => return Err(AppError::NotFound("User not found".to_string())),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to fetch user".to_string()));
             }
+        }
+    }
+
+    let start_dt = match parse_dt(&body.start_time) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid start_time".to_string())),
+    };
+    let end_dt = match parse_dt(&body.end_time) {
+        Ok(v) => v,
+        Err(_) => return Err(AppError::BadRequest("Invalid end_time".to_string())),
+    };
+    if start_dt >= end_dt {
+        return Err(AppError::BadRequest(
+            "'start_time' must be < 'end_time'".to_string(),
+        ));
+    }
+    let (start_dt, end_dt, _) = match slots::align(&state.config.slots, start_dt, end_dt) {
+        Ok(aligned) => aligned,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+
+    match classroom::Entity::find_by_id(&body.classroom_id)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(c)) if c.deleted_at.is_none() => {}
+        Ok(_) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    }
+    match review_scope(&state.db, &staff).await {
+        Ok(scope) if scope.allows(Some(&body.classroom_id)) => {}
+        Ok(_) => return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to create reservation".to_string(),
+            ));
+        }
+    }
+    if let Some(error) = reject_if_unavailable(&state, &body.classroom_id, start_dt, end_dt).await {
+        return Err(error);
+    }
 
-            (StatusCode::CREATED, Json(model)).into_response()
+    let (user_id, guest_name, guest_email) = match booked_for {
+        BookedFor::User(user_id) => (Some(user_id), None, None),
+        BookedFor::Guest { name, email } => (None, Some(name), email),
+    };
+    let event_name = body
+        .event_name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string);
+    let new_reservation = reservation::ActiveModel {
+        id: Set(nanoid!()),
+        user_id: Set(user_id),
+        classroom_id: Set(Some(body.classroom_id)),
+        purpose: Set(body.purpose),
+        start_time: Set(start_dt),
+        end_time: Set(end_dt),
+        approved_by: Set(Some(staff.id.clone())),
+        reject_reason: NotSet,
+        cancel_reason: NotSet,
+        status: Set(ReservationStatus::Approved),
+        flagged_for_review: Set(false),
+        flag_reason: NotSet,
+        checked_in_at: NotSet,
+        created_at: NotSet,
+        reviewed_at: Set(Some(Utc::now().with_timezone(&campus_offset()))),
+        event_name: Set(event_name),
+        version: NotSet,
+        group_id: NotSet,
+        guest_name: Set(guest_name),
+        guest_email: Set(guest_email),
+    };
+    let model = match new_reservation.insert(&state.db).await {
+        Ok(model) => model,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to create reservation".to_string(),
+            ));
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to create reservation",
+    };
+    audit
+        .log(
+            &state.db,
+            Target::Reservation,
+            &model.id,
+            "create_on_behalf",
+            None,
+            Some(snapshot(&model)),
         )
-            .into_response(),
+        .await;
+
+    state.cache.invalidate_reservation(&model).await;
+    state
+        .cache
+        .set(CacheKey::Reservation(&model.id), &model)
+        .await;
+    tokio::spawn(key_cabinet::issue_pin(state.clone(), model.clone()));
+    tokio::spawn(pickup::issue_code(state.clone(), model.clone()));
+    // Tells the user their booking is approved, like any other review
+    domain_events::publish(
+        &state,
+        DomainEvent::ReservationReviewed {
+            reservation: model.clone(),
+            alternatives: Vec::new(),
+        },
+    )
+    .await;
+
+    Ok((StatusCode::CREATED, Json(model)).into_response())
+}
+
+/// Saves the reservations of one request, all of them or none.
+async fn insert_reservations(
+    db: &DatabaseConnection,
+    reservations: Vec<reservation::ActiveModel>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let mut models = Vec::with_capacity(reservations.len());
+    for reservation in reservations {
+        models.push(reservation.insert(&txn).await?);
     }
+    txn.commit().await?;
+    Ok(models)
 }
 
 // ===============================
@@ -189,18 +572,81 @@ pub async fn create_reservation(
 pub struct ReviewReservationBody {
     pub status: ReservationStatus,
     pub reject_reason: Option<String>,
+    /// Set when rejecting because of a scheduling problem; similar classrooms
+    /// free in the same window are then suggested to the requester
+    #[serde(default)]
+    pub scheduling_conflict: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ReviewReservationResponse {
+    pub reservation: reservation::Model,
+    /// The other rooms booked together with it, reviewed along with it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub linked_reservations: Vec<reservation::Model>,
+    /// Only filled when rejecting with `scheduling_conflict`
+    pub alternatives: Vec<AlternativeRoom>,
+    /// Set when this was the first of the two approvals the classroom
+    /// requires; the reservation stays pending until another reviewer
+    /// approves it
+    pub awaiting_second_approval: bool,
+}
+
+/// Records the approval of a pending reservation when its classroom
+/// requires two, and tells whether it is the first or the second. Reads go
+/// through `txn` too, as memory mode has a single connection.
+async fn approval_step(
+    txn: &DatabaseTransaction,
+    reservation: &reservation::Model,
+    reviewer_id: &str,
+) -> Result<ApprovalStep, AppError> {
+    let failed = || AppError::Internal("Failed to review reservation".to_string());
+    let requires_double_approval = match &reservation.classroom_id {
+        Some(classroom_id) => classroom::Entity::find_by_id(classroom_id)
+            .one(txn)
+            .await
+            .map_err(|_| failed())?
+            .is_some_and(|c| c.requires_double_approval),
+        None => false,
+    };
+    if !requires_double_approval {
+        return Ok(ApprovalStep::Approve);
+    }
+    let approvals = double_approval::approvals_for(txn, &reservation.id)
+        .await
+        .map_err(|_| failed())?;
+    let step = double_approval::next_step(true, &approvals, reviewer_id)?;
+    let now = Utc::now().with_timezone(&campus_offset());
+    double_approval::record(txn, &reservation.id, reviewer_id, now)
+        .await
+        .map_err(|_| failed())?;
+    Ok(step)
+}
+
+/// Drops the reviewer's claims on reservations they just reviewed. Claims
+/// held by others are left alone.
+async fn release_claims(
+    redis: &mut RedisConnection,
+    reservation_ids: &[String],
+    reviewer_id: &str,
+) {
+    for reservation_id in reservation_ids {
+        let _ = review_lock::release(redis, reservation_id, reviewer_id).await;
+    }
 }
 
 #[utoipa::path(
     put,
     tags = ["Reservation"],
-    description = "Review a reservation (Admin only)",
+    description = "Review a reservation (Admin, or an assistant for the reservation's classroom). Only transitions allowed by the reservation lifecycle are accepted: approve/reject/cancel a pending reservation, or cancel/complete/mark no-show an approved one. In classrooms requiring double approval the first approval leaves the reservation pending with `awaiting_second_approval` set and notifies the other reviewers; it becomes approved once a different reviewer approves it too. Reservations claimed by another reviewer cannot be reviewed until the claim is released or runs out; reviewing releases the caller's own claims. For rooms booked together, a claim on any of them holds up the review. Reservations of several rooms booked together are reviewed as one: every change applies to all of them, and the others are returned in `linked_reservations`.",
     path = "/{id}/review",
     request_body(content = ReviewReservationBody, content_type = "application/json"),
     responses(
-        (status = 200, body = String),
-        (status = 404, body = String),
-        (status = 500, body = String),
+        (status = 200, body = ReviewReservationResponse),
+        (status = 403, description = "Assistant not granted the reservation's classroom", body = ErrorResponse),
+        (status = 404, body = ErrorResponse),
+        (status = 409, description = "Status change not allowed, the reviewer already gave the first of two approvals, or another reviewer claimed the reservation", body = ErrorResponse),
+        (status = 500, body = ErrorResponse),
     ),
     params(("id" = String, Path)),
     security(("session_cookie" = []))
@@ -208,85 +654,223 @@ pub struct ReviewReservationBody {
 pub async fn review_reservation(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    session: AuthSession,
+    audit: AuditContext,
     Json(body): Json<ReviewReservationBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
+    let reviewer = session.user.unwrap();
     let ReviewReservationBody {
         status,
         reject_reason,
+        scheduling_conflict,
     } = body;
+    let failed = || AppError::Internal("Failed to review reservation".to_string());
 
-    match reservation::Entity::find_by_id(&id).one(&state.db).await {
-        Ok(Some(res_model)) => {
-            let mut reservation: reservation::ActiveModel = res_model.into();
-            reservation.status = Set(status);
-            reservation.reject_reason = Set(reject_reason);
-
-            match reservation.update(&state.db).await {
-                Ok(reservation_updated) => {
-                    // Invalidate cache for this reservation
-                    let mut redis = state.redis.clone();
-                    let _: Result<(), redis::RedisError> = redis
-                        .del(format!("reservation_{}", reservation_updated.id))
-                        .await;
-                    // Also invalidate user's reservation list cache if it exists
-                    if let Some(user_id) = &reservation_updated.user_id {
-                        let _: Result<(), redis::RedisError> =
-                            redis.del(format!("reservations_user_{}", user_id)).await;
-                    }
-
-                    let user = match user::Entity::find_by_id(
-                        reservation_updated.user_id.as_ref().unwrap(),
-                    )
-                    .one(&state.db)
-                    .await
-                    {
-                        Ok(Some(u)) => u,
-                        Ok(None) => {
-                            return (StatusCode::NOT_FOUND, "User not found").into_response();
-                        }
-                        Err(_) => {
-                            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user")
-                                .into_response();
-                        }
-                    };
-
-                    let mut body_builder = Builder::default();
-                    body_builder.append("Your reservation has been reviewed.\nStatus: ");
-                    body_builder.append(format!("{:?}", reservation_updated.status));
-                    if reservation_updated.status == ReservationStatus::Rejected {
-                        if let Some(ref reason) = reservation_updated.reject_reason {
-                            body_builder.append("\nReason: ");
-                            body_builder.append(reason.as_str());
-                        }
-                    }
-                    let email_body = body_builder.string().unwrap();
-
-                    send_email(
-                        user.email,
-                        format!(
-                            "Reservation has been reviewed: {:?}",
-                            reservation_updated.id
-                        ),
-                        email_body,
+    let res_model = match reservation::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(res_model)) => res_model,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => return Err(failed()),
+    };
+    // Rooms booked together are reviewed together, so the reviewer needs
+    // every one of them in scope
+    let members = reservation_groups::members(&state.db, &res_model)
+        .await
+        .map_err(|_| failed())?;
+    match review_scope(&state.db, &reviewer).await {
+        Ok(scope)
+            if members
+                .iter()
+                .all(|member| scope.allows(member.classroom_id.as_deref())) => {}
+        Ok(_) => return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string())),
+        Err(_) => return Err(failed()),
+    }
+    for member in &members {
+        reservation_lifecycle::check(member, &status, Actor::Admin)?;
+    }
+    // Claims fail open: with Redis down reviews go ahead unguarded. Another
+    // reviewer's claim on any room of the group holds up the whole group
+    let mut redis = state.redis.clone();
+    let member_ids: Vec<String> = members.iter().map(|member| member.id.clone()).collect();
+    if let Ok(locks) = review_lock::holders(&mut redis, &member_ids).await {
+        for member_id in &member_ids {
+            review_lock::check(locks.get(member_id), &reviewer.id)?;
+        }
+    }
+    // The approval, the status change and their audit entries are saved
+    // together or not at all
+    let txn = state.db.begin().await.map_err(|_| failed())?;
+    if res_model.status == ReservationStatus::Pending && status == ReservationStatus::Approved {
+        let mut awaiting_second = false;
+        for member in &members {
+            if approval_step(&txn, member, &reviewer.id).await? == ApprovalStep::AwaitSecond {
+                awaiting_second = true;
+            }
+        }
+        if awaiting_second {
+            for member in &members {
+                audit
+                    .record(
+                        &txn,
+                        Target::Reservation,
+                        &member.id,
+                        "first_approval",
+                        None,
+                        None,
                     )
                     .await
-                    .unwrap();
-                    (StatusCode::OK, "Reservation reviewed successfully").into_response()
-                }
-                Err(_) => (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to review reservation",
-                )
-                    .into_response(),
+                    .map_err(|_| failed())?;
             }
+            txn.commit().await.map_err(|_| failed())?;
+            domain_events::publish(
+                &state,
+                DomainEvent::ReservationPartiallyApproved {
+                    reservation: res_model.clone(),
+                    approved_by: reviewer.id.clone(),
+                },
+            )
+            .await;
+            release_claims(&mut redis, &member_ids, &reviewer.id).await;
+            let mut members = members.into_iter();
+            return Ok((
+                StatusCode::OK,
+                Json(ReviewReservationResponse {
+                    reservation: members.next().unwrap(),
+                    linked_reservations: members.collect(),
+                    alternatives: Vec::new(),
+                    awaiting_second_approval: true,
+                }),
+            )
+                .into_response());
+        }
+    }
+
+    let mut updated = Vec::with_capacity(members.len());
+    for member in members {
+        // Approving or rejecting a pending request counts as its review;
+        // later transitions such as completing it do not
+        let first_review = member.status == ReservationStatus::Pending
+            && matches!(
+                status,
+                ReservationStatus::Approved | ReservationStatus::Rejected
+            );
+        let previous = member.clone();
+        let mut reservation: reservation::ActiveModel = member.into();
+        if first_review {
+            reservation.approved_by = Set(Some(reviewer.id.clone()));
+            reservation.reviewed_at = Set(Some(Utc::now().with_timezone(&campus_offset())));
+        }
+        reservation.status = Set(status.clone());
+        reservation.reject_reason = Set(reject_reason.clone());
+        reservation.flagged_for_review = Set(false);
+        reservation.flag_reason = Set(None);
+
+        let reservation_updated =
+            versioning::update(&txn, reservation, reservation::Column::Version, None)
+                .await
+                .map_err(|_| failed())?;
+        audit
+            .record(
+                &txn,
+                Target::Reservation,
+                &reservation_updated.id,
+                "review",
+                Some(snapshot(&previous)),
+                Some(snapshot(&reservation_updated)),
+            )
+            .await
+            .map_err(|_| failed())?;
+        updated.push(reservation_updated);
+    }
+    txn.commit().await.map_err(|_| failed())?;
+
+    for reservation_updated in &updated {
+        // Hand the key over through the cabinet or with a pickup code, or
+        // take back a PIN or code issued before the reservation was
+        // cancelled
+        if reservation_updated.status == ReservationStatus::Approved {
+            tokio::spawn(key_cabinet::issue_pin(
+                state.clone(),
+                reservation_updated.clone(),
+            ));
+            tokio::spawn(pickup::issue_code(
+                state.clone(),
+                reservation_updated.clone(),
+            ));
+        } else {
+            tokio::spawn(key_cabinet::revoke_pins(
+                state.clone(),
+                reservation_updated.id.clone(),
+            ));
+            tokio::spawn(pickup::revoke_codes(
+                state.clone(),
+                reservation_updated.id.clone(),
+            ));
+        }
+        state
+            .cache
+            .invalidate_reservation(reservation_updated)
+            .await;
+    }
+    release_claims(&mut redis, &member_ids, &reviewer.id).await;
+
+    let mut updated = updated.into_iter();
+    let reservation_updated = updated.next().unwrap();
+    let linked_reservations: Vec<reservation::Model> = updated.collect();
+    let classroom = match &reservation_updated.classroom_id {
+        Some(classroom_id) => classroom::Entity::find_by_id(classroom_id)
+            .one(&state.db)
+            .await
+            .unwrap_or(None),
+        None => None,
+    };
+
+    let alternatives = match &classroom {
+        Some(original)
+            if scheduling_conflict
+                && linked_reservations.is_empty()
+                && reservation_updated.status == ReservationStatus::Rejected =>
+        {
+            suggest_alternatives(
+                &state.db,
+                original,
+                reservation_updated.start_time,
+                reservation_updated.end_time,
+                MAX_ALTERNATIVE_ROOMS,
+            )
+            .await
+            .unwrap_or_default()
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Reservation not found").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to review reservation",
+        _ => Vec::new(),
+    };
+
+    domain_events::publish(
+        &state,
+        DomainEvent::ReservationReviewed {
+            reservation: reservation_updated.clone(),
+            alternatives: alternatives.clone(),
+        },
+    )
+    .await;
+    for linked in &linked_reservations {
+        domain_events::publish(
+            &state,
+            DomainEvent::ReservationReviewed {
+                reservation: linked.clone(),
+                alternatives: Vec::new(),
+            },
         )
-            .into_response(),
+        .await;
     }
+    Ok((
+        StatusCode::OK,
+        Json(ReviewReservationResponse {
+            reservation: reservation_updated,
+            linked_reservations,
+            alternatives,
+            awaiting_second_approval: false,
+        }),
+    )
+        .into_response())
 }
 
 // ===============================
@@ -294,24 +878,62 @@ pub async fn review_reservation(
 // ===============================
 #[derive(Deserialize, ToSchema)]
 pub struct UpdateReservationBody {
-    pub purpose: Option<String>,
-    pub start_time: Option<String>,
-    pub end_time: Option<String>,
+    pub purpose: String,
+    pub start_time: String,
+    pub end_time: String,
+    /// Left out or empty clears it
+    #[serde(default)]
+    pub event_name: Option<String>,
+    /// `version` of the reservation this edit was made on; refused with 409
+    /// when someone else saved it since
+    pub version: Option<i32>,
+}
+
+/// JSON Merge Patch of a reservation: left-out fields stay as they are and
+/// `null` clears `event_name`.
+#[derive(Deserialize, ToSchema)]
+pub struct PatchReservationBody {
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub purpose: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub start_time: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub end_time: Patch<String>,
+    #[serde(default)]
+    #[schema(value_type = Option<String>)]
+    pub event_name: Patch<String>,
+    /// `version` of the reservation this edit was made on; refused with 409
+    /// when someone else saved it since
+    pub version: Option<i32>,
+}
+
+/// Changes to a pending reservation; `None` leaves a field untouched.
+struct ReservationChanges {
+    purpose: Option<String>,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    event_name: Option<Option<String>>,
+    /// Version the changes were made on, if the client sent one
+    version: Option<i32>,
 }
 
 #[utoipa::path(
     put,
     tags = ["Reservation"],
-    description = "Update own reservation request (only when pending)",
+    description = "Replace own reservation request (only when pending): every field is set from the body, and a left-out `event_name` is cleared. Use PATCH to change single fields. New times are fitted to the slot grid like on creation. A first approval already given in a classroom requiring two is withdrawn. For rooms booked together, `purpose` and `event_name` change for all of them, and the others are returned in `linked_reservations`; their times cannot be changed.",
     path = "/{id}",
     request_body(content = UpdateReservationBody, content_type = "application/json"),
     responses(
-        (status = 200, description = "Reservation updated", body = reservation::Model),
-        (status = 401, description = "Unauthorized"),
-        (status = 403, description = "Forbidden"),
-        (status = 404, description = "Reservation not found"),
-        (status = 400, description = "Only pending reservations can be updated"),
-        (status = 500, description = "Failed to update reservation")
+        (status = 200, description = "Reservation updated", body = UpdatedReservation),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 400, description = "Only pending reservations can be updated", body = ErrorResponse),
+        (status = 409, description = "Classroom closed or already booked, with `details.alternatives` suggesting other rooms; or someone else saved the reservation since `version`, with `details.current_version` the version now", body = ErrorResponse),
+        (status = 500, description = "Failed to update reservation", body = ErrorResponse)
     ),
     params(("id" = String, Path)),
     security(("session_cookie" = []))
@@ -321,98 +943,242 @@ pub async fn update_reservation(
     State(state): State<AppState>,
     Path(id): Path<String>,
     Json(body): Json<UpdateReservationBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
+    let changes = ReservationChanges {
+        purpose: Some(body.purpose),
+        start_time: Some(body.start_time),
+        end_time: Some(body.end_time),
+        event_name: Some(body.event_name),
+        version: body.version,
+    };
+    apply_reservation_changes(session, &state, id, changes).await
+}
+
+#[utoipa::path(
+    patch,
+    tags = ["Reservation"],
+    description = "Partially update own reservation request (only when pending) with a JSON Merge Patch: left-out fields are untouched and `null` clears `event_name`. `purpose` and the times cannot be null. New times are fitted to the slot grid like on creation. A first approval already given in a classroom requiring two is withdrawn. For rooms booked together, `purpose` and `event_name` change for all of them, and the others are returned in `linked_reservations`; their times cannot be changed.",
+    path = "/{id}",
+    request_body(content = PatchReservationBody, content_type = "application/merge-patch+json"),
+    responses(
+        (status = 200, description = "Reservation updated", body = UpdatedReservation),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 400, description = "Only pending reservations can be updated, or a required field was set to null", body = ErrorResponse),
+        (status = 409, description = "Classroom closed or already booked, with `details.alternatives` suggesting other rooms; or someone else saved the reservation since `version`, with `details.current_version` the version now", body = ErrorResponse),
+        (status = 500, description = "Failed to update reservation", body = ErrorResponse)
+    ),
+    params(("id" = String, Path)),
+    security(("session_cookie" = []))
+)]
+pub async fn patch_reservation(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<PatchReservationBody>,
+) -> Result<Response, AppError> {
+    let changes = match (
+        body.purpose.required("purpose"),
+        body.start_time.required("start_time"),
+        body.end_time.required("end_time"),
+    ) {
+        (Ok(purpose), Ok(start_time), Ok(end_time)) => ReservationChanges {
+            purpose,
+            start_time,
+            end_time,
+            event_name: body.event_name.optional(),
+            version: body.version,
+        },
+        (Err(message), _, _) | (_, Err(message), _) | (_, _, Err(message)) => {
+            return Err(AppError::BadRequest(message));
+        }
+    };
+    apply_reservation_changes(session, &state, id, changes).await
+}
+
+async fn apply_reservation_changes(
+    session: AuthSession,
+    state: &AppState,
+    id: String,
+    changes: ReservationChanges,
+) -> Result<Response, AppError> {
     let user = match session.user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
     };
 
-    let UpdateReservationBody {
+    let ReservationChanges {
         purpose,
         start_time,
         end_time,
-    } = body;
+        event_name,
+        version,
+    } = changes;
 
     let res_model = match reservation::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(r)) => r,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Reservation not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch reservation",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
         }
     };
 
     if res_model.user_id != Some(user.id) {
-        return (
-            StatusCode::FORBIDDEN,
-            "You can only update your own reservation",
-        )
-            .into_response();
+        return Err(AppError::Forbidden(
+            "You can only update your own reservation".to_string(),
+        ));
     }
 
-    if res_model.status != ReservationStatus::Pending {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Only pending reservations can be updated",
-        )
-            .into_response();
+    // A stale edit hears about the newer version first, even when that
+    // version is what took the reservation out of pending
+    versioning::check(version, res_model.version)?;
+    if !reservation_lifecycle::is_editable(&res_model.status) {
+        return Err(AppError::BadRequest(
+            "Only pending reservations can be updated".to_string(),
+        ));
     }
 
-    let mut reservation: reservation::ActiveModel = res_model.into();
-
-    if let Some(p) = purpose {
-        reservation.purpose = Set(p);
+    let times_changed = start_time.is_some() || end_time.is_some();
+    // The rooms of a group keep the slot they were booked for together
+    if times_changed && res_model.group_id.is_some() {
+        return Err(AppError::BadRequest(
+            "Times of rooms booked together cannot be changed; cancel and book them again"
+                .to_string(),
+        ));
     }
+    // The other rooms of a group describe the same event, so they take the
+    // same purpose and event name
+    let others: Vec<reservation::ActiveModel> =
+        match reservation_groups::members(&state.db, &res_model).await {
+            Ok(members) => members.into_iter().skip(1).map(Into::into).collect(),
+            Err(_) => {
+                return Err(AppError::Internal(
+                    "Failed to fetch reservation".to_string(),
+                ));
+            }
+        };
+    let event_name = event_name.map(|name| {
+        let name = name.as_deref().map(str::trim).unwrap_or_default();
+        (!name.is_empty()).then(|| name.to_string())
+    });
+    let describe = |reservation: &mut reservation::ActiveModel| {
+        if let Some(purpose) = &purpose {
+            reservation.purpose = Set(purpose.clone());
+        }
+        if let Some(event_name) = &event_name {
+            reservation.event_name = Set(event_name.clone());
+        }
+    };
+
+    let classroom_id = res_model.classroom_id.clone();
+    let loaded_version = res_model.version;
+    let mut start_dt = res_model.start_time;
+    let mut end_dt = res_model.end_time;
+    let mut reservation: reservation::ActiveModel = res_model.into();
+    describe(&mut reservation);
+    let others = others
+        .into_iter()
+        .map(|mut other| {
+            describe(&mut other);
+            other
+        })
+        .collect();
 
     if let Some(start) = start_time {
-        let start_dt = match parse_dt(&start) {
+        start_dt = match parse_dt(&start) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid start_time").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid start_time".to_string())),
         };
-        reservation.start_time = Set(start_dt);
     }
 
     if let Some(end) = end_time {
-        let end_dt = match parse_dt(&end) {
+        end_dt = match parse_dt(&end) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid end_time").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid end_time".to_string())),
         };
+    }
+
+    let mut time_adjustment = None;
+    if times_changed {
+        if start_dt >= end_dt {
+            return Err(AppError::BadRequest(
+                "'start_time' must be < 'end_time'".to_string(),
+            ));
+        }
+        (start_dt, end_dt, time_adjustment) =
+            match slots::align(&state.config.slots, start_dt, end_dt) {
+                Ok(aligned) => aligned,
+                Err(message) => return Err(AppError::BadRequest(message)),
+            };
+        reservation.start_time = Set(start_dt);
         reservation.end_time = Set(end_dt);
+        if let Some(classroom_id) = classroom_id
+            && let Some(error) = reject_if_unavailable(state, &classroom_id, start_dt, end_dt).await
+        {
+            return Err(error);
+        }
     }
 
-    match reservation.update(&state.db).await {
+    match save_changes(&state.db, reservation, loaded_version, others).await {
         Ok(updated) => {
-            // Update cache and invalidate user's list cache
-            let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("reservation_{}", updated.id),
-                    serde_json::to_string(&updated).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!(
-                    "Failed to update cache for reservation {} in Redis: {}",
-                    updated.id, e
-                );
-            }
-            // Invalidate user's reservation list cache
-            if let Some(user_id) = &updated.user_id {
-                let _: Result<(), redis::RedisError> =
-                    redis.del(format!("reservations_user_{}", user_id)).await;
+            for updated in &updated {
+                // Approvals given so far were for the reservation as it was
+                if let Err(e) = double_approval::clear(&state.db, &updated.id).await {
+                    warn!(
+                        "Failed to clear approvals of reservation {}: {}",
+                        updated.id, e
+                    );
+                }
+                state.cache.invalidate_reservation(updated).await;
+                state
+                    .cache
+                    .set(CacheKey::Reservation(&updated.id), updated)
+                    .await;
             }
-            (StatusCode::OK, Json(updated)).into_response()
+            let mut updated = updated.into_iter();
+            Ok((
+                StatusCode::OK,
+                Json(UpdatedReservation {
+                    reservation: updated.next().unwrap(),
+                    linked_reservations: updated.collect(),
+                    time_adjustment,
+                }),
+            )
+                .into_response())
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update reservation",
+        Err(DbErr::RecordNotUpdated) => Err(versioning::stale(None)),
+        Err(_) => Err(AppError::Internal(
+            "Failed to update reservation".to_string(),
+        )),
+    }
+}
+
+/// Saves an edited reservation, checking it is still at `loaded_version`,
+/// together with the other rooms of its group. Returns them in that order.
+async fn save_changes(
+    db: &DatabaseConnection,
+    reservation: reservation::ActiveModel,
+    loaded_version: i32,
+    others: Vec<reservation::ActiveModel>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let mut updated = vec![
+        versioning::update(
+            &txn,
+            reservation,
+            reservation::Column::Version,
+            Some(loaded_version),
         )
-            .into_response(),
+        .await?,
+    ];
+    for other in others {
+        updated.push(versioning::update(&txn, other, reservation::Column::Version, None).await?);
     }
+    txn.commit().await?;
+    Ok(updated)
 }
 
 // ===============================
@@ -421,34 +1187,49 @@ pub async fn update_reservation(
 #[utoipa::path(
     get,
     tags = ["Reservation"],
-    description = "Get all reservations (Admin only)",
+    description = "Get all reservations (Admin only; assistants get those for their classrooms)",
     path = "",
     responses(
         (status = 200, description = "List of reservations with the specified status", body = [reservation::Model]),
-        (status = 500, description = "Failed to fetch reservations")
+        (status = 304, description = "The list is unchanged since the `ETag` sent in `If-None-Match`"),
+        (status = 400, description = "Unknown field", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
     ),
     params(
-        ("status" = Option<ReservationStatus>, Query, description = "Status of the reservations to fetch")
+        ("status" = Option<ReservationStatus>, Query, description = "Status of the reservations to fetch"),
+        ("fields" = Option<String>, Query, description = "Comma-separated columns to return, e.g. `id,start_time,status`; `id` is always included"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a list fetched before; answered with 304 while it is unchanged")
     ),
     security(("session_cookie" = []))
 )]
 pub async fn get_reservations(
+    session: AuthSession,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<GetReservationsQuery>,
-) -> impl IntoResponse {
-    let mut find_query = reservation::Entity::find();
+) -> Result<Response, AppError> {
+    let columns = match parse_fields(query.fields.as_deref()) {
+        Ok(columns) => columns,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let mut find_query = match review_scope(&state.db, &session.user.unwrap()).await {
+        Ok(scope) => scope.apply(reservation::Entity::find()),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
 
     if let Some(status) = query.status {
         find_query = find_query.filter(reservation::Column::Status.eq(status));
     }
 
-    match find_query.all(&state.db).await {
-        Ok(list) => (StatusCode::OK, Json(list)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch reservations",
-        )
-            .into_response(),
+    match fields::all(&state.db, find_query, columns.as_deref()).await {
+        Ok(list) => Ok(json_with_etag(&headers, &list)),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservations".to_string(),
+        )),
     }
 }
 
@@ -457,85 +1238,54 @@ pub async fn get_reservations(
     tags = ["Reservation"],
     description = "Get all reservations for self",
     path = "/self",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a list fetched before; answered with 304 while it is unchanged")
+    ),
     responses(
         (status = 200, description = "List of all reservations", body = [reservation::Model]),
+        (status = 304, description = "The list is unchanged since the `ETag` sent in `If-None-Match`"),
     ),
     security(("session_cookie" = []))
 )]
 pub async fn get_all_reservations_for_self(
     session: AuthSession,
     State(state): State<AppState>,
-) -> impl IntoResponse {
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
     let user = session.user.unwrap();
 
-    // Clone connection once for this handler
-    let mut redis = state.redis.clone();
-
     // Try to get from cache first
-    let cache_key = format!("reservations_user_{}", user.id);
-    let cached_reservations: Option<String> = match redis.get_ex(&cache_key, REDIS_EXPIRY).await {
-        Ok(reservations) => reservations,
-        Err(e) => {
-            warn!(
-                "Failed to get reservations for user {} from Redis cache: {}",
-                user.id, e
-            );
-            None
-        }
-    };
-
-    if let Some(reservations_str) = cached_reservations {
-        if let Ok(reservations) = serde_json::from_str::<Vec<reservation::Model>>(&reservations_str)
-        {
-            return (StatusCode::OK, Json(reservations)).into_response();
-        }
-    }
-
-    // Fallback to database
-    let reservations = match reservation::Entity::find()
-        .filter(reservation::Column::UserId.eq(&user.id))
-        .all(&state.db)
+    let reservations = match state
+        .cache
+        .get_or_load(CacheKey::UserReservations(&user.id), || {
+            reservation::Entity::find()
+                .filter(reservation::Column::UserId.eq(&user.id))
+                .all(&state.db)
+        })
         .await
     {
-        Ok(reservations) => {
-            // Cache the result for future requests
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    cache_key,
-                    serde_json::to_string(&reservations).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!(
-                    "Failed to cache reservations for user {} in Redis: {}",
-                    user.id, e
-                );
-            }
-            reservations
-        }
+        Ok(reservations) => reservations,
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch reservations",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
         }
     };
-    (StatusCode::OK, Json(reservations)).into_response()
+    Ok(json_with_etag(&headers, &reservations))
 }
 
 #[utoipa::path(
     delete,
     tags = ["Reservation"],
-    description = "Cancel a reservation",
+    description = "Cancel own pending or approved reservation before it starts. The reservation is kept with status Cancelled. Reservations of several rooms booked together are cancelled together.",
     path = "/{id}",
     responses(
-        (status = 200, description = "Reservation cancelled successfully"),
-        (status = 401, description = "Unauthorized"),
-        (status = 403, description = "Forbidden"),
-        (status = 404, description = "Reservation not found"),
-        (status = 500, description = "Failed to cancel reservation"),
+        (status = 200, description = "Reservation cancelled successfully", body = reservation::Model),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 409, description = "Reservation can no longer be cancelled", body = ErrorResponse),
+        (status = 500, description = "Failed to cancel reservation", body = ErrorResponse),
     ),
     params(("id" = String, Path)),
     security(("session_cookie" = []))
@@ -544,128 +1294,177 @@ pub async fn cancel_reservation(
     session: AuthSession,
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user = match session.user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
     };
 
     let reservation = match reservation::Entity::find_by_id(&id).one(&state.db).await {
         Ok(Some(reservation)) => reservation,
-        Ok(None) => return (StatusCode::NOT_FOUND, "Reservation not found").into_response(),
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
         Err(_) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch reservation",
-            )
-                .into_response();
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
         }
     };
 
     if reservation.user_id != Some(user.id) {
-        return (
-            StatusCode::FORBIDDEN,
-            "You can only cancel your own reservation",
-        )
-            .into_response();
+        return Err(AppError::Forbidden(
+            "You can only cancel your own reservation".to_string(),
+        ));
     }
 
-    if reservation.status != ReservationStatus::Pending {
-        return (
-            StatusCode::BAD_REQUEST,
-            "Only pending reservations can be cancelled",
-        )
-            .into_response();
+    // Rooms booked together are cancelled together
+    let members = match reservation_groups::members(&state.db, &reservation).await {
+        Ok(members) => members,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    for member in &members {
+        reservation_lifecycle::check(member, &ReservationStatus::Cancelled, Actor::Owner)?;
     }
 
-    // Save user_id before deleting (delete consumes the reservation)
-    let user_id = reservation.user_id.clone();
-
-    match reservation.delete(&state.db).await {
-        Ok(_) => {
-            // Invalidate cache
-            let mut redis = state.redis.clone();
-            let _: Result<(), redis::RedisError> = redis.del(format!("reservation_{}", id)).await;
-            // Invalidate user's reservation list cache
-            if let Some(user_id) = user_id {
-                let _: Result<(), redis::RedisError> =
-                    redis.del(format!("reservations_user_{}", user_id)).await;
+    match cancel_members(&state.db, members).await {
+        Ok(cancelled) => {
+            for reservation in &cancelled {
+                tokio::spawn(key_cabinet::revoke_pins(
+                    state.clone(),
+                    reservation.id.clone(),
+                ));
+                tokio::spawn(pickup::revoke_codes(state.clone(), reservation.id.clone()));
+                state.cache.invalidate_reservation(reservation).await;
             }
-            (StatusCode::OK, "Reservation cancelled successfully").into_response()
+            let cancelled = cancelled.into_iter().next().unwrap();
+            Ok((StatusCode::OK, Json(cancelled)).into_response())
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to cancel reservation",
-        )
-            .into_response(),
+        Err(_) => Err(AppError::Internal(
+            "Failed to cancel reservation".to_string(),
+        )),
     }
 }
 
+async fn cancel_members(
+    db: &DatabaseConnection,
+    members: Vec<reservation::Model>,
+) -> Result<Vec<reservation::Model>, DbErr> {
+    let txn = db.begin().await?;
+    let mut cancelled = Vec::with_capacity(members.len());
+    for member in members {
+        let mut reservation: reservation::ActiveModel = member.into();
+        reservation.status = Set(ReservationStatus::Cancelled);
+        cancelled
+            .push(versioning::update(&txn, reservation, reservation::Column::Version, None).await?);
+    }
+    txn.commit().await?;
+    Ok(cancelled)
+}
+
 // ===============================
 //   get reservation by id
 // ===============================
+#[derive(Serialize, ToSchema)]
+pub struct AdminReservationDetail {
+    #[serde(flatten)]
+    pub reservation: reservation::Model,
+    /// Internal notes, oldest first; never shown to the requester
+    pub notes: Vec<ReservationNote>,
+    /// Supporting documents, oldest first
+    pub attachments: Vec<reservation_attachment::Model>,
+    /// When the door system saw the room used; `None` if the door was not
+    /// opened for the reservation
+    pub usage: Option<ActualUsage>,
+}
+
 #[utoipa::path(
     get,
     tags = ["Reservation"],
-    description = "Admin: get reservation by id",
+    description = "Admin: get reservation by id, with its internal notes, attachments and when the door system saw the room used",
     path = "/admin/{id}",
     params(
         ("id" = String, Path, description = "Reservation id")
     ),
     responses(
-        (status = 200, description = "Reservation found", body = reservation::Model),
-        (status = 404, description = "Reservation not found", body = String),
-        (status = 500, description = "Failed to fetch reservation", body = String),
+        (status = 200, description = "Reservation found", body = AdminReservationDetail),
+        (status = 403, description = "Assistant not granted the reservation's classroom", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservation", body = ErrorResponse),
     ),
     security(("session_cookie" = []))
 )]
 pub async fn admin_get_reservation_by_id(
+    session: AuthSession,
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> impl IntoResponse {
-    // Clone connection once for this handler
-    let mut redis = state.redis.clone();
-
+) -> Result<Response, AppError> {
     // Try to get from cache first
-    let cached_reservation: Option<String> = match redis
-        .get_ex(format!("reservation_{}", id), REDIS_EXPIRY)
-        .await
-    {
-        Ok(reservation) => reservation,
-        Err(e) => {
-            warn!("Failed to get reservation {} from Redis cache: {}", id, e);
-            None
-        }
+    let cached = state
+        .cache
+        .get::<reservation::Model>(CacheKey::Reservation(&id))
+        .await;
+    let model = match cached {
+        Some(model) => model,
+        // Fallback to database
+        None => match reservation::Entity::find_by_id(&id).one(&state.db).await {
+            Ok(Some(model)) => {
+                // Cache the result for future requests
+                state
+                    .cache
+                    .set(CacheKey::Reservation(&model.id), &model)
+                    .await;
+                model
+            }
+            Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+            Err(_) => {
+                return Err(AppError::Internal(
+                    "Failed to fetch reservation".to_string(),
+                ));
+            }
+        },
     };
 
-    if let Some(reservation_str) = cached_reservation {
-        if let Ok(reservation) = serde_json::from_str::<reservation::Model>(&reservation_str) {
-            return (StatusCode::OK, Json(reservation)).into_response();
+    match review_scope(&state.db, &session.user.unwrap()).await {
+        Ok(scope) if scope.allows(model.classroom_id.as_deref()) => {}
+        Ok(_) => return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
         }
     }
 
-    // Fallback to database
-    match reservation::Entity::find_by_id(&id).one(&state.db).await {
-        Ok(Some(model)) => {
-            // Cache the result for future requests
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("reservation_{}", model.id),
-                    serde_json::to_string(&model).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache reservation {} in Redis: {}", model.id, e);
-            }
-            (StatusCode::OK, Json(model)).into_response()
+    // Notes, attachments and usage are not cached so they show up as soon
+    // as they are recorded
+    let notes = match notes_for(&state.db, &model.id).await {
+        Ok(notes) => notes,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch notes".to_string()));
+        }
+    };
+    let attachments = match attachments_for(&state.db, &model.id).await {
+        Ok(attachments) => attachments,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch attachments".to_string(),
+            ));
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "Reservation not found").into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch reservation",
+    };
+    match usage_for(&state.db, &model.id).await {
+        Ok(usage) => Ok((
+            StatusCode::OK,
+            Json(AdminReservationDetail {
+                reservation: model,
+                notes,
+                attachments,
+                usage,
+            }),
         )
-            .into_response(),
+            .into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch usage".to_string())),
     }
 }
 
@@ -678,7 +1477,11 @@ pub struct SelfListQuery {
     pub classroom_id: Option<String>,
     pub from: Option<String>,
     pub to: Option<String>,
-    pub sort: Option<String>, // asc | desc
+    /// Instead of `from` and `to`
+    pub date: Option<DateShorthand>,
+    pub sort: Option<String>,
+    /// Comma-separated columns to return, e.g. `id,start_time,status`
+    pub fields: Option<String>,
 }
 
 #[utoipa::path(
@@ -691,28 +1494,42 @@ pub struct SelfListQuery {
         ("classroom_id" = Option<String>, Query, description = "Filter by classroom id"),
         ("from" = Option<String>, Query, description = "Filter: start_time >= from (ISO8601)"),
         ("to" = Option<String>, Query, description = "Filter: start_time <= to (ISO8601)"),
-        ("sort" = Option<String>, Query, description = "Sort by start_time: asc|desc (default desc)")
+        ("date" = Option<DateShorthand>, Query, description = "Filter: reservations starting today, tomorrow, this week or next week in campus time (weeks start on Monday); cannot be combined with `from` or `to`"),
+        ("sort" = Option<String>, Query, description = "`start_time`, `end_time` or `created_at`, optionally suffixed with `:asc` or `:desc`; `asc` or `desc` alone sorts by start_time (default start_time:desc)"),
+        ("fields" = Option<String>, Query, description = "Comma-separated columns to return, e.g. `id,start_time,status`; `id` is always included"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a list fetched before; answered with 304 while it is unchanged")
     ),
     responses(
-        (status = 200, description = "List of reservations", body = [reservation::Model]),
-        (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid query"),
-        (status = 500, description = "Failed to fetch reservations")
+        (status = 200, description = "List of reservations; only the requested columns when `fields` is given", body = [reservation::Model],
+            headers(
+                ("Quota-Warning" = String, description = "Present when the user is within one reservation or one hour of their quota, e.g. `Reservation quota almost reached`"),
+                ("Quota-Remaining-Reservations" = u64, description = "Sent with `Quota-Warning` when a reservation limit is set: reservations left before it"),
+                ("Quota-Remaining-Hours" = f64, description = "Sent with `Quota-Warning` when an hours limit is set: hours left before it")
+            )),
+        (status = 304, description = "The list is unchanged since the `ETag` sent in `If-None-Match`"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn get_self_reservations_filtered(
     session: AuthSession,
     State(state): State<AppState>,
+    headers: HeaderMap,
     Query(query): Query<SelfListQuery>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user = match session.user {
         Some(u) => u,
-        None => return (StatusCode::UNAUTHORIZED, "Unauthorized").into_response(),
+        None => return Err(AppError::Unauthorized("Unauthorized".to_string())),
+    };
+    let columns = match parse_fields(query.fields.as_deref()) {
+        Ok(columns) => columns,
+        Err(message) => return Err(AppError::BadRequest(message)),
     };
 
     let mut find_query =
-        reservation::Entity::find().filter(reservation::Column::UserId.eq(Some(user.id)));
+        reservation::Entity::find().filter(reservation::Column::UserId.eq(Some(user.id.clone())));
 
     if let Some(status) = query.status {
         find_query = find_query.filter(reservation::Column::Status.eq(status));
@@ -722,10 +1539,20 @@ pub async fn get_self_reservations_filtered(
         find_query = find_query.filter(reservation::Column::ClassroomId.eq(Some(classroom_id)));
     }
 
+    if let Some(date) = query.date {
+        if query.from.is_some() || query.to.is_some() {
+            return Err(AppError::BadRequest(DATE_WITH_RANGE.to_string()));
+        }
+        let (start, end) = date.bounds(Utc::now().with_timezone(&campus_offset()));
+        find_query = find_query
+            .filter(reservation::Column::StartTime.gte(start))
+            .filter(reservation::Column::StartTime.lt(end));
+    }
+
     if let Some(from) = query.from {
         let from_dt = match parse_dt(&from) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid 'from'").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid 'from'".to_string())),
         };
         find_query = find_query.filter(reservation::Column::StartTime.gte(from_dt));
     }
@@ -733,27 +1560,39 @@ pub async fn get_self_reservations_filtered(
     if let Some(to) = query.to {
         let to_dt = match parse_dt(&to) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid 'to'").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid 'to'".to_string())),
         };
         find_query = find_query.filter(reservation::Column::StartTime.lte(to_dt));
     }
 
-    match query.sort.as_deref() {
-        Some("asc") => find_query = find_query.order_by_asc(reservation::Column::StartTime),
-        Some("desc") | None => {
-            find_query = find_query.order_by_desc(reservation::Column::StartTime)
-        }
-        Some(_) => return (StatusCode::BAD_REQUEST, "Invalid 'sort'").into_response(),
+    match parse_sort(query.sort.as_deref()) {
+        Ok(sort) => find_query = sort.apply(find_query),
+        Err(message) => return Err(AppError::BadRequest(message)),
     }
 
-    match find_query.all(&state.db).await {
-        Ok(list) => (StatusCode::OK, Json(list)).into_response(),
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to fetch reservations",
-        )
-            .into_response(),
+    let items = match fields::all(&state.db, find_query, columns.as_deref()).await {
+        Ok(list) => list,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
+
+    let quota_warning = warning_for_user(&state.db, &state.config.quota, &user.id)
+        .await
+        .unwrap_or(None);
+
+    let mut response = json_with_etag(&headers, &items);
+    for (name, value) in quota_warning.iter().flat_map(QuotaWarning::headers) {
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                response.headers_mut().insert(name.clone(), value);
+            }
+            Err(e) => warn!("Failed to build the {} header: {}", name, e),
+        }
     }
+    Ok(response)
 }
 
 // ===============================
@@ -762,7 +1601,7 @@ pub async fn get_self_reservations_filtered(
 #[utoipa::path(
     get,
     tags = ["Reservation"],
-    description = "Admin: list reservations with filters (status/classroom/user/time overlap) and pagination",
+    description = "Admin: list reservations with filters (status/classroom/user/time overlap) and pagination. Assistants only see reservations for their classrooms. `being_reviewed_by` names the reviewer who claimed a reservation with POST /reservation/admin/{id}/claim.",
     path = "/admin/list",
     params(
         ("status" = Option<ReservationStatus>, Query, description = "Filter by status"),
@@ -770,22 +1609,39 @@ pub async fn get_self_reservations_filtered(
         ("user_id" = Option<String>, Query, description = "Filter by user id"),
         ("from" = Option<String>, Query, description = "Time filter lower bound (overlap), ISO8601 or 'YYYY-MM-DD HH:MM'"),
         ("to" = Option<String>, Query, description = "Time filter upper bound (overlap), ISO8601 or 'YYYY-MM-DD HH:MM'"),
-        ("sort" = Option<String>, Query, description = "Sort by start_time: asc|desc (default desc)"),
-        ("page" = Option<u64>, Query, description = "Page number (default 1)"),
-        ("page_size" = Option<u64>, Query, description = "Page size (default 20, max 100)")
+        ("date" = Option<DateShorthand>, Query, description = "Reservations overlapping today, tomorrow, this week or next week in campus time (weeks start on Monday); cannot be combined with `from` or `to`"),
+        ("sort" = Option<String>, Query, description = "`start_time`, `end_time` or `created_at`, optionally suffixed with `:asc` or `:desc`; `asc` or `desc` alone sorts by start_time (default start_time:desc)"),
+        PageParams,
+        ("fields" = Option<String>, Query, description = "Comma-separated columns to return, e.g. `id,start_time,status`; `id` is always included"),
+        ("If-None-Match" = Option<String>, Header, description = "`ETag` of a page fetched before; answered with 304 while it is unchanged")
     ),
     responses(
-        (status = 200, description = "Paged list", body = PagedReservations),
-        (status = 400, description = "Invalid query"),
-        (status = 500, description = "Failed to fetch reservations")
+        (status = 200, description = "Paged list", body = PagedResponse<QueuedReservation<reservation::Model>>),
+        (status = 304, description = "The page is unchanged since the `ETag` sent in `If-None-Match`"),
+        (status = 400, description = "Invalid query", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
     ),
     security(("session_cookie" = []))
 )]
 pub async fn admin_list_reservations(
+    session: AuthSession,
     State(state): State<AppState>,
+    headers: HeaderMap,
+    pagination: Pagination,
     Query(query): Query<AdminListQuery>,
-) -> impl IntoResponse {
-    let mut find_query = reservation::Entity::find();
+) -> Result<Response, AppError> {
+    let columns = match parse_fields(query.fields.as_deref()) {
+        Ok(columns) => columns,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let mut find_query = match review_scope(&state.db, &session.user.unwrap()).await {
+        Ok(scope) => scope.apply(reservation::Entity::find()),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
 
     // status
     if let Some(status) = query.status {
@@ -802,28 +1658,42 @@ pub async fn admin_list_reservations(
         find_query = find_query.filter(reservation::Column::UserId.eq(Some(user_id)));
     }
 
+    if let Some(flagged) = query.flagged {
+        find_query = find_query.filter(reservation::Column::FlaggedForReview.eq(flagged));
+    }
+
+    if let Some(date) = query.date {
+        if query.from.is_some() || query.to.is_some() {
+            return Err(AppError::BadRequest(DATE_WITH_RANGE.to_string()));
+        }
+        let (start, end) = date.bounds(Utc::now().with_timezone(&campus_offset()));
+        find_query = find_query
+            .filter(reservation::Column::StartTime.lt(end))
+            .filter(reservation::Column::EndTime.gt(start));
+    }
+
     // time overlap: require both from & to
     if query.from.is_some() || query.to.is_some() {
         let from = match query.from.as_deref() {
             Some(v) => v,
-            None => return (StatusCode::BAD_REQUEST, "Missing 'from'").into_response(),
+            None => return Err(AppError::BadRequest("Missing 'from'".to_string())),
         };
         let to = match query.to.as_deref() {
             Some(v) => v,
-            None => return (StatusCode::BAD_REQUEST, "Missing 'to'").into_response(),
+            None => return Err(AppError::BadRequest("Missing 'to'".to_string())),
         };
 
         let from_dt = match parse_dt(from) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid 'from'").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid 'from'".to_string())),
         };
         let to_dt = match parse_dt(to) {
             Ok(v) => v,
-            Err(_) => return (StatusCode::BAD_REQUEST, "Invalid 'to'").into_response(),
+            Err(_) => return Err(AppError::BadRequest("Invalid 'to'".to_string())),
         };
 
         if from_dt >= to_dt {
-            return (StatusCode::BAD_REQUEST, "'from' must be < 'to'").into_response();
+            return Err(AppError::BadRequest("'from' must be < 'to'".to_string()));
         }
 
         // overlap: start < to AND end > from
@@ -833,61 +1703,150 @@ pub async fn admin_list_reservations(
     }
 
     // sorting
-    match query.sort.as_deref() {
-        Some("asc") => find_query = find_query.order_by_asc(reservation::Column::StartTime),
-        Some("desc") | None => {
-            find_query = find_query.order_by_desc(reservation::Column::StartTime)
-        }
-        Some(_) => return (StatusCode::BAD_REQUEST, "Invalid 'sort'").into_response(),
+    match parse_sort(query.sort.as_deref()) {
+        Ok(sort) => find_query = sort.apply(find_query),
+        Err(message) => return Err(AppError::BadRequest(message)),
     }
 
-    // pagination
-    let page_size = query.page_size.unwrap_or(20).min(100).max(1);
-    let page = query.page.unwrap_or(1).max(1);
-
-    let paginator = find_query.paginate(&state.db, page_size);
-    let total = match paginator.num_items().await {
-        Ok(v) => v,
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to count").into_response(),
+    let page = match fields::page(&state.db, find_query, columns.as_deref(), pagination).await {
+        Ok(page) => page,
+        Err(_) => return Err(AppError::Internal("Failed to fetch".to_string())),
     };
+    let ids: Vec<String> = page.items.iter().filter_map(row_id).collect();
+    let mut redis = state.redis.clone();
+    let mut locks = review_lock::holders(&mut redis, &ids)
+        .await
+        .unwrap_or_default();
+    let page = page.map(|row| QueuedReservation {
+        being_reviewed_by: row_id(&row).and_then(|id| locks.remove(&id)),
+        reservation: row,
+    });
+    Ok(json_with_etag(&headers, &page))
+}
 
-    let items = match paginator.fetch_page(page - 1).await {
-        Ok(v) => v,
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch").into_response(),
-    };
+/// A reservation in the review queue.
+#[derive(Serialize, ToSchema)]
+pub struct QueuedReservation<R> {
+    #[serde(flatten)]
+    pub reservation: R,
+    /// The reviewer who claimed the reservation and is looking at it now
+    pub being_reviewed_by: Option<ReviewLock>,
+}
 
-    (
-        StatusCode::OK,
-        Json(PagedReservations {
-            page,
-            page_size,
-            total,
-            items,
-        }),
-    )
-        .into_response()
+fn row_id(row: &Row<reservation::Model>) -> Option<String> {
+    match row {
+        Row::Full(reservation) => Some(reservation.id.clone()),
+        Row::Partial(value) => value.get("id")?.as_str().map(str::to_string),
+    }
 }
 
 // ===============================
 //   Reservation Router
 // ===============================
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Reservation", description = "Reservation endpoints")
+    ),
+    paths(
+        review_reservation,
+        create_reservation,
+        admin_create_reservation,
+        update_reservation,
+        patch_reservation,
+        get_reservations,
+        get_all_reservations_for_self,
+        admin_list_reservations,
+        admin_get_reservation_by_id,
+        cancel_reservation,
+        get_self_reservations_filtered,
+        crate::routes::pickup_code::get_pickup_code,
+        crate::routes::pickup_code::regenerate_pickup_code,
+        crate::routes::reservation_attachment::list_attachments,
+        crate::routes::reservation_attachment::upload_attachment,
+        crate::routes::reservation_attachment::download_attachment,
+        crate::routes::reservation_attachment::delete_attachment,
+        crate::routes::reservation_comment::list_reservation_comments,
+        crate::routes::reservation_comment::create_reservation_comment,
+        crate::routes::reservation_note::list_reservation_notes,
+        crate::routes::reservation_note::create_reservation_note,
+        crate::routes::review_nudge::nudge_reviewers,
+        crate::routes::event_duplicate::list_event_duplicates,
+        crate::routes::second_approval::list_awaiting_second_approval,
+        crate::routes::review_lock::claim_reservation,
+        crate::routes::review_lock::release_reservation
+    ),
+    components(schemas(
+        crate::entities::reservation::Model,
+        AdminCreateReservationBody,
+        crate::entities::sea_orm_active_enums::ReservationStatus,
+        ReviewReservationBody,
+        CreateReservationBody,
+        UpdateReservationBody,
+        PatchReservationBody,
+        GetReservationsQuery,
+        SelfListQuery,
+        AdminListQuery,
+        crate::pagination::PagedResponse<crate::entities::reservation::Model>,
+        crate::pagination::PagedResponse<QueuedReservation<crate::entities::reservation::Model>>,
+        QueuedReservation<crate::entities::reservation::Model>,
+        crate::review_lock::ReviewLock,
+        CreatedReservation,
+        UpdatedReservation,
+        crate::slots::TimeAdjustment,
+        ReviewReservationResponse,
+        crate::routes::classroom_schedule::UnavailableDetails,
+        crate::availability::AlternativeRoom,
+        crate::availability::DateShorthand,
+        crate::quota::QuotaWarning,
+        crate::routes::pickup_code::PickupCodeResponse,
+        AdminReservationDetail,
+        crate::door_events::ActualUsage,
+        crate::routes::reservation_attachment::UploadAttachmentBody,
+        crate::entities::reservation_attachment::Model,
+        crate::routes::reservation_comment::ReservationComment,
+        crate::routes::reservation_comment::CreateReservationCommentBody,
+        crate::routes::reservation_note::ReservationNote,
+        crate::routes::reservation_note::CreateReservationNoteBody,
+        crate::routes::review_nudge::NudgeResponse,
+        crate::routes::event_duplicate::EventDuplicatesQuery,
+        crate::event_duplicates::EventGroup,
+        crate::double_approval::AwaitingSecondApproval,
+        crate::entities::reservation_approval::Model,
+        crate::entities::key_pickup_code::Model
+    ))
+)]
+pub struct ReservationApi;
+
 pub fn reservation_router() -> Router<AppState> {
     let admin_only_route = Router::new()
+        .route("/admin", post(admin_create_reservation))
         .route("/admin/list", get(admin_list_reservations))
         .route("/admin/{id}", get(admin_get_reservation_by_id))
         .route("/{id}/review", put(review_reservation))
         .route("/", get(get_reservations))
-        .route_layer(permission_required!(AuthBackend, Role::Admin));
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ReviewReservations
+        ));
 
     let login_required_route = Router::new()
         .route("/", post(create_reservation))
         .route("/self", get(get_all_reservations_for_self))
         .route("/self/list", get(get_self_reservations_filtered))
-        .route("/{id}", put(update_reservation))
+        .route("/{id}", put(update_reservation).patch(patch_reservation))
         .route("/{id}", delete(cancel_reservation))
         .route_layer(login_required!(AuthBackend));
 
     Router::new()
         .merge(admin_only_route)
         .merge(login_required_route)
+        .merge(pickup_code_router())
+        .merge(reservation_attachment_router())
+        .merge(reservation_comment_router())
+        .merge(reservation_note_router())
+        .merge(review_nudge_router())
+        .merge(event_duplicate_router())
+        .merge(second_approval_router())
+        .merge(review_lock_router())
 }
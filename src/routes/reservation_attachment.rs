@@ -0,0 +1,351 @@
+use axum::{
+    Json, Router,
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::login_required;
+use axum_typed_multipart::{FieldData, TryFromMultipart, TypedMultipart};
+use nanoid::nanoid;
+use reqwest::multipart::{Form, Part};
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
+};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    audit::{AuditContext, Target, snapshot},
+    concurrency::limit_photo_uploads,
+    entities::reservation_attachment,
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    routes::{classroom::IMAGE_SERVICE_CLIENT, reservation::visible_reservation},
+    server_timing::{Dependency, measure},
+};
+
+#[derive(TryFromMultipart, ToSchema)]
+pub struct UploadAttachmentBody {
+    #[form_data(limit = "10MB")]
+    #[schema(value_type = String, format = "binary")]
+    file: FieldData<Bytes>,
+}
+
+/// Attachments of a reservation, oldest first.
+pub async fn attachments_for(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+) -> Result<Vec<reservation_attachment::Model>, DbErr> {
+    reservation_attachment::Entity::find()
+        .filter(reservation_attachment::Column::ReservationId.eq(reservation_id))
+        .order_by_asc(reservation_attachment::Column::CreatedAt)
+        .all(db)
+        .await
+}
+
+async fn find_attachment(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+    attachment_id: &str,
+) -> Result<reservation_attachment::Model, AppError> {
+    match reservation_attachment::Entity::find_by_id(attachment_id)
+        .filter(reservation_attachment::Column::ReservationId.eq(reservation_id))
+        .one(db)
+        .await
+    {
+        Ok(Some(attachment)) => Ok(attachment),
+        Ok(None) => Err(AppError::NotFound("Attachment not found".to_string())),
+        Err(_) => Err(AppError::Internal("Failed to fetch attachment".to_string())),
+    }
+}
+
+/// Removes a file from the image service. Failures are only logged; the
+/// attachment row is what grants access to it.
+async fn delete_file(state: &AppState, file_id: &str) {
+    let request = IMAGE_SERVICE_CLIENT
+        .delete(format!("{}/{}", state.config.image_service.url, file_id))
+        .header("key", state.config.image_service.api_key.clone())
+        .send();
+    match measure(Dependency::ImageService, request).await {
+        Ok(resp) if resp.status().is_success() || resp.status() == StatusCode::NOT_FOUND => {}
+        Ok(resp) => warn!(
+            "Failed to delete attachment file {}: image service returned {}",
+            file_id,
+            resp.status()
+        ),
+        Err(e) => warn!("Failed to delete attachment file {}: {}", file_id, e),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Attachments of a reservation, oldest first. Only the reservation's owner and reviewers of its classroom see them.",
+    path = "/{id}/attachments",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 200, description = "Attachments of the reservation", body = Vec<reservation_attachment::Model>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch attachments", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_attachments(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    visible_reservation(&state.db, &session.user.unwrap(), &id).await?;
+    match attachments_for(&state.db, &id).await {
+        Ok(attachments) => Ok((StatusCode::OK, Json(attachments)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch attachments".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Attach a supporting document, e.g. a club's approval PDF, to a reservation. The file is stored in the image service.",
+    path = "/{id}/attachments",
+    params(("id" = String, Path, description = "Reservation ID")),
+    request_body(content = UploadAttachmentBody, content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Attachment added", body = reservation_attachment::Model),
+        (status = 400, description = "The image service rejected the file", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to upload attachment", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn upload_attachment(
+    session: AuthSession,
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<String>,
+    TypedMultipart(UploadAttachmentBody { file }): TypedMultipart<UploadAttachmentBody>,
+) -> Result<Response, AppError> {
+    let uploader = session.user.unwrap();
+    visible_reservation(&state.db, &uploader, &id).await?;
+
+    let file_name = file
+        .metadata
+        .file_name
+        .clone()
+        .unwrap_or_else(|| "attachment".to_string());
+    let content_type = file.metadata.content_type.clone();
+    let size = file.contents.len() as i64;
+    let mut part = Part::bytes(file.contents.to_vec()).file_name(file_name.clone());
+    if let Some(mime) = &content_type {
+        part = match part.mime_str(mime) {
+            Ok(part) => part,
+            Err(_) => return Err(AppError::BadRequest("Invalid content type".to_string())),
+        };
+    }
+
+    let request = IMAGE_SERVICE_CLIENT
+        .post(format!("{}/", state.config.image_service.url))
+        .multipart(Form::new().part("image", part))
+        .header("key", state.config.image_service.api_key.clone())
+        .send();
+    let file_id = match measure(Dependency::ImageService, request).await {
+        Ok(resp) => match resp.status() {
+            StatusCode::CREATED => resp.text().await.unwrap(),
+            _ => return Err(AppError::BadRequest(resp.text().await.unwrap())),
+        },
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to upload attachment".to_string(),
+            ));
+        }
+    };
+
+    let attachment = reservation_attachment::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(id.clone()),
+        file_id: Set(file_id.clone()),
+        file_name: Set(file_name),
+        content_type: Set(content_type),
+        size: Set(size),
+        uploaded_by: Set(Some(uploader.id)),
+        created_at: NotSet,
+    };
+    let attachment = match attachment.insert(&state.db).await {
+        Ok(attachment) => attachment,
+        Err(e) => {
+            warn!("Failed to save attachment of reservation {}: {}", id, e);
+            delete_file(&state, &file_id).await;
+            return Err(AppError::Internal(
+                "Failed to upload attachment".to_string(),
+            ));
+        }
+    };
+    audit
+        .log(
+            &state.db,
+            Target::Reservation,
+            &id,
+            "attachment_add",
+            None,
+            Some(snapshot(&attachment)),
+        )
+        .await;
+
+    Ok((StatusCode::CREATED, Json(attachment)).into_response())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Download an attachment of a reservation, streamed from the image service.",
+    path = "/{id}/attachments/{attachment_id}",
+    params(
+        ("id" = String, Path, description = "Reservation ID"),
+        ("attachment_id" = String, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 200, description = "File contents", content_type = "application/octet-stream"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation or attachment not found", body = ErrorResponse),
+        (status = 502, description = "Image service unavailable", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn download_attachment(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path((id, attachment_id)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    visible_reservation(&state.db, &session.user.unwrap(), &id).await?;
+    let attachment = find_attachment(&state.db, &id, &attachment_id).await?;
+
+    let request = IMAGE_SERVICE_CLIENT
+        .get(format!(
+            "{}/{}",
+            state.config.image_service.url, attachment.file_id
+        ))
+        .header("key", state.config.image_service.api_key.clone())
+        .send();
+    let resp = match measure(Dependency::ImageService, request).await {
+        Ok(resp) => resp,
+        Err(e) => {
+            warn!(
+                "Failed to fetch attachment file {} from image service: {}",
+                attachment.file_id, e
+            );
+            return Err(AppError::BadGateway(
+                "Image service unavailable".to_string(),
+            ));
+        }
+    };
+    match resp.status() {
+        StatusCode::OK => {}
+        StatusCode::NOT_FOUND => {
+            return Err(AppError::NotFound("Attachment not found".to_string()));
+        }
+        status => {
+            warn!(
+                "Image service returned {} for attachment file {}",
+                status, attachment.file_id
+            );
+            return Err(AppError::BadGateway(
+                "Image service unavailable".to_string(),
+            ));
+        }
+    }
+
+    let content_type = attachment
+        .content_type
+        .as_deref()
+        .unwrap_or("application/octet-stream");
+    let mut headers = HeaderMap::new();
+    if let Ok(value) = HeaderValue::from_str(content_type) {
+        headers.insert(header::CONTENT_TYPE, value);
+    }
+    // Quotes would end the file name early
+    let disposition = format!(
+        "attachment; filename=\"{}\"",
+        attachment.file_name.replace('"', "")
+    );
+    if let Ok(value) = HeaderValue::from_str(&disposition) {
+        headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    if let Some(len) = resp.content_length() {
+        headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+    }
+    Ok((
+        StatusCode::OK,
+        headers,
+        Body::from_stream(resp.bytes_stream()),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    delete,
+    tags = ["Reservation"],
+    description = "Remove an attachment from a reservation and delete its file from the image service.",
+    path = "/{id}/attachments/{attachment_id}",
+    params(
+        ("id" = String, Path, description = "Reservation ID"),
+        ("attachment_id" = String, Path, description = "Attachment ID")
+    ),
+    responses(
+        (status = 204, description = "Attachment removed"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation or attachment not found", body = ErrorResponse),
+        (status = 500, description = "Failed to remove attachment", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn delete_attachment(
+    session: AuthSession,
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path((id, attachment_id)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    visible_reservation(&state.db, &session.user.unwrap(), &id).await?;
+    let attachment = find_attachment(&state.db, &id, &attachment_id).await?;
+
+    if attachment.clone().delete(&state.db).await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to remove attachment".to_string(),
+        ));
+    }
+    delete_file(&state, &attachment.file_id).await;
+    audit
+        .log(
+            &state.db,
+            Target::Reservation,
+            &id,
+            "attachment_remove",
+            Some(snapshot(&attachment)),
+            None,
+        )
+        .await;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+pub fn reservation_attachment_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/{id}/attachments",
+            get(list_attachments)
+                .merge(post(upload_attachment).layer(middleware::from_fn(limit_photo_uploads))),
+        )
+        .route(
+            "/{id}/attachments/{attachment_id}",
+            get(download_attachment).delete(delete_attachment),
+        )
+        .route_layer(login_required!(AuthBackend))
+}
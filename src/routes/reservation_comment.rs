@@ -0,0 +1,164 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::login_required;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    domain_events::{self, DomainEvent},
+    entities::{reservation_comment, user},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    routes::reservation::visible_reservation,
+};
+
+/// A message in the thread between the requester and the reviewers of a
+/// reservation, e.g. a request to move it an hour later.
+#[derive(Serialize, ToSchema)]
+pub struct ReservationComment {
+    pub id: String,
+    pub body: String,
+    /// `None` once the author's account is deleted
+    pub author_id: Option<String>,
+    pub author_name: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl ReservationComment {
+    fn new(comment: reservation_comment::Model, author: Option<user::Model>) -> Self {
+        Self {
+            id: comment.id,
+            body: comment.body,
+            author_id: comment.author_id,
+            author_name: author.map(|author| author.name),
+            created_at: comment.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateReservationCommentBody {
+    pub body: String,
+}
+
+/// Comments on a reservation, oldest first.
+pub async fn comments_for(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+) -> Result<Vec<ReservationComment>, DbErr> {
+    Ok(reservation_comment::Entity::find()
+        .filter(reservation_comment::Column::ReservationId.eq(reservation_id))
+        .order_by_asc(reservation_comment::Column::CreatedAt)
+        .find_also_related(user::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(comment, author)| ReservationComment::new(comment, author))
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Comments on a reservation, oldest first. Only the reservation's owner and reviewers of its classroom see them.",
+    path = "/{id}/comments",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 200, description = "Comments on the reservation", body = Vec<ReservationComment>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch comments", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_reservation_comments(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    visible_reservation(&state.db, &session.user.unwrap(), &id).await?;
+    match comments_for(&state.db, &id).await {
+        Ok(comments) => Ok((StatusCode::OK, Json(comments)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch comments".to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Comment on a reservation. Reviewers are notified of the requester's comments and the requester of the reviewers', by email and over `GET /events`.",
+    path = "/{id}/comments",
+    params(("id" = String, Path, description = "Reservation ID")),
+    request_body(content = CreateReservationCommentBody, content_type = "application/json"),
+    responses(
+        (status = 201, description = "Comment added", body = ReservationComment),
+        (status = 400, description = "Comment is empty", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to add comment", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn create_reservation_comment(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<CreateReservationCommentBody>,
+) -> Result<Response, AppError> {
+    let author = session.user.unwrap();
+    let text = body.body.trim();
+    if text.is_empty() {
+        return Err(AppError::BadRequest("Comment is empty".to_string()));
+    }
+    let reservation = visible_reservation(&state.db, &author, &id).await?;
+
+    let comment = reservation_comment::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(id),
+        author_id: Set(Some(author.id.clone())),
+        body: Set(text.to_string()),
+        created_at: NotSet,
+    };
+    let comment = match comment.insert(&state.db).await {
+        Ok(comment) => comment,
+        Err(_) => return Err(AppError::Internal("Failed to add comment".to_string())),
+    };
+
+    domain_events::publish(
+        &state,
+        DomainEvent::ReservationCommented {
+            reservation,
+            comment: comment.clone(),
+        },
+    )
+    .await;
+    Ok((
+        StatusCode::CREATED,
+        Json(ReservationComment::new(comment, Some(author))),
+    )
+        .into_response())
+}
+
+pub fn reservation_comment_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/{id}/comments",
+            get(list_reservation_comments).post(create_reservation_comment),
+        )
+        .route_layer(login_required!(AuthBackend))
+}
@@ -0,0 +1,194 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    entities::{reservation, reservation_note, user},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::{Permission, review_scope},
+    routes::reservation::OUT_OF_SCOPE,
+};
+
+/// An internal note on a reservation, e.g. a summary of a phone call with
+/// the requester. Never shown to the requester.
+#[derive(Serialize, ToSchema)]
+pub struct ReservationNote {
+    pub id: String,
+    pub body: String,
+    /// `None` once the author's account is deleted
+    pub author_id: Option<String>,
+    pub author_name: Option<String>,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+}
+
+impl ReservationNote {
+    fn new(note: reservation_note::Model, author: Option<user::Model>) -> Self {
+        Self {
+            id: note.id,
+            body: note.body,
+            author_id: note.author_id,
+            author_name: author.map(|author| author.name),
+            created_at: note.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateReservationNoteBody {
+    pub body: String,
+}
+
+/// Notes on a reservation, oldest first.
+pub async fn notes_for(
+    db: &DatabaseConnection,
+    reservation_id: &str,
+) -> Result<Vec<ReservationNote>, DbErr> {
+    Ok(reservation_note::Entity::find()
+        .filter(reservation_note::Column::ReservationId.eq(reservation_id))
+        .order_by_asc(reservation_note::Column::CreatedAt)
+        .find_also_related(user::Entity)
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|(note, author)| ReservationNote::new(note, author))
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Admin: internal notes on a reservation, oldest first. Notes are only visible to admins and the classroom's assistants.",
+    path = "/admin/{id}/notes",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 200, description = "Notes on the reservation", body = Vec<ReservationNote>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden, or an assistant not granted the reservation's classroom", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch notes", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_reservation_notes(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let reservation = match reservation::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(reservation)) => reservation,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    match review_scope(&state.db, &session.user.unwrap()).await {
+        Ok(scope) if scope.allows(reservation.classroom_id.as_deref()) => {}
+        Ok(_) => return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    }
+    match notes_for(&state.db, &id).await {
+        Ok(notes) => Ok((StatusCode::OK, Json(notes)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch notes".to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Admin: add an internal note to a reservation, recorded with the current admin as author. Notes cannot be edited or deleted, so they serve as a log.",
+    path = "/admin/{id}/notes",
+    params(("id" = String, Path, description = "Reservation ID")),
+    request_body(content = CreateReservationNoteBody, content_type = "application/json"),
+    responses(
+        (status = 201, description = "Note added", body = ReservationNote),
+        (status = 400, description = "Note is empty", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden, or an assistant not granted the reservation's classroom", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 500, description = "Failed to add note", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn create_reservation_note(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<CreateReservationNoteBody>,
+) -> Result<Response, AppError> {
+    let author = session.user.unwrap();
+    let text = body.body.trim();
+    if text.is_empty() {
+        return Err(AppError::BadRequest("Note is empty".to_string()));
+    }
+
+    let reservation = match reservation::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(reservation)) => reservation,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    match review_scope(&state.db, &author).await {
+        Ok(scope) if scope.allows(reservation.classroom_id.as_deref()) => {}
+        Ok(_) => return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    }
+
+    let note = reservation_note::ActiveModel {
+        id: Set(nanoid!()),
+        reservation_id: Set(id),
+        author_id: Set(Some(author.id.clone())),
+        body: Set(text.to_string()),
+        created_at: NotSet,
+    };
+    match note.insert(&state.db).await {
+        Ok(note) => Ok((
+            StatusCode::CREATED,
+            Json(ReservationNote::new(note, Some(author))),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal("Failed to add note".to_string())),
+    }
+}
+
+pub fn reservation_note_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/{id}/notes",
+            get(list_reservation_notes).post(create_reservation_note),
+        )
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ReviewReservations
+        ))
+}
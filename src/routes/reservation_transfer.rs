@@ -0,0 +1,204 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use axum_login::permission_required;
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    email_queue::{Priority, queue_email_to_user},
+    email_templates::reservations_transferred,
+    entities::{classroom, reservation, user},
+    error::{AppError, ErrorResponse},
+    login_system::AuthBackend,
+    permissions::Permission,
+    reservation_transfer::{select, transfer, transferable},
+};
+
+#[derive(Deserialize, ToSchema)]
+pub struct TransferReservationsBody {
+    /// User who takes over the reservations
+    pub to_user_id: String,
+    /// Reservations to transfer; all upcoming approved or pending ones when
+    /// left out
+    #[serde(default)]
+    pub reservation_ids: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TransferReservationsResponse {
+    pub transferred: Vec<reservation::Model>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Admin"],
+    description = "Upcoming approved or pending reservations of a user that can be transferred to someone else, e.g. before the account is removed.",
+    path = "/users/{user_id}/transferable-reservations",
+    params(("user_id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Transferable reservations, soonest first", body = Vec<reservation::Model>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_transferable_reservations(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+) -> Result<Response, AppError> {
+    match user::Entity::find_by_id(&user_id).one(&state.db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    }
+    match transferable(&state.db, &user_id).await {
+        Ok(reservations) => Ok((StatusCode::OK, Json(reservations)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservations".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Admin"],
+    description = "Reassign a user's upcoming reservations to another user instead of cancelling them, e.g. when a club president graduates. The reservations keep their status; both users are notified.",
+    path = "/users/{user_id}/transfer-reservations",
+    params(("user_id" = String, Path, description = "Current holder of the reservations")),
+    request_body(content = TransferReservationsBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Reservations transferred", body = TransferReservationsResponse),
+        (status = 400, description = "Transfer to the same user, or a reservation that is not an upcoming one of this user", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Failed to transfer reservations", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn transfer_reservations(
+    State(state): State<AppState>,
+    Path(user_id): Path<String>,
+    Json(body): Json<TransferReservationsBody>,
+) -> Result<Response, AppError> {
+    if body.to_user_id == user_id {
+        return Err(AppError::BadRequest(
+            "Cannot transfer reservations to the same user".to_string(),
+        ));
+    }
+    let (from, to) = match (
+        user::Entity::find_by_id(&user_id).one(&state.db).await,
+        user::Entity::find_by_id(&body.to_user_id)
+            .one(&state.db)
+            .await,
+    ) {
+        (Ok(Some(from)), Ok(Some(to))) => (from, to),
+        (Ok(_), Ok(_)) => return Err(AppError::NotFound("User not found".to_string())),
+        _ => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    };
+
+    let candidates = match transferable(&state.db, &from.id).await {
+        Ok(reservations) => reservations,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
+    let selected = match select(candidates, body.reservation_ids.as_deref()) {
+        Ok(selected) => selected,
+        Err(id) => {
+            return Err(AppError::BadRequest(format!(
+                "Reservation {} is not an upcoming reservation of this user",
+                id
+            )));
+        }
+    };
+    if selected.is_empty() {
+        return Ok((
+            StatusCode::OK,
+            Json(TransferReservationsResponse {
+                transferred: Vec::new(),
+            }),
+        )
+            .into_response());
+    }
+
+    let transferred = match transfer(&state.db, selected, &to.id).await {
+        Ok(transferred) => transferred,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to transfer reservations".to_string(),
+            ));
+        }
+    };
+
+    let mut with_classrooms = Vec::new();
+    for reservation in &transferred {
+        let classroom = match &reservation.classroom_id {
+            Some(id) => classroom::Entity::find_by_id(id)
+                .one(&state.db)
+                .await
+                .ok()
+                .flatten(),
+            None => None,
+        };
+        with_classrooms.push((reservation.clone(), classroom));
+    }
+    for (recipient, counterpart, received) in [(&to, &from, true), (&from, &to, false)] {
+        let email = reservations_transferred(
+            &state.config.email_templates,
+            &with_classrooms,
+            counterpart,
+            received,
+            state.config.email_templates.locale_for(recipient),
+        );
+        if let Err(e) = queue_email_to_user(
+            &state,
+            recipient,
+            email.subject,
+            email.body,
+            Priority::Normal,
+        )
+        .await
+        {
+            warn!(
+                "Failed to notify user {} of reservation transfer: {}",
+                recipient.id, e
+            );
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(TransferReservationsResponse { transferred }),
+    )
+        .into_response())
+}
+
+pub fn reservation_transfer_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/{user_id}/transferable-reservations",
+            get(list_transferable_reservations),
+        )
+        .route(
+            "/users/{user_id}/transfer-reservations",
+            post(transfer_reservations),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers))
+}
@@ -0,0 +1,117 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_login::permission_required;
+use sea_orm::EntityTrait;
+use tracing::warn;
+
+use crate::{
+    AppState,
+    entities::{reservation, user},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::{Permission, review_scope},
+    review_lock::{self, ClaimError, ReviewLock},
+    routes::reservation::OUT_OF_SCOPE,
+};
+
+/// Finds the reservation and checks the reviewer may review it.
+async fn reviewable(state: &AppState, reviewer: &user::Model, id: &str) -> Result<(), AppError> {
+    let failed = || AppError::Internal("Failed to claim reservation".to_string());
+    let reservation = reservation::Entity::find_by_id(id)
+        .one(&state.db)
+        .await
+        .map_err(|_| failed())?
+        .ok_or_else(|| AppError::NotFound("Reservation not found".to_string()))?;
+    let scope = review_scope(&state.db, reviewer)
+        .await
+        .map_err(|_| failed())?;
+    if !scope.allows(reservation.classroom_id.as_deref()) {
+        return Err(AppError::Forbidden(OUT_OF_SCOPE.to_string()));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Reviewer: claims a reservation while reviewing it, so other reviewers see it as being reviewed and cannot review it meanwhile. The claim runs out unless renewed; call this again as a heartbeat before `expires_at`. Reviewing the reservation releases it.",
+    path = "/admin/{id}/claim",
+    params(("id" = String, Path)),
+    responses(
+        (status = 200, description = "Claimed or renewed", body = ReviewLock),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 409, description = "Another reviewer holds the claim, given in `details`", body = ErrorResponse),
+        (status = 503, description = "Claims are unavailable while Redis is down", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn claim_reservation(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let reviewer = session.user.unwrap();
+    reviewable(&state, &reviewer, &id).await?;
+    let mut redis = state.redis.clone();
+    match review_lock::claim(&mut redis, state.config.review_lock.ttl, &id, &reviewer).await {
+        Ok(lock) => Ok((StatusCode::OK, Json(lock)).into_response()),
+        Err(ClaimError::Held(held)) => Err(held.into()),
+        Err(ClaimError::Redis(e)) => {
+            warn!("Failed to claim reservation {}: {}", id, e);
+            Err(AppError::ServiceUnavailable(
+                "Claims are unavailable right now".to_string(),
+            ))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    tags = ["Reservation"],
+    description = "Reviewer: releases the caller's claim on a reservation, e.g. when leaving it without a decision. Claims of other reviewers are left alone.",
+    path = "/admin/{id}/claim",
+    params(("id" = String, Path)),
+    responses(
+        (status = 204, description = "Released"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Claims are unavailable while Redis is down", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn release_reservation(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let reviewer = session.user.unwrap();
+    let mut redis = state.redis.clone();
+    match review_lock::release(&mut redis, &id, &reviewer.id).await {
+        Ok(()) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Err(e) => {
+            warn!("Failed to release reservation {}: {}", id, e);
+            Err(AppError::ServiceUnavailable(
+                "Claims are unavailable right now".to_string(),
+            ))
+        }
+    }
+}
+
+pub fn review_lock_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/{id}/claim",
+            post(claim_reservation).delete(release_reservation),
+        )
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ReviewReservations
+        ))
+}
@@ -0,0 +1,110 @@
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+};
+use axum_login::login_required;
+use chrono::Utc;
+use sea_orm::{EntityTrait, prelude::DateTimeWithTimeZone};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    domain_events::{self, DomainEvent},
+    entities::reservation,
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    review_nudge::{NudgeRejection, check, cooldown, last_nudge},
+};
+
+#[derive(Serialize, ToSchema)]
+pub struct NudgeResponse {
+    /// When the next reminder for this reservation can be sent
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub next_nudge_at: DateTimeWithTimeZone,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["Reservation"],
+    description = "Remind the reviewers of a reservation that still waits for review, re-sending the review notification to admins and the classroom's assistants. Only the requester can send a reminder, at most once every 24 hours.",
+    path = "/admin/{id}/nudge",
+    params(("id" = String, Path, description = "Reservation ID")),
+    responses(
+        (status = 200, description = "Reviewers reminded", body = NudgeResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Not the requester of this reservation", body = ErrorResponse),
+        (status = 404, description = "Reservation not found", body = ErrorResponse),
+        (status = 409, description = "Reservation is no longer pending", body = ErrorResponse),
+        (status = 429, description = "A reminder was sent less than 24 hours ago", body = ErrorResponse),
+        (status = 500, description = "Failed to send reminder", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn nudge_reviewers(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+    let reservation = match reservation::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return Err(AppError::NotFound("Reservation not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservation".to_string(),
+            ));
+        }
+    };
+    let last = match last_nudge(&state.db, &reservation.id).await {
+        Ok(last) => last,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to send reminder".to_string()));
+        }
+    };
+
+    let now = Utc::now().with_timezone(&campus_offset());
+    match check(&reservation, &user.id, last, now) {
+        Ok(()) => {}
+        Err(NudgeRejection::NotRequester) => {
+            return Err(AppError::Forbidden(
+                "Only the requester can send a reminder".to_string(),
+            ));
+        }
+        Err(NudgeRejection::NotPending) => {
+            return Err(AppError::Conflict(
+                "Only pending reservations can be nudged".to_string(),
+            ));
+        }
+        Err(NudgeRejection::TooSoon(next)) => {
+            return Err(AppError::TooManyRequests {
+                message: format!(
+                    "A reminder was already sent; try again after {}",
+                    next.with_timezone(&campus_offset())
+                        .format("%Y-%m-%d %H:%M (GMT+8)")
+                ),
+                retry_after: None,
+            });
+        }
+    }
+
+    domain_events::publish(&state, DomainEvent::ReservationNudged { reservation }).await;
+    Ok((
+        StatusCode::OK,
+        Json(NudgeResponse {
+            next_nudge_at: now + cooldown(),
+        }),
+    )
+        .into_response())
+}
+
+pub fn review_nudge_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/{id}/nudge", post(nudge_reviewers))
+        .route_layer(login_required!(AuthBackend))
+}
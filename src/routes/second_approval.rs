@@ -0,0 +1,59 @@
+use axum::{
+    Json, Router,
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+
+use crate::{
+    AppState,
+    double_approval::{AwaitingSecondApproval, second_approval_queue},
+    error::{AppError, ErrorResponse},
+    login_system::{AuthBackend, AuthSession},
+    permissions::{Permission, review_scope},
+};
+
+#[utoipa::path(
+    get,
+    tags = ["Reservation"],
+    description = "Reviewer: pending reservations in classrooms requiring double approval that another reviewer approved once and that wait for the caller's second approval, earliest start first. Approve or reject them with PUT /reservation/{id}/review. Assistants only see reservations for their classrooms.",
+    path = "/admin/second-approval",
+    responses(
+        (status = 200, description = "Reservations awaiting a second approval", body = Vec<AwaitingSecondApproval>),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Failed to fetch reservations", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_awaiting_second_approval(
+    session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let reviewer = session.user.unwrap();
+    let scope = match review_scope(&state.db, &reviewer).await {
+        Ok(scope) => scope,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to fetch reservations".to_string(),
+            ));
+        }
+    };
+    match second_approval_queue(&state.db, &scope, &reviewer.id).await {
+        Ok(queue) => Ok((StatusCode::OK, Json(queue)).into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch reservations".to_string(),
+        )),
+    }
+}
+
+pub fn second_approval_router() -> Router<AppState> {
+    Router::new()
+        .route("/admin/second-approval", get(list_awaiting_second_approval))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ReviewReservations
+        ))
+}
@@ -0,0 +1,353 @@
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use axum_login::permission_required;
+use chrono::{Datelike, Duration, Utc};
+use redis::AsyncCommands;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde::Deserialize;
+use tracing::warn;
+use utoipa::{IntoParams, OpenApi};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    concurrency::limit_reports,
+    constants::get_redis_set_options,
+    door_events::{AttendanceSummary, summarize_attendance, usage_by_reservation},
+    entities::{
+        reservation,
+        sea_orm_active_enums::{ReservationStatus, Role},
+        user,
+    },
+    error::{AppError, ErrorResponse},
+    export::parse_filter,
+    login_system::AuthBackend,
+    permissions::Permission,
+    public_stats::{
+        PublicStats, add_months, load_public_stats, month_start, months_between, parse_month,
+    },
+    workload::{AdminWorkload, summarize},
+};
+
+/// Longest period a single request may cover.
+const MAX_STATS_MONTHS: i32 = 36;
+
+/// Window `/admin-workload` covers when `from` is omitted.
+const DEFAULT_WORKLOAD_DAYS: i64 = 30;
+
+/// Window `/attendance` covers when `from` is omitted.
+const DEFAULT_ATTENDANCE_DAYS: i64 = 30;
+
+#[derive(Deserialize, IntoParams)]
+pub struct PublicStatsQuery {
+    /// First month to include, `YYYY-MM`; defaults to 11 months before `to`
+    pub from: Option<String>,
+    /// Last month to include, `YYYY-MM`; defaults to the current month
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Stats"],
+    description = "Anonymized utilization statistics for publication: bookings per building per month and the busiest hours of the day. Any group involving fewer distinct users than the configured minimum is suppressed, and no classroom- or user-level data is returned.",
+    path = "/public",
+    params(PublicStatsQuery),
+    responses(
+        (status = 200, description = "Statistics", body = PublicStats),
+        (status = 400, description = "Invalid month range", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse),
+        (status = 500, description = "Failed to compute statistics", body = ErrorResponse)
+    )
+)]
+pub async fn get_public_stats(
+    State(state): State<AppState>,
+    Query(query): Query<PublicStatsQuery>,
+) -> Result<Response, AppError> {
+    let to_month = match query.to.as_deref() {
+        Some(s) => match parse_month(s) {
+            Some(dt) => dt,
+            None => {
+                return Err(AppError::BadRequest(
+                    "Invalid to, expected YYYY-MM".to_string(),
+                ));
+            }
+        },
+        None => {
+            let now = Utc::now().with_timezone(&campus_offset());
+            month_start(now.year(), now.month()).expect("current month exists")
+        }
+    };
+    let from_month = match query.from.as_deref() {
+        Some(s) => match parse_month(s) {
+            Some(dt) => dt,
+            None => {
+                return Err(AppError::BadRequest(
+                    "Invalid from, expected YYYY-MM".to_string(),
+                ));
+            }
+        },
+        None => add_months(to_month, -11),
+    };
+
+    let span = months_between(from_month, to_month) + 1;
+    if span < 1 {
+        return Err(AppError::BadRequest(
+            "from must not be after to".to_string(),
+        ));
+    }
+    if span > MAX_STATS_MONTHS {
+        return Err(AppError::BadRequest(format!(
+            "At most {} months can be requested",
+            MAX_STATS_MONTHS
+        )));
+    }
+
+    let min_group_size = state.config.public_stats.min_group_size;
+    let cache_key = format!(
+        "public_stats:{}:{}:{}",
+        from_month.format("%Y-%m"),
+        to_month.format("%Y-%m"),
+        min_group_size
+    );
+
+    let mut redis = state.redis.clone();
+    if let Ok(Some(cached)) = redis.get::<_, Option<String>>(&cache_key).await
+        && let Ok(stats) = serde_json::from_str::<PublicStats>(&cached)
+    {
+        return Ok((StatusCode::OK, Json(stats)).into_response());
+    }
+
+    let stats = match load_public_stats(
+        &state.db,
+        from_month,
+        add_months(to_month, 1),
+        min_group_size,
+    )
+    .await
+    {
+        Ok(stats) => stats,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to compute statistics".to_string(),
+            ));
+        }
+    };
+
+    let result: Result<(), redis::RedisError> = redis
+        .set_options(
+            &cache_key,
+            serde_json::to_string(&stats).unwrap(),
+            get_redis_set_options(),
+        )
+        .await;
+    if let Err(e) = result {
+        warn!("Failed to cache public stats in Redis: {}", e);
+    }
+
+    Ok((StatusCode::OK, Json(stats)).into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AdminWorkloadQuery {
+    /// Reviewed at or after this time; defaults to 30 days before `to`
+    pub from: Option<String>,
+    /// Reviewed before this time; defaults to now
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Stats"],
+    description = "Review workload per admin for rebalancing: reservations approved or rejected in `[from, to)` and the median time from submission to review, plus the current pending queue. Shares the report concurrency limit.",
+    path = "/admin-workload",
+    params(AdminWorkloadQuery),
+    responses(
+        (status = 200, description = "Admin workload", body = AdminWorkload),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse),
+        (status = 500, description = "Failed to compute statistics", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_admin_workload(
+    State(state): State<AppState>,
+    Query(query): Query<AdminWorkloadQuery>,
+) -> Result<Response, AppError> {
+    let to = match parse_filter(&query.to, "to") {
+        Ok(to) => to.unwrap_or_else(|| Utc::now().with_timezone(&campus_offset())),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let from = match parse_filter(&query.from, "from") {
+        Ok(from) => from.unwrap_or(to - Duration::days(DEFAULT_WORKLOAD_DAYS)),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be < 'to'".to_string()));
+    }
+
+    let reviewed = reservation::Entity::find()
+        .filter(reservation::Column::ReviewedAt.gte(from))
+        .filter(reservation::Column::ReviewedAt.lt(to))
+        .all(&state.db)
+        .await;
+    let pending = reservation::Entity::find()
+        .filter(reservation::Column::Status.eq(ReservationStatus::Pending))
+        .all(&state.db)
+        .await;
+    let admins = user::Entity::find()
+        .select_only()
+        .column(user::Column::Id)
+        .column(user::Column::Name)
+        // Assistants review reservations too
+        .filter(user::Column::Role.is_in([Role::Admin, Role::Assistant]))
+        .into_tuple::<(String, String)>()
+        .all(&state.db)
+        .await;
+    let (reviewed, pending, admins) = match (reviewed, pending, admins) {
+        (Ok(reviewed), Ok(pending), Ok(admins)) => (reviewed, pending, admins),
+        _ => {
+            return Err(AppError::Internal(
+                "Failed to compute statistics".to_string(),
+            ));
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(summarize(
+            &reviewed,
+            &pending,
+            &admins.into_iter().collect(),
+            from,
+            to,
+        )),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct AttendanceQuery {
+    /// Reservations ending at or after this time; defaults to 30 days before
+    /// `to`
+    pub from: Option<String>,
+    /// Reservations ending before this time; defaults to now, and later
+    /// times are capped to now
+    pub to: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Stats"],
+    description = "How approved reservations that ended in `[from, to)` were attended, combining manual check-in with door-open events from the access control system. Reservations nobody checked into but whose door was opened are counted separately, so they are not mistaken for no-shows. Shares the report concurrency limit.",
+    path = "/attendance",
+    params(AttendanceQuery),
+    responses(
+        (status = 200, description = "Attendance summary", body = AttendanceSummary),
+        (status = 400, description = "Invalid window", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse),
+        (status = 500, description = "Failed to compute statistics", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_attendance(
+    State(state): State<AppState>,
+    Query(query): Query<AttendanceQuery>,
+) -> Result<Response, AppError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let to = match parse_filter(&query.to, "to") {
+        Ok(to) => to.map_or(now, |to| to.min(now)),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let from = match parse_filter(&query.from, "from") {
+        Ok(from) => from.unwrap_or(to - Duration::days(DEFAULT_ATTENDANCE_DAYS)),
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    if from >= to {
+        return Err(AppError::BadRequest("'from' must be < 'to'".to_string()));
+    }
+
+    let reservations = match reservation::Entity::find()
+        .filter(reservation::Column::EndTime.gte(from))
+        .filter(reservation::Column::EndTime.lt(to))
+        .filter(reservation::Column::Status.is_in([
+            ReservationStatus::Approved,
+            ReservationStatus::Completed,
+            ReservationStatus::NoShow,
+        ]))
+        .all(&state.db)
+        .await
+    {
+        Ok(reservations) => reservations,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to compute statistics".to_string(),
+            ));
+        }
+    };
+    let ids = reservations.iter().map(|r| r.id.clone()).collect();
+    match usage_by_reservation(&state.db, ids).await {
+        Ok(usage) => Ok((
+            StatusCode::OK,
+            Json(summarize_attendance(&reservations, &usage)),
+        )
+            .into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to compute statistics".to_string(),
+        )),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Stats", description = "Usage and review workload statistics")
+    ),
+    paths(
+        get_public_stats,
+        get_admin_workload,
+        get_attendance,
+    ),
+    components(schemas(
+        crate::public_stats::PublicStats,
+        crate::public_stats::BuildingMonthStat,
+        crate::public_stats::HourStat,
+        crate::workload::AdminWorkload,
+        crate::workload::ReviewerWorkload,
+        crate::workload::ReviewQueue,
+        crate::door_events::AttendanceSummary,
+    ))
+)]
+pub struct StatsApi;
+
+pub fn stats_router() -> Router<AppState> {
+    let admin_only_route = Router::new()
+        .route(
+            "/admin-workload",
+            get(get_admin_workload).layer(middleware::from_fn(limit_reports)),
+        )
+        .route(
+            "/attendance",
+            get(get_attendance).layer(middleware::from_fn(limit_reports)),
+        )
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ViewStatistics
+        ));
+
+    Router::new()
+        .route(
+            "/public",
+            get(get_public_stats).layer(middleware::from_fn(limit_reports)),
+        )
+        .merge(admin_only_route)
+}
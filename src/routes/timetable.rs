@@ -0,0 +1,374 @@
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+};
+use axum_login::permission_required;
+use chrono::{NaiveDate, NaiveTime, Utc};
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState,
+    availability::campus_offset,
+    closure_impact::{AffectedReservation, ClosureAction, apply_action, finish},
+    entities::{classroom, course_session, reservation},
+    error::{AppError, ErrorResponse},
+    login_system::AuthBackend,
+    permissions::Permission,
+    timetable::{Collision, find_collisions},
+    utils::parse_dt,
+};
+
+const DEFAULT_COLLISION_REASON: &str = "The classroom is needed for a course on the timetable";
+
+// ===============================
+//   Request / Response bodies
+// ===============================
+#[derive(Deserialize, ToSchema)]
+pub struct CourseSessionBody {
+    pub course_name: String,
+    /// 0 = Monday ... 6 = Sunday
+    pub weekday: i16,
+    /// "HH:MM" or "HH:MM:SS" in campus time
+    pub start_time: String,
+    /// "HH:MM" or "HH:MM:SS" in campus time
+    pub end_time: String,
+    /// First day of the term, "YYYY-MM-DD"
+    pub term_start: String,
+    /// Last day of the term (inclusive), "YYYY-MM-DD"
+    pub term_end: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportTimetableBody {
+    pub sessions: Vec<CourseSessionBody>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct TimetableImportResponse {
+    pub sessions: Vec<course_session::Model>,
+    /// Approved reservations that now overlap a course; resolve them through
+    /// `/classroom/timetable/collisions/resolve`
+    pub collisions: Vec<Collision>,
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct CollisionQuery {
+    /// Only this classroom
+    pub classroom_id: Option<String>,
+    /// Only reservations ending after this time; defaults to now
+    pub from: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ResolveCollisionsBody {
+    /// `cancel` or `rebook` the colliding reservations, or only `flag` them
+    pub action: ClosureAction,
+    /// Reason given to the requesters
+    pub reason: Option<String>,
+    /// Only resolve these reservations; defaults to every current collision
+    pub reservation_ids: Option<Vec<String>>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ResolveCollisionsResponse {
+    pub affected_reservations: Vec<AffectedReservation>,
+}
+
+fn parse_time(s: &str) -> Option<NaiveTime> {
+    let raw = s.trim();
+    NaiveTime::parse_from_str(raw, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(raw, "%H:%M"))
+        .ok()
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok()
+}
+
+// ===============================
+//   Get Timetable
+// ===============================
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "List the course sessions on a classroom's timetable",
+    path = "/{id}/timetable",
+    params(("id" = String, Path, description = "Classroom ID")),
+    responses(
+        (status = 200, description = "Course sessions", body = Vec<course_session::Model>),
+        (status = 500, description = "Failed to fetch timetable", body = ErrorResponse)
+    )
+)]
+pub async fn get_timetable(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    match course_session::Entity::find()
+        .filter(course_session::Column::ClassroomId.eq(&id))
+        .order_by_asc(course_session::Column::Weekday)
+        .order_by_asc(course_session::Column::StartTime)
+        .all(&state.db)
+        .await
+    {
+        Ok(sessions) => Ok((StatusCode::OK, Json(sessions)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to fetch timetable".to_string())),
+    }
+}
+
+// ===============================
+//   Import Timetable (Admin)
+// ===============================
+#[utoipa::path(
+    put,
+    tags = ["Classroom"],
+    description = "Replace the course timetable of a classroom. New bookings can no longer overlap a course; approved reservations that already do are returned as collisions instead of being changed, so staff can decide how to resolve them.",
+    path = "/{id}/timetable",
+    request_body(content = ImportTimetableBody, content_type = "application/json"),
+    params(("id" = String, Path, description = "Classroom ID")),
+    responses(
+        (status = 200, description = "Timetable imported", body = TimetableImportResponse),
+        (status = 400, description = "Invalid course session", body = ErrorResponse),
+        (status = 404, description = "Classroom not found", body = ErrorResponse),
+        (status = 500, description = "Failed to import timetable", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn import_timetable(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<ImportTimetableBody>,
+) -> Result<Response, AppError> {
+    match classroom::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(_)) => {}
+        Ok(None) => return Err(AppError::NotFound("Classroom not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch classroom".to_string()));
+        }
+    }
+
+    let mut new_sessions = Vec::with_capacity(body.sessions.len());
+    for entry in body.sessions {
+        if entry.course_name.trim().is_empty() {
+            return Err(AppError::BadRequest(
+                "'course_name' must not be empty".to_string(),
+            ));
+        }
+        if !(0..=6).contains(&entry.weekday) {
+            return Err(AppError::BadRequest(
+                "Invalid 'weekday', expected 0-6".to_string(),
+            ));
+        }
+        let Some(start_time) = parse_time(&entry.start_time) else {
+            return Err(AppError::BadRequest("Invalid 'start_time'".to_string()));
+        };
+        let Some(end_time) = parse_time(&entry.end_time) else {
+            return Err(AppError::BadRequest("Invalid 'end_time'".to_string()));
+        };
+        if start_time >= end_time {
+            return Err(AppError::BadRequest(
+                "'start_time' must be < 'end_time'".to_string(),
+            ));
+        }
+        let Some(term_start) = parse_date(&entry.term_start) else {
+            return Err(AppError::BadRequest("Invalid 'term_start'".to_string()));
+        };
+        let Some(term_end) = parse_date(&entry.term_end) else {
+            return Err(AppError::BadRequest("Invalid 'term_end'".to_string()));
+        };
+        if term_start > term_end {
+            return Err(AppError::BadRequest(
+                "'term_start' must be <= 'term_end'".to_string(),
+            ));
+        }
+        new_sessions.push(course_session::ActiveModel {
+            id: Set(nanoid!()),
+            classroom_id: Set(id.clone()),
+            course_name: Set(entry.course_name.trim().to_string()),
+            weekday: Set(entry.weekday),
+            start_time: Set(start_time),
+            end_time: Set(end_time),
+            term_start: Set(term_start),
+            term_end: Set(term_end),
+            created_at: NotSet,
+        });
+    }
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to import timetable".to_string()));
+        }
+    };
+
+    if course_session::Entity::delete_many()
+        .filter(course_session::Column::ClassroomId.eq(&id))
+        .exec(&txn)
+        .await
+        .is_err()
+    {
+        return Err(AppError::Internal("Failed to import timetable".to_string()));
+    }
+
+    let mut sessions = Vec::with_capacity(new_sessions.len());
+    for session in new_sessions {
+        match session.insert(&txn).await {
+            Ok(model) => sessions.push(model),
+            Err(_) => {
+                return Err(AppError::Internal("Failed to import timetable".to_string()));
+            }
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal("Failed to import timetable".to_string()));
+    }
+
+    let now = Utc::now().with_timezone(&campus_offset());
+    let collisions = match find_collisions(&state.db, Some(vec![id]), now).await {
+        Ok(collisions) => collisions,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to check collisions".to_string()));
+        }
+    };
+
+    Ok((
+        StatusCode::OK,
+        Json(TimetableImportResponse {
+            sessions,
+            collisions,
+        }),
+    )
+        .into_response())
+}
+
+// ===============================
+//   Collision Report (Admin)
+// ===============================
+#[utoipa::path(
+    get,
+    tags = ["Classroom"],
+    description = "Reconciliation report of approved reservations that overlap a course on the timetable, soonest first",
+    path = "/timetable/collisions",
+    params(CollisionQuery),
+    responses(
+        (status = 200, description = "Colliding reservations", body = Vec<Collision>),
+        (status = 400, description = "Invalid 'from'", body = ErrorResponse),
+        (status = 500, description = "Failed to check collisions", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_collisions(
+    State(state): State<AppState>,
+    Query(query): Query<CollisionQuery>,
+) -> Result<Response, AppError> {
+    let from = match &query.from {
+        Some(from) => match parse_dt(from) {
+            Ok(from) => from,
+            Err(_) => return Err(AppError::BadRequest("Invalid 'from'".to_string())),
+        },
+        None => Utc::now().with_timezone(&campus_offset()),
+    };
+
+    match find_collisions(&state.db, query.classroom_id.map(|id| vec![id]), from).await {
+        Ok(collisions) => Ok((StatusCode::OK, Json(collisions)).into_response()),
+        Err(_) => Err(AppError::Internal("Failed to check collisions".to_string())),
+    }
+}
+
+// ===============================
+//   Resolve Collisions (Admin)
+// ===============================
+#[utoipa::path(
+    post,
+    tags = ["Classroom"],
+    description = "Cancel, rebook or flag reservations that collide with the timetable and notify their owners. Collisions are recomputed first, so reservations that no longer collide are left alone.",
+    path = "/timetable/collisions/resolve",
+    request_body(content = ResolveCollisionsBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Collisions resolved", body = ResolveCollisionsResponse),
+        (status = 500, description = "Failed to resolve collisions", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn resolve_collisions(
+    State(state): State<AppState>,
+    Json(body): Json<ResolveCollisionsBody>,
+) -> Result<Response, AppError> {
+    let now = Utc::now().with_timezone(&campus_offset());
+    let collisions = match find_collisions(&state.db, None, now).await {
+        Ok(collisions) => collisions,
+        Err(_) => {
+            return Err(AppError::Internal("Failed to check collisions".to_string()));
+        }
+    };
+    let reservations: Vec<reservation::Model> = collisions
+        .into_iter()
+        .map(|c| c.reservation)
+        .filter(|r| {
+            body.reservation_ids
+                .as_ref()
+                .is_none_or(|ids| ids.contains(&r.id))
+        })
+        .collect();
+
+    let reason = body
+        .reason
+        .filter(|reason| !reason.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_COLLISION_REASON.to_string());
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+    let updated = match apply_action(&txn, reservations, body.action, &reason).await {
+        Ok(updated) => updated,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to resolve collisions".to_string(),
+            ));
+        }
+    };
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to resolve collisions".to_string(),
+        ));
+    }
+
+    let affected_reservations = finish(&state, updated, body.action, reason).await;
+    Ok((
+        StatusCode::OK,
+        Json(ResolveCollisionsResponse {
+            affected_reservations,
+        }),
+    )
+        .into_response())
+}
+
+pub fn timetable_router() -> Router<AppState> {
+    let admin_only_route = Router::new()
+        .route("/{id}/timetable", put(import_timetable))
+        .route("/timetable/collisions", get(list_collisions))
+        .route("/timetable/collisions/resolve", post(resolve_collisions))
+        .route_layer(permission_required!(
+            AuthBackend,
+            Permission::ManageClassrooms
+        ));
+
+    Router::new()
+        .route("/{id}/timetable", get(get_timetable))
+        .merge(admin_only_route)
+}
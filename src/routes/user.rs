@@ -1,29 +1,51 @@
+use std::net::SocketAddr;
+
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
-    routing::{get, post, put},
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post, put},
 };
-use axum_login::login_required;
-use redis::AsyncCommands;
+use axum_login::{login_required, permission_required};
+use chrono::Utc;
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    EntityTrait,
+    ColumnTrait, DbErr, EntityTrait, Order, QueryFilter, TransactionTrait,
     prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait, Func},
 };
 use serde::{Deserialize, Serialize};
 use tracing::warn;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, OpenApi, ToSchema};
 
 use crate::{
     AppState,
-    argon_hasher::{hash, verify},
-    constants::{REDIS_EXPIRY, get_redis_set_options},
-    entities::{self, sea_orm_active_enums::Role, user},
+    account_deletion::{DeletionSummary, delete_account, deletion_blocked},
+    audit::{AuditContext, Target, user_snapshot},
+    cache::CacheKey,
+    domain_events::{self, DomainEvent},
+    email_change::{self, ConfirmOutcome},
+    email_client::{send_email, send_email_to_user},
+    email_templates::{self, Locale},
+    entities::{
+        announcement_mute,
+        sea_orm_active_enums::{AnnouncementCategory, Role},
+        user,
+    },
+    error::{AppError, ErrorResponse},
+    key_cabinet, login_guard,
     login_system::{AuthBackend, AuthSession, Credentials},
-    utils::check_student_id,
+    pagination::{PageParams, PagedResponse, Pagination, SortSpec, fetch},
+    permissions::{Permission, has_permission},
+    pickup,
+    rate_limit::{self, Endpoint},
+    redis_breaker::RedisConnection,
+    routes::{invite::invite_router, user_export::user_export_router},
+    sessions, soft_launch, student_id,
+    user_conflicts::{Candidate, find_conflict, from_db_error},
+    versioning,
 };
 
 use nanoid::nanoid;
@@ -35,7 +57,10 @@ pub struct RegisterBody {
     password: String,
     phone_number: String,
     name: String,
-    student_id: String,
+    /// Checked against the configured student ID format; may be left out when
+    /// the `user` role is exempt
+    #[serde(default)]
+    student_id: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, ToSchema)]
@@ -52,11 +77,40 @@ pub struct UserResponse {
     pub email: String,
     pub phone_number: String,
     pub role: Role,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub created_at: DateTimeWithTimeZone,
-    #[schema(value_type = String)]
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
     pub updated_at: DateTimeWithTimeZone,
     pub name: String,
+    pub locale: Option<String>,
+    /// Set when mail to this address bounced or was reported as spam; no
+    /// emails are sent until the address is changed
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub email_undeliverable_at: Option<DateTimeWithTimeZone>,
+    pub email_undeliverable_reason: Option<String>,
+    /// Set for staff onboarded through an invitation
+    pub department: Option<String>,
+    /// Only shown to the user themselves and to staff who manage users
+    pub student_id: Option<String>,
+    /// Send back with a profile update to have it refused if the profile was
+    /// saved elsewhere in between
+    pub version: i32,
+}
+
+impl UserResponse {
+    /// The user as seen by `viewer`, who may be logged out.
+    pub fn for_viewer(user: user::Model, viewer: Option<&user::Model>) -> Self {
+        let sees_student_id = viewer
+            .is_some_and(|v| v.id == user.id || has_permission(&v.role, Permission::ManageUsers));
+        let mut response = Self::from(user);
+        if !sees_student_id {
+            response.student_id = None;
+        }
+        response
+    }
 }
 
 // ===============================
@@ -69,6 +123,26 @@ pub struct UpdateProfileBody {
     pub email: Option<String>,
     pub phone_number: Option<String>,
     pub name: Option<String>,
+    /// Preferred language for emails: "en" or "zh-TW"
+    pub locale: Option<String>,
+    /// `version` of the profile this update was made on; refused with 409
+    /// when it was saved elsewhere since, e.g. from another device
+    pub version: Option<i32>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct UpdateProfileResponse {
+    #[serde(flatten)]
+    pub user: UserResponse,
+    /// New address waiting for confirmation via `/confirm-email-change`; the
+    /// email in the profile changes only once it is confirmed
+    pub pending_email: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ConfirmEmailChangeBody {
+    /// Code sent to the new address
+    pub code: String,
 }
 
 impl From<user::Model> for UserResponse {
@@ -82,6 +156,12 @@ impl From<user::Model> for UserResponse {
             created_at: user.created_at,
             updated_at: user.updated_at,
             name: user.name,
+            locale: user.locale,
+            email_undeliverable_at: user.email_undeliverable_at,
+            email_undeliverable_reason: user.email_undeliverable_reason,
+            department: user.department,
+            student_id: user.student_id,
+            version: user.version,
         }
     }
 }
@@ -89,18 +169,33 @@ impl From<user::Model> for UserResponse {
 #[utoipa::path(
     post,
     tags = ["User"],
-    description = "Register a new user",
+    description = "Register a new user. During a soft launch only configured email domains and student ID prefixes may register",
     path = "/register",
     request_body(content = RegisterBody, description = "User registration data", content_type = "application/json"),
     responses(
         (status = 201, description = "User registered successfully", body = UserResponse),
-        (status = 500, description = "Failed to create user", body = String),
+        (status = 400, description = "Missing or invalid student ID", body = ErrorResponse),
+        (status = 403, description = "Email domain or student ID not open for registration yet", body = ErrorResponse),
+        (status = 409, description = "Username, email or student ID already registered; `details.field` names which", body = ErrorResponse),
+        (status = 429, description = "Too many registrations from this address or for this email; see `Retry-After`", body = ErrorResponse),
+        (status = 500, description = "Failed to create user", body = ErrorResponse),
     )
 )]
 pub async fn register(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<RegisterBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
+    rate_limit::check(
+        &state,
+        Endpoint::Register,
+        &headers,
+        peer.ip(),
+        Some(&body.email),
+    )
+    .await?;
+
     let RegisterBody {
         username,
         email,
@@ -110,11 +205,31 @@ pub async fn register(
         student_id,
     } = body;
 
-    if !check_student_id(&student_id) {
-        return (StatusCode::BAD_REQUEST, "Invalid student ID").into_response();
+    if let Err(e) = student_id::check(&state.config.student_id, &Role::User, student_id.as_deref())
+    {
+        return Err(AppError::BadRequest(e.message().to_string()));
+    }
+    if let Err(e) = soft_launch::check(&state.config.soft_launch, &email, student_id.as_deref()) {
+        return Err(AppError::Forbidden(e.message().to_string()));
     }
+    let student_id = student_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty());
 
-    let hashed_password = hash(password).await.unwrap();
+    let candidate = Candidate {
+        username: Some(&username),
+        email: Some(&email),
+        student_id: student_id.as_deref(),
+    };
+    match find_conflict(&state.db, candidate, None).await {
+        Ok(Some(field)) => return Err(field.into()),
+        Ok(None) => {}
+        Err(_) => {
+            return Err(AppError::Internal("Failed to query user".to_string()));
+        }
+    }
+
+    let hashed_password = state.hasher.hash(password).await.unwrap();
 
     let new_user = user::ActiveModel {
         id: Set(nanoid!()),
@@ -126,27 +241,26 @@ pub async fn register(
         created_at: NotSet,
         updated_at: NotSet,
         name: Set(name),
+        locale: NotSet,
+        email_undeliverable_at: NotSet,
+        email_undeliverable_reason: NotSet,
+        department: NotSet,
+        student_id: Set(student_id),
+        deleted_at: NotSet,
+        version: NotSet,
     };
 
     match new_user.insert(&state.db).await {
         Ok(user) => {
-            // Cache the new user (ignore errors - caching is best effort)
-            let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("user_{}", user.id),
-                    serde_json::to_string(&user).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache user {} in Redis: {}", user.id, e);
-            }
+            state.user_cache.put(&user).await;
 
             let user_response = UserResponse::from(user);
-            (StatusCode::CREATED, Json(user_response)).into_response()
+            Ok((StatusCode::CREATED, Json(user_response)).into_response())
         }
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create user").into_response(),
+        Err(e) => match from_db_error(&e) {
+            Some(field) => Err(field.into()),
+            None => Err(AppError::Internal("Failed to create user".to_string())),
+        },
     }
 }
 
@@ -158,28 +272,149 @@ pub async fn register(
     request_body(content = Credentials, description = "User login credentials", content_type = "application/json"),
     responses(
         (status = 200, description = "User logged in successfully", body = UserResponse),
-        (status = 401, description = "Invalid credentials", body = String),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 401, description = "Invalid credentials", body = ErrorResponse),
+        (status = 423, description = "Account locked after too many failed logins", body = ErrorResponse),
+        (status = 429, description = "Too many logins or failed logins for this email or address; see `Retry-After`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
 pub async fn login(
     mut auth_session: AuthSession,
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(body): Json<Credentials>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
+    rate_limit::check(
+        &state,
+        Endpoint::Login,
+        &headers,
+        peer.ip(),
+        Some(&body.email),
+    )
+    .await?;
+
+    let guard_config = &state.config.login_guard;
+    let ip = login_guard::client_ip(&headers, peer.ip(), guard_config.trust_forwarded_for);
+    let email = body.email.clone();
+    let mut redis = state.redis.clone();
+
+    // Throttling fails open so a Redis outage does not block every login
+    match login_guard::throttled(&mut redis, guard_config, &email, ip).await {
+        Ok(Some(retry_after)) => {
+            return Err(AppError::TooManyRequests {
+                message: "Too many failed login attempts, try again later".to_string(),
+                retry_after: Some(retry_after),
+            });
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to check login attempts for {}: {}", ip, e),
+    }
+
     let user = match auth_session.authenticate(body).await {
         Ok(Some(user)) => user,
-        Ok(None) => return (StatusCode::UNAUTHORIZED, "Invalid credentials").into_response(),
+        Ok(None) => {
+            match login_guard::record_failure(&mut redis, guard_config, &email, ip).await {
+                Ok(failures)
+                    if guard_config.lock_accounts
+                        && failures >= guard_config.max_failures_per_email =>
+                {
+                    lock_account(&state, &mut redis, &email, failures).await;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to record failed login from {}: {}", ip, e),
+            }
+            return Err(AppError::Unauthorized("Invalid credentials".to_string()));
+        }
         Err(_) => {
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error").into_response();
+            return Err(AppError::Internal("Internal server error".to_string()));
         }
     };
 
-    if auth_session.login(&user).await.is_err() {
-        return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to log in").into_response();
+    match login_guard::lock_state(&mut redis, &user.id).await {
+        Ok(Some(_)) => {
+            return Err(AppError::Locked(
+                "Account is locked, use the unlock link sent to your email".to_string(),
+            ));
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Failed to check lock of user {}: {}", user.id, e),
+    }
+    if let Err(e) = login_guard::clear_failures(&mut redis, &email).await {
+        warn!("Failed to reset login attempts for user {}: {}", user.id, e);
+    }
+
+    if let Err(e) = sessions::login(&mut auth_session, &mut redis, &user).await {
+        warn!("{} for user {}", e, user.id);
+        return Err(AppError::Internal("Failed to log in".to_string()));
     }
 
     let user_response = UserResponse::from(user);
-    (StatusCode::OK, Json(user_response)).into_response()
+    Ok((StatusCode::OK, Json(user_response)).into_response())
+}
+
+/// Locks the accounts behind `email`. Failures are counted per lowercased
+/// email, so every account that email could mean is locked.
+async fn lock_account(state: &AppState, redis: &mut RedisConnection, email: &str, failures: u32) {
+    let users = match user::Entity::find()
+        .filter(
+            Expr::expr(Func::lower(Expr::col(user::Column::Email))).eq(email.trim().to_lowercase()),
+        )
+        .all(&state.db)
+        .await
+    {
+        Ok(users) => users,
+        Err(e) => {
+            warn!("Failed to fetch user to lock: {}", e);
+            return;
+        }
+    };
+    for user in users {
+        match login_guard::lock_state(redis, &user.id).await {
+            Ok(None) => {
+                if let Err(e) = login_guard::lock(state, redis, &user, failures).await {
+                    warn!("Failed to lock user {}: {}", user.id, e);
+                }
+            }
+            Ok(Some(_)) => {}
+            Err(e) => warn!("Failed to check lock of user {}: {}", user.id, e),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct UnlockAccountBody {
+    /// Token from the unlock email
+    pub token: String,
+}
+
+#[utoipa::path(
+    post,
+    tags = ["User"],
+    description = "Unlock an account locked after too many failed logins, using the token from the unlock email",
+    path = "/unlock",
+    request_body(content = UnlockAccountBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Account unlocked", body = String),
+        (status = 400, description = "Invalid or expired unlock token", body = ErrorResponse),
+        (status = 500, description = "Failed to unlock account", body = ErrorResponse),
+    )
+)]
+pub async fn unlock_account(
+    State(state): State<AppState>,
+    Json(body): Json<UnlockAccountBody>,
+) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    match login_guard::redeem_unlock_token(&mut redis, body.token.trim()).await {
+        Ok(true) => Ok((StatusCode::OK, "Account unlocked").into_response()),
+        Ok(false) => Err(AppError::BadRequest(
+            "Invalid or expired unlock token".to_string(),
+        )),
+        Err(e) => {
+            warn!("Failed to unlock account: {}", e);
+            Err(AppError::Internal("Failed to unlock account".to_string()))
+        }
+    }
 }
 
 #[utoipa::path(
@@ -189,13 +424,46 @@ pub async fn login(
     path = "/logout",
     responses(
         (status = 200, description = "User logged out successfully"),
-        (status = 500, description = "Failed to log out", body = String),
+        (status = 500, description = "Failed to log out", body = ErrorResponse),
     )
 )]
-pub async fn logout(mut auth_session: AuthSession) -> impl IntoResponse {
+pub async fn logout(
+    mut auth_session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let mut redis = state.redis.clone();
+    sessions::forget(&auth_session, &mut redis).await;
     match auth_session.logout().await {
-        Ok(_) => (StatusCode::OK, "Logged out successfully").into_response(),
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to log out").into_response(),
+        Ok(_) => Ok((StatusCode::OK, "Logged out successfully").into_response()),
+        Err(_) => return Err(AppError::Internal("Failed to log out".to_string())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["User"],
+    description = "Log out of every session of the current user, on all devices",
+    path = "/logout-all",
+    responses(
+        (status = 200, description = "All sessions logged out", body = String),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to log out", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn logout_all(
+    mut auth_session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user_id = auth_session.user.as_ref().unwrap().id.clone();
+    let mut redis = state.redis.clone();
+    if let Err(e) = sessions::revoke_all(&mut redis, &user_id, None).await {
+        warn!("Failed to revoke sessions of user {}: {}", user_id, e);
+        return Err(AppError::Internal("Failed to log out".to_string()));
+    }
+    match auth_session.logout().await {
+        Ok(_) => Ok((StatusCode::OK, "Logged out of all sessions").into_response()),
+        Err(_) => Err(AppError::Internal("Failed to log out".to_string())),
     }
 }
 
@@ -206,7 +474,7 @@ pub async fn logout(mut auth_session: AuthSession) -> impl IntoResponse {
     path = "/profile",
     responses(
         (status = 200, description = "User profile retrieved successfully", body = UserResponse),
-        (status = 401, description = "Unauthorized"),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
     ),
     security(
         ("session_cookie" = [])
@@ -220,124 +488,116 @@ async fn profile(session: AuthSession) -> impl IntoResponse {
 #[utoipa::path(
     get,
     tags = ["User"],
-    description = "Get user by ID",
+    description = "Get user by ID. The student ID is left out unless the caller is the user or manages users.",
     path = "/{id}",
     params(
         ("id" = String, Path, description = "User ID")
     ),
     responses(
         (status = 200, description = "User found", body = UserResponse),
-        (status = 404, description = "User not found", body = String),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     )
 )]
-pub async fn get_user(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
-    // Clone connection once for this handler
-    let mut redis = state.redis.clone();
-
-    // Try to get from cache first
-    let cached_user: Option<String> = match redis.get_ex(format!("user_{}", id), REDIS_EXPIRY).await
-    {
-        Ok(user) => user,
-        Err(e) => {
-            warn!("Failed to get user {} from Redis cache: {}", id, e);
-            None
-        }
-    };
-
-    if let Some(user_str) = cached_user {
-        if let Ok(user) = serde_json::from_str::<entities::user::Model>(&user_str) {
-            let user_response = UserResponse::from(user);
-            return (StatusCode::OK, Json(user_response)).into_response();
-        }
+pub async fn get_user(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let viewer = session.user.as_ref();
+    if let Some(user) = state.user_cache.get(&id).await {
+        let user_response = UserResponse::for_viewer(user, viewer);
+        return Ok((StatusCode::OK, Json(user_response)).into_response());
+    }
+    // IDs found not to exist are answered without asking the database again
+    if state.cache.is_missing(CacheKey::MissingUser(&id)).await {
+        return Err(AppError::NotFound("User not found".to_string()));
     }
 
-    // Fallback to database
     match user::Entity::find_by_id(id.clone()).one(&state.db).await {
         Ok(Some(user)) => {
-            // Cache the result for future requests
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("user_{}", user.id),
-                    serde_json::to_string(&user).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!("Failed to cache user {} in Redis: {}", user.id, e);
-            }
-            let user_response = UserResponse::from(user);
-            (StatusCode::OK, Json(user_response)).into_response()
+            state.user_cache.put(&user).await;
+            let user_response = UserResponse::for_viewer(user, viewer);
+            Ok((StatusCode::OK, Json(user_response)).into_response())
         }
-        Ok(None) => (StatusCode::NOT_FOUND, "User not found").into_response(),
-        Err(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Failed to fetch user").into_response(),
+        Ok(None) => {
+            state.cache.mark_missing(CacheKey::MissingUser(&id)).await;
+            Err(AppError::NotFound("User not found".to_string()))
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch user".to_string())),
     }
 }
 
 #[utoipa::path(
     put,
     tags = ["User"],
-    description = "Update user password",
+    description = "Update user password. The current session stays signed in; every other session of the user is logged out.",
     path = "/update-password",
     request_body(content = UpdatePasswordBody, description = "User password update data", content_type = "application/json"),
     responses(
         (status = 200, description = "Password updated successfully", body = String),
-        (status = 400, description = "New password and confirm password are not same", body = String),
-        (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 400, description = "New password and confirm password are not same", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     security(
         ("session_cookie" = [])
     )
 )]
 pub async fn update_password(
-    session: AuthSession,
+    mut session: AuthSession,
     State(state): State<AppState>,
     Json(body): Json<UpdatePasswordBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let UpdatePasswordBody {
         old_password,
         new_password,
         confirm,
     } = body;
     if new_password != confirm {
-        return (
-            StatusCode::BAD_REQUEST,
-            "New password and confirm password are not same",
-        );
+        return Err(AppError::BadRequest(
+            "New password and confirm password are not same".to_string(),
+        ));
     }
-    let user_current = session.user.unwrap();
+    let user_current = session.user.clone().unwrap();
     let old_hashed_password = &user_current.password;
-    if verify(old_password, old_hashed_password).await.is_err() {
-        return (StatusCode::BAD_REQUEST, "Old password is not correct");
+    if state
+        .hasher
+        .verify(old_password, old_hashed_password)
+        .await
+        .is_err()
+    {
+        return Err(AppError::BadRequest(
+            "Old password is not correct".to_string(),
+        ));
     }
 
     let mut new_user: user::ActiveModel = user_current.into();
-    let new_hashed_password = hash(new_password).await.unwrap();
+    let new_hashed_password = state.hasher.hash(new_password).await.unwrap();
     new_user.password = Set(new_hashed_password);
-    match new_user.update(&state.db).await {
+    match versioning::update(&state.db, new_user, user::Column::Version, None).await {
         Ok(updated_user) => {
-            // Update cache (ignore errors - caching is best effort)
+            // Keep this session signed in under the new password and end the
+            // user's other sessions
             let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("user_{}", updated_user.id),
-                    serde_json::to_string(&updated_user).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
+            if let Err(e) = sessions::login(&mut session, &mut redis, &updated_user).await {
+                warn!("{} for user {}", e, updated_user.id);
+            }
+            if let Err(e) =
+                sessions::revoke_all(&mut redis, &updated_user.id, session.session.id()).await
+            {
                 warn!(
-                    "Failed to update cache for user {} in Redis: {}",
+                    "Failed to revoke sessions of user {}: {}",
                     updated_user.id, e
                 );
             }
-            (StatusCode::OK, "Password updated successfully")
+
+            state.user_cache.put(&updated_user).await;
+            Ok((StatusCode::OK, "Password updated successfully").into_response())
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update user password",
-        ),
+        Err(_) => Err(AppError::Internal(
+            "Failed to update user password".to_string(),
+        )),
     }
 }
 
@@ -348,7 +608,7 @@ pub async fn update_password(
 #[utoipa::path(
     put,
     tags = ["User"],
-    description = "Update user profile info",
+    description = "Update user profile info. A new email is not applied right away: a confirmation code is sent to it, to be submitted to `/confirm-email-change`.",
     path = "/update-profile",
     request_body(
         content = UpdateProfileBody,
@@ -356,9 +616,11 @@ pub async fn update_password(
         content_type = "application/json"
     ),
     responses(
-        (status = 200, description = "Profile updated successfully", body = UserResponse),
-        (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Internal server error", body = String),
+        (status = 200, description = "Profile updated successfully", body = UpdateProfileResponse),
+        (status = 400, description = "Unsupported locale", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 409, description = "Username or email already registered, with `details.field` naming which; or the profile was saved elsewhere since `version`, with `details.current_version` the version now", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
     ),
     security(("session_cookie" = []))
 )]
@@ -366,63 +628,657 @@ pub async fn update_profile(
     session: AuthSession,
     State(state): State<AppState>,
     Json(body): Json<UpdateProfileBody>,
-) -> impl IntoResponse {
+) -> Result<Response, AppError> {
     let user_current = session.user.unwrap();
+    versioning::check(body.version, user_current.version)?;
+
+    if let Some(locale) = &body.locale
+        && Locale::from_tag(locale).is_none()
+    {
+        return Err(AppError::BadRequest("Unsupported locale".to_string()));
+    }
+
+    let candidate = Candidate {
+        username: body.username.as_deref(),
+        email: body.email.as_deref(),
+        student_id: None,
+    };
+    match find_conflict(&state.db, candidate, Some(&user_current.id)).await {
+        Ok(Some(field)) => return Err(field.into()),
+        Ok(None) => {}
+        Err(_) => {
+            return Err(AppError::Internal("Internal server error".to_string()));
+        }
+    }
+
+    let mut redis = state.redis.clone();
+    let pending_email = match body.email.map(|email| email.trim().to_string()) {
+        Some(email) if email != user_current.email => {
+            let pending = match email_change::start(
+                &mut redis,
+                &state.config.password_reset,
+                &user_current.id,
+                &email,
+            )
+            .await
+            {
+                Ok(pending) => pending,
+                Err(e) => {
+                    warn!(
+                        "Failed to store email change for user {} in Redis: {}",
+                        user_current.id, e
+                    );
+                    return Err(AppError::Internal(
+                        "Failed to start email change".to_string(),
+                    ));
+                }
+            };
+            let content = format!(
+                "Your email change confirmation code is: {}\n\nThis code will expire in {} minutes. If you did not request this change, you can ignore this email.",
+                pending.code,
+                state.config.password_reset.code_ttl.num_minutes()
+            );
+            if send_email(
+                state.config.smtp.as_ref(),
+                &email,
+                "Confirm Your New Email Address",
+                content,
+            )
+            .await
+            .is_err()
+            {
+                return Err(AppError::Internal(
+                    "Failed to send confirmation email".to_string(),
+                ));
+            }
+            Some(email)
+        }
+        _ => None,
+    };
 
+    let loaded_version = user_current.version;
     let mut new_user: user::ActiveModel = user_current.into();
 
     if let Some(username) = body.username {
         new_user.username = Set(username);
     }
-    if let Some(email) = body.email {
-        new_user.email = Set(email);
-    }
     if let Some(phone_number) = body.phone_number {
         new_user.phone_number = Set(phone_number);
     }
     if let Some(name) = body.name {
         new_user.name = Set(name);
     }
+    if let Some(locale) = body.locale {
+        new_user.locale = Set(Some(locale));
+    }
 
-    match new_user.update(&state.db).await {
+    // The signed-in user may come from the cache, so the version is only
+    // relied on when the client asked for the check
+    match versioning::update(
+        &state.db,
+        new_user,
+        user::Column::Version,
+        body.version.map(|_| loaded_version),
+    )
+    .await
+    {
         Ok(updated_user) => {
-            // Update cache (ignore errors - caching is best effort)
-            let mut redis = state.redis.clone();
-            let result: Result<(), redis::RedisError> = redis
-                .set_options(
-                    format!("user_{}", updated_user.id),
-                    serde_json::to_string(&updated_user).unwrap(),
-                    get_redis_set_options(),
-                )
-                .await;
-            if let Err(e) = result {
-                warn!(
-                    "Failed to update cache for user {} in Redis: {}",
-                    updated_user.id, e
-                );
-            }
-            let user_response = UserResponse::from(updated_user);
-            (StatusCode::OK, Json(user_response)).into_response()
+            state.user_cache.put(&updated_user).await;
+            let response = UpdateProfileResponse {
+                user: UserResponse::from(updated_user),
+                pending_email,
+            };
+            Ok((StatusCode::OK, Json(response)).into_response())
         }
-        Err(_) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to update profile",
+        Err(DbErr::RecordNotUpdated) => Err(versioning::stale(None)),
+        Err(e) => match from_db_error(&e) {
+            Some(field) => Err(field.into()),
+            None => Err(AppError::Internal("Failed to update profile".to_string())),
+        },
+    }
+}
+
+#[utoipa::path(
+    post,
+    tags = ["User"],
+    description = "Confirm a pending email change with the code sent to the new address. The old address is notified of the change.",
+    path = "/confirm-email-change",
+    request_body(content = ConfirmEmailChangeBody, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Email changed", body = UserResponse),
+        (status = 400, description = "No pending change, invalid code, or too many failed attempts", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 409, description = "Email registered by another account in the meantime; `details.field` is `email`", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn confirm_email_change(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Json(body): Json<ConfirmEmailChangeBody>,
+) -> Result<Response, AppError> {
+    let user_current = session.user.unwrap();
+    let mut redis = state.redis.clone();
+
+    let email = match email_change::confirm(
+        &mut redis,
+        &state.config.password_reset,
+        &user_current.id,
+        &body.code,
+    )
+    .await
+    {
+        Ok(ConfirmOutcome::Confirmed(email)) => email,
+        Ok(ConfirmOutcome::NotPending) => {
+            return Err(AppError::BadRequest("No pending email change".to_string()));
+        }
+        Ok(ConfirmOutcome::WrongCode) => {
+            return Err(AppError::BadRequest("Invalid code".to_string()));
+        }
+        Ok(ConfirmOutcome::TooManyAttempts) => {
+            return Err(AppError::BadRequest(
+                "Too many failed attempts, request the change again".to_string(),
+            ));
+        }
+        Err(e) => {
+            warn!(
+                "Failed to read email change for user {} from Redis: {}",
+                user_current.id, e
+            );
+            return Err(AppError::Internal(
+                "Failed to confirm email change".to_string(),
+            ));
+        }
+    };
+
+    // The address may have been taken since the change was requested
+    let candidate = Candidate {
+        username: None,
+        email: Some(&email),
+        student_id: None,
+    };
+    match find_conflict(&state.db, candidate, Some(&user_current.id)).await {
+        Ok(Some(field)) => return Err(field.into()),
+        Ok(None) => {}
+        Err(_) => {
+            return Err(AppError::Internal("Internal server error".to_string()));
+        }
+    }
+
+    let previous = user_current.clone();
+    let mut new_user: user::ActiveModel = user_current.into();
+    new_user.email = Set(email.clone());
+    // A new address gets a fresh chance after a bounce
+    new_user.email_undeliverable_at = Set(None);
+    new_user.email_undeliverable_reason = Set(None);
+
+    let updated_user =
+        match versioning::update(&state.db, new_user, user::Column::Version, None).await {
+            Ok(updated_user) => updated_user,
+            Err(e) => {
+                return match from_db_error(&e) {
+                    Some(field) => Err(field.into()),
+                    None => Err(AppError::Internal("Failed to update email".to_string())),
+                };
+            }
+        };
+
+    if let Err(e) = email_change::discard(&mut redis, &updated_user.id).await {
+        warn!(
+            "Failed to clear email change for user {} in Redis: {}",
+            updated_user.id, e
+        );
+    }
+    state.user_cache.put(&updated_user).await;
+
+    let content = format!(
+        "The email address of your account was changed to {}.\n\nIf you did not make this change, contact an administrator immediately.",
+        email
+    );
+    if let Err(e) = send_email_to_user(
+        state.config.smtp.as_ref(),
+        &previous,
+        "Your Email Address Was Changed",
+        content,
+    )
+    .await
+    {
+        warn!(
+            "Failed to notify previous address of user {}: {}",
+            updated_user.id, e
+        );
+    }
+
+    Ok((StatusCode::OK, Json(UserResponse::from(updated_user))).into_response())
+}
+
+// ===============================
+//   Notification Preferences
+// ===============================
+
+#[derive(Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferences {
+    /// Announcement categories the user no longer receives emails for.
+    /// Emergency announcements are always sent.
+    pub muted_categories: Vec<AnnouncementCategory>,
+}
+
+#[utoipa::path(
+    get,
+    tags = ["User"],
+    description = "Get the current user's notification preferences",
+    path = "/notification-preferences",
+    responses(
+        (status = 200, description = "Notification preferences", body = NotificationPreferences),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_notification_preferences(
+    session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+
+    match announcement_mute::Entity::find()
+        .filter(announcement_mute::Column::UserId.eq(&user.id))
+        .all(&state.db)
+        .await
+    {
+        Ok(mutes) => Ok((
+            StatusCode::OK,
+            Json(NotificationPreferences {
+                muted_categories: mutes.into_iter().map(|m| m.category).collect(),
+            }),
         )
-            .into_response(),
+            .into_response()),
+        Err(_) => Err(AppError::Internal(
+            "Failed to fetch notification preferences".to_string(),
+        )),
+    }
+}
+
+#[utoipa::path(
+    put,
+    tags = ["User"],
+    description = "Replace the current user's muted announcement categories",
+    path = "/notification-preferences",
+    request_body(content = NotificationPreferences, content_type = "application/json"),
+    responses(
+        (status = 200, description = "Notification preferences updated", body = NotificationPreferences),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn update_notification_preferences(
+    session: AuthSession,
+    State(state): State<AppState>,
+    Json(body): Json<NotificationPreferences>,
+) -> Result<Response, AppError> {
+    let user = session.user.unwrap();
+
+    let mut unique: Vec<AnnouncementCategory> = Vec::new();
+    for category in body.muted_categories {
+        if !unique.contains(&category) {
+            unique.push(category);
+        }
+    }
+
+    let txn = match state.db.begin().await {
+        Ok(txn) => txn,
+        Err(_) => {
+            return Err(AppError::Internal(
+                "Failed to start transaction".to_string(),
+            ));
+        }
+    };
+
+    if announcement_mute::Entity::delete_many()
+        .filter(announcement_mute::Column::UserId.eq(&user.id))
+        .exec(&txn)
+        .await
+        .is_err()
+    {
+        return Err(AppError::Internal(
+            "Failed to update notification preferences".to_string(),
+        ));
+    }
+
+    for category in &unique {
+        let mute = announcement_mute::ActiveModel {
+            user_id: Set(user.id.clone()),
+            category: Set(category.clone()),
+        };
+        if mute.insert(&txn).await.is_err() {
+            return Err(AppError::Internal(
+                "Failed to update notification preferences".to_string(),
+            ));
+        }
+    }
+
+    if txn.commit().await.is_err() {
+        return Err(AppError::Internal(
+            "Failed to update notification preferences".to_string(),
+        ));
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(NotificationPreferences {
+            muted_categories: unique,
+        }),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    get,
+    tags = ["User"],
+    description = "Find the account a student ID belongs to, e.g. when someone shows their student card at the key desk",
+    path = "/admin/by-student-id/{sid}",
+    params(("sid" = String, Path, description = "Student ID")),
+    responses(
+        (status = 200, description = "User found", body = UserResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "No user with this student ID", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn get_user_by_student_id(
+    State(state): State<AppState>,
+    Path(sid): Path<String>,
+) -> Result<Response, AppError> {
+    match user::Entity::find()
+        .filter(user::Column::StudentId.eq(sid.trim()))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(user)) => Ok((StatusCode::OK, Json(UserResponse::from(user))).into_response()),
+        Ok(None) => Err(AppError::NotFound(
+            "No user with this student ID".to_string(),
+        )),
+        Err(_) => Err(AppError::Internal("Failed to fetch user".to_string())),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ListUsersQuery {
+    pub role: Option<Role>,
+    /// `created_at` (default), `username`, `name` or `email`, optionally with
+    /// `:asc` or `:desc`
+    pub sort: Option<String>,
+}
+
+const USER_SORT_FIELDS: [(&str, user::Column); 4] = [
+    ("created_at", user::Column::CreatedAt),
+    ("username", user::Column::Username),
+    ("name", user::Column::Name),
+    ("email", user::Column::Email),
+];
+
+#[utoipa::path(
+    get,
+    tags = ["User"],
+    description = "List accounts, newest first by default. Deleted accounts are left out.",
+    path = "/admin/list",
+    params(ListUsersQuery, PageParams),
+    responses(
+        (status = 200, description = "Users", body = PagedResponse<UserResponse>),
+        (status = 400, description = "Invalid sort", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    Query(query): Query<ListUsersQuery>,
+    pagination: Pagination,
+) -> Result<Response, AppError> {
+    let sort = match SortSpec::parse(
+        query.sort.as_deref(),
+        &USER_SORT_FIELDS,
+        user::Column::CreatedAt,
+        Order::Desc,
+    ) {
+        Ok(sort) => sort,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let mut select = user::Entity::find().filter(user::Column::DeletedAt.is_null());
+    if let Some(role) = query.role {
+        select = select.filter(user::Column::Role.eq(role));
+    }
+    match fetch(&state.db, sort.apply(select), pagination).await {
+        Ok(users) => {
+            let users: PagedResponse<UserResponse> = users.map(Into::into);
+            Ok((StatusCode::OK, Json(users)).into_response())
+        }
+        Err(_) => Err(AppError::Internal("Failed to fetch users".to_string())),
+    }
+}
+
+// ===============================
+//   Account Deletion
+// ===============================
+
+/// Deletes the account, then withdraws what its cancelled reservations
+/// granted, ends its sessions and tells the old address.
+async fn delete_and_notify(state: &AppState, user: user::Model) -> Result<DeletionSummary, ()> {
+    let user_id = user.id.clone();
+    let previous = user.clone();
+    let policy = state.config.account_deletion.reservations;
+    let (summary, cancelled) =
+        match delete_account(&state.db, user, policy, Utc::now().fixed_offset()).await {
+            Ok(deleted) => deleted,
+            Err(e) => {
+                warn!("Failed to delete account {}: {}", user_id, e);
+                return Err(());
+            }
+        };
+
+    // Cabinet PINs and pickup codes of the cancelled reservations would
+    // still hand out keys
+    for reservation in cancelled {
+        tokio::spawn(key_cabinet::revoke_pins(
+            state.clone(),
+            reservation.id.clone(),
+        ));
+        tokio::spawn(pickup::revoke_codes(state.clone(), reservation.id.clone()));
+        state.cache.invalidate_reservation(&reservation).await;
+        domain_events::publish(state, DomainEvent::ReservationCancelled { reservation }).await;
+    }
+    state.user_cache.forget(&user_id).await;
+
+    // The password hash changed, so remaining sessions fail their auth check
+    // even if revoking them here does not get through
+    let mut redis = state.redis.clone();
+    if let Err(e) = sessions::revoke_all(&mut redis, &user_id, None).await {
+        warn!(
+            "Failed to revoke sessions of deleted user {}: {}",
+            user_id, e
+        );
+    }
+
+    let email = email_templates::account_deleted(
+        summary.cancelled_reservations,
+        state.config.email_templates.locale_for(&previous),
+    );
+    if let Err(e) = send_email_to_user(
+        state.config.smtp.as_ref(),
+        &previous,
+        email.subject,
+        email.body,
+    )
+    .await
+    {
+        warn!(
+            "Failed to send deletion confirmation to user {}: {}",
+            user_id, e
+        );
     }
+    Ok(summary)
 }
 
+#[utoipa::path(
+    delete,
+    tags = ["User"],
+    description = "Delete the current account. Personal fields are anonymized, upcoming reservations are cancelled and past ones are kept or unlinked per the configured policy. All sessions are logged out and a confirmation is emailed.",
+    path = "/self",
+    responses(
+        (status = 200, description = "Account deleted", body = DeletionSummary),
+        (status = 400, description = "Admin accounts cannot be deleted", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Failed to delete account", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn delete_self(
+    mut auth_session: AuthSession,
+    State(state): State<AppState>,
+) -> Result<Response, AppError> {
+    let user = auth_session.user.clone().unwrap();
+    if let Some(reason) = deletion_blocked(&user) {
+        return Err(AppError::BadRequest(reason.to_string()));
+    }
+    let Ok(summary) = delete_and_notify(&state, user).await else {
+        return Err(AppError::Internal("Failed to delete account".to_string()));
+    };
+    if let Err(e) = auth_session.logout().await {
+        warn!("Failed to log out deleted account: {}", e);
+    }
+    Ok((StatusCode::OK, Json(summary)).into_response())
+}
+
+#[utoipa::path(
+    delete,
+    tags = ["User"],
+    description = "Delete a user's account on their behalf, with the same anonymization as `DELETE /self`. Use `/admin/users/{user_id}/transfer-reservations` first to keep their upcoming reservations.",
+    path = "/{id}",
+    params(("id" = String, Path, description = "User ID")),
+    responses(
+        (status = 200, description = "Account deleted", body = DeletionSummary),
+        (status = 400, description = "Admin or already deleted account", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 404, description = "User not found", body = ErrorResponse),
+        (status = 500, description = "Failed to delete account", body = ErrorResponse),
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn delete_user(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let user = match user::Entity::find_by_id(&id).one(&state.db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return Err(AppError::NotFound("User not found".to_string())),
+        Err(_) => {
+            return Err(AppError::Internal("Failed to fetch user".to_string()));
+        }
+    };
+    if let Some(reason) = deletion_blocked(&user) {
+        return Err(AppError::BadRequest(reason.to_string()));
+    }
+    let previous = user_snapshot(&user);
+    match delete_and_notify(&state, user).await {
+        Ok(summary) => {
+            audit
+                .log(&state.db, Target::User, &id, "delete", Some(previous), None)
+                .await;
+            Ok((StatusCode::OK, Json(summary)).into_response())
+        }
+        Err(()) => Err(AppError::Internal("Failed to delete account".to_string())),
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "User", description = "User endpoints")
+    ),
+    paths(
+        register,
+        login,
+        logout,
+        logout_all,
+        unlock_account,
+        profile,
+        get_user,
+        update_password,
+        update_profile,
+        confirm_email_change,
+        delete_self,
+        delete_user,
+        list_users,
+        get_user_by_student_id,
+        get_notification_preferences,
+        update_notification_preferences,
+        crate::routes::user_export::export_users,
+        crate::routes::invite::invite_user,
+        crate::routes::invite::accept_invite
+    ),
+    components(schemas(
+        crate::entities::user::Model,
+        crate::entities::sea_orm_active_enums::Role,
+        crate::login_system::Credentials,
+        RegisterBody,
+        UpdatePasswordBody,
+        UnlockAccountBody,
+        UserResponse,
+        crate::pagination::PagedResponse<UserResponse>,
+        UpdateProfileBody,
+        UpdateProfileResponse,
+        ConfirmEmailChangeBody,
+        crate::account_deletion::DeletionSummary,
+        NotificationPreferences,
+        crate::entities::sea_orm_active_enums::AnnouncementCategory,
+        crate::routes::user_export::UserExportQuery,
+        crate::routes::user_export::UserExportRow,
+        crate::export::ExportFormat,
+        crate::routes::invite::InviteBody,
+        crate::routes::invite::InviteResponse,
+        crate::routes::invite::AcceptInviteBody,
+        crate::user_conflicts::ConflictDetails,
+        crate::user_conflicts::UniqueField
+    ))
+)]
+pub struct UserApi;
+
 pub fn user_router() -> Router<AppState> {
     let login_required_router = Router::new()
         .route("/profile", get(profile))
         .route("/update-password", put(update_password))
+        .route("/logout-all", post(logout_all))
         .route("/update-profile", put(update_profile))
+        .route("/confirm-email-change", post(confirm_email_change))
+        .route("/self", delete(delete_self))
+        .route(
+            "/notification-preferences",
+            get(get_notification_preferences).put(update_notification_preferences),
+        )
         .route_layer(login_required!(AuthBackend));
 
+    let admin_router = Router::new()
+        .route("/{id}", delete(delete_user))
+        .route("/admin/list", get(list_users))
+        .route("/admin/by-student-id/{sid}", get(get_user_by_student_id))
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers));
+
     Router::new()
         .route("/login", post(login))
         .route("/logout", get(logout))
+        .route("/unlock", post(unlock_account))
         .route("/register", post(register))
         .route("/{id}", get(get_user))
         .merge(login_required_router)
+        .merge(admin_router)
+        .merge(user_export_router())
+        .merge(invite_router())
 }
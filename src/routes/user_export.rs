@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{Query, State},
+    middleware,
+    response::Response,
+    routing::get,
+};
+use axum_login::permission_required;
+use futures_util::stream;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, QuerySelect,
+    QueryTrait, Select,
+    prelude::DateTimeWithTimeZone,
+    sea_query::{Expr, ExprTrait, Func},
+};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{
+    AppState,
+    concurrency::limit_reports,
+    entities::{infraction, reservation, sea_orm_active_enums::Role, user},
+    error::{AppError, ErrorResponse},
+    export::{EXPORT_CHUNK_SIZE, ExportFormat, csv_line, download, parse_filter},
+    login_system::AuthBackend,
+    permissions::Permission,
+};
+
+const CSV_HEADER: [&str; 9] = [
+    "id",
+    "username",
+    "name",
+    "email",
+    "phone_number",
+    "role",
+    "created_at",
+    "infraction_count",
+    "last_reservation_at",
+];
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct UserExportQuery {
+    /// `csv` (default) or `ndjson`
+    pub format: Option<ExportFormat>,
+    pub role: Option<Role>,
+    /// Registered at or after this time
+    pub registered_from: Option<String>,
+    /// Registered before this time
+    pub registered_to: Option<String>,
+    /// Only users with a reservation starting at or after this time
+    pub active_since: Option<String>,
+    /// Only users without any reservation starting at or after this time
+    pub inactive_since: Option<String>,
+    pub min_infractions: Option<u64>,
+    pub max_infractions: Option<u64>,
+}
+
+/// One exported user. Never includes credentials.
+#[derive(Serialize, ToSchema)]
+pub struct UserExportRow {
+    pub id: String,
+    pub username: String,
+    pub name: String,
+    pub email: String,
+    pub phone_number: String,
+    pub role: Role,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub created_at: DateTimeWithTimeZone,
+    pub infraction_count: i64,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub last_reservation_at: Option<DateTimeWithTimeZone>,
+}
+
+impl UserExportRow {
+    fn csv(&self) -> String {
+        let role = match self.role {
+            Role::Admin => "admin",
+            Role::Assistant => "assistant",
+            Role::User => "user",
+        };
+        csv_line(&[
+            self.id.as_str(),
+            self.username.as_str(),
+            self.name.as_str(),
+            self.email.as_str(),
+            self.phone_number.as_str(),
+            role,
+            &self.created_at.to_rfc3339(),
+            &self.infraction_count.to_string(),
+            &self
+                .last_reservation_at
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        ])
+    }
+}
+
+/// Applies the export filters; infraction and activity filters become
+/// subqueries so paging stays in the database.
+fn filtered_users(q: &UserExportQuery) -> Result<Select<user::Entity>, String> {
+    let mut stmt = user::Entity::find();
+
+    if let Some(role) = &q.role {
+        stmt = stmt.filter(user::Column::Role.eq(role.clone()));
+    }
+    if let Some(from) = parse_filter(&q.registered_from, "registered_from")? {
+        stmt = stmt.filter(user::Column::CreatedAt.gte(from));
+    }
+    if let Some(to) = parse_filter(&q.registered_to, "registered_to")? {
+        stmt = stmt.filter(user::Column::CreatedAt.lt(to));
+    }
+
+    let reserved_since = |since: DateTimeWithTimeZone| {
+        reservation::Entity::find()
+            .select_only()
+            .column(reservation::Column::UserId)
+            .filter(reservation::Column::UserId.is_not_null())
+            .filter(reservation::Column::StartTime.gte(since))
+            .into_query()
+    };
+    if let Some(since) = parse_filter(&q.active_since, "active_since")? {
+        stmt = stmt.filter(user::Column::Id.in_subquery(reserved_since(since)));
+    }
+    if let Some(since) = parse_filter(&q.inactive_since, "inactive_since")? {
+        stmt = stmt.filter(user::Column::Id.not_in_subquery(reserved_since(since)));
+    }
+
+    let infraction_count = Func::count(Expr::col(infraction::Column::Id));
+    let users_with_infractions = |having: Expr| {
+        infraction::Entity::find()
+            .select_only()
+            .column(infraction::Column::UserId)
+            .filter(infraction::Column::UserId.is_not_null())
+            .group_by(infraction::Column::UserId)
+            .having(having)
+            .into_query()
+    };
+    if let Some(min) = q.min_infractions.filter(|min| *min > 0) {
+        stmt = stmt.filter(user::Column::Id.in_subquery(users_with_infractions(
+            Expr::expr(infraction_count.clone()).gte(min),
+        )));
+    }
+    // Users without any infraction have no row to count, so the upper bound
+    // excludes those above it instead
+    if let Some(max) = q.max_infractions {
+        stmt = stmt.filter(
+            user::Column::Id
+                .not_in_subquery(users_with_infractions(Expr::expr(infraction_count).gt(max))),
+        );
+    }
+
+    Ok(stmt)
+}
+
+async fn export_rows(
+    db: &DatabaseConnection,
+    users: Vec<user::Model>,
+) -> Result<Vec<UserExportRow>, DbErr> {
+    let ids: Vec<String> = users.iter().map(|u| u.id.clone()).collect();
+
+    let infraction_counts: HashMap<String, i64> = infraction::Entity::find()
+        .select_only()
+        .column(infraction::Column::UserId)
+        .column_as(
+            Expr::expr(Func::count(Expr::col(infraction::Column::Id))),
+            "count",
+        )
+        .filter(infraction::Column::UserId.is_in(ids.clone()))
+        .group_by(infraction::Column::UserId)
+        .into_tuple::<(Option<String>, i64)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(user_id, count)| Some((user_id?, count)))
+        .collect();
+
+    let last_reservations: HashMap<String, DateTimeWithTimeZone> = reservation::Entity::find()
+        .select_only()
+        .column(reservation::Column::UserId)
+        .column_as(
+            Expr::expr(Func::max(Expr::col(reservation::Column::StartTime))),
+            "last",
+        )
+        .filter(reservation::Column::UserId.is_in(ids))
+        .group_by(reservation::Column::UserId)
+        .into_tuple::<(Option<String>, Option<DateTimeWithTimeZone>)>()
+        .all(db)
+        .await?
+        .into_iter()
+        .filter_map(|(user_id, last)| Some((user_id?, last?)))
+        .collect();
+
+    Ok(users
+        .into_iter()
+        .map(|u| UserExportRow {
+            infraction_count: infraction_counts.get(&u.id).copied().unwrap_or(0),
+            last_reservation_at: last_reservations.get(&u.id).copied(),
+            id: u.id,
+            username: u.username,
+            name: u.name,
+            email: u.email,
+            phone_number: u.phone_number,
+            role: u.role,
+            created_at: u.created_at,
+        })
+        .collect())
+}
+
+struct ExportCursor {
+    db: DatabaseConnection,
+    query: Select<user::Entity>,
+    format: ExportFormat,
+    last_id: Option<String>,
+    first_chunk: bool,
+    done: bool,
+}
+
+/// Next chunk of the export, keyed on the user ID so rows are neither skipped
+/// nor repeated when users register during the download.
+async fn next_chunk(
+    mut cursor: ExportCursor,
+) -> Option<(Result<Bytes, std::io::Error>, ExportCursor)> {
+    if cursor.done {
+        return None;
+    }
+
+    let mut stmt = cursor
+        .query
+        .clone()
+        .order_by_asc(user::Column::Id)
+        .limit(EXPORT_CHUNK_SIZE);
+    if let Some(last_id) = &cursor.last_id {
+        stmt = stmt.filter(user::Column::Id.gt(last_id.as_str()));
+    }
+    let rows = match stmt.all(&cursor.db).await {
+        Ok(users) => {
+            cursor.done = (users.len() as u64) < EXPORT_CHUNK_SIZE;
+            cursor.last_id = users.last().map(|u| u.id.clone());
+            export_rows(&cursor.db, users).await
+        }
+        Err(e) => Err(e),
+    };
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            // Aborts the body so clients see a truncated download, not a
+            // silently short file
+            cursor.done = true;
+            return Some((Err(std::io::Error::other(e.to_string())), cursor));
+        }
+    };
+
+    let mut out = String::new();
+    if cursor.first_chunk && cursor.format == ExportFormat::Csv {
+        out.push_str(&csv_line(&CSV_HEADER));
+    }
+    cursor.first_chunk = false;
+    for row in &rows {
+        match cursor.format {
+            ExportFormat::Csv => out.push_str(&row.csv()),
+            ExportFormat::Ndjson => {
+                out.push_str(&serde_json::to_string(row).unwrap_or_default());
+                out.push('\n');
+            }
+        }
+    }
+    Some((Ok(Bytes::from(out)), cursor))
+}
+
+#[utoipa::path(
+    get,
+    tags = ["User"],
+    description = "Stream the user list as CSV or NDJSON for roster reconciliation. Each row carries the user's infraction count and last reservation start. Filters combine with AND. Shares the report concurrency limit.",
+    path = "/admin/export",
+    params(UserExportQuery),
+    responses(
+        (status = 200, description = "User export", content_type = "text/csv"),
+        (status = 400, description = "Invalid filter", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 403, description = "Forbidden", body = ErrorResponse),
+        (status = 503, description = "Too many reports running, retry later", body = ErrorResponse)
+    ),
+    security(("session_cookie" = []))
+)]
+pub async fn export_users(
+    State(state): State<AppState>,
+    Query(q): Query<UserExportQuery>,
+) -> Result<Response, AppError> {
+    let query = match filtered_users(&q) {
+        Ok(query) => query,
+        Err(message) => return Err(AppError::BadRequest(message)),
+    };
+    let format = q.format.unwrap_or_default();
+
+    let cursor = ExportCursor {
+        db: state.db.clone(),
+        query,
+        format,
+        last_id: None,
+        first_chunk: true,
+        done: false,
+    };
+    Ok(download(
+        format,
+        "users",
+        stream::unfold(cursor, next_chunk),
+    ))
+}
+
+pub fn user_export_router() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/export",
+            get(export_users).layer(middleware::from_fn(limit_reports)),
+        )
+        .route_layer(permission_required!(AuthBackend, Permission::ManageUsers))
+}
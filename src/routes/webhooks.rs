@@ -0,0 +1,106 @@
+use axum::{Json, Router, http::StatusCode, response::IntoResponse, routing::get};
+use serde_json::{Value, json};
+use utoipa::{
+    OpenApi,
+    openapi::{self, extensions::Extensions},
+};
+
+use crate::{
+    AppState, availability,
+    domain_events::{self, EVENT_KINDS},
+    entities,
+};
+
+/// Schemas of the event payloads, kept apart from the endpoint schemas so the
+/// webhook document can be served on its own.
+#[derive(OpenApi)]
+#[openapi(components(schemas(
+    domain_events::DomainEvent,
+    entities::reservation::Model,
+    entities::sea_orm_active_enums::ReservationStatus,
+    availability::AlternativeRoom,
+)))]
+struct EventSchemas;
+
+/// The OpenAPI `webhooks` object: one entry per event kind, each posting a
+/// [`domain_events::DomainEvent`] whose `type` is the kind.
+fn webhooks_object() -> Value {
+    let webhooks: serde_json::Map<String, Value> = EVENT_KINDS
+        .iter()
+        .map(|(kind, description)| {
+            (
+                kind.to_string(),
+                json!({
+                    "post": {
+                        "operationId": kind,
+                        "summary": description,
+                        "description": format!("Payload of a `{}` event; its `type` field is `{}`.", kind, kind),
+                        "requestBody": {
+                            "required": true,
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/DomainEvent" }
+                                }
+                            }
+                        },
+                        "responses": {
+                            "200": { "description": "Event received" }
+                        }
+                    }
+                }),
+            )
+        })
+        .collect();
+    Value::Object(webhooks)
+}
+
+/// Adds the event payloads to the API document as OpenAPI webhooks.
+pub struct WebhooksAddon;
+
+impl utoipa::Modify for WebhooksAddon {
+    fn modify(&self, openapi: &mut openapi::OpenApi) {
+        openapi.merge(EventSchemas::openapi());
+        // utoipa has no webhooks support; extensions are written at the top
+        // level of the document, which is where OpenAPI 3.1 expects them
+        openapi
+            .extensions
+            .get_or_insert_with(Extensions::default)
+            .insert("webhooks".to_string(), webhooks_object());
+    }
+}
+
+/// A standalone OpenAPI document describing only the event payloads.
+pub fn webhook_document() -> openapi::OpenApi {
+    let mut document = EventSchemas::openapi();
+    document.info.title = "Classroom Borrowing API events".to_string();
+    document.info.description =
+        Some("Payloads of the events recorded under /admin/events.".to_string());
+    utoipa::Modify::modify(&WebhooksAddon, &mut document);
+    document
+}
+
+#[utoipa::path(
+    get,
+    tags = ["Webhooks"],
+    description = "OpenAPI document of the event payloads, with one webhook per event kind and the payload schemas under `components`, so integrators can validate events without the full API document.",
+    path = "/schema",
+    responses(
+        (status = 200, description = "Webhook OpenAPI document", body = Object),
+    )
+)]
+pub async fn get_webhook_schema() -> impl IntoResponse {
+    (StatusCode::OK, Json(webhook_document()))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    tags(
+        (name = "Webhooks", description = "Event payload documentation")
+    ),
+    paths(get_webhook_schema)
+)]
+pub struct WebhooksApi;
+
+pub fn webhooks_router() -> Router<AppState> {
+    Router::new().route("/schema", get(get_webhook_schema))
+}
@@ -0,0 +1,247 @@
+use nanoid::nanoid;
+use sea_orm::{
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, TransactionTrait,
+};
+
+use crate::{
+    argon_hasher::Hasher,
+    entities::{
+        announcement, classroom, key,
+        sea_orm_active_enums::{
+            AnnouncementAudience, AnnouncementCategory, ClassroomStatus, KeyStatus, Role,
+        },
+        user,
+    },
+};
+
+/// Length of the password made up for the first admin when none is given.
+const GENERATED_PASSWORD_LENGTH: usize = 20;
+
+/// The account created when a deployment has no admin yet.
+pub struct AdminSeed {
+    pub email: String,
+    pub username: String,
+    pub name: String,
+    /// Made up and reported back when `None`
+    pub password: Option<String>,
+}
+
+pub struct SeededAdmin {
+    pub email: String,
+    /// Only set when the password was made up, so it can be shown once
+    pub generated_password: Option<String>,
+}
+
+/// What `seed` created; everything that existed already is left alone.
+#[derive(Default)]
+pub struct SeedReport {
+    pub admin: Option<SeededAdmin>,
+    pub classrooms: usize,
+    pub keys: usize,
+    pub announcements: usize,
+}
+
+struct DemoClassroom {
+    name: &'static str,
+    name_en: &'static str,
+    name_zh_tw: &'static str,
+    location: &'static str,
+    capacity: i32,
+    description: &'static str,
+    keys: &'static [&'static str],
+}
+
+const DEMO_CLASSROOMS: [DemoClassroom; 2] = [
+    DemoClassroom {
+        name: "Demo Classroom 101",
+        name_en: "Demo Classroom 101",
+        name_zh_tw: "示範教室 101",
+        location: "Building A, 1F",
+        capacity: 40,
+        description: "A sample classroom; edit or delete it once real classrooms are added.",
+        keys: &["DEMO-101-1", "DEMO-101-2"],
+    },
+    DemoClassroom {
+        name: "Demo Lecture Hall 201",
+        name_en: "Demo Lecture Hall 201",
+        name_zh_tw: "示範演講廳 201",
+        location: "Building A, 2F",
+        capacity: 120,
+        description: "A sample lecture hall; edit or delete it once real classrooms are added.",
+        keys: &["DEMO-201-1"],
+    },
+];
+
+struct DemoAnnouncement {
+    title: &'static str,
+    title_zh_tw: &'static str,
+    content: &'static str,
+    content_zh_tw: &'static str,
+    category: AnnouncementCategory,
+    pinned: bool,
+}
+
+const DEMO_ANNOUNCEMENTS: [DemoAnnouncement; 2] = [
+    DemoAnnouncement {
+        title: "Welcome to classroom borrowing",
+        title_zh_tw: "歡迎使用教室借用系統",
+        content: "Browse the classrooms, pick a free slot and send a reservation. Keys are picked up once it is approved.",
+        content_zh_tw: "瀏覽教室、選擇空閒時段並送出預約，核准後即可領取鑰匙。",
+        category: AnnouncementCategory::System,
+        pinned: true,
+    },
+    DemoAnnouncement {
+        title: "Return keys on time",
+        title_zh_tw: "請準時歸還鑰匙",
+        content: "Keys not returned by the end of a reservation count as an infraction.",
+        content_zh_tw: "預約結束後未歸還鑰匙將記違規一次。",
+        category: AnnouncementCategory::Policy,
+        pinned: false,
+    },
+];
+
+/// Gives a fresh deployment its first admin and, with `demo`, a couple of
+/// classrooms with keys and announcements to try things out with. Safe to
+/// run again: the admin is only created when there is no admin, and the
+/// demo data only when there are no classrooms.
+pub async fn seed(
+    db: &DatabaseConnection,
+    hasher: &Hasher,
+    admin: AdminSeed,
+    demo: bool,
+) -> Result<SeedReport, DbErr> {
+    // Hashing is slow, so it happens before the transaction starts
+    let generated_password = admin
+        .password
+        .is_none()
+        .then(|| nanoid!(GENERATED_PASSWORD_LENGTH));
+    let password = admin
+        .password
+        .clone()
+        .or_else(|| generated_password.clone())
+        .unwrap();
+    let hashed_password = hasher
+        .hash(password)
+        .await
+        .map_err(|e| DbErr::Custom(format!("Failed to hash the admin password: {}", e)))?;
+
+    let txn = db.begin().await?;
+    let mut report = SeedReport::default();
+
+    let has_admin = user::Entity::find()
+        .filter(user::Column::Role.eq(Role::Admin))
+        .filter(user::Column::DeletedAt.is_null())
+        .count(&txn)
+        .await?
+        > 0;
+    let admin_id = if has_admin {
+        None
+    } else {
+        let created = user::ActiveModel {
+            id: Set(nanoid!()),
+            username: Set(admin.username),
+            email: Set(admin.email.clone()),
+            password: Set(hashed_password),
+            phone_number: Set(String::new()),
+            role: Set(Role::Admin),
+            created_at: NotSet,
+            updated_at: NotSet,
+            name: Set(admin.name),
+            locale: NotSet,
+            email_undeliverable_at: NotSet,
+            email_undeliverable_reason: NotSet,
+            department: NotSet,
+            student_id: NotSet,
+            deleted_at: NotSet,
+            version: NotSet,
+        }
+        .insert(&txn)
+        .await?;
+        report.admin = Some(SeededAdmin {
+            email: admin.email,
+            generated_password,
+        });
+        Some(created.id)
+    };
+
+    if demo && classroom::Entity::find().count(&txn).await? == 0 {
+        seed_demo(&txn, admin_id, &mut report).await?;
+    }
+
+    txn.commit().await?;
+    Ok(report)
+}
+
+async fn seed_demo(
+    db: &impl ConnectionTrait,
+    created_by: Option<String>,
+    report: &mut SeedReport,
+) -> Result<(), DbErr> {
+    for demo in &DEMO_CLASSROOMS {
+        let classroom = classroom::ActiveModel {
+            id: Set(nanoid!()),
+            name: Set(demo.name.to_string()),
+            location: Set(demo.location.to_string()),
+            capacity: Set(demo.capacity),
+            description: Set(demo.description.to_string()),
+            status: Set(ClassroomStatus::Available),
+            created_at: NotSet,
+            updated_at: NotSet,
+            // No photo until one is uploaded
+            photo_id: Set(String::new()),
+            status_reason: NotSet,
+            status_from: NotSet,
+            status_until: NotSet,
+            deleted_at: NotSet,
+            name_en: Set(Some(demo.name_en.to_string())),
+            name_zh_tw: Set(Some(demo.name_zh_tw.to_string())),
+            description_en: Set(Some(demo.description.to_string())),
+            description_zh_tw: NotSet,
+            requires_double_approval: Set(false),
+            version: NotSet,
+        }
+        .insert(db)
+        .await?;
+        report.classrooms += 1;
+
+        for key_number in demo.keys {
+            key::ActiveModel {
+                id: Set(nanoid!()),
+                classroom_id: Set(Some(classroom.id.clone())),
+                key_number: Set(key_number.to_string()),
+                status: Set(KeyStatus::Active),
+                cabinet_slot: Set(None),
+                version: NotSet,
+            }
+            .insert(db)
+            .await?;
+            report.keys += 1;
+        }
+    }
+
+    for demo in &DEMO_ANNOUNCEMENTS {
+        announcement::ActiveModel {
+            id: Set(nanoid!()),
+            title: Set(demo.title.to_string()),
+            content: Set(demo.content.to_string()),
+            published_at: NotSet,
+            created_by: Set(created_by.clone()),
+            emergency: Set(false),
+            pinned: Set(demo.pinned),
+            category: Set(demo.category.clone()),
+            title_en: Set(Some(demo.title.to_string())),
+            title_zh_tw: Set(Some(demo.title_zh_tw.to_string())),
+            content_en: Set(Some(demo.content.to_string())),
+            content_zh_tw: Set(Some(demo.content_zh_tw.to_string())),
+            audience: Set(AnnouncementAudience::All),
+            audience_classroom_id: Set(None),
+        }
+        .insert(db)
+        .await?;
+        report.announcements += 1;
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+#[cfg(test)]
+mod tests {
+    use sea_orm::{ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter};
+
+    use super::super::{
+        argon_hasher::{Argon2Config, Hasher},
+        entities::{announcement, classroom, key, sea_orm_active_enums::Role, user},
+        memory_mode,
+        seed::{AdminSeed, seed},
+    };
+
+    fn hasher() -> Hasher {
+        Hasher::new(Argon2Config {
+            secret_key: b"test-secret".to_vec(),
+            iterations: 1,
+            parallelism: 1,
+            memory_cost: 64,
+        })
+    }
+
+    fn admin(password: Option<&str>) -> AdminSeed {
+        AdminSeed {
+            email: "admin@example.com".to_string(),
+            username: "admin".to_string(),
+            name: "Administrator".to_string(),
+            password: password.map(str::to_string),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seed_creates_admin_and_demo_data() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let report = seed(&db, &hasher(), admin(Some("correct horse")), true)
+            .await
+            .unwrap();
+
+        let created = report.admin.unwrap();
+        assert_eq!(created.email, "admin@example.com");
+        assert_eq!(created.generated_password, None);
+        let stored = user::Entity::find()
+            .filter(user::Column::Email.eq("admin@example.com"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.role, Role::Admin);
+        assert!(
+            hasher()
+                .verify("correct horse", &stored.password)
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(report.classrooms, 2);
+        assert_eq!(report.keys, 3);
+        assert_eq!(report.announcements, 2);
+        assert_eq!(classroom::Entity::find().count(&db).await.unwrap(), 2);
+        assert_eq!(key::Entity::find().count(&db).await.unwrap(), 3);
+        let announcements = announcement::Entity::find().all(&db).await.unwrap();
+        assert!(
+            announcements
+                .iter()
+                .all(|a| a.created_by.as_deref() == Some(stored.id.as_str()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_seed_again_creates_nothing() {
+        let db = memory_mode::connect_db().await.unwrap();
+        seed(&db, &hasher(), admin(None), true).await.unwrap();
+
+        let mut other = admin(None);
+        other.email = "other@example.com".to_string();
+        other.username = "other".to_string();
+        let report = seed(&db, &hasher(), other, true).await.unwrap();
+        assert!(report.admin.is_none());
+        assert_eq!(report.classrooms + report.keys + report.announcements, 0);
+        assert_eq!(user::Entity::find().count(&db).await.unwrap(), 1);
+        assert_eq!(classroom::Entity::find().count(&db).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_generated_password_logs_in_and_demo_is_optional() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let report = seed(&db, &hasher(), admin(None), false).await.unwrap();
+
+        let password = report.admin.unwrap().generated_password.unwrap();
+        assert!(password.len() >= 16);
+        let stored = user::Entity::find().one(&db).await.unwrap().unwrap();
+        assert!(hasher().verify(&password, &stored.password).await.unwrap());
+        assert_eq!(classroom::Entity::find().count(&db).await.unwrap(), 0);
+    }
+}
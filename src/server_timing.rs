@@ -0,0 +1,171 @@
+use std::{
+    cell::RefCell,
+    fmt::Write,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{Instrument, debug, field, info_span};
+
+use crate::request_id;
+
+pub static SERVER_TIMING: HeaderName = HeaderName::from_static("server-timing");
+
+#[derive(Clone, Default)]
+pub struct ServerTimingConfig {
+    /// Return the per-request breakdown in a `Server-Timing` header. Meant for
+    /// debug environments; it tells clients how the backend is built.
+    pub expose_header: bool,
+}
+
+/// Whether `APP_ENV` names an environment where the header is on by default.
+pub fn is_debug_environment(environment: &str) -> bool {
+    matches!(
+        environment.to_ascii_lowercase().as_str(),
+        "local" | "dev" | "development"
+    )
+}
+
+/// An outside service a request may wait on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dependency {
+    Db,
+    Redis,
+    Smtp,
+    ImageService,
+}
+
+impl Dependency {
+    pub const ALL: [Dependency; 4] = [
+        Dependency::Db,
+        Dependency::Redis,
+        Dependency::Smtp,
+        Dependency::ImageService,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Dependency::Db => "db",
+            Dependency::Redis => "redis",
+            Dependency::Smtp => "smtp",
+            Dependency::ImageService => "image-service",
+        }
+    }
+}
+
+/// Time one request spent waiting on each dependency.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Timings {
+    /// Total time and number of calls, indexed like [`Dependency::ALL`]
+    spent: [(Duration, u32); 4],
+}
+
+impl Timings {
+    pub fn add(&mut self, dependency: Dependency, elapsed: Duration) {
+        let entry = &mut self.spent[dependency as usize];
+        entry.0 += elapsed;
+        entry.1 += 1;
+    }
+
+    pub fn get(&self, dependency: Dependency) -> (Duration, u32) {
+        self.spent[dependency as usize]
+    }
+
+    /// `Server-Timing` header value listing the dependencies that were called
+    /// and the total, in milliseconds.
+    pub fn header_value(&self, total: Duration) -> String {
+        let mut value = String::new();
+        for dependency in Dependency::ALL {
+            let (spent, calls) = self.get(dependency);
+            if calls > 0 {
+                let _ = write!(
+                    value,
+                    "{};dur={:.1};desc=\"{} calls\", ",
+                    dependency.name(),
+                    millis(spent),
+                    calls
+                );
+            }
+        }
+        let _ = write!(value, "total;dur={:.1}", millis(total));
+        value
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+tokio::task_local! {
+    static TIMINGS: RefCell<Timings>;
+}
+
+/// Adds `elapsed` to the current request's timings. Does nothing outside a
+/// request, e.g. in background jobs.
+pub fn record(dependency: Dependency, elapsed: Duration) {
+    let _ = TIMINGS.try_with(|timings| timings.borrow_mut().add(dependency, elapsed));
+}
+
+/// Runs `future`, counting the time it takes against `dependency`.
+pub async fn measure<F: Future>(dependency: Dependency, future: F) -> F::Output {
+    let start = Instant::now();
+    let output = future.await;
+    record(dependency, start.elapsed());
+    output
+}
+
+/// Collects the timings of each request, logs them on the request's span and
+/// returns them in a `Server-Timing` header when enabled.
+pub async fn track(State(config): State<ServerTimingConfig>, req: Request, next: Next) -> Response {
+    let span = info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+        request_id = request_id::current().unwrap_or_default(),
+        total_ms = field::Empty,
+        db_ms = field::Empty,
+        redis_ms = field::Empty,
+        smtp_ms = field::Empty,
+        image_service_ms = field::Empty,
+    );
+    let start = Instant::now();
+    let (mut response, timings) = TIMINGS
+        .scope(
+            RefCell::new(Timings::default()),
+            async {
+                let response = next.run(req).await;
+                (response, TIMINGS.with(|timings| timings.borrow().clone()))
+            }
+            .instrument(span.clone()),
+        )
+        .await;
+    let total = start.elapsed();
+
+    span.record("total_ms", millis(total));
+    for dependency in Dependency::ALL {
+        let (spent, calls) = timings.get(dependency);
+        if calls > 0 {
+            let field = match dependency {
+                Dependency::Db => "db_ms",
+                Dependency::Redis => "redis_ms",
+                Dependency::Smtp => "smtp_ms",
+                Dependency::ImageService => "image_service_ms",
+            };
+            span.record(field, millis(spent));
+        }
+    }
+    span.in_scope(|| debug!("Request finished"));
+
+    if config.expose_header
+        && let Ok(value) = HeaderValue::from_str(&timings.header_value(total))
+    {
+        response.headers_mut().insert(SERVER_TIMING.clone(), value);
+    }
+    response
+}
@@ -0,0 +1,43 @@
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::super::server_timing::{Dependency, Timings, is_debug_environment};
+
+    #[test]
+    fn debug_environments() {
+        assert!(is_debug_environment("local"));
+        assert!(is_debug_environment("Development"));
+        assert!(!is_debug_environment("production"));
+        assert!(!is_debug_environment("staging"));
+    }
+
+    #[test]
+    fn adds_up_calls_per_dependency() {
+        let mut timings = Timings::default();
+        timings.add(Dependency::Db, Duration::from_millis(3));
+        timings.add(Dependency::Db, Duration::from_millis(4));
+        timings.add(Dependency::Smtp, Duration::from_millis(120));
+        assert_eq!(timings.get(Dependency::Db), (Duration::from_millis(7), 2));
+        assert_eq!(timings.get(Dependency::Redis), (Duration::ZERO, 0));
+    }
+
+    #[test]
+    fn header_lists_called_dependencies_and_total() {
+        let mut timings = Timings::default();
+        timings.add(Dependency::Db, Duration::from_micros(12_340));
+        timings.add(Dependency::ImageService, Duration::from_millis(250));
+        assert_eq!(
+            timings.header_value(Duration::from_millis(300)),
+            "db;dur=12.3;desc=\"1 calls\", image-service;dur=250.0;desc=\"1 calls\", total;dur=300.0"
+        );
+    }
+
+    #[test]
+    fn header_without_calls_has_only_total() {
+        assert_eq!(
+            Timings::default().header_value(Duration::from_micros(1500)),
+            "total;dur=1.5"
+        );
+    }
+}
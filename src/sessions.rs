@@ -0,0 +1,82 @@
+use redis::{AsyncCommands, RedisError};
+use tower_sessions::session::Id;
+use tracing::warn;
+
+use crate::{
+    cache::CacheKey, entities::user, login_system::AuthSession, redis_breaker::RedisConnection,
+};
+
+/// Redis set holding the IDs of a user's sessions in the session store, so
+/// they can be revoked together.
+pub fn user_sessions_key(user_id: &str) -> String {
+    format!("user_sessions_{}", user_id)
+}
+
+/// Logs `user` into `auth_session` and records the new session under the
+/// user. The session is saved right away because its ID is only assigned on
+/// save.
+pub async fn login(
+    auth_session: &mut AuthSession,
+    redis: &mut RedisConnection,
+    user: &user::Model,
+) -> Result<(), String> {
+    auth_session
+        .login(user)
+        .await
+        .map_err(|e| format!("Failed to log in: {}", e))?;
+    auth_session
+        .session
+        .save()
+        .await
+        .map_err(|e| format!("Failed to save session: {}", e))?;
+    if let Some(id) = auth_session.session.id() {
+        // Best effort: an untracked session still ends when the password
+        // changes, since its auth hash no longer matches
+        let result: Result<(), RedisError> = redis
+            .sadd(user_sessions_key(&user.id), id.to_string())
+            .await;
+        if let Err(e) = result {
+            warn!("Failed to track session for user {}: {}", user.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Drops the current session from its user's tracked sessions, before it is
+/// logged out.
+pub async fn forget(auth_session: &AuthSession, redis: &mut RedisConnection) {
+    let (Some(user), Some(id)) = (&auth_session.user, auth_session.session.id()) else {
+        return;
+    };
+    let result: Result<(), RedisError> = redis
+        .srem(user_sessions_key(&user.id), id.to_string())
+        .await;
+    if let Err(e) = result {
+        warn!("Failed to untrack session for user {}: {}", user.id, e);
+    }
+}
+
+/// Deletes every tracked session of a user from the session store except
+/// `keep`, and drops the cached user so the next request reloads it.
+/// Returns how many sessions were deleted.
+pub async fn revoke_all(
+    redis: &mut RedisConnection,
+    user_id: &str,
+    keep: Option<Id>,
+) -> Result<usize, RedisError> {
+    let keep = keep.map(|id| id.to_string());
+    let set_key = user_sessions_key(user_id);
+    let session_ids: Vec<String> = redis.smembers(&set_key).await?;
+    let revoked: Vec<&String> = session_ids
+        .iter()
+        .filter(|id| Some(*id) != keep.as_ref())
+        .collect();
+    if !revoked.is_empty() {
+        redis.del::<_, ()>(&revoked).await?;
+        redis.srem::<_, _, ()>(&set_key, &revoked).await?;
+    }
+    redis
+        .del::<_, ()>(CacheKey::User(user_id).to_string())
+        .await?;
+    Ok(revoked.len())
+}
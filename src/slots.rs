@@ -0,0 +1,105 @@
+use chrono::{Duration, Timelike};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::availability::campus_offset;
+
+/// What happens to a requested time that is not on a slot boundary.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlotPolicy {
+    /// Widen the booking to the enclosing slots and report the change
+    #[default]
+    Round,
+    /// Refuse the request
+    Reject,
+}
+
+impl SlotPolicy {
+    pub fn from_setting(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "round" => Ok(Self::Round),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("unknown slot policy '{}'", other)),
+        }
+    }
+}
+
+/// Reservations start and end on multiples of `granularity_minutes` past
+/// midnight campus time. `None` accepts any minute.
+#[derive(Clone, Default)]
+pub struct SlotConfig {
+    pub granularity_minutes: Option<u32>,
+    pub policy: SlotPolicy,
+}
+
+/// Reported back when the requested times were moved onto slot boundaries.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct TimeAdjustment {
+    pub granularity_minutes: u32,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub requested_start_time: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub requested_end_time: DateTimeWithTimeZone,
+}
+
+fn on_boundary(t: DateTimeWithTimeZone, granularity_minutes: u32) -> bool {
+    let local = t.with_timezone(&campus_offset());
+    local.nanosecond() == 0
+        && (local.num_seconds_from_midnight() as i64) % (granularity_minutes as i64 * 60) == 0
+}
+
+fn floor(t: DateTimeWithTimeZone, granularity_minutes: u32) -> DateTimeWithTimeZone {
+    let local = t.with_timezone(&campus_offset());
+    let truncated = local.with_nanosecond(0).unwrap();
+    let seconds = truncated.num_seconds_from_midnight() as i64;
+    truncated - Duration::seconds(seconds % (granularity_minutes as i64 * 60))
+}
+
+/// Fits `[start, end)` to the slot grid. Under [`SlotPolicy::Round`] the start
+/// moves back and the end forward to the nearest boundaries, so the booking
+/// still covers the requested time; under [`SlotPolicy::Reject`] off-grid
+/// times are an error.
+pub fn align(
+    config: &SlotConfig,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<
+    (
+        DateTimeWithTimeZone,
+        DateTimeWithTimeZone,
+        Option<TimeAdjustment>,
+    ),
+    String,
+> {
+    let Some(granularity) = config.granularity_minutes.filter(|g| *g > 0) else {
+        return Ok((start, end, None));
+    };
+    if on_boundary(start, granularity) && on_boundary(end, granularity) {
+        return Ok((start, end, None));
+    }
+    if config.policy == SlotPolicy::Reject {
+        return Err(format!(
+            "Reservations must start and end on {}-minute boundaries",
+            granularity
+        ));
+    }
+
+    let aligned_start = floor(start, granularity);
+    let aligned_end = if on_boundary(end, granularity) {
+        end.with_timezone(&campus_offset())
+    } else {
+        floor(end, granularity) + Duration::minutes(granularity as i64)
+    };
+    Ok((
+        aligned_start,
+        aligned_end,
+        Some(TimeAdjustment {
+            granularity_minutes: granularity,
+            requested_start_time: start,
+            requested_end_time: end,
+        }),
+    ))
+}
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+    use super::super::slots::{SlotConfig, SlotPolicy, align};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn half_hours(policy: SlotPolicy) -> SlotConfig {
+        SlotConfig {
+            granularity_minutes: Some(30),
+            policy,
+        }
+    }
+
+    #[test]
+    fn test_no_granularity_keeps_times() {
+        let (start, end, adjustment) = align(
+            &SlotConfig::default(),
+            dt("2025-03-10T10:07:00+08:00"),
+            dt("2025-03-10T11:13:00+08:00"),
+        )
+        .unwrap();
+        assert_eq!(start, dt("2025-03-10T10:07:00+08:00"));
+        assert_eq!(end, dt("2025-03-10T11:13:00+08:00"));
+        assert_eq!(adjustment, None);
+    }
+
+    #[test]
+    fn test_times_on_the_grid_are_not_adjusted() {
+        let (_, _, adjustment) = align(
+            &half_hours(SlotPolicy::Reject),
+            dt("2025-03-10T10:30:00+08:00"),
+            dt("2025-03-10T12:00:00+08:00"),
+        )
+        .unwrap();
+        assert_eq!(adjustment, None);
+    }
+
+    #[test]
+    fn test_rounding_widens_to_enclosing_slots() {
+        let (start, end, adjustment) = align(
+            &half_hours(SlotPolicy::Round),
+            dt("2025-03-10T10:10:00+08:00"),
+            dt("2025-03-10T11:31:00+08:00"),
+        )
+        .unwrap();
+        assert_eq!(start, dt("2025-03-10T10:00:00+08:00"));
+        assert_eq!(end, dt("2025-03-10T12:00:00+08:00"));
+        let adjustment = adjustment.unwrap();
+        assert_eq!(adjustment.granularity_minutes, 30);
+        assert_eq!(
+            adjustment.requested_start_time,
+            dt("2025-03-10T10:10:00+08:00")
+        );
+    }
+
+    #[test]
+    fn test_grid_follows_campus_time() {
+        // 02:10 UTC is 10:10 campus time
+        let (start, end, _) = align(
+            &half_hours(SlotPolicy::Round),
+            dt("2025-03-10T02:10:00+00:00"),
+            dt("2025-03-10T03:00:00+00:00"),
+        )
+        .unwrap();
+        assert_eq!(start, dt("2025-03-10T10:00:00+08:00"));
+        assert_eq!(end, dt("2025-03-10T11:00:00+08:00"));
+    }
+
+    #[test]
+    fn test_reject_policy_refuses_off_grid_times() {
+        assert!(
+            align(
+                &half_hours(SlotPolicy::Reject),
+                dt("2025-03-10T10:00:00+08:00"),
+                dt("2025-03-10T10:45:00+08:00"),
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_slot_policy_from_setting() {
+        assert_eq!(SlotPolicy::from_setting("Round"), Ok(SlotPolicy::Round));
+        assert_eq!(SlotPolicy::from_setting("reject"), Ok(SlotPolicy::Reject));
+        assert!(SlotPolicy::from_setting("nearest").is_err());
+    }
+}
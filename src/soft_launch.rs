@@ -0,0 +1,75 @@
+/// Who may sign up while the service is piloted, through registration or an
+/// invitation. Empty lists let everyone in.
+#[derive(Clone, Debug, Default)]
+pub struct SoftLaunchConfig {
+    /// Lowercase email domains without the `@`, e.g.
+    /// `mail.university.edu.tw`. Subdomains must be listed on their own.
+    pub email_domains: Vec<String>,
+    /// A student ID given at registration must start with one of these
+    pub student_id_prefixes: Vec<String>,
+}
+
+impl SoftLaunchConfig {
+    /// Parses comma-separated email domains, with or without a leading `@`.
+    pub fn parse_domains(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(|domain| domain.trim().trim_start_matches('@').to_ascii_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect()
+    }
+
+    /// Parses comma-separated student ID prefixes.
+    pub fn parse_prefixes(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|prefix| !prefix.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Why a new account is turned away during the soft launch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoftLaunchError {
+    EmailDomain,
+    StudentIdPrefix,
+}
+
+impl SoftLaunchError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::EmailDomain => "Registration is not open to this email domain yet",
+            Self::StudentIdPrefix => "Registration is not open to this student ID yet",
+        }
+    }
+}
+
+/// Checks a new account's email and, when one is given, student ID against
+/// `config`.
+pub fn check(
+    config: &SoftLaunchConfig,
+    email: &str,
+    student_id: Option<&str>,
+) -> Result<(), SoftLaunchError> {
+    if !config.email_domains.is_empty() {
+        let domain = email
+            .trim()
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_ascii_lowercase());
+        if !domain.is_some_and(|domain| config.email_domains.contains(&domain)) {
+            return Err(SoftLaunchError::EmailDomain);
+        }
+    }
+    if !config.student_id_prefixes.is_empty()
+        && let Some(student_id) = student_id.map(str::trim).filter(|id| !id.is_empty())
+        && !config
+            .student_id_prefixes
+            .iter()
+            .any(|prefix| student_id.starts_with(prefix.as_str()))
+    {
+        return Err(SoftLaunchError::StudentIdPrefix);
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod tests {
+    use super::super::soft_launch::{SoftLaunchConfig, SoftLaunchError, check};
+
+    fn pilot() -> SoftLaunchConfig {
+        SoftLaunchConfig {
+            email_domains: SoftLaunchConfig::parse_domains(" @Mail.University.edu.tw, ,gms.edu "),
+            student_id_prefixes: SoftLaunchConfig::parse_prefixes("411, 611"),
+        }
+    }
+
+    #[test]
+    fn test_no_lists_allow_everyone() {
+        let config = SoftLaunchConfig::default();
+        assert_eq!(
+            check(&config, "someone@example.com", Some("01104101")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_email_domain_must_be_listed_exactly() {
+        let config = pilot();
+        assert_eq!(config.email_domains, ["mail.university.edu.tw", "gms.edu"]);
+        assert_eq!(check(&config, "a@MAIL.university.edu.tw", None), Ok(()));
+        assert_eq!(
+            check(&config, "a@sub.gms.edu", None),
+            Err(SoftLaunchError::EmailDomain)
+        );
+        assert_eq!(
+            check(&config, "a@evilgms.edu", None),
+            Err(SoftLaunchError::EmailDomain)
+        );
+        assert_eq!(
+            check(&config, "not-an-email", None),
+            Err(SoftLaunchError::EmailDomain)
+        );
+    }
+
+    #[test]
+    fn test_student_id_prefix_only_checks_given_ids() {
+        let config = pilot();
+        assert_eq!(check(&config, "a@gms.edu", Some("41100001")), Ok(()));
+        assert_eq!(check(&config, "a@gms.edu", Some(" ")), Ok(()));
+        assert_eq!(
+            check(&config, "a@gms.edu", Some("51100001")),
+            Err(SoftLaunchError::StudentIdPrefix)
+        );
+    }
+}
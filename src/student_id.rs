@@ -0,0 +1,79 @@
+use regex::Regex;
+
+use crate::{entities::sea_orm_active_enums::Role, utils::check_student_id};
+
+/// How student IDs given at registration are checked.
+#[derive(Clone, Debug, Default)]
+pub enum StudentIdValidator {
+    /// The original campus format, see [`check_student_id`]
+    #[default]
+    Default,
+    /// The whole ID must match the expression
+    Pattern(Regex),
+    /// Any non-empty ID is accepted
+    Disabled,
+}
+
+impl StudentIdValidator {
+    /// Builds a validator from `kind` (`default`, `pattern` or `none`). A
+    /// `pattern` validator needs `pattern`, which is anchored at both ends.
+    pub fn from_setting(kind: &str, pattern: Option<&str>) -> Result<Self, String> {
+        match kind.trim().to_ascii_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "none" => Ok(Self::Disabled),
+            "pattern" => {
+                let pattern = pattern.ok_or("a pattern validator needs a pattern")?;
+                Regex::new(&format!("^(?:{})$", pattern))
+                    .map(Self::Pattern)
+                    .map_err(|e| format!("invalid pattern: {}", e))
+            }
+            other => Err(format!("unknown validator '{}'", other)),
+        }
+    }
+
+    pub fn validate(&self, student_id: &str) -> bool {
+        match self {
+            Self::Default => check_student_id(student_id),
+            Self::Pattern(regex) => regex.is_match(student_id),
+            Self::Disabled => !student_id.trim().is_empty(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StudentIdConfig {
+    pub validator: StudentIdValidator,
+    /// Roles that may register without a student ID
+    pub exempt_roles: Vec<Role>,
+}
+
+/// Why a student ID is not acceptable for an account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StudentIdError {
+    Missing,
+    Invalid,
+}
+
+impl StudentIdError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Missing => "Student ID is required",
+            Self::Invalid => "Invalid student ID",
+        }
+    }
+}
+
+/// Checks the student ID of a new account with `role` against `config`.
+/// Exempt roles may leave it out, but one that is given must still be valid.
+pub fn check(
+    config: &StudentIdConfig,
+    role: &Role,
+    student_id: Option<&str>,
+) -> Result<(), StudentIdError> {
+    match student_id.map(str::trim).filter(|id| !id.is_empty()) {
+        Some(id) if config.validator.validate(id) => Ok(()),
+        Some(_) => Err(StudentIdError::Invalid),
+        None if config.exempt_roles.contains(role) => Ok(()),
+        None => Err(StudentIdError::Missing),
+    }
+}
@@ -0,0 +1,54 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::sea_orm_active_enums::Role;
+    use super::super::student_id::{StudentIdConfig, StudentIdError, StudentIdValidator, check};
+
+    #[test]
+    fn test_default_validator_keeps_campus_format() {
+        let validator = StudentIdValidator::from_setting("default", None).unwrap();
+        assert!(validator.validate("01104101"));
+        assert!(!validator.validate("S1234567"));
+    }
+
+    #[test]
+    fn test_pattern_must_match_whole_id() {
+        let validator = StudentIdValidator::from_setting("pattern", Some(r"[A-Z]\d{7}")).unwrap();
+        assert!(validator.validate("S1234567"));
+        assert!(!validator.validate("xS1234567"));
+        assert!(!validator.validate("S12345678"));
+    }
+
+    #[test]
+    fn test_from_setting_rejects_bad_settings() {
+        assert!(StudentIdValidator::from_setting("pattern", None).is_err());
+        assert!(StudentIdValidator::from_setting("pattern", Some("(")).is_err());
+        assert!(StudentIdValidator::from_setting("ldap", None).is_err());
+    }
+
+    #[test]
+    fn test_exempt_roles_may_omit_student_id() {
+        let config = StudentIdConfig {
+            validator: StudentIdValidator::Disabled,
+            exempt_roles: vec![Role::Admin],
+        };
+        assert_eq!(check(&config, &Role::Admin, None), Ok(()));
+        assert_eq!(check(&config, &Role::Admin, Some("  ")), Ok(()));
+        assert_eq!(
+            check(&config, &Role::User, None),
+            Err(StudentIdError::Missing)
+        );
+        assert_eq!(check(&config, &Role::User, Some("anything")), Ok(()));
+    }
+
+    #[test]
+    fn test_given_student_id_is_validated_even_when_exempt() {
+        let config = StudentIdConfig {
+            validator: StudentIdValidator::Default,
+            exempt_roles: vec![Role::User],
+        };
+        assert_eq!(
+            check(&config, &Role::User, Some("not-an-id")),
+            Err(StudentIdError::Invalid)
+        );
+    }
+}
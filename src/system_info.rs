@@ -0,0 +1,152 @@
+use std::{sync::OnceLock, time::Instant};
+
+use chrono::Utc;
+use sea_orm::{
+    ConnectionTrait, DatabaseConnection, DbBackend, Statement, prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset, config::Config, datetime::DatetimeFormat,
+    redis_breaker::RedisConnection,
+};
+
+/// Commit the binary was built from, or `unknown`; see `build.rs`.
+pub const GIT_SHA: &str = env!("GIT_SHA");
+
+static STARTED: OnceLock<(Instant, DateTimeWithTimeZone)> = OnceLock::new();
+
+/// Records when the server started; uptime counts from the first call.
+pub fn mark_started() {
+    STARTED.get_or_init(|| (Instant::now(), Utc::now().with_timezone(&campus_offset())));
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub git_sha: &'static str,
+    /// `release` or `debug`
+    pub profile: &'static str,
+    /// Operating system and CPU architecture, e.g. `linux-x86_64`
+    pub target: String,
+}
+
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        git_sha: GIT_SHA,
+        profile: if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        },
+        target: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+    }
+}
+
+/// Switches that change how the server behaves.
+#[derive(Serialize, ToSchema)]
+pub struct Flags {
+    pub in_memory: bool,
+    pub debug_routes: bool,
+    pub server_timing_header: bool,
+    /// `rfc3339` or `legacy`
+    pub datetime_format: &'static str,
+    pub pickup_codes: bool,
+    pub cors_allowed_origins: Vec<String>,
+    pub session_cookie_secure: bool,
+}
+
+pub fn flags(config: &Config) -> Flags {
+    Flags {
+        in_memory: config.in_memory,
+        debug_routes: config.debug.enabled,
+        server_timing_header: config.server_timing.expose_header,
+        datetime_format: match config.datetime.format {
+            DatetimeFormat::Rfc3339 => "rfc3339",
+            DatetimeFormat::Legacy => "legacy",
+        },
+        pickup_codes: config.pickup.enabled,
+        cors_allowed_origins: config
+            .cors
+            .allowed_origins
+            .iter()
+            .filter_map(|origin| origin.to_str().ok().map(str::to_string))
+            .collect(),
+        session_cookie_secure: config.session_cookie.secure,
+    }
+}
+
+/// Versions reported by the servers the API is connected to; `null` when
+/// one cannot be reached.
+#[derive(Serialize, ToSchema)]
+pub struct DependencyVersions {
+    pub database: Option<String>,
+    pub redis: Option<String>,
+}
+
+pub async fn database_version(db: &DatabaseConnection) -> Option<String> {
+    let backend = db.get_database_backend();
+    let sql = match backend {
+        DbBackend::Sqlite => "SELECT 'SQLite ' || sqlite_version()",
+        _ => "SELECT version()",
+    };
+    db.query_one_raw(Statement::from_string(backend, sql))
+        .await
+        .ok()??
+        .try_get_by_index(0)
+        .ok()
+}
+
+/// `redis_version` from the output of `INFO server`.
+pub fn parse_redis_version(info: &str) -> Option<String> {
+    info.lines()
+        .find_map(|line| line.strip_prefix("redis_version:"))
+        .map(|version| version.trim().to_string())
+}
+
+pub async fn redis_version(redis: &RedisConnection, in_memory: bool) -> Option<String> {
+    if in_memory {
+        return Some("in-process store".to_string());
+    }
+    if redis.is_degraded() {
+        return None;
+    }
+    let mut redis = redis.clone();
+    let info: String = redis::cmd("INFO")
+        .arg("server")
+        .query_async(&mut redis)
+        .await
+        .ok()?;
+    parse_redis_version(&info)
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct SystemInfo {
+    pub build: BuildInfo,
+    pub flags: Flags,
+    pub dependencies: DependencyVersions,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub started_at: DateTimeWithTimeZone,
+    pub uptime_seconds: u64,
+}
+
+pub async fn system_info(
+    db: &DatabaseConnection,
+    redis: &RedisConnection,
+    config: &Config,
+) -> SystemInfo {
+    mark_started();
+    let (started, started_at) = *STARTED.get().unwrap();
+    let (database, redis) =
+        tokio::join!(database_version(db), redis_version(redis, config.in_memory));
+    SystemInfo {
+        build: build_info(),
+        flags: flags(config),
+        dependencies: DependencyVersions { database, redis },
+        started_at,
+        uptime_seconds: started.elapsed().as_secs(),
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        config::{Config, Source},
+        memory_mode,
+        memory_redis::MemoryRedis,
+        redis_breaker::RedisConnection,
+        system_info::{parse_redis_version, system_info},
+    };
+
+    #[test]
+    fn test_redis_version_is_read_from_info() {
+        let info = "# Server\r\nredis_version:7.2.4\r\nredis_git_sha1:00000000\r\n";
+        assert_eq!(parse_redis_version(info), Some("7.2.4".to_string()));
+        assert_eq!(parse_redis_version("# Server\r\n"), None);
+    }
+
+    #[tokio::test]
+    async fn test_system_info_in_memory() {
+        let config = Config::from_source(&Source::from_toml("in_memory = true").unwrap()).unwrap();
+        let db = memory_mode::connect_db().await.unwrap();
+        let redis = RedisConnection::in_memory(MemoryRedis::default());
+
+        let info = system_info(&db, &redis, &config).await;
+        assert_eq!(info.build.version, env!("CARGO_PKG_VERSION"));
+        assert!(!info.build.git_sha.is_empty());
+        assert!(info.flags.in_memory);
+        assert!(
+            info.dependencies
+                .database
+                .is_some_and(|version| version.starts_with("SQLite 3"))
+        );
+        assert!(info.dependencies.redis.is_some());
+    }
+}
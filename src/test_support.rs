@@ -0,0 +1,168 @@
+//! Helpers for tests that drive the whole app over HTTP, backed by the
+//! in-memory database and Redis of [`memory_mode`], and fixtures for tests
+//! of plain functions.
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Router,
+    body::{Body, to_bytes},
+    extract::connect_info::MockConnectInfo,
+    http::{Request, StatusCode, header},
+    response::Response,
+};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, prelude::DateTimeWithTimeZone};
+use serde_json::{Value, json};
+use tower::ServiceExt;
+use tower_sessions::MemoryStore;
+
+use crate::{
+    AppState, api_doc, app,
+    argon_hasher::Hasher,
+    cache::{CacheService, RedisCache},
+    config::{Config, Source},
+    entities::{
+        classroom, reservation,
+        sea_orm_active_enums::{ClassroomStatus, ReservationStatus, Role},
+        user,
+    },
+    live_events::LiveEvents,
+    login_system::{RedisUserCache, UserCache},
+    memory_mode,
+    memory_redis::MemoryRedis,
+    redis_breaker::RedisConnection,
+};
+
+/// Password of every user added with [`add_user`].
+pub const PASSWORD: &str = "secret";
+
+pub async fn state() -> AppState {
+    let redis = RedisConnection::in_memory(MemoryRedis::default());
+    let user_cache: Arc<dyn UserCache> = Arc::new(RedisUserCache::new(redis.clone()));
+    let cache: Arc<dyn CacheService> = Arc::new(RedisCache::new(redis.clone()));
+    let config = Config::from_source(
+        &Source::from_toml(
+            r#"
+            in_memory = true
+            argon2 = { iterations = 1, parallelism = 1, memory_kib = 64 }
+            "#,
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    AppState {
+        db: memory_mode::connect_db().await.unwrap(),
+        redis,
+        hasher: Hasher::new(config.argon2.clone()),
+        user_cache,
+        cache,
+        live: LiveEvents::default(),
+        config: Arc::new(config),
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    app(state, MemoryStore::default(), api_doc())
+        .layer(MockConnectInfo(SocketAddr::from(([127, 0, 0, 1], 4000))))
+}
+
+pub fn login(email: &str, password: &str) -> Request<Body> {
+    Request::post("/user/login")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "email": email, "password": password }).to_string(),
+        ))
+        .unwrap()
+}
+
+pub async fn json(response: Response) -> Value {
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    serde_json::from_slice(&bytes).unwrap()
+}
+
+/// Adds a user who signs in with `email` and [`PASSWORD`].
+pub async fn add_user(state: &AppState, id: &str, email: &str, role: Role) -> user::Model {
+    user::ActiveModel {
+        id: Set(id.to_string()),
+        username: Set(id.to_string()),
+        name: Set(id.to_string()),
+        email: Set(email.to_string()),
+        password: Set(state.hasher.hash(PASSWORD).await.unwrap()),
+        phone_number: Set("0912345678".to_string()),
+        role: Set(role),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await
+    .unwrap()
+}
+
+/// Adds an available classroom named after its ID.
+pub async fn add_classroom(state: &AppState, id: &str) -> classroom::Model {
+    classroom::ActiveModel {
+        id: Set(id.to_string()),
+        name: Set(id.to_string()),
+        location: Set("Building A".to_string()),
+        capacity: Set(40),
+        description: Set(String::new()),
+        status: Set(ClassroomStatus::Available),
+        photo_id: Set(String::new()),
+        requires_double_approval: Set(false),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await
+    .unwrap()
+}
+
+/// Signs in a user added with [`add_user`] and returns the session cookie.
+pub async fn sign_in(app: &Router, email: &str) -> String {
+    let response = app.clone().oneshot(login(email, PASSWORD)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response.headers()[header::SET_COOKIE]
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string()
+}
+
+/// A JSON request sent with the session `cookie`.
+pub fn send(method: &str, uri: &str, cookie: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::COOKIE, cookie)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+/// A pending reservation of classroom `c1` by user `u1`, created on
+/// 2025-03-01. Tests override what they look at with struct update syntax.
+pub fn reservation_at(id: &str, start: &str, end: &str) -> reservation::Model {
+    let dt = |s: &str| -> DateTimeWithTimeZone { s.parse().unwrap() };
+    reservation::Model {
+        id: id.to_string(),
+        user_id: Some("u1".to_string()),
+        classroom_id: Some("c1".to_string()),
+        purpose: "Study group".to_string(),
+        start_time: dt(start),
+        end_time: dt(end),
+        approved_by: None,
+        reject_reason: None,
+        cancel_reason: None,
+        status: ReservationStatus::Pending,
+        flagged_for_review: false,
+        flag_reason: None,
+        checked_in_at: None,
+        created_at: dt("2025-03-01T09:00:00+08:00"),
+        reviewed_at: None,
+        event_name: None,
+        version: 1,
+        group_id: None,
+        guest_name: None,
+        guest_email: None,
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use chrono::{Datelike, Days, NaiveDate, NaiveTime};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    prelude::DateTimeWithTimeZone,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    availability::campus_offset,
+    entities::{course_session, reservation, sea_orm_active_enums::ReservationStatus},
+};
+
+/// An approved reservation that overlaps a meeting of a course on the
+/// timetable.
+#[derive(Serialize, ToSchema, Clone)]
+pub struct Collision {
+    pub reservation: reservation::Model,
+    pub course_session_id: String,
+    pub course_name: String,
+    /// First meeting of the course that overlaps the reservation
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub course_start: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub course_end: DateTimeWithTimeZone,
+}
+
+/// Meetings of `session` that overlap `[from, to)`, in campus time. Weekdays
+/// are numbered from Monday (0) to Sunday (6) like opening hours, and the
+/// term runs from `term_start` to `term_end` inclusive.
+pub fn occurrences(
+    session: &course_session::Model,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+) -> Vec<(DateTimeWithTimeZone, DateTimeWithTimeZone)> {
+    let offset = campus_offset();
+    let first_day = from
+        .with_timezone(&offset)
+        .date_naive()
+        .max(session.term_start);
+    let last_day = to.with_timezone(&offset).date_naive().min(session.term_end);
+
+    let mut meetings = Vec::new();
+    let mut day = first_day;
+    while day <= last_day {
+        if day.weekday().num_days_from_monday() as i16 == session.weekday {
+            let start = meeting_time(day, session.start_time);
+            let end = meeting_time(day, session.end_time);
+            if start < to && end > from {
+                meetings.push((start, end));
+            }
+        }
+        day = match day.checked_add_days(Days::new(1)) {
+            Some(next) => next,
+            None => break,
+        };
+    }
+    meetings
+}
+
+fn meeting_time(day: NaiveDate, time: NaiveTime) -> DateTimeWithTimeZone {
+    day.and_time(time)
+        .and_local_timezone(campus_offset())
+        .unwrap()
+}
+
+/// The first course meeting that overlaps `reservation`, if any.
+pub fn find_collision(
+    sessions: &[course_session::Model],
+    reservation: &reservation::Model,
+) -> Option<Collision> {
+    sessions
+        .iter()
+        .filter(|s| reservation.classroom_id.as_deref() == Some(s.classroom_id.as_str()))
+        .filter_map(|s| {
+            occurrences(s, reservation.start_time, reservation.end_time)
+                .into_iter()
+                .next()
+                .map(|(start, end)| (s, start, end))
+        })
+        .min_by_key(|(_, start, _)| *start)
+        .map(|(session, start, end)| Collision {
+            reservation: reservation.clone(),
+            course_session_id: session.id.clone(),
+            course_name: session.course_name.clone(),
+            course_start: start,
+            course_end: end,
+        })
+}
+
+/// Course sessions of a classroom that meet during `[start, end)`.
+pub async fn sessions_in_window(
+    db: &DatabaseConnection,
+    classroom_id: &str,
+    start: DateTimeWithTimeZone,
+    end: DateTimeWithTimeZone,
+) -> Result<Vec<course_session::Model>, DbErr> {
+    let sessions = course_session::Entity::find()
+        .filter(course_session::Column::ClassroomId.eq(classroom_id))
+        .filter(
+            course_session::Column::TermEnd.gte(start.with_timezone(&campus_offset()).date_naive()),
+        )
+        .filter(
+            course_session::Column::TermStart.lte(end.with_timezone(&campus_offset()).date_naive()),
+        )
+        .all(db)
+        .await?;
+    Ok(sessions
+        .into_iter()
+        .filter(|s| !occurrences(s, start, end).is_empty())
+        .collect())
+}
+
+/// Approved reservations from `from` onwards that collide with the timetable,
+/// optionally limited to some classrooms. Sorted by reservation start.
+pub async fn find_collisions(
+    db: &DatabaseConnection,
+    classroom_ids: Option<Vec<String>>,
+    from: DateTimeWithTimeZone,
+) -> Result<Vec<Collision>, DbErr> {
+    let mut session_query = course_session::Entity::find().filter(
+        course_session::Column::TermEnd.gte(from.with_timezone(&campus_offset()).date_naive()),
+    );
+    if let Some(ids) = &classroom_ids {
+        session_query =
+            session_query.filter(course_session::Column::ClassroomId.is_in(ids.clone()));
+    }
+    let sessions = session_query.all(db).await?;
+    if sessions.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut by_classroom: BTreeMap<String, Vec<course_session::Model>> = BTreeMap::new();
+    for session in sessions {
+        by_classroom
+            .entry(session.classroom_id.clone())
+            .or_default()
+            .push(session);
+    }
+
+    let reservations = reservation::Entity::find()
+        .filter(reservation::Column::ClassroomId.is_in(by_classroom.keys().cloned()))
+        .filter(reservation::Column::Status.eq(ReservationStatus::Approved))
+        .filter(reservation::Column::EndTime.gt(from))
+        .order_by_asc(reservation::Column::StartTime)
+        .all(db)
+        .await?;
+
+    Ok(reservations
+        .iter()
+        .filter_map(|r| {
+            let sessions = by_classroom.get(r.classroom_id.as_deref()?)?;
+            find_collision(sessions, r)
+        })
+        .collect())
+}
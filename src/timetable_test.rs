@@ -0,0 +1,106 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{
+        course_session, reservation, sea_orm_active_enums::ReservationStatus,
+    };
+    use super::super::test_support::reservation_at;
+    use super::super::timetable::{find_collision, occurrences};
+    use chrono::{NaiveDate, NaiveTime};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    /// Mondays 10:00-12:00 from 3 March to 30 June 2025
+    fn monday_course() -> course_session::Model {
+        course_session::Model {
+            id: "c1".to_string(),
+            classroom_id: "room-1".to_string(),
+            course_name: "Software Engineering".to_string(),
+            weekday: 0,
+            start_time: NaiveTime::from_hms_opt(10, 0, 0).unwrap(),
+            end_time: NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+            term_start: NaiveDate::from_ymd_opt(2025, 3, 3).unwrap(),
+            term_end: NaiveDate::from_ymd_opt(2025, 6, 30).unwrap(),
+            created_at: dt("2025-02-01T00:00:00+08:00"),
+        }
+    }
+
+    fn reservation_in(classroom_id: &str, start: &str, end: &str) -> reservation::Model {
+        reservation::Model {
+            classroom_id: Some(classroom_id.to_string()),
+            status: ReservationStatus::Approved,
+            ..reservation_at("r1", start, end)
+        }
+    }
+
+    #[test]
+    fn test_occurrences_follow_weekday_in_campus_time() {
+        let meetings = occurrences(
+            &monday_course(),
+            dt("2025-03-09T00:00:00+08:00"),
+            dt("2025-03-23T00:00:00+08:00"),
+        );
+        assert_eq!(
+            meetings,
+            vec![
+                (
+                    dt("2025-03-10T10:00:00+08:00"),
+                    dt("2025-03-10T12:00:00+08:00")
+                ),
+                (
+                    dt("2025-03-17T10:00:00+08:00"),
+                    dt("2025-03-17T12:00:00+08:00")
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_occurrences_stay_inside_term() {
+        let course = monday_course();
+        assert!(
+            occurrences(
+                &course,
+                dt("2025-02-24T00:00:00+08:00"),
+                dt("2025-03-02T23:59:00+08:00")
+            )
+            .is_empty()
+        );
+        // The last day of the term is included
+        assert_eq!(
+            occurrences(
+                &course,
+                dt("2025-06-30T00:00:00+08:00"),
+                dt("2025-07-08T00:00:00+08:00")
+            )
+            .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_collision_needs_same_classroom_and_overlap() {
+        let sessions = vec![monday_course()];
+
+        let overlapping = reservation_in("room-1", "2025-03-10T02:30:00Z", "2025-03-10T03:30:00Z");
+        let collision = find_collision(&sessions, &overlapping).unwrap();
+        assert_eq!(collision.course_session_id, "c1");
+        assert_eq!(collision.course_start, dt("2025-03-10T10:00:00+08:00"));
+
+        let back_to_back = reservation_in(
+            "room-1",
+            "2025-03-10T12:00:00+08:00",
+            "2025-03-10T13:00:00+08:00",
+        );
+        assert!(find_collision(&sessions, &back_to_back).is_none());
+
+        let other_room = reservation_in(
+            "room-2",
+            "2025-03-10T10:30:00+08:00",
+            "2025-03-10T11:30:00+08:00",
+        );
+        assert!(find_collision(&sessions, &other_room).is_none());
+    }
+}
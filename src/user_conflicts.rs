@@ -0,0 +1,114 @@
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, SqlErr,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{entities::user, error::AppError};
+
+/// A user field that must be unique across accounts.
+#[derive(Serialize, ToSchema, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UniqueField {
+    Username,
+    Email,
+    StudentId,
+}
+
+impl UniqueField {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::Username => "Username already taken",
+            Self::Email => "Email already registered",
+            Self::StudentId => "Student ID already registered",
+        }
+    }
+}
+
+/// `details` of the conflict error for a taken field.
+#[derive(Serialize, ToSchema)]
+pub struct ConflictDetails {
+    /// The field another account already uses
+    pub field: UniqueField,
+}
+
+impl From<UniqueField> for AppError {
+    fn from(field: UniqueField) -> Self {
+        AppError::Conflict(field.message().to_string()).with_details(ConflictDetails { field })
+    }
+}
+
+/// Values of a new or changed account to check; fields left as `None` are
+/// not being set.
+#[derive(Clone, Copy, Default)]
+pub struct Candidate<'a> {
+    pub username: Option<&'a str>,
+    pub email: Option<&'a str>,
+    pub student_id: Option<&'a str>,
+}
+
+/// Which field of `candidate` the `existing` account already holds, checked
+/// in the order username, email, student ID.
+pub fn conflicting_field(existing: &user::Model, candidate: &Candidate) -> Option<UniqueField> {
+    if candidate.username == Some(existing.username.as_str()) {
+        Some(UniqueField::Username)
+    } else if candidate.email == Some(existing.email.as_str()) {
+        Some(UniqueField::Email)
+    } else if candidate.student_id.is_some()
+        && candidate.student_id == existing.student_id.as_deref()
+    {
+        Some(UniqueField::StudentId)
+    } else {
+        None
+    }
+}
+
+/// Looks for another account holding one of `candidate`'s values. The
+/// account being updated is passed as `exclude_id` so it does not conflict
+/// with itself.
+pub async fn find_conflict(
+    db: &DatabaseConnection,
+    candidate: Candidate<'_>,
+    exclude_id: Option<&str>,
+) -> Result<Option<UniqueField>, DbErr> {
+    let mut any = Condition::any();
+    if let Some(username) = candidate.username {
+        any = any.add(user::Column::Username.eq(username));
+    }
+    if let Some(email) = candidate.email {
+        any = any.add(user::Column::Email.eq(email));
+    }
+    if let Some(student_id) = candidate.student_id {
+        any = any.add(user::Column::StudentId.eq(student_id));
+    }
+    if any.is_empty() {
+        return Ok(None);
+    }
+
+    let mut stmt = user::Entity::find().filter(any);
+    if let Some(exclude_id) = exclude_id {
+        stmt = stmt.filter(user::Column::Id.ne(exclude_id));
+    }
+    let existing = stmt.all(db).await?;
+    Ok(existing
+        .iter()
+        .filter_map(|existing| conflicting_field(existing, &candidate))
+        .min_by_key(|field| *field as u8))
+}
+
+/// The field behind a unique constraint violation, for an account written
+/// between [`find_conflict`] and the insert or update.
+pub fn from_db_error(err: &DbErr) -> Option<UniqueField> {
+    let Some(SqlErr::UniqueConstraintViolation(message)) = err.sql_err() else {
+        return None;
+    };
+    if message.contains("student_id") {
+        Some(UniqueField::StudentId)
+    } else if message.contains("email") {
+        Some(UniqueField::Email)
+    } else if message.contains("username") {
+        Some(UniqueField::Username)
+    } else {
+        None
+    }
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+mod tests {
+    use super::super::entities::{sea_orm_active_enums::Role, user};
+    use super::super::user_conflicts::{Candidate, UniqueField, conflicting_field};
+
+    fn existing() -> user::Model {
+        let at = "2025-03-01T09:00:00+08:00".parse().unwrap();
+        user::Model {
+            id: "u1".to_string(),
+            username: "alice".to_string(),
+            name: "Alice".to_string(),
+            email: "alice@example.edu".to_string(),
+            password: String::new(),
+            phone_number: "0912345678".to_string(),
+            role: Role::User,
+            created_at: at,
+            updated_at: at,
+            locale: None,
+            email_undeliverable_at: None,
+            email_undeliverable_reason: None,
+            department: None,
+            student_id: Some("B11012345".to_string()),
+            deleted_at: None,
+            version: 1,
+        }
+    }
+
+    #[test]
+    fn test_reports_the_conflicting_field() {
+        let existing = existing();
+        let candidate = Candidate {
+            username: Some("bob"),
+            email: Some("alice@example.edu"),
+            student_id: Some("B11099999"),
+        };
+        assert_eq!(
+            conflicting_field(&existing, &candidate),
+            Some(UniqueField::Email)
+        );
+
+        let candidate = Candidate {
+            student_id: Some("B11012345"),
+            ..Candidate::default()
+        };
+        assert_eq!(
+            conflicting_field(&existing, &candidate),
+            Some(UniqueField::StudentId)
+        );
+    }
+
+    #[test]
+    fn test_unset_fields_never_conflict() {
+        let mut existing = existing();
+        existing.student_id = None;
+        let candidate = Candidate {
+            username: Some("bob"),
+            email: Some("bob@example.edu"),
+            student_id: None,
+        };
+        assert_eq!(conflicting_field(&existing, &candidate), None);
+    }
+}
@@ -1,5 +1,13 @@
+use axum::{
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+};
 use chrono::{Datelike, Local};
 use sea_orm::sqlx::types::chrono::{DateTime as ChronoDateTime, FixedOffset};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
 
 pub fn check_student_id(student_id: impl AsRef<str>) -> bool {
     let id = student_id.as_ref();
@@ -46,22 +54,6 @@ pub fn check_student_id(student_id: impl AsRef<str>) -> bool {
     return true;
 }
 
-pub fn classroom_key(id: &str) -> String {
-    format!("classroom_{}", id)
-}
-
-pub fn classroom_with_keys_key(id: &str) -> String {
-    format!("classroom_{}_keys", id)
-}
-
-pub fn classroom_with_reservations_key(id: &str) -> String {
-    format!("classroom_{}_reservations", id)
-}
-
-pub fn classroom_with_keys_and_reservations_key(id: &str) -> String {
-    format!("classroom_{}_keys_reservations", id)
-}
-
 // ===============================
 //   datetime parser (minimal add)
 // ===============================
@@ -90,4 +82,82 @@ pub fn parse_dt(s: &str) -> Result<ChronoDateTime<FixedOffset>, ()> {
     base.push_str("+08:00");
 
     base.parse::<ChronoDateTime<FixedOffset>>().map_err(|_| ())
-}
\ No newline at end of file
+}
+
+/// [`parse_dt`] for a request field, with an error message naming the field.
+pub fn parse_dt_field(value: &str, field: &str) -> Result<ChronoDateTime<FixedOffset>, String> {
+    parse_dt(value).map_err(|_| {
+        format!(
+            "Invalid {}: expected a time such as 2025-03-10T10:00:00+08:00",
+            field
+        )
+    })
+}
+// ===============================
+//   conditional requests
+// ===============================
+
+/// Whether an `If-None-Match` header value matches `etag`, using the weak
+/// comparison HTTP caches expect for GET requests.
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let strip_weak = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = strip_weak(etag);
+    if_none_match
+        .split(',')
+        .any(|candidate| candidate.trim() == "*" || strip_weak(candidate) == etag)
+}
+
+/// Strong entity tag of a response body, quoted as sent in `ETag`.
+pub fn body_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+    format!("\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// `value` as a JSON 200 with an `ETag` of its body, or an empty 304 when
+/// `If-None-Match` shows the client already has it. For lists that kiosks
+/// and apps poll every few seconds.
+pub fn json_with_etag(headers: &HeaderMap, value: &impl Serialize) -> Response {
+    let body = match serde_json::to_vec(value) {
+        Ok(body) => body,
+        Err(_) => {
+            return AppError::Internal("Failed to serialize the response".to_string())
+                .into_response();
+        }
+    };
+    let etag = body_etag(&body);
+    let not_modified = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| etag_matches(value, &etag));
+    if not_modified {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/json".to_string()),
+            (header::ETAG, etag),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+// ===============================
+//   search
+// ===============================
+
+/// An `ILIKE` pattern matching `text` anywhere, with `%`, `_` and `\` in it
+/// taken literally.
+pub fn contains_pattern(text: &str) -> String {
+    let mut pattern = String::with_capacity(text.len() + 2);
+    pattern.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern.push('%');
+    pattern
+}
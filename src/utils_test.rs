@@ -1,7 +1,11 @@
 #[cfg(test)]
 mod tests {
-    use super::super::utils::check_student_id;
+    use super::super::utils::{
+        check_student_id, contains_pattern, etag_matches, json_with_etag, parse_dt_field,
+    };
+    use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
     use chrono::{Datelike, Local};
+    use serde_json::json;
 
     #[test]
     fn test_valid_student_id() {
@@ -275,4 +279,55 @@ mod tests {
         assert!(check_student_id(&format!("0{}Ab001", valid_year)));
         assert!(check_student_id(&format!("0{}aB001", valid_year)));
     }
+
+    #[test]
+    fn test_etag_matches() {
+        assert!(etag_matches("\"abc\"", "\"abc\""));
+        assert!(etag_matches("W/\"abc\"", "\"abc\""));
+        assert!(etag_matches("\"xyz\", \"abc\"", "W/\"abc\""));
+        assert!(etag_matches("*", "\"abc\""));
+        assert!(!etag_matches("\"xyz\"", "\"abc\""));
+        assert!(!etag_matches("", "\"abc\""));
+    }
+
+    #[test]
+    fn test_json_with_etag_answers_unchanged_lists_with_304() {
+        let list = json!([{ "id": "c1" }]);
+        let response = json_with_etag(&HeaderMap::new(), &list);
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].clone();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, etag.clone());
+        let response = json_with_etag(&headers, &list);
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+
+        // Any change gives a new tag
+        let changed = json!([{ "id": "c1" }, { "id": "c2" }]);
+        let response = json_with_etag(&headers, &changed);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()[header::ETAG], etag);
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"stale\""));
+        assert_eq!(json_with_etag(&headers, &list).status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_parse_dt_field_names_the_field() {
+        assert_eq!(
+            parse_dt_field("2025-03-10 10:00", "deadline").unwrap(),
+            "2025-03-10T10:00:00+08:00"
+                .parse::<chrono::DateTime<chrono::FixedOffset>>()
+                .unwrap()
+        );
+        let err = parse_dt_field("tomorrow", "returned_at").unwrap_err();
+        assert!(err.starts_with("Invalid returned_at"), "{}", err);
+    }
+
+    #[test]
+    fn test_contains_pattern_escapes_wildcards() {
+        assert_eq!(contains_pattern("exam"), "%exam%");
+        assert_eq!(contains_pattern("100%_off"), "%100\\%\\_off%");
+        assert_eq!(contains_pattern("a\\b"), "%a\\\\b%");
+    }
 }
@@ -0,0 +1,51 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel,
+    QueryFilter, QueryTrait,
+    sea_query::{Expr, ExprTrait},
+};
+use serde_json::json;
+
+use crate::error::AppError;
+
+/// Refusal of an edit made against an older version of a record.
+pub fn stale(current: Option<i32>) -> AppError {
+    let error = AppError::Conflict(
+        "Someone else changed this record since you loaded it; reload it and try again".to_string(),
+    );
+    match current {
+        Some(current) => error.with_details(json!({ "current_version": current })),
+        None => error,
+    }
+}
+
+/// Checks the version a client sent with an edit against the record's
+/// `current` one. Edits sent without a version are let through.
+pub fn check(expected: Option<i32>, current: i32) -> Result<(), AppError> {
+    match expected {
+        Some(expected) if expected != current => Err(stale(Some(current))),
+        _ => Ok(()),
+    }
+}
+
+/// Saves `active` and bumps its `version` column. With `expected`, the row
+/// is only written while it is still at that version, so an edit racing
+/// another one fails with `DbErr::RecordNotUpdated` instead of overwriting
+/// it.
+pub async fn update<A, C>(
+    db: &C,
+    active: A,
+    version: <A::Entity as EntityTrait>::Column,
+    expected: Option<i32>,
+) -> Result<<A::Entity as EntityTrait>::Model, DbErr>
+where
+    A: ActiveModelTrait,
+    C: ConnectionTrait,
+    <A::Entity as EntityTrait>::Model: IntoActiveModel<A>,
+{
+    let mut update = A::Entity::update(active).validate()?;
+    QueryTrait::query(&mut update).value(version, Expr::col(version).add(1));
+    if let Some(expected) = expected {
+        update = update.filter(version.eq(expected));
+    }
+    update.exec(db).await
+}
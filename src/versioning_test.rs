@@ -0,0 +1,122 @@
+#[cfg(test)]
+mod tests {
+    use axum::http::StatusCode;
+    use sea_orm::{
+        ActiveModelTrait,
+        ActiveValue::{NotSet, Set},
+        DbErr, IntoActiveModel,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::super::{
+        entities::{
+            key,
+            sea_orm_active_enums::{KeyStatus, Role},
+        },
+        error::AppError,
+        memory_mode,
+        test_support::{
+            add_classroom, add_user, json, reservation_at, router, send, sign_in, state,
+        },
+        versioning::{check, update},
+    };
+
+    #[test]
+    fn test_check_refuses_stale_versions() {
+        assert!(check(None, 3).is_ok());
+        assert!(check(Some(3), 3).is_ok());
+        match check(Some(2), 3) {
+            Err(AppError::Detailed(error, details)) => {
+                assert!(matches!(*error, AppError::Conflict(_)));
+                assert_eq!(details["current_version"], 3);
+            }
+            _ => panic!("expected a conflict"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_bumps_the_version_unless_stale() {
+        let db = memory_mode::connect_db().await.unwrap();
+        let created = key::ActiveModel {
+            id: Set("k1".to_string()),
+            classroom_id: Set(None),
+            key_number: Set("A-1".to_string()),
+            status: Set(KeyStatus::Active),
+            cabinet_slot: Set(None),
+            version: NotSet,
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        assert_eq!(created.version, 1);
+
+        let mut first: key::ActiveModel = created.clone().into();
+        first.key_number = Set("A-2".to_string());
+        let saved = update(&db, first, key::Column::Version, Some(1))
+            .await
+            .unwrap();
+        assert_eq!(saved.key_number, "A-2");
+        assert_eq!(saved.version, 2);
+
+        // An edit loaded before that save no longer applies
+        let mut second: key::ActiveModel = created.into();
+        second.key_number = Set("A-3".to_string());
+        let err = update(&db, second.clone(), key::Column::Version, Some(1))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, DbErr::RecordNotUpdated));
+        let saved = update(&db, second, key::Column::Version, None)
+            .await
+            .unwrap();
+        assert_eq!(saved.key_number, "A-3");
+        assert_eq!(saved.version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_a_review_outdates_edits_loaded_before_it() {
+        let state = state().await;
+        add_user(&state, "u1", "u1@example.com", Role::User).await;
+        add_user(&state, "a1", "a1@example.com", Role::Admin).await;
+        add_classroom(&state, "c1").await;
+        let reservation = reservation_at(
+            "r1",
+            "2030-03-11T09:00:00+08:00",
+            "2030-03-11T11:00:00+08:00",
+        )
+        .into_active_model()
+        .reset_all()
+        .insert(&state.db)
+        .await
+        .unwrap();
+        assert_eq!(reservation.version, 1);
+        let app = router(state.clone());
+        let requester = sign_in(&app, "u1@example.com").await;
+        let admin = sign_in(&app, "a1@example.com").await;
+
+        let review = app
+            .clone()
+            .oneshot(send(
+                "PUT",
+                "/reservation/r1/review",
+                &admin,
+                json!({ "status": "Approved" }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(review.status(), StatusCode::OK);
+        assert_eq!(json(review).await["reservation"]["version"], 2);
+
+        let edit = app
+            .oneshot(send(
+                "PATCH",
+                "/reservation/r1",
+                &requester,
+                json!({ "purpose": "Moved to the afternoon", "version": 1 }),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(edit.status(), StatusCode::CONFLICT);
+        assert_eq!(json(edit).await["details"]["current_version"], 2);
+    }
+}
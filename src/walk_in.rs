@@ -0,0 +1,33 @@
+/// Who staff book a reservation for: a registered user, or a guest without
+/// an account such as a caller booking by phone.
+#[derive(Debug, PartialEq)]
+pub enum BookedFor {
+    User(String),
+    Guest { name: String, email: Option<String> },
+}
+
+/// Reads who a reservation is for from the request. Exactly one of
+/// `user_id` and `guest_name` must be given; `guest_email` only goes with a
+/// guest.
+pub fn booked_for(
+    user_id: Option<String>,
+    guest_name: Option<String>,
+    guest_email: Option<String>,
+) -> Result<BookedFor, String> {
+    let trimmed = |value: Option<String>| {
+        value
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+    match (trimmed(user_id), trimmed(guest_name), trimmed(guest_email)) {
+        (Some(user_id), None, None) => Ok(BookedFor::User(user_id)),
+        (None, Some(name), email) => {
+            if email.as_ref().is_some_and(|email| !email.contains('@')) {
+                return Err("Invalid guest_email".to_string());
+            }
+            Ok(BookedFor::Guest { name, email })
+        }
+        (Some(_), _, _) => Err("Give either 'user_id' or a guest, not both".to_string()),
+        (None, None, _) => Err("'user_id' or 'guest_name' is required".to_string()),
+    }
+}
@@ -0,0 +1,47 @@
+#[cfg(test)]
+mod tests {
+    use super::super::walk_in::{BookedFor, booked_for};
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn test_booked_for_a_user() {
+        assert_eq!(
+            booked_for(some("u1"), None, None),
+            Ok(BookedFor::User("u1".to_string()))
+        );
+        // Blank guest fields from a form are ignored
+        assert_eq!(
+            booked_for(some("u1"), some(" "), some("")),
+            Ok(BookedFor::User("u1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_booked_for_a_guest() {
+        assert_eq!(
+            booked_for(None, some(" Chen Wei "), some("chen@example.com")),
+            Ok(BookedFor::Guest {
+                name: "Chen Wei".to_string(),
+                email: some("chen@example.com"),
+            })
+        );
+        assert_eq!(
+            booked_for(None, some("Chen Wei"), None),
+            Ok(BookedFor::Guest {
+                name: "Chen Wei".to_string(),
+                email: None,
+            })
+        );
+        assert!(booked_for(None, some("Chen Wei"), some("not-an-address")).is_err());
+    }
+
+    #[test]
+    fn test_booked_for_needs_exactly_one() {
+        assert!(booked_for(None, None, None).is_err());
+        assert!(booked_for(None, None, some("chen@example.com")).is_err());
+        assert!(booked_for(some("u1"), some("Chen Wei"), None).is_err());
+    }
+}
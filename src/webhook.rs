@@ -0,0 +1,87 @@
+use axum::http::HeaderMap;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::error::AppError;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// ===============================
+//   Signed webhooks
+// ===============================
+// Callbacks from external services (mail provider, key cabinet) carry a
+// `t=<unix seconds>,v1=<hex HMAC-SHA256 of "t.body">` header so they can be
+// checked without a session.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureError {
+    Missing,
+    Malformed,
+    Stale,
+    Invalid,
+}
+
+impl From<SignatureError> for AppError {
+    fn from(error: SignatureError) -> Self {
+        let message = match error {
+            SignatureError::Missing => "Missing signature",
+            SignatureError::Stale => "Signature timestamp out of range",
+            SignatureError::Malformed | SignatureError::Invalid => "Invalid signature",
+        };
+        AppError::Unauthorized(message.to_string())
+    }
+}
+
+fn mac(secret: &[u8], timestamp: i64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Checks a signature header value. The timestamp must be within `tolerance`
+/// of `now` and the MAC is compared in constant time.
+pub fn verify_signature(
+    secret: &[u8],
+    header: &str,
+    body: &[u8],
+    tolerance: Duration,
+    now: DateTime<Utc>,
+) -> Result<(), SignatureError> {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", v)) => timestamp = v.parse::<i64>().ok(),
+            Some(("v1", v)) => signature = hex::decode(v).ok(),
+            _ => {}
+        }
+    }
+    let (Some(timestamp), Some(signature)) = (timestamp, signature) else {
+        return Err(SignatureError::Malformed);
+    };
+    if (now.timestamp() - timestamp).abs() > tolerance.num_seconds() {
+        return Err(SignatureError::Stale);
+    }
+    mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .map_err(|_| SignatureError::Invalid)
+}
+
+/// Checks the signature a request carries in `header_name` against its raw
+/// body.
+pub fn verify_request(
+    secret: &[u8],
+    headers: &HeaderMap,
+    header_name: &str,
+    body: &[u8],
+    tolerance: Duration,
+) -> Result<(), SignatureError> {
+    let header = headers
+        .get(header_name)
+        .and_then(|v| v.to_str().ok())
+        .ok_or(SignatureError::Missing)?;
+    verify_signature(secret, header, body, tolerance, Utc::now())
+}
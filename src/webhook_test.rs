@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod tests {
+    use super::super::webhook::{SignatureError, verify_signature};
+    use chrono::{DateTime, Duration, Utc};
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+
+    const SECRET: &[u8] = b"webhook-secret";
+    const BODY: &[u8] = br#"{"events":[]}"#;
+
+    /// What a webhook sender puts in its signature header.
+    fn sign(secret: &[u8], timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(format!("{}.", timestamp).as_bytes());
+        mac.update(body);
+        format!(
+            "t={},v1={}",
+            timestamp,
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    fn now() -> DateTime<Utc> {
+        "2025-03-10T09:00:00Z".parse().unwrap()
+    }
+
+    #[test]
+    fn test_signed_body_verifies() {
+        let header = sign(SECRET, now().timestamp(), BODY);
+        assert_eq!(
+            verify_signature(SECRET, &header, BODY, Duration::minutes(5), now()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_tampered_body_or_wrong_secret_is_rejected() {
+        let header = sign(SECRET, now().timestamp(), BODY);
+        assert_eq!(
+            verify_signature(
+                SECRET,
+                &header,
+                br#"{"events":[1]}"#,
+                Duration::minutes(5),
+                now()
+            ),
+            Err(SignatureError::Invalid)
+        );
+        assert_eq!(
+            verify_signature(b"other", &header, BODY, Duration::minutes(5), now()),
+            Err(SignatureError::Invalid)
+        );
+    }
+
+    #[test]
+    fn test_old_or_malformed_signatures_are_rejected() {
+        let old = sign(SECRET, (now() - Duration::minutes(6)).timestamp(), BODY);
+        assert_eq!(
+            verify_signature(SECRET, &old, BODY, Duration::minutes(5), now()),
+            Err(SignatureError::Stale)
+        );
+        assert_eq!(
+            verify_signature(SECRET, "v1=abcd", BODY, Duration::minutes(5), now()),
+            Err(SignatureError::Malformed)
+        );
+        assert_eq!(
+            verify_signature(SECRET, "t=1,v1=not-hex", BODY, Duration::minutes(5), now()),
+            Err(SignatureError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use sea_orm::prelude::DateTimeWithTimeZone;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+
+/// Reviews handled by one admin in the report window.
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct ReviewerWorkload {
+    pub admin_id: String,
+    /// `None` when the reviewer is no longer an admin
+    pub name: Option<String>,
+    pub reviews: u64,
+    pub approved: u64,
+    pub rejected: u64,
+    /// Median time from submission to review; `None` without reviews
+    pub median_latency_minutes: Option<f64>,
+}
+
+/// Requests still waiting for a decision.
+#[derive(Serialize, ToSchema, Clone, Debug, Default, PartialEq)]
+pub struct ReviewQueue {
+    pub pending: u64,
+    /// Pending requests flagged for a closer look
+    pub flagged: u64,
+    #[schema(value_type = Option<String>, format = DateTime)]
+    #[serde(default, with = "crate::datetime::rfc3339::option")]
+    pub oldest_pending_since: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Serialize, ToSchema, Clone, Debug, PartialEq)]
+pub struct AdminWorkload {
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub from: DateTimeWithTimeZone,
+    #[schema(value_type = String, format = DateTime)]
+    #[serde(with = "crate::datetime::rfc3339")]
+    pub to: DateTimeWithTimeZone,
+    /// Most reviews first; admins without reviews are listed too
+    pub reviewers: Vec<ReviewerWorkload>,
+    /// Requests are not assigned to reviewers, so every admin works from
+    /// this one queue
+    pub queue: ReviewQueue,
+}
+
+/// Median of `values`, averaging the middle pair for an even count.
+pub fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Groups `reviewed` reservations by reviewer. `admins` maps every current
+/// admin to their name so idle admins show up with zero reviews.
+pub fn summarize(
+    reviewed: &[reservation::Model],
+    pending: &[reservation::Model],
+    admins: &HashMap<String, String>,
+    from: DateTimeWithTimeZone,
+    to: DateTimeWithTimeZone,
+) -> AdminWorkload {
+    let mut per_admin: HashMap<&str, (ReviewerWorkload, Vec<f64>)> = admins
+        .iter()
+        .map(|(id, name)| {
+            (
+                id.as_str(),
+                (
+                    ReviewerWorkload {
+                        admin_id: id.clone(),
+                        name: Some(name.clone()),
+                        reviews: 0,
+                        approved: 0,
+                        rejected: 0,
+                        median_latency_minutes: None,
+                    },
+                    Vec::new(),
+                ),
+            )
+        })
+        .collect();
+
+    for reservation in reviewed {
+        let (Some(admin_id), Some(reviewed_at)) =
+            (&reservation.approved_by, reservation.reviewed_at)
+        else {
+            continue;
+        };
+        let (workload, latencies) = per_admin.entry(admin_id).or_insert_with(|| {
+            (
+                ReviewerWorkload {
+                    admin_id: admin_id.clone(),
+                    name: None,
+                    reviews: 0,
+                    approved: 0,
+                    rejected: 0,
+                    median_latency_minutes: None,
+                },
+                Vec::new(),
+            )
+        });
+        workload.reviews += 1;
+        match reservation.status {
+            ReservationStatus::Rejected => workload.rejected += 1,
+            // Approved requests may since have been completed or cancelled
+            _ => workload.approved += 1,
+        }
+        latencies.push((reviewed_at - reservation.created_at).num_seconds().max(0) as f64 / 60.0);
+    }
+
+    let mut reviewers: Vec<ReviewerWorkload> = per_admin
+        .into_values()
+        .map(|(mut workload, mut latencies)| {
+            workload.median_latency_minutes = median(&mut latencies);
+            workload
+        })
+        .collect();
+    reviewers.sort_by(|a, b| b.reviews.cmp(&a.reviews).then(a.admin_id.cmp(&b.admin_id)));
+
+    let queue = ReviewQueue {
+        pending: pending.len() as u64,
+        flagged: pending.iter().filter(|r| r.flagged_for_review).count() as u64,
+        oldest_pending_since: pending.iter().map(|r| r.created_at).min(),
+    };
+
+    AdminWorkload {
+        from,
+        to,
+        reviewers,
+        queue,
+    }
+}
@@ -0,0 +1,108 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::super::entities::{reservation, sea_orm_active_enums::ReservationStatus};
+    use super::super::test_support::reservation_at;
+    use super::super::workload::{median, summarize};
+    use sea_orm::prelude::DateTimeWithTimeZone;
+
+    fn dt(s: &str) -> DateTimeWithTimeZone {
+        s.parse().unwrap()
+    }
+
+    fn reviewed(
+        id: &str,
+        admin_id: &str,
+        status: ReservationStatus,
+        latency_minutes: i64,
+    ) -> reservation::Model {
+        let created_at = dt("2025-03-01T09:00:00+08:00");
+        reservation::Model {
+            classroom_id: Some("room-1".to_string()),
+            approved_by: Some(admin_id.to_string()),
+            status,
+            created_at,
+            reviewed_at: Some(created_at + chrono::Duration::minutes(latency_minutes)),
+            ..reservation_at(id, "2025-03-10T10:00:00+08:00", "2025-03-10T12:00:00+08:00")
+        }
+    }
+
+    fn pending(id: &str, created_at: &str, flagged: bool) -> reservation::Model {
+        reservation::Model {
+            approved_by: None,
+            status: ReservationStatus::Pending,
+            flagged_for_review: flagged,
+            created_at: dt(created_at),
+            reviewed_at: None,
+            event_name: None,
+            ..reviewed(id, "", ReservationStatus::Pending, 0)
+        }
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut []), None);
+        assert_eq!(median(&mut [30.0, 10.0, 20.0]), Some(20.0));
+        assert_eq!(median(&mut [40.0, 10.0, 20.0, 30.0]), Some(25.0));
+    }
+
+    #[test]
+    fn test_summarize_counts_reviews_per_admin() {
+        let admins = HashMap::from([
+            ("a1".to_string(), "Alice".to_string()),
+            ("a2".to_string(), "Bob".to_string()),
+        ]);
+        let reviewed = [
+            reviewed("r1", "a1", ReservationStatus::Approved, 10),
+            reviewed("r2", "a1", ReservationStatus::Rejected, 30),
+            reviewed("r3", "a1", ReservationStatus::Completed, 60),
+            reviewed("r4", "gone", ReservationStatus::Approved, 5),
+        ];
+        let stats = summarize(
+            &reviewed,
+            &[],
+            &admins,
+            dt("2025-03-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+        );
+
+        assert_eq!(stats.reviewers.len(), 3);
+        let alice = &stats.reviewers[0];
+        assert_eq!(alice.admin_id, "a1");
+        assert_eq!(alice.name.as_deref(), Some("Alice"));
+        assert_eq!((alice.reviews, alice.approved, alice.rejected), (3, 2, 1));
+        assert_eq!(alice.median_latency_minutes, Some(30.0));
+
+        // Reviewer accounts deleted since keep their reviews, idle admins
+        // are listed with none
+        let gone = &stats.reviewers[1];
+        assert_eq!(
+            (gone.admin_id.as_str(), gone.name.as_deref()),
+            ("gone", None)
+        );
+        let bob = &stats.reviewers[2];
+        assert_eq!((bob.reviews, bob.median_latency_minutes), (0, None));
+    }
+
+    #[test]
+    fn test_summarize_queue() {
+        let pending = [
+            pending("p1", "2025-03-05T09:00:00+08:00", false),
+            pending("p2", "2025-03-02T09:00:00+08:00", true),
+        ];
+        let stats = summarize(
+            &[],
+            &pending,
+            &HashMap::new(),
+            dt("2025-03-01T00:00:00+08:00"),
+            dt("2025-04-01T00:00:00+08:00"),
+        );
+        assert_eq!(stats.queue.pending, 2);
+        assert_eq!(stats.queue.flagged, 1);
+        assert_eq!(
+            stats.queue.oldest_pending_since,
+            Some(dt("2025-03-02T09:00:00+08:00"))
+        );
+    }
+}